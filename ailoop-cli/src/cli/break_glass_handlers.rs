@@ -0,0 +1,46 @@
+//! Handler for `ailoop break-glass`.
+
+use super::task_handlers::resolve_server_url;
+use ailoop_core::models::MessageContent;
+use ailoop_core::BreakGlassClient;
+use anyhow::{Context, Result};
+use uuid::Uuid;
+
+/// Approve an authorization nobody answered, as a break-glass token holder.
+pub async fn handle_break_glass(
+    id: String,
+    justification: String,
+    token: Option<String>,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let id = Uuid::parse_str(id.trim()).with_context(|| format!("Invalid id '{}'", id))?;
+    let token = token
+        .or_else(|| std::env::var("AILOOP_BREAK_GLASS_TOKEN").ok())
+        .filter(|t| !t.is_empty())
+        .context("A break-glass token is required (--token or AILOOP_BREAK_GLASS_TOKEN)")?;
+    let server_url = resolve_server_url(server)?;
+    let response = BreakGlassClient::new(&server_url, token)
+        .approve(id, justification.trim())
+        .await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&response)?);
+        return Ok(());
+    }
+    let responder = response
+        .metadata
+        .as_ref()
+        .and_then(|m| m["responder"].as_str())
+        .unwrap_or("break-glass");
+    match &response.content {
+        MessageContent::Response { answer, .. } => println!(
+            "Authorization {} approved by {}: {}",
+            id,
+            responder,
+            answer.as_deref().unwrap_or_default()
+        ),
+        _ => println!("Authorization {} approved by {}", id, responder),
+    }
+    Ok(())
+}
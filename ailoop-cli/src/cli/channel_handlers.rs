@@ -0,0 +1,57 @@
+//! Handlers for the `ailoop channel` subcommands.
+
+use super::task_handlers::resolve_server_url;
+use ailoop_core::{ChannelClient, ChannelHoldResponse};
+use anyhow::Result;
+
+/// Hold prompt delivery on a channel; agents' messages queue until it is resumed.
+pub async fn handle_channel_pause(channel: String, server: String, json: bool) -> Result<()> {
+    validate(&channel)?;
+    let server_url = resolve_server_url(server)?;
+    let hold = ChannelClient::new(&server_url).pause(&channel).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&hold)?);
+    } else if hold.changed {
+        println!(
+            "Channel '{}' paused; prompts will queue until `ailoop channel resume {}`",
+            hold.channel, hold.channel
+        );
+    } else {
+        print_already(&hold);
+    }
+    Ok(())
+}
+
+/// Deliver the prompts queued on a held channel.
+pub async fn handle_channel_resume(channel: String, server: String, json: bool) -> Result<()> {
+    validate(&channel)?;
+    let server_url = resolve_server_url(server)?;
+    let hold = ChannelClient::new(&server_url).resume(&channel).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&hold)?);
+    } else if hold.changed {
+        println!(
+            "Channel '{}' resumed; {} queued message(s) will be delivered",
+            hold.channel, hold.queued
+        );
+    } else {
+        print_already(&hold);
+    }
+    Ok(())
+}
+
+fn validate(channel: &str) -> Result<()> {
+    ailoop_core::channel::validation::validate_channel_name(channel)
+        .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))
+}
+
+fn print_already(hold: &ChannelHoldResponse) {
+    match hold.held_since {
+        Some(since) => println!(
+            "Channel '{}' is already paused (since {})",
+            hold.channel,
+            since.format("%Y-%m-%d %H:%M:%S UTC")
+        ),
+        None => println!("Channel '{}' is not paused", hold.channel),
+    }
+}
@@ -0,0 +1,134 @@
+//! Handler for `ailoop chat`: a free-form conversation with the human on a channel.
+//!
+//! Lines read from stdin go to the human; the human's lines are printed as they arrive. The
+//! conversation ends when either side closes it (`/close` here, or end of input).
+
+use ailoop_core::client::chat_client::ChatSession;
+use ailoop_core::models::{Message, MessageContent};
+use anyhow::{bail, Context, Result};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// Commands that end the conversation; text after them is sent as the last line.
+const CLOSE_COMMANDS: [&str; 3] = ["/close", "/quit", "/exit"];
+
+/// Open a conversation on `channel`, starting with `message` or the first line of stdin.
+pub async fn handle_chat(
+    message: Option<String>,
+    channel: String,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    ailoop_core::channel::validation::validate_channel_name(&channel)
+        .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+    let operation_mode = crate::mode::determine_operation_mode(Some(server))
+        .map_err(|e| anyhow::anyhow!("Failed to determine operation mode: {}", e))?;
+    let server_url = match operation_mode.server_url {
+        Some(url) if operation_mode.is_server() => url,
+        _ => bail!("ailoop chat needs a server: pass --server or set AILOOP_SERVER"),
+    };
+
+    let mut stdin = BufReader::new(tokio::io::stdin()).lines();
+    let first_line = match message.filter(|m| !m.trim().is_empty()) {
+        Some(message) => message,
+        None => loop {
+            match stdin.next_line().await.context("Failed to read stdin")? {
+                Some(line) if !line.trim().is_empty() => break line,
+                Some(_) => continue,
+                None => bail!("Nothing to say: give a message or type one"),
+            }
+        },
+    };
+
+    let mut session = ChatSession::open(&server_url, &channel, first_line.trim()).await?;
+    if !json {
+        eprintln!(
+            "Conversation {} open on {}; type /close to end it",
+            session.conversation_id(),
+            channel
+        );
+    }
+
+    loop {
+        tokio::select! {
+            line = stdin.next_line() => match line.context("Failed to read stdin")? {
+                Some(line) => {
+                    let line = line.trim();
+                    if let Some(last) = close_command(line) {
+                        session.close(last).await?;
+                        break;
+                    }
+                    if !line.is_empty() {
+                        session.say(line).await?;
+                    }
+                }
+                // End of input ends the conversation.
+                None => {
+                    session.close("").await?;
+                    break;
+                }
+            },
+            reply = session.next_reply() => match reply? {
+                Some(reply) => {
+                    print_reply(&reply, json)?;
+                    if session.is_closed() {
+                        if !json {
+                            eprintln!("The human closed the conversation");
+                        }
+                        session.close("").await?;
+                        break;
+                    }
+                }
+                None => bail!("Server closed the connection"),
+            },
+        }
+    }
+    Ok(())
+}
+
+/// The last line to send if `line` is a close command.
+fn close_command(line: &str) -> Option<&str> {
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    CLOSE_COMMANDS
+        .contains(&command.to_ascii_lowercase().as_str())
+        .then(|| rest.trim())
+}
+
+fn print_reply(reply: &Message, json: bool) -> Result<()> {
+    let MessageContent::Chat {
+        conversation_id,
+        text,
+        closed,
+    } = &reply.content
+    else {
+        return Ok(());
+    };
+    if json {
+        let line = serde_json::json!({
+            "conversation_id": conversation_id,
+            "text": text,
+            "closed": closed,
+            "timestamp": reply.timestamp.to_rfc3339(),
+        });
+        println!("{}", serde_json::to_string(&line)?);
+    } else if !text.is_empty() {
+        println!("{}: {}", reply.speaker(), text);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_commands_keep_their_last_line() {
+        assert_eq!(close_command("/close"), Some(""));
+        assert_eq!(
+            close_command("/quit thanks, that's all"),
+            Some("thanks, that's all")
+        );
+        assert_eq!(close_command("/EXIT"), Some(""));
+        assert_eq!(close_command("closing the ticket"), None);
+        assert_eq!(close_command("/closed"), None);
+    }
+}
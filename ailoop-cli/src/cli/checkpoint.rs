@@ -0,0 +1,172 @@
+//! Resume checkpoints for `forward --input`
+//!
+//! With `--resume`, `forward` records how far into the input file it has sent events, keyed by
+//! `--client-id`, in `checkpoints/<client id>.json` under the ailoop data directory. A restarted
+//! `forward` with the same client id and input seeks past what was already sent. Progress is
+//! only recorded between complete events, so a crash re-sends at most the last few events.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often progress is written while forwarding.
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Saved {
+    input: PathBuf,
+    /// Byte offset of the first line not yet sent.
+    offset: u64,
+    /// Lines before `offset`.
+    lines: u64,
+    updated_at: DateTime<Utc>,
+}
+
+/// Progress of one client through one input file.
+pub struct Checkpoint {
+    path: PathBuf,
+    input: PathBuf,
+    offset: u64,
+    lines: u64,
+    saved_offset: u64,
+    last_save: Instant,
+}
+
+impl Checkpoint {
+    /// `checkpoints` in the state directory.
+    pub fn default_dir() -> Result<PathBuf> {
+        ailoop_core::paths::state_path("checkpoints").context("Could not determine state directory")
+    }
+
+    /// Load the checkpoint of `client_id` in `dir`. A checkpoint for another input, or one
+    /// past the end of the file (truncated or rotated), starts over from the beginning. FIFOs
+    /// are never skipped into: what was read from them is gone already.
+    pub fn open(dir: &Path, client_id: &str, input: &Path) -> Result<Self> {
+        let input = input
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve input file {:?}", input))?;
+        let path = dir.join(format!("{}.json", file_stem(client_id)));
+        let saved = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str::<Saved>(&text).ok(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read checkpoint {:?}", path))
+            }
+        };
+        let metadata = std::fs::metadata(&input)?;
+        let (offset, lines) = match saved {
+            _ if !metadata.is_file() => (0, 0),
+            Some(saved) if saved.input == input && saved.offset <= metadata.len() => {
+                (saved.offset, saved.lines)
+            }
+            Some(saved) if saved.input == input => {
+                eprintln!(
+                    "Warning: {} is shorter than its checkpoint; starting from the beginning",
+                    input.display()
+                );
+                (0, 0)
+            }
+            _ => (0, 0),
+        };
+        Ok(Self {
+            path,
+            input,
+            offset,
+            lines,
+            saved_offset: offset,
+            last_save: Instant::now(),
+        })
+    }
+
+    /// Byte offset to resume reading from.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Lines already sent before [`Checkpoint::offset`].
+    pub fn lines(&self) -> u64 {
+        self.lines
+    }
+
+    /// Record that everything up to `offset` (after `lines` lines) has been sent.
+    pub fn commit(&mut self, offset: u64, lines: u64) {
+        self.offset = offset;
+        self.lines = lines;
+    }
+
+    /// Whether unsaved progress is older than the save interval.
+    pub fn is_due(&self) -> bool {
+        self.offset != self.saved_offset && self.last_save.elapsed() >= SAVE_INTERVAL
+    }
+
+    /// Write the checkpoint; replaces the file atomically.
+    pub fn save(&mut self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create checkpoint directory {:?}", dir))?;
+        }
+        let saved = Saved {
+            input: self.input.clone(),
+            offset: self.offset,
+            lines: self.lines,
+            updated_at: Utc::now(),
+        };
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&saved)?)
+            .with_context(|| format!("Failed to write checkpoint {:?}", tmp))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to write checkpoint {:?}", self.path))?;
+        self.saved_offset = self.offset;
+        self.last_save = Instant::now();
+        Ok(())
+    }
+}
+
+/// Client ids become file names; anything but `[A-Za-z0-9._-]` is replaced.
+fn file_stem(client_id: &str) -> String {
+    client_id
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '_' | '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("agent.jsonl");
+        std::fs::write(&input, "one\ntwo\nthree\n")?;
+        let checkpoints = dir.path().join("checkpoints");
+
+        let mut checkpoint = Checkpoint::open(&checkpoints, "ci/run 1", &input)?;
+        assert_eq!(checkpoint.offset(), 0);
+        checkpoint.commit(8, 2);
+        checkpoint.save()?;
+        assert!(checkpoints.join("ci_run_1.json").exists());
+
+        let resumed = Checkpoint::open(&checkpoints, "ci/run 1", &input)?;
+        assert_eq!((resumed.offset(), resumed.lines()), (8, 2));
+
+        // Another input, or a truncated one, starts over.
+        let other = dir.path().join("other.jsonl");
+        std::fs::write(&other, "one\ntwo\nthree\n")?;
+        assert_eq!(
+            Checkpoint::open(&checkpoints, "ci/run 1", &other)?.offset(),
+            0
+        );
+        std::fs::write(&input, "one\n")?;
+        assert_eq!(
+            Checkpoint::open(&checkpoints, "ci/run 1", &input)?.offset(),
+            0
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+//! Coalescing of assistant output between the parser and the transport
+//!
+//! Streaming agents emit one event per delta, which makes chat providers unreadable.
+//! Consecutive assistant events are merged into one until the block ends (another event type
+//! arrives or the text ends with a blank line), the flush interval elapses, or `max_chars` is
+//! reached.
+
+use crate::parser::{AgentEvent, EventType};
+use ailoop_core::models::CoalesceRules;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+/// Buffers assistant deltas and releases them as merged events.
+pub struct Coalescer {
+    interval: Option<Duration>,
+    max_chars: usize,
+    pending: Option<Pending>,
+}
+
+struct Pending {
+    first: AgentEvent,
+    text: String,
+    count: usize,
+    started: Instant,
+}
+
+impl Coalescer {
+    /// Coalescer for `rules`, or `None` when coalescing is disabled.
+    pub fn new(rules: &CoalesceRules) -> Option<Self> {
+        if !rules.is_enabled() {
+            return None;
+        }
+        Some(Self {
+            interval: rules.interval(),
+            max_chars: rules.max_chars(),
+            pending: None,
+        })
+    }
+
+    /// Feed one parsed event; returns the events ready to forward, in order.
+    pub fn push(&mut self, event: AgentEvent) -> Vec<AgentEvent> {
+        let delta = match event.event_type {
+            EventType::Assistant => delta_text(&event.content),
+            _ => None,
+        };
+        let Some(delta) = delta else {
+            // Anything else ends the current block.
+            return self.flush().into_iter().chain(Some(event)).collect();
+        };
+
+        let pending = self.pending.get_or_insert_with(|| Pending {
+            first: event,
+            text: String::new(),
+            count: 0,
+            started: Instant::now(),
+        });
+        pending.text.push_str(&delta);
+        pending.count += 1;
+
+        let block_done = pending.text.ends_with("\n\n");
+        let full = pending.text.chars().count() >= self.max_chars;
+        let due = self
+            .interval
+            .is_some_and(|interval| pending.started.elapsed() >= interval);
+        if block_done || full || due {
+            self.flush().into_iter().collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// When the buffered text must be sent even if no further event arrives.
+    pub fn deadline(&self) -> Option<tokio::time::Instant> {
+        let pending = self.pending.as_ref()?;
+        let interval = self.interval?;
+        Some(tokio::time::Instant::from_std(pending.started + interval))
+    }
+
+    /// Whether no text is being held back.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_none()
+    }
+
+    /// Release the buffered text as one assistant event.
+    pub fn flush(&mut self) -> Option<AgentEvent> {
+        let pending = self.pending.take()?;
+        if pending.count == 1 {
+            return Some(pending.first);
+        }
+        let text = pending.text.trim_end().to_string();
+        Some(AgentEvent {
+            content: json!({
+                "type": "assistant",
+                "message": text,
+                "coalesced": pending.count,
+            }),
+            ..pending.first
+        })
+    }
+}
+
+/// Text carried by an assistant event: `message` or `text` as a string, or the text parts of
+/// a Cursor-style `message.content` array. `None` when the shape is not recognized.
+fn delta_text(content: &serde_json::Value) -> Option<String> {
+    for key in ["message", "text"] {
+        if let Some(text) = content.get(key).and_then(|v| v.as_str()) {
+            return Some(text.to_string());
+        }
+    }
+    let parts = content.get("message")?.get("content")?.as_array()?;
+    let text: String = parts
+        .iter()
+        .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn event(event_type: EventType, content: serde_json::Value) -> AgentEvent {
+        AgentEvent {
+            _agent_type: "jsonl".to_string(),
+            event_type,
+            content,
+            metadata: HashMap::new(),
+            timestamp: None,
+        }
+    }
+
+    fn delta(text: &str) -> AgentEvent {
+        event(EventType::Assistant, json!({ "message": text }))
+    }
+
+    fn rules(interval_seconds: u64, max_chars: usize) -> CoalesceRules {
+        CoalesceRules {
+            enabled: Some(true),
+            interval_seconds: Some(interval_seconds),
+            max_chars: Some(max_chars),
+        }
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        assert!(Coalescer::new(&CoalesceRules::default()).is_none());
+    }
+
+    #[test]
+    fn test_deltas_merge_until_another_event() {
+        let mut coalescer = Coalescer::new(&rules(60, 3000)).unwrap();
+        assert!(coalescer.push(delta("Hel")).is_empty());
+        assert!(coalescer.push(delta("lo, ")).is_empty());
+        assert!(coalescer.push(delta("world")).is_empty());
+        assert!(coalescer.deadline().is_some());
+
+        let out = coalescer.push(event(EventType::ToolCall, json!({"tool": "ls"})));
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].content["message"], "Hello, world");
+        assert_eq!(out[0].content["coalesced"], 3);
+        assert_eq!(out[1].event_type, EventType::ToolCall);
+        assert!(coalescer.deadline().is_none());
+    }
+
+    #[test]
+    fn test_block_end_and_size_flush() {
+        let mut coalescer = Coalescer::new(&rules(0, 20)).unwrap();
+        assert!(coalescer.deadline().is_none());
+        assert!(coalescer.push(delta("First para")).is_empty());
+        let out = coalescer.push(delta("graph.\n\n"));
+        assert_eq!(out[0].content["message"], "First paragraph.");
+
+        let out = coalescer.push(delta("a long delta of more than twenty"));
+        assert_eq!(out.len(), 1);
+        // A single delta is passed through unchanged.
+        assert!(out[0].content.get("coalesced").is_none());
+    }
+
+    #[test]
+    fn test_cursor_message_parts() {
+        let content = json!({
+            "type": "assistant",
+            "message": {"role": "assistant", "content": [{"type": "text", "text": "hi"}]}
+        });
+        assert_eq!(delta_text(&content).as_deref(), Some("hi"));
+        assert_eq!(delta_text(&json!({"other": 1})), None);
+    }
+}
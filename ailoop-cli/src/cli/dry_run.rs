@@ -0,0 +1,192 @@
+//! `--dry-run` for `ask`, `authorize` and `say`: check the channel and the server, show the
+//! message that would be sent, and answer with a canned response instead of asking a human.
+
+use crate::mode::OperationMode;
+use ailoop_core::models::{Message, MessageContent, ResponseType};
+use anyhow::{Context, Result};
+
+/// Where a dry-run message would have gone.
+pub struct Target {
+    /// HTTP URL of the server; `None` in direct mode, where the local terminal would be asked.
+    pub server: Option<String>,
+    /// Channels the server is holding prompts back on.
+    pub held_channels: Vec<String>,
+}
+
+impl Target {
+    /// Check that the server of `mode`, if any, answers its health endpoint.
+    pub async fn check(mode: &OperationMode) -> Result<Self> {
+        let Some(server_url) = mode.server_url.as_deref().filter(|_| mode.is_server()) else {
+            return Ok(Self {
+                server: None,
+                held_channels: Vec::new(),
+            });
+        };
+        let base = super::media::http_base(server_url)
+            .trim_end_matches('/')
+            .to_string();
+        let health: serde_json::Value = ailoop_core::transport::proxy::http_client()
+            .get(format!("{}/api/v1/health", base))
+            .send()
+            .await
+            .with_context(|| format!("Server {} is unreachable", base))?
+            .error_for_status()
+            .with_context(|| format!("Server {} is not healthy", base))?
+            .json()
+            .await
+            .context("Unexpected health response")?;
+        let held_channels = health["held_channels"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c.as_str().map(str::to_string))
+            .collect();
+        Ok(Self {
+            server: Some(base),
+            held_channels,
+        })
+    }
+
+    /// Print `message` as it would be sent. With `json`, to stderr, keeping stdout for the
+    /// command's usual JSON output.
+    pub fn show(&self, message: &Message, json: bool) -> Result<()> {
+        let destination = match &self.server {
+            Some(server) => format!("server {}", server),
+            None => "the local terminal (direct mode)".to_string(),
+        };
+        let mut lines = vec![format!(
+            "Dry run: would send to channel {} on {}:",
+            message.channel, destination
+        )];
+        if self.held_channels.contains(&message.channel) {
+            lines.push(format!(
+                "Note: channel {} is paused; a real prompt would wait until it is resumed",
+                message.channel
+            ));
+        }
+        lines.push(serde_json::to_string_pretty(message)?);
+        if json {
+            eprintln!("{}", lines.join("\n"));
+        } else {
+            println!("{}", lines.join("\n"));
+        }
+        Ok(())
+    }
+}
+
+/// Canned answer to a decision: the recommended option, else the first one.
+pub fn decision_response(decision: &Message) -> Message {
+    let MessageContent::Decision {
+        options,
+        recommendation,
+        ..
+    } = &decision.content
+    else {
+        return canned(
+            decision,
+            ResponseType::Cancelled,
+            None,
+            serde_json::json!({}),
+        );
+    };
+    let index = recommendation
+        .as_ref()
+        .and_then(|r| options.iter().position(|o| o.id == r.option_id))
+        .unwrap_or(0);
+    match options.get(index) {
+        Some(option) => canned(
+            decision,
+            ResponseType::Text,
+            Some(option.id.clone()),
+            serde_json::json!({
+                "option_id": option.id,
+                "label": option.label,
+                "index": index,
+            }),
+        ),
+        None => canned(
+            decision,
+            ResponseType::Cancelled,
+            None,
+            serde_json::json!({}),
+        ),
+    }
+}
+
+/// Canned answer to an authorization: approved, or denied when the default is no.
+pub fn authorization_response(authorization: &Message, approve: bool) -> Message {
+    let response_type = if approve {
+        ResponseType::AuthorizationApproved
+    } else {
+        ResponseType::AuthorizationDenied
+    };
+    canned(
+        authorization,
+        response_type,
+        Some("dry run".to_string()),
+        serde_json::json!({}),
+    )
+}
+
+fn canned(
+    prompt: &Message,
+    response_type: ResponseType,
+    answer: Option<String>,
+    mut metadata: serde_json::Value,
+) -> Message {
+    let mut response = Message::response(
+        prompt.channel.clone(),
+        MessageContent::Response {
+            answer,
+            response_type,
+        },
+        prompt.id,
+    );
+    metadata["dry_run"] = serde_json::json!(true);
+    response.metadata = Some(metadata);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{DecisionOption, DecisionRecommendation};
+
+    #[test]
+    fn test_canned_decision_takes_the_recommendation() {
+        let option = |id: &str| DecisionOption {
+            id: id.to_string(),
+            label: id.to_uppercase(),
+            detail_markdown: None,
+        };
+        let decision = ailoop_core::client::decision(
+            "ops",
+            "d1".to_string(),
+            "Which region?".to_string(),
+            None,
+            vec![option("eu"), option("us")],
+            Some(DecisionRecommendation {
+                option_id: "us".to_string(),
+                rationale_markdown: None,
+            }),
+            0,
+            false,
+            None,
+        );
+        let response = decision_response(&decision);
+        assert_eq!(response.correlation_id, Some(decision.id));
+        let metadata = response.metadata.unwrap();
+        assert_eq!(metadata["option_id"], "us");
+        assert_eq!(metadata["index"], 1);
+        assert_eq!(metadata["dry_run"], true);
+
+        let authorization = ailoop_core::client::authorization("ops", "deploy", 300);
+        assert!(matches!(
+            authorization_response(&authorization, false).content,
+            MessageContent::Response {
+                response_type: ResponseType::AuthorizationDenied,
+                ..
+            }
+        ));
+    }
+}
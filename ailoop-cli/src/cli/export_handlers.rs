@@ -0,0 +1,241 @@
+//! Handlers for the `ailoop export` subcommands: channel transcripts posted where the code
+//! lives.
+
+use super::schedule_handlers::{parse_at, parse_delay};
+use super::task_handlers::resolve_server_url;
+use ailoop_core::models::{Message, MessageContent, ResponseType};
+use ailoop_core::{ChannelClient, GitHubClient, IssueRef};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::fmt::Write;
+
+/// GitHub refuses comments longer than this many characters.
+const MAX_COMMENT_CHARS: usize = 65_536;
+
+/// Post the transcript of `channel` since `since`, or of one incident on it, as a comment on a
+/// GitHub issue or pull request, or with `dry_run` print it instead.
+pub async fn handle_export_github(
+    issue: String,
+    channel: String,
+    since: Option<String>,
+    incident: Option<String>,
+    limit: usize,
+    dry_run: bool,
+    server: String,
+) -> Result<()> {
+    let issue: IssueRef = issue.parse()?;
+    ailoop_core::channel::validation::validate_channel_name(&channel)
+        .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+    let since = since.as_deref().map(parse_since).transpose()?;
+    let server_url = resolve_server_url(server)?;
+    let messages = ChannelClient::new(&server_url)
+        .messages(&channel, since, incident.as_deref(), limit)
+        .await
+        .with_context(|| format!("Failed to fetch the history of '{}'", channel))?;
+    let transcript = github_transcript(&channel, since, &messages);
+    if dry_run {
+        print!("{}", transcript);
+        return Ok(());
+    }
+
+    let token = ["GITHUB_TOKEN", "GH_TOKEN"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|t| !t.is_empty()))
+        .context("Set GITHUB_TOKEN (or GH_TOKEN) to a token that may comment on the issue")?;
+    let mut client = GitHubClient::new(token);
+    if let Ok(api_url) = std::env::var("GITHUB_API_URL") {
+        client = client.with_api_url(api_url);
+    }
+    let url = client
+        .comment(&issue, &transcript)
+        .await
+        .context("Failed to post the transcript")?;
+    println!(
+        "Posted {} message(s) from '{}' to {}",
+        messages.len(),
+        channel,
+        url
+    );
+    Ok(())
+}
+
+/// `--since`: an age such as `2h` or `1d`, or a time as for `say --at`.
+fn parse_since(since: &str) -> Result<DateTime<Utc>> {
+    if since.contains(['-', ':']) {
+        parse_at(since)
+    } else {
+        let age = parse_delay(since).with_context(|| {
+            format!(
+                "Invalid --since '{}': use e.g. 2h or 2025-07-01T09:00",
+                since
+            )
+        })?;
+        Ok(Utc::now() - age)
+    }
+}
+
+/// Markdown transcript of `messages`, newest last. The oldest lines are left out when the
+/// comment would be too long for GitHub.
+pub fn github_transcript(
+    channel: &str,
+    since: Option<DateTime<Utc>>,
+    messages: &[Message],
+) -> String {
+    let lines: Vec<String> = messages.iter().filter_map(transcript_line).collect();
+    let mut header = format!("### ailoop transcript of `{}`\n\n", channel);
+    let (first, last) = (messages.first(), messages.last());
+    match (since.or(first.map(|m| m.timestamp)), last) {
+        (Some(from), Some(to)) => {
+            let _ = writeln!(
+                header,
+                "_{} message(s) from {} to {}_",
+                lines.len(),
+                from.format("%Y-%m-%d %H:%M UTC"),
+                to.timestamp.format("%Y-%m-%d %H:%M UTC")
+            );
+        }
+        _ => {
+            let _ = writeln!(header, "_No messages._");
+        }
+    }
+
+    let mut budget = MAX_COMMENT_CHARS - header.chars().count() - 64;
+    let mut kept = lines
+        .iter()
+        .rev()
+        .take_while(|line| {
+            let len = line.chars().count() + 1;
+            let fits = len <= budget;
+            budget = budget.saturating_sub(len);
+            fits
+        })
+        .collect::<Vec<_>>();
+    kept.reverse();
+
+    let mut out = header;
+    if kept.len() < lines.len() {
+        let _ = writeln!(
+            out,
+            "\n_{} earlier message(s) left out._",
+            lines.len() - kept.len()
+        );
+    }
+    if !kept.is_empty() {
+        out.push('\n');
+    }
+    for line in kept {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// One list item: time, speaker and what was said. Status pulses and task events are left
+/// out.
+fn transcript_line(message: &Message) -> Option<String> {
+    let text = match &message.content {
+        MessageContent::Decision {
+            summary, options, ..
+        } => {
+            let options: Vec<&str> = options.iter().map(|o| o.label.as_str()).collect();
+            format!("❓ {} ({})", summary, options.join(" / "))
+        }
+        MessageContent::Authorization { action, .. } => format!("🔐 authorize: {}", action),
+        MessageContent::Navigate { url, .. } => format!("🔗 {}", url),
+        MessageContent::Notification { text, .. } | MessageContent::Chat { text, .. } => {
+            text.clone()
+        }
+        MessageContent::Response {
+            answer,
+            response_type,
+        } => match response_type {
+            ResponseType::AuthorizationApproved => "✅ approved".to_string(),
+            ResponseType::AuthorizationDenied => match answer {
+                Some(reason) if !reason.is_empty() => format!("❌ denied: {}", reason),
+                _ => "❌ denied".to_string(),
+            },
+            ResponseType::Timeout => "⏱ no answer in time".to_string(),
+            ResponseType::Cancelled => "cancelled".to_string(),
+            ResponseType::Text => answer.clone().unwrap_or_default(),
+        },
+        _ => return None,
+    };
+    let speaker = match &message.content {
+        MessageContent::Response { .. } => message
+            .team_member()
+            .or_else(|| message.responder())
+            .map(str::to_string)
+            .unwrap_or_else(|| message.speaker()),
+        _ => message.speaker(),
+    };
+    Some(format!(
+        "- `{}` **{}**: {}",
+        message.timestamp.format("%m-%d %H:%M"),
+        speaker,
+        text.trim().replace('\n', "\n  ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{NotificationPriority, SenderType};
+
+    #[test]
+    fn transcript_lists_prompts_and_answers() {
+        let prompt = ailoop_core::client::authorization("ops", "deploy v2", 60);
+        let mut answer = Message::response(
+            "ops".to_string(),
+            MessageContent::Response {
+                answer: None,
+                response_type: ResponseType::AuthorizationApproved,
+            },
+            prompt.id,
+        );
+        answer.metadata =
+            Some(serde_json::json!({"responder": "telegram:alice", "member": "@alice"}));
+        let note = Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "deployed\nall green".to_string(),
+                priority: NotificationPriority::Normal,
+            },
+        );
+        let status = Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Status {
+                text: "working".to_string(),
+            },
+        );
+
+        let transcript = github_transcript("ops", None, &[prompt, answer, status, note]);
+        assert!(transcript.starts_with("### ailoop transcript of `ops`\n\n_3 message(s) from "));
+        assert!(transcript.contains("**agent**: 🔐 authorize: deploy v2\n"));
+        assert!(transcript.contains("**@alice**: ✅ approved\n"));
+        assert!(transcript.contains("**agent**: deployed\n  all green\n"));
+        assert!(!transcript.contains("working"));
+        assert!(github_transcript("ops", None, &[]).contains("_No messages._"));
+    }
+
+    #[test]
+    fn transcript_leaves_out_the_oldest_lines_when_too_long() {
+        let long = |i: usize| {
+            Message::new(
+                "ops".to_string(),
+                SenderType::Agent,
+                MessageContent::Notification {
+                    text: format!("{} {}", i, "x".repeat(10_000)),
+                    priority: NotificationPriority::Normal,
+                },
+            )
+        };
+        let messages: Vec<Message> = (0..10).map(long).collect();
+        let transcript = github_transcript("ops", None, &messages);
+        assert!(transcript.chars().count() <= MAX_COMMENT_CHARS);
+        assert!(transcript.contains("_4 earlier message(s) left out._"));
+        assert!(transcript.contains("**agent**: 9 x"));
+        assert!(!transcript.contains("**agent**: 3 x"));
+    }
+}
@@ -0,0 +1,165 @@
+//! One `say` or `navigate` sent to several channels (`--channels ops,dev,*-prod`).
+
+use super::task_handlers::resolve_server_url;
+use ailoop_core::channel::pattern::{is_pattern, matches_pattern};
+use ailoop_core::client::channel_client::ChannelClient;
+use ailoop_core::client::{ChannelDelivery, DeliveryStatus};
+use ailoop_core::models::Message;
+use ailoop_core::transport::outbox::Outbox;
+use anyhow::{bail, Context, Result};
+
+/// Targets from `--channels` when given, otherwise from `--channel`. Both take comma-separated
+/// lists.
+pub fn channel_targets(channel: &str, channels: Option<&str>) -> Vec<String> {
+    channels
+        .unwrap_or(channel)
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether `targets` is more than a single plain channel name.
+pub fn is_fan_out(targets: &[String]) -> bool {
+    targets.len() > 1 || targets.iter().any(|t| is_pattern(t))
+}
+
+/// Validate the plain names and expand the patterns against the channels the server has seen,
+/// keeping the order given and dropping duplicates.
+pub async fn resolve_channels(targets: &[String], server: &str) -> Result<Vec<String>> {
+    if targets.is_empty() {
+        bail!("No channel given");
+    }
+    let mut known: Option<Vec<String>> = None;
+    let mut resolved: Vec<String> = Vec::new();
+    for target in targets {
+        let matched = if is_pattern(target) {
+            if known.is_none() {
+                let server_url = resolve_server_url(server.to_string())?;
+                let names = ChannelClient::new(&server_url)
+                    .list()
+                    .await
+                    .context("Failed to list channels for channel patterns")?;
+                known = Some(names);
+            }
+            let matched: Vec<String> = known
+                .iter()
+                .flatten()
+                .filter(|name| matches_pattern(target, name))
+                .cloned()
+                .collect();
+            if matched.is_empty() {
+                eprintln!("Warning: no channel matches '{}'", target);
+            }
+            matched
+        } else {
+            ailoop_core::channel::validation::validate_channel_name(target)
+                .map_err(|e| anyhow::anyhow!("Invalid channel name '{}': {}", target, e))?;
+            vec![target.clone()]
+        };
+        for name in matched {
+            if !resolved.contains(&name) {
+                resolved.push(name);
+            }
+        }
+    }
+    if resolved.is_empty() {
+        bail!("No channel matches {}", targets.join(", "));
+    }
+    Ok(resolved)
+}
+
+/// Send `messages` over one connection. With an outbox, they are all spooled instead when the
+/// server is unreachable or older entries for it are still waiting.
+pub async fn deliver(
+    server_url: &str,
+    messages: Vec<Message>,
+    outbox: Option<&Outbox>,
+) -> Result<Vec<ChannelDelivery>> {
+    let Some(outbox) = outbox else {
+        return ailoop_core::client::send_to_channels(server_url, messages)
+            .await
+            .context("Failed to send to server");
+    };
+    let backlog = outbox.pending(Some(server_url))? > 0
+        && outbox.flush(Some(server_url)).await?.remaining > 0;
+    if !backlog {
+        match ailoop_core::client::send_to_channels(server_url, messages.clone()).await {
+            Ok(deliveries) => return Ok(deliveries),
+            Err(e) => {
+                tracing::warn!(server = %server_url, error = %e, "Server unreachable; spooling messages")
+            }
+        }
+    }
+    messages
+        .into_iter()
+        .map(|message| {
+            outbox.spool(server_url, &message)?;
+            Ok(ChannelDelivery {
+                channel: message.channel,
+                message_id: message.id,
+                status: DeliveryStatus::Spooled,
+                error: None,
+            })
+        })
+        .collect()
+}
+
+/// Print per-channel results; fails when the server refused any of the messages.
+pub fn report(deliveries: &[ChannelDelivery], json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(deliveries)?);
+    } else {
+        for delivery in deliveries {
+            match (&delivery.status, &delivery.error) {
+                (DeliveryStatus::Rejected, Some(error)) => {
+                    println!("{}: rejected ({})", delivery.channel, error)
+                }
+                (DeliveryStatus::Rejected, None) => println!("{}: rejected", delivery.channel),
+                (DeliveryStatus::Sent, _) => println!("{}: sent", delivery.channel),
+                (DeliveryStatus::Spooled, _) => println!("{}: saved to outbox", delivery.channel),
+            }
+        }
+    }
+    let rejected = deliveries
+        .iter()
+        .filter(|d| d.status == DeliveryStatus::Rejected)
+        .count();
+    if rejected > 0 {
+        bail!(
+            "{} of {} channels rejected the message",
+            rejected,
+            deliveries.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_targets() {
+        assert_eq!(channel_targets("public", None), ["public"]);
+        assert_eq!(channel_targets("a, b,,c", None), ["a", "b", "c"]);
+        assert_eq!(
+            channel_targets("public", Some("ops,*-prod")),
+            ["ops", "*-prod"]
+        );
+        assert!(!is_fan_out(&channel_targets("ops", None)));
+        assert!(is_fan_out(&channel_targets("ops,dev", None)));
+        assert!(is_fan_out(&channel_targets("*-prod", None)));
+    }
+
+    #[tokio::test]
+    async fn test_plain_channels_resolve_without_a_server() {
+        let targets = channel_targets("ops,dev,ops", None);
+        let resolved = resolve_channels(&targets, "").await.unwrap();
+        assert_eq!(resolved, ["ops", "dev"]);
+        assert!(resolve_channels(&["Bad Name".to_string()], "")
+            .await
+            .is_err());
+    }
+}
@@ -1,11 +1,22 @@
 //! Forward command for streaming agent output to ailoop server
 
+use crate::cli::checkpoint::Checkpoint;
+use crate::cli::coalesce::Coalescer;
+use crate::cli::guardrail::ToolGuard;
 use crate::cli::message_converter::MessageConverter;
-use crate::parser::{create_parser, InputFormat};
+use crate::cli::quarantine::Quarantine;
+use crate::parser::reassemble::JsonReassembler;
+use crate::parser::{create_parser, AgentEvent, InputFormat};
+use ailoop_core::models::{CoalesceRules, ConverterConfig, GuardrailConfig};
 use ailoop_core::transport::factory::{create_transport, TransportConfig, TransportType};
+use ailoop_core::transport::outbox::Outbox;
+use ailoop_core::transport::Transport;
 use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
-use tokio::io::AsyncBufReadExt;
+use tokio::io::{AsyncBufReadExt, AsyncSeekExt};
+use tracing::Instrument;
 
 /// Forward command configuration
 pub struct ForwardConfig {
@@ -17,6 +28,46 @@ pub struct ForwardConfig {
     pub file_path: Option<PathBuf>,
     pub client_id: Option<String>,
     pub input_file: Option<PathBuf>,
+    /// Spool messages here while the server is unreachable (WebSocket transport only).
+    pub outbox: Option<Outbox>,
+    /// Event-to-notification mapping (`[converter]`).
+    pub converter: ConverterConfig,
+    /// Tool calls that need approval (`[guardrail]`).
+    pub guardrail: GuardrailConfig,
+    /// Merging of assistant deltas for this channel (`[coalesce]`).
+    pub coalesce: CoalesceRules,
+    /// Append lines that fail to parse to this file.
+    pub quarantine: Option<PathBuf>,
+    /// Reassemble JSON objects that span several lines.
+    pub multiline: bool,
+    /// Skip events stamped before this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Skip events stamped at or after this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Parse and count events without sending anything.
+    pub dry_run: bool,
+    /// Resume `input_file` from, and record progress in, the checkpoint of `client_id` kept
+    /// in this directory.
+    pub checkpoint_dir: Option<PathBuf>,
+}
+
+/// Parse a `--since`/`--until` value: RFC 3339, `YYYY-MM-DDTHH:MM:SS` or `YYYY-MM-DD`, the
+/// last two in UTC.
+pub fn parse_time_bound(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S") {
+        return Ok(time.and_utc());
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_time(Default::default()).and_utc())
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "Invalid time '{}': expected RFC 3339 (2024-05-01T12:00:00Z) or a date (2024-05-01)",
+                value
+            )
+        })
 }
 
 /// Execute the forward command
@@ -25,16 +76,58 @@ pub async fn execute_forward(config: ForwardConfig) -> Result<()> {
     ailoop_core::channel::validation::validate_channel_name(&config.channel)
         .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
 
+    if config.multiline && config.format == InputFormat::Text {
+        anyhow::bail!("--multiline requires the json or stream-json format");
+    }
+    if let (Some(since), Some(until)) = (config.since, config.until) {
+        if since >= until {
+            anyhow::bail!("--since must be earlier than --until");
+        }
+    }
+    let checkpoint = match (
+        &config.checkpoint_dir,
+        &config.input_file,
+        &config.client_id,
+    ) {
+        (None, _, _) => None,
+        _ if config.dry_run => None,
+        (Some(dir), Some(input_file), Some(client_id)) => {
+            Some(Checkpoint::open(dir, client_id, input_file)?)
+        }
+        _ => anyhow::bail!("--resume requires --input and --client-id"),
+    };
+
     // Create parser
-    let mut parser = create_parser(config.agent_type.clone(), config.format)
+    let parser = create_parser(config.agent_type.clone(), config.format)
         .context("Failed to create parser")?;
 
+    // Without --agent-name or AILOOP_AGENT_NAME, messages name the agent type as sender.
+    let mut agent = ailoop_core::client::agent_identity();
+    agent
+        .name
+        .get_or_insert_with(|| parser.agent_type().to_string());
+
     // Create message converter
-    let mut converter = MessageConverter::new(
+    let converter = MessageConverter::new(
         config.channel.clone(),
         config.client_id.clone(),
         parser.agent_type().to_string(),
-    );
+    )
+    .with_rules(config.converter.rules_for(parser.agent_type()))
+    .with_agent(agent.clone());
+
+    // Approval prompts go to the server; with the file transport nobody can answer them.
+    let approval_url = match config.transport_type {
+        TransportType::WebSocket => config.url.clone(),
+        TransportType::File => None,
+    };
+    let guard = ToolGuard::new(
+        config.guardrail.clone(),
+        approval_url,
+        config.channel.clone(),
+    )
+    .filter(|_| !config.dry_run)
+    .map(|guard| guard.with_agent(agent));
 
     // Create transport
     let transport_config = TransportConfig {
@@ -46,111 +139,334 @@ pub async fn execute_forward(config: ForwardConfig) -> Result<()> {
             .map(|p| p.to_string_lossy().to_string()),
         channel: config.channel.clone(),
         client_id: config.client_id.clone(),
+        outbox: config.outbox.clone(),
+    };
+    // A dry run only parses; nothing is opened or sent.
+    let transport = match config.dry_run {
+        true => None,
+        false => Some(create_transport(transport_config).context("Failed to create transport")?),
+    };
+    let mut input = Input {
+        parser,
+        quarantine: Quarantine::open(config.quarantine.clone()).await?,
+        reassembler: config.multiline.then(JsonReassembler::default),
+        since: config.since,
+        until: config.until,
+        counts: BTreeMap::new(),
+        out_of_range: 0,
+    };
+
+    let mut pipeline = Pipeline {
+        coalescer: Coalescer::new(&config.coalesce),
+        guard,
+        converter,
+        transport,
     };
-    let mut transport = create_transport(transport_config).context("Failed to create transport")?;
 
     // Determine input source
     if let Some(input_file) = config.input_file {
         // Read from file
-        process_file_input(&mut *parser, &mut converter, &mut *transport, input_file).await?;
+        let checkpoint =
+            process_file_input(&mut input, &mut pipeline, input_file, checkpoint).await?;
+        if let Some(mut checkpoint) = checkpoint {
+            pipeline.flush_transport().await?;
+            checkpoint.save()?;
+        }
     } else {
         // Read from stdin
-        process_stdin_input(&mut *parser, &mut converter, &mut *transport).await?;
+        process_stdin_input(&mut input, &mut pipeline).await?;
+    }
+    input.finish().await?;
+    if config.dry_run {
+        input.report_counts();
+        return Ok(());
     }
 
-    // Flush and close transport
-    transport
-        .flush()
-        .await
-        .context("Failed to flush transport")?;
-    transport
-        .close()
-        .await
-        .context("Failed to close transport")?;
+    // Send anything still being coalesced, then flush and close transport
+    pipeline.finish().await;
+    if let Some(transport) = &mut pipeline.transport {
+        transport
+            .flush()
+            .await
+            .context("Failed to flush transport")?;
+        transport
+            .close()
+            .await
+            .context("Failed to close transport")?;
+    }
+
+    if let (Some(outbox), Some(url)) = (&config.outbox, &config.url) {
+        let pending = outbox.pending(Some(url.as_str()))?;
+        if pending > 0 {
+            eprintln!(
+                "{} message(s) waiting in the outbox ({}); run `ailoop outbox flush` once the server is reachable",
+                pending,
+                outbox.dir().display()
+            );
+        }
+    }
 
     Ok(())
 }
 
-/// Process input from stdin
-async fn process_stdin_input(
-    parser: &mut dyn crate::parser::AgentParser,
-    converter: &mut MessageConverter,
-    transport: &mut dyn ailoop_core::transport::Transport,
-) -> Result<()> {
-    let stdin = tokio::io::stdin();
-    let reader = tokio::io::BufReader::new(stdin);
-    let mut lines = reader.lines();
+/// Stages a parsed event goes through: coalescing, tool-call approval, conversion, transport.
+struct Pipeline {
+    coalescer: Option<Coalescer>,
+    guard: Option<ToolGuard>,
+    converter: MessageConverter,
+    /// `None` in a dry run.
+    transport: Option<Box<dyn Transport>>,
+}
 
-    while let Some(line) = lines
-        .next_line()
-        .await
-        .context("Failed to read line from stdin")?
-    {
-        match parser.parse_line(&line).await {
+impl Pipeline {
+    async fn push(&mut self, event: AgentEvent) {
+        let ready = match &mut self.coalescer {
+            Some(coalescer) => coalescer.push(event),
+            None => vec![event],
+        };
+        for event in ready {
+            self.forward(event).await;
+        }
+    }
+
+    /// Whether every event pushed so far has been sent.
+    fn is_idle(&self) -> bool {
+        self.coalescer.as_ref().is_none_or(Coalescer::is_empty)
+    }
+
+    async fn flush_transport(&mut self) -> Result<()> {
+        if let Some(transport) = &mut self.transport {
+            transport
+                .flush()
+                .await
+                .context("Failed to flush transport")?;
+        }
+        Ok(())
+    }
+
+    /// When coalesced text must be sent even if no further input arrives.
+    fn deadline(&self) -> Option<tokio::time::Instant> {
+        self.coalescer.as_ref().and_then(Coalescer::deadline)
+    }
+
+    /// Send any coalesced text now.
+    async fn finish(&mut self) {
+        if let Some(event) = self.coalescer.as_mut().and_then(Coalescer::flush) {
+            self.forward(event).await;
+        }
+    }
+
+    /// Ask for approval of guarded tool calls, then convert and send the event.
+    async fn forward(&mut self, event: AgentEvent) {
+        let event_type = event.event_type.name().to_string();
+        let span = tracing::info_span!("forward", event_type = %event_type);
+        self.forward_event(event, &event_type)
+            .instrument(span)
+            .await
+    }
+
+    async fn forward_event(&mut self, event: AgentEvent, event_type: &str) {
+        if let Some(guard) = &self.guard {
+            if let Err(e) = guard.check(&event).await {
+                eprintln!("Warning: Tool-call approval failed: {}", e);
+            }
+        }
+
+        let Some(transport) = &mut self.transport else {
+            return;
+        };
+
+        // Convert event to messages
+        let messages = self.converter.convert(event);
+
+        // Send each message through transport
+        for message in messages {
+            let sent = transport.send(message).await;
+            ailoop_server::server::telemetry::forwarded(event_type, sent.is_ok());
+            if let Err(e) = sent {
+                eprintln!("Warning: Failed to send message: {}", e);
+                // Continue processing despite transport errors
+            }
+        }
+    }
+}
+
+/// Parser state shared by the stdin and file inputs.
+struct Input {
+    parser: Box<dyn crate::parser::AgentParser>,
+    quarantine: Quarantine,
+    /// Joins multi-line JSON values (`--multiline`).
+    reassembler: Option<JsonReassembler>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    /// Events passed on, by type.
+    counts: BTreeMap<String, usize>,
+    /// Events skipped by `--since`/`--until`.
+    out_of_range: usize,
+}
+
+impl Input {
+    /// Handle one line read from the input.
+    async fn push_line(&mut self, pipeline: &mut Pipeline, line: &str) {
+        let Some(reassembler) = &mut self.reassembler else {
+            return self.process_line(pipeline, line).await;
+        };
+        for value in reassembler.push_line(line) {
+            match value {
+                Ok(value) => self.process_line(pipeline, &value).await,
+                Err(e) => {
+                    self.quarantine.line_read();
+                    self.quarantine.record(e.partial(), &e.clone().into()).await;
+                }
+            }
+        }
+    }
+
+    /// Parse one input line and send the event; malformed lines are skipped into `quarantine`.
+    async fn process_line(&mut self, pipeline: &mut Pipeline, line: &str) {
+        self.quarantine.line_read();
+        match self.parser.parse_line(line).await {
             Ok(Some(event)) => {
-                let messages = converter.convert(event);
-                for message in messages {
-                    if let Err(e) = transport.send(message).await {
-                        eprintln!("Warning: Failed to send message: {}", e);
-                    }
+                if !self.in_range(&event) {
+                    self.out_of_range += 1;
+                    return;
                 }
+                *self
+                    .counts
+                    .entry(event.event_type.name().to_string())
+                    .or_default() += 1;
+                pipeline.push(event).await;
             }
-            Ok(None) => {}
-            Err(e) => {
-                eprintln!("Warning: Failed to parse line (skipping): {}", e);
-                eprintln!("  Line: {}", line);
+            Ok(None) => {
+                // Line was skipped (empty or comment)
             }
+            Err(e) => self.quarantine.record(line, &e).await,
+        }
+    }
+
+    /// Whether no multi-line value is half read.
+    fn is_idle(&self) -> bool {
+        self.reassembler
+            .as_ref()
+            .is_none_or(JsonReassembler::is_idle)
+    }
+
+    /// Whether the event falls within `--since`/`--until`; events without a timestamp are kept.
+    fn in_range(&self, event: &AgentEvent) -> bool {
+        let Some(timestamp) = event.timestamp else {
+            return true;
+        };
+        self.since.is_none_or(|since| timestamp >= since)
+            && self.until.is_none_or(|until| timestamp < until)
+    }
+
+    /// Print the dry-run summary: events per type that would have been sent.
+    fn report_counts(&self) {
+        let total: usize = self.counts.values().sum();
+        println!("Dry run: {} event(s) would be forwarded", total);
+        for (event_type, count) in &self.counts {
+            println!("  {:<16} {}", event_type, count);
+        }
+        if self.out_of_range > 0 {
+            println!(
+                "{} event(s) outside the --since/--until range were skipped",
+                self.out_of_range
+            );
         }
     }
 
+    /// End of input: quarantine an unfinished value and report the totals.
+    async fn finish(&mut self) -> Result<()> {
+        if let Some(e) = self.reassembler.as_mut().and_then(|r| r.finish()) {
+            self.quarantine.line_read();
+            self.quarantine.record(e.partial(), &e.clone().into()).await;
+        }
+        self.quarantine.finish().await
+    }
+}
+
+/// Process input from stdin
+async fn process_stdin_input(input: &mut Input, pipeline: &mut Pipeline) -> Result<()> {
+    let stdin = tokio::io::stdin();
+    let reader = tokio::io::BufReader::new(stdin);
+    let mut lines = reader.lines();
+
+    loop {
+        let deadline = pipeline.deadline();
+        let line = tokio::select! {
+            line = lines.next_line() => line.context("Failed to read line from stdin")?,
+            // Coalesced text is due while the agent is quiet.
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)),
+                if deadline.is_some() => {
+                pipeline.finish().await;
+                continue;
+            }
+        };
+        let Some(line) = line else {
+            break;
+        };
+        input.push_line(pipeline, &line).await;
+    }
+
     Ok(())
 }
 
-/// Process input from file
+/// Process input from file, starting at the checkpoint if there is one. Progress is
+/// committed to the checkpoint only once the events read so far have all been sent.
 async fn process_file_input(
-    parser: &mut dyn crate::parser::AgentParser,
-    converter: &mut MessageConverter,
-    transport: &mut dyn ailoop_core::transport::Transport,
+    input: &mut Input,
+    pipeline: &mut Pipeline,
     file_path: PathBuf,
-) -> Result<()> {
-    let file = tokio::fs::File::open(&file_path)
+    mut checkpoint: Option<Checkpoint>,
+) -> Result<Option<Checkpoint>> {
+    let mut file = tokio::fs::File::open(&file_path)
         .await
         .with_context(|| format!("Failed to open file: {:?}", file_path))?;
 
+    let (mut offset, mut lines) = checkpoint
+        .as_ref()
+        .map_or((0, 0), |c| (c.offset(), c.lines()));
+    if offset > 0 {
+        file.seek(std::io::SeekFrom::Start(offset))
+            .await
+            .with_context(|| format!("Failed to seek in file: {:?}", file_path))?;
+        eprintln!("Resuming {} after line {}", file_path.display(), lines);
+    }
+
     let mut reader = tokio::io::BufReader::new(file);
     let mut line = String::new();
 
-    while reader.read_line(&mut line).await? > 0 {
-        let line_trimmed = line.trim_end();
-
-        // Parse line (skip malformed lines with warning)
-        match parser.parse_line(line_trimmed).await {
-            Ok(Some(event)) => {
-                // Convert event to messages
-                let messages = converter.convert(event);
-
-                // Send each message through transport
-                for message in messages {
-                    if let Err(e) = transport.send(message).await {
-                        eprintln!("Warning: Failed to send message: {}", e);
-                        // Continue processing despite transport errors
-                    }
-                }
-            }
-            Ok(None) => {
-                // Line was skipped (empty or comment)
-            }
-            Err(e) => {
-                // Malformed line - log warning and continue
-                eprintln!("Warning: Failed to parse line (skipping): {}", e);
-                eprintln!("  Line: {}", line_trimmed);
-            }
+    loop {
+        let read = reader.read_line(&mut line).await?;
+        if read == 0 {
+            break;
         }
-
+        input.push_line(pipeline, line.trim_end()).await;
         line.clear();
+        offset += read as u64;
+        lines += 1;
+
+        let Some(checkpoint) = &mut checkpoint else {
+            continue;
+        };
+        if input.is_idle() && pipeline.is_idle() {
+            checkpoint.commit(offset, lines);
+        }
+        if checkpoint.is_due() {
+            pipeline.flush_transport().await?;
+            checkpoint.save()?;
+        }
     }
 
-    Ok(())
+    // Coalesced text is sent now, so the end of the file counts as done unless it stops
+    // inside a multi-line value that may still be completed.
+    pipeline.finish().await;
+    if let Some(checkpoint) = &mut checkpoint {
+        if input.is_idle() {
+            checkpoint.commit(offset, lines);
+        }
+    }
+    Ok(checkpoint)
 }
 
 #[cfg(test)]
@@ -187,6 +503,16 @@ mod tests {
             file_path: Some(output_path.clone()),
             client_id: None,
             input_file: Some(input_file.path().to_path_buf()),
+            outbox: None,
+            converter: Default::default(),
+            guardrail: Default::default(),
+            coalesce: Default::default(),
+            quarantine: None,
+            multiline: false,
+            since: None,
+            until: None,
+            dry_run: false,
+            checkpoint_dir: None,
         };
 
         execute_forward(config).await?;
@@ -216,4 +542,295 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_forward_coalesces_text_parts() -> Result<()> {
+        let output_file = NamedTempFile::new()?;
+        let output_path = output_file.path().to_path_buf();
+
+        let input_content = r#"{"type":"text","timestamp":1700000001000,"sessionID":"sess-1","part":{"type":"text","text":"Building "}}
+{"type":"text","timestamp":1700000001100,"sessionID":"sess-1","part":{"type":"text","text":"the "}}
+{"type":"text","timestamp":1700000001200,"sessionID":"sess-1","part":{"type":"text","text":"project"}}
+{"type":"step_finish","timestamp":1700000002000,"sessionID":"sess-1","part":{"type":"step-finish","reason":"stop","cost":1.2,"tokens":12}}
+"#;
+        let input_file = write_temp_file(input_content)?;
+
+        let config = ForwardConfig {
+            channel: "opencode-channel".to_string(),
+            agent_type: Some("opencode".to_string()),
+            format: InputFormat::StreamJson,
+            transport_type: TransportType::File,
+            url: None,
+            file_path: Some(output_path.clone()),
+            client_id: None,
+            input_file: Some(input_file.path().to_path_buf()),
+            outbox: None,
+            converter: Default::default(),
+            guardrail: Default::default(),
+            coalesce: CoalesceRules {
+                enabled: Some(true),
+                ..Default::default()
+            },
+            quarantine: None,
+            multiline: false,
+            since: None,
+            until: None,
+            dry_run: false,
+            checkpoint_dir: None,
+        };
+
+        execute_forward(config).await?;
+
+        let output = std::fs::read_to_string(&output_path)?;
+        let messages: Vec<Message> = output
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+
+        assert_eq!(messages.len(), 2);
+        match &messages[0].content {
+            MessageContent::Notification { text, .. } => {
+                assert_eq!(text, "[opencode] Building the project")
+            }
+            _ => anyhow::bail!("Expected notification message"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_forward_quarantines_malformed_lines() -> Result<()> {
+        let output_file = NamedTempFile::new()?;
+        let quarantine_dir = tempfile::tempdir()?;
+        let quarantine_path = quarantine_dir.path().join("bad_lines.jsonl");
+
+        let input_content = r#"{"type":"text","timestamp":1700000001000,"sessionID":"sess-1","part":{"type":"text","text":"Before"}}
+{"type":"text","part":
+{"type":"text","timestamp":1700000001100,"sessionID":"sess-1","part":{"type":"text","text":"After"}}
+"#;
+        let input_file = write_temp_file(input_content)?;
+
+        let config = ForwardConfig {
+            channel: "opencode-channel".to_string(),
+            agent_type: Some("opencode".to_string()),
+            format: InputFormat::StreamJson,
+            transport_type: TransportType::File,
+            url: None,
+            file_path: Some(output_file.path().to_path_buf()),
+            client_id: None,
+            input_file: Some(input_file.path().to_path_buf()),
+            outbox: None,
+            converter: Default::default(),
+            guardrail: Default::default(),
+            coalesce: Default::default(),
+            quarantine: Some(quarantine_path.clone()),
+            multiline: false,
+            since: None,
+            until: None,
+            dry_run: false,
+            checkpoint_dir: None,
+        };
+
+        execute_forward(config).await?;
+
+        let output = std::fs::read_to_string(output_file.path())?;
+        assert_eq!(output.lines().filter(|l| !l.trim().is_empty()).count(), 2);
+        assert_eq!(
+            std::fs::read_to_string(&quarantine_path)?,
+            "{\"type\":\"text\",\"part\":\n"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_forward_reassembles_multiline_json() -> Result<()> {
+        let output_file = NamedTempFile::new()?;
+
+        let input_content = r#"{
+  "type": "text",
+  "timestamp": 1700000001000,
+  "sessionID": "sess-1",
+  "part": {
+    "type": "text",
+    "text": "Pretty {printed}"
+  }
+}
+{"type":"text","timestamp":1700000001100,"sessionID":"sess-1","part":{"type":"text","text":"Compact"}}
+"#;
+        let input_file = write_temp_file(input_content)?;
+
+        let config = ForwardConfig {
+            channel: "opencode-channel".to_string(),
+            agent_type: Some("opencode".to_string()),
+            format: InputFormat::StreamJson,
+            transport_type: TransportType::File,
+            url: None,
+            file_path: Some(output_file.path().to_path_buf()),
+            client_id: None,
+            input_file: Some(input_file.path().to_path_buf()),
+            outbox: None,
+            converter: Default::default(),
+            guardrail: Default::default(),
+            coalesce: Default::default(),
+            quarantine: None,
+            multiline: true,
+            since: None,
+            until: None,
+            dry_run: false,
+            checkpoint_dir: None,
+        };
+
+        execute_forward(config).await?;
+
+        let output = std::fs::read_to_string(output_file.path())?;
+        let texts: Vec<String> = output
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| match serde_json::from_str::<Message>(l)?.content {
+                MessageContent::Notification { text, .. } => Ok(text),
+                _ => anyhow::bail!("Expected notification message"),
+            })
+            .collect::<Result<_>>()?;
+        assert_eq!(texts.len(), 2);
+        assert!(texts[0].contains("Pretty {printed}"));
+        assert!(texts[1].contains("Compact"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_forward_filters_time_range() -> Result<()> {
+        let output_file = NamedTempFile::new()?;
+
+        let input_content = r#"{"type":"text","timestamp":1700000001000,"sessionID":"sess-1","part":{"type":"text","text":"Too early"}}
+{"type":"text","timestamp":1700000002000,"sessionID":"sess-1","part":{"type":"text","text":"In range"}}
+{"type":"text","timestamp":1700000003000,"sessionID":"sess-1","part":{"type":"text","text":"Too late"}}
+"#;
+        let input_file = write_temp_file(input_content)?;
+
+        let config = ForwardConfig {
+            channel: "opencode-channel".to_string(),
+            agent_type: Some("opencode".to_string()),
+            format: InputFormat::StreamJson,
+            transport_type: TransportType::File,
+            url: None,
+            file_path: Some(output_file.path().to_path_buf()),
+            client_id: None,
+            input_file: Some(input_file.path().to_path_buf()),
+            outbox: None,
+            converter: Default::default(),
+            guardrail: Default::default(),
+            coalesce: Default::default(),
+            quarantine: None,
+            multiline: false,
+            since: Some(parse_time_bound("2023-11-14T22:13:22Z")?),
+            until: Some(parse_time_bound("2023-11-14T22:13:23Z")?),
+            dry_run: false,
+            checkpoint_dir: None,
+        };
+
+        execute_forward(config).await?;
+
+        let output = std::fs::read_to_string(output_file.path())?;
+        let lines: Vec<&str> = output.lines().filter(|l| !l.trim().is_empty()).collect();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("In range"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_forward_dry_run_sends_nothing() -> Result<()> {
+        let output_dir = tempfile::tempdir()?;
+        let output_path = output_dir.path().join("messages.jsonl");
+        let input_file = write_temp_file(
+            r#"{"type":"text","timestamp":1700000001000,"sessionID":"sess-1","part":{"type":"text","text":"Hello"}}
+"#,
+        )?;
+
+        let config = ForwardConfig {
+            channel: "opencode-channel".to_string(),
+            agent_type: Some("opencode".to_string()),
+            format: InputFormat::StreamJson,
+            transport_type: TransportType::File,
+            url: None,
+            file_path: Some(output_path.clone()),
+            client_id: None,
+            input_file: Some(input_file.path().to_path_buf()),
+            outbox: None,
+            converter: Default::default(),
+            guardrail: Default::default(),
+            coalesce: Default::default(),
+            quarantine: None,
+            multiline: false,
+            since: None,
+            until: None,
+            dry_run: true,
+            checkpoint_dir: None,
+        };
+
+        execute_forward(config).await?;
+        assert!(!output_path.exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_forward_resumes_from_checkpoint() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input_path = dir.path().join("agent.jsonl");
+        let line = |n: u32| {
+            format!(
+                "{{\"type\":\"text\",\"timestamp\":{},\"sessionID\":\"sess-1\",\"part\":{{\"type\":\"text\",\"text\":\"Line {}\"}}}}\n",
+                1700000001000u64 + n as u64,
+                n
+            )
+        };
+        std::fs::write(&input_path, line(1) + &line(2))?;
+
+        let run = |output: PathBuf| ForwardConfig {
+            channel: "opencode-channel".to_string(),
+            agent_type: Some("opencode".to_string()),
+            format: InputFormat::StreamJson,
+            transport_type: TransportType::File,
+            url: None,
+            file_path: Some(output),
+            client_id: Some("replay".to_string()),
+            input_file: Some(input_path.clone()),
+            outbox: None,
+            converter: Default::default(),
+            guardrail: Default::default(),
+            coalesce: Default::default(),
+            quarantine: None,
+            multiline: false,
+            since: None,
+            until: None,
+            dry_run: false,
+            checkpoint_dir: Some(dir.path().join("checkpoints")),
+        };
+
+        let first = dir.path().join("first.jsonl");
+        execute_forward(run(first.clone())).await?;
+        assert_eq!(std::fs::read_to_string(&first)?.lines().count(), 2);
+
+        // The agent wrote more; only the new line is sent.
+        let mut file = std::fs::OpenOptions::new().append(true).open(&input_path)?;
+        std::io::Write::write_all(&mut file, line(3).as_bytes())?;
+        let second = dir.path().join("second.jsonl");
+        execute_forward(run(second.clone())).await?;
+        let output = std::fs::read_to_string(&second)?;
+        assert_eq!(output.lines().count(), 1);
+        assert!(output.contains("Line 3"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_time_bound() -> Result<()> {
+        let expected = parse_time_bound("2024-05-01T12:00:00Z")?;
+        assert_eq!(parse_time_bound("2024-05-01T14:00:00+02:00")?, expected);
+        assert_eq!(parse_time_bound("2024-05-01T12:00:00")?, expected);
+        assert_eq!(
+            parse_time_bound("2024-05-01")?,
+            parse_time_bound("2024-05-01T00:00:00Z")?
+        );
+        assert!(parse_time_bound("yesterday").is_err());
+        Ok(())
+    }
 }
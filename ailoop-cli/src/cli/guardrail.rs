@@ -0,0 +1,272 @@
+//! Tool-call approval bridge for `forward`
+//!
+//! Tool calls matching a `[guardrail]` pattern raise an authorization prompt on the server.
+//! Only a granted call is recorded, as a marker file and/or a JSON line on stdout, so a hook
+//! in the agent can wait for it before running the tool.
+
+use crate::parser::{AgentEvent, EventType};
+use ailoop_core::models::{
+    AgentIdentity, GuardrailConfig, Message, MessageContent, ResponseType, SenderType,
+    DEFAULT_AUTHORIZATION_TIMEOUT_SECS,
+};
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::path::PathBuf;
+
+/// Outcome of checking one event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Not a tool call, or no pattern matched.
+    Unguarded,
+    Approved {
+        tool_call_id: String,
+    },
+    Denied {
+        tool_call_id: String,
+    },
+}
+
+/// Raises authorization prompts for dangerous tool calls.
+pub struct ToolGuard {
+    config: GuardrailConfig,
+    server_url: Option<String>,
+    channel: String,
+    timeout_secs: u32,
+    agent: Option<AgentIdentity>,
+}
+
+impl ToolGuard {
+    /// Guard for `channel`, or `None` when no patterns are configured. Without a server URL
+    /// (file transport) matching calls are denied, since nobody can approve them.
+    pub fn new(
+        config: GuardrailConfig,
+        server_url: Option<String>,
+        channel: String,
+    ) -> Option<Self> {
+        if config.patterns.is_empty() {
+            return None;
+        }
+        let timeout_secs = config
+            .timeout_seconds
+            .unwrap_or(DEFAULT_AUTHORIZATION_TIMEOUT_SECS);
+        Some(Self {
+            config,
+            server_url,
+            channel,
+            timeout_secs,
+            agent: None,
+        })
+    }
+
+    /// Name `agent` as the sender of approval prompts.
+    pub fn with_agent(mut self, agent: AgentIdentity) -> Self {
+        self.agent = Some(agent);
+        self
+    }
+
+    /// Ask for approval when `event` is a matching tool call, recording a granted approval.
+    pub async fn check(&self, event: &AgentEvent) -> Result<Verdict> {
+        if event.event_type != EventType::ToolCall {
+            return Ok(Verdict::Unguarded);
+        }
+        let tool = tool_name(&event.content);
+        let command = tool_command(&event.content);
+        let Some(pattern) = self
+            .config
+            .matching_pattern(&format!("{} {}", tool, command))
+        else {
+            return Ok(Verdict::Unguarded);
+        };
+        let tool_call_id = tool_call_id(event);
+
+        let Some(server_url) = &self.server_url else {
+            eprintln!(
+                "Guardrail: denied tool call {} ({}): no server to ask for approval",
+                tool_call_id, pattern
+            );
+            return Ok(Verdict::Denied { tool_call_id });
+        };
+
+        let mut message = Message::new(
+            self.channel.clone(),
+            SenderType::Agent,
+            MessageContent::Authorization {
+                action: format!("Run {}: {}", tool, command),
+                context: Some(json!({
+                    "tool": tool,
+                    "command": command,
+                    "args": find_args(&event.content),
+                    "pattern": pattern,
+                    "tool_call_id": tool_call_id,
+                })),
+                timeout_seconds: self.timeout_secs,
+            },
+        );
+        message.agent = self.agent.clone();
+        let response = ailoop_core::transport::websocket::send_message_and_wait_response(
+            server_url.clone(),
+            self.channel.clone(),
+            message,
+            self.timeout_secs,
+        )
+        .await
+        .context("Failed to request tool-call approval")?;
+        let approved = matches!(
+            response.map(|m| m.content),
+            Some(MessageContent::Response {
+                response_type: ResponseType::AuthorizationApproved,
+                ..
+            })
+        );
+        if !approved {
+            eprintln!("Guardrail: denied tool call {} ({})", tool_call_id, pattern);
+            return Ok(Verdict::Denied { tool_call_id });
+        }
+
+        self.record_approval(&tool_call_id, &tool, &command)?;
+        Ok(Verdict::Approved { tool_call_id })
+    }
+
+    fn record_approval(&self, tool_call_id: &str, tool: &str, command: &str) -> Result<()> {
+        let record = json!({
+            "tool_call_id": tool_call_id,
+            "tool": tool,
+            "command": command,
+            "approved": true,
+            "approved_at": chrono::Utc::now().to_rfc3339(),
+        });
+        if let Some(dir) = &self.config.approval_dir {
+            let dir = PathBuf::from(dir);
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create approval directory {:?}", dir))?;
+            let path = dir.join(format!("{}.approved", marker_name(tool_call_id)));
+            std::fs::write(&path, serde_json::to_vec_pretty(&record)?)
+                .with_context(|| format!("Failed to write approval marker {:?}", path))?;
+        }
+        if self.config.ack_stdout {
+            println!("{}", record);
+        }
+        Ok(())
+    }
+}
+
+fn tool_name(content: &serde_json::Value) -> String {
+    ["tool", "name"]
+        .iter()
+        .find_map(|key| content.get(*key).and_then(|v| v.as_str()))
+        .unwrap_or("tool")
+        .to_string()
+}
+
+/// The command a tool call runs: a `command`/`cmd` argument when present, else all arguments.
+fn tool_command(content: &serde_json::Value) -> String {
+    let args = find_args(content).unwrap_or(&serde_json::Value::Null);
+    ["command", "cmd"]
+        .iter()
+        .find_map(|key| args.get(*key).and_then(|v| v.as_str()))
+        .map(str::to_string)
+        .or_else(|| args.as_str().map(str::to_string))
+        .unwrap_or_else(|| {
+            if args.is_null() {
+                String::new()
+            } else {
+                args.to_string()
+            }
+        })
+}
+
+/// The first `args` object, searched depth-first; Cursor nests it under the tool kind
+/// (`tool_call.shellToolCall.args`).
+fn find_args(value: &serde_json::Value) -> Option<&serde_json::Value> {
+    let object = value.as_object()?;
+    object
+        .get("args")
+        .or_else(|| object.values().find_map(find_args))
+}
+
+/// The agent's id for the call when it has one, else a fresh id.
+fn tool_call_id(event: &AgentEvent) -> String {
+    ["call_id", "tool_call_id", "id"]
+        .iter()
+        .find_map(|key| event.content.get(*key).and_then(|v| v.as_str()))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// `id` reduced to characters that are safe in a file name.
+fn marker_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn tool_call(content: serde_json::Value) -> AgentEvent {
+        AgentEvent {
+            _agent_type: "opencode".to_string(),
+            event_type: EventType::ToolCall,
+            content,
+            metadata: HashMap::new(),
+            timestamp: None,
+        }
+    }
+
+    fn guard(server_url: Option<String>) -> ToolGuard {
+        ToolGuard::new(
+            GuardrailConfig {
+                patterns: vec!["rm".to_string()],
+                ..Default::default()
+            },
+            server_url,
+            "public".to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_no_patterns_means_no_guard() {
+        assert!(ToolGuard::new(GuardrailConfig::default(), None, "public".to_string()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_calls_are_unguarded() {
+        let event = tool_call(json!({"tool": "shell", "args": {"cmd": "ls -la"}}));
+        assert_eq!(guard(None).check(&event).await.unwrap(), Verdict::Unguarded);
+    }
+
+    #[tokio::test]
+    async fn test_matching_call_without_server_is_denied() {
+        let event = tool_call(json!({
+            "tool": "shell",
+            "call_id": "call/1",
+            "args": {"cmd": "rm -rf build"}
+        }));
+        assert_eq!(
+            guard(None).check(&event).await.unwrap(),
+            Verdict::Denied {
+                tool_call_id: "call/1".to_string()
+            }
+        );
+        assert_eq!(marker_name("call/1"), "call_1");
+    }
+
+    #[test]
+    fn test_command_from_nested_cursor_args() {
+        let content = json!({
+            "type": "tool_call",
+            "call_id": "c1",
+            "tool_call": {"shellToolCall": {"args": {"command": "terraform apply"}}}
+        });
+        assert_eq!(tool_command(&content), "terraform apply");
+    }
+}
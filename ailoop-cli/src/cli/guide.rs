@@ -0,0 +1,195 @@
+//! Long-form guide pages built into the binary (`ailoop guide protocols`).
+//!
+//! Pages are plain text: `# ` starts a section, lines indented by four spaces are examples and
+//! blank lines separate paragraphs. `--man` renders a page as roff, section 7.
+
+use anyhow::{bail, Result};
+
+/// One guide page.
+pub struct Topic {
+    pub name: &'static str,
+    pub summary: &'static str,
+    pub text: &'static str,
+}
+
+pub const TOPICS: &[Topic] = &[
+    Topic {
+        name: "protocols",
+        summary: "Transports, message format, discovery and strict mode",
+        text: include_str!("guide/protocols.txt"),
+    },
+    Topic {
+        name: "providers",
+        summary: "Built-in providers, exec sinks and delivery",
+        text: include_str!("guide/providers.txt"),
+    },
+    Topic {
+        name: "files",
+        summary: "Config file, state and cache directories, server lock",
+        text: include_str!("guide/files.txt"),
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Topic> {
+    TOPICS.iter().find(|t| t.name.eq_ignore_ascii_case(name))
+}
+
+/// The page asked for by `ailoop help <topic> [--man]`, an alias of `ailoop guide`, and
+/// whether `--man` was given. `args` excludes the program name. `help` with a command name or
+/// nothing after it is left to the framework's own help.
+pub fn help_alias(args: &[String]) -> Option<(&'static Topic, bool)> {
+    let (command, rest) = args.split_first()?;
+    if command != "help" {
+        return None;
+    }
+    let mut topic = None;
+    let mut man = false;
+    for arg in rest {
+        match arg.as_str() {
+            "--man" => man = true,
+            name if topic.is_none() => topic = Some(find(name)?),
+            _ => return None,
+        }
+    }
+    Some((topic?, man))
+}
+
+/// Handle `ailoop guide [TOPIC] [--man]`.
+pub fn handle_guide(topic: Option<String>, man: bool) -> Result<()> {
+    let Some(name) = topic else {
+        if man {
+            print!("{}", index_roff());
+        } else {
+            println!("Guide topics (ailoop guide <topic>):");
+            for topic in TOPICS {
+                println!("  {:<10} {}", topic.name, topic.summary);
+            }
+        }
+        return Ok(());
+    };
+    let Some(topic) = find(&name) else {
+        let names: Vec<&str> = TOPICS.iter().map(|t| t.name).collect();
+        bail!("No guide topic '{}'; topics: {}", name, names.join(", "));
+    };
+    if man {
+        print!("{}", to_roff(topic));
+    } else {
+        print!("{}", to_terminal(topic.text));
+    }
+    Ok(())
+}
+
+/// Section titles in capitals and body text indented, as in a man page.
+fn to_terminal(text: &str) -> String {
+    let mut out = String::new();
+    for line in text.lines() {
+        if let Some(title) = line.strip_prefix("# ") {
+            out.push_str(&title.to_uppercase());
+        } else if !line.is_empty() {
+            out.push_str("    ");
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The page as a roff man page named `ailoop-<topic>(7)`.
+pub fn to_roff(topic: &Topic) -> String {
+    let mut out = header(&format!("ailoop-{}", topic.name), topic.summary);
+    let mut example = false;
+    for line in topic.text.lines() {
+        let indented = line.strip_prefix("    ");
+        if example && indented.is_none() {
+            out.push_str(".fi\n.RE\n");
+            example = false;
+        }
+        if let Some(code) = indented {
+            if !example {
+                out.push_str(".RS 4\n.nf\n");
+                example = true;
+            }
+            out.push_str(&escape(code));
+        } else if let Some(title) = line.strip_prefix("# ") {
+            out.push_str(&format!(".SH {}", escape(&title.to_uppercase())));
+        } else if line.is_empty() {
+            out.push_str(".PP");
+        } else {
+            out.push_str(&escape(line));
+        }
+        out.push('\n');
+    }
+    if example {
+        out.push_str(".fi\n.RE\n");
+    }
+    out
+}
+
+/// `ailoop-guide(7)`, listing the topics.
+fn index_roff() -> String {
+    let mut out = header("ailoop-guide", "Guide pages built into ailoop");
+    out.push_str(".SH TOPICS\n");
+    for topic in TOPICS {
+        out.push_str(&format!(
+            ".TP\n\\fBailoop-{}\\fR(7)\n{}\n",
+            topic.name,
+            escape(topic.summary)
+        ));
+    }
+    out
+}
+
+fn header(name: &str, summary: &str) -> String {
+    format!(
+        ".TH {} 7 \"\" \"ailoop {}\"\n.SH NAME\n{} \\- {}\n",
+        name.to_uppercase(),
+        env!("CARGO_PKG_VERSION"),
+        name,
+        escape(summary)
+    )
+}
+
+/// Escape backslashes, and lines starting with a control character.
+fn escape(line: &str) -> String {
+    let line = line.replace('\\', "\\e");
+    if line.starts_with('.') || line.starts_with('\'') {
+        format!("\\&{}", line)
+    } else {
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_topic_renders() {
+        for topic in TOPICS {
+            assert!(find(&topic.name.to_uppercase()).is_some());
+            let roff = to_roff(topic);
+            assert!(roff.starts_with(&format!(".TH AILOOP-{} 7", topic.name.to_uppercase())));
+            assert_eq!(roff.matches(".nf\n").count(), roff.matches(".fi\n").count());
+            assert!(!to_terminal(topic.text).contains("# "));
+        }
+        assert!(find("nope").is_none());
+        assert_eq!(escape(".hidden \\n"), "\\&.hidden \\en");
+    }
+
+    #[test]
+    fn test_help_topic_is_an_alias_of_guide() {
+        let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+        let (topic, man) = help_alias(&args("help providers")).unwrap();
+        assert_eq!(topic.name, "providers");
+        assert!(!man);
+        let (topic, man) = help_alias(&args("help --man Files")).unwrap();
+        assert_eq!(topic.name, "files");
+        assert!(man);
+
+        assert!(help_alias(&args("help")).is_none());
+        assert!(help_alias(&args("help serve")).is_none());
+        assert!(help_alias(&args("help files protocols")).is_none());
+        assert!(help_alias(&args("guide files")).is_none());
+        assert!(help_alias(&[]).is_none());
+    }
+}
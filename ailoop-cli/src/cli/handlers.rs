@@ -1,12 +1,13 @@
 //! CLI command handlers
 
+use super::media::{MediaDelivery, MediaSource};
+use ailoop_core::models::{InvalidInputPolicy, PromptKind, ResponseType, TimeoutPolicy};
 use anyhow::{Context, Result};
 use std::io::{self, IsTerminal, Write};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
-use std::time::Duration;
 use tokio::signal;
 
 /// Minimal serde-compatible struct for parsing --decision-json input.
@@ -23,6 +24,18 @@ struct DecisionInput {
     timeout_seconds: u32,
 }
 
+/// Print the file attached to a response (`metadata.attachment`).
+fn print_attachment(attachment: &serde_json::Value) {
+    let field = |name: &str| attachment.get(name).and_then(|v| v.as_str()).unwrap_or("");
+    println!(
+        "Attachment: {} ({} bytes)",
+        field("name"),
+        attachment.get("size").and_then(|v| v.as_u64()).unwrap_or(0)
+    );
+    println!("  Path: {}", field("path"));
+    println!("  URL: {}", field("url"));
+}
+
 /// Handle the 'ask' command
 pub async fn handle_ask(
     payload: String,
@@ -30,21 +43,33 @@ pub async fn handle_ask(
     timeout_secs: u32,
     server: String,
     json: bool,
+    expect_file: bool,
+    follow_up: Option<String>,
+    assign: Option<String>,
+    dry_run: bool,
 ) -> Result<()> {
     // Validate channel name
     ailoop_core::channel::validation::validate_channel_name(&channel)
         .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
 
+    let parent_id = follow_up
+        .map(|id| uuid::Uuid::parse_str(id.trim()))
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("Invalid --follow-up id: {}", e))?;
+
     // Parse the decision JSON
     let input: DecisionInput =
         serde_json::from_str(&payload).map_err(|e| anyhow::anyhow!("Invalid --payload: {}", e))?;
 
     // Use CLI --timeout if non-zero, otherwise use the value from the JSON
-    let effective_timeout = if timeout_secs > 0 {
-        timeout_secs
-    } else {
-        input.timeout_seconds
-    };
+    let policy = TimeoutPolicy::new(
+        PromptKind::Question,
+        if timeout_secs > 0 {
+            timeout_secs
+        } else {
+            input.timeout_seconds
+        },
+    );
 
     // Validate decision options before sending
     ailoop_core::models::validate_decision(&input.options, &input.recommendation)
@@ -54,30 +79,46 @@ pub async fn handle_ask(
     let operation_mode = crate::mode::determine_operation_mode(Some(server))
         .map_err(|e| anyhow::anyhow!("Failed to determine operation mode: {}", e))?;
 
-    // If server mode, send message via WebSocket and wait for response
-    if operation_mode.is_server() {
-        let server_url = operation_mode
-            .server_url
-            .ok_or_else(|| anyhow::anyhow!("Server URL is required in server mode"))?;
-
-        if !json {
-            println!("Sending decision to server: {}", input.summary);
-            println!("Waiting for response...");
-        }
-
-        // Send decision and wait for response
-        let response = ailoop_core::client::ask_decision(
-            &server_url,
+    // If server mode (or a dry run), send message via WebSocket and wait for response
+    if operation_mode.is_server() || dry_run {
+        let summary = input.summary.clone();
+        let mut decision = ailoop_core::client::decision(
             &channel,
             input.decision_id,
             input.summary,
             input.context_markdown,
             input.options,
             input.recommendation,
-            effective_timeout,
-        )
-        .await
-        .context("Failed to communicate with server")?;
+            policy.secs(),
+            expect_file,
+            parent_id,
+        );
+        if let Some(assignee) = assign.as_deref() {
+            decision = decision.with_assignee(assignee);
+        }
+        let response = if dry_run {
+            super::dry_run::Target::check(&operation_mode)
+                .await?
+                .show(&decision, json)?;
+            Some(super::dry_run::decision_response(&decision))
+        } else {
+            let server_url = operation_mode
+                .server_url
+                .ok_or_else(|| anyhow::anyhow!("Server URL is required in server mode"))?;
+
+            if !json {
+                println!("Sending decision to server: {}", summary);
+                if let Some(assignee) = decision.assignee() {
+                    println!("Assigned to @{}", assignee);
+                }
+                println!("Waiting for response...");
+            }
+
+            // Send decision and wait for response
+            ailoop_core::client::send_prompt(&server_url, decision, policy.secs())
+                .await
+                .context("Failed to communicate with server")?
+        };
 
         match response {
             Some(response_msg) => {
@@ -97,6 +138,11 @@ pub async fn handle_ask(
                                     "channel": channel,
                                     "timestamp": chrono::Utc::now().to_rfc3339()
                                 });
+                                // The prompt's id, for `--follow-up`
+                                if let Some(prompt_id) = response_msg.correlation_id {
+                                    json_response["message_id"] =
+                                        serde_json::json!(prompt_id.to_string());
+                                }
 
                                 // Add metadata (option_id, label, index) if present
                                 if let Some(metadata) = &response_msg.metadata {
@@ -123,6 +169,22 @@ pub async fn handle_ask(
                                 } else {
                                     println!("Decision resolved: {}", answer_text);
                                 }
+                                if let Some(attachment) = response_msg
+                                    .metadata
+                                    .as_ref()
+                                    .and_then(|m| m.get("attachment"))
+                                {
+                                    print_attachment(attachment);
+                                }
+                            }
+                            if expect_file
+                                && response_msg
+                                    .metadata
+                                    .as_ref()
+                                    .and_then(|m| m.get("attachment"))
+                                    .is_none()
+                            {
+                                eprintln!("Warning: No file was attached to the response");
                             }
                             return Ok(());
                         }
@@ -138,7 +200,7 @@ pub async fn handle_ask(
                             } else {
                                 println!(
                                     "Timeout: No response received within {} seconds",
-                                    effective_timeout
+                                    policy.secs()
                                 );
                             }
                             return Err(anyhow::anyhow!("Decision timed out"));
@@ -222,9 +284,26 @@ pub async fn handle_ask(
         io::stdout().flush().context("Failed to flush stdout")?;
 
         let response = tokio::select! {
-            result = read_user_input() => {
-                result.context("Failed to read user input")?
-            }
+            result = policy.wait(read_user_input()) => match result {
+                Some(result) => result.context("Failed to read user input")?,
+                None => {
+                    if json {
+                        let json_response = serde_json::json!({
+                            "error": "timeout",
+                            "message": "Decision timed out",
+                            "channel": channel,
+                            "timestamp": chrono::Utc::now().to_rfc3339()
+                        });
+                        println!("\n{}", serde_json::to_string_pretty(&json_response)?);
+                    } else {
+                        println!(
+                            "\nTimeout: No response received within {} seconds",
+                            policy.secs()
+                        );
+                    }
+                    return Err(anyhow::anyhow!("Decision timed out"));
+                }
+            },
             _ = signal::ctrl_c() => {
                 if json {
                     let error_response = serde_json::json!({
@@ -268,6 +347,7 @@ async fn stop_terminal_input<T>(
 }
 
 /// Handle the 'authorize' command
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_authorize(
     action: String,
     channel: String,
@@ -275,70 +355,98 @@ pub async fn handle_authorize(
     server: String,
     json: bool,
     default_yes: bool,
+    dry_run: bool,
+    config_arg: &str,
 ) -> Result<()> {
     // Validate channel name
     ailoop_core::channel::validation::validate_channel_name(&channel)
         .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+    let policy =
+        TimeoutPolicy::new(PromptKind::Authorization, timeout_secs).grant_on_expiry(default_yes);
 
     // Determine operation mode
     let operation_mode = crate::mode::determine_operation_mode(Some(server))
         .map_err(|e| anyhow::anyhow!("Failed to determine operation mode: {}", e))?;
 
-    // If server mode, send message via WebSocket and wait for response
-    if operation_mode.is_server() {
-        let server_url = operation_mode
-            .server_url
-            .ok_or_else(|| anyhow::anyhow!("Server URL is required in server mode"))?;
+    // If server mode (or a dry run), send message via WebSocket and wait for response
+    if operation_mode.is_server() || dry_run {
+        let response = if dry_run {
+            let authorization =
+                ailoop_core::client::authorization(&channel, &action, policy.secs());
+            super::dry_run::Target::check(&operation_mode)
+                .await?
+                .show(&authorization, json)?;
+            Some(super::dry_run::authorization_response(
+                &authorization,
+                default_yes,
+            ))
+        } else {
+            let server_url = operation_mode
+                .server_url
+                .ok_or_else(|| anyhow::anyhow!("Server URL is required in server mode"))?;
 
-        if !json {
-            println!("Sending authorization request to server: {}", action);
-            println!("Waiting for response...");
-        }
+            if !json {
+                println!("Sending authorization request to server: {}", action);
+                println!("Waiting for response...");
+            }
 
-        // Send message and wait for response
-        let response = ailoop_core::client::authorize(&server_url, &channel, &action, timeout_secs)
-            .await
-            .context("Failed to communicate with server")?;
+            // Send message and wait for response
+            ailoop_core::client::authorize(&server_url, &channel, &action, policy.secs())
+                .await
+                .context("Failed to communicate with server")?
+        };
 
         match response {
             Some(response_msg) => {
                 // Extract authorization decision from response
                 if let ailoop_core::models::MessageContent::Response {
-                    answer: _,
+                    answer,
                     response_type,
                 } = &response_msg.content
                 {
                     match response_type {
                         ailoop_core::models::ResponseType::AuthorizationApproved => {
                             if json {
-                                let json_response = serde_json::json!({
+                                let mut json_response = serde_json::json!({
                                     "authorized": true,
                                     "action": action,
                                     "channel": channel,
                                     "timestamp": chrono::Utc::now().to_rfc3339()
                                 });
+                                if let Some(reason) = answer {
+                                    json_response["reason"] = serde_json::json!(reason);
+                                }
                                 println!("{}", serde_json::to_string_pretty(&json_response)?);
                             } else {
                                 println!("Authorization GRANTED");
+                                if let Some(reason) = answer {
+                                    println!("Reason: {}", reason);
+                                }
                             }
                             return Ok(());
                         }
                         ailoop_core::models::ResponseType::AuthorizationDenied => {
                             if json {
-                                let json_response = serde_json::json!({
+                                let mut json_response = serde_json::json!({
                                     "authorized": false,
                                     "action": action,
                                     "channel": channel,
                                     "timestamp": chrono::Utc::now().to_rfc3339()
                                 });
+                                if let Some(reason) = answer {
+                                    json_response["reason"] = serde_json::json!(reason);
+                                }
                                 println!("{}", serde_json::to_string_pretty(&json_response)?);
                             } else {
                                 println!("Authorization DENIED");
+                                if let Some(reason) = answer {
+                                    println!("Reason: {}", reason);
+                                }
                             }
                             return Err(anyhow::anyhow!("Authorization denied"));
                         }
                         ailoop_core::models::ResponseType::Timeout => {
-                            let decision = timeout_decision(default_yes);
+                            let decision = timeout_decision(&policy);
                             let authorized = matches!(decision, AuthorizationDecision::Approved);
                             if json {
                                 let json_response = serde_json::json!({
@@ -399,7 +507,7 @@ pub async fn handle_authorize(
                 }
             }
             None => {
-                let decision = timeout_decision(default_yes);
+                let decision = timeout_decision(&policy);
                 let authorized = matches!(decision, AuthorizationDecision::Approved);
                 if json {
                     let json_response = serde_json::json!({
@@ -426,12 +534,15 @@ pub async fn handle_authorize(
 
     // Direct mode: display the authorization request locally
     let is_tty = io::stdin().is_terminal() && io::stdout().is_terminal();
+    let invalid_input = super::provider_handlers::load_config(config_arg)?
+        .authorization
+        .invalid_input;
     println!("Authorization Request");
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     println!("Action: {}", action);
     println!("Channel: {}", channel);
-    if timeout_secs > 0 && !is_tty {
-        println!("Timeout: {} seconds", timeout_secs);
+    if policy.timeout().is_some() && !is_tty {
+        println!("Timeout: {} seconds", policy.secs());
     }
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     let prompt = if default_yes {
@@ -442,8 +553,7 @@ pub async fn handle_authorize(
     print!("{}", prompt);
     io::stdout().flush().context("Failed to flush stdout")?;
 
-    let decision = if timeout_secs > 0 {
-        let timeout_duration = Duration::from_secs(timeout_secs as u64);
+    let decision = if let Some(timeout_duration) = policy.timeout() {
         let cancelled = Arc::new(AtomicBool::new(false));
         let mut input_task = tokio::task::spawn_blocking({
             let cancelled = Arc::clone(&cancelled);
@@ -458,10 +568,10 @@ pub async fn handle_authorize(
             result = &mut input_task => {
                 match result {
                     Ok(Ok(ailoop_core::terminal::countdown::InputResult::Submitted(answer))) => {
-                        parse_authorization_response(&answer, default_yes)?
+                        parse_authorization_response(&answer, default_yes, invalid_input)?
                     }
                     Ok(Ok(ailoop_core::terminal::countdown::InputResult::Timeout)) => {
-                        let decision = timeout_decision(default_yes);
+                        let decision = timeout_decision(&policy);
                         let authorized = matches!(decision, AuthorizationDecision::Approved);
                         if json {
                             let error_response = serde_json::json!({
@@ -527,7 +637,7 @@ pub async fn handle_authorize(
         tokio::select! {
             result = read_user_input() => {
                 let answer = result.context("Failed to read user input")?;
-                parse_authorization_response(&answer, default_yes)?
+                parse_authorization_response(&answer, default_yes, invalid_input)?
             }
             _ = signal::ctrl_c() => {
                 if json {
@@ -580,16 +690,20 @@ enum AuthorizationDecision {
 
 /// Maps a timeout event to an authorization decision based on the configured default.
 /// `--default yes` + timeout => Approved; `--default no` + timeout => Denied.
-fn timeout_decision(default_yes: bool) -> AuthorizationDecision {
-    if default_yes {
-        AuthorizationDecision::Approved
-    } else {
-        AuthorizationDecision::Denied
+fn timeout_decision(policy: &TimeoutPolicy) -> AuthorizationDecision {
+    match policy.expiry_response() {
+        ResponseType::AuthorizationApproved => AuthorizationDecision::Approved,
+        _ => AuthorizationDecision::Denied,
     }
 }
 
-/// Parse user input for authorization response
-fn parse_authorization_response(input: &str, default_yes: bool) -> Result<AuthorizationDecision> {
+/// Parse user input for authorization response. Anything that is not yes or no is asked again
+/// or decided by `invalid_input` (`[authorization] invalid_input`).
+fn parse_authorization_response(
+    input: &str,
+    default_yes: bool,
+    invalid_input: InvalidInputPolicy,
+) -> Result<AuthorizationDecision> {
     let normalized = input.trim().to_lowercase();
 
     match normalized.as_str() {
@@ -600,17 +714,30 @@ fn parse_authorization_response(input: &str, default_yes: bool) -> Result<Author
         }),
         "authorized" | "yes" | "y" | "approve" | "ok" => Ok(AuthorizationDecision::Approved),
         "denied" | "no" | "n" | "deny" | "reject" => Ok(AuthorizationDecision::Denied),
-        _ => {
-            let retry_prompt = if default_yes {
-                "Invalid response. Please enter YES (ENTER) | no: "
-            } else {
-                "Invalid response. Please enter yes | NO (ENTER): "
-            };
-            print!("{}", retry_prompt);
-            io::stdout().flush().context("Failed to flush stdout")?;
-            let retry = read_user_input_sync()?;
-            parse_authorization_response(&retry, default_yes)
-        }
+        _ => match invalid_input {
+            InvalidInputPolicy::Deny => {
+                println!("Invalid response '{}'. Defaulting to DENIED.", input.trim());
+                Ok(AuthorizationDecision::Denied)
+            }
+            InvalidInputPolicy::Approve => {
+                println!(
+                    "Invalid response '{}'. Defaulting to GRANTED.",
+                    input.trim()
+                );
+                Ok(AuthorizationDecision::Approved)
+            }
+            InvalidInputPolicy::Reprompt => {
+                let retry_prompt = if default_yes {
+                    "Invalid response. Please enter YES (ENTER) | no: "
+                } else {
+                    "Invalid response. Please enter yes | NO (ENTER): "
+                };
+                print!("{}", retry_prompt);
+                io::stdout().flush().context("Failed to flush stdout")?;
+                let retry = read_user_input_sync()?;
+                parse_authorization_response(&retry, default_yes, invalid_input)
+            }
+        },
     }
 }
 
@@ -623,16 +750,30 @@ fn read_user_input_sync() -> Result<String> {
     Ok(buffer)
 }
 
-/// Handle the 'say' command
+/// Handle the 'say' command. Several channels, or channel patterns, get one copy each over a
+/// single connection, with per-channel results.
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_say(
     message: String,
-    channel: String,
+    channels: Vec<String>,
     priority: String,
     server: String,
+    outbox: bool,
+    at: Option<String>,
+    delay: Option<String>,
+    attach: Option<String>,
+    json: bool,
+    dry_run: bool,
 ) -> Result<()> {
-    // Validate channel name
-    ailoop_core::channel::validation::validate_channel_name(&channel)
-        .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+    let fan_out = super::fanout::is_fan_out(&channels);
+    let Some(channel) = channels.first().cloned() else {
+        anyhow::bail!("No channel given");
+    };
+    if !fan_out {
+        // Validate channel name
+        ailoop_core::channel::validation::validate_channel_name(&channel)
+            .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+    }
 
     // Normalize priority for display and client usage
     let priority_level = match priority.to_lowercase().as_str() {
@@ -646,8 +787,49 @@ pub async fn handle_say(
         }
     };
 
+    // A local file is uploaded once and every copy links to it
+    let media = attach.as_deref().map(MediaSource::parse).transpose()?;
+
+    if dry_run {
+        return dry_run_say(
+            &message,
+            &channels,
+            priority_level,
+            &server,
+            at,
+            delay,
+            media,
+            json,
+        )
+        .await;
+    }
+    let attachment = match &media {
+        Some(source) => {
+            MediaDelivery::resolve(&server, None)?
+                .prepare(source)
+                .await?
+        }
+        None => None,
+    };
+    let notify = |channel: &str| {
+        let mut notification = ailoop_core::client::notification(channel, &message, priority_level);
+        if let Some(attachment) = &attachment {
+            super::media::attach(&mut notification, attachment);
+        }
+        notification
+    };
+
+    // Scheduled notifications are held by the server until they are due
+    if let Some(at) = super::schedule_handlers::parse_when(at, delay)? {
+        for channel in super::fanout::resolve_channels(&channels, &server).await? {
+            super::schedule_handlers::schedule_message(notify(&channel), at, server.clone())
+                .await?;
+        }
+        return Ok(());
+    }
+
     // Determine operation mode
-    let operation_mode = crate::mode::determine_operation_mode(Some(server))
+    let operation_mode = crate::mode::determine_operation_mode(Some(server.clone()))
         .map_err(|e| anyhow::anyhow!("Failed to determine operation mode: {}", e))?;
 
     if operation_mode.is_server() {
@@ -655,9 +837,42 @@ pub async fn handle_say(
             .server_url
             .ok_or_else(|| anyhow::anyhow!("Server URL is required in server mode"))?;
 
-        ailoop_core::client::say(&server_url, &channel, &message, priority_level)
+        if fan_out || json {
+            let messages = super::fanout::resolve_channels(&channels, &server)
+                .await?
+                .iter()
+                .map(|channel| notify(channel))
+                .collect();
+            let outbox = resolve_outbox(outbox)?;
+            let deliveries = super::fanout::deliver(&server_url, messages, outbox.as_ref()).await?;
+            if !json {
+                println!(
+                    "Notification sent to server [{}]: {}",
+                    priority_level.to_uppercase(),
+                    message
+                );
+            }
+            return super::fanout::report(&deliveries, json);
+        }
+
+        if let Some(outbox) = resolve_outbox(outbox)? {
+            if !outbox.send_or_spool(&server_url, notify(&channel)).await? {
+                println!(
+                    "Server unreachable; notification saved to outbox ({})",
+                    outbox.dir().display()
+                );
+                println!("Channel: {}", channel);
+                return Ok(());
+            }
+        } else {
+            ailoop_core::transport::websocket::send_message_no_response(
+                server_url.clone(),
+                channel.clone(),
+                notify(&channel),
+            )
             .await
             .context("Failed to send notification to server")?;
+        }
 
         println!(
             "Notification sent to server [{}]: {}",
@@ -676,30 +891,244 @@ pub async fn handle_say(
         _ => "[INFO]",
     };
 
+    if channels
+        .iter()
+        .any(|c| ailoop_core::channel::pattern::is_pattern(c))
+    {
+        anyhow::bail!("Channel patterns need a server (--server or AILOOP_SERVER)");
+    }
+    let channels = super::fanout::resolve_channels(&channels, &server).await?;
     println!("{} {}", priority_label, message);
-    println!("Channel: {}", channel);
+    if let Some(source) = &media {
+        println!("Attachment: {}", source);
+    }
+    println!("Channel: {}", channels.join(", "));
+
+    Ok(())
+}
+
+/// `say --dry-run`: show the notification for each channel without sending or uploading.
+#[allow(clippy::too_many_arguments)]
+async fn dry_run_say(
+    message: &str,
+    channels: &[String],
+    priority: &str,
+    server: &str,
+    at: Option<String>,
+    delay: Option<String>,
+    media: Option<MediaSource>,
+    json: bool,
+) -> Result<()> {
+    let when = super::schedule_handlers::parse_when(at, delay)?;
+    let operation_mode = crate::mode::determine_operation_mode(Some(server.to_string()))
+        .map_err(|e| anyhow::anyhow!("Failed to determine operation mode: {}", e))?;
+    let target = super::dry_run::Target::check(&operation_mode).await?;
+    let mut notifications = Vec::new();
+    for channel in super::fanout::resolve_channels(channels, server).await? {
+        let notification = ailoop_core::client::notification(&channel, message, priority);
+        target.show(&notification, json)?;
+        notifications.push(notification);
+    }
+    if json {
+        let mut report = serde_json::json!({
+            "dry_run": true,
+            "messages": notifications,
+        });
+        if let Some(when) = when {
+            report["scheduled_for"] = serde_json::json!(when.to_rfc3339());
+        }
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    if let Some(source) = &media {
+        println!("Attachment: {} (not uploaded)", source);
+    }
+    if let Some(when) = when {
+        println!("Would be scheduled for {}", when.to_rfc3339());
+    }
+    println!("Dry run: nothing was sent");
+    Ok(())
+}
+
+/// Handle the 'status' command: a transient "working" pulse, not kept in history
+pub async fn handle_status(text: String, channel: String, server: String) -> Result<()> {
+    ailoop_core::channel::validation::validate_channel_name(&channel)
+        .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+
+    let operation_mode = crate::mode::determine_operation_mode(Some(server))
+        .map_err(|e| anyhow::anyhow!("Failed to determine operation mode: {}", e))?;
+
+    if operation_mode.is_server() {
+        let server_url = operation_mode
+            .server_url
+            .ok_or_else(|| anyhow::anyhow!("Server URL is required in server mode"))?;
+        ailoop_core::client::status(&server_url, &channel, &text)
+            .await
+            .context("Failed to send status to server")?;
+        return Ok(());
+    }
 
+    // Direct mode: nobody else is watching, so just echo it
+    println!("⏳ [{}] {}", channel, text);
     Ok(())
 }
 
+/// Outbox for undeliverable messages when `--outbox` is passed or `[outbox] enabled` is set.
+fn resolve_outbox(flag: bool) -> Result<Option<ailoop_core::transport::outbox::Outbox>> {
+    use ailoop_core::models::Configuration;
+
+    let config = Configuration::default_config_path()
+        .ok()
+        .and_then(|path| Configuration::load_from_file(&path).ok())
+        .unwrap_or_default();
+    if !flag && !config.outbox.enabled {
+        return Ok(None);
+    }
+    ailoop_core::transport::outbox::Outbox::from_config(&config.outbox).map(Some)
+}
+
 /// Handle the 'serve' command
-pub async fn handle_serve(host: String, port: u16, channel: String, web: bool) -> Result<()> {
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_serve(
+    host: String,
+    port: u16,
+    channel: String,
+    web: bool,
+    ingest_dir: Option<String>,
+    stdio: bool,
+    listen: Option<String>,
+    strict: bool,
+    force: bool,
+    echo: bool,
+) -> Result<()> {
     use ailoop_core::models::Configuration;
+    use ailoop_server::server::listen::{serve_endpoint, ListenEndpoint};
     use ailoop_server::{AiloopAppState, ServeConfig};
-    use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+    use std::{path::PathBuf, sync::Arc};
     use tokio_util::sync::CancellationToken;
 
     // Validate channel name
     ailoop_core::channel::validation::validate_channel_name(&channel)
         .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
 
+    if let Some(dir) = &ingest_dir {
+        if !PathBuf::from(dir).is_dir() {
+            return Err(anyhow::anyhow!("Ingest directory does not exist: {}", dir));
+        }
+    }
+
     // Load config from default path (for provider settings)
     let config_path =
         Configuration::default_config_path().unwrap_or_else(|_| PathBuf::from("config.toml"));
-    let provider_config = Configuration::load_from_file(&config_path).unwrap_or_default();
+    let provider_config = Configuration::load_from_file(&config_path).unwrap_or_else(|e| {
+        eprintln!(
+            "Warning: ignoring {} ({}); using defaults",
+            config_path.display(),
+            e
+        );
+        Configuration::default()
+    });
+
+    let quota = ailoop_server::server::usage::UsageQuota::from(&provider_config.quotas);
+    let security = &provider_config.security;
+    let ip_filter = if security.allowed_cidrs.is_empty() && security.denied_cidrs.is_empty() {
+        None
+    } else {
+        Some(
+            ailoop_server::IpFilterConfig::from_cidrs(
+                &security.allowed_cidrs,
+                &security.denied_cidrs,
+            )
+            .map_err(|e| anyhow::anyhow!("{}", e))?,
+        )
+    };
 
-    let state =
-        Arc::new(AiloopAppState::new(channel.clone()).with_provider_config(provider_config));
+    let endpoint: ListenEndpoint = match &listen {
+        Some(listen) => listen.parse()?,
+        None => format!("{}:{}", host, port).parse()?,
+    };
+    if ip_filter.is_some() && !stdio && !endpoint.has_peer_addr() {
+        return Err(anyhow::anyhow!(
+            "IP allow/deny lists need a TCP endpoint; {} has no peer address",
+            endpoint
+        ));
+    }
+    // Taken before any store is opened; the lock is released when the server returns.
+    let _data_lock = match ailoop_core::paths::state_dir() {
+        Some(dir) => {
+            let holder = if stdio {
+                "stdio".to_string()
+            } else {
+                endpoint.to_string()
+            };
+            Some(ailoop_server::server::lock::DataDirLock::acquire(
+                &dir, &holder, force,
+            )?)
+        }
+        None => None,
+    };
+    let signer =
+        ailoop_core::services::signing::ResponseSigner::from_config(&provider_config.signing)
+            .map_err(|e| anyhow::anyhow!("Response signing: {}", e))?;
+    let event_log =
+        ailoop_server::server::events::EventLog::from_config(&provider_config.event_log)?;
+    let hooks = ailoop_server::server::hooks::MessageHooks::from_config(&provider_config.hooks);
+    let hooks_enabled = hooks.is_enabled();
+    let scheduler =
+        ailoop_server::server::scheduler::Scheduler::from_config(&provider_config.scheduler)?;
+    let loop_detection = provider_config.loop_detection.clone();
+    let echo_config = echo.then(|| provider_config.echo.clone());
+    let mut state = AiloopAppState::new(channel.clone())
+        .with_loop_detection(loop_detection)
+        .with_provider_config(provider_config)
+        .with_usage_quota(quota)
+        .with_hooks(hooks)
+        .with_scheduler(scheduler)
+        .with_attachment_dir(ailoop_core::paths::cache_path("attachments"));
+    // In stdio mode stdout carries the protocol, so status lines go to stderr.
+    let status = |line: String| {
+        if stdio {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    };
+    if let Some(signer) = signer {
+        status(format!(
+            "Signing responses with operator key {}",
+            signer.operator_public_key()
+        ));
+        state = state.with_response_signer(signer);
+    }
+    if hooks_enabled {
+        status("Running message hooks from [hooks] in config".to_string());
+    }
+    if let Some(event_log) = event_log {
+        status(format!("Event log: {}", event_log.path().display()));
+        ailoop_server::server::events::install(event_log);
+    }
+    if ailoop_server::server::telemetry::is_enabled() {
+        status("Exporting OpenTelemetry traces and metrics over OTLP".to_string());
+    }
+    if strict {
+        status("Strict mode: rejecting messages that fail schema validation".to_string());
+        state = state.with_strict_validation();
+    }
+    if let Some(echo_config) = echo_config {
+        status("Echo mode: answering every prompt with canned answers from [echo]".to_string());
+        state = state.with_echo(echo_config);
+    }
+    // Who is away, what they watch and open incidents survive restarts, like scheduled messages.
+    if let Some(file) = ailoop_core::paths::state_path("away.json") {
+        state.broadcast_manager.away().open(file)?;
+    }
+    if let Some(file) = ailoop_core::paths::state_path("subscriptions.json") {
+        state.broadcast_manager.subscriptions().open(file)?;
+    }
+    if let Some(file) = ailoop_core::paths::state_path("incidents.json") {
+        state.message_history.incidents().open(file)?;
+    }
+    let state = Arc::new(state);
 
     let serve_config = ServeConfig {
         host: host.clone(),
@@ -709,65 +1138,97 @@ pub async fn handle_serve(host: String, port: u16, channel: String, web: bool) -
         web,
         auth: None,
         cors: None,
+        ip_filter,
     };
 
-    let built_router = ailoop_server::router(Arc::clone(&state), &serve_config)
-        .map_err(|e| anyhow::anyhow!("{}", e))?;
-
-    let address: SocketAddr = format!("{}:{}", host, port)
-        .parse()
-        .map_err(|e| anyhow::anyhow!("Invalid server address: {}", e))?;
-
-    println!("ailoop server starting on {}", address);
-    println!("Default channel: {}", channel);
-    println!("Press Ctrl+C to stop the server");
-    if web {
-        println!("Web UI available at http://{}:{}/", host, port);
-    }
-
     let token = CancellationToken::new();
     let token_for_shutdown = token.clone();
 
     let task_handle =
         ailoop_server::spawn_background_tasks(Arc::clone(&state), &serve_config, token.clone());
+    let ingest_handle = match ingest_dir {
+        Some(dir) => {
+            use ailoop_server::server::ingest::{spawn_ingest, IngestConfig};
+            let dir = PathBuf::from(dir);
+            status(format!("Ingesting message files from {}", dir.display()));
+            let config = IngestConfig::new(dir);
+            let ingest_state = Arc::clone(&state);
+            let ingest_token = token.clone();
+            Some(
+                state
+                    .supervisor
+                    .supervise("ingest", token.clone(), move || {
+                        spawn_ingest(
+                            Arc::clone(&ingest_state),
+                            config.clone(),
+                            ingest_token.clone(),
+                        )
+                    }),
+            )
+        }
+        None => None,
+    };
 
-    let listener = tokio::net::TcpListener::bind(address)
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to bind to {}: {}", address, e))?;
+    if stdio {
+        status(format!(
+            "ailoop serving on stdio (default channel: {})",
+            channel
+        ));
+        // Runs until stdin closes or Ctrl+C.
+        tokio::select! {
+            _ = ailoop_server::server::stdio::serve_stdio(Arc::clone(&state)) => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    } else {
+        let built_router = ailoop_server::router(Arc::clone(&state), &serve_config)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        println!("ailoop server starting on {}", endpoint);
+        println!("Default channel: {}", channel);
+        println!("Press Ctrl+C to stop the server");
+        if web {
+            if let ListenEndpoint::Tcp(address) = &endpoint {
+                println!("Web UI available at http://{}/", address);
+            }
+        }
 
-    axum::serve(listener, built_router)
-        .with_graceful_shutdown(async move {
+        serve_endpoint(built_router, &endpoint, async move {
             let _ = tokio::signal::ctrl_c().await;
             token_for_shutdown.cancel();
         })
         .await?;
+    }
 
     token.cancel();
     let _ = task_handle.await;
+    if let Some(handle) = ingest_handle {
+        let _ = handle.await;
+    }
 
     Ok(())
 }
 
+/// Expand `~/` in a `--config-file` value.
+fn resolve_config_file(config_file: String) -> Result<std::path::PathBuf> {
+    use std::path::PathBuf;
+
+    if let Some(rest) = config_file.strip_prefix("~/") {
+        let home = std::env::var("HOME")
+            .map_err(|_| anyhow::anyhow!("HOME environment variable not set"))?;
+        Ok(PathBuf::from(home).join(rest))
+    } else {
+        Ok(PathBuf::from(config_file))
+    }
+}
+
 /// Handle the 'config' command
 pub async fn handle_config_init(config_file: String) -> Result<()> {
     use ailoop_core::models::{Configuration, LogLevel};
-    use std::path::PathBuf;
 
     println!("Initializing ailoop configuration");
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
-    // Resolve config file path
-    let config_path = if config_file.starts_with("~/") {
-        let home = std::env::var("HOME")
-            .map_err(|_| anyhow::anyhow!("HOME environment variable not set"))?;
-        PathBuf::from(config_file.replacen("~/", &format!("{}/", home), 1))
-    } else if config_file == "~/.config/ailoop/config.toml" {
-        // Use XDG default
-        Configuration::default_config_path()
-            .map_err(|e| anyhow::anyhow!("Failed to get default config path: {}", e))?
-    } else {
-        PathBuf::from(config_file)
-    };
+    let config_path = resolve_config_file(config_file)?;
 
     println!("Config file: {}", config_path.display());
 
@@ -945,55 +1406,72 @@ pub async fn handle_config_init(config_file: String) -> Result<()> {
 }
 
 /// Handle the 'image' command
-pub async fn handle_image(image_path: String, channel: String, _server: String) -> Result<()> {
+pub async fn handle_image(
+    image_path: String,
+    channel: String,
+    server: String,
+    open: bool,
+    token: Option<String>,
+    json: bool,
+) -> Result<()> {
     // Validate channel name
     ailoop_core::channel::validation::validate_channel_name(&channel)
         .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+    let source = MediaSource::parse(&image_path)
+        .map_err(|_| anyhow::anyhow!("Image file not found: {}", image_path))?;
+    let delivery = MediaDelivery::resolve(&server, token)?;
 
-    // Check if it's a URL or file path
-    let is_url = image_path.starts_with("http://") || image_path.starts_with("https://");
+    let Some(server_url) = delivery.server_url().map(str::to_string) else {
+        return delivery.show_locally(&channel, &source, open);
+    };
 
-    if is_url {
-        println!("[{}] Image URL: {}", channel, image_path);
-        println!("Please open this URL in your browser to view the image:");
-        println!("   {}", image_path);
-    } else {
-        // Check if file exists
-        let path = std::path::Path::new(&image_path);
-        if path.exists() {
-            println!("[{}] Image file: {}", channel, image_path);
-            println!("Image location: {}", path.canonicalize()?.display());
-
-            // Try to determine image type
-            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                let img_type = match ext.to_lowercase().as_str() {
-                    "jpg" | "jpeg" => "JPEG",
-                    "png" => "PNG",
-                    "gif" => "GIF",
-                    "webp" => "WebP",
-                    "svg" => "SVG",
-                    _ => "Unknown",
-                };
-                println!("Image type: {}", img_type);
-            }
+    let attachment = delivery.prepare(&source).await?.unwrap_or_default();
+    let mut notification =
+        ailoop_core::client::notification(&channel, &format!("Image: {}", source.name()), "normal");
+    super::media::attach(&mut notification, &attachment);
+    let url = attachment["url"].as_str().unwrap_or_default().to_string();
+    ailoop_core::transport::websocket::send_message_no_response(
+        server_url,
+        channel.clone(),
+        notification,
+    )
+    .await
+    .context("Failed to send image to server")?;
 
-            println!("Please open this file in an image viewer to view it.");
-        } else {
-            return Err(anyhow::anyhow!("Image file not found: {}", image_path));
-        }
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({ "channel": channel, "attachment": attachment })
+        );
+    } else {
+        println!("[{}] Image sent to server: {}", channel, source.name());
+        println!("URL: {}", url);
     }
-
-    // In server mode, this would be sent to connected humans
-    // In direct mode, we just display the information
-
     Ok(())
 }
 
 /// Handle the 'navigate' command
-pub async fn handle_navigate(url: String, channel: String, server: String) -> Result<()> {
-    // Validate channel name
-    ailoop_core::channel::validation::validate_channel_name(&channel)
-        .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+pub async fn handle_navigate(
+    url: String,
+    channels: Vec<String>,
+    timeout_secs: u32,
+    wait: bool,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let fan_out = super::fanout::is_fan_out(&channels);
+    let Some(channel) = channels.first().cloned() else {
+        anyhow::bail!("No channel given");
+    };
+    if wait && fan_out {
+        anyhow::bail!("--wait needs a single channel");
+    }
+    let policy = TimeoutPolicy::new(PromptKind::Navigation, timeout_secs);
+    if !fan_out {
+        // Validate channel name
+        ailoop_core::channel::validation::validate_channel_name(&channel)
+            .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+    }
 
     // Validate URL format
     if !url.starts_with("http://") && !url.starts_with("https://") {
@@ -1003,7 +1481,7 @@ pub async fn handle_navigate(url: String, channel: String, server: String) -> Re
     }
 
     // Determine operation mode
-    let operation_mode = crate::mode::determine_operation_mode(Some(server))
+    let operation_mode = crate::mode::determine_operation_mode(Some(server.clone()))
         .map_err(|e| anyhow::anyhow!("Failed to determine operation mode: {}", e))?;
 
     // If server mode, send message via WebSocket
@@ -1012,8 +1490,41 @@ pub async fn handle_navigate(url: String, channel: String, server: String) -> Re
             .server_url
             .ok_or_else(|| anyhow::anyhow!("Server URL is required in server mode"))?;
 
+        if wait {
+            if !json {
+                println!("Sending navigation request to server: {}", url);
+                println!("Waiting for response...");
+            }
+            let response =
+                ailoop_core::client::navigate_and_wait(&server_url, &channel, &url, policy.secs())
+                    .await
+                    .context("Failed to communicate with server")?;
+            let outcome = match response.map(|m| m.content) {
+                Some(ailoop_core::models::MessageContent::Response { response_type, .. }) => {
+                    response_type
+                }
+                Some(_) => anyhow::bail!("Server sent unexpected message type"),
+                // The server never answered: expired, as far as the agent can tell.
+                None => policy.expiry_response(),
+            };
+            return report_navigation(&url, &channel, &outcome, json);
+        }
+
+        if fan_out || json {
+            let messages = super::fanout::resolve_channels(&channels, &server)
+                .await?
+                .iter()
+                .map(|channel| ailoop_core::client::navigation(channel, &url, policy.secs()))
+                .collect();
+            let deliveries = super::fanout::deliver(&server_url, messages, None).await?;
+            if !json {
+                println!("Navigation request sent to server: {}", url);
+            }
+            return super::fanout::report(&deliveries, json);
+        }
+
         // Send message to server (no response expected for navigate)
-        ailoop_core::client::navigate(&server_url, &channel, &url)
+        ailoop_core::client::navigate(&server_url, &channel, &url, policy.secs())
             .await
             .context("Failed to send navigate message to server")?;
 
@@ -1022,30 +1533,82 @@ pub async fn handle_navigate(url: String, channel: String, server: String) -> Re
     }
 
     // Direct mode: display the navigation suggestion
-    println!("[{}] Navigation suggestion", channel);
+    if channels
+        .iter()
+        .any(|c| ailoop_core::channel::pattern::is_pattern(c))
+    {
+        anyhow::bail!("Channel patterns need a server (--server or AILOOP_SERVER)");
+    }
+    let channels = super::fanout::resolve_channels(&channels, &server).await?;
+    println!("[{}] Navigation suggestion", channels.join(", "));
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     println!("URL: {}", url);
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+    if wait {
+        print!("Open in browser? yes | NO (ENTER): ");
+        io::stdout().flush().context("Failed to flush stdout")?;
+        let outcome = tokio::select! {
+            result = policy.wait(read_user_input()) => match result {
+                Some(answer) => {
+                    let answer = answer.context("Failed to read user input")?;
+                    if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                        super::media::open_locally(&url);
+                        ResponseType::AuthorizationApproved
+                    } else {
+                        ResponseType::AuthorizationDenied
+                    }
+                }
+                None => {
+                    println!();
+                    policy.expiry_response()
+                }
+            },
+            _ = signal::ctrl_c() => {
+                println!();
+                ResponseType::Cancelled
+            }
+        };
+        return report_navigation(&url, &channel, &outcome, json);
+    }
+
     println!("Please navigate to this URL in your browser:");
     println!("   {}", url);
+    super::media::open_locally(&url);
 
-    // Try to open URL if possible (platform-specific)
-    #[cfg(target_os = "linux")]
-    {
-        let _ = std::process::Command::new("xdg-open").arg(&url).spawn();
-    }
-    #[cfg(target_os = "windows")]
-    {
-        let _ = std::process::Command::new("cmd")
-            .args(["/C", "start", "", &url])
-            .spawn();
+    Ok(())
+}
+
+/// Print what became of a navigation waited for with `--wait`; fails unless it was opened.
+fn report_navigation(url: &str, channel: &str, outcome: &ResponseType, json: bool) -> Result<()> {
+    let opened = matches!(outcome, ResponseType::AuthorizationApproved);
+    let reason = match outcome {
+        ResponseType::AuthorizationApproved => None,
+        ResponseType::Timeout => Some("timeout"),
+        ResponseType::Cancelled => Some("cancelled"),
+        _ => Some("declined"),
+    };
+    if json {
+        let mut json_response = serde_json::json!({
+            "opened": opened,
+            "url": url,
+            "channel": channel,
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        });
+        if let Some(reason) = reason {
+            json_response["reason"] = serde_json::json!(reason);
+        }
+        println!("{}", serde_json::to_string_pretty(&json_response)?);
+    } else if opened {
+        println!("Navigation opened: {}", url);
+    } else {
+        println!("Navigation not opened ({})", reason.unwrap_or("declined"));
     }
-    #[cfg(target_os = "macos")]
-    {
-        let _ = std::process::Command::new("open").arg(&url).spawn();
+    if opened {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Navigation not opened"))
     }
-
-    Ok(())
 }
 
 /// Handle the 'forward' command
@@ -1059,8 +1622,16 @@ pub async fn handle_forward(
     output: Option<String>,
     client_id: Option<String>,
     input: Option<String>,
+    coalesce_flag: Option<String>,
+    quarantine: Option<String>,
+    multiline: bool,
+    since: Option<String>,
+    until: Option<String>,
+    dry_run: bool,
+    resume: bool,
+    outbox: bool,
 ) -> Result<()> {
-    use crate::cli::forward::{execute_forward, ForwardConfig};
+    use crate::cli::forward::{execute_forward, parse_time_bound, ForwardConfig};
     use crate::parser::InputFormat;
     use ailoop_core::transport::factory::TransportType;
     use std::path::PathBuf;
@@ -1091,7 +1662,7 @@ pub async fn handle_forward(
             TransportType::WebSocket
         }
         "file" => {
-            if output.is_none() {
+            if output.is_none() && !dry_run {
                 return Err(anyhow::anyhow!("File transport requires --output option"));
             }
             TransportType::File
@@ -1104,6 +1675,39 @@ pub async fn handle_forward(
         }
     };
 
+    let outbox = match transport_type {
+        TransportType::WebSocket if !dry_run => resolve_outbox(outbox)?,
+        _ => None,
+    };
+    let app_config = ailoop_core::models::Configuration::default_config_path()
+        .ok()
+        .and_then(|path| ailoop_core::models::Configuration::load_from_file(&path).ok())
+        .unwrap_or_default();
+    let mut guardrail = app_config.guardrail;
+    guardrail.timeout_seconds = guardrail.timeout_seconds.or(app_config.timeout_seconds);
+
+    // `--coalesce SECS` overrides the `[coalesce]` rules for this channel.
+    let mut coalesce = app_config.coalesce.rules_for(&channel);
+    if let Some(secs) = coalesce_flag {
+        let secs: u64 = secs.parse().map_err(|_| {
+            anyhow::anyhow!("Invalid --coalesce value '{}': expected seconds", secs)
+        })?;
+        coalesce.enabled = Some(secs > 0);
+        coalesce.interval_seconds = Some(secs);
+    }
+
+    let since = since
+        .map(|value| parse_time_bound(&value).context("Invalid --since value"))
+        .transpose()?;
+    let until = until
+        .map(|value| parse_time_bound(&value).context("Invalid --until value"))
+        .transpose()?;
+
+    let checkpoint_dir = match resume {
+        true => Some(crate::cli::checkpoint::Checkpoint::default_dir()?),
+        false => None,
+    };
+
     // Build forward config
     let config = ForwardConfig {
         channel,
@@ -1114,6 +1718,16 @@ pub async fn handle_forward(
         file_path: output.map(PathBuf::from),
         client_id,
         input_file: input.map(PathBuf::from),
+        outbox,
+        converter: app_config.converter,
+        guardrail,
+        coalesce,
+        quarantine: quarantine.map(PathBuf::from),
+        multiline,
+        since,
+        until,
+        dry_run,
+        checkpoint_dir,
     };
 
     // Execute forward command
@@ -1123,18 +1737,8 @@ pub async fn handle_forward(
 /// Handle `ailoop config` (no flags) — display current config values.
 pub async fn handle_config_show(config_file: String) -> Result<()> {
     use ailoop_core::models::Configuration;
-    use std::path::PathBuf;
 
-    let config_path = if config_file.starts_with("~/") {
-        let home = std::env::var("HOME")
-            .map_err(|_| anyhow::anyhow!("HOME environment variable not set"))?;
-        PathBuf::from(config_file.replacen("~/", &format!("{}/", home), 1))
-    } else if config_file == "~/.config/ailoop/config.toml" {
-        Configuration::default_config_path()
-            .map_err(|e| anyhow::anyhow!("Failed to get default config path: {}", e))?
-    } else {
-        PathBuf::from(config_file)
-    };
+    let config_path = resolve_config_file(config_file)?;
 
     if !config_path.exists() {
         println!("No config file found at {}", config_path.display());
@@ -1146,6 +1750,7 @@ pub async fn handle_config_show(config_file: String) -> Result<()> {
         .map_err(|e| anyhow::anyhow!("Failed to load config: {}", e))?;
 
     println!("Config: {}", config_path.display());
+    println!("  version:          {}", config.version);
     println!("  server_host:      {}", config.server_host);
     println!("  server_port:      {}", config.server_port);
     println!("  default_channel:  {}", config.default_channel);
@@ -1153,10 +1758,91 @@ pub async fn handle_config_show(config_file: String) -> Result<()> {
     Ok(())
 }
 
+/// Handle `ailoop config migrate` — bring an older config file up to the current schema.
+pub async fn handle_config_migrate(config_file: String, dry_run: bool) -> Result<()> {
+    use ailoop_core::models::{migrate_config, Configuration};
+
+    let config_path = resolve_config_file(config_file)?;
+    if !config_path.exists() {
+        println!("No config file found at {}", config_path.display());
+        return Ok(());
+    }
+    let content = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read {}", config_path.display()))?;
+    let migration = migrate_config(&content)
+        .map_err(|e| anyhow::anyhow!("{}: {}", config_path.display(), e))?;
+    let Some(migration) = migration else {
+        println!(
+            "{} is already at version {}",
+            config_path.display(),
+            ailoop_core::models::CONFIG_VERSION
+        );
+        return Ok(());
+    };
+
+    println!(
+        "{}: version {} -> {}",
+        config_path.display(),
+        migration.from,
+        migration.to
+    );
+    for line in line_diff(&migration.original, &migration.migrated) {
+        println!("{}", line);
+    }
+    if dry_run {
+        println!("Dry run: nothing written");
+        return Ok(());
+    }
+    let backup = Configuration::write_migration(&config_path, &migration)
+        .map_err(|e| anyhow::anyhow!("Failed to write migrated config: {}", e))?;
+    println!("Migrated; the old file is at {}", backup.display());
+    Ok(())
+}
+
+/// Line diff of `old` and `new`: removed lines prefixed `-`, added ones `+`, unchanged ones
+/// indented.
+fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // Longest common subsequence lengths of the suffixes.
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(format!("- {}", old[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_line_diff() {
+        let diff = line_diff("a = 1\nb = 2\n", "a = 1\nb = 3\nc = 4\n");
+        assert_eq!(diff, ["  a = 1", "- b = 2", "+ b = 3", "+ c = 4"]);
+    }
+
     #[tokio::test]
     async fn test_handle_ask_with_empty_server() {
         // Test that handle_ask falls back to direct mode when server is not provided.
@@ -1184,6 +1870,10 @@ mod tests {
             10,
             "http://nonexistent.invalid:12345".to_string(),
             false,
+            false,
+            None,
+            None,
+            false,
         )
         .await;
 
@@ -1200,9 +1890,56 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_dry_run_answers_without_asking_anyone() {
+        let payload =
+            r#"{"decision_id":"d","summary":"Region?","options":[{"id":"eu","label":"EU"}]}"#;
+        let result = handle_ask(
+            payload.to_string(),
+            "test-channel".to_string(),
+            10,
+            String::new(),
+            true,
+            false,
+            None,
+            None,
+            true,
+        )
+        .await;
+        assert!(result.is_ok());
+
+        // The canned authorization follows --default, so scripts can rehearse a denial.
+        let denied = handle_authorize(
+            "deploy".to_string(),
+            "test-channel".to_string(),
+            10,
+            String::new(),
+            true,
+            false,
+            true,
+            "~/.config/ailoop/config.toml",
+        )
+        .await;
+        assert!(denied.is_err());
+
+        // A dry run still fails when the server cannot be reached.
+        let unreachable = handle_authorize(
+            "deploy".to_string(),
+            "test-channel".to_string(),
+            10,
+            "http://nonexistent.invalid:12345".to_string(),
+            true,
+            true,
+            true,
+            "~/.config/ailoop/config.toml",
+        )
+        .await;
+        assert!(unreachable.is_err());
+    }
+
     #[test]
     fn test_parse_authorization_response_empty_default_yes() {
-        let result = parse_authorization_response("", true).unwrap();
+        let result = parse_authorization_response("", true, InvalidInputPolicy::Deny).unwrap();
         assert!(
             matches!(result, AuthorizationDecision::Approved),
             "Empty input with default_yes=true should return Approved"
@@ -1211,7 +1948,7 @@ mod tests {
 
     #[test]
     fn test_parse_authorization_response_empty_default_no() {
-        let result = parse_authorization_response("", false).unwrap();
+        let result = parse_authorization_response("", false, InvalidInputPolicy::Deny).unwrap();
         assert!(
             matches!(result, AuthorizationDecision::Denied),
             "Empty input with default_yes=false should return Denied"
@@ -1220,7 +1957,7 @@ mod tests {
 
     #[test]
     fn test_parse_authorization_response_explicit_yes_overrides_default_no() {
-        let result = parse_authorization_response("yes", false).unwrap();
+        let result = parse_authorization_response("yes", false, InvalidInputPolicy::Deny).unwrap();
         assert!(
             matches!(result, AuthorizationDecision::Approved),
             "Explicit 'yes' should override default_no"
@@ -1229,7 +1966,7 @@ mod tests {
 
     #[test]
     fn test_parse_authorization_response_explicit_no_overrides_default_yes() {
-        let result = parse_authorization_response("no", true).unwrap();
+        let result = parse_authorization_response("no", true, InvalidInputPolicy::Deny).unwrap();
         assert!(
             matches!(result, AuthorizationDecision::Denied),
             "Explicit 'no' should override default_yes"
@@ -1241,7 +1978,9 @@ mod tests {
         let approve_keywords = vec!["authorized", "yes", "y", "approve", "ok"];
         for keyword in approve_keywords {
             for default in [true, false] {
-                let result = parse_authorization_response(keyword, default).unwrap();
+                let result =
+                    parse_authorization_response(keyword, default, InvalidInputPolicy::Deny)
+                        .unwrap();
                 assert!(
                     matches!(result, AuthorizationDecision::Approved),
                     "'{}' with default_yes={} should return Approved",
@@ -1257,7 +1996,9 @@ mod tests {
         let deny_keywords = vec!["denied", "no", "n", "deny", "reject"];
         for keyword in deny_keywords {
             for default in [true, false] {
-                let result = parse_authorization_response(keyword, default).unwrap();
+                let result =
+                    parse_authorization_response(keyword, default, InvalidInputPolicy::Deny)
+                        .unwrap();
                 assert!(
                     matches!(result, AuthorizationDecision::Denied),
                     "'{}' with default_yes={} should return Denied",
@@ -1270,7 +2011,7 @@ mod tests {
 
     #[test]
     fn test_parse_authorization_response_whitespace_empty_default_yes() {
-        let result = parse_authorization_response("   ", true).unwrap();
+        let result = parse_authorization_response("   ", true, InvalidInputPolicy::Deny).unwrap();
         assert!(
             matches!(result, AuthorizationDecision::Approved),
             "Whitespace-only input with default_yes=true should return Approved"
@@ -1279,16 +2020,26 @@ mod tests {
 
     #[test]
     fn test_parse_authorization_response_whitespace_empty_default_no() {
-        let result = parse_authorization_response("   ", false).unwrap();
+        let result = parse_authorization_response("   ", false, InvalidInputPolicy::Deny).unwrap();
         assert!(
             matches!(result, AuthorizationDecision::Denied),
             "Whitespace-only input with default_yes=false should return Denied"
         );
     }
 
+    #[test]
+    fn test_parse_authorization_response_invalid_input_follows_policy() {
+        let denied = parse_authorization_response("maybe", true, InvalidInputPolicy::Deny).unwrap();
+        assert!(matches!(denied, AuthorizationDecision::Denied));
+        let approved =
+            parse_authorization_response("maybe", false, InvalidInputPolicy::Approve).unwrap();
+        assert!(matches!(approved, AuthorizationDecision::Approved));
+    }
+
     #[test]
     fn test_timeout_decision_default_yes_returns_approved() {
-        let decision = timeout_decision(true);
+        let policy = TimeoutPolicy::new(PromptKind::Authorization, 60).grant_on_expiry(true);
+        let decision = timeout_decision(&policy);
         assert!(
             matches!(decision, AuthorizationDecision::Approved),
             "timeout_decision(true) should return Approved"
@@ -1297,10 +2048,21 @@ mod tests {
 
     #[test]
     fn test_timeout_decision_default_no_returns_denied() {
-        let decision = timeout_decision(false);
+        let decision = timeout_decision(&TimeoutPolicy::new(PromptKind::Authorization, 60));
         assert!(
             matches!(decision, AuthorizationDecision::Denied),
             "timeout_decision(false) should return Denied"
         );
     }
+
+    #[test]
+    fn test_navigation_is_opened_only_when_approved() {
+        let url = "https://example.com";
+        assert!(
+            report_navigation(url, "public", &ResponseType::AuthorizationApproved, true).is_ok()
+        );
+        let expired = TimeoutPolicy::new(PromptKind::Navigation, 5).expiry_response();
+        assert!(report_navigation(url, "public", &expired, true).is_err());
+        assert!(report_navigation(url, "public", &ResponseType::Cancelled, true).is_err());
+    }
 }
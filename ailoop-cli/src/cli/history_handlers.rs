@@ -0,0 +1,95 @@
+//! Handlers for the `ailoop history` subcommands.
+
+use super::schedule_handlers::{parse_at, parse_delay};
+use super::task_handlers::resolve_server_url;
+use ailoop_core::models::{PurgeReport, PurgeRequest};
+use ailoop_core::ChannelClient;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+/// Remove messages older than `before` from the server's history of `channel` (`*` for every
+/// channel), or with `dry_run` only report what would be removed.
+pub async fn handle_history_purge(
+    channel: String,
+    before: String,
+    dry_run: bool,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let channel = if channel == "*" {
+        None
+    } else {
+        ailoop_core::channel::validation::validate_channel_name(&channel)
+            .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+        Some(channel)
+    };
+    let request = PurgeRequest {
+        channel,
+        before: parse_before(&before)?,
+        dry_run,
+    };
+    let server_url = resolve_server_url(server)?;
+    let report = ChannelClient::new(&server_url)
+        .purge_history(&request)
+        .await
+        .context("Failed to purge history")?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report, &request);
+    }
+    Ok(())
+}
+
+/// `--before`: an age such as `30d` or `12h`, or a time as for `say --at`.
+fn parse_before(before: &str) -> Result<DateTime<Utc>> {
+    if before.contains(['-', ':']) {
+        parse_at(before)
+    } else {
+        let age = parse_delay(before).with_context(|| {
+            format!(
+                "Invalid --before '{}': use e.g. 30d or 2025-07-01T00:00",
+                before
+            )
+        })?;
+        Ok(Utc::now() - age)
+    }
+}
+
+fn print_report(report: &PurgeReport, request: &PurgeRequest) {
+    let verb = if report.dry_run {
+        "Would purge"
+    } else {
+        "Purged"
+    };
+    println!(
+        "{} {} message(s) and {} session event(s) older than {}",
+        verb,
+        report.messages(),
+        report.session_events,
+        request
+            .before
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+    );
+    for (channel, count) in &report.channels {
+        println!("  {}: {}", channel, count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ages_and_times() {
+        let age = Utc::now() - parse_before("30d").unwrap();
+        assert!((age - chrono::Duration::days(30)).num_seconds().abs() < 5);
+        assert_eq!(
+            parse_before("2025-07-01T09:00:00Z").unwrap().to_rfc3339(),
+            "2025-07-01T09:00:00+00:00"
+        );
+        assert!(parse_before("30 days").is_err());
+        assert!(parse_before("2025-13-01T00:00").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+//! Handlers for the `ailoop incident` subcommands.
+
+use super::task_handlers::resolve_server_url;
+use ailoop_core::{ChannelClient, IncidentResponse};
+use anyhow::Result;
+
+/// Put each of `channels` (comma-separated) in incident mode. Channels without an incident
+/// share one id: `id`, or the one the server picks for the first.
+pub async fn handle_incident_start(
+    channels: String,
+    id: Option<String>,
+    title: Option<String>,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let channels = parse_channels(&channels)?;
+    let server_url = resolve_server_url(server)?;
+    let client = ChannelClient::new(&server_url);
+    let mut id = id;
+    let mut responses = Vec::new();
+    for channel in &channels {
+        let response = client
+            .start_incident(channel, id.as_deref(), title.as_deref())
+            .await?;
+        if response.changed && id.is_none() {
+            id = response.incident.as_ref().map(|i| i.id.clone());
+        }
+        responses.push(response);
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&responses)?);
+        return Ok(());
+    }
+    for response in &responses {
+        let Some(incident) = &response.incident else {
+            continue;
+        };
+        if response.changed {
+            println!(
+                "Incident {} started on '{}'; `ailoop incident stop --channel {}` ends it",
+                incident.id, response.channel, response.channel
+            );
+        } else {
+            println!(
+                "Channel '{}' is already in incident {} (since {})",
+                response.channel,
+                incident.id,
+                incident.started_at.format("%Y-%m-%d %H:%M:%S UTC")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// End the incidents open on `channels` (comma-separated).
+pub async fn handle_incident_stop(channels: String, server: String, json: bool) -> Result<()> {
+    let channels = parse_channels(&channels)?;
+    let server_url = resolve_server_url(server)?;
+    let client = ChannelClient::new(&server_url);
+    let mut responses: Vec<IncidentResponse> = Vec::new();
+    for channel in &channels {
+        responses.push(client.stop_incident(channel).await?);
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&responses)?);
+        return Ok(());
+    }
+    for response in &responses {
+        match &response.incident {
+            Some(incident) => println!(
+                "Incident {} ended on '{}'; `ailoop export github --channel {} --incident {}` posts its messages",
+                incident.id, response.channel, response.channel, incident.id
+            ),
+            None => println!("Channel '{}' has no open incident", response.channel),
+        }
+    }
+    Ok(())
+}
+
+fn parse_channels(list: &str) -> Result<Vec<String>> {
+    let channels: Vec<String> = list
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(str::to_string)
+        .collect();
+    if channels.is_empty() {
+        anyhow::bail!("Name at least one channel with --channel");
+    }
+    for channel in &channels {
+        ailoop_core::channel::validation::validate_channel_name(channel)
+            .map_err(|e| anyhow::anyhow!("Invalid channel name '{}': {}", channel, e))?;
+    }
+    Ok(channels)
+}
@@ -0,0 +1,108 @@
+//! `ailoop logs`: read the server's event log remotely.
+
+use super::task_handlers::resolve_server_url;
+use ailoop_core::LogsClient;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// Time between polls with `--follow`.
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What `ailoop logs` should fetch and how to print it.
+pub struct LogsOptions {
+    pub since: Option<DateTime<Utc>>,
+    pub event: Option<String>,
+    pub limit: usize,
+    pub follow: bool,
+    pub token: Option<String>,
+    pub json: bool,
+}
+
+/// Print the newest events (those after `since`, when given), then with `follow` keep printing
+/// new ones until interrupted.
+pub async fn handle_logs(server: String, options: LogsOptions) -> Result<()> {
+    let server_url = resolve_server_url(server)?;
+    let token = options.token.or_else(|| std::env::var("AILOOP_TOKEN").ok());
+    let client = LogsClient::new(&server_url).with_token(token);
+    let event = options.event.as_deref();
+
+    let page = client
+        .events(options.since, event, Some(options.limit))
+        .await
+        .context("Failed to read the event log")?;
+    for line in &page.events {
+        print_event(line, options.json)?;
+    }
+    if !options.follow {
+        return Ok(());
+    }
+
+    let mut since = page
+        .next_since
+        .or(options.since)
+        .or_else(|| Some(Utc::now()));
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(FOLLOW_INTERVAL) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+        match client.events(since, event, None).await {
+            Ok(page) => {
+                for line in &page.events {
+                    print_event(line, options.json)?;
+                }
+                since = page.next_since.or(since);
+            }
+            Err(e) => eprintln!("Warning: failed to read the event log: {:#}", e),
+        }
+    }
+}
+
+/// One event as its JSON line, or as `ts  event  key=value ...`.
+fn print_event(event: &serde_json::Value, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(event)?);
+    } else {
+        println!("{}", format_event(event));
+    }
+    Ok(())
+}
+
+fn format_event(event: &serde_json::Value) -> String {
+    let text = |v: &serde_json::Value| match v {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let mut line = format!("{}  {:<8}", text(&event["ts"]), text(&event["event"]));
+    if let Some(fields) = event.as_object() {
+        for (key, value) in fields {
+            if key == "ts" || key == "event" || value.is_null() {
+                continue;
+            }
+            line.push_str(&format!(" {}={}", key, text(value)));
+        }
+    }
+    line.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_event() {
+        let event = serde_json::json!({
+            "ts": "2026-10-16T09:12:03.120Z",
+            "event": "prompt",
+            "state": "pending",
+            "channel": "ops",
+            "correlation_id": null,
+            "attempts": 2,
+        });
+        assert_eq!(
+            format_event(&event),
+            "2026-10-16T09:12:03.120Z  prompt   attempts=2 channel=ops state=pending"
+        );
+    }
+}
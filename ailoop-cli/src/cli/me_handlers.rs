@@ -0,0 +1,238 @@
+//! Handlers for a responder's own state: away status and watched keywords (`ailoop me`), and
+//! unread notifications (`ailoop status --unread`).
+
+use super::schedule_handlers::{parse_at, parse_delay};
+use super::task_handlers::resolve_server_url;
+use ailoop_core::{AwayResponse, ReadClient, TeamClient, UnreadCounts, WatchResponse};
+use ailoop_server::server::subscriptions::parse_keywords;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Utc, Weekday};
+
+/// Mark yourself away until `until`, or until `ailoop me back`.
+pub async fn handle_me_away(
+    until: Option<String>,
+    note: Option<String>,
+    responder: Option<String>,
+    token: Option<String>,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let until = until.as_deref().map(parse_until).transpose()?;
+    let response = client(server, token)?
+        .away(
+            responder_or_user(responder).as_deref(),
+            until,
+            note.as_deref(),
+        )
+        .await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&response)?);
+    } else {
+        print_status(&response);
+    }
+    Ok(())
+}
+
+/// Mark yourself back.
+pub async fn handle_me_back(
+    responder: Option<String>,
+    token: Option<String>,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let response = client(server, token)?
+        .back(responder_or_user(responder).as_deref())
+        .await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&response)?);
+    } else {
+        print_status(&response);
+    }
+    Ok(())
+}
+
+/// Watch `keywords` (comma-separated), or list the watched ones when there are none.
+pub async fn handle_me_watch(
+    keywords: Option<String>,
+    responder: Option<String>,
+    token: Option<String>,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let keywords = keywords.as_deref().map(parse_keywords).unwrap_or_default();
+    let client = client(server, token)?;
+    let responder = responder_or_user(responder);
+    let response = if keywords.is_empty() {
+        client.watching(responder.as_deref()).await?
+    } else {
+        client.watch(responder.as_deref(), &keywords).await?
+    };
+    print_watched(&response, json)
+}
+
+/// Stop watching `keywords` (comma-separated), or every keyword when there are none.
+pub async fn handle_me_unwatch(
+    keywords: Option<String>,
+    responder: Option<String>,
+    token: Option<String>,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let keywords = keywords.as_deref().map(parse_keywords).unwrap_or_default();
+    let response = client(server, token)?
+        .unwatch(responder_or_user(responder).as_deref(), &keywords)
+        .await?;
+    print_watched(&response, json)
+}
+
+/// Show the agent notifications a responder has not read, per channel.
+pub async fn handle_status_unread(
+    responder: Option<String>,
+    token: Option<String>,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let server_url = resolve_server_url(server)?;
+    let counts = ReadClient::new(&server_url)
+        .with_token(token.or_else(|| std::env::var("AILOOP_TOKEN").ok()))
+        .unread(responder_or_user(responder).as_deref())
+        .await
+        .context("Failed to fetch unread notifications")?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&counts)?);
+    } else {
+        print_unread(&counts);
+    }
+    Ok(())
+}
+
+/// When a responder is back: `tomorrow` or a weekday (the start of the next such day, local
+/// time), a delay such as `3d`, a date (its start) or a time accepted by `say --at`.
+pub fn parse_until(value: &str) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Some(day) = back_on(value, Local::now().date_naive()) {
+        return Local
+            .from_local_datetime(&day.and_time(Default::default()))
+            .earliest()
+            .map(|time| time.with_timezone(&Utc))
+            .with_context(|| format!("'{}' does not exist in the local time zone", value));
+    }
+    if let Ok(delay) = parse_delay(value) {
+        return Ok(Utc::now() + delay);
+    }
+    parse_at(value).with_context(|| {
+        format!(
+            "Invalid time '{}': use e.g. friday, tomorrow, 3d or 2025-07-01",
+            value
+        )
+    })
+}
+
+/// The day `value` names, relative to `today`: `tomorrow`, a weekday (the next one after
+/// today) or a `YYYY-MM-DD` date.
+fn back_on(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let lower = value.to_ascii_lowercase();
+    if lower == "tomorrow" {
+        return today.succ_opt();
+    }
+    if let Ok(weekday) = lower.parse::<Weekday>() {
+        let ahead =
+            (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+        let ahead = if ahead == 0 { 7 } else { ahead };
+        return Some(today + Duration::days(i64::from(ahead)));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+fn responder_or_user(responder: Option<String>) -> Option<String> {
+    responder.or_else(|| std::env::var("USER").ok())
+}
+
+fn client(server: String, token: Option<String>) -> Result<TeamClient> {
+    let server_url = resolve_server_url(server)?;
+    Ok(TeamClient::new(&server_url)
+        .with_token(token.or_else(|| std::env::var("AILOOP_TOKEN").ok())))
+}
+
+fn print_status(response: &AwayResponse) {
+    match &response.away {
+        Some(away) => {
+            let until = away
+                .until
+                .map(|until| {
+                    format!(
+                        " until {}",
+                        until.with_timezone(&Local).format("%a %Y-%m-%d %H:%M")
+                    )
+                })
+                .unwrap_or_else(|| " until `ailoop me back`".to_string());
+            println!("{} is away{}", response.member, until);
+        }
+        None => println!("{} is back", response.member),
+    }
+}
+
+fn print_watched(response: &WatchResponse, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(response)?);
+    } else if response.keywords.is_empty() {
+        println!("{} watches no keywords", response.member);
+    } else {
+        println!(
+            "{} watches: {}",
+            response.member,
+            response.keywords.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn print_unread(counts: &UnreadCounts) {
+    if counts.channels.is_empty() {
+        println!("{} has read every notification", counts.reader);
+        return;
+    }
+    let width = counts
+        .channels
+        .iter()
+        .map(|c| c.channel.len())
+        .max()
+        .unwrap_or(0)
+        .max("CHANNEL".len());
+    println!(
+        "{:<width$}  {:>6}  {:>6}  OLDEST",
+        "CHANNEL", "UNREAD", "URGENT"
+    );
+    for channel in &counts.channels {
+        let oldest = channel
+            .oldest
+            .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        println!(
+            "{:<width$}  {:>6}  {:>6}  {}",
+            channel.channel, channel.unread, channel.urgent, oldest
+        );
+    }
+    println!("{} unread for {}", counts.total, counts.reader);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_days_back() {
+        // A Wednesday.
+        let today = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d);
+        assert_eq!(back_on("friday", today), day(2026, 10, 16));
+        assert_eq!(back_on("Fri", today), day(2026, 10, 16));
+        assert_eq!(back_on("wednesday", today), day(2026, 10, 21));
+        assert_eq!(back_on("tomorrow", today), day(2026, 10, 15));
+        assert_eq!(back_on("2026-11-02", today), day(2026, 11, 2));
+        assert_eq!(back_on("3d", today), None);
+
+        assert!(parse_until("3d").unwrap() > Utc::now() + Duration::days(2));
+        assert!(parse_until("someday").is_err());
+    }
+}
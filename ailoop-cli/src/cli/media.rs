@@ -0,0 +1,238 @@
+//! Images and files sent with `ailoop image` and `ailoop say --attach`
+//!
+//! Both commands hand their media to a [`MediaDelivery`], which does the same thing in either
+//! mode. In direct mode the media is shown where the agent runs: described, and with `--open`
+//! opened in the local viewer. In server mode a local file is uploaded to the server's
+//! attachment store and the notification carries it in `metadata.attachment`, so the human can
+//! download it from wherever they read the channel; a URL is passed on as is.
+
+use ailoop_core::models::Message;
+use ailoop_core::{AttachmentClient, UploadedAttachment};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// An image or file given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaSource {
+    Url(String),
+    File(PathBuf),
+}
+
+impl MediaSource {
+    /// A URL when `value` starts with `http://` or `https://`, otherwise a file that must exist.
+    pub fn parse(value: &str) -> Result<Self> {
+        if value.starts_with("http://") || value.starts_with("https://") {
+            return Ok(MediaSource::Url(value.to_string()));
+        }
+        let path = Path::new(value);
+        if !path.is_file() {
+            anyhow::bail!("File not found: {}", value);
+        }
+        Ok(MediaSource::File(path.to_path_buf()))
+    }
+
+    /// File name shown to the human.
+    pub fn name(&self) -> String {
+        match self {
+            MediaSource::Url(url) => url.rsplit('/').next().unwrap_or(url).to_string(),
+            MediaSource::File(path) => path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string()),
+        }
+    }
+
+    /// MIME type guessed from the extension, for the few formats viewers care about.
+    pub fn content_type(&self) -> Option<&'static str> {
+        let name = self.name();
+        let ext = name.rsplit_once('.')?.1.to_lowercase();
+        Some(match ext.as_str() {
+            "jpg" | "jpeg" => "image/jpeg",
+            "png" => "image/png",
+            "gif" => "image/gif",
+            "webp" => "image/webp",
+            "svg" => "image/svg+xml",
+            "pdf" => "application/pdf",
+            "txt" | "log" => "text/plain",
+            "json" => "application/json",
+            _ => return None,
+        })
+    }
+
+    /// Image format for display, or `None` when the file is not a known image.
+    pub fn image_type(&self) -> Option<&'static str> {
+        Some(match self.content_type()? {
+            "image/jpeg" => "JPEG",
+            "image/png" => "PNG",
+            "image/gif" => "GIF",
+            "image/webp" => "WebP",
+            "image/svg+xml" => "SVG",
+            _ => return None,
+        })
+    }
+}
+
+/// Where media goes: shown locally, or uploaded to a server.
+pub struct MediaDelivery {
+    /// WebSocket URL of the server, in server mode.
+    server: Option<String>,
+    token: Option<String>,
+}
+
+impl MediaDelivery {
+    /// Delivery for the mode `server` selects; `token` (else `AILOOP_TOKEN`) authenticates
+    /// uploads.
+    pub fn resolve(server: &str, token: Option<String>) -> Result<Self> {
+        let mode = crate::mode::determine_operation_mode(Some(server.to_string()))
+            .map_err(|e| anyhow::anyhow!("Failed to determine operation mode: {}", e))?;
+        Ok(Self {
+            server: mode.server_url.filter(|_| mode.is_server()),
+            token: token.or_else(|| std::env::var("AILOOP_TOKEN").ok()),
+        })
+    }
+
+    /// The server's WebSocket URL, or `None` in direct mode.
+    pub fn server_url(&self) -> Option<&str> {
+        self.server.as_deref()
+    }
+
+    /// Make `source` reachable from the server: a local file is uploaded, a URL is described
+    /// as is. Returns `None` in direct mode.
+    pub async fn prepare(&self, source: &MediaSource) -> Result<Option<serde_json::Value>> {
+        let Some(server) = &self.server else {
+            return Ok(None);
+        };
+        let attachment = match source {
+            MediaSource::Url(url) => serde_json::json!({
+                "name": source.name(),
+                "content_type": source.content_type(),
+                "url": url,
+            }),
+            MediaSource::File(path) => {
+                let bytes = tokio::fs::read(path)
+                    .await
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let uploaded: UploadedAttachment = AttachmentClient::new(http_base(server))
+                    .with_token(self.token.clone())
+                    .upload(&source.name(), source.content_type(), bytes)
+                    .await
+                    .context("Failed to upload attachment")?;
+                serde_json::json!(uploaded)
+            }
+        };
+        Ok(Some(attachment))
+    }
+
+    /// Show `source` where the agent runs, opening it in the local viewer with `open`.
+    pub fn show_locally(&self, channel: &str, source: &MediaSource, open: bool) -> Result<()> {
+        match source {
+            MediaSource::Url(url) => {
+                println!("[{}] Image URL: {}", channel, url);
+                if !open {
+                    println!("Please open this URL in your browser to view the image:");
+                    println!("   {}", url);
+                }
+            }
+            MediaSource::File(path) => {
+                println!("[{}] Image file: {}", channel, path.display());
+                println!("Image location: {}", path.canonicalize()?.display());
+                println!("Image type: {}", source.image_type().unwrap_or("Unknown"));
+                if !open {
+                    println!("Please open this file in an image viewer to view it.");
+                }
+            }
+        }
+        if open && !open_locally(&source.to_string()) {
+            eprintln!("Warning: no viewer found to open {}", source);
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for MediaSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MediaSource::Url(url) => f.write_str(url),
+            MediaSource::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Put `attachment` in the message's `metadata.attachment`.
+pub fn attach(message: &mut Message, attachment: &serde_json::Value) {
+    message
+        .metadata
+        .get_or_insert_with(|| serde_json::json!({}))["attachment"] = attachment.clone();
+}
+
+/// `http(s)://` base of a `ws(s)://` server URL.
+pub fn http_base(ws_url: &str) -> String {
+    if let Some(rest) = ws_url.strip_prefix("wss://") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = ws_url.strip_prefix("ws://") {
+        format!("http://{}", rest)
+    } else {
+        ws_url.to_string()
+    }
+}
+
+/// Open a URL or file with the platform's default handler (falling back to `feh` on Linux).
+/// Returns false when nothing could be started.
+pub fn open_locally(target: &str) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        ["xdg-open", "feh"].iter().any(|viewer| {
+            std::process::Command::new(viewer)
+                .arg(target)
+                .spawn()
+                .is_ok()
+        })
+    }
+    #[cfg(target_os = "windows")]
+    {
+        std::process::Command::new("cmd")
+            .args(["/C", "start", "", target])
+            .spawn()
+            .is_ok()
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg(target)
+            .spawn()
+            .is_ok()
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+    {
+        let _ = target;
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources_and_types() {
+        let url = MediaSource::parse("https://example.com/shots/plot.PNG").unwrap();
+        assert_eq!(url.name(), "plot.PNG");
+        assert_eq!(url.content_type(), Some("image/png"));
+        assert_eq!(url.image_type(), Some("PNG"));
+        assert!(MediaSource::parse("/definitely/not/here.png").is_err());
+
+        let file = tempfile::Builder::new().suffix(".log").tempfile().unwrap();
+        let source = MediaSource::parse(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(source.content_type(), Some("text/plain"));
+        assert_eq!(source.image_type(), None);
+    }
+
+    #[test]
+    fn test_http_base_of_websocket_url() {
+        assert_eq!(http_base("ws://127.0.0.1:8080"), "http://127.0.0.1:8080");
+        assert_eq!(
+            http_base("wss://loop.example.com"),
+            "https://loop.example.com"
+        );
+    }
+}
@@ -1,7 +1,9 @@
 //! Message converter for transforming agent events to ailoop messages
 
 use crate::parser::{AgentEvent, EventType};
-use ailoop_core::models::{Message, MessageContent, NotificationPriority, SenderType};
+use ailoop_core::models::{
+    AgentIdentity, ConverterRules, Message, MessageContent, NotificationPriority, SenderType,
+};
 use chrono::Utc;
 use serde_json::json;
 use std::collections::HashMap;
@@ -10,11 +12,15 @@ use std::collections::HashMap;
 ///
 /// This converter is completely transport-independent and agent-agnostic.
 /// It preserves agent-specific metadata in the message.metadata field.
+/// [`ConverterRules`] (from `[converter]` in the config) can drop event types, template
+/// tool-call text and cap notification length.
 pub struct MessageConverter {
     channel: String,
     client_id: Option<String>,
     agent_type: String,
     session_id: Option<String>,
+    rules: ConverterRules,
+    agent: Option<AgentIdentity>,
 }
 
 impl MessageConverter {
@@ -25,29 +31,46 @@ impl MessageConverter {
             client_id,
             agent_type,
             session_id: None,
+            rules: ConverterRules::default(),
+            agent: None,
         }
     }
 
+    /// Attach `agent` to every message.
+    pub fn with_agent(mut self, agent: AgentIdentity) -> Self {
+        self.agent = Some(agent);
+        self
+    }
+
+    /// Apply configured mapping rules (see `ConverterConfig::rules_for`).
+    pub fn with_rules(mut self, rules: ConverterRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
     /// Set the session ID (extracted from system events)
     pub fn set_session_id(&mut self, session_id: String) {
         self.session_id = Some(session_id);
     }
 
-    /// Set the agent type
     /// Convert an agent event to one or more messages
     ///
     /// Returns a vector to handle cases where one event produces multiple messages.
     /// Preserves agent_type, session_id, client_id, and timestamp in message.metadata.
     pub fn convert(&mut self, event: AgentEvent) -> Vec<Message> {
-        // Update session_id from system events
+        // Update session_id from any event that carries one
+        if let Some(session_id) = event.metadata.get("session_id") {
+            self.set_session_id(session_id.clone());
+        }
         if let EventType::System = event.event_type {
-            if let Some(session_id) = event.metadata.get("session_id") {
-                self.set_session_id(session_id.clone());
-            }
             // System events don't produce messages, just update state
             return vec![];
         }
 
+        if self.rules.drops(event.event_type.name()) {
+            return vec![];
+        }
+
         // Build metadata object with all tracking information
         let mut metadata = json!({
             "agent_type": self.agent_type.clone(),
@@ -76,10 +99,14 @@ impl MessageConverter {
         // Use event timestamp if available, otherwise use current time
         let timestamp = event.timestamp.unwrap_or_else(Utc::now);
 
+        // The untruncated event, for the server's session transcripts
+        let mut record = json!({ "type": event.event_type.name() });
+
         // Convert based on event type
-        let content = match event.event_type {
+        let mut content = match event.event_type {
             EventType::Assistant => {
                 let text = self.extract_text(&event.content, "message", "text");
+                record["text"] = json!(text);
                 MessageContent::Notification {
                     text: format!("[{}] {}", self.agent_type, text),
                     priority: NotificationPriority::Normal,
@@ -89,8 +116,24 @@ impl MessageConverter {
                 let tool_name = self.extract_text(&event.content, "tool", "name");
                 let status = self.extract_text(&event.content, "status", "state");
                 metadata["tool_args"] = event.content.get("args").cloned().unwrap_or(json!(null));
+                record["tool"] = json!(tool_name);
+                record["status"] = json!(status);
+                if let Some(call_id) = ["call_id", "tool_call_id", "id"]
+                    .iter()
+                    .find_map(|key| event.content.get(*key).and_then(|v| v.as_str()))
+                {
+                    record["call_id"] = json!(call_id);
+                }
+                let text = match &self.rules.tool_call_template {
+                    Some(template) => template
+                        .replace("{agent}", &self.agent_type)
+                        .replace("{tool}", &tool_name)
+                        .replace("{status}", &status)
+                        .replace("{args}", &metadata["tool_args"].to_string()),
+                    None => format!("[{}] Tool: {} - {}", self.agent_type, tool_name, status),
+                };
                 MessageContent::Notification {
-                    text: format!("[{}] Tool: {} - {}", self.agent_type, tool_name, status),
+                    text,
                     priority: NotificationPriority::Low,
                 }
             }
@@ -101,6 +144,7 @@ impl MessageConverter {
                     .get("duration")
                     .cloned()
                     .unwrap_or(json!(null));
+                record["text"] = json!(result_text);
                 MessageContent::Notification {
                     text: format!("[{}] Result: {}", self.agent_type, result_text),
                     priority: NotificationPriority::High,
@@ -109,6 +153,7 @@ impl MessageConverter {
             EventType::User => {
                 // Optional: include user events for context
                 let text = self.extract_text(&event.content, "message", "text");
+                record["text"] = json!(text);
                 MessageContent::Notification {
                     text: format!("[{}] User: {}", self.agent_type, text),
                     priority: NotificationPriority::Low,
@@ -117,6 +162,7 @@ impl MessageConverter {
             EventType::Error => {
                 let error_text = self.extract_text(&event.content, "error", "message");
                 metadata["error_details"] = event.content.clone();
+                record["text"] = json!(error_text);
                 MessageContent::Notification {
                     text: format!("[{}] Error: {}", self.agent_type, error_text),
                     priority: NotificationPriority::Urgent,
@@ -129,6 +175,7 @@ impl MessageConverter {
             EventType::Custom(typ) => {
                 metadata["custom_type"] = json!(typ);
                 let text = self.extract_text(&event.content, "message", "text");
+                record["text"] = json!(text);
                 MessageContent::Notification {
                     text: format!("[{}] {}: {}", self.agent_type, typ, text),
                     priority: NotificationPriority::Normal,
@@ -136,6 +183,16 @@ impl MessageConverter {
             }
         };
 
+        if let (Some(max), MessageContent::Notification { text, .. }) =
+            (self.rules.max_length, &mut content)
+        {
+            if text.chars().count() > max {
+                *text = truncate(text, max);
+                metadata["truncated"] = json!(true);
+            }
+        }
+        metadata["event"] = record;
+
         let message = Message {
             id: uuid::Uuid::new_v4(),
             channel: self.channel.clone(),
@@ -143,18 +200,20 @@ impl MessageConverter {
             content,
             timestamp,
             correlation_id: None,
+            parent_id: None,
             metadata: Some(metadata),
+            agent: self.agent.clone(),
+            sequence: None,
         };
 
         vec![message]
     }
 
-    /// Extract text from JSON content using multiple possible keys
+    /// Extract text from JSON content using the first of two keys that holds a string
     fn extract_text(&self, content: &serde_json::Value, primary: &str, secondary: &str) -> String {
-        content
-            .get(primary)
-            .or_else(|| content.get(secondary))
-            .and_then(|v| v.as_str())
+        [primary, secondary]
+            .iter()
+            .find_map(|key| content.get(*key).and_then(|v| v.as_str()))
             .map(|s| s.to_string())
             .unwrap_or_else(|| {
                 // Fallback: try to serialize the whole content
@@ -163,6 +222,13 @@ impl MessageConverter {
     }
 }
 
+/// `text` cut to at most `max` characters, ending with `…`.
+fn truncate(text: &str, max: usize) -> String {
+    let mut cut: String = text.chars().take(max.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -195,5 +261,88 @@ mod tests {
         let metadata = messages[0].metadata.as_ref().unwrap();
         assert_eq!(metadata["agent_type"], "cursor");
         assert_eq!(metadata["client_id"], "client-123");
+        assert_eq!(
+            metadata["event"],
+            json!({"type": "assistant", "text": "Hello, world!"})
+        );
+    }
+
+    #[test]
+    fn test_error_text_from_message_when_error_is_an_object() {
+        let mut converter =
+            MessageConverter::new("test-channel".to_string(), None, "cursor".to_string());
+        let messages = converter.convert(event(
+            EventType::Error,
+            json!({"error": {"message": "Rate limit exceeded"}, "message": "Rate limit exceeded"}),
+        ));
+        assert_eq!(text_of(&messages[0]), "[cursor] Error: Rate limit exceeded");
+    }
+
+    #[test]
+    fn test_session_id_from_event_metadata() {
+        let mut converter =
+            MessageConverter::new("test-channel".to_string(), None, "opencode".to_string());
+        let mut tool = event(
+            EventType::ToolCall,
+            json!({"tool": "shell", "status": "completed", "call_id": "c1"}),
+        );
+        tool.metadata
+            .insert("session_id".to_string(), "sess-1".to_string());
+
+        let messages = converter.convert(tool);
+        let metadata = messages[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata["session_id"], "sess-1");
+        assert_eq!(
+            metadata["event"],
+            json!({"type": "tool_call", "tool": "shell", "status": "completed", "call_id": "c1"})
+        );
+    }
+
+    fn event(event_type: EventType, content: serde_json::Value) -> AgentEvent {
+        AgentEvent {
+            _agent_type: "claude".to_string(),
+            event_type,
+            content,
+            metadata: HashMap::new(),
+            timestamp: None,
+        }
+    }
+
+    fn text_of(message: &Message) -> &str {
+        match &message.content {
+            MessageContent::Notification { text, .. } => text,
+            other => panic!("unexpected content: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rules_drop_template_and_truncate() {
+        let mut converter =
+            MessageConverter::new("test-channel".to_string(), None, "claude".to_string())
+                .with_rules(ConverterRules {
+                    drop: Some(vec!["user".to_string()]),
+                    tool_call_template: Some("{agent} ran {tool} ({status}) {args}".to_string()),
+                    max_length: Some(20),
+                });
+
+        assert!(converter
+            .convert(event(EventType::User, json!({"message": "hi"})))
+            .is_empty());
+
+        let tool = converter.convert(event(
+            EventType::ToolCall,
+            json!({"tool": "ls", "status": "ok", "args": {"a": 1}}),
+        ));
+        assert_eq!(text_of(&tool[0]), "claude ran ls (ok) …");
+        assert_eq!(tool[0].metadata.as_ref().unwrap()["truncated"], true);
+
+        let short = converter.convert(event(EventType::Assistant, json!({"message": "done"})));
+        assert_eq!(text_of(&short[0]), "[claude] done");
+        assert!(short[0]
+            .metadata
+            .as_ref()
+            .unwrap()
+            .get("truncated")
+            .is_none());
     }
 }
@@ -1,14 +1,38 @@
 //! CLI command handling
 
+pub mod break_glass_handlers;
+pub mod channel_handlers;
+pub mod chat_handlers;
+pub mod checkpoint;
+pub mod coalesce;
 pub mod commands;
 pub mod doctor;
+pub mod dry_run;
+pub mod export_handlers;
+pub mod fanout;
 pub mod forward;
+pub mod guardrail;
+pub mod guide;
 pub mod handlers;
+pub mod history_handlers;
+pub mod incident_handlers;
+pub mod logs_handlers;
+pub mod me_handlers;
+pub mod media;
 pub mod message_converter;
+pub mod outbox_handlers;
+pub mod privacy_handlers;
+pub mod project;
 pub mod provider;
 pub mod provider_handlers;
+pub mod quarantine;
 pub mod queue;
 pub mod queue_handlers;
+pub mod schedule_handlers;
+pub mod session_handlers;
+pub mod stats_handlers;
 pub mod task;
 pub mod task_handlers;
 pub mod terminal_input;
+#[cfg(feature = "tray")]
+pub mod tray;
@@ -0,0 +1,23 @@
+//! Handler for outbox flush
+
+use anyhow::Result;
+
+use crate::cli::provider_handlers::load_config;
+use ailoop_core::transport::outbox::Outbox;
+
+/// Send every spooled message; entries for servers that are still down stay queued.
+pub async fn handle_outbox_flush(config_arg: &str) -> Result<()> {
+    let config = load_config(config_arg)?;
+    let outbox = Outbox::from_config(&config.outbox)?;
+    let report = outbox.flush(None).await?;
+    println!(
+        "Sent {} message(s); {} still queued in {}",
+        report.sent,
+        report.remaining,
+        outbox.dir().display()
+    );
+    if report.remaining > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! Handlers for the `ailoop privacy` subcommands.
+
+use super::session_handlers::write_compressed_to_stdout;
+use super::task_handlers::resolve_server_url;
+use ailoop_core::services::compression;
+use ailoop_core::PrivacyClient;
+use anyhow::{bail, Context, Result};
+
+/// Export everything the server holds for `responder` as JSON, to `output` or stdout,
+/// zstd-compressed with `compress`.
+pub async fn handle_privacy_export(
+    responder: String,
+    output: Option<String>,
+    compress: bool,
+    server: String,
+) -> Result<()> {
+    let server_url = resolve_server_url(server)?;
+    let export = PrivacyClient::new(&server_url)
+        .export(&responder)
+        .await
+        .context("Failed to export responder data")?;
+    let json = serde_json::to_string_pretty(&export)? + "\n";
+    match output {
+        Some(path) => {
+            let data = if compress {
+                compression::compress(json.as_bytes())?
+            } else {
+                json.into_bytes()
+            };
+            std::fs::write(&path, data).with_context(|| format!("Failed to write {}", path))?;
+            eprintln!(
+                "Exported {} message(s) for {} to {}",
+                export.messages.len(),
+                export.responder,
+                path
+            );
+        }
+        None if compress => write_compressed_to_stdout(json.as_bytes())?,
+        None => print!("{}", json),
+    }
+    Ok(())
+}
+
+/// Erase the responses of `responder` and the files they sent. Needs `yes`, as it cannot be
+/// undone.
+pub async fn handle_privacy_erase(
+    responder: String,
+    yes: bool,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    if !yes {
+        bail!(
+            "Erasing {}'s data cannot be undone; run `ailoop privacy export` first if needed, then pass --yes",
+            responder
+        );
+    }
+    let server_url = resolve_server_url(server)?;
+    let erasure = PrivacyClient::new(&server_url)
+        .erase(&responder)
+        .await
+        .context("Failed to erase responder data")?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&erasure)?);
+    } else {
+        println!(
+            "Erased {} message(s) and {} attachment(s) for {}",
+            erasure.messages, erasure.attachments, erasure.responder
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,178 @@
+//! Per-project defaults from `.ailoop.toml` (`ailoop init`).
+//!
+//! Commands run in a directory containing the file, or below it, take their channel, server and
+//! timeout from it when the matching flag is not given. `AILOOP_SERVER` still wins over the
+//! project's server, as it does over `--server`.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Name of the project file.
+pub const PROJECT_FILE: &str = ".ailoop.toml";
+
+/// Channel written by `ailoop init` when the directory name is not a valid channel name.
+const FALLBACK_CHANNEL: &str = "public";
+
+/// Contents of `.ailoop.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Channel used when `--channel` is not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Server URL used when `--server` is not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// Prompt timeout in seconds used when `--timeout` is not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u32>,
+}
+
+impl ProjectConfig {
+    /// The nearest project file in `start` or one of its parents.
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let project: Self =
+            toml::from_str(&content).with_context(|| format!("Invalid {}", path.display()))?;
+        if let Some(channel) = &project.channel {
+            ailoop_core::channel::validation::validate_channel_name(channel)
+                .map_err(|e| anyhow::anyhow!("{}: channel: {}", path.display(), e))?;
+        }
+        Ok(project)
+    }
+}
+
+static CURRENT: OnceLock<ProjectConfig> = OnceLock::new();
+
+/// The project file for the working directory, or empty defaults when there is none. A file
+/// that cannot be read is reported once and ignored.
+pub fn current() -> &'static ProjectConfig {
+    CURRENT.get_or_init(|| {
+        let Some(path) = std::env::current_dir()
+            .ok()
+            .and_then(|dir| ProjectConfig::find(&dir))
+        else {
+            return ProjectConfig::default();
+        };
+        ProjectConfig::load(&path).unwrap_or_else(|e| {
+            eprintln!("Warning: ignoring {:#}", e);
+            ProjectConfig::default()
+        })
+    })
+}
+
+/// Channel named after `dir`, when its name can be made into a valid channel name.
+fn channel_for_dir(dir: &Path) -> Option<String> {
+    let name: String = dir
+        .file_name()?
+        .to_string_lossy()
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let name = name.trim_matches('-').to_string();
+    ailoop_core::channel::validation::validate_channel_name(&name)
+        .is_ok()
+        .then_some(name)
+}
+
+/// Handle `ailoop init`: write `.ailoop.toml` in the working directory.
+pub fn handle_init(
+    channel: Option<String>,
+    server: Option<String>,
+    timeout_seconds: Option<u32>,
+    force: bool,
+) -> Result<()> {
+    let dir = std::env::current_dir().context("Failed to get the working directory")?;
+    let path = dir.join(PROJECT_FILE);
+    if path.exists() && !force {
+        bail!(
+            "{} already exists; pass --force to replace it",
+            path.display()
+        );
+    }
+    let channel = match channel {
+        Some(channel) => {
+            ailoop_core::channel::validation::validate_channel_name(&channel)
+                .map_err(|e| anyhow::anyhow!("Invalid channel name '{}': {}", channel, e))?;
+            channel
+        }
+        None => channel_for_dir(&dir).unwrap_or_else(|| FALLBACK_CHANNEL.to_string()),
+    };
+    let project = ProjectConfig {
+        channel: Some(channel),
+        server: Some(server.unwrap_or_else(|| "http://127.0.0.1:8080".to_string())),
+        timeout_seconds: Some(timeout_seconds.unwrap_or(300)),
+    };
+    let content = format!(
+        "# ailoop project settings, used by commands run in this directory or below it.\n\
+         # Command-line flags take precedence.\n{}",
+        toml::to_string(&project)?
+    );
+    std::fs::write(&path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("Wrote {}", path.display());
+    println!("  channel:         {}", project.channel.unwrap_or_default());
+    println!("  server:          {}", project.server.unwrap_or_default());
+    println!(
+        "  timeout_seconds: {}",
+        project.timeout_seconds.unwrap_or_default()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_file_is_found_from_subdirectories() {
+        let root = tempfile::tempdir().unwrap();
+        let nested = root.path().join("src").join("deep");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert!(ProjectConfig::find(&nested).is_none_or(|p| !p.starts_with(root.path())));
+
+        std::fs::write(
+            root.path().join(PROJECT_FILE),
+            "channel = \"deploys\"\ntimeout_seconds = 60\n",
+        )
+        .unwrap();
+        let path = ProjectConfig::find(&nested).unwrap();
+        assert_eq!(path, root.path().join(PROJECT_FILE));
+        let project = ProjectConfig::load(&path).unwrap();
+        assert_eq!(project.channel.as_deref(), Some("deploys"));
+        assert_eq!(project.server, None);
+        assert_eq!(project.timeout_seconds, Some(60));
+
+        std::fs::write(&path, "channel = \"Bad Name\"\n").unwrap();
+        assert!(ProjectConfig::load(&path).is_err());
+        std::fs::write(&path, "chanel = \"typo\"\n").unwrap();
+        assert!(ProjectConfig::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_channel_for_dir() {
+        assert_eq!(
+            channel_for_dir(Path::new("/src/My Repo.v2")).as_deref(),
+            Some("my-repo-v2")
+        );
+        assert_eq!(channel_for_dir(Path::new("/src/ailoop")), None);
+        assert_eq!(channel_for_dir(Path::new("/")), None);
+    }
+}
@@ -1,10 +1,13 @@
-//! Handler for provider list and provider telegram test
+//! Handlers for provider list, provider telegram test and notify-test
 
 use anyhow::Result;
 use std::path::PathBuf;
 
 use ailoop_core::models::{Configuration, Message, MessageContent, SenderType};
-use ailoop_server::server::providers::NotificationSink;
+use ailoop_core::transport::proxy::ProxySettings;
+use ailoop_server::server::providers::{
+    probe, NotificationSink, ProbeDelivery, ProbeReply, ProbeReport,
+};
 
 fn resolve_config_path(config_arg: &str) -> Result<PathBuf> {
     if config_arg.starts_with("~/") {
@@ -21,7 +24,7 @@ fn resolve_config_path(config_arg: &str) -> Result<PathBuf> {
     }
 }
 
-fn load_config(config_arg: &str) -> Result<Configuration> {
+pub(crate) fn load_config(config_arg: &str) -> Result<Configuration> {
     let path = resolve_config_path(config_arg)?;
     Ok(Configuration::load_from_file(&path).unwrap_or_default())
 }
@@ -82,7 +85,9 @@ pub async fn handle_provider_telegram_test(config_arg: &str) -> Result<()> {
             std::process::exit(1);
         }
     };
+    let proxy = ProxySettings::resolve(&config.proxy)?;
     let sink = ailoop_server::server::providers::TelegramSink::new(token, chat_id)
+        .and_then(|sink| sink.with_proxy(&proxy))
         .map_err(|e| anyhow::anyhow!("Failed to create Telegram sink: {}", e))?;
     let msg = Message::new(
         "public".to_string(),
@@ -98,3 +103,102 @@ pub async fn handle_provider_telegram_test(config_arg: &str) -> Result<()> {
     println!("Test message sent to Telegram");
     Ok(())
 }
+
+/// Text of the prompt `ailoop notify-test` sends.
+const NOTIFY_TEST_PROMPT: &str =
+    "ailoop notify-test: approve this to confirm answers from here reach ailoop";
+
+/// Send a test authorization through every configured provider, wait up to `wait` for an
+/// answer through each, and print what came of it. Fails when a provider could not be
+/// started, did not deliver, or was not answered in time.
+pub async fn handle_notify_test(
+    config_arg: &str,
+    channel: Option<String>,
+    wait: std::time::Duration,
+) -> Result<()> {
+    let config = load_config(config_arg)?;
+    let channel = channel.unwrap_or_else(|| config.default_channel.clone());
+    let state = ailoop_server::AiloopAppState::new(config.default_channel.clone());
+    let message = ailoop_core::client::authorization(
+        &channel,
+        NOTIFY_TEST_PROMPT,
+        u32::try_from(wait.as_secs()).unwrap_or(u32::MAX),
+    );
+
+    let mut reports = Vec::new();
+    let mut probes = Vec::new();
+    let mut off = Vec::new();
+    for (name, outcome) in state.providers.build(&config, &state) {
+        match outcome {
+            Ok(Some(provider)) => {
+                probes.push(tokio::spawn(probe(provider, message.clone(), wait)));
+            }
+            Ok(None) => off.push(name),
+            Err(e) => reports.push(ProbeReport {
+                provider: name,
+                delivery: ProbeDelivery::Failed(format!("cannot start: {}", e)),
+                reply: ProbeReply::NotWaited,
+            }),
+        }
+    }
+    if probes.is_empty() && reports.is_empty() {
+        anyhow::bail!("No provider is configured in {}", config_arg);
+    }
+    if !probes.is_empty() {
+        println!(
+            "Test prompt sent on channel '{}'. Approve it on each provider within {}s.",
+            channel,
+            wait.as_secs()
+        );
+    }
+    for handle in probes {
+        reports.push(handle.await?);
+    }
+
+    println!("provider\tdelivery\treply");
+    for report in &reports {
+        println!(
+            "{}\t{}\t{}",
+            report.provider,
+            delivery_label(&report.delivery),
+            reply_label(&report.reply)
+        );
+    }
+    if !off.is_empty() {
+        println!(
+            "Not started (disabled or missing token): {}",
+            off.join(", ")
+        );
+    }
+    let failed = reports.iter().filter(|r| r.failed()).count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} providers failed the test", failed, reports.len());
+    }
+    Ok(())
+}
+
+fn delivery_label(delivery: &ProbeDelivery) -> String {
+    match delivery {
+        ProbeDelivery::Delivered => "delivered".to_string(),
+        ProbeDelivery::Skipped => "skipped".to_string(),
+        ProbeDelivery::Failed(e) => format!("FAILED: {}", e),
+    }
+}
+
+fn reply_label(reply: &ProbeReply) -> String {
+    match reply {
+        ProbeReply::Received {
+            responder,
+            answer,
+            elapsed,
+        } => format!(
+            "{} from {} after {}s",
+            answer.as_deref().unwrap_or("answer"),
+            responder,
+            elapsed.as_secs()
+        ),
+        ProbeReply::TimedOut => "NO ANSWER".to_string(),
+        ProbeReply::Unsupported => "n/a (answers not read here)".to_string(),
+        ProbeReply::NotWaited => "-".to_string(),
+    }
+}
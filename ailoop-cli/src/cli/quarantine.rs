@@ -0,0 +1,122 @@
+//! Recovery from malformed agent output in `forward`
+//!
+//! A line the parser rejects is skipped: it is logged at debug level, counted, and, with
+//! `--quarantine FILE`, appended to that file unchanged so it can be inspected or replayed.
+//! The totals are reported on stderr when forwarding ends.
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+/// Counts input lines and keeps the ones that failed to parse.
+pub struct Quarantine {
+    lines: usize,
+    malformed: usize,
+    path: Option<PathBuf>,
+    file: Option<tokio::fs::File>,
+}
+
+impl Quarantine {
+    /// Start counting; malformed lines are appended to `path` when given.
+    pub async fn open(path: Option<PathBuf>) -> Result<Self> {
+        let file = match &path {
+            Some(path) => Some(
+                tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .with_context(|| format!("Failed to open quarantine file {:?}", path))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            lines: 0,
+            malformed: 0,
+            path,
+            file,
+        })
+    }
+
+    /// Count a line read from the input.
+    pub fn line_read(&mut self) {
+        self.lines += 1;
+    }
+
+    /// Record a line the parser rejected.
+    pub async fn record(&mut self, line: &str, error: &anyhow::Error) {
+        self.malformed += 1;
+        tracing::debug!(line_number = self.lines, error = %error, line, "Skipping malformed line");
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        let written = async {
+            file.write_all(line.as_bytes()).await?;
+            file.write_all(b"\n").await
+        }
+        .await;
+        if let Err(e) = written {
+            eprintln!("Warning: Failed to write to quarantine file: {}", e);
+            // Keep counting, but stop trying to write.
+            self.file = None;
+        }
+    }
+
+    pub fn malformed(&self) -> usize {
+        self.malformed
+    }
+
+    /// Flush the quarantine file and report the totals when anything was skipped.
+    pub async fn finish(&mut self) -> Result<()> {
+        if let Some(file) = &mut self.file {
+            file.flush()
+                .await
+                .context("Failed to flush quarantine file")?;
+        }
+        if self.malformed == 0 {
+            return Ok(());
+        }
+        match &self.path {
+            Some(path) => eprintln!(
+                "Skipped {} malformed line(s) of {}; saved to {}",
+                self.malformed,
+                self.lines,
+                path.display()
+            ),
+            None => eprintln!(
+                "Skipped {} malformed line(s) of {}; use --quarantine FILE to keep them",
+                self.malformed, self.lines
+            ),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_malformed_lines_are_appended() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("bad_lines.jsonl");
+        std::fs::write(&path, "{\"earlier\":\n")?;
+
+        let mut quarantine = Quarantine::open(Some(path.clone())).await?;
+        for line in ["{\"ok\":true}", "{not json", "{\"ok\":true}", "[1,"] {
+            quarantine.line_read();
+            if line.starts_with("{\"ok") {
+                continue;
+            }
+            quarantine.record(line, &anyhow::anyhow!("bad")).await;
+        }
+        quarantine.finish().await?;
+
+        assert_eq!(quarantine.malformed(), 2);
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "{\"earlier\":\n{not json\n[1,\n"
+        );
+        Ok(())
+    }
+}
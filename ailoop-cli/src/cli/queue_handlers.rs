@@ -1,9 +1,11 @@
-//! Handler for the `ailoop queue` subcommand.
+//! Handlers for the `ailoop queue` subcommands.
 
 use super::task_handlers::resolve_server_url;
-use ailoop_core::PendingClient;
-use anyhow::Result;
+use ailoop_core::{PendingClient, QueueClient, QueueDropResponse, QueuedMessageResponse};
+use anyhow::{Context, Result};
+use uuid::Uuid;
 
+/// Prompts currently put to humans (`ailoop queue`).
 pub async fn handle_queue(server: String, channel: Option<String>, json: bool) -> Result<()> {
     let server_url = resolve_server_url(server)?;
     let client = PendingClient::new(&server_url);
@@ -23,6 +25,14 @@ pub async fn handle_queue(server: String, channel: Option<String>, json: bool) -
         "Human queue: {} pending ({})",
         response.total_count, filter_label
     );
+    for held in &response.held_channels {
+        println!(
+            "Paused: {} since {} ({} queued)",
+            held.channel,
+            held.held_since.format("%Y-%m-%d %H:%M:%S UTC"),
+            held.queued
+        );
+    }
     println!();
 
     if response.items.is_empty() {
@@ -56,7 +66,11 @@ pub async fn handle_queue(server: String, channel: Option<String>, json: bool) -
         let msg_id_str = item.message_id.to_string().replace('-', "");
         let msg_display = format!("{}...", &msg_id_str[..8]);
 
-        let title_display: String = item.label.chars().take(title_width).collect();
+        let title = match &item.claimed_by {
+            Some(responder) => format!("[{}] {}", responder, item.label),
+            None => item.label.clone(),
+        };
+        let title_display: String = title.chars().take(title_width).collect();
 
         println!(
             "{:<2}  {:<2}  {:<10}  {:<10}  {}",
@@ -66,3 +80,134 @@ pub async fn handle_queue(server: String, channel: Option<String>, json: bool) -
 
     Ok(())
 }
+
+/// Messages waiting on the channel queues, before they are put to humans.
+pub async fn handle_queue_list(channel: Option<String>, server: String, json: bool) -> Result<()> {
+    let server_url = resolve_server_url(server)?;
+    let response = QueueClient::new(&server_url)
+        .list(channel.as_deref())
+        .await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&response)?);
+        return Ok(());
+    }
+    if response.channels.is_empty() {
+        println!("(no queued messages)");
+        return Ok(());
+    }
+    for queue in &response.channels {
+        match queue.held_since {
+            Some(since) => println!(
+                "{}: {} queued (paused since {})",
+                queue.channel,
+                queue.messages.len(),
+                since.format("%Y-%m-%d %H:%M:%S UTC")
+            ),
+            None => println!("{}: {} queued", queue.channel, queue.messages.len()),
+        }
+        for message in &queue.messages {
+            print_message(message);
+        }
+    }
+    Ok(())
+}
+
+/// Remove one queued message; the server cancels it if it was a prompt.
+pub async fn handle_queue_drop(
+    id: String,
+    token: Option<String>,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let id = Uuid::parse_str(id.trim()).with_context(|| format!("Invalid id '{}'", id))?;
+    let server_url = resolve_server_url(server)?;
+    let response = client(&server_url, token).drop_message(id).await?;
+    print_dropped(&response, json)
+}
+
+/// Claim a pending prompt for `responder`, or with `release` give it back.
+pub async fn handle_queue_claim(
+    message_id: String,
+    responder: Option<String>,
+    release: bool,
+    token: Option<String>,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let message_id = Uuid::parse_str(message_id.trim())
+        .with_context(|| format!("Invalid message id '{}'", message_id))?;
+    let responder = responder.or_else(|| std::env::var("USER").ok());
+    let server_url = resolve_server_url(server)?;
+    let client = PendingClient::new(&server_url)
+        .with_token(token.or_else(|| std::env::var("AILOOP_TOKEN").ok()));
+    let response = if release {
+        client.release(message_id, responder.as_deref()).await?
+    } else {
+        client.claim(message_id, responder.as_deref()).await?
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&response)?);
+    } else if response.claimed {
+        println!(
+            "Claimed {} on {} for {}",
+            response.message_id, response.channel, response.responder
+        );
+    } else {
+        println!(
+            "Released {} on {}; it is open again",
+            response.message_id, response.channel
+        );
+    }
+    Ok(())
+}
+
+/// Remove every message queued on `channel`.
+pub async fn handle_queue_clear(
+    channel: String,
+    token: Option<String>,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    ailoop_core::channel::validation::validate_channel_name(&channel)
+        .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+    let server_url = resolve_server_url(server)?;
+    let response = client(&server_url, token).clear(&channel).await?;
+    print_dropped(&response, json)
+}
+
+fn client(server_url: &str, token: Option<String>) -> QueueClient {
+    QueueClient::new(server_url).with_token(token.or_else(|| std::env::var("AILOOP_TOKEN").ok()))
+}
+
+fn print_dropped(response: &QueueDropResponse, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(response)?);
+        return Ok(());
+    }
+    println!("Dropped {} message(s)", response.dropped.len());
+    for message in &response.dropped {
+        print_message(message);
+    }
+    Ok(())
+}
+
+fn print_message(message: &QueuedMessageResponse) {
+    let label: String = message.label.chars().take(60).collect();
+    println!(
+        "  {}  {:<12}  {:>6}  {}",
+        message.id,
+        message.kind,
+        age(message.age_seconds),
+        label
+    );
+}
+
+/// "45s", "12m", "3h" or "2d".
+fn age(seconds: i64) -> String {
+    match seconds {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86_400),
+    }
+}
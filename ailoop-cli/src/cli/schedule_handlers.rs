@@ -0,0 +1,311 @@
+//! Scheduled messages (`ailoop say --at/--in`, `ailoop schedule`) and recurring reminders
+//! (`ailoop remind`).
+
+use super::task_handlers::resolve_server_url;
+use ailoop_core::models::{
+    CronSchedule, Message, NotificationPriority, Reminder, ScheduledMessage,
+};
+use ailoop_core::ScheduleClient;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone, Utc};
+use uuid::Uuid;
+
+/// Local date-time forms accepted by `--at`, besides RFC 3339.
+const LOCAL_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+];
+
+/// When to send, from `--at` or `--in`; `None` when neither is given.
+pub fn parse_when(at: Option<String>, delay: Option<String>) -> Result<Option<DateTime<Utc>>> {
+    match (at, delay) {
+        (Some(_), Some(_)) => bail!("Give either --at or --in, not both"),
+        (Some(at), None) => parse_at(&at).map(Some),
+        (None, Some(delay)) => Ok(Some(Utc::now() + parse_delay(&delay)?)),
+        (None, None) => Ok(None),
+    }
+}
+
+/// `2025-07-01T09:00` (local time) or an RFC 3339 timestamp.
+pub fn parse_at(at: &str) -> Result<DateTime<Utc>> {
+    let at = at.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(at) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let naive = LOCAL_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(at, format).ok())
+        .with_context(|| format!("Invalid time '{}': use e.g. 2025-07-01T09:00", at))?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .with_context(|| format!("'{}' does not exist in the local time zone", at))
+}
+
+/// A delay such as `90s`, `30m`, `2h` or `1d`.
+pub fn parse_delay(delay: &str) -> Result<Duration> {
+    let delay = delay.trim();
+    let split = delay
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(delay.len());
+    let (amount, unit) = delay.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .with_context(|| format!("Invalid delay '{}': use e.g. 30m, 2h or 1d", delay))?;
+    match unit {
+        "s" => Ok(Duration::seconds(amount)),
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        _ => bail!("Invalid delay '{}': use e.g. 30m, 2h or 1d", delay),
+    }
+}
+
+/// Hand `message` to the server to send at `at`.
+pub async fn schedule_message(message: Message, at: DateTime<Utc>, server: String) -> Result<()> {
+    let server_url = resolve_server_url(server)?;
+    let entry = ScheduleClient::new(&server_url)
+        .schedule(at, message)
+        .await
+        .context("Failed to schedule message")?;
+    println!(
+        "Scheduled for {} on channel {} (id {})",
+        local_time(entry.due_at),
+        entry.message.channel,
+        entry.id
+    );
+    Ok(())
+}
+
+/// List the messages waiting to be sent.
+pub async fn handle_schedule_list(
+    channel: Option<String>,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    if let Some(channel) = &channel {
+        ailoop_core::channel::validation::validate_channel_name(channel)
+            .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+    }
+    let server_url = resolve_server_url(server)?;
+    let scheduled = ScheduleClient::new(&server_url)
+        .list(channel.as_deref())
+        .await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&scheduled)?);
+        return Ok(());
+    }
+    if scheduled.is_empty() {
+        println!("No scheduled messages");
+        return Ok(());
+    }
+    for entry in &scheduled {
+        print_entry(entry);
+    }
+    Ok(())
+}
+
+/// Drop a scheduled message before it is sent.
+pub async fn handle_schedule_cancel(id: String, server: String, json: bool) -> Result<()> {
+    let id = Uuid::parse_str(id.trim()).with_context(|| format!("Invalid id '{}'", id))?;
+    let server_url = resolve_server_url(server)?;
+    let Some(entry) = ScheduleClient::new(&server_url).cancel(id).await? else {
+        bail!("No scheduled message with id {}", id);
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entry)?);
+    } else {
+        println!("Cancelled:");
+        print_entry(&entry);
+    }
+    Ok(())
+}
+
+fn print_entry(entry: &ScheduledMessage) {
+    println!(
+        "{}  {}  [{}] {}",
+        entry.id,
+        local_time(entry.due_at),
+        entry.message.channel,
+        entry.summary()
+    );
+}
+
+/// Have the server send `text` on `channel` whenever `schedule` matches.
+pub async fn handle_remind_add(
+    schedule: String,
+    text: String,
+    channel: String,
+    priority: String,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    ailoop_core::channel::validation::validate_channel_name(&channel)
+        .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+    let schedule: CronSchedule = schedule
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid schedule: {}", e))?;
+    let priority = parse_priority(&priority)?;
+    let server_url = resolve_server_url(server)?;
+    let reminder = ScheduleClient::new(&server_url)
+        .add_reminder(&channel, schedule, &text, Some(priority))
+        .await
+        .context("Failed to add reminder")?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reminder)?);
+    } else {
+        println!("Added reminder {}", reminder.id);
+        print_reminder(&reminder);
+    }
+    Ok(())
+}
+
+/// List reminders with their next occurrence and acknowledgement state.
+pub async fn handle_remind_list(channel: Option<String>, server: String, json: bool) -> Result<()> {
+    if let Some(channel) = &channel {
+        ailoop_core::channel::validation::validate_channel_name(channel)
+            .map_err(|e| anyhow::anyhow!("Invalid channel name: {}", e))?;
+    }
+    let server_url = resolve_server_url(server)?;
+    let reminders = ScheduleClient::new(&server_url)
+        .reminders(channel.as_deref())
+        .await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reminders)?);
+        return Ok(());
+    }
+    if reminders.is_empty() {
+        println!("No reminders");
+        return Ok(());
+    }
+    for reminder in &reminders {
+        print_reminder(reminder);
+    }
+    Ok(())
+}
+
+/// What `ailoop remind remove/skip/ack` does to a reminder.
+pub enum ReminderAction {
+    Remove,
+    Skip(u32),
+    Acknowledge,
+}
+
+/// Remove, skip or acknowledge a reminder.
+pub async fn handle_remind_action(
+    id: String,
+    action: ReminderAction,
+    server: String,
+    json: bool,
+) -> Result<()> {
+    let id = Uuid::parse_str(id.trim()).with_context(|| format!("Invalid id '{}'", id))?;
+    let server_url = resolve_server_url(server)?;
+    let client = ScheduleClient::new(&server_url);
+    let (reminder, done) = match action {
+        ReminderAction::Remove => (client.remove_reminder(id).await?, "Removed"),
+        ReminderAction::Skip(count) => (client.skip_reminder(id, count).await?, "Skipping"),
+        ReminderAction::Acknowledge => (client.acknowledge_reminder(id).await?, "Acknowledged"),
+    };
+    let Some(reminder) = reminder else {
+        bail!("No reminder with id {}", id);
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reminder)?);
+    } else {
+        println!("{}:", done);
+        print_reminder(&reminder);
+    }
+    Ok(())
+}
+
+/// `low`, `normal`, `high` or `urgent`.
+fn parse_priority(priority: &str) -> Result<NotificationPriority> {
+    serde_json::from_value(serde_json::Value::String(priority.trim().to_lowercase())).map_err(
+        |_| {
+            anyhow::anyhow!(
+                "Invalid priority '{}': use low, normal, high or urgent",
+                priority
+            )
+        },
+    )
+}
+
+fn print_reminder(reminder: &Reminder) {
+    let next = match reminder.next_due {
+        Some(due) => local_time(due),
+        None => "never".to_string(),
+    };
+    let mut state = Vec::new();
+    if reminder.unacknowledged > 0 {
+        state.push(format!("{} unacknowledged", reminder.unacknowledged));
+    }
+    if reminder.skip > 0 {
+        state.push(format!("skipping {}", reminder.skip));
+    }
+    if reminder.from_config {
+        state.push("config".to_string());
+    }
+    let state = if state.is_empty() {
+        String::new()
+    } else {
+        format!("  ({})", state.join(", "))
+    };
+    println!(
+        "{}  {}  next {}  [{}] {}{}",
+        reminder.id, reminder.schedule, next, reminder.channel, reminder.text, state
+    );
+}
+
+fn local_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_delays() {
+        assert_eq!(parse_delay("90s").unwrap(), Duration::seconds(90));
+        assert_eq!(parse_delay("30m").unwrap(), Duration::minutes(30));
+        assert_eq!(parse_delay(" 2h ").unwrap(), Duration::hours(2));
+        assert_eq!(parse_delay("1d").unwrap(), Duration::days(1));
+        assert!(parse_delay("30").is_err());
+        assert!(parse_delay("m").is_err());
+        assert!(parse_delay("30 minutes").is_err());
+    }
+
+    #[test]
+    fn parses_times() {
+        let utc = parse_at("2025-07-01T09:00:00Z").unwrap();
+        assert_eq!(utc.to_rfc3339(), "2025-07-01T09:00:00+00:00");
+        let local = parse_at("2025-07-01T09:00").unwrap();
+        assert_eq!(
+            local.with_timezone(&Local).format("%H:%M").to_string(),
+            "09:00"
+        );
+        assert!(parse_at("tomorrow").is_err());
+    }
+
+    #[test]
+    fn parses_priorities() {
+        assert!(matches!(
+            parse_priority("High").unwrap(),
+            NotificationPriority::High
+        ));
+        assert!(parse_priority("critical").is_err());
+    }
+
+    #[test]
+    fn at_and_in_are_exclusive() {
+        assert!(parse_when(None, None).unwrap().is_none());
+        assert!(parse_when(Some("2025-07-01T09:00".into()), Some("5m".into())).is_err());
+        let soon = parse_when(None, Some("5m".into())).unwrap().unwrap();
+        assert!(soon > Utc::now());
+    }
+}
@@ -0,0 +1,51 @@
+//! Handler for the `ailoop session` subcommands.
+
+use super::task_handlers::resolve_server_url;
+use ailoop_core::services::compression;
+use ailoop_core::SessionClient;
+use anyhow::{bail, Context, Result};
+use std::io::{IsTerminal, Write};
+
+/// Print or save the transcript of a forwarded agent session, zstd-compressed with `compress`.
+pub async fn handle_session_export(
+    session_id: String,
+    format: String,
+    output: Option<String>,
+    compress: bool,
+    server: String,
+) -> Result<()> {
+    if !matches!(format.as_str(), "md" | "json") {
+        bail!("Invalid format: {}. Must be md or json", format);
+    }
+    let server_url = resolve_server_url(server)?;
+    let transcript = SessionClient::new(&server_url)
+        .transcript(&session_id, &format)
+        .await?;
+
+    match output {
+        Some(path) => {
+            let data = if compress {
+                compression::compress(transcript.as_bytes())?
+            } else {
+                transcript.into_bytes()
+            };
+            std::fs::write(&path, data)
+                .with_context(|| format!("Failed to write transcript to {}", path))?;
+            println!("Transcript of session {} written to {}", session_id, path);
+        }
+        None if compress => write_compressed_to_stdout(transcript.as_bytes())?,
+        None => print!("{}", transcript),
+    }
+    Ok(())
+}
+
+/// Write `data` zstd-compressed to stdout, which must not be a terminal.
+pub(crate) fn write_compressed_to_stdout(data: &[u8]) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    if stdout.is_terminal() {
+        bail!("Not writing compressed output to a terminal; pass --output or pipe it");
+    }
+    stdout.write_all(&compression::compress(data)?)?;
+    stdout.flush()?;
+    Ok(())
+}
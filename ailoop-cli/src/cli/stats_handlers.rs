@@ -0,0 +1,127 @@
+//! `ailoop status --stats`: how prompts fare with the humans answering them, and whether they
+//! are answered within the `[[sla]]` rules.
+
+use super::task_handlers::resolve_server_url;
+use ailoop_core::models::{ResponseMetrics, ResponseSummary, SlaSummary};
+use ailoop_core::StatsClient;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+/// Print response latency, timeout rate and approval ratio per channel and per responder.
+pub async fn handle_status_stats(server: String, json: bool) -> Result<()> {
+    let server_url = resolve_server_url(server)?;
+    let metrics = StatsClient::new(&server_url)
+        .response_metrics(None)
+        .await
+        .context("Failed to fetch response metrics")?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&metrics)?);
+    } else {
+        print_metrics(&metrics);
+    }
+    Ok(())
+}
+
+fn print_metrics(metrics: &ResponseMetrics) {
+    if metrics.channels.is_empty() {
+        println!("No prompts yet");
+    } else {
+        print_table("CHANNEL", &metrics.channels, true);
+    }
+    if !metrics.responders.is_empty() {
+        println!();
+        print_table("RESPONDER", &metrics.responders, false);
+    }
+    if !metrics.slas.is_empty() {
+        println!();
+        print_slas(&metrics.slas);
+    }
+}
+
+fn print_slas(slas: &BTreeMap<String, SlaSummary>) {
+    let width = slas.keys().map(String::len).max().unwrap_or(0).max(3);
+    println!(
+        "{:<width$}  WITHIN  MET    BREACHED  OPEN   COMPLIANCE",
+        "SLA",
+        width = width
+    );
+    for (name, sla) in slas {
+        println!(
+            "{:<width$}  {:<6}  {:<5}  {:<8}  {:<5}  {}",
+            name,
+            duration(Some(sla.within_seconds as f64)),
+            sla.met,
+            sla.breached,
+            sla.open,
+            percent(sla.compliance),
+            width = width
+        );
+    }
+}
+
+fn print_table(label: &str, rows: &BTreeMap<String, ResponseSummary>, with_asked: bool) {
+    let width = rows
+        .keys()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max(label.len());
+    let asked = if with_asked { "ASKED  " } else { "" };
+    println!(
+        "{:<width$}  {}ANSWERED  TIMEOUTS  APPROVED  MEDIAN   MAX",
+        label,
+        asked,
+        width = width
+    );
+    for (name, summary) in rows {
+        let asked = if with_asked {
+            format!("{:<5}  ", summary.asked)
+        } else {
+            String::new()
+        };
+        println!(
+            "{:<width$}  {}{:<8}  {:<8}  {:<8}  {:<7}  {}",
+            name,
+            asked,
+            summary.answered,
+            percent(summary.timeout_rate),
+            percent(summary.approval_rate),
+            duration(summary.median_latency_seconds),
+            duration(summary.max_latency_seconds),
+            width = width
+        );
+    }
+}
+
+fn percent(rate: Option<f64>) -> String {
+    rate.map(|r| format!("{:.0}%", r * 100.0))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// `42s`, `3m10s` or `2h05m`.
+fn duration(seconds: Option<f64>) -> String {
+    let Some(seconds) = seconds else {
+        return "-".to_string();
+    };
+    let secs = seconds.round() as u64;
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_rates_and_durations() {
+        assert_eq!(percent(Some(0.25)), "25%");
+        assert_eq!(percent(None), "-");
+        assert_eq!(duration(Some(42.4)), "42s");
+        assert_eq!(duration(Some(190.0)), "3m10s");
+        assert_eq!(duration(Some(7500.0)), "2h05m");
+        assert_eq!(duration(None), "-");
+    }
+}
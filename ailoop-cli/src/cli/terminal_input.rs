@@ -1,9 +1,7 @@
 use ailoop_core::terminal::countdown::{CountdownRenderer, InputResult};
+use ailoop_server::server::terminal::{self, RawMode};
 use anyhow::{Context, Result};
-use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
-    terminal::{disable_raw_mode, enable_raw_mode},
-};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use std::io::{self, IsTerminal, Write};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -19,16 +17,17 @@ pub fn read_user_input_with_countdown(
         return read_user_input_fallback(timeout);
     }
 
-    if enable_raw_mode().is_err() {
+    let Ok(mut raw) = RawMode::enable() else {
         return read_user_input_fallback(timeout);
-    }
-
-    let result = read_with_countdown_inner(timeout, cancelled);
-    disable_raw_mode().ok();
-    result
+    };
+    read_with_countdown_inner(&mut raw, timeout, cancelled)
 }
 
-fn read_with_countdown_inner(timeout: Duration, cancelled: Arc<AtomicBool>) -> Result<InputResult> {
+fn read_with_countdown_inner(
+    raw: &mut RawMode,
+    timeout: Duration,
+    cancelled: Arc<AtomicBool>,
+) -> Result<InputResult> {
     let mut buffer = String::new();
     let mut countdown = CountdownRenderer::new(timeout);
     let mut countdown_enabled = true;
@@ -37,6 +36,12 @@ fn read_with_countdown_inner(timeout: Duration, cancelled: Arc<AtomicBool>) -> R
     io::stdout().flush().ok();
 
     loop {
+        if raw.take_resumed() {
+            // The shell wrote over the input line while the process was stopped.
+            print!("\r\n{}", buffer);
+            println!("\x1B[s");
+            io::stdout().flush().ok();
+        }
         if cancelled.load(Ordering::Relaxed) {
             print!("\r\x1B[2K\x1B[u");
             io::stdout().flush().ok();
@@ -67,6 +72,9 @@ fn read_with_countdown_inner(timeout: Duration, cancelled: Arc<AtomicBool>) -> R
                                 println!();
                                 return Ok(InputResult::Cancelled);
                             }
+                            KeyCode::Char(_) if terminal::is_suspend_key(&key_event) => {
+                                terminal::suspend();
+                            }
                             KeyCode::Char(c) => {
                                 buffer.push(c);
                                 print!("\x1B[u{}\x1B[s\x1B[B\r", c);
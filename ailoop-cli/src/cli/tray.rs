@@ -0,0 +1,305 @@
+//! `ailoop tray`: a server's pending prompts in the system tray (built with the `tray` feature).
+//!
+//! The icon turns orange while prompts wait, and its tooltip (on macOS also its title) gives
+//! their number. The menu lists them; authorizations can be approved or denied from there.
+//! Clicking the icon opens the server's web UI, where every kind of prompt can be answered.
+//! The server is polled every `--interval` seconds and again after each answer.
+
+use super::task_handlers::resolve_server_url;
+use ailoop_core::models::ResponseType;
+use ailoop_core::{PendingClient, PendingItemResponse, PendingListResponse};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tao::event::{Event, StartCause};
+use tao::event_loop::{ControlFlow, EventLoopBuilder, EventLoopProxy};
+use tray_icon::menu::{Menu, MenuEvent, MenuId, MenuItem, PredefinedMenuItem, Submenu};
+use tray_icon::{Icon, MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use uuid::Uuid;
+
+/// Longest prompt label shown in the menu, in characters.
+const MAX_LABEL_CHARS: usize = 60;
+const ICON_SIZE: u32 = 32;
+
+pub struct TrayOptions {
+    pub interval: Duration,
+    pub token: Option<String>,
+    /// Who answers, when the token carries no SSO identity.
+    pub responder: Option<String>,
+}
+
+/// What reaches the tray's event loop from other threads.
+enum TrayEvent {
+    Pending(Result<PendingListResponse, String>),
+    Answered(Result<(), String>),
+    Menu(MenuEvent),
+    Icon(TrayIconEvent),
+}
+
+/// What a menu entry does when chosen.
+#[derive(Debug, Clone, PartialEq)]
+enum Action {
+    Answer(Uuid, ResponseType),
+    OpenWebUi,
+    Quit,
+}
+
+/// Run the tray until Quit is chosen. Blocks the calling thread, which must be the main one.
+pub fn handle_tray(server: String, options: TrayOptions) -> Result<()> {
+    let server_url = resolve_server_url(server)?;
+    let client = Arc::new(PendingClient::new(&server_url).with_token(options.token.clone()));
+    let runtime = tokio::runtime::Handle::current();
+
+    let event_loop = EventLoopBuilder::<TrayEvent>::with_user_event().build();
+    let proxy = event_loop.create_proxy();
+    let menu_proxy = proxy.clone();
+    MenuEvent::set_event_handler(Some(move |event| {
+        let _ = menu_proxy.send_event(TrayEvent::Menu(event));
+    }));
+    let icon_proxy = proxy.clone();
+    TrayIconEvent::set_event_handler(Some(move |event| {
+        let _ = icon_proxy.send_event(TrayEvent::Icon(event));
+    }));
+    runtime.spawn(poll(Arc::clone(&client), options.interval, proxy.clone()));
+
+    let mut tray: Option<TrayIcon> = None;
+    let mut actions: HashMap<MenuId, Action> = HashMap::new();
+    let mut last_error: Option<String> = None;
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+        match event {
+            // The icon may only be created once the event loop runs (macOS).
+            Event::NewEvents(StartCause::Init) => {
+                let (menu, entries) = build_menu(&[], None);
+                actions = entries;
+                match TrayIconBuilder::new()
+                    .with_menu(Box::new(menu))
+                    .with_menu_on_left_click(false)
+                    .with_tooltip(format!("ailoop: {}", server_url))
+                    .with_icon(icon(false))
+                    .build()
+                {
+                    Ok(icon) => tray = Some(icon),
+                    Err(e) => {
+                        eprintln!("Cannot show the tray icon: {}", e);
+                        *control_flow = ControlFlow::Exit;
+                    }
+                }
+            }
+            Event::UserEvent(TrayEvent::Pending(result)) => {
+                let Some(tray) = &tray else { return };
+                let items = match result {
+                    Ok(list) => {
+                        last_error = None;
+                        list.items
+                    }
+                    Err(e) => {
+                        last_error = Some(e);
+                        Vec::new()
+                    }
+                };
+                let (menu, entries) = build_menu(&items, last_error.as_deref());
+                actions = entries;
+                tray.set_menu(Some(Box::new(menu)));
+                let _ = tray.set_icon(Some(icon(!items.is_empty())));
+                let _ = tray.set_tooltip(Some(tooltip(
+                    &server_url,
+                    items.len(),
+                    last_error.as_deref(),
+                )));
+                tray.set_title(badge(items.len()).as_deref());
+            }
+            Event::UserEvent(TrayEvent::Answered(result)) => {
+                if let Err(e) = result {
+                    eprintln!("Answer not sent: {}", e);
+                }
+                runtime.spawn(refresh(Arc::clone(&client), proxy.clone()));
+            }
+            Event::UserEvent(TrayEvent::Menu(event)) => match actions.get(event.id()) {
+                Some(Action::Answer(message_id, response_type)) => {
+                    let client = Arc::clone(&client);
+                    let proxy = proxy.clone();
+                    let (message_id, response_type) = (*message_id, response_type.clone());
+                    let responder = options.responder.clone();
+                    runtime.spawn(async move {
+                        let result = client
+                            .respond(message_id, None, response_type, responder.as_deref())
+                            .await
+                            .map_err(|e| e.to_string());
+                        let _ = proxy.send_event(TrayEvent::Answered(result));
+                    });
+                }
+                Some(Action::OpenWebUi) => open_in_browser(&server_url),
+                Some(Action::Quit) => *control_flow = ControlFlow::Exit,
+                None => {}
+            },
+            Event::UserEvent(TrayEvent::Icon(TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            })) => open_in_browser(&server_url),
+            _ => {}
+        }
+    })
+}
+
+/// Fetch the pending prompts every `interval`, until the event loop is gone.
+async fn poll(client: Arc<PendingClient>, interval: Duration, proxy: EventLoopProxy<TrayEvent>) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        if !refresh(Arc::clone(&client), proxy.clone()).await {
+            return;
+        }
+    }
+}
+
+/// Fetch the pending prompts once; false when the event loop is gone.
+async fn refresh(client: Arc<PendingClient>, proxy: EventLoopProxy<TrayEvent>) -> bool {
+    let result = client
+        .list_pending(None)
+        .await
+        .map_err(|e| format!("{:#}", e));
+    proxy.send_event(TrayEvent::Pending(result)).is_ok()
+}
+
+/// The menu for `items`, and what each of its entries does.
+fn build_menu(
+    items: &[PendingItemResponse],
+    error: Option<&str>,
+) -> (Menu, HashMap<MenuId, Action>) {
+    let menu = Menu::new();
+    let mut actions = HashMap::new();
+    let mut add = |item: &MenuItem, action: Action| {
+        actions.insert(item.id().clone(), action);
+    };
+    let header = match error {
+        Some(e) => format!("Server unreachable: {}", label(e)),
+        None => summary(items.len()),
+    };
+    let _ = menu.append(&MenuItem::new(header, false, None));
+    let _ = menu.append(&PredefinedMenuItem::separator());
+    for item in items {
+        let text = format!("[{}] {}", item.channel, label(&item.label));
+        if item.kind != "authorize" {
+            let _ = menu.append(&MenuItem::new(text, false, None));
+            continue;
+        }
+        let approve = MenuItem::new("Approve", true, None);
+        let deny = MenuItem::new("Deny", true, None);
+        add(
+            &approve,
+            Action::Answer(item.message_id, ResponseType::AuthorizationApproved),
+        );
+        add(
+            &deny,
+            Action::Answer(item.message_id, ResponseType::AuthorizationDenied),
+        );
+        if let Ok(submenu) = Submenu::with_items(text, true, &[&approve, &deny]) {
+            let _ = menu.append(&submenu);
+        }
+    }
+    if !items.is_empty() {
+        let _ = menu.append(&PredefinedMenuItem::separator());
+    }
+    let open = MenuItem::new("Open web UI", true, None);
+    let quit = MenuItem::new("Quit", true, None);
+    add(&open, Action::OpenWebUi);
+    add(&quit, Action::Quit);
+    let _ = menu.append(&open);
+    let _ = menu.append(&quit);
+    (menu, actions)
+}
+
+fn summary(count: usize) -> String {
+    match count {
+        0 => "No prompts pending".to_string(),
+        1 => "1 prompt pending".to_string(),
+        n => format!("{} prompts pending", n),
+    }
+}
+
+fn tooltip(server_url: &str, count: usize, error: Option<&str>) -> String {
+    match error {
+        Some(_) => format!("ailoop: {} unreachable", server_url),
+        None => format!("ailoop: {}", summary(count).to_lowercase()),
+    }
+}
+
+/// Number shown next to the icon: none when nothing waits, `9+` past nine.
+fn badge(count: usize) -> Option<String> {
+    match count {
+        0 => None,
+        1..=9 => Some(count.to_string()),
+        _ => Some("9+".to_string()),
+    }
+}
+
+/// `text` on one line, cut to [`MAX_LABEL_CHARS`] characters.
+fn label(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default().trim();
+    if line.chars().count() <= MAX_LABEL_CHARS {
+        return line.to_string();
+    }
+    let mut out: String = line.chars().take(MAX_LABEL_CHARS - 1).collect();
+    out.push('…');
+    out
+}
+
+/// A filled circle: orange while prompts wait, grey otherwise.
+fn icon(pending: bool) -> Icon {
+    let (r, g, b) = if pending {
+        (0xf5, 0x8c, 0x1e)
+    } else {
+        (0x8a, 0x8a, 0x8a)
+    };
+    let radius = ICON_SIZE as f32 / 2.0;
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let dx = x as f32 + 0.5 - radius;
+            let dy = y as f32 + 0.5 - radius;
+            let inside = dx * dx + dy * dy <= (radius - 1.0) * (radius - 1.0);
+            rgba.extend_from_slice(&[r, g, b, if inside { 0xff } else { 0 }]);
+        }
+    }
+    Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE).expect("icon buffer matches its size")
+}
+
+fn open_in_browser(url: &str) {
+    let opener = if cfg!(target_os = "macos") {
+        std::process::Command::new("open").arg(url).spawn()
+    } else if cfg!(windows) {
+        std::process::Command::new("cmd")
+            .args(["/C", "start", "", url])
+            .spawn()
+    } else {
+        std::process::Command::new("xdg-open").arg(url).spawn()
+    };
+    if let Err(e) = opener.context("Failed to open the browser") {
+        eprintln!("{:#}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_badge_and_labels() {
+        assert_eq!(badge(0), None);
+        assert_eq!(badge(3).as_deref(), Some("3"));
+        assert_eq!(badge(12).as_deref(), Some("9+"));
+        assert_eq!(summary(1), "1 prompt pending");
+        assert_eq!(
+            tooltip("http://localhost:8080", 2, None),
+            "ailoop: 2 prompts pending"
+        );
+
+        assert_eq!(label("deploy v2\nto prod"), "deploy v2");
+        let long = label(&"x".repeat(100));
+        assert_eq!(long.chars().count(), MAX_LABEL_CHARS);
+        assert!(long.ends_with('…'));
+    }
+}
@@ -39,6 +39,33 @@ fn opt_named(args: &HashMap<String, ArgValue>, key: &str) -> Option<String> {
     }
 }
 
+/// `--channel`, else the project file's channel, else `public`.
+fn channel_name(args: &HashMap<String, ArgValue>) -> String {
+    opt_named(args, "channel")
+        .or_else(|| cli::project::current().channel.clone())
+        .unwrap_or_else(|| "public".to_string())
+}
+
+/// `--server`, else the project file's server; empty when neither is set.
+fn server_url(args: &HashMap<String, ArgValue>) -> String {
+    opt_named(args, "server")
+        .or_else(|| cli::project::current().server.clone())
+        .unwrap_or_default()
+}
+
+/// `--timeout`, else the project file's timeout, else `default`.
+fn timeout_secs(args: &HashMap<String, ArgValue>, default: u32) -> u32 {
+    opt_named(args, "timeout")
+        .and_then(|t| t.parse().ok())
+        .or(cli::project::current().timeout_seconds)
+        .unwrap_or(default)
+}
+
+/// Channels from `--channels`, or else `--channel` (which also takes a comma-separated list).
+fn channel_targets(args: &HashMap<String, ArgValue>) -> Vec<String> {
+    cli::fanout::channel_targets(&channel_name(args), opt_named(args, "channels").as_deref())
+}
+
 // ── arg spec helpers ───────────────────────────────────────────────────────────
 
 fn opt_arg(name: &'static str, help: &'static str) -> ArgSpec {
@@ -105,6 +132,22 @@ fn req_pos_arg(name: &'static str, help: &'static str) -> ArgSpec {
     }
 }
 
+fn opt_pos_arg(name: &'static str, help: &'static str) -> ArgSpec {
+    ArgSpec {
+        name,
+        kind: ArgKind::Positional,
+        short: None,
+        long: None,
+        value_type: ArgValueType::String,
+        cardinality: Cardinality::Optional,
+        default: None,
+        conflicts_with: vec![],
+        requires: vec![],
+        help,
+        ..Default::default()
+    }
+}
+
 fn flag_arg(name: &'static str, help: &'static str) -> ArgSpec {
     ArgSpec {
         name,
@@ -122,17 +165,72 @@ fn flag_arg(name: &'static str, help: &'static str) -> ArgSpec {
 }
 
 fn channel_arg() -> ArgSpec {
-    opt_arg_default("channel", "public", "Channel name")
+    opt_arg(
+        "channel",
+        "Channel name (default: project file, else public)",
+    )
+}
+
+fn channels_arg() -> ArgSpec {
+    opt_arg(
+        "channels",
+        "Send to several channels: comma-separated names or patterns (ops,dev,*-prod)",
+    )
 }
 
 fn server_arg() -> ArgSpec {
-    opt_arg("server", "Server URL for remote operation")
+    opt_arg(
+        "server",
+        "Server URL for remote operation (default: project file)",
+    )
 }
 
 fn json_arg() -> ArgSpec {
     flag_arg("json", "Output in JSON format")
 }
 
+fn agent_name_arg() -> ArgSpec {
+    opt_arg(
+        "agent-name",
+        "Name shown as the sender (default: AILOOP_AGENT_NAME)",
+    )
+}
+
+fn dry_run_arg() -> ArgSpec {
+    flag_arg(
+        "dry-run",
+        "Check the channel and server and show what would be sent; answer with a canned response",
+    )
+}
+
+/// Apply `--agent-name` to the messages this command sends.
+fn apply_agent_name(args: &HashMap<String, ArgValue>) {
+    if let Some(name) = opt_named(args, "agent-name") {
+        ailoop_core::client::set_agent_name(name);
+    }
+}
+
+fn data_dir_arg() -> ArgSpec {
+    opt_arg(
+        "data-dir",
+        "Directory for state and cache files (default: AILOOP_DATA_DIR, else XDG dirs)",
+    )
+}
+
+/// Apply `--data-dir` to the files this command keeps.
+fn apply_data_dir(args: &HashMap<String, ArgValue>) {
+    if let Some(dir) = opt_named(args, "data-dir") {
+        ailoop_core::paths::set_data_dir(dir);
+    }
+}
+
+fn outbox_arg() -> ArgSpec {
+    flag_arg(
+        "outbox",
+        "Spool messages to the local outbox when the server is unreachable",
+    )
+}
+
 // ── command factories ──────────────────────────────────────────────────────────
 
 fn ask_command() -> Command {
@@ -148,13 +246,26 @@ fn ask_command() -> Command {
                     "JSON-encoded decision payload (decision_id, summary, options, ...)",
                 ),
                 channel_arg(),
-                opt_arg_default(
+                opt_arg(
                     "timeout",
-                    "0",
-                    "Response timeout in seconds (0 = use payload timeout)",
+                    "Response timeout in seconds (default: project file, else 0 = payload timeout)",
                 ),
                 server_arg(),
                 json_arg(),
+                agent_name_arg(),
+                flag_arg(
+                    "expect-file",
+                    "Ask the responder to attach a file; its path and URL are printed with the answer",
+                ),
+                opt_arg(
+                    "follow-up",
+                    "Id of an earlier prompt this one follows up on; shown with its thread",
+                ),
+                opt_arg(
+                    "assign",
+                    "Team member to put the prompt to; others get it after [assignment] fallback_seconds",
+                ),
+                dry_run_arg(),
             ],
             ..Default::default()
         }),
@@ -163,12 +274,28 @@ fn ask_command() -> Command {
         expose_chat: true,
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
+                apply_agent_name(&args);
                 let payload = named(&args, "payload");
-                let channel = named_or(&args, "channel", "public");
-                let timeout: u32 = named_or(&args, "timeout", "0").parse().unwrap_or(0);
-                let server = named(&args, "server");
+                let channel = channel_name(&args);
+                let timeout = timeout_secs(&args, 0);
+                let server = server_url(&args);
                 let json = flag(&args, "json");
-                cli::handlers::handle_ask(payload, channel, timeout, server, json).await
+                let expect_file = flag(&args, "expect-file");
+                let follow_up = opt_named(&args, "follow-up");
+                let assign = opt_named(&args, "assign");
+                let dry_run = flag(&args, "dry-run");
+                cli::handlers::handle_ask(
+                    payload,
+                    channel,
+                    timeout,
+                    server,
+                    json,
+                    expect_file,
+                    follow_up,
+                    assign,
+                    dry_run,
+                )
+                .await
             })
         }),
     }
@@ -184,7 +311,10 @@ fn authorize_command() -> Command {
             args: vec![
                 req_pos_arg("action", "Description of action requiring authorization"),
                 channel_arg(),
-                opt_arg_default("timeout", "300", "Authorization timeout in seconds"),
+                opt_arg(
+                    "timeout",
+                    "Authorization timeout in seconds (default: project file, else 300)",
+                ),
                 server_arg(),
                 json_arg(),
                 opt_arg_default(
@@ -192,6 +322,13 @@ fn authorize_command() -> Command {
                     "yes",
                     "Default decision when ENTER is pressed (yes or no)",
                 ),
+                agent_name_arg(),
+                dry_run_arg(),
+                opt_arg_default(
+                    "config",
+                    "~/.config/ailoop/config.toml",
+                    "Path to config file",
+                ),
             ],
             ..Default::default()
         }),
@@ -200,14 +337,29 @@ fn authorize_command() -> Command {
         expose_chat: true,
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
+                apply_agent_name(&args);
                 let action = named(&args, "action");
-                let channel = named_or(&args, "channel", "public");
-                let timeout: u32 = named_or(&args, "timeout", "300").parse().unwrap_or(300);
-                let server = named(&args, "server");
+                let channel = channel_name(&args);
+                let timeout = timeout_secs(
+                    &args,
+                    ailoop_core::models::DEFAULT_AUTHORIZATION_TIMEOUT_SECS,
+                );
+                let server = server_url(&args);
                 let json = flag(&args, "json");
                 let default_yes = named_or(&args, "default", "yes") != "no";
-                cli::handlers::handle_authorize(action, channel, timeout, server, json, default_yes)
-                    .await
+                let dry_run = flag(&args, "dry-run");
+                let config = named_or(&args, "config", "~/.config/ailoop/config.toml");
+                cli::handlers::handle_authorize(
+                    action,
+                    channel,
+                    timeout,
+                    server,
+                    json,
+                    default_yes,
+                    dry_run,
+                    &config,
+                )
+                .await
             })
         }),
     }
@@ -223,12 +375,27 @@ fn say_command() -> Command {
             args: vec![
                 req_pos_arg("message", "Notification message text"),
                 channel_arg(),
+                channels_arg(),
                 opt_arg_default(
                     "priority",
                     "normal",
                     "Message priority (normal, high, critical)",
                 ),
                 server_arg(),
+                outbox_arg(),
+                opt_arg(
+                    "at",
+                    "Send later, at this time (2025-07-01T09:00 local, or RFC 3339)",
+                ),
+                opt_arg("in", "Send later, after this delay (30s, 30m, 2h, 1d)"),
+                opt_arg(
+                    "attach",
+                    "File or URL to send with the notification (uploaded in server mode)",
+                ),
+                json_arg(),
+                agent_name_arg(),
+                data_dir_arg(),
+                dry_run_arg(),
             ],
             ..Default::default()
         }),
@@ -237,11 +404,118 @@ fn say_command() -> Command {
         expose_chat: true,
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
+                apply_agent_name(&args);
+                apply_data_dir(&args);
                 let message = named(&args, "message");
-                let channel = named_or(&args, "channel", "public");
+                let channels = channel_targets(&args);
                 let priority = named_or(&args, "priority", "normal");
-                let server = named(&args, "server");
-                cli::handlers::handle_say(message, channel, priority, server).await
+                let server = server_url(&args);
+                let outbox = flag(&args, "outbox");
+                let at = opt_named(&args, "at");
+                let delay = opt_named(&args, "in");
+                let attach = opt_named(&args, "attach");
+                let json = flag(&args, "json");
+                let dry_run = flag(&args, "dry-run");
+                cli::handlers::handle_say(
+                    message, channels, priority, server, outbox, at, delay, attach, json, dry_run,
+                )
+                .await
+            })
+        }),
+    }
+}
+
+fn status_command() -> Command {
+    Command {
+        id: "status".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Show what the agent is working on as a transient indicator",
+            syntax: Some("status <text> | status --stats | status --unread [--as NAME]"),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                opt_pos_arg("text", "What the agent is doing, such as \"running tests\""),
+                channel_arg(),
+                server_arg(),
+                agent_name_arg(),
+                flag_arg(
+                    "stats",
+                    "Show response latency, timeouts and approvals per channel and responder",
+                ),
+                flag_arg(
+                    "unread",
+                    "Show agent notifications you have not read, per channel",
+                ),
+                opt_arg(
+                    "as",
+                    "Whose unread notifications to show (default: $USER; ignored with an SSO token)",
+                ),
+                opt_arg("token", "API token (default: AILOOP_TOKEN)"),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: true,
+        expose_chat: true,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let server = server_url(&args);
+                if flag(&args, "stats") {
+                    return cli::stats_handlers::handle_status_stats(server, flag(&args, "json"))
+                        .await;
+                }
+                if flag(&args, "unread") {
+                    return cli::me_handlers::handle_status_unread(
+                        opt_named(&args, "as"),
+                        opt_named(&args, "token"),
+                        server,
+                        flag(&args, "json"),
+                    )
+                    .await;
+                }
+                apply_agent_name(&args);
+                let Some(text) = opt_named(&args, "text") else {
+                    anyhow::bail!(
+                        "Give the status text, --stats for response metrics or --unread for unread notifications"
+                    );
+                };
+                let channel = channel_name(&args);
+                cli::handlers::handle_status(text, channel, server).await
+            })
+        }),
+    }
+}
+
+fn chat_command() -> Command {
+    Command {
+        id: "chat".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Hold a free-form conversation with the human on a channel",
+            syntax: Some("chat [--message <text>]"),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                opt_arg(
+                    "message",
+                    "Opening line (default: the first line read from stdin)",
+                ),
+                channel_arg(),
+                server_arg(),
+                json_arg(),
+                agent_name_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                apply_agent_name(&args);
+                let message = opt_named(&args, "message");
+                let channel = channel_name(&args);
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::chat_handlers::handle_chat(message, channel, server, json).await
             })
         }),
     }
@@ -252,7 +526,9 @@ fn serve_command() -> Command {
         id: "serve".into(),
         spec: Arc::new(CommandSpec {
             summary: "Start ailoop server for multi-agent communication",
-            syntax: Some("serve [--host HOST] [--port PORT]"),
+            syntax: Some(
+                "serve [--host HOST] [--port PORT] [--listen ENDPOINT] [--stdio] [--strict] [--echo]",
+            ),
             category: Some("server"),
             args: vec![
                 opt_arg_default("host", "127.0.0.1", "Server bind address"),
@@ -262,6 +538,31 @@ fn serve_command() -> Command {
                     "web",
                     "Enable the embedded web UI on the HTTP API port (port+1)",
                 ),
+                opt_arg(
+                    "ingest-dir",
+                    "Ingest NDJSON message files dropped into this directory",
+                ),
+                opt_arg(
+                    "listen",
+                    "Listen on ENDPOINT instead of host:port (unix:PATH, pipe:NAME, launchd:NAME)",
+                ),
+                flag_arg(
+                    "stdio",
+                    "Speak the JSON protocol over stdin/stdout instead of listening on a port",
+                ),
+                flag_arg(
+                    "strict",
+                    "Reject messages with unknown fields, missing timeouts or priorities",
+                ),
+                flag_arg(
+                    "force",
+                    "Start even if another server holds the data directory lock",
+                ),
+                flag_arg(
+                    "echo",
+                    "Answer every prompt at once with canned answers from [echo] and log all messages",
+                ),
+                data_dir_arg(),
             ],
             ..Default::default()
         }),
@@ -270,11 +571,21 @@ fn serve_command() -> Command {
         expose_chat: false,
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
+                apply_data_dir(&args);
                 let host = named_or(&args, "host", "127.0.0.1");
                 let port: u16 = named_or(&args, "port", "8080").parse().unwrap_or(8080);
-                let channel = named_or(&args, "channel", "public");
+                let channel = channel_name(&args);
                 let web = flag(&args, "web");
-                cli::handlers::handle_serve(host, port, channel, web).await
+                let ingest_dir = opt_named(&args, "ingest-dir");
+                let stdio = flag(&args, "stdio");
+                let listen = opt_named(&args, "listen");
+                let strict = flag(&args, "strict");
+                let force = flag(&args, "force");
+                let echo = flag(&args, "echo");
+                cli::handlers::handle_serve(
+                    host, port, channel, web, ingest_dir, stdio, listen, strict, force, echo,
+                )
+                .await
             })
         }),
     }
@@ -285,10 +596,18 @@ fn config_command() -> Command {
         id: "config".into(),
         spec: Arc::new(CommandSpec {
             summary: "Configure ailoop settings",
-            syntax: Some("config [--init] [--config-file PATH]"),
+            syntax: Some("config [migrate [--dry-run]] [--init] [--config-file PATH]"),
             category: Some("configuration"),
             args: vec![
+                opt_pos_arg(
+                    "action",
+                    "`migrate` to upgrade an older config file to the current schema",
+                ),
                 flag_arg("init", "Start interactive configuration setup"),
+                flag_arg(
+                    "dry-run",
+                    "With migrate, show the changes without writing them",
+                ),
                 opt_arg_default(
                     "config-file",
                     "~/.config/ailoop/config.toml",
@@ -304,6 +623,17 @@ fn config_command() -> Command {
             Box::pin(async move {
                 let init = flag(&args, "init");
                 let config_file = named_or(&args, "config-file", "~/.config/ailoop/config.toml");
+                match opt_named(&args, "action").as_deref() {
+                    Some("migrate") => {
+                        return cli::handlers::handle_config_migrate(
+                            config_file,
+                            flag(&args, "dry-run"),
+                        )
+                        .await
+                    }
+                    Some(other) => anyhow::bail!("Unknown config action '{}'", other),
+                    None => {}
+                }
                 if init {
                     cli::handlers::handle_config_init(config_file).await
                 } else {
@@ -314,6 +644,68 @@ fn config_command() -> Command {
     }
 }
 
+fn init_command() -> Command {
+    Command {
+        id: "init".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Write a .ailoop.toml project file with this repository's defaults",
+            syntax: Some("init [--channel NAME] [--server URL] [--timeout SECS] [--force]"),
+            category: Some("configuration"),
+            args: vec![
+                opt_arg("channel", "Project channel (default: directory name)"),
+                opt_arg("server", "Server URL (default: http://127.0.0.1:8080)"),
+                opt_arg("timeout", "Prompt timeout in seconds (default: 300)"),
+                flag_arg("force", "Replace an existing .ailoop.toml"),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let timeout = match opt_named(&args, "timeout") {
+                    Some(t) => Some(
+                        t.parse()
+                            .map_err(|_| anyhow::anyhow!("Invalid --timeout '{}'", t))?,
+                    ),
+                    None => None,
+                };
+                cli::project::handle_init(
+                    opt_named(&args, "channel"),
+                    opt_named(&args, "server"),
+                    timeout,
+                    flag(&args, "force"),
+                )
+            })
+        }),
+    }
+}
+
+fn guide_command() -> Command {
+    Command {
+        id: "guide".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Read long-form guide pages (protocols, providers, files)",
+            syntax: Some("guide [topic] [--man]"),
+            category: Some("configuration"),
+            args: vec![
+                opt_pos_arg("topic", "Page to show; lists the pages when left out"),
+                flag_arg("man", "Print the page as a roff man page"),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                cli::guide::handle_guide(opt_named(&args, "topic"), flag(&args, "man"))
+            })
+        }),
+    }
+}
+
 fn image_command() -> Command {
     Command {
         id: "image".into(),
@@ -324,7 +716,13 @@ fn image_command() -> Command {
             args: vec![
                 req_pos_arg("image_path", "Image file path or URL"),
                 channel_arg(),
+                flag_arg("open", "Open the image in the local viewer (direct mode)"),
+                opt_arg(
+                    "token",
+                    "API token for uploading the image (default: AILOOP_TOKEN)",
+                ),
                 server_arg(),
+                json_arg(),
             ],
             ..Default::default()
         }),
@@ -334,9 +732,12 @@ fn image_command() -> Command {
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
                 let image_path = named(&args, "image_path");
-                let channel = named_or(&args, "channel", "public");
-                let server = named(&args, "server");
-                cli::handlers::handle_image(image_path, channel, server).await
+                let channel = channel_name(&args);
+                let server = server_url(&args);
+                let open = flag(&args, "open");
+                let token = opt_named(&args, "token");
+                let json = flag(&args, "json");
+                cli::handlers::handle_image(image_path, channel, server, open, token, json).await
             })
         }),
     }
@@ -352,7 +753,18 @@ fn navigate_command() -> Command {
             args: vec![
                 req_pos_arg("url", "URL to navigate to"),
                 channel_arg(),
+                channels_arg(),
+                opt_arg(
+                    "timeout",
+                    "Seconds to wait for the URL to be opened (default: project file, else 0 = server default)",
+                ),
+                flag_arg(
+                    "wait",
+                    "Wait until the URL is opened or not; exit 1 when it is not opened",
+                ),
                 server_arg(),
+                json_arg(),
+                agent_name_arg(),
             ],
             ..Default::default()
         }),
@@ -361,10 +773,14 @@ fn navigate_command() -> Command {
         expose_chat: true,
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
+                apply_agent_name(&args);
                 let url = named(&args, "url");
-                let channel = named_or(&args, "channel", "public");
-                let server = named(&args, "server");
-                cli::handlers::handle_navigate(url, channel, server).await
+                let channels = channel_targets(&args);
+                let timeout = timeout_secs(&args, 0);
+                let wait = flag(&args, "wait");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::handlers::handle_navigate(url, channels, timeout, wait, server, json).await
             })
         }),
     }
@@ -393,6 +809,37 @@ fn forward_command() -> Command {
                 opt_arg("output", "Output file path (for file transport)"),
                 opt_arg("client-id", "Client ID for tracking"),
                 opt_arg("input", "Input file path (if not reading from stdin)"),
+                opt_arg(
+                    "coalesce",
+                    "Merge assistant deltas, sending at most every SECS seconds (0 = off)",
+                ),
+                opt_arg(
+                    "quarantine",
+                    "Append input lines that fail to parse to this file",
+                ),
+                flag_arg(
+                    "multiline",
+                    "Reassemble JSON objects that span several lines",
+                ),
+                opt_arg(
+                    "since",
+                    "Skip events before this time (RFC 3339 or YYYY-MM-DD)",
+                ),
+                opt_arg(
+                    "until",
+                    "Skip events at or after this time (RFC 3339 or YYYY-MM-DD)",
+                ),
+                flag_arg(
+                    "dry-run",
+                    "Parse and count events per type without sending anything",
+                ),
+                flag_arg(
+                    "resume",
+                    "Resume --input from this client's checkpoint (requires --client-id)",
+                ),
+                outbox_arg(),
+                agent_name_arg(),
+                data_dir_arg(),
             ],
             ..Default::default()
         }),
@@ -401,7 +848,9 @@ fn forward_command() -> Command {
         expose_chat: false,
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
-                let channel = named_or(&args, "channel", "public");
+                apply_agent_name(&args);
+                apply_data_dir(&args);
+                let channel = channel_name(&args);
                 let agent_type = opt_named(&args, "agent-type");
                 let format = named_or(&args, "format", "stream-json");
                 let transport = named_or(&args, "transport", "websocket");
@@ -409,8 +858,17 @@ fn forward_command() -> Command {
                 let output = opt_named(&args, "output");
                 let client_id = opt_named(&args, "client-id");
                 let input = opt_named(&args, "input");
+                let coalesce = opt_named(&args, "coalesce");
+                let quarantine = opt_named(&args, "quarantine");
+                let multiline = flag(&args, "multiline");
+                let since = opt_named(&args, "since");
+                let until = opt_named(&args, "until");
+                let dry_run = flag(&args, "dry-run");
+                let resume = flag(&args, "resume");
+                let outbox = flag(&args, "outbox");
                 cli::handlers::handle_forward(
                     channel, agent_type, format, transport, url, output, client_id, input,
+                    coalesce, quarantine, multiline, since, until, dry_run, resume, outbox,
                 )
                 .await
             })
@@ -423,11 +881,29 @@ fn queue_command() -> Command {
         id: "queue".into(),
         spec: Arc::new(CommandSpec {
             summary: "Inspect the human prompt queue",
-            syntax: Some("queue [--channel CHANNEL]"),
+            syntax: Some(
+                "queue [list|drop <id>|clear|claim <id>|release <id>] [--channel CHANNEL]",
+            ),
             category: Some("human-in-the-loop"),
             args: vec![
+                opt_pos_arg(
+                    "action",
+                    "`list`, `drop`, `clear`, `claim` or `release`; shows the prompts put to humans when left out",
+                ),
+                opt_pos_arg("id", "Message id for `drop`, `claim` and `release`"),
                 server_arg(),
-                opt_arg("channel", "Filter by channel name"),
+                opt_arg(
+                    "channel",
+                    "Filter by channel name; the channel to empty for `clear`",
+                ),
+                opt_arg(
+                    "token",
+                    "API token with the admin role for `drop` and `clear`, the responder role for `claim` and `release` (default: AILOOP_TOKEN)",
+                ),
+                opt_arg(
+                    "as",
+                    "Responder name for `claim` and `release` (default: $USER; ignored with an SSO token)",
+                ),
                 json_arg(),
             ],
             ..Default::default()
@@ -437,81 +913,249 @@ fn queue_command() -> Command {
         expose_chat: false,
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
-                let server = named(&args, "server");
+                let server = server_url(&args);
                 let channel = opt_named(&args, "channel");
+                let token = opt_named(&args, "token");
                 let json = flag(&args, "json");
-                cli::queue_handlers::handle_queue(server, channel, json).await
+                let id = |action: &str| {
+                    opt_named(&args, "id")
+                        .ok_or_else(|| anyhow::anyhow!("queue {} needs a message id", action))
+                };
+                match opt_named(&args, "action").as_deref() {
+                    None => cli::queue_handlers::handle_queue(server, channel, json).await,
+                    Some("list") => {
+                        cli::queue_handlers::handle_queue_list(channel, server, json).await
+                    }
+                    Some("drop") => {
+                        cli::queue_handlers::handle_queue_drop(id("drop")?, token, server, json)
+                            .await
+                    }
+                    Some("clear") => {
+                        let Some(channel) = channel else {
+                            anyhow::bail!("queue clear needs --channel");
+                        };
+                        cli::queue_handlers::handle_queue_clear(channel, token, server, json)
+                            .await
+                    }
+                    Some(action @ ("claim" | "release")) => {
+                        cli::queue_handlers::handle_queue_claim(
+                            id(action)?,
+                            opt_named(&args, "as"),
+                            action == "release",
+                            token,
+                            server,
+                            json,
+                        )
+                        .await
+                    }
+                    Some(other) => anyhow::bail!("Unknown queue action '{}'", other),
+                }
             })
         }),
     }
 }
 
-// ── task subcommands ───────────────────────────────────────────────────────────
-
-fn task_create_command() -> Command {
+fn logs_command() -> Command {
     Command {
-        id: "create".into(),
+        id: "logs".into(),
         spec: Arc::new(CommandSpec {
-            summary: "Create a new task",
-            syntax: Some("task create <title> --description DESC"),
-            category: Some("task"),
+            summary: "Show the server's event log; needs an admin token when auth is on",
+            syntax: Some("logs [--since TIME] [--event TYPE] [--follow]"),
+            category: Some("server"),
             args: vec![
-                req_pos_arg("title", "Task title"),
-                req_opt_arg("description", "Detailed task description"),
-                channel_arg(),
+                opt_arg(
+                    "since",
+                    "Only events after this time (RFC 3339 or YYYY-MM-DD)",
+                ),
+                opt_arg(
+                    "event",
+                    "Only events of this type: message, prompt, delivery or break_glass",
+                ),
+                opt_arg_default("limit", "20", "Show at most this many of the newest events"),
+                flag_arg("follow", "Keep printing new events until interrupted"),
+                opt_arg(
+                    "token",
+                    "API token with the admin role (default: AILOOP_TOKEN)",
+                ),
                 server_arg(),
-                json_arg(),
+                flag_arg("json", "Print each event as its JSON line"),
             ],
             ..Default::default()
         }),
         validator: None,
-        expose_mcp: true,
-        expose_chat: true,
+        expose_mcp: false,
+        expose_chat: false,
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
-                let title = named(&args, "title");
-                let description = named(&args, "description");
-                let channel = named_or(&args, "channel", "public");
-                let server = named(&args, "server");
-                let json = flag(&args, "json");
-                cli::task_handlers::handle_task_create(title, description, channel, server, json)
-                    .await
+                let since = opt_named(&args, "since")
+                    .map(|s| cli::forward::parse_time_bound(&s))
+                    .transpose()?;
+                let options = cli::logs_handlers::LogsOptions {
+                    since,
+                    event: opt_named(&args, "event"),
+                    limit: named_or(&args, "limit", "20").parse().unwrap_or(20),
+                    follow: flag(&args, "follow"),
+                    token: opt_named(&args, "token"),
+                    json: flag(&args, "json"),
+                };
+                let server = server_url(&args);
+                cli::logs_handlers::handle_logs(server, options).await
             })
         }),
     }
 }
 
-fn task_list_command() -> Command {
+#[cfg(feature = "tray")]
+fn tray_command() -> Command {
     Command {
-        id: "list".into(),
+        id: "tray".into(),
         spec: Arc::new(CommandSpec {
-            summary: "List all tasks",
-            syntax: Some("task list [--state STATE]"),
-            category: Some("task"),
+            summary: "Show a server's pending prompts in the system tray",
+            syntax: Some("tray [--interval 10] [--responder NAME]"),
+            category: Some("human-in-the-loop"),
             args: vec![
-                channel_arg(),
-                opt_arg("state", "Filter by task state (pending, done, abandoned)"),
+                opt_arg_default("interval", "10", "Seconds between checks of the server"),
+                opt_arg(
+                    "token",
+                    "API token with the responder role (default: AILOOP_TOKEN)",
+                ),
+                opt_arg(
+                    "responder",
+                    "Name answers are given as, when the token carries no SSO identity",
+                ),
                 server_arg(),
-                json_arg(),
             ],
             ..Default::default()
         }),
         validator: None,
-        expose_mcp: true,
-        expose_chat: true,
+        expose_mcp: false,
+        expose_chat: false,
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
-                let channel = named_or(&args, "channel", "public");
-                let state = opt_named(&args, "state");
-                let server = named(&args, "server");
-                let json = flag(&args, "json");
-                cli::task_handlers::handle_task_list(channel, state, server, json).await
+                let seconds: u64 = named_or(&args, "interval", "10").parse().unwrap_or(10);
+                let options = cli::tray::TrayOptions {
+                    interval: std::time::Duration::from_secs(seconds.max(1)),
+                    token: opt_named(&args, "token").or_else(|| std::env::var("AILOOP_TOKEN").ok()),
+                    responder: opt_named(&args, "responder"),
+                };
+                let server = server_url(&args);
+                cli::tray::handle_tray(server, options)
             })
         }),
     }
 }
 
-fn task_show_command() -> Command {
+fn break_glass_command() -> Command {
+    Command {
+        id: "break-glass".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Approve an authorization nobody answered, with a break-glass token",
+            syntax: Some("break-glass <id> --justification TEXT"),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                req_pos_arg("id", "Id of the pending authorization"),
+                req_opt_arg(
+                    "justification",
+                    "Why the action is approved without its usual responders",
+                ),
+                opt_arg(
+                    "token",
+                    "Break-glass token (default: AILOOP_BREAK_GLASS_TOKEN)",
+                ),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let id = named(&args, "id");
+                let justification = named(&args, "justification");
+                let token = opt_named(&args, "token");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::break_glass_handlers::handle_break_glass(
+                    id,
+                    justification,
+                    token,
+                    server,
+                    json,
+                )
+                .await
+            })
+        }),
+    }
+}
+
+// ── task subcommands ───────────────────────────────────────────────────────────
+
+fn task_create_command() -> Command {
+    Command {
+        id: "create".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Create a new task",
+            syntax: Some("task create <title> --description DESC"),
+            category: Some("task"),
+            args: vec![
+                req_pos_arg("title", "Task title"),
+                req_opt_arg("description", "Detailed task description"),
+                channel_arg(),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: true,
+        expose_chat: true,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let title = named(&args, "title");
+                let description = named(&args, "description");
+                let channel = channel_name(&args);
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::task_handlers::handle_task_create(title, description, channel, server, json)
+                    .await
+            })
+        }),
+    }
+}
+
+fn task_list_command() -> Command {
+    Command {
+        id: "list".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "List all tasks",
+            syntax: Some("task list [--state STATE]"),
+            category: Some("task"),
+            args: vec![
+                channel_arg(),
+                opt_arg("state", "Filter by task state (pending, done, abandoned)"),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: true,
+        expose_chat: true,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let channel = channel_name(&args);
+                let state = opt_named(&args, "state");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::task_handlers::handle_task_list(channel, state, server, json).await
+            })
+        }),
+    }
+}
+
+fn task_show_command() -> Command {
     Command {
         id: "show".into(),
         spec: Arc::new(CommandSpec {
@@ -532,8 +1176,8 @@ fn task_show_command() -> Command {
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
                 let task_id = named(&args, "task_id");
-                let channel = named_or(&args, "channel", "public");
-                let server = named(&args, "server");
+                let channel = channel_name(&args);
+                let server = server_url(&args);
                 let json = flag(&args, "json");
                 cli::task_handlers::handle_task_show(task_id, channel, server, json).await
             })
@@ -564,8 +1208,8 @@ fn task_update_command() -> Command {
             Box::pin(async move {
                 let task_id = named(&args, "task_id");
                 let state = named(&args, "state");
-                let channel = named_or(&args, "channel", "public");
-                let server = named(&args, "server");
+                let channel = channel_name(&args);
+                let server = server_url(&args);
                 let json = flag(&args, "json");
                 cli::task_handlers::handle_task_update(task_id, state, channel, server, json).await
             })
@@ -588,8 +1232,8 @@ fn task_ready_command() -> Command {
         expose_chat: true,
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
-                let channel = named_or(&args, "channel", "public");
-                let server = named(&args, "server");
+                let channel = channel_name(&args);
+                let server = server_url(&args);
                 let json = flag(&args, "json");
                 cli::task_handlers::handle_task_ready(channel, server, json).await
             })
@@ -612,8 +1256,8 @@ fn task_blocked_command() -> Command {
         expose_chat: true,
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
-                let channel = named_or(&args, "channel", "public");
-                let server = named(&args, "server");
+                let channel = channel_name(&args);
+                let server = server_url(&args);
                 let json = flag(&args, "json");
                 cli::task_handlers::handle_task_blocked(channel, server, json).await
             })
@@ -649,8 +1293,8 @@ fn task_dep_add_command() -> Command {
                 let child_id = named(&args, "child_id");
                 let parent_id = named(&args, "parent_id");
                 let dependency_type = named_or(&args, "dependency-type", "blocks");
-                let channel = named_or(&args, "channel", "public");
-                let server = named(&args, "server");
+                let channel = channel_name(&args);
+                let server = server_url(&args);
                 cli::task_handlers::handle_dep_add(
                     child_id,
                     parent_id,
@@ -686,8 +1330,8 @@ fn task_dep_remove_command() -> Command {
             Box::pin(async move {
                 let child_id = named(&args, "child_id");
                 let parent_id = named(&args, "parent_id");
-                let channel = named_or(&args, "channel", "public");
-                let server = named(&args, "server");
+                let channel = channel_name(&args);
+                let server = server_url(&args);
                 cli::task_handlers::handle_dep_remove(child_id, parent_id, channel, server).await
             })
         }),
@@ -714,8 +1358,8 @@ fn task_dep_graph_command() -> Command {
         execute: Arc::new(|_ctx, args| {
             Box::pin(async move {
                 let task_id = named(&args, "task_id");
-                let channel = named_or(&args, "channel", "public");
-                let server = named(&args, "server");
+                let channel = channel_name(&args);
+                let server = server_url(&args);
                 cli::task_handlers::handle_dep_graph(task_id, channel, server).await
             })
         }),
@@ -776,93 +1420,1022 @@ fn provider_telegram_test_command() -> Command {
     }
 }
 
-// ── doctor checks ──────────────────────────────────────────────────────────────
+fn notify_test_command() -> Command {
+    Command {
+        id: "notify-test".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Send a test prompt through each configured provider and wait for answers",
+            syntax: Some("notify-test [--channel NAME] [--wait 120]"),
+            category: Some("configuration"),
+            args: vec![
+                opt_arg(
+                    "channel",
+                    "Channel of the test prompt (default: the config's default channel)",
+                ),
+                opt_arg_default(
+                    "wait",
+                    "120",
+                    "Seconds to wait for an answer through each provider; 0 only checks delivery",
+                ),
+                opt_arg_default(
+                    "config",
+                    "~/.config/ailoop/config.toml",
+                    "Path to config file",
+                ),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let config = named_or(&args, "config", "~/.config/ailoop/config.toml");
+                let wait: u64 = named_or(&args, "wait", "120").parse().unwrap_or(120);
+                cli::provider_handlers::handle_notify_test(
+                    &config,
+                    opt_named(&args, "channel"),
+                    std::time::Duration::from_secs(wait),
+                )
+                .await
+            })
+        }),
+    }
+}
 
-fn ailoop_doctor_checks() -> Vec<Arc<dyn cli_framework::doctor::check::DoctorCheck>> {
-    vec![
-        Arc::new(cli::doctor::ConfigFileCheck),
-        Arc::new(cli::doctor::ServerConnectivityCheck),
-    ]
+// ── session subcommands ────────────────────────────────────────────────────────
+
+fn session_export_command() -> Command {
+    Command {
+        id: "export".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Export the transcript of a forwarded agent session",
+            syntax: Some("session export <session_id> [--format md|json] [--compress]"),
+            category: Some("agent"),
+            args: vec![
+                req_pos_arg("session_id", "Session ID reported by the agent"),
+                opt_arg_default("format", "md", "Transcript format (md, json)"),
+                opt_arg(
+                    "output",
+                    "Write the transcript to this file instead of stdout",
+                ),
+                flag_arg("compress", "Compress the transcript with zstd"),
+                server_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let session_id = named(&args, "session_id");
+                let format = named_or(&args, "format", "md");
+                let output = opt_named(&args, "output");
+                let compress = flag(&args, "compress");
+                let server = server_url(&args);
+                cli::session_handlers::handle_session_export(
+                    session_id, format, output, compress, server,
+                )
+                .await
+            })
+        }),
+    }
 }
 
-// ── entry point ────────────────────────────────────────────────────────────────
+// ── export subcommands ─────────────────────────────────────────────────────────
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let task_path = |segs: &[&str]| CommandPath::new(segs).expect("valid path");
+fn export_github_command() -> Command {
+    Command {
+        id: "github".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Post a channel's transcript as a comment on a GitHub issue or pull request",
+            syntax: Some(
+                "export github --issue owner/repo#123 [--channel NAME] [--since 2h] [--incident ID]",
+            ),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                req_opt_arg(
+                    "issue",
+                    "Issue or pull request: owner/repo#123 or its URL",
+                ),
+                channel_arg(),
+                opt_arg(
+                    "since",
+                    "Only messages from this long ago (2h, 1d) or since this time (default: all kept)",
+                ),
+                opt_arg(
+                    "incident",
+                    "Only messages tagged with this incident id (see `ailoop incident`)",
+                ),
+                opt_arg_default("limit", "500", "Most messages to include (the latest)"),
+                flag_arg("dry-run", "Print the comment instead of posting it"),
+                server_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let issue = named(&args, "issue");
+                let channel = channel_name(&args);
+                let since = opt_named(&args, "since");
+                let incident = opt_named(&args, "incident");
+                let limit: usize = named_or(&args, "limit", "500").parse().unwrap_or(500);
+                let dry_run = flag(&args, "dry-run");
+                let server = server_url(&args);
+                cli::export_handlers::handle_export_github(
+                    issue, channel, since, incident, limit, dry_run, server,
+                )
+                .await
+            })
+        }),
+    }
+}
 
-    let mut app = AppBuilder::new()
-        .with_version("ailoop", env!("CARGO_PKG_VERSION"))
-        .with_ailoop_channel("public")
-        // human-in-the-loop
-        .register_command(ask_command())?
-        .register_command(authorize_command())?
-        .register_command(say_command())?
-        // server
-        .register_command(serve_command())?
-        // configuration
-        .register_command(config_command())?
-        // media
-        .register_command(image_command())?
-        .register_command(navigate_command())?
-        // agent
-        .register_command(forward_command())?
-        // queue
-        .register_command(queue_command())?
-        // task group
-        .register_group(
-            &CommandPath::root_for("task"),
-            GroupMetadata {
-                summary: "Task management commands",
-                hidden: false,
-            },
-        )?
-        .register_command_at(&task_path(&["task", "create"]), task_create_command())?
-        .register_command_at(&task_path(&["task", "list"]), task_list_command())?
-        .register_command_at(&task_path(&["task", "show"]), task_show_command())?
-        .register_command_at(&task_path(&["task", "update"]), task_update_command())?
-        .register_command_at(&task_path(&["task", "ready"]), task_ready_command())?
-        .register_command_at(&task_path(&["task", "blocked"]), task_blocked_command())?
-        // task dep sub-group
-        .register_group(
-            &task_path(&["task", "dep"]),
-            GroupMetadata {
-                summary: "Manage task dependencies",
-                hidden: false,
-            },
-        )?
-        .register_command_at(&task_path(&["task", "dep", "add"]), task_dep_add_command())?
-        .register_command_at(
-            &task_path(&["task", "dep", "remove"]),
-            task_dep_remove_command(),
-        )?
-        .register_command_at(
-            &task_path(&["task", "dep", "graph"]),
-            task_dep_graph_command(),
-        )?
-        // provider group
-        .register_group(
-            &CommandPath::root_for("provider"),
-            GroupMetadata {
-                summary: "Provider status and testing",
-                hidden: false,
-            },
-        )?
-        .register_command_at(&task_path(&["provider", "list"]), provider_list_command())?
-        .register_group(
-            &task_path(&["provider", "telegram"]),
-            GroupMetadata {
-                summary: "Telegram provider commands",
-                hidden: false,
-            },
-        )?
-        .register_command_at(
-            &task_path(&["provider", "telegram", "test"]),
-            provider_telegram_test_command(),
+// ── channel subcommands ────────────────────────────────────────────────────────
+
+fn channel_pause_command() -> Command {
+    Command {
+        id: "pause".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Hold prompts on a channel; agents' messages queue until resumed",
+            syntax: Some("channel pause <channel>"),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                req_pos_arg("channel", "Channel to pause"),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let channel = named(&args, "channel");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::channel_handlers::handle_channel_pause(channel, server, json).await
+            })
+        }),
+    }
+}
+
+fn channel_resume_command() -> Command {
+    Command {
+        id: "resume".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Deliver the prompts queued on a paused channel",
+            syntax: Some("channel resume <channel>"),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                req_pos_arg("channel", "Channel to resume"),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let channel = named(&args, "channel");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::channel_handlers::handle_channel_resume(channel, server, json).await
+            })
+        }),
+    }
+}
+
+// ── incident subcommands ───────────────────────────────────────────────────────
+
+fn incident_start_command() -> Command {
+    Command {
+        id: "start".into(),
+        spec: Arc::new(CommandSpec {
+            summary:
+                "Put channels in incident mode: higher priority, faster escalation, larger quotas",
+            syntax: Some("incident start --channel ops[,deploys] [--id INC-42] [--title TEXT]"),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                req_opt_arg("channel", "Channel(s) in the incident, comma-separated"),
+                opt_arg(
+                    "id",
+                    "Incident id messages are tagged with (default: <channel>-<YYYYMMDD-HHMM>)",
+                ),
+                opt_arg(
+                    "title",
+                    "What the incident is about, shown in its announcement",
+                ),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let channels = named(&args, "channel");
+                let id = opt_named(&args, "id");
+                let title = opt_named(&args, "title");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::incident_handlers::handle_incident_start(channels, id, title, server, json)
+                    .await
+            })
+        }),
+    }
+}
+
+fn incident_stop_command() -> Command {
+    Command {
+        id: "stop".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "End the incident on channels and return them to normal",
+            syntax: Some("incident stop --channel ops[,deploys]"),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                req_opt_arg(
+                    "channel",
+                    "Channel(s) to end the incident on, comma-separated",
+                ),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let channels = named(&args, "channel");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::incident_handlers::handle_incident_stop(channels, server, json).await
+            })
+        }),
+    }
+}
+
+// ── schedule subcommands ───────────────────────────────────────────────────────
+
+fn schedule_list_command() -> Command {
+    Command {
+        id: "list".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "List messages waiting to be sent, soonest first",
+            syntax: Some("schedule list [--channel <channel>]"),
+            category: Some("schedule"),
+            args: vec![
+                opt_arg("channel", "Only this channel"),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let channel = opt_named(&args, "channel");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::schedule_handlers::handle_schedule_list(channel, server, json).await
+            })
+        }),
+    }
+}
+
+fn schedule_cancel_command() -> Command {
+    Command {
+        id: "cancel".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Cancel a scheduled message before it is sent",
+            syntax: Some("schedule cancel <id>"),
+            category: Some("schedule"),
+            args: vec![
+                req_pos_arg("id", "Id of the scheduled message"),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let id = named(&args, "id");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::schedule_handlers::handle_schedule_cancel(id, server, json).await
+            })
+        }),
+    }
+}
+
+// ── remind subcommands ─────────────────────────────────────────────────────────
+
+fn remind_add_command() -> Command {
+    Command {
+        id: "add".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Send a notification on a recurring cron schedule",
+            syntax: Some("remind add <schedule> <text>"),
+            category: Some("remind"),
+            args: vec![
+                req_pos_arg(
+                    "schedule",
+                    "Cron expression in the server's local time, e.g. \"0 9 * * MON\"",
+                ),
+                req_pos_arg("text", "Reminder text"),
+                channel_arg(),
+                opt_arg_default(
+                    "priority",
+                    "normal",
+                    "Notification priority (low, normal, high, urgent)",
+                ),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let schedule = named(&args, "schedule");
+                let text = named(&args, "text");
+                let channel = channel_name(&args);
+                let priority = named_or(&args, "priority", "normal");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::schedule_handlers::handle_remind_add(
+                    schedule, text, channel, priority, server, json,
+                )
+                .await
+            })
+        }),
+    }
+}
+
+fn remind_list_command() -> Command {
+    Command {
+        id: "list".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "List reminders with their next time and unacknowledged count",
+            syntax: Some("remind list [--channel <channel>]"),
+            category: Some("remind"),
+            args: vec![
+                opt_arg("channel", "Only this channel"),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let channel = opt_named(&args, "channel");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::schedule_handlers::handle_remind_list(channel, server, json).await
+            })
+        }),
+    }
+}
+
+fn remind_remove_command() -> Command {
+    Command {
+        id: "remove".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Stop a reminder",
+            syntax: Some("remind remove <id>"),
+            category: Some("remind"),
+            args: vec![
+                req_pos_arg("id", "Id of the reminder"),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let id = named(&args, "id");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::schedule_handlers::handle_remind_action(
+                    id,
+                    cli::schedule_handlers::ReminderAction::Remove,
+                    server,
+                    json,
+                )
+                .await
+            })
+        }),
+    }
+}
+
+fn remind_skip_command() -> Command {
+    Command {
+        id: "skip".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Skip the next occurrences of a reminder",
+            syntax: Some("remind skip <id> [--count <n>]"),
+            category: Some("remind"),
+            args: vec![
+                req_pos_arg("id", "Id of the reminder"),
+                opt_arg_default("count", "1", "Occurrences to skip"),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let id = named(&args, "id");
+                let count: u32 = named_or(&args, "count", "1").parse().unwrap_or(1);
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::schedule_handlers::handle_remind_action(
+                    id,
+                    cli::schedule_handlers::ReminderAction::Skip(count),
+                    server,
+                    json,
+                )
+                .await
+            })
+        }),
+    }
+}
+
+fn remind_ack_command() -> Command {
+    Command {
+        id: "ack".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Acknowledge a reminder's notifications",
+            syntax: Some("remind ack <id>"),
+            category: Some("remind"),
+            args: vec![
+                req_pos_arg("id", "Id of the reminder"),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let id = named(&args, "id");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::schedule_handlers::handle_remind_action(
+                    id,
+                    cli::schedule_handlers::ReminderAction::Acknowledge,
+                    server,
+                    json,
+                )
+                .await
+            })
+        }),
+    }
+}
+
+// ── history subcommands ────────────────────────────────────────────────────────
+
+fn history_purge_command() -> Command {
+    Command {
+        id: "purge".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Remove old messages from the server's channel history",
+            syntax: Some("history purge --channel <channel> --before <age|time> [--dry-run]"),
+            category: Some("history"),
+            args: vec![
+                req_opt_arg("channel", "Channel to purge, or * for every channel"),
+                req_opt_arg(
+                    "before",
+                    "Purge messages older than this age (30d, 12h) or time (2025-07-01T00:00)",
+                ),
+                flag_arg("dry-run", "Only count the messages that would be purged"),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let channel = named(&args, "channel");
+                let before = named(&args, "before");
+                let dry_run = flag(&args, "dry-run");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::history_handlers::handle_history_purge(channel, before, dry_run, server, json)
+                    .await
+            })
+        }),
+    }
+}
+
+// ── privacy subcommands ────────────────────────────────────────────────────────
+
+fn privacy_export_command() -> Command {
+    Command {
+        id: "export".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Export everything the server holds for a responder identity",
+            syntax: Some("privacy export <responder> [--output <file>] [--compress]"),
+            category: Some("privacy"),
+            args: vec![
+                req_pos_arg("responder", "Responder identity, e.g. telegram:alice"),
+                opt_arg("output", "Write the export to this file instead of stdout"),
+                flag_arg("compress", "Compress the export with zstd"),
+                server_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let responder = named(&args, "responder");
+                let output = opt_named(&args, "output");
+                let compress = flag(&args, "compress");
+                let server = server_url(&args);
+                cli::privacy_handlers::handle_privacy_export(responder, output, compress, server)
+                    .await
+            })
+        }),
+    }
+}
+
+fn privacy_erase_command() -> Command {
+    Command {
+        id: "erase".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Erase a responder's responses and the files they sent",
+            syntax: Some("privacy erase <responder> --yes"),
+            category: Some("privacy"),
+            args: vec![
+                req_pos_arg("responder", "Responder identity, e.g. telegram:alice"),
+                flag_arg("yes", "Confirm the erasure, which cannot be undone"),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let responder = named(&args, "responder");
+                let yes = flag(&args, "yes");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::privacy_handlers::handle_privacy_erase(responder, yes, server, json).await
+            })
+        }),
+    }
+}
+
+// ── me subcommands ─────────────────────────────────────────────────────────────
+
+fn me_away_command() -> Command {
+    Command {
+        id: "away".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Say you are away; routing passes you over until you are back",
+            syntax: Some("me away [--until WHEN] [--note TEXT] [--as NAME]"),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                opt_arg(
+                    "until",
+                    "When you are back: friday, tomorrow, 3d, 2025-07-01 or 2025-07-01T09:00 (default: until `me back`)",
+                ),
+                opt_arg("note", "Shown to agents that assign you a prompt"),
+                opt_arg(
+                    "as",
+                    "Team member or handle (default: $USER; ignored with an SSO token)",
+                ),
+                opt_arg(
+                    "token",
+                    "API token with the responder role (default: AILOOP_TOKEN)",
+                ),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let until = opt_named(&args, "until");
+                let note = opt_named(&args, "note");
+                let responder = opt_named(&args, "as");
+                let token = opt_named(&args, "token");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::me_handlers::handle_me_away(until, note, responder, token, server, json)
+                    .await
+            })
+        }),
+    }
+}
+
+fn me_back_command() -> Command {
+    Command {
+        id: "back".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Say you are back from being away",
+            syntax: Some("me back [--as NAME]"),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                opt_arg(
+                    "as",
+                    "Team member or handle (default: $USER; ignored with an SSO token)",
+                ),
+                opt_arg(
+                    "token",
+                    "API token with the responder role (default: AILOOP_TOKEN)",
+                ),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let responder = opt_named(&args, "as");
+                let token = opt_named(&args, "token");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::me_handlers::handle_me_back(responder, token, server, json).await
+            })
+        }),
+    }
+}
+
+fn me_watch_command() -> Command {
+    Command {
+        id: "watch".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Get an alert on your providers when a message contains a keyword",
+            syntax: Some("me watch [KEYWORD,...] [--as NAME]"),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                opt_pos_arg(
+                    "keywords",
+                    "Comma-separated keywords or @mentions to watch; without any, list the watched ones",
+                ),
+                opt_arg(
+                    "as",
+                    "Team member or handle (default: $USER; ignored with an SSO token)",
+                ),
+                opt_arg(
+                    "token",
+                    "API token with the responder role (default: AILOOP_TOKEN)",
+                ),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let keywords = opt_named(&args, "keywords");
+                let responder = opt_named(&args, "as");
+                let token = opt_named(&args, "token");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::me_handlers::handle_me_watch(keywords, responder, token, server, json).await
+            })
+        }),
+    }
+}
+
+fn me_unwatch_command() -> Command {
+    Command {
+        id: "unwatch".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Stop watching keywords",
+            syntax: Some("me unwatch [KEYWORD,...] [--as NAME]"),
+            category: Some("human-in-the-loop"),
+            args: vec![
+                opt_pos_arg(
+                    "keywords",
+                    "Comma-separated keywords to stop watching; without any, stop watching all",
+                ),
+                opt_arg(
+                    "as",
+                    "Team member or handle (default: $USER; ignored with an SSO token)",
+                ),
+                opt_arg(
+                    "token",
+                    "API token with the responder role (default: AILOOP_TOKEN)",
+                ),
+                server_arg(),
+                json_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                let keywords = opt_named(&args, "keywords");
+                let responder = opt_named(&args, "as");
+                let token = opt_named(&args, "token");
+                let server = server_url(&args);
+                let json = flag(&args, "json");
+                cli::me_handlers::handle_me_unwatch(keywords, responder, token, server, json).await
+            })
+        }),
+    }
+}
+
+// ── outbox subcommands ─────────────────────────────────────────────────────────
+
+fn outbox_flush_command() -> Command {
+    Command {
+        id: "flush".into(),
+        spec: Arc::new(CommandSpec {
+            summary: "Send messages spooled while the server was unreachable",
+            syntax: Some("outbox flush"),
+            category: Some("outbox"),
+            args: vec![
+                opt_arg_default(
+                    "config",
+                    "~/.config/ailoop/config.toml",
+                    "Path to config file",
+                ),
+                data_dir_arg(),
+            ],
+            ..Default::default()
+        }),
+        validator: None,
+        expose_mcp: false,
+        expose_chat: false,
+        execute: Arc::new(|_ctx, args| {
+            Box::pin(async move {
+                apply_data_dir(&args);
+                let config = named_or(&args, "config", "~/.config/ailoop/config.toml");
+                cli::outbox_handlers::handle_outbox_flush(&config).await
+            })
+        }),
+    }
+}
+
+// ── doctor checks ──────────────────────────────────────────────────────────────
+
+fn ailoop_doctor_checks() -> Vec<Arc<dyn cli_framework::doctor::check::DoctorCheck>> {
+    vec![
+        Arc::new(cli::doctor::ConfigFileCheck),
+        Arc::new(cli::doctor::ServerConnectivityCheck),
+    ]
+}
+
+// ── entry point ────────────────────────────────────────────────────────────────
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // `ailoop help <topic>` shows a guide page; `help <command>` stays with the framework.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some((topic, man)) = cli::guide::help_alias(&args) {
+        return cli::guide::handle_guide(Some(topic.name.to_string()), man);
+    }
+
+    // Outbound proxy for the WebSocket and HTTP clients: [proxy] in config.toml, else HTTPS_PROXY.
+    if let Ok(path) = ailoop_core::models::Configuration::default_config_path() {
+        if let Ok(config) = ailoop_core::models::Configuration::load_from_file(&path) {
+            ailoop_core::transport::proxy::configure(config.proxy);
+        }
+    }
+
+    // OTLP traces and metrics when built with `otel` and OTEL_EXPORTER_OTLP_ENDPOINT is set;
+    // flushed when the guard drops at exit.
+    let _telemetry = ailoop_server::server::telemetry::init();
+
+    let task_path = |segs: &[&str]| CommandPath::new(segs).expect("valid path");
+
+    let builder = AppBuilder::new()
+        .with_version("ailoop", env!("CARGO_PKG_VERSION"))
+        .with_ailoop_channel("public")
+        // human-in-the-loop
+        .register_command(ask_command())?
+        .register_command(authorize_command())?
+        .register_command(say_command())?
+        .register_command(status_command())?
+        .register_command(chat_command())?
+        .register_command(break_glass_command())?
+        // server
+        .register_command(serve_command())?
+        .register_command(logs_command())?
+        // configuration
+        .register_command(config_command())?
+        .register_command(init_command())?
+        .register_command(guide_command())?
+        .register_command(notify_test_command())?
+        // media
+        .register_command(image_command())?
+        .register_command(navigate_command())?
+        // agent
+        .register_command(forward_command())?
+        // queue
+        .register_command(queue_command())?
+        // task group
+        .register_group(
+            &CommandPath::root_for("task"),
+            GroupMetadata {
+                summary: "Task management commands",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["task", "create"]), task_create_command())?
+        .register_command_at(&task_path(&["task", "list"]), task_list_command())?
+        .register_command_at(&task_path(&["task", "show"]), task_show_command())?
+        .register_command_at(&task_path(&["task", "update"]), task_update_command())?
+        .register_command_at(&task_path(&["task", "ready"]), task_ready_command())?
+        .register_command_at(&task_path(&["task", "blocked"]), task_blocked_command())?
+        // task dep sub-group
+        .register_group(
+            &task_path(&["task", "dep"]),
+            GroupMetadata {
+                summary: "Manage task dependencies",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["task", "dep", "add"]), task_dep_add_command())?
+        .register_command_at(
+            &task_path(&["task", "dep", "remove"]),
+            task_dep_remove_command(),
+        )?
+        .register_command_at(
+            &task_path(&["task", "dep", "graph"]),
+            task_dep_graph_command(),
+        )?
+        // provider group
+        .register_group(
+            &CommandPath::root_for("provider"),
+            GroupMetadata {
+                summary: "Provider status and testing",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["provider", "list"]), provider_list_command())?
+        .register_group(
+            &task_path(&["provider", "telegram"]),
+            GroupMetadata {
+                summary: "Telegram provider commands",
+                hidden: false,
+            },
+        )?
+        .register_command_at(
+            &task_path(&["provider", "telegram", "test"]),
+            provider_telegram_test_command(),
+        )?
+        // session group
+        .register_group(
+            &CommandPath::root_for("session"),
+            GroupMetadata {
+                summary: "Agent sessions seen by the server",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["session", "export"]), session_export_command())?
+        // export group
+        .register_group(
+            &CommandPath::root_for("export"),
+            GroupMetadata {
+                summary: "Record channel transcripts elsewhere",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["export", "github"]), export_github_command())?
+        // channel group
+        .register_group(
+            &CommandPath::root_for("channel"),
+            GroupMetadata {
+                summary: "Hold and resume prompt delivery on a channel",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["channel", "pause"]), channel_pause_command())?
+        .register_command_at(&task_path(&["channel", "resume"]), channel_resume_command())?
+        // incident group
+        .register_group(
+            &CommandPath::root_for("incident"),
+            GroupMetadata {
+                summary: "Incident mode: urgent handling of channels during an incident",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["incident", "start"]), incident_start_command())?
+        .register_command_at(&task_path(&["incident", "stop"]), incident_stop_command())?
+        // schedule group
+        .register_group(
+            &CommandPath::root_for("schedule"),
+            GroupMetadata {
+                summary: "Messages scheduled to be sent later",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["schedule", "list"]), schedule_list_command())?
+        .register_command_at(
+            &task_path(&["schedule", "cancel"]),
+            schedule_cancel_command(),
+        )?
+        // remind group
+        .register_group(
+            &CommandPath::root_for("remind"),
+            GroupMetadata {
+                summary: "Recurring reminders sent on a cron schedule",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["remind", "add"]), remind_add_command())?
+        .register_command_at(&task_path(&["remind", "list"]), remind_list_command())?
+        .register_command_at(&task_path(&["remind", "remove"]), remind_remove_command())?
+        .register_command_at(&task_path(&["remind", "skip"]), remind_skip_command())?
+        .register_command_at(&task_path(&["remind", "ack"]), remind_ack_command())?
+        // history group
+        .register_group(
+            &CommandPath::root_for("history"),
+            GroupMetadata {
+                summary: "Channel history kept by the server",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["history", "purge"]), history_purge_command())?
+        // privacy group
+        .register_group(
+            &CommandPath::root_for("privacy"),
+            GroupMetadata {
+                summary: "Export or erase the data of a responder identity",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["privacy", "export"]), privacy_export_command())?
+        .register_command_at(&task_path(&["privacy", "erase"]), privacy_erase_command())?
+        // me group
+        .register_group(
+            &CommandPath::root_for("me"),
+            GroupMetadata {
+                summary: "Your own status as a responder",
+                hidden: false,
+            },
+        )?
+        .register_command_at(&task_path(&["me", "away"]), me_away_command())?
+        .register_command_at(&task_path(&["me", "back"]), me_back_command())?
+        .register_command_at(&task_path(&["me", "watch"]), me_watch_command())?
+        .register_command_at(&task_path(&["me", "unwatch"]), me_unwatch_command())?
+        // outbox group
+        .register_group(
+            &CommandPath::root_for("outbox"),
+            GroupMetadata {
+                summary: "Offline outbox for undeliverable messages",
+                hidden: false,
+            },
         )?
+        .register_command_at(&task_path(&["outbox", "flush"]), outbox_flush_command())?
         // doctor checks (auto-registers `doctor` command)
-        .register_doctor_checks(ailoop_doctor_checks())
-        .build(AiloopApp)?;
+        .register_doctor_checks(ailoop_doctor_checks());
+    #[cfg(feature = "tray")]
+    let builder = builder.register_command(tray_command())?;
+    let mut app = builder.build(AiloopApp)?;
 
     app.run().await
 }
@@ -1,7 +1,7 @@
 //! Channel isolation mechanisms
 
 use crate::channel::manager::ChannelManager;
-use crate::models::Message;
+use crate::models::{Message, NotificationPriority};
 use std::sync::{Arc, Mutex};
 
 /// Thread-safe channel isolation wrapper
@@ -36,6 +36,25 @@ impl ChannelIsolation {
         }
     }
 
+    /// Put a message back at the head of a specific channel's queue
+    pub fn requeue_front(&self, channel_name: &str, message: Message) {
+        if let Ok(mut manager) = self.manager.lock() {
+            manager.requeue_front(channel_name, message);
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+        }
+    }
+
+    /// Take the last message queued on a specific channel
+    pub fn dequeue_last(&self, channel_name: &str) -> Option<Message> {
+        if let Ok(mut manager) = self.manager.lock() {
+            manager.dequeue_last(channel_name)
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+            None
+        }
+    }
+
     /// Get queue size for a specific channel
     pub fn get_queue_size(&self, channel_name: &str) -> usize {
         if let Ok(manager) = self.manager.lock() {
@@ -46,6 +65,51 @@ impl ChannelIsolation {
         }
     }
 
+    /// Queued messages per channel, oldest first; only `channel` when given
+    pub fn get_queued_messages(&self, channel: Option<&str>) -> Vec<(String, Vec<Message>)> {
+        if let Ok(manager) = self.manager.lock() {
+            manager.get_queued_messages(channel)
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+            Vec::new()
+        }
+    }
+
+    /// Remove a queued message from whichever channel holds it
+    pub fn remove_queued_message(&self, id: &uuid::Uuid) -> Option<Message> {
+        if let Ok(mut manager) = self.manager.lock() {
+            manager.remove_queued_message(id)
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+            None
+        }
+    }
+
+    /// Raise a queued message to `priority`, moving it ahead in its channel's queue. Returns
+    /// its channel and new index, or `None` when it is not queued.
+    pub fn boost_queued_message(
+        &self,
+        id: &uuid::Uuid,
+        priority: NotificationPriority,
+    ) -> Option<(String, usize)> {
+        if let Ok(mut manager) = self.manager.lock() {
+            manager.boost_queued_message(id, priority)
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+            None
+        }
+    }
+
+    /// Remove and return every message queued on a specific channel
+    pub fn clear_queue(&self, channel_name: &str) -> Vec<Message> {
+        if let Ok(mut manager) = self.manager.lock() {
+            manager.clear_queue(channel_name)
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+            Vec::new()
+        }
+    }
+
     /// Add a connection to a specific channel
     pub fn add_connection(&self, channel_name: &str) {
         if let Ok(mut manager) = self.manager.lock() {
@@ -74,6 +138,80 @@ impl ChannelIsolation {
         }
     }
 
+    /// Hold prompt delivery on a channel. Returns false if it was already held.
+    pub fn hold(&self, channel_name: &str) -> bool {
+        if let Ok(mut manager) = self.manager.lock() {
+            manager.hold(channel_name)
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+            false
+        }
+    }
+
+    /// Resume prompt delivery on a channel. Returns false if it was not held.
+    pub fn release(&self, channel_name: &str) -> bool {
+        if let Ok(mut manager) = self.manager.lock() {
+            manager.release(channel_name)
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+            false
+        }
+    }
+
+    /// When the channel was put on hold, if it is held
+    pub fn held_since(&self, channel_name: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+        if let Ok(manager) = self.manager.lock() {
+            manager.held_since(channel_name)
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+            None
+        }
+    }
+
+    /// Held channels and when each was put on hold
+    pub fn get_held_channels(&self) -> Vec<(String, chrono::DateTime<chrono::Utc>)> {
+        if let Ok(manager) = self.manager.lock() {
+            manager.get_held_channels()
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+            Vec::new()
+        }
+    }
+
+    /// Set a prompt aside until `until`
+    pub fn snooze(
+        &self,
+        channel_name: &str,
+        message: Message,
+        until: chrono::DateTime<chrono::Utc>,
+    ) {
+        if let Ok(mut manager) = self.manager.lock() {
+            manager.snooze(channel_name, message, until);
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+        }
+    }
+
+    /// Remove and return the snoozed prompts that are due at `now`
+    pub fn take_due_snoozed(&self, now: chrono::DateTime<chrono::Utc>) -> Vec<Message> {
+        if let Ok(mut manager) = self.manager.lock() {
+            manager.take_due_snoozed(now)
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+            Vec::new()
+        }
+    }
+
+    /// Number of snoozed prompts on a channel
+    pub fn get_snoozed_count(&self, channel_name: &str) -> usize {
+        if let Ok(manager) = self.manager.lock() {
+            manager.get_snoozed_count(channel_name)
+        } else {
+            eprintln!("Failed to acquire channel manager lock");
+            0
+        }
+    }
+
     /// Clean up inactive channels
     pub fn cleanup_inactive_channels(&self) {
         if let Ok(mut manager) = self.manager.lock() {
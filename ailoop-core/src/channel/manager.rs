@@ -1,7 +1,8 @@
 //! Channel lifecycle management
 
-use crate::models::Message;
+use crate::models::{Message, NotificationPriority};
 use crate::server::MessageQueue;
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 
 /// Channel manager for handling multiple communication channels
@@ -13,6 +14,10 @@ pub struct ChannelManager {
 pub struct ChannelState {
     queue: MessageQueue,
     active_connections: usize,
+    /// When prompt delivery was put on hold; messages keep queueing meanwhile.
+    held_since: Option<DateTime<Utc>>,
+    /// Prompts set aside by the operator, with when to present them again
+    snoozed: Vec<(DateTime<Utc>, Message)>,
     _created_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -27,6 +32,8 @@ impl ChannelManager {
             ChannelState {
                 queue: MessageQueue::default(),
                 active_connections: 0,
+                held_since: None,
+                snoozed: Vec::new(),
                 _created_at: chrono::Utc::now(),
             },
         );
@@ -46,6 +53,8 @@ impl ChannelManager {
                 ChannelState {
                     queue: MessageQueue::default(),
                     active_connections: 0,
+                    held_since: None,
+                    snoozed: Vec::new(),
                     _created_at: chrono::Utc::now(),
                 }
             })
@@ -67,6 +76,19 @@ impl ChannelManager {
         }
     }
 
+    /// Put a message back at the head of a channel's queue
+    pub fn requeue_front(&mut self, channel_name: &str, message: Message) {
+        let channel = self.get_or_create_channel(channel_name);
+        channel.queue.requeue_front(message);
+    }
+
+    /// Take the last message queued on a channel
+    pub fn dequeue_last(&mut self, channel_name: &str) -> Option<Message> {
+        self.channels
+            .get_mut(channel_name)
+            .and_then(|channel| channel.queue.dequeue_last())
+    }
+
     /// Get queue size for a channel
     pub fn get_queue_size(&self, channel_name: &str) -> usize {
         self.channels
@@ -75,6 +97,51 @@ impl ChannelManager {
             .unwrap_or(0)
     }
 
+    /// Queued messages per channel, oldest first; only `channel` when given. Channels are
+    /// sorted by name and empty queues are left out.
+    pub fn get_queued_messages(&self, channel: Option<&str>) -> Vec<(String, Vec<Message>)> {
+        let mut queued: Vec<_> = self
+            .channels
+            .iter()
+            .filter(|(name, state)| {
+                channel.is_none_or(|c| c == name.as_str()) && !state.queue.is_empty()
+            })
+            .map(|(name, state)| (name.clone(), state.queue.iter().cloned().collect()))
+            .collect();
+        queued.sort_by(|a, b| a.0.cmp(&b.0));
+        queued
+    }
+
+    /// Remove the queued message with `id` from whichever channel holds it
+    pub fn remove_queued_message(&mut self, id: &uuid::Uuid) -> Option<Message> {
+        self.channels
+            .values_mut()
+            .find_map(|channel| channel.queue.remove(id))
+    }
+
+    /// Raise the queued message with `id` to `priority` (see [`MessageQueue::boost`]).
+    /// Returns its channel and new index, or `None` when it is not queued.
+    pub fn boost_queued_message(
+        &mut self,
+        id: &uuid::Uuid,
+        priority: NotificationPriority,
+    ) -> Option<(String, usize)> {
+        self.channels.iter_mut().find_map(|(name, channel)| {
+            channel
+                .queue
+                .boost(id, priority.clone())
+                .map(|index| (name.clone(), index))
+        })
+    }
+
+    /// Remove and return every message queued on a channel
+    pub fn clear_queue(&mut self, channel_name: &str) -> Vec<Message> {
+        self.channels
+            .get_mut(channel_name)
+            .map(|channel| channel.queue.drain())
+            .unwrap_or_default()
+    }
+
     /// Add a connection to a channel
     pub fn add_connection(&mut self, channel_name: &str) {
         let channel = self.get_or_create_channel(channel_name);
@@ -114,6 +181,69 @@ impl ChannelManager {
             .sum()
     }
 
+    /// Hold prompt delivery on a channel. Returns false if it was already held.
+    pub fn hold(&mut self, channel_name: &str) -> bool {
+        let channel = self.get_or_create_channel(channel_name);
+        if channel.held_since.is_some() {
+            return false;
+        }
+        channel.held_since = Some(Utc::now());
+        true
+    }
+
+    /// Resume prompt delivery on a channel. Returns false if it was not held.
+    pub fn release(&mut self, channel_name: &str) -> bool {
+        self.channels
+            .get_mut(channel_name)
+            .and_then(|channel| channel.held_since.take())
+            .is_some()
+    }
+
+    /// When the channel was put on hold, if it is held
+    pub fn held_since(&self, channel_name: &str) -> Option<DateTime<Utc>> {
+        self.channels
+            .get(channel_name)
+            .and_then(|channel| channel.held_since)
+    }
+
+    /// Held channels and when each was put on hold, sorted by name
+    pub fn get_held_channels(&self) -> Vec<(String, DateTime<Utc>)> {
+        let mut held: Vec<_> = self
+            .channels
+            .iter()
+            .filter_map(|(name, state)| state.held_since.map(|since| (name.clone(), since)))
+            .collect();
+        held.sort();
+        held
+    }
+
+    /// Set a prompt aside until `until`; [`Self::take_due_snoozed`] returns it afterwards.
+    pub fn snooze(&mut self, channel_name: &str, message: Message, until: DateTime<Utc>) {
+        let channel = self.get_or_create_channel(channel_name);
+        channel.snoozed.push((until, message));
+    }
+
+    /// Remove and return the snoozed prompts that are due at `now`, across all channels
+    pub fn take_due_snoozed(&mut self, now: DateTime<Utc>) -> Vec<Message> {
+        let mut due = Vec::new();
+        for channel in self.channels.values_mut() {
+            let (ready, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut channel.snoozed)
+                .into_iter()
+                .partition(|(until, _)| *until <= now);
+            channel.snoozed = waiting;
+            due.extend(ready.into_iter().map(|(_, message)| message));
+        }
+        due
+    }
+
+    /// Number of snoozed prompts on a channel
+    pub fn get_snoozed_count(&self, channel_name: &str) -> usize {
+        self.channels
+            .get(channel_name)
+            .map(|channel| channel.snoozed.len())
+            .unwrap_or(0)
+    }
+
     /// Clean up inactive channels (no connections and empty queue)
     pub fn cleanup_inactive_channels(&mut self) {
         let channels_to_remove: Vec<String> = self
@@ -122,7 +252,9 @@ impl ChannelManager {
             .filter(|(name, state)| {
                 name != &&self.default_channel && // Don't remove default channel
                 state.active_connections == 0 &&
-                state.queue.is_empty()
+                state.queue.is_empty() &&
+                state.held_since.is_none() &&
+                state.snoozed.is_empty()
             })
             .map(|(name, _)| name.clone())
             .collect();
@@ -234,4 +366,91 @@ mod tests {
         // Default channel should remain
         assert!(manager.channels.contains_key("public"));
     }
+
+    #[test]
+    fn test_hold_and_release() {
+        let mut manager = ChannelManager::default();
+
+        assert!(manager.hold("ops"));
+        assert!(!manager.hold("ops"), "holding twice reports no change");
+        assert!(manager.held_since("ops").is_some());
+        assert_eq!(manager.get_held_channels().len(), 1);
+
+        // A held channel survives cleanup even when idle
+        manager.cleanup_inactive_channels();
+        assert!(manager.channels.contains_key("ops"));
+
+        assert!(manager.release("ops"));
+        assert!(!manager.release("ops"));
+        assert!(manager.held_since("ops").is_none());
+        assert!(!manager.release("unknown"));
+    }
+
+    #[test]
+    fn test_snoozed_prompts_come_back_when_due() {
+        let mut manager = ChannelManager::default();
+        let now = Utc::now();
+        let message = |text: &str| {
+            Message::new(
+                "ops".to_string(),
+                SenderType::Agent,
+                MessageContent::Notification {
+                    text: text.to_string(),
+                    priority: Default::default(),
+                },
+            )
+        };
+
+        manager.snooze("ops", message("later"), now + chrono::Duration::minutes(10));
+        manager.snooze("ops", message("soon"), now + chrono::Duration::minutes(1));
+        assert_eq!(manager.get_snoozed_count("ops"), 2);
+        assert_eq!(manager.get_queue_size("ops"), 0);
+
+        // Snoozed prompts keep an idle channel alive
+        manager.cleanup_inactive_channels();
+        assert!(manager.channels.contains_key("ops"));
+
+        assert!(manager.take_due_snoozed(now).is_empty());
+        let due = manager.take_due_snoozed(now + chrono::Duration::minutes(5));
+        assert_eq!(due.len(), 1);
+        assert!(matches!(
+            &due[0].content,
+            MessageContent::Notification { text, .. } if text == "soon"
+        ));
+        assert_eq!(manager.get_snoozed_count("ops"), 1);
+    }
+
+    #[test]
+    fn test_inspect_and_drop_queued_messages() {
+        let mut manager = ChannelManager::default();
+        let message = |channel: &str| {
+            Message::new(
+                channel.to_string(),
+                SenderType::Agent,
+                MessageContent::Notification {
+                    text: "stuck".to_string(),
+                    priority: Default::default(),
+                },
+            )
+        };
+        let (first, second, other) = (message("ops"), message("ops"), message("build"));
+        manager.enqueue_message("ops", first.clone());
+        manager.enqueue_message("ops", second.clone());
+        manager.enqueue_message("build", other.clone());
+
+        let queued = manager.get_queued_messages(None);
+        let names: Vec<_> = queued.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["build", "ops"]);
+        assert_eq!(manager.get_queued_messages(Some("ops"))[0].1.len(), 2);
+
+        assert_eq!(
+            manager.remove_queued_message(&second.id).unwrap().id,
+            second.id
+        );
+        assert!(manager.remove_queued_message(&second.id).is_none());
+        assert_eq!(manager.clear_queue("ops").len(), 1);
+        assert_eq!(manager.get_queue_size("ops"), 0);
+        assert!(manager.clear_queue("missing").is_empty());
+        assert_eq!(manager.get_queue_size("build"), 1);
+    }
 }
@@ -2,6 +2,7 @@
 
 pub mod isolation;
 pub mod manager;
+pub mod pattern;
 pub mod validation;
 
 pub use isolation::ChannelIsolation;
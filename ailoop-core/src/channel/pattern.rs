@@ -0,0 +1,49 @@
+//! Channel name patterns such as `*-prod` or `team-*`, where `*` stands for any run of
+//! characters (including none).
+
+/// Whether `target` is a pattern rather than a plain channel name.
+pub fn is_pattern(target: &str) -> bool {
+    target.contains('*')
+}
+
+/// Whether `name` matches `pattern`. A pattern without `*` matches only itself.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` at all
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("*-prod", "api-prod"));
+        assert!(!matches_pattern("*-prod", "api-prod-eu"));
+        assert!(matches_pattern("team-*", "team-"));
+        assert!(matches_pattern("team-*", "team-web"));
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("a*b*c", "a-x-b-y-c"));
+        assert!(!matches_pattern("a*b*c", "a-c-b"));
+        assert!(!matches_pattern("ab*ba", "aba"));
+        assert!(matches_pattern("ops", "ops"));
+        assert!(!matches_pattern("ops", "ops-2"));
+        assert!(is_pattern("*-prod"));
+        assert!(!is_pattern("ops"));
+    }
+}
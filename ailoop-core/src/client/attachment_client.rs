@@ -0,0 +1,71 @@
+//! HTTP client for uploading files to the server's attachment store.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A file stored by `POST /api/v1/attachments`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadedAttachment {
+    pub id: Uuid,
+    pub name: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Absolute download URL.
+    pub url: String,
+}
+
+pub struct AttachmentClient {
+    base_url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl AttachmentClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base = base_url.into();
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            base_url: base,
+            token: None,
+            client: crate::transport::proxy::http_client(),
+        }
+    }
+
+    /// Send `token` as a bearer token when the server requires authentication.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token.filter(|t| !t.is_empty());
+        self
+    }
+
+    /// Store `bytes` as a file called `name`. The returned `url` is absolute, so it can be
+    /// handed to people and providers that do not know the server's address.
+    pub async fn upload(
+        &self,
+        name: &str,
+        content_type: Option<&str>,
+        bytes: Vec<u8>,
+    ) -> anyhow::Result<UploadedAttachment> {
+        let url = format!("{}/api/v1/attachments", self.base_url);
+        let mut request = self.client.post(&url).query(&[("name", name)]).body(bytes);
+        if let Some(content_type) = content_type {
+            request = request.header(reqwest::header::CONTENT_TYPE, content_type);
+        }
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let resp = request.send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        let mut attachment = resp.json::<UploadedAttachment>().await?;
+        if attachment.url.starts_with('/') {
+            attachment.url = format!("{}{}", self.base_url, attachment.url);
+        }
+        Ok(attachment)
+    }
+}
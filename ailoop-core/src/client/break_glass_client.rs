@@ -0,0 +1,44 @@
+//! HTTP client for emergency (break-glass) approval of unanswered authorizations.
+
+use crate::models::Message;
+use uuid::Uuid;
+
+pub struct BreakGlassClient {
+    base_url: String,
+    client: reqwest::Client,
+    token: String,
+}
+
+impl BreakGlassClient {
+    /// `token` is a break-glass token from the server's `[break_glass]` holders, not an API
+    /// token.
+    pub fn new(base_url: impl Into<String>, token: impl Into<String>) -> Self {
+        let base = base_url.into();
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            base_url: base,
+            client: crate::transport::proxy::http_client(),
+            token: token.into(),
+        }
+    }
+
+    /// Approve the authorization `id`, stating why; returns the approval as sent to the agent.
+    pub async fn approve(&self, id: Uuid, justification: &str) -> anyhow::Result<Message> {
+        let url = format!("{}/api/v1/messages/{}/break-glass", self.base_url, id);
+        let resp = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({ "justification": justification }))
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<Message>().await?)
+    }
+}
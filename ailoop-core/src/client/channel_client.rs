@@ -0,0 +1,187 @@
+//! HTTP client for channel history, hold and incident controls, and history purges.
+
+use crate::models::{Incident, Message, PurgeReport, PurgeRequest};
+use chrono::{DateTime, Utc};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+
+/// Hold state of a channel, as returned by the pause and resume endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelHoldResponse {
+    pub channel: String,
+    pub held: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub held_since: Option<DateTime<Utc>>,
+    /// Messages waiting on the channel.
+    pub queued: usize,
+    /// False when the channel was already in the requested state.
+    pub changed: bool,
+}
+
+/// Incident state of a channel, as returned by the incident start and stop endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncidentResponse {
+    pub channel: String,
+    /// The open incident; on stop, the one that was ended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incident: Option<Incident>,
+    /// False when the channel was already in the requested state.
+    pub changed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelsResponse {
+    channels: Vec<ChannelName>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelName {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    messages: Vec<Message>,
+}
+
+pub struct ChannelClient {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl ChannelClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base = base_url.into();
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            base_url: base,
+            client: crate::transport::proxy::http_client(),
+        }
+    }
+
+    /// Names of the channels the server has seen messages on.
+    pub async fn list(&self) -> anyhow::Result<Vec<String>> {
+        let url = format!("{}/api/channels", self.base_url);
+        let resp = self.client.get(&url).send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Server returned {}", resp.status());
+        }
+        Ok(resp
+            .json::<ChannelsResponse>()
+            .await?
+            .channels
+            .into_iter()
+            .map(|c| c.name)
+            .collect())
+    }
+
+    /// Up to `limit` of the latest messages in the history of `channel`, oldest first; only
+    /// those sent at or after `since`, and during `incident`, when set.
+    pub async fn messages(
+        &self,
+        channel: &str,
+        since: Option<DateTime<Utc>>,
+        incident: Option<&str>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Message>> {
+        let url = format!(
+            "{}/api/channels/{}/messages",
+            self.base_url,
+            utf8_percent_encode(channel, NON_ALPHANUMERIC)
+        );
+        let mut request = self.client.get(&url).query(&[("limit", limit.to_string())]);
+        if let Some(since) = since {
+            request = request.query(&[("since", since.to_rfc3339())]);
+        }
+        if let Some(incident) = incident {
+            request = request.query(&[("incident", incident)]);
+        }
+        let resp = request.send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Server returned {}", resp.status());
+        }
+        Ok(resp.json::<MessagesResponse>().await?.messages)
+    }
+
+    /// Stop delivering prompts on `channel` to humans; agents' messages keep queueing.
+    pub async fn pause(&self, channel: &str) -> anyhow::Result<ChannelHoldResponse> {
+        self.post(channel, "pause").await
+    }
+
+    /// Deliver the prompts queued on `channel` again.
+    pub async fn resume(&self, channel: &str) -> anyhow::Result<ChannelHoldResponse> {
+        self.post(channel, "resume").await
+    }
+
+    /// Put `channel` in incident mode, under `id` when given; an incident already open there
+    /// is kept.
+    pub async fn start_incident(
+        &self,
+        channel: &str,
+        id: Option<&str>,
+        title: Option<&str>,
+    ) -> anyhow::Result<IncidentResponse> {
+        self.incident(
+            channel,
+            "start",
+            serde_json::json!({ "id": id, "title": title }),
+        )
+        .await
+    }
+
+    /// End the incident open on `channel`.
+    pub async fn stop_incident(&self, channel: &str) -> anyhow::Result<IncidentResponse> {
+        self.incident(channel, "stop", serde_json::json!({})).await
+    }
+
+    async fn incident(
+        &self,
+        channel: &str,
+        action: &str,
+        body: serde_json::Value,
+    ) -> anyhow::Result<IncidentResponse> {
+        let url = format!(
+            "{}/api/v1/channels/{}/incident/{}",
+            self.base_url,
+            utf8_percent_encode(channel, NON_ALPHANUMERIC),
+            action
+        );
+        let resp = self.client.post(&url).json(&body).send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<IncidentResponse>().await?)
+    }
+
+    /// Remove old messages from the server's history, or with `request.dry_run` count them.
+    pub async fn purge_history(&self, request: &PurgeRequest) -> anyhow::Result<PurgeReport> {
+        let url = format!("{}/api/v1/history/purge", self.base_url);
+        let resp = self.client.post(&url).json(request).send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<PurgeReport>().await?)
+    }
+
+    async fn post(&self, channel: &str, action: &str) -> anyhow::Result<ChannelHoldResponse> {
+        let url = format!(
+            "{}/api/v1/channels/{}/{}",
+            self.base_url,
+            utf8_percent_encode(channel, NON_ALPHANUMERIC),
+            action
+        );
+        let resp = self.client.post(&url).send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Server returned {}", resp.status());
+        }
+        Ok(resp.json::<ChannelHoldResponse>().await?)
+    }
+}
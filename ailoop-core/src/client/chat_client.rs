@@ -0,0 +1,145 @@
+//! WebSocket client for free-form conversations with the human (`ailoop chat`).
+//!
+//! The agent opens a conversation with its first line; every later line carries the same
+//! `conversation_id`. The human's lines come back on the channel until either side sends a
+//! line with `closed` set.
+
+use anyhow::{Context, Result};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use url::Url;
+use uuid::Uuid;
+
+use super::agent_message;
+use crate::models::{Message, MessageContent, SenderType};
+use crate::transport::proxy::{connect_websocket, WsStream};
+
+/// An open conversation on one channel.
+pub struct ChatSession {
+    channel: String,
+    conversation_id: Uuid,
+    sender: SplitSink<WsStream, WsMessage>,
+    receiver: SplitStream<WsStream>,
+    closed: bool,
+}
+
+impl ChatSession {
+    /// Connect to `server_url` and open a conversation on `channel` with `first_line`.
+    pub async fn open(server_url: &str, channel: &str, first_line: &str) -> Result<Self> {
+        let url = Url::parse(server_url)
+            .with_context(|| format!("Invalid WebSocket URL: {}", server_url))?;
+        let (ws_stream, _) = connect_websocket(&url)
+            .await
+            .context("Failed to connect to WebSocket server")?;
+        let (sender, receiver) = ws_stream.split();
+
+        // The conversation is named after its first message.
+        let conversation_id = Uuid::new_v4();
+        let mut opening = agent_message(
+            channel,
+            MessageContent::Chat {
+                conversation_id,
+                text: first_line.to_string(),
+                closed: false,
+            },
+        );
+        opening.id = conversation_id;
+
+        let mut session = Self {
+            channel: channel.to_string(),
+            conversation_id,
+            sender,
+            receiver,
+            closed: false,
+        };
+        session.send_message(&opening).await?;
+        Ok(session)
+    }
+
+    pub fn conversation_id(&self) -> Uuid {
+        self.conversation_id
+    }
+
+    /// Whether either side has ended the conversation.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Send one line to the human.
+    pub async fn say(&mut self, text: &str) -> Result<()> {
+        self.send_line(text, false).await
+    }
+
+    /// End the conversation, optionally with a last line, and disconnect.
+    pub async fn close(&mut self, text: &str) -> Result<()> {
+        if !self.closed {
+            self.send_line(text, true).await?;
+            self.closed = true;
+        }
+        let _ = self.sender.close().await;
+        Ok(())
+    }
+
+    /// The human's next line in this conversation; `None` once the connection ends. A line
+    /// with `closed` set ends the conversation.
+    pub async fn next_reply(&mut self) -> Result<Option<Message>> {
+        while let Some(frame) = self.receiver.next().await {
+            let text = match frame.context("WebSocket error")? {
+                WsMessage::Text(text) => text,
+                WsMessage::Close(_) => break,
+                _ => continue,
+            };
+            let Ok(message) = serde_json::from_str::<Message>(&text) else {
+                continue;
+            };
+            match &message.content {
+                MessageContent::Chat {
+                    conversation_id,
+                    closed,
+                    ..
+                } if *conversation_id == self.conversation_id
+                    && matches!(message.sender_type, SenderType::Human) =>
+                {
+                    self.closed |= *closed;
+                    return Ok(Some(message));
+                }
+                // Replies to the opening line, e.g. a quota or hook refusal, end the session.
+                MessageContent::Response { answer, .. }
+                    if message.correlation_id == Some(self.conversation_id) =>
+                {
+                    self.closed = true;
+                    anyhow::bail!(
+                        "Server refused the conversation: {}",
+                        answer.as_deref().unwrap_or("no reason given")
+                    );
+                }
+                _ => continue,
+            }
+        }
+        self.closed = true;
+        Ok(None)
+    }
+
+    async fn send_line(&mut self, text: &str, closed: bool) -> Result<()> {
+        let message = agent_message(
+            &self.channel,
+            MessageContent::Chat {
+                conversation_id: self.conversation_id,
+                text: text.to_string(),
+                closed,
+            },
+        );
+        self.send_message(&message).await
+    }
+
+    async fn send_message(&mut self, message: &Message) -> Result<()> {
+        for frame in crate::transport::websocket::message_frames(message)? {
+            self.sender
+                .send(frame)
+                .await
+                .context("Failed to send message")?;
+        }
+        Ok(())
+    }
+}
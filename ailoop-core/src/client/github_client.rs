@@ -0,0 +1,150 @@
+//! HTTP client for GitHub issue and pull request comments (`ailoop export github`).
+
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Public GitHub API; GitHub Enterprise servers set `GITHUB_API_URL` instead.
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// An issue or pull request: `owner/repo#123` or its `https://github.com/...` URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueRef {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+impl FromStr for IssueRef {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        let value = value.trim();
+        let invalid = || {
+            anyhow::anyhow!(
+                "Invalid issue '{}': use owner/repo#123 or the URL of the issue or pull request",
+                value
+            )
+        };
+        let (owner, repo, number) = match value.split_once('#') {
+            Some((repo_path, number)) => {
+                let (owner, repo) = repo_path.split_once('/').ok_or_else(invalid)?;
+                (owner, repo, number)
+            }
+            None => {
+                let path = value
+                    .split_once("://")
+                    .map(|(_, rest)| rest)
+                    .ok_or_else(invalid)?;
+                let parts: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+                match parts.as_slice() {
+                    [_host, owner, repo, "issues" | "pull", number, ..] => (*owner, *repo, *number),
+                    _ => return Err(invalid()),
+                }
+            }
+        };
+        let number = number.parse().map_err(|_| invalid())?;
+        if owner.is_empty() || repo.is_empty() || repo.contains('/') || number == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            number,
+        })
+    }
+}
+
+impl fmt::Display for IssueRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}#{}", self.owner, self.repo, self.number)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedComment {
+    html_url: String,
+}
+
+pub struct GitHubClient {
+    api_url: String,
+    client: reqwest::Client,
+    token: String,
+}
+
+impl GitHubClient {
+    /// Client for the public GitHub API, authenticated with `token`.
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            api_url: GITHUB_API_URL.to_string(),
+            client: crate::transport::proxy::http_client(),
+            token: token.into(),
+        }
+    }
+
+    /// Use another API root, such as `https://github.example.com/api/v3`.
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Comment `body` (markdown) on `issue`, which may be a pull request; returns the URL of
+    /// the comment.
+    pub async fn comment(&self, issue: &IssueRef, body: &str) -> anyhow::Result<String> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/comments",
+            self.api_url, issue.owner, issue.repo, issue.number
+        );
+        let resp = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.token)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", concat!("ailoop/", env!("CARGO_PKG_VERSION")))
+            .json(&serde_json::json!({ "body": body }))
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "GitHub returned {} for {}: {}",
+                resp.status(),
+                issue,
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<CreatedComment>().await?.html_url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_issue_refs() {
+        let issue = IssueRef {
+            owner: "goailoop".to_string(),
+            repo: "ailoop".to_string(),
+            number: 123,
+        };
+        assert_eq!("goailoop/ailoop#123".parse::<IssueRef>().unwrap(), issue);
+        assert_eq!(
+            "https://github.com/goailoop/ailoop/pull/123/files"
+                .parse::<IssueRef>()
+                .unwrap(),
+            issue
+        );
+        assert_eq!(
+            "https://github.com/goailoop/ailoop/issues/123"
+                .parse::<IssueRef>()
+                .unwrap(),
+            issue
+        );
+        assert_eq!(issue.to_string(), "goailoop/ailoop#123");
+        assert!("ailoop#123".parse::<IssueRef>().is_err());
+        assert!("goailoop/ailoop#x".parse::<IssueRef>().is_err());
+        assert!("https://github.com/goailoop/ailoop"
+            .parse::<IssueRef>()
+            .is_err());
+    }
+}
@@ -0,0 +1,71 @@
+//! HTTP client for reading the server's event log.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Events returned by `GET /api/logs`, oldest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventLogPage {
+    pub events: Vec<serde_json::Value>,
+    /// Time of the newest event returned; pass it as `since` to get only later events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_since: Option<DateTime<Utc>>,
+}
+
+pub struct LogsClient {
+    base_url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl LogsClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base = base_url.into();
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            base_url: base,
+            token: None,
+            client: crate::transport::proxy::http_client(),
+        }
+    }
+
+    /// Send `token` as a bearer token; reading the log needs the admin role.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token.filter(|t| !t.is_empty());
+        self
+    }
+
+    /// Events stamped after `since` (the newest `limit`, when given), of type `event` only
+    /// when given.
+    pub async fn events(
+        &self,
+        since: Option<DateTime<Utc>>,
+        event: Option<&str>,
+        limit: Option<usize>,
+    ) -> anyhow::Result<EventLogPage> {
+        let url = format!("{}/api/logs", self.base_url);
+        let mut query: Vec<(&str, String)> = Vec::new();
+        if let Some(since) = since {
+            query.push(("since", since.to_rfc3339()));
+        }
+        if let Some(event) = event {
+            query.push(("event", event.to_string()));
+        }
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        let mut request = self.client.get(&url).query(&query);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let resp = request.send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<EventLogPage>().await?)
+    }
+}
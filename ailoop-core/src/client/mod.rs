@@ -1,15 +1,54 @@
 //! Client helpers for working with an Ailoop server (message and task APIs).
 
 use crate::models::{
-    DecisionOption, DecisionRecommendation, Message, MessageContent, NotificationPriority,
-    SenderType,
+    AgentIdentity, DecisionOption, DecisionRecommendation, Message, MessageContent,
+    NotificationPriority, SenderType,
 };
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
 
+pub mod attachment_client;
+pub mod break_glass_client;
+pub mod channel_client;
+pub mod chat_client;
+pub mod github_client;
+pub mod logs_client;
 pub mod pending_client;
+pub mod privacy_client;
+pub mod queue_client;
+pub mod read_client;
+pub mod schedule_client;
+pub mod session_client;
+pub mod stats_client;
 pub mod task_client;
+pub mod team_client;
+
+/// Agent name given on the command line (`--agent-name`).
+static AGENT_NAME: OnceLock<String> = OnceLock::new();
+
+/// Name this process's agent messages with `name`, overriding `AILOOP_AGENT_NAME`. Only the
+/// first call has an effect.
+pub fn set_agent_name(name: String) {
+    let _ = AGENT_NAME.set(name);
+}
+
+/// Identity attached to agent messages sent by this process.
+pub fn agent_identity() -> AgentIdentity {
+    let mut identity = AgentIdentity::detect();
+    if let Some(name) = AGENT_NAME.get() {
+        identity.name = Some(name.clone());
+    }
+    identity
+}
+
+/// An agent message carrying [`agent_identity`].
+pub fn agent_message(channel: &str, content: MessageContent) -> Message {
+    Message::new(channel.to_string(), SenderType::Agent, content).with_agent(agent_identity())
+}
 
 /// Send a structured decision and wait for the human's selection (returns the Response message).
+/// With `parent_id`, the decision follows up on that earlier prompt and is shown with its thread.
 #[allow(clippy::too_many_arguments)]
 pub async fn ask_decision(
     server_url: &str,
@@ -20,11 +59,56 @@ pub async fn ask_decision(
     options: Vec<DecisionOption>,
     recommendation: Option<DecisionRecommendation>,
     timeout_secs: u32,
+    expect_file: bool,
+    parent_id: Option<uuid::Uuid>,
 ) -> Result<Option<Message>> {
     crate::models::validate_decision(&options, &recommendation).map_err(|e| anyhow::anyhow!(e))?;
-    let message = Message::new(
-        channel.to_string(),
-        SenderType::Agent,
+    let message = decision(
+        channel,
+        decision_id,
+        summary,
+        context_markdown,
+        options,
+        recommendation,
+        timeout_secs,
+        expect_file,
+        parent_id,
+    );
+    send_prompt(server_url, message, timeout_secs).await
+}
+
+/// Send a prompt built by [`decision`], [`authorization`] or the like and wait for its
+/// response, e.g. after assigning it with [`Message::with_assignee`].
+pub async fn send_prompt(
+    server_url: &str,
+    message: Message,
+    timeout_secs: u32,
+) -> Result<Option<Message>> {
+    let channel = message.channel.clone();
+    crate::transport::websocket::send_message_and_wait_response(
+        server_url.to_string(),
+        channel,
+        message,
+        timeout_secs,
+    )
+    .await
+}
+
+/// Agent decision as sent by [`ask_decision`].
+#[allow(clippy::too_many_arguments)]
+pub fn decision(
+    channel: &str,
+    decision_id: String,
+    summary: String,
+    context_markdown: Option<String>,
+    options: Vec<DecisionOption>,
+    recommendation: Option<DecisionRecommendation>,
+    timeout_secs: u32,
+    expect_file: bool,
+    parent_id: Option<uuid::Uuid>,
+) -> Message {
+    let mut message = agent_message(
+        channel,
         MessageContent::Decision {
             decision_id,
             summary,
@@ -34,13 +118,24 @@ pub async fn ask_decision(
             timeout_seconds: timeout_secs,
         },
     );
-    crate::transport::websocket::send_message_and_wait_response(
-        server_url.to_string(),
-        channel.to_string(),
-        message,
-        timeout_secs,
+    if expect_file {
+        // Asks the responder for a file; the reply's `metadata.attachment` describes it.
+        message.metadata = Some(serde_json::json!({ "expect_file": true }));
+    }
+    message.parent_id = parent_id;
+    message
+}
+
+/// Agent authorization request as sent by [`authorize`].
+pub fn authorization(channel: &str, action: &str, timeout_secs: u32) -> Message {
+    agent_message(
+        channel,
+        MessageContent::Authorization {
+            action: action.to_string(),
+            context: None,
+            timeout_seconds: timeout_secs,
+        },
     )
-    .await
 }
 
 /// Request authorization through the WebSocket API and wait for a response.
@@ -50,15 +145,7 @@ pub async fn authorize(
     action: &str,
     timeout_secs: u32,
 ) -> Result<Option<Message>> {
-    let message = Message::new(
-        channel.to_string(),
-        SenderType::Agent,
-        MessageContent::Authorization {
-            action: action.to_string(),
-            context: None,
-            timeout_seconds: timeout_secs,
-        },
-    );
+    let message = authorization(channel, action, timeout_secs);
 
     crate::transport::websocket::send_message_and_wait_response(
         server_url.to_string(),
@@ -69,14 +156,36 @@ pub async fn authorize(
     .await
 }
 
+/// Agent notification as sent by [`say`]; `priority` is low, normal, high or urgent.
+pub fn notification(channel: &str, text: &str, priority: &str) -> Message {
+    agent_message(
+        channel,
+        MessageContent::Notification {
+            text: text.to_string(),
+            priority: map_priority(priority),
+        },
+    )
+}
+
 /// Send a notification message through the WebSocket API without waiting for a response.
 pub async fn say(server_url: &str, channel: &str, text: &str, priority: &str) -> Result<()> {
-    let message = Message::new(
+    let message = notification(channel, text, priority);
+
+    crate::transport::websocket::send_message_no_response(
+        server_url.to_string(),
         channel.to_string(),
-        SenderType::Agent,
-        MessageContent::Notification {
+        message,
+    )
+    .await
+}
+
+/// Send a status pulse ("running tests") through the WebSocket API. Providers show it as a
+/// transient indicator; it is not kept in history.
+pub async fn status(server_url: &str, channel: &str, text: &str) -> Result<()> {
+    let message = agent_message(
+        channel,
+        MessageContent::Status {
             text: text.to_string(),
-            priority: map_priority(priority),
         },
     );
 
@@ -89,14 +198,8 @@ pub async fn say(server_url: &str, channel: &str, text: &str, priority: &str) ->
 }
 
 /// Request navigation through the WebSocket API without waiting for a response.
-pub async fn navigate(server_url: &str, channel: &str, url: &str) -> Result<()> {
-    let message = Message::new(
-        channel.to_string(),
-        SenderType::Agent,
-        MessageContent::Navigate {
-            url: url.to_string(),
-        },
-    );
+pub async fn navigate(server_url: &str, channel: &str, url: &str, timeout_secs: u32) -> Result<()> {
+    let message = navigation(channel, url, timeout_secs);
 
     crate::transport::websocket::send_message_no_response(
         server_url.to_string(),
@@ -106,6 +209,83 @@ pub async fn navigate(server_url: &str, channel: &str, url: &str) -> Result<()>
     .await
 }
 
+/// Request navigation through the WebSocket API and wait for the human to open the URL or not.
+/// An approved response means it was opened.
+pub async fn navigate_and_wait(
+    server_url: &str,
+    channel: &str,
+    url: &str,
+    timeout_secs: u32,
+) -> Result<Option<Message>> {
+    let message = navigation(channel, url, timeout_secs);
+
+    crate::transport::websocket::send_message_and_wait_response(
+        server_url.to_string(),
+        channel.to_string(),
+        message,
+        timeout_secs,
+    )
+    .await
+}
+
+/// A navigate request for `url`; `timeout_secs` 0 uses the server default.
+pub fn navigation(channel: &str, url: &str, timeout_secs: u32) -> Message {
+    agent_message(
+        channel,
+        MessageContent::Navigate {
+            url: url.to_string(),
+            timeout_seconds: timeout_secs,
+        },
+    )
+}
+
+/// What became of one message of a fan-out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    /// Accepted by the server.
+    Sent,
+    /// Refused by the server (quota, inbound hook).
+    Rejected,
+    /// Saved to the offline outbox because the server was unreachable.
+    Spooled,
+}
+
+/// Per-channel result of sending one announcement to several channels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelDelivery {
+    pub channel: String,
+    pub message_id: uuid::Uuid,
+    pub status: DeliveryStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Send `messages` (typically the same announcement for several channels) over one WebSocket
+/// connection, reporting what the server did with each.
+pub async fn send_to_channels(
+    server_url: &str,
+    messages: Vec<Message>,
+) -> Result<Vec<ChannelDelivery>> {
+    let rejections =
+        crate::transport::websocket::send_messages_no_response(server_url.to_string(), &messages)
+            .await?;
+    Ok(messages
+        .into_iter()
+        .zip(rejections)
+        .map(|(message, rejection)| ChannelDelivery {
+            channel: message.channel,
+            message_id: message.id,
+            status: if rejection.is_some() {
+                DeliveryStatus::Rejected
+            } else {
+                DeliveryStatus::Sent
+            },
+            error: rejection,
+        })
+        .collect())
+}
+
 fn map_priority(priority: &str) -> NotificationPriority {
     match priority.to_lowercase().as_str() {
         "low" => NotificationPriority::Low,
@@ -10,17 +10,44 @@ pub struct PendingItemResponse {
     pub channel: String,
     pub position: usize,
     pub label: String,
+    /// Responder who claimed the prompt; others leave it to them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claimed_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claimed_since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A responder's claim on a pending prompt, as taken or given back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptClaimResponse {
+    pub message_id: Uuid,
+    pub channel: String,
+    pub responder: String,
+    pub since: chrono::DateTime<chrono::Utc>,
+    /// False once released.
+    pub claimed: bool,
+}
+
+/// A channel whose prompts are held back from humans.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeldChannelResponse {
+    pub channel: String,
+    pub held_since: chrono::DateTime<chrono::Utc>,
+    pub queued: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PendingListResponse {
     pub items: Vec<PendingItemResponse>,
     pub total_count: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub held_channels: Vec<HeldChannelResponse>,
 }
 
 pub struct PendingClient {
     base_url: String,
     client: reqwest::Client,
+    token: Option<String>,
 }
 
 impl PendingClient {
@@ -29,19 +56,106 @@ impl PendingClient {
         let base = base.trim_end_matches('/').to_string();
         Self {
             base_url: base,
-            client: reqwest::Client::new(),
+            client: crate::transport::proxy::http_client(),
+            token: None,
         }
     }
 
+    /// Send `token` as a bearer token; claiming needs the responder role.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token.filter(|t| !t.is_empty());
+        self
+    }
+
     pub async fn list_pending(&self, channel: Option<&str>) -> anyhow::Result<PendingListResponse> {
         let mut url = format!("{}/api/v1/pending", self.base_url);
         if let Some(ch) = channel {
             url.push_str(&format!("?channel={}", ch));
         }
-        let resp = self.client.get(&url).send().await?;
+        let mut request = self.client.get(&url);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let resp = request.send().await?;
         if !resp.status().is_success() {
             anyhow::bail!("Server returned {}", resp.status());
         }
         Ok(resp.json::<PendingListResponse>().await?)
     }
+
+    /// Answer the pending prompt `message_id` as `responder` (ignored when the token carries an
+    /// SSO identity).
+    pub async fn respond(
+        &self,
+        message_id: Uuid,
+        answer: Option<&str>,
+        response_type: crate::models::ResponseType,
+        responder: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let url = format!("{}/api/v1/messages/{}/response", self.base_url, message_id);
+        let mut request = self.client.post(&url).json(&serde_json::json!({
+            "answer": answer,
+            "response_type": response_type,
+            "responder": responder,
+        }));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let resp = request.send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(())
+    }
+
+    /// Claim the pending prompt `message_id` as `responder` (ignored when the token carries an
+    /// SSO identity).
+    pub async fn claim(
+        &self,
+        message_id: Uuid,
+        responder: Option<&str>,
+    ) -> anyhow::Result<PromptClaimResponse> {
+        self.post_claim(message_id, "claim", responder).await
+    }
+
+    /// Release `responder`'s claim on `message_id`.
+    pub async fn release(
+        &self,
+        message_id: Uuid,
+        responder: Option<&str>,
+    ) -> anyhow::Result<PromptClaimResponse> {
+        self.post_claim(message_id, "release", responder).await
+    }
+
+    async fn post_claim(
+        &self,
+        message_id: Uuid,
+        action: &str,
+        responder: Option<&str>,
+    ) -> anyhow::Result<PromptClaimResponse> {
+        let url = format!(
+            "{}/api/v1/messages/{}/{}",
+            self.base_url, message_id, action
+        );
+        let mut request = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "responder": responder }));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let resp = request.send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<PromptClaimResponse>().await?)
+    }
 }
@@ -0,0 +1,50 @@
+//! HTTP client for exporting and erasing a responder's data.
+
+use crate::models::{ResponderErasure, ResponderExport, ResponderRequest};
+use serde::de::DeserializeOwned;
+
+pub struct PrivacyClient {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl PrivacyClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base = base_url.into();
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            base_url: base,
+            client: crate::transport::proxy::http_client(),
+        }
+    }
+
+    /// Everything the server holds that is attributed to `responder`.
+    pub async fn export(&self, responder: &str) -> anyhow::Result<ResponderExport> {
+        self.post("export", responder).await
+    }
+
+    /// Remove the responses attributed to `responder` and the files sent with them.
+    pub async fn erase(&self, responder: &str) -> anyhow::Result<ResponderErasure> {
+        self.post("erase", responder).await
+    }
+
+    async fn post<T: DeserializeOwned>(&self, action: &str, responder: &str) -> anyhow::Result<T> {
+        let url = format!("{}/api/v1/privacy/{}", self.base_url, action);
+        let resp = self
+            .client
+            .post(&url)
+            .json(&ResponderRequest {
+                responder: responder.to_string(),
+            })
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<T>().await?)
+    }
+}
@@ -0,0 +1,110 @@
+//! HTTP client for inspecting and emptying the server's message queues.
+
+use chrono::{DateTime, Utc};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A message waiting on a channel to be put to a human.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedMessageResponse {
+    pub id: Uuid,
+    /// Content type, such as `decision` or `notification`.
+    pub kind: String,
+    pub channel: String,
+    /// The question, notification text or URL.
+    pub label: String,
+    pub queued_at: DateTime<Utc>,
+    pub age_seconds: i64,
+}
+
+/// The queue of one channel, oldest message first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelQueueResponse {
+    pub channel: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub held_since: Option<DateTime<Utc>>,
+    pub messages: Vec<QueuedMessageResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueListResponse {
+    pub channels: Vec<ChannelQueueResponse>,
+    pub total_count: usize,
+}
+
+/// Messages removed from a queue. Prompts among them were answered `cancelled`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueDropResponse {
+    pub dropped: Vec<QueuedMessageResponse>,
+}
+
+pub struct QueueClient {
+    base_url: String,
+    client: reqwest::Client,
+    token: Option<String>,
+}
+
+impl QueueClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base = base_url.into();
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            base_url: base,
+            client: crate::transport::proxy::http_client(),
+            token: None,
+        }
+    }
+
+    /// Send `token` as a bearer token; dropping messages needs the admin role.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token.filter(|t| !t.is_empty());
+        self
+    }
+
+    /// Messages queued on every channel, or only on `channel`.
+    pub async fn list(&self, channel: Option<&str>) -> anyhow::Result<QueueListResponse> {
+        let mut url = format!("{}/api/queues", self.base_url);
+        if let Some(ch) = channel {
+            url.push_str(&format!(
+                "?channel={}",
+                utf8_percent_encode(ch, NON_ALPHANUMERIC)
+            ));
+        }
+        self.send(self.client.get(&url)).await
+    }
+
+    /// Remove the queued message `id`, whichever channel it is on.
+    pub async fn drop_message(&self, id: Uuid) -> anyhow::Result<QueueDropResponse> {
+        let url = format!("{}/api/queues/messages/{}", self.base_url, id);
+        self.send(self.client.delete(&url)).await
+    }
+
+    /// Remove every message queued on `channel`.
+    pub async fn clear(&self, channel: &str) -> anyhow::Result<QueueDropResponse> {
+        let url = format!(
+            "{}/api/queues/{}",
+            self.base_url,
+            utf8_percent_encode(channel, NON_ALPHANUMERIC)
+        );
+        self.send(self.client.delete(&url)).await
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        mut request: reqwest::RequestBuilder,
+    ) -> anyhow::Result<T> {
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let resp = request.send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<T>().await?)
+    }
+}
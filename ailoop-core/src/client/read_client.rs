@@ -0,0 +1,105 @@
+//! HTTP client for notification read state (`ailoop status --unread`).
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Agent notifications a responder has not read on one channel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelUnread {
+    pub channel: String,
+    pub unread: usize,
+    /// How many of them are high or urgent priority.
+    pub urgent: usize,
+    /// When the oldest of them was sent.
+    pub oldest: Option<DateTime<Utc>>,
+}
+
+/// Unread notifications per channel, as returned by `/api/v1/unread` and the mark-read
+/// endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnreadCounts {
+    /// Whose counts these are: `@name` for a team member, else the responder as given.
+    pub reader: String,
+    /// Channels with unread notifications, by name.
+    pub channels: Vec<ChannelUnread>,
+    pub total: usize,
+}
+
+pub struct ReadClient {
+    base_url: String,
+    client: reqwest::Client,
+    token: Option<String>,
+}
+
+impl ReadClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base = base_url.into();
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            base_url: base,
+            client: crate::transport::proxy::http_client(),
+            token: None,
+        }
+    }
+
+    /// Send `token` as a bearer token; marking read needs the responder role.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token.filter(|t| !t.is_empty());
+        self
+    }
+
+    /// Unread counts of `responder` (ignored when the token carries an SSO identity).
+    pub async fn unread(&self, responder: Option<&str>) -> anyhow::Result<UnreadCounts> {
+        let url = format!("{}/api/v1/unread", self.base_url);
+        let mut request = self.client.get(&url);
+        if let Some(responder) = responder {
+            request = request.query(&[("responder", responder)]);
+        }
+        self.send(request).await
+    }
+
+    /// Mark one notification read by `responder`.
+    pub async fn mark_read(
+        &self,
+        message_id: Uuid,
+        responder: Option<&str>,
+    ) -> anyhow::Result<UnreadCounts> {
+        self.post(&format!("messages/{}/read", message_id), responder)
+            .await
+    }
+
+    /// Mark every notification on `channel` read by `responder`.
+    pub async fn mark_channel_read(
+        &self,
+        channel: &str,
+        responder: Option<&str>,
+    ) -> anyhow::Result<UnreadCounts> {
+        self.post(&format!("channels/{}/read", channel), responder)
+            .await
+    }
+
+    async fn post(&self, path: &str, responder: Option<&str>) -> anyhow::Result<UnreadCounts> {
+        let url = format!("{}/api/v1/{}", self.base_url, path);
+        let request = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "responder": responder }));
+        self.send(request).await
+    }
+
+    async fn send(&self, mut request: reqwest::RequestBuilder) -> anyhow::Result<UnreadCounts> {
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let resp = request.send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<UnreadCounts>().await?)
+    }
+}
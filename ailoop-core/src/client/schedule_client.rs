@@ -0,0 +1,169 @@
+//! HTTP client for scheduled messages and recurring reminders.
+
+use crate::models::{
+    CronSchedule, Message, NotificationPriority, Reminder, ReminderRequest, ScheduleRequest,
+    ScheduledMessage,
+};
+use chrono::{DateTime, Utc};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct ScheduleListResponse {
+    scheduled: Vec<ScheduledMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReminderListResponse {
+    reminders: Vec<Reminder>,
+}
+
+pub struct ScheduleClient {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl ScheduleClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base = base_url.into();
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            base_url: base,
+            client: crate::transport::proxy::http_client(),
+        }
+    }
+
+    /// Have the server send `message` at `at`.
+    pub async fn schedule(
+        &self,
+        at: DateTime<Utc>,
+        message: Message,
+    ) -> anyhow::Result<ScheduledMessage> {
+        let url = format!("{}/api/v1/schedule", self.base_url);
+        let resp = self
+            .client
+            .post(&url)
+            .json(&ScheduleRequest { at, message })
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<ScheduledMessage>().await?)
+    }
+
+    /// Messages waiting to be sent, soonest first, optionally on one channel only.
+    pub async fn list(&self, channel: Option<&str>) -> anyhow::Result<Vec<ScheduledMessage>> {
+        let mut url = format!("{}/api/v1/schedule", self.base_url);
+        if let Some(channel) = channel {
+            url.push_str(&format!(
+                "?channel={}",
+                utf8_percent_encode(channel, NON_ALPHANUMERIC)
+            ));
+        }
+        let resp = self.client.get(&url).send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Server returned {}", resp.status());
+        }
+        Ok(resp.json::<ScheduleListResponse>().await?.scheduled)
+    }
+
+    /// Drop a scheduled message before it is sent. `None` if there was no such message.
+    pub async fn cancel(&self, id: Uuid) -> anyhow::Result<Option<ScheduledMessage>> {
+        let url = format!("{}/api/v1/schedule/{}", self.base_url, id);
+        let resp = self.client.delete(&url).send().await?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            anyhow::bail!("Server returned {}", resp.status());
+        }
+        Ok(Some(resp.json::<ScheduledMessage>().await?))
+    }
+
+    /// Have the server send `text` on `channel` whenever `schedule` matches.
+    pub async fn add_reminder(
+        &self,
+        channel: &str,
+        schedule: CronSchedule,
+        text: &str,
+        priority: Option<NotificationPriority>,
+    ) -> anyhow::Result<Reminder> {
+        let url = format!("{}/api/v1/reminders", self.base_url);
+        let request = ReminderRequest {
+            channel: channel.to_string(),
+            schedule,
+            text: text.to_string(),
+            priority,
+        };
+        let resp = self.client.post(&url).json(&request).send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<Reminder>().await?)
+    }
+
+    /// Reminders, soonest first, optionally on one channel only.
+    pub async fn reminders(&self, channel: Option<&str>) -> anyhow::Result<Vec<Reminder>> {
+        let mut url = format!("{}/api/v1/reminders", self.base_url);
+        if let Some(channel) = channel {
+            url.push_str(&format!(
+                "?channel={}",
+                utf8_percent_encode(channel, NON_ALPHANUMERIC)
+            ));
+        }
+        let resp = self.client.get(&url).send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Server returned {}", resp.status());
+        }
+        Ok(resp.json::<ReminderListResponse>().await?.reminders)
+    }
+
+    /// Stop a reminder. `None` if there was no such reminder.
+    pub async fn remove_reminder(&self, id: Uuid) -> anyhow::Result<Option<Reminder>> {
+        let url = format!("{}/api/v1/reminders/{}", self.base_url, id);
+        self.reminder_request(self.client.delete(&url)).await
+    }
+
+    /// Let the next `count` occurrences of a reminder pass without sending it.
+    pub async fn skip_reminder(&self, id: Uuid, count: u32) -> anyhow::Result<Option<Reminder>> {
+        let url = format!(
+            "{}/api/v1/reminders/{}/skip?count={}",
+            self.base_url, id, count
+        );
+        self.reminder_request(self.client.post(&url)).await
+    }
+
+    /// Mark a reminder's notifications as seen.
+    pub async fn acknowledge_reminder(&self, id: Uuid) -> anyhow::Result<Option<Reminder>> {
+        let url = format!("{}/api/v1/reminders/{}/ack", self.base_url, id);
+        self.reminder_request(self.client.post(&url)).await
+    }
+
+    async fn reminder_request(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> anyhow::Result<Option<Reminder>> {
+        let resp = request.send().await?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(Some(resp.json::<Reminder>().await?))
+    }
+}
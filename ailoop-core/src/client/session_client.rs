@@ -0,0 +1,37 @@
+//! HTTP client for agent session transcripts.
+
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+pub struct SessionClient {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl SessionClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base = base_url.into();
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            base_url: base,
+            client: crate::transport::proxy::http_client(),
+        }
+    }
+
+    /// Transcript of `session_id` as rendered by the server; `format` is `md` or `json`.
+    pub async fn transcript(&self, session_id: &str, format: &str) -> anyhow::Result<String> {
+        let url = format!(
+            "{}/api/sessions/{}/transcript?format={}",
+            self.base_url,
+            utf8_percent_encode(session_id, NON_ALPHANUMERIC),
+            format
+        );
+        let resp = self.client.get(&url).send().await?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            anyhow::bail!("Session '{}' not found on the server", session_id);
+        }
+        if !resp.status().is_success() {
+            anyhow::bail!("Server returned {}", resp.status());
+        }
+        Ok(resp.text().await?)
+    }
+}
@@ -0,0 +1,43 @@
+//! HTTP client for the server's response metrics.
+
+use crate::models::ResponseMetrics;
+use chrono::{DateTime, Utc};
+
+pub struct StatsClient {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl StatsClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base = base_url.into();
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            base_url: base,
+            client: crate::transport::proxy::http_client(),
+        }
+    }
+
+    /// Prompt-to-answer latency, timeouts and approvals per channel and responder, over the
+    /// prompts asked or settled since `since` (everything the server remembers when `None`).
+    pub async fn response_metrics(
+        &self,
+        since: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<ResponseMetrics> {
+        let url = format!("{}/api/stats", self.base_url);
+        let mut request = self.client.get(&url);
+        if let Some(since) = since {
+            request = request.query(&[("since", since.to_rfc3339())]);
+        }
+        let resp = request.send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        let mut body: serde_json::Value = resp.json().await?;
+        Ok(serde_json::from_value(body["responses"].take())?)
+    }
+}
@@ -42,7 +42,7 @@ impl TaskClient {
         let base = base.trim_end_matches('/').to_string();
         Self {
             base_url: base,
-            client: Client::new(),
+            client: crate::transport::proxy::http_client(),
         }
     }
 
@@ -0,0 +1,130 @@
+//! HTTP client for a responder's own team status and keyword subscriptions (`ailoop me`).
+
+use crate::models::AwayStatus;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A member's away status, as returned by `/api/v1/me/away` and `/api/v1/me/back`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwayResponse {
+    /// `@name`, as used in `metadata.member`.
+    pub member: String,
+    /// `None` once they are back.
+    pub away: Option<AwayStatus>,
+}
+
+/// Keywords a member watches, as returned by `/api/v1/me/watch` and `/api/v1/me/unwatch`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchResponse {
+    /// `@name`, as used in `metadata.member`.
+    pub member: String,
+    pub keywords: Vec<String>,
+}
+
+pub struct TeamClient {
+    base_url: String,
+    client: reqwest::Client,
+    token: Option<String>,
+}
+
+impl TeamClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base = base_url.into();
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            base_url: base,
+            client: crate::transport::proxy::http_client(),
+            token: None,
+        }
+    }
+
+    /// Send `token` as a bearer token; these requests need the responder role.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token.filter(|t| !t.is_empty());
+        self
+    }
+
+    /// Mark `responder` (ignored when the token carries an SSO identity) away until `until`, or
+    /// until [`Self::back`].
+    pub async fn away(
+        &self,
+        responder: Option<&str>,
+        until: Option<DateTime<Utc>>,
+        note: Option<&str>,
+    ) -> anyhow::Result<AwayResponse> {
+        self.post(
+            "away",
+            serde_json::json!({ "responder": responder, "until": until, "note": note }),
+        )
+        .await
+    }
+
+    /// Mark `responder` back.
+    pub async fn back(&self, responder: Option<&str>) -> anyhow::Result<AwayResponse> {
+        self.post("back", serde_json::json!({ "responder": responder }))
+            .await
+    }
+
+    /// Keywords `responder` watches.
+    pub async fn watching(&self, responder: Option<&str>) -> anyhow::Result<WatchResponse> {
+        let url = format!("{}/api/v1/me/watch", self.base_url);
+        let mut request = self.client.get(&url);
+        if let Some(responder) = responder {
+            request = request.query(&[("responder", responder)]);
+        }
+        self.send(request).await
+    }
+
+    /// Alert `responder` when a message contains one of `keywords`.
+    pub async fn watch(
+        &self,
+        responder: Option<&str>,
+        keywords: &[String],
+    ) -> anyhow::Result<WatchResponse> {
+        self.post(
+            "watch",
+            serde_json::json!({ "responder": responder, "keywords": keywords }),
+        )
+        .await
+    }
+
+    /// Stop watching `keywords`, or every keyword when it is empty.
+    pub async fn unwatch(
+        &self,
+        responder: Option<&str>,
+        keywords: &[String],
+    ) -> anyhow::Result<WatchResponse> {
+        self.post(
+            "unwatch",
+            serde_json::json!({ "responder": responder, "keywords": keywords }),
+        )
+        .await
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        action: &str,
+        body: serde_json::Value,
+    ) -> anyhow::Result<T> {
+        let url = format!("{}/api/v1/me/{}", self.base_url, action);
+        self.send(self.client.post(&url).json(&body)).await
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        mut request: reqwest::RequestBuilder,
+    ) -> anyhow::Result<T> {
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let resp = request.send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "Server returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            );
+        }
+        Ok(resp.json::<T>().await?)
+    }
+}
@@ -22,9 +22,27 @@ pub mod channel;
 pub mod client;
 pub mod models;
 pub mod parser;
+pub mod paths;
 pub mod server;
 pub mod services;
 pub mod terminal;
 pub mod transport;
 
-pub use client::pending_client::{PendingClient, PendingItemResponse, PendingListResponse};
+pub use client::attachment_client::{AttachmentClient, UploadedAttachment};
+pub use client::break_glass_client::BreakGlassClient;
+pub use client::channel_client::{ChannelClient, ChannelHoldResponse, IncidentResponse};
+pub use client::github_client::{GitHubClient, IssueRef};
+pub use client::logs_client::{EventLogPage, LogsClient};
+pub use client::pending_client::{
+    HeldChannelResponse, PendingClient, PendingItemResponse, PendingListResponse,
+    PromptClaimResponse,
+};
+pub use client::privacy_client::PrivacyClient;
+pub use client::queue_client::{
+    ChannelQueueResponse, QueueClient, QueueDropResponse, QueueListResponse, QueuedMessageResponse,
+};
+pub use client::read_client::{ChannelUnread, ReadClient, UnreadCounts};
+pub use client::schedule_client::ScheduleClient;
+pub use client::session_client::SessionClient;
+pub use client::stats_client::StatsClient;
+pub use client::team_client::{AwayResponse, TeamClient, WatchResponse};
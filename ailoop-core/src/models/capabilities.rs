@@ -0,0 +1,75 @@
+//! What a server supports (`GET /api/capabilities`, WebSocket `{"type": "capabilities"}`), so
+//! clients can adapt instead of assuming.
+
+use serde::{Deserialize, Serialize};
+
+/// `content.type` values a server accepts.
+pub const CONTENT_TYPES: &[&str] = &[
+    "decision",
+    "authorization",
+    "notification",
+    "response",
+    "navigate",
+    "chat",
+    "status",
+    "task_create",
+    "task_update",
+    "task_dependency_add",
+    "task_dependency_remove",
+];
+
+/// `response_type` values a server sends.
+pub const RESPONSE_TYPES: &[&str] = &[
+    "text",
+    "authorization_approved",
+    "authorization_denied",
+    "timeout",
+    "cancelled",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Server version
+    pub version: String,
+    /// Providers that are running (e.g. `telegram`, `twilio`, `email`, exec sink names)
+    pub providers: Vec<String>,
+    pub content_types: Vec<String>,
+    pub response_types: Vec<String>,
+    /// Whether prompt responses carry a signature (`metadata.signature`)
+    #[serde(default)]
+    pub signed_responses: bool,
+    /// Answers are streamed while typed to connections that accept `typing`; absent when off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typing: Option<super::TypingCapability>,
+    pub limits: ServerLimits,
+}
+
+/// Sizes and rates a server holds clients to. `None` means no limit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServerLimits {
+    /// Configured `max_message_size`, in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_message_size: Option<usize>,
+    /// Largest file accepted by `POST /api/v1/attachments`, in bytes
+    pub max_attachment_bytes: usize,
+    /// Largest WebSocket frame accepted; larger messages are sent in `chunk` frames
+    #[serde(default)]
+    pub max_frame_bytes: usize,
+    /// Largest message accepted in `chunk` frames, in bytes of JSON
+    #[serde(default)]
+    pub max_chunked_message_bytes: usize,
+    /// Messages kept per channel in history
+    pub max_history_per_channel: usize,
+    /// Per-client quotas (see `GET /api/usage`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_messages_per_hour: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompts_per_hour: Option<u32>,
+}
+
+impl Capabilities {
+    /// Whether the server accepts messages with this `content.type`.
+    pub fn supports(&self, content_type: &str) -> bool {
+        self.content_types.iter().any(|t| t == content_type)
+    }
+}
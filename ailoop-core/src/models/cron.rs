@@ -0,0 +1,243 @@
+//! Cron expressions for recurring reminders (`ailoop remind add "0 9 * * MON" ...`)
+//!
+//! The usual five fields: minute, hour, day of month, month and day of week. Each field takes
+//! `*`, numbers, ranges (`1-5`), lists (`1,15`) and steps (`*/15`, `9-17/2`); months and weekdays
+//! also take names (`JAN`, `MON`), and `7` is Sunday like `0`. As in cron, when both the day of
+//! month and the day of week are restricted a day matching either one matches.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// How far ahead to look for the next match before giving up (e.g. `0 0 30 2 *`).
+const SEARCH_DAYS: i64 = 366 * 5;
+
+/// A parsed five-field cron expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// The expression as written.
+    pub fn as_str(&self) -> &str {
+        &self.expression
+    }
+
+    /// The first matching minute strictly after `after`, in `after`'s time zone. Minutes that do
+    /// not exist in that zone (a daylight saving gap) are skipped.
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut time = start;
+        while time - start < Duration::days(SEARCH_DAYS) {
+            if !self.matches_day(time.date()) {
+                time = (time.date() + Duration::days(1)).and_hms_opt(0, 0, 0)?;
+            } else if !bit(self.hours, time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+            } else if !bit(self.minutes, time.minute()) {
+                time += Duration::minutes(1);
+            } else if let Some(found) = tz.from_local_datetime(&time).earliest() {
+                return Some(found);
+            } else {
+                time += Duration::minutes(1);
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if !bit(self.months, date.month()) {
+            return false;
+        }
+        let day = bit(self.days, date.day());
+        let weekday = bit(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => day,
+            (true, false) => weekday,
+            (false, false) => day || weekday,
+        }
+    }
+}
+
+fn bit(mask: u64, value: u32) -> bool {
+    mask & (1u64 << value) != 0
+}
+
+/// Parse one field into a bit mask of the values in `min..=max`.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], name: &str) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u32, String> {
+        let upper = s.to_ascii_uppercase();
+        let parsed = match names.iter().position(|n| *n == upper) {
+            Some(index) => index as u32 + min,
+            None => s.parse().map_err(|_| format!("invalid {} '{}'", name, s))?,
+        };
+        if parsed < min || parsed > max {
+            return Err(format!("{} {} is out of range {}-{}", name, s, min, max));
+        }
+        Ok(parsed)
+    };
+
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("invalid step '{}' in {}", step, name))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (first, last) = if range == "*" {
+            (min, max)
+        } else if let Some((first, last)) = range.split_once('-') {
+            (value(first)?, value(last)?)
+        } else {
+            let single = value(range)?;
+            // `5/15` means from 5 to the end of the range in steps of 15.
+            (single, if part.contains('/') { max } else { single })
+        };
+        // `FRI-SUN` and `5-7` wrap past the end of the week.
+        let last = if name == "weekday" && last < first && last == 0 {
+            7
+        } else {
+            last
+        };
+        if first > last {
+            return Err(format!("invalid {} range '{}'", name, range));
+        }
+        for v in (first..=last).step_by(step as usize) {
+            // 7 is Sunday, like 0.
+            mask |= 1u64 << if name == "weekday" { v % 7 } else { v };
+        }
+    }
+    Ok(mask)
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "'{}' must have five fields: minute hour day-of-month month day-of-week",
+                s.trim()
+            ));
+        };
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_field(minute, 0, 59, &[], "minute")?,
+            hours: parse_field(hour, 0, 23, &[], "hour")?,
+            days: parse_field(day, 1, 31, &[], "day of month")?,
+            months: parse_field(month, 1, 12, &MONTHS, "month")?,
+            weekdays: parse_field(weekday, 0, 7, &WEEKDAYS, "weekday")?,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+impl Serialize for CronSchedule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.expression)
+    }
+}
+
+impl<'de> Deserialize<'de> for CronSchedule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(expr: &str, after: &str) -> String {
+        let cron: CronSchedule = expr.parse().unwrap();
+        cron.next_after(&at(after)).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn finds_the_next_occurrence() {
+        // 2025-06-30 is a Monday.
+        assert_eq!(
+            next("0 9 * * MON", "2025-06-30T08:59:30Z"),
+            "2025-06-30T09:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 9 * * MON", "2025-06-30T09:00:00Z"),
+            "2025-07-07T09:00:00+00:00"
+        );
+        assert_eq!(
+            next("*/15 9-17 * * 1-5", "2025-07-04T17:50:00Z"),
+            "2025-07-07T09:00:00+00:00"
+        );
+        assert_eq!(
+            next("30 8 1 jan,jul *", "2025-01-02T00:00:00Z"),
+            "2025-07-01T08:30:00+00:00"
+        );
+        // Day of month or weekday when both are restricted.
+        assert_eq!(
+            next("0 0 15 * SUN", "2025-07-01T00:00:00Z"),
+            "2025-07-06T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 12 * * 7", "2025-07-01T00:00:00Z"),
+            "2025-07-06T12:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2025-03-01T00:00:00Z"),
+            "2028-02-29T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_expressions() {
+        for bad in [
+            "0 9 * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * FOO *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "0 9 * * MON extra",
+        ] {
+            assert!(bad.parse::<CronSchedule>().is_err(), "{}", bad);
+        }
+        let cron: CronSchedule = "0 0 31 2 *".parse().unwrap();
+        assert!(cron.next_after(&Utc::now()).is_none());
+    }
+}
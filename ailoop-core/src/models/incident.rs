@@ -0,0 +1,35 @@
+//! Incidents: channels put in incident mode with `ailoop incident start`
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// An incident open on a channel. Messages on the channel carry its id in
+/// `metadata.incident` until it is stopped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Incident {
+    /// Id given at start, or `<channel>-<YYYYMMDD-HHMM>`.
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl Incident {
+    /// An incident on `channel` starting at `now`, with a generated id unless `id` is given.
+    pub fn new(
+        channel: &str,
+        id: Option<String>,
+        title: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let id = id
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| format!("{}-{}", channel, now.format("%Y%m%d-%H%M")));
+        Self {
+            id,
+            started_at: now,
+            title: title.filter(|t| !t.trim().is_empty()),
+        }
+    }
+}
@@ -11,6 +11,9 @@ pub enum SenderType {
     Agent,
     #[serde(rename = "HUMAN")]
     Human,
+    /// Notices from the server itself, such as a channel being put on hold.
+    #[serde(rename = "SYSTEM")]
+    System,
 }
 
 /// A single selectable option within a Decision.
@@ -69,6 +72,10 @@ pub fn validate_decision(
     Ok(())
 }
 
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
 /// Content of a message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -109,7 +116,26 @@ pub enum MessageContent {
         response_type: ResponseType,
     },
     #[serde(rename = "navigate")]
-    Navigate { url: String },
+    Navigate {
+        url: String,
+        /// Seconds to wait for the human to open the URL. 0 = use server default.
+        #[serde(default, skip_serializing_if = "is_zero")]
+        timeout_seconds: u32,
+    },
+    /// One line of a free-form conversation between the agent and the human (`ailoop chat`).
+    #[serde(rename = "chat")]
+    Chat {
+        /// Id of the conversation: the id of its first message.
+        conversation_id: Uuid,
+        text: String,
+        /// Set on the message that ends the conversation.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        closed: bool,
+    },
+    /// Transient "working" pulse, such as "running tests". Shown as an indicator and never
+    /// kept in history.
+    #[serde(rename = "status")]
+    Status { text: String },
     #[serde(rename = "task_create")]
     TaskCreate { task: Task },
     #[serde(rename = "task_update")]
@@ -147,6 +173,18 @@ pub enum NotificationPriority {
     Urgent,
 }
 
+impl NotificationPriority {
+    /// Order of the levels, from 0 for `low` to 3 for `urgent`.
+    pub fn rank(&self) -> u8 {
+        match self {
+            NotificationPriority::Low => 0,
+            NotificationPriority::Normal => 1,
+            NotificationPriority::High => 2,
+            NotificationPriority::Urgent => 3,
+        }
+    }
+}
+
 /// Types of responses to questions/authorizations
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ResponseType {
@@ -242,6 +280,131 @@ impl Task {
     }
 }
 
+/// Who sent an agent message: shown as "deploy-bot on host-a" instead of an anonymous prompt.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentIdentity {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+}
+
+impl AgentIdentity {
+    /// Identity from the environment: `AILOOP_AGENT_NAME`, `AILOOP_AGENT_VERSION`, and
+    /// `AILOOP_AGENT_HOST` or the machine's host name.
+    pub fn detect() -> Self {
+        let var = |key: &str| {
+            std::env::var(key)
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        Self {
+            name: var("AILOOP_AGENT_NAME"),
+            version: var("AILOOP_AGENT_VERSION"),
+            host: var("AILOOP_AGENT_HOST").or_else(host_name),
+        }
+    }
+
+    /// "deploy-bot 1.2 on host-a"; `None` when nothing is known.
+    pub fn label(&self) -> Option<String> {
+        if self.name.is_none() && self.host.is_none() {
+            return None;
+        }
+        let mut label = self.name.clone().unwrap_or_else(|| "agent".to_string());
+        if let Some(version) = &self.version {
+            label.push(' ');
+            label.push_str(version);
+        }
+        if let Some(host) = &self.host {
+            label.push_str(" on ");
+            label.push_str(host);
+        }
+        Some(label)
+    }
+}
+
+/// Host name from `HOSTNAME`/`COMPUTERNAME` or `/etc/hostname`.
+fn host_name() -> Option<String> {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .find_map(|key| std::env::var(key).ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// One earlier prompt of a thread and how it was answered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThreadEntry {
+    pub id: Uuid,
+    pub question: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answer: Option<String>,
+}
+
+/// Where an agent message falls in the order its client produced it. A client sending over
+/// several requests at once numbers its messages so the server can record them in that order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sequence {
+    /// Identifies the sending client instance.
+    pub stream: Uuid,
+    /// Position within `stream`, counting from 1.
+    pub seq: u64,
+}
+
+/// Where a prompt waits in line, told to the agent that sent it in `metadata.queued` of an
+/// interim system notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuePosition {
+    pub prompt_id: Uuid,
+    /// Messages ahead of the prompt, counting the one being answered now.
+    pub position: usize,
+    /// Rough seconds until the prompt is put to humans, when the server has answer times to
+    /// go by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eta_seconds: Option<u64>,
+}
+
+/// `type` of the frame asking to move a queued prompt ahead.
+pub const BOOST_FRAME: &str = "boost";
+
+/// One `{"type": "boost"}` frame: an agent asking for its queued prompt to be put to humans
+/// sooner, e.g. because its own deadline is near.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueBoost {
+    pub prompt_id: Uuid,
+    /// New place among queued messages; must be above the prompt's current one.
+    pub priority: NotificationPriority,
+    /// Why, kept in the event log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl QueueBoost {
+    /// The frame as sent over the WebSocket, tagged with `"type": "boost"`.
+    pub fn to_frame(&self) -> String {
+        #[derive(Serialize)]
+        struct Frame<'a> {
+            r#type: &'static str,
+            #[serde(flatten)]
+            boost: &'a QueueBoost,
+        }
+        serde_json::to_string(&Frame {
+            r#type: BOOST_FRAME,
+            boost: self,
+        })
+        .unwrap_or_default()
+    }
+
+    /// Whether `frame` is tagged as a boost request, well-formed or not.
+    pub fn is_frame(frame: &serde_json::Value) -> bool {
+        frame.get("type").and_then(|t| t.as_str()) == Some(BOOST_FRAME)
+    }
+}
+
 /// Core message structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -257,9 +420,18 @@ pub struct Message {
     pub timestamp: DateTime<Utc>,
     /// Links related messages (optional)
     pub correlation_id: Option<Uuid>,
+    /// Earlier prompt this one follows up on; related prompts form a thread
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Uuid>,
     /// Extended metadata for agent-specific and client tracking information
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub metadata: Option<serde_json::Value>,
+    /// Sending agent, set by clients on agent messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<AgentIdentity>,
+    /// Position in the sending client's order, for clients sending concurrently
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<Sequence>,
 }
 
 impl Message {
@@ -272,7 +444,10 @@ impl Message {
             content,
             timestamp: Utc::now(),
             correlation_id: None,
+            parent_id: None,
             metadata: None,
+            agent: None,
+            sequence: None,
         }
     }
 
@@ -285,9 +460,174 @@ impl Message {
             content,
             timestamp: Utc::now(),
             correlation_id: Some(correlation_id),
+            parent_id: None,
             metadata: None,
+            agent: None,
+            sequence: None,
         }
     }
+
+    pub fn with_agent(mut self, agent: AgentIdentity) -> Self {
+        self.agent = Some(agent);
+        self
+    }
+
+    /// Mark this prompt as a follow-up to `parent_id`.
+    pub fn with_parent(mut self, parent_id: Uuid) -> Self {
+        self.parent_id = Some(parent_id);
+        self
+    }
+
+    /// What a prompt asks: a decision's summary, the action to authorize or the URL.
+    pub fn prompt_text(&self) -> Option<&str> {
+        match &self.content {
+            MessageContent::Decision { summary, .. } => Some(summary),
+            MessageContent::Authorization { action, .. } => Some(action),
+            MessageContent::Navigate { url, .. } => Some(url),
+            _ => None,
+        }
+    }
+
+    /// Earlier questions and answers of this prompt's thread, oldest first, as attached by
+    /// the server in `metadata.thread`.
+    pub fn thread(&self) -> Vec<ThreadEntry> {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get("thread"))
+            .and_then(|t| serde_json::from_value(t.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// The thread rendered for display above the prompt; `None` outside a thread.
+    pub fn thread_summary(&self) -> Option<String> {
+        let thread = self.thread();
+        if thread.is_empty() {
+            return None;
+        }
+        let mut summary = "Earlier in this thread:".to_string();
+        for entry in thread {
+            summary.push_str(&format!(
+                "\n  • {} → {}",
+                entry.question,
+                entry.answer.as_deref().unwrap_or("(no answer)")
+            ));
+        }
+        Some(summary)
+    }
+
+    /// Who answered, as recorded by the server in `metadata.responder` (e.g.
+    /// `"telegram:alice"` or an SSO subject).
+    pub fn responder(&self) -> Option<&str> {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get("responder"))
+            .and_then(|r| r.as_str())
+    }
+
+    /// Team directory member who answered, as `@name` (`metadata.member`); set alongside
+    /// `metadata.responder` when the responder's handle is in the directory.
+    pub fn team_member(&self) -> Option<&str> {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get("member"))
+            .and_then(|r| r.as_str())
+    }
+
+    /// Where a queued prompt waits, on the server's interim notices (`metadata.queued`).
+    pub fn queue_position(&self) -> Option<QueuePosition> {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get("queued"))
+            .and_then(|q| serde_json::from_value(q.clone()).ok())
+    }
+
+    /// Where the message goes in a queue: the priority it was boosted to (`metadata.boosted`,
+    /// set by the server), else a notification's own priority, else `normal`.
+    pub fn queue_priority(&self) -> NotificationPriority {
+        let boosted = self
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get("boosted"))
+            .and_then(|p| serde_json::from_value(p.clone()).ok());
+        match (boosted, &self.content) {
+            (Some(priority), _) => priority,
+            (None, MessageContent::Notification { priority, .. }) => priority.clone(),
+            (None, _) => NotificationPriority::Normal,
+        }
+    }
+
+    /// Incident the message was sent during (`metadata.incident`, set by the server).
+    pub fn incident(&self) -> Option<&str> {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get("incident"))
+            .and_then(|i| i.as_str())
+    }
+
+    /// Team member a prompt is assigned to (`metadata.assignee`, set by `ask --assign`),
+    /// without a leading `@`.
+    pub fn assignee(&self) -> Option<&str> {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get("assignee"))
+            .and_then(|a| a.as_str())
+            .map(|a| a.trim_start_matches('@'))
+            .filter(|a| !a.is_empty())
+    }
+
+    /// Assign this prompt to the team member `assignee`.
+    pub fn with_assignee(mut self, assignee: &str) -> Self {
+        let assignee = serde_json::json!(assignee.trim().trim_start_matches('@'));
+        match self.metadata.as_mut().and_then(|m| m.as_object_mut()) {
+            Some(map) => {
+                map.insert("assignee".to_string(), assignee);
+            }
+            None => self.metadata = Some(serde_json::json!({ "assignee": assignee })),
+        }
+        self
+    }
+
+    /// Id of the file sent with a response (`metadata.attachment.id`).
+    pub fn attachment_id(&self) -> Option<Uuid> {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get("attachment"))
+            .and_then(|a| a.get("id"))
+            .and_then(|id| id.as_str())
+            .and_then(|id| id.parse().ok())
+    }
+
+    /// Label of the sending agent, such as "deploy-bot on host-a".
+    pub fn agent_label(&self) -> Option<String> {
+        self.agent.as_ref().and_then(AgentIdentity::label)
+    }
+
+    /// Who said this in a conversation: the agent's label, "agent", "human" or "system".
+    pub fn speaker(&self) -> String {
+        match self.sender_type {
+            SenderType::Agent => self.agent_label().unwrap_or_else(|| "agent".to_string()),
+            SenderType::Human => "human".to_string(),
+            SenderType::System => "system".to_string(),
+        }
+    }
+
+    /// Whether this is a status pulse, delivered live but not recorded or queued.
+    pub fn is_status(&self) -> bool {
+        matches!(self.content, MessageContent::Status { .. })
+    }
+
+    /// Heading shown above agent prompts and notifications, such as
+    /// "deploy-bot on host-a asks:".
+    pub fn agent_heading(&self) -> Option<String> {
+        let verb = match self.content {
+            MessageContent::Decision { .. }
+            | MessageContent::Authorization { .. }
+            | MessageContent::Navigate { .. } => "asks",
+            MessageContent::Notification { .. } | MessageContent::Chat { .. } => "says",
+            _ => return None,
+        };
+        Some(format!("{} {}:", self.agent_label()?, verb))
+    }
 }
 
 #[cfg(test)]
@@ -370,6 +710,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_navigate_timeout_is_optional() {
+        let content: MessageContent =
+            serde_json::from_str(r#"{"type": "navigate", "url": "https://example.com"}"#).unwrap();
+        assert!(matches!(
+            content,
+            MessageContent::Navigate {
+                timeout_seconds: 0,
+                ..
+            }
+        ));
+        assert!(serde_json::to_value(&content)
+            .unwrap()
+            .get("timeout_seconds")
+            .is_none());
+
+        let content = MessageContent::Navigate {
+            url: "https://example.com".to_string(),
+            timeout_seconds: 30,
+        };
+        assert_eq!(
+            serde_json::to_value(&content).unwrap()["timeout_seconds"],
+            30
+        );
+    }
+
     #[test]
     fn test_validate_decision_too_few_options() {
         let options = vec![DecisionOption {
@@ -533,4 +899,39 @@ mod tests {
         assert!(serialized.contains("\"task_create\""));
         assert!(serialized.contains(&task.title));
     }
+
+    #[test]
+    fn test_agent_identity_label() {
+        let agent = AgentIdentity {
+            name: Some("deploy-bot".to_string()),
+            version: None,
+            host: Some("host-a".to_string()),
+        };
+        assert_eq!(agent.label().as_deref(), Some("deploy-bot on host-a"));
+        let versioned = AgentIdentity {
+            version: Some("1.2".to_string()),
+            host: None,
+            ..agent.clone()
+        };
+        assert_eq!(versioned.label().as_deref(), Some("deploy-bot 1.2"));
+        assert_eq!(AgentIdentity::default().label(), None);
+
+        let message = Message::new(
+            "public".to_string(),
+            SenderType::Agent,
+            MessageContent::Navigate {
+                url: "https://example.com".to_string(),
+                timeout_seconds: 0,
+            },
+        );
+        let json = serde_json::to_value(&message).unwrap();
+        assert!(json.get("agent").is_none());
+        let message = message.with_agent(agent);
+        let round_trip: Message =
+            serde_json::from_str(&serde_json::to_string(&message).unwrap()).unwrap();
+        assert_eq!(
+            round_trip.agent_heading().as_deref(),
+            Some("deploy-bot on host-a asks:")
+        );
+    }
 }
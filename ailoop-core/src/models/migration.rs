@@ -0,0 +1,131 @@
+//! Versioned `config.toml` schema and the migrations from older versions.
+//!
+//! Files without a `version` key predate versioning and count as version 0. Each step rewrites
+//! the parsed document; [`Configuration::load_from_file`] runs the steps a file needs in memory,
+//! and `ailoop config migrate` writes the result back, keeping the original next to it as
+//! `config.toml.v<N>.bak`.
+
+use super::configuration::Configuration;
+use std::path::{Path, PathBuf};
+
+/// Schema version written by this build.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Top-level keys without a serde default as of version 1.
+const REQUIRED_KEYS: [&str; 6] = [
+    "default_channel",
+    "log_level",
+    "server_host",
+    "server_port",
+    "max_connections",
+    "max_message_size",
+];
+
+/// One migration step: rewrites a parsed document to the next version.
+type MigrationStep = fn(&mut toml::Table) -> Result<(), String>;
+
+/// Steps indexed by the version they migrate from.
+const STEPS: [MigrationStep; CONFIG_VERSION as usize] = [v0_to_v1];
+
+/// A config file's text before and after migration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigMigration {
+    pub from: u32,
+    pub to: u32,
+    pub original: String,
+    pub migrated: String,
+}
+
+/// Schema version of a parsed config file; 0 when it has no `version` key.
+pub fn config_version(doc: &toml::Table) -> Result<u32, String> {
+    match doc.get("version") {
+        None => Ok(0),
+        Some(toml::Value::Integer(v)) => {
+            u32::try_from(*v).map_err(|_| format!("version: invalid value {}", v))
+        }
+        Some(other) => Err(format!("version: expected an integer, got {}", other)),
+    }
+}
+
+/// Migrate a config file's text to [`CONFIG_VERSION`]. `None` when it is already current.
+///
+/// Files from a newer ailoop are refused rather than loaded with their new settings dropped.
+pub fn migrate_config(content: &str) -> Result<Option<ConfigMigration>, String> {
+    let mut doc: toml::Table = toml::from_str(content).map_err(|e| e.to_string())?;
+    let from = config_version(&doc)?;
+    if from > CONFIG_VERSION {
+        return Err(format!(
+            "config version {} is newer than this ailoop supports ({}); upgrade ailoop",
+            from, CONFIG_VERSION
+        ));
+    }
+    if from == CONFIG_VERSION {
+        return Ok(None);
+    }
+    for step in &STEPS[from as usize..] {
+        step(&mut doc)?;
+    }
+    doc.insert(
+        "version".to_string(),
+        toml::Value::Integer(CONFIG_VERSION.into()),
+    );
+    let migrated = toml::to_string_pretty(&doc).map_err(|e| e.to_string())?;
+    Ok(Some(ConfigMigration {
+        from,
+        to: CONFIG_VERSION,
+        original: content.to_string(),
+        migrated,
+    }))
+}
+
+/// Where the pre-migration copy of `path` is kept, e.g. `config.toml.v0.bak`.
+pub fn backup_path(path: &Path, from: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".v{}.bak", from));
+    PathBuf::from(name)
+}
+
+/// Version 0 to 1: the top-level server settings had no defaults, so files that left any of them
+/// out failed to load. Fill the missing ones in from the defaults.
+fn v0_to_v1(doc: &mut toml::Table) -> Result<(), String> {
+    let defaults = toml::Table::try_from(Configuration::default()).map_err(|e| e.to_string())?;
+    for key in REQUIRED_KEYS {
+        if !doc.contains_key(key) {
+            if let Some(value) = defaults.get(key) {
+                doc.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unversioned_config_is_migrated() {
+        let old = "default_channel = \"ops\"\n\n[providers.telegram]\nenabled = true\n";
+        let migration = migrate_config(old).unwrap().unwrap();
+        assert_eq!((migration.from, migration.to), (0, CONFIG_VERSION));
+
+        let config: Configuration = toml::from_str(&migration.migrated).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.default_channel, "ops");
+        assert_eq!(config.server_port, 8080);
+        assert!(config.providers.telegram.enabled);
+
+        assert!(migrate_config(&migration.migrated).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_newer_or_invalid_versions_are_refused() {
+        assert!(migrate_config("version = 99\n").is_err());
+        assert!(migrate_config("version = \"1\"\n").is_err());
+        assert!(migrate_config("version = -1\n").is_err());
+        assert_eq!(
+            backup_path(Path::new("/etc/ailoop/config.toml"), 0),
+            PathBuf::from("/etc/ailoop/config.toml.v0.bak")
+        );
+    }
+}
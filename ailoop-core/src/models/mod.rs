@@ -1,10 +1,39 @@
 //! Data models for ailoop
 
 pub mod authorization;
+pub mod capabilities;
 pub mod configuration;
+pub mod cron;
+pub mod incident;
 pub mod message;
+pub mod migration;
+pub mod oncall;
+pub mod privacy;
+pub mod retention;
+pub mod schedule;
+pub mod stats;
+pub mod strict;
+pub mod team;
+pub mod timeout;
+pub mod typing;
 
+pub use capabilities::{Capabilities, ServerLimits};
 pub use configuration::*;
+pub use cron::CronSchedule;
+pub use incident::Incident;
 pub use message::*;
+pub use migration::{migrate_config, ConfigMigration, CONFIG_VERSION};
+pub use oncall::{OnCallPerson, Rotation, Shift};
+pub use privacy::{ResponderErasure, ResponderExport, ResponderRequest};
+pub use retention::{PurgeReport, PurgeRequest};
+pub use schedule::{Reminder, ReminderRequest, ScheduleRequest, ScheduledMessage};
+pub use stats::{ResponseMetrics, ResponseSummary, SlaSummary};
+pub use team::{AwayStatus, TeamDirectory, TeamMember};
+pub use timeout::{
+    PromptKind, TimeoutPolicy, DEFAULT_AUTHORIZATION_TIMEOUT_SECS, DEFAULT_PROMPT_TIMEOUT_ENV,
+};
+pub use typing::{
+    TypingCapability, TypingConfig, TypingEvent, DEFAULT_TYPING_INTERVAL_MS, TYPING_FEATURE,
+};
 
 pub use message::{DependencyType, Task, TaskState};
@@ -0,0 +1,154 @@
+//! On-call rotation: who answers prompts right now
+//!
+//! A rotation file (TOML) lists people in rotation order with a fixed shift length, plus
+//! overrides for swaps and holidays, and how each person is reached:
+//!
+//! ```toml
+//! start = "2026-10-05T09:00:00Z"
+//! shift_hours = 168
+//! order = ["alice", "bob"]
+//!
+//! [[overrides]]
+//! who = "bob"
+//! start = "2026-10-07T09:00:00Z"
+//! end = "2026-10-08T09:00:00Z"
+//!
+//! [people.alice]
+//! providers = ["telegram"]
+//! contact = "@alice"
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Default shift length: one week.
+pub const DEFAULT_SHIFT_HOURS: u64 = 168;
+
+/// An on-call schedule.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rotation {
+    /// When the first person in `order` starts their shift.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<DateTime<Utc>>,
+    /// Defaults to [`DEFAULT_SHIFT_HOURS`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shift_hours: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+    /// Shifts that replace the rotation while they last.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<Shift>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub people: HashMap<String, OnCallPerson>,
+}
+
+/// One person on call from `start` until `end`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shift {
+    pub who: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// How to reach someone on call.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OnCallPerson {
+    /// Providers (by name, e.g. `telegram`, an exec sink's name) that reach this person.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub providers: Vec<String>,
+    /// Free-form contact (handle, phone, address), passed along with routed prompts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+}
+
+impl Rotation {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Who is on call at `at`, followed by who is next in line to escalate to. Empty when
+    /// nobody is scheduled.
+    pub fn on_call(&self, at: DateTime<Utc>) -> Vec<&str> {
+        let mut people = Vec::new();
+        if let Some(shift) = self.overrides.iter().find(|s| s.start <= at && at < s.end) {
+            people.push(shift.who.as_str());
+        }
+        if let (Some(start), false) = (self.start, self.order.is_empty()) {
+            let shift_secs = (self.shift_hours.unwrap_or(DEFAULT_SHIFT_HOURS).max(1) * 3600) as i64;
+            let shifts_since = (at - start).num_seconds().div_euclid(shift_secs);
+            let len = self.order.len() as i64;
+            for offset in 0..2 {
+                let index = (shifts_since + offset).rem_euclid(len) as usize;
+                people.push(self.order[index].as_str());
+            }
+        }
+        let mut seen = std::collections::HashSet::new();
+        people.retain(|p| seen.insert(*p));
+        people.truncate(2);
+        people
+    }
+
+    pub fn person(&self, name: &str) -> Option<&OnCallPerson> {
+        self.people.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_rotation_with_overrides() {
+        let rotation: Rotation = toml::from_str(
+            r#"
+            start = "2026-10-05T09:00:00Z"
+            shift_hours = 24
+            order = ["alice", "bob", "carol"]
+
+            [[overrides]]
+            who = "carol"
+            start = "2026-10-05T12:00:00Z"
+            end = "2026-10-05T18:00:00Z"
+
+            [people.alice]
+            providers = ["telegram"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rotation.on_call(at("2026-10-05T10:00:00Z")),
+            ["alice", "bob"]
+        );
+        assert_eq!(
+            rotation.on_call(at("2026-10-06T10:00:00Z")),
+            ["bob", "carol"]
+        );
+        assert_eq!(
+            rotation.on_call(at("2026-10-07T10:00:00Z")),
+            ["carol", "alice"]
+        );
+        // Before the start the rotation runs backwards.
+        assert_eq!(
+            rotation.on_call(at("2026-10-04T10:00:00Z")),
+            ["carol", "alice"]
+        );
+        // An override goes first; the regular shift becomes the backup.
+        assert_eq!(
+            rotation.on_call(at("2026-10-05T13:00:00Z")),
+            ["carol", "alice"]
+        );
+        assert_eq!(rotation.person("alice").unwrap().providers, ["telegram"]);
+
+        assert!(Rotation::default()
+            .on_call(at("2026-10-05T10:00:00Z"))
+            .is_empty());
+    }
+}
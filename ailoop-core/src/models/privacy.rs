@@ -0,0 +1,30 @@
+//! Export and erasure of what one responder said (`ailoop privacy export|erase`)
+
+use crate::models::Message;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /api/v1/privacy/export` and `POST /api/v1/privacy/erase`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponderRequest {
+    /// Identity recorded in `metadata.responder`, e.g. `telegram:alice` or an SSO subject.
+    pub responder: String,
+}
+
+/// Everything the server holds that is attributed to one responder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponderExport {
+    pub responder: String,
+    pub exported_at: DateTime<Utc>,
+    /// Messages attributed to them (their responses), oldest first. Files they sent are described in
+    /// `metadata.attachment`, with the `url` they can be downloaded from.
+    pub messages: Vec<Message>,
+}
+
+/// What erasing a responder removed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponderErasure {
+    pub responder: String,
+    pub messages: usize,
+    pub attachments: usize,
+}
@@ -0,0 +1,35 @@
+//! Purging old channel history (`ailoop history purge`)
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Body of `POST /api/v1/history/purge`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgeRequest {
+    /// Channel to purge; every channel when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Messages stamped before this time are purged.
+    pub before: DateTime<Utc>,
+    /// Count what would be purged without removing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// What a purge removed (or, for a dry run, would remove).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PurgeReport {
+    pub dry_run: bool,
+    /// Messages purged per channel; channels with none are left out.
+    pub channels: BTreeMap<String, usize>,
+    /// Events purged from agent session transcripts.
+    pub session_events: usize,
+}
+
+impl PurgeReport {
+    /// Messages purged across all channels.
+    pub fn messages(&self) -> usize {
+        self.channels.values().sum()
+    }
+}
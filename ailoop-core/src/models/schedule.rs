@@ -0,0 +1,147 @@
+//! Messages held by the server until a later time (`ailoop say --at`, `ailoop schedule`) and
+//! recurring reminders (`ailoop remind`)
+
+use crate::models::{CronSchedule, Message, MessageContent, NotificationPriority, SenderType};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A message the server delivers at `due_at` as if the agent had sent it then.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    /// Id used to cancel it; the same as the message's id.
+    pub id: Uuid,
+    pub due_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub message: Message,
+}
+
+impl ScheduledMessage {
+    pub fn new(due_at: DateTime<Utc>, message: Message) -> Self {
+        Self {
+            id: message.id,
+            due_at,
+            created_at: Utc::now(),
+            message,
+        }
+    }
+
+    /// Short description for listings: the notification text, prompt question or URL.
+    pub fn summary(&self) -> String {
+        match &self.message.content {
+            MessageContent::Notification { text, .. } => text.clone(),
+            _ => self
+                .message
+                .prompt_text()
+                .map(str::to_string)
+                .unwrap_or_else(|| "(message)".to_string()),
+        }
+    }
+}
+
+/// Body of `POST /api/v1/schedule`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRequest {
+    /// When to deliver; a time in the past delivers right away.
+    pub at: DateTime<Utc>,
+    pub message: Message,
+}
+
+/// A notification the server sends on `channel` every time `schedule` matches (in the server's
+/// local time), until the reminder is removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: Uuid,
+    pub channel: String,
+    pub schedule: CronSchedule,
+    pub text: String,
+    pub priority: NotificationPriority,
+    /// Defined in `[scheduler] reminders`: kept in step with the config at startup, and not
+    /// removable through the API.
+    #[serde(default)]
+    pub from_config: bool,
+    pub created_at: DateTime<Utc>,
+    /// When it is next sent; `None` when the schedule never matches again.
+    pub next_due: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_sent: Option<DateTime<Utc>>,
+    /// Id of the last notification sent, for matching an acknowledgement to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_message_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    /// Times it was sent since it was last acknowledged.
+    #[serde(default)]
+    pub unacknowledged: u32,
+    /// Upcoming occurrences to let pass without sending.
+    #[serde(default)]
+    pub skip: u32,
+    /// Occurrences skipped so far.
+    #[serde(default)]
+    pub skipped: u32,
+}
+
+impl Reminder {
+    pub fn new(
+        channel: String,
+        schedule: CronSchedule,
+        text: String,
+        priority: NotificationPriority,
+    ) -> Self {
+        let now = Utc::now();
+        let mut reminder = Self {
+            id: Uuid::new_v4(),
+            channel,
+            schedule,
+            text,
+            priority,
+            from_config: false,
+            created_at: now,
+            next_due: None,
+            last_sent: None,
+            last_message_id: None,
+            acknowledged_at: None,
+            unacknowledged: 0,
+            skip: 0,
+            skipped: 0,
+        };
+        reminder.schedule_after(now);
+        reminder
+    }
+
+    /// Set `next_due` to the first occurrence after `after`.
+    pub fn schedule_after(&mut self, after: DateTime<Utc>) {
+        self.next_due = self
+            .schedule
+            .next_after(&after.with_timezone(&Local))
+            .map(|next| next.with_timezone(&Utc));
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_due.is_some_and(|due| due <= now)
+    }
+
+    /// The notification sent for one occurrence, tagged with `metadata.reminder_id`.
+    pub fn notification(&self) -> Message {
+        let mut message = Message::new(
+            self.channel.clone(),
+            SenderType::System,
+            MessageContent::Notification {
+                text: format!("Reminder: {}", self.text),
+                priority: self.priority.clone(),
+            },
+        );
+        message.metadata = Some(serde_json::json!({ "reminder_id": self.id }));
+        message
+    }
+}
+
+/// Body of `POST /api/v1/reminders`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReminderRequest {
+    pub channel: String,
+    pub schedule: CronSchedule,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<NotificationPriority>,
+}
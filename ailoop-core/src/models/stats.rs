@@ -0,0 +1,62 @@
+//! Human response metrics (`GET /api/stats`, `ailoop status --stats`)
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How prompts fared with the humans answering them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponseSummary {
+    /// Prompts asked (per channel only), including those still waiting.
+    #[serde(default)]
+    pub asked: u64,
+    /// Prompts answered, approvals and denials included.
+    pub answered: u64,
+    pub timed_out: u64,
+    pub approved: u64,
+    pub denied: u64,
+    /// Share of settled prompts that timed out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_rate: Option<f64>,
+    /// Share of authorization decisions that were approvals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_rate: Option<f64>,
+    /// Time from prompt to answer, over answered prompts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mean_latency_seconds: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub median_latency_seconds: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_latency_seconds: Option<f64>,
+}
+
+/// Compliance with one `[[sla]]` rule.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SlaSummary {
+    pub within_seconds: u64,
+    /// Prompts answered in time.
+    pub met: u64,
+    /// Prompts answered late, timed out or still waiting past the SLA.
+    pub breached: u64,
+    /// Prompts still waiting within the SLA.
+    #[serde(default)]
+    pub open: u64,
+    /// Share of settled or breached prompts that were answered in time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compliance: Option<f64>,
+}
+
+/// Response metrics per channel and per responder.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponseMetrics {
+    /// Start of the period covered; `None` for everything the server remembers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    pub channels: BTreeMap<String, ResponseSummary>,
+    /// Keyed by `metadata.responder`, or `operator` for answers at the server terminal.
+    /// Timeouts have no responder and only count per channel.
+    pub responders: BTreeMap<String, ResponseSummary>,
+    /// Compliance per `[[sla]]` rule, by rule name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub slas: BTreeMap<String, SlaSummary>,
+}
@@ -0,0 +1,266 @@
+//! Strict protocol validation (`ailoop serve --strict`)
+//!
+//! By default the server takes messages on a best-effort basis: unknown fields are ignored, a
+//! notification without a priority is sent as `low` and a prompt timeout of 0 means the server
+//! default. In strict mode each of those is an error, reported field by field, so that a client
+//! drifting from the protocol fails in CI instead of being quietly accepted.
+
+use crate::models::Message;
+use serde_json::{Map, Value};
+
+const MESSAGE_FIELDS: &[&str] = &[
+    "id",
+    "channel",
+    "sender_type",
+    "content",
+    "timestamp",
+    "correlation_id",
+    "parent_id",
+    "metadata",
+    "agent",
+    "sequence",
+];
+const AGENT_FIELDS: &[&str] = &["name", "version", "host"];
+const SEQUENCE_FIELDS: &[&str] = &["stream", "seq"];
+const OPTION_FIELDS: &[&str] = &["id", "label", "detail_markdown"];
+const RECOMMENDATION_FIELDS: &[&str] = &["option_id", "rationale_markdown"];
+const TASK_FIELDS: &[&str] = &[
+    "id",
+    "title",
+    "description",
+    "state",
+    "created_at",
+    "updated_at",
+    "assignee",
+    "metadata",
+    "depends_on",
+    "blocking_for",
+    "blocked",
+    "dependency_type",
+];
+
+/// Fields of each content type, `type` included.
+fn content_fields(content_type: &str) -> Option<&'static [&'static str]> {
+    let fields: &'static [&'static str] = match content_type {
+        "decision" => &[
+            "type",
+            "decision_id",
+            "summary",
+            "context_markdown",
+            "options",
+            "recommendation",
+            "timeout_seconds",
+        ],
+        "authorization" => &["type", "action", "context", "timeout_seconds"],
+        "notification" => &["type", "text", "priority"],
+        "response" => &["type", "answer", "response_type"],
+        "navigate" => &["type", "url", "timeout_seconds"],
+        "chat" => &["type", "conversation_id", "text", "closed"],
+        "status" => &["type", "text"],
+        "task_create" => &["type", "task"],
+        "task_update" => &["type", "task_id", "state", "updated_at"],
+        "task_dependency_add" => &[
+            "type",
+            "task_id",
+            "depends_on",
+            "dependency_type",
+            "timestamp",
+        ],
+        "task_dependency_remove" => &["type", "task_id", "depends_on", "timestamp"],
+        _ => return None,
+    };
+    Some(fields)
+}
+
+/// Parse `raw` as a message, failing on anything the default mode would let through. Every
+/// problem found is returned, each prefixed with the path of the field at fault.
+pub fn parse_strict(raw: Value) -> Result<Message, Vec<String>> {
+    let mut errors = Vec::new();
+    let Some(object) = raw.as_object() else {
+        return Err(vec!["message: expected a JSON object".to_string()]);
+    };
+    unknown_fields(object, MESSAGE_FIELDS, "", &mut errors);
+    if let Some(agent) = object.get("agent").and_then(Value::as_object) {
+        unknown_fields(agent, AGENT_FIELDS, "agent.", &mut errors);
+    }
+    if let Some(sequence) = object.get("sequence").and_then(Value::as_object) {
+        unknown_fields(sequence, SEQUENCE_FIELDS, "sequence.", &mut errors);
+    }
+    if let Some(content) = object.get("content").and_then(Value::as_object) {
+        check_content(content, &mut errors);
+    }
+
+    let message = match serde_json::from_value::<Message>(raw) {
+        Ok(message) => message,
+        Err(e) => {
+            errors.push(format!("message: {}", e));
+            return Err(errors);
+        }
+    };
+    if let Err(e) = crate::channel::validation::validate_channel_name(&message.channel) {
+        errors.push(format!("channel: {}", e));
+    }
+    if let crate::models::MessageContent::Decision {
+        options,
+        recommendation,
+        ..
+    } = &message.content
+    {
+        if let Err(e) = crate::models::validate_decision(options, recommendation) {
+            errors.push(format!("content: {}", e));
+        }
+    }
+    if errors.is_empty() {
+        Ok(message)
+    } else {
+        Err(errors)
+    }
+}
+
+fn check_content(content: &Map<String, Value>, errors: &mut Vec<String>) {
+    let Some(content_type) = content.get("type").and_then(Value::as_str) else {
+        return;
+    };
+    let Some(fields) = content_fields(content_type) else {
+        return;
+    };
+    unknown_fields(content, fields, "content.", errors);
+    match content_type {
+        "decision" | "authorization" => {
+            if content.get("timeout_seconds").and_then(Value::as_u64) == Some(0) {
+                errors.push(
+                    "content.timeout_seconds: must be set; 0 (the server default) is not allowed"
+                        .to_string(),
+                );
+            }
+        }
+        "notification" if !content.contains_key("priority") => {
+            errors.push("content.priority: missing".to_string());
+        }
+        _ => {}
+    }
+    if let Some(options) = content.get("options").and_then(Value::as_array) {
+        for (n, option) in options.iter().enumerate() {
+            if let Some(option) = option.as_object() {
+                let path = format!("content.options[{}].", n);
+                unknown_fields(option, OPTION_FIELDS, &path, errors);
+            }
+        }
+    }
+    if let Some(recommendation) = content.get("recommendation").and_then(Value::as_object) {
+        unknown_fields(
+            recommendation,
+            RECOMMENDATION_FIELDS,
+            "content.recommendation.",
+            errors,
+        );
+    }
+    if let Some(task) = content.get("task").and_then(Value::as_object) {
+        unknown_fields(task, TASK_FIELDS, "content.task.", errors);
+    }
+}
+
+fn unknown_fields(
+    object: &Map<String, Value>,
+    known: &[&str],
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    for key in object.keys().filter(|key| !known.contains(&key.as_str())) {
+        errors.push(format!("{}{}: unknown field", path, key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        DecisionOption, DecisionRecommendation, MessageContent, NotificationPriority, SenderType,
+        Task,
+    };
+    use serde_json::json;
+
+    fn valid(content: MessageContent) -> Value {
+        let mut message = Message::new("ops".to_string(), SenderType::Agent, content);
+        message.metadata = Some(json!({ "anything": "goes" }));
+        message.agent = Some(crate::models::AgentIdentity {
+            name: Some("bot".to_string()),
+            ..Default::default()
+        });
+        serde_json::to_value(message).unwrap()
+    }
+
+    #[test]
+    fn well_formed_messages_pass() {
+        let option = |id: &str| DecisionOption {
+            id: id.to_string(),
+            label: id.to_string(),
+            detail_markdown: Some("details".to_string()),
+        };
+        for content in [
+            MessageContent::Decision {
+                decision_id: "d".to_string(),
+                summary: "Deploy?".to_string(),
+                context_markdown: None,
+                options: vec![option("yes"), option("no")],
+                recommendation: Some(DecisionRecommendation {
+                    option_id: "yes".to_string(),
+                    rationale_markdown: None,
+                }),
+                timeout_seconds: 60,
+            },
+            MessageContent::Authorization {
+                action: "deploy".to_string(),
+                context: Some(json!({ "env": "prod" })),
+                timeout_seconds: 60,
+            },
+            MessageContent::Notification {
+                text: "done".to_string(),
+                priority: NotificationPriority::High,
+            },
+            MessageContent::TaskCreate {
+                task: Task::new("t".to_string(), "d".to_string()),
+            },
+        ] {
+            assert!(parse_strict(valid(content)).is_ok());
+        }
+    }
+
+    #[test]
+    fn lenient_shortcuts_are_reported_by_field() {
+        let mut raw = valid(MessageContent::Authorization {
+            action: "deploy".to_string(),
+            context: None,
+            timeout_seconds: 0,
+        });
+        raw["extra"] = json!(1);
+        raw["content"]["reason"] = json!("typo");
+        let errors = parse_strict(raw).unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                "extra: unknown field",
+                "content.reason: unknown field",
+                "content.timeout_seconds: must be set; 0 (the server default) is not allowed",
+            ]
+        );
+
+        let mut raw = valid(MessageContent::Notification {
+            text: "hi".to_string(),
+            priority: NotificationPriority::Normal,
+        });
+        raw["content"].as_object_mut().unwrap().remove("priority");
+        assert_eq!(
+            parse_strict(raw).unwrap_err(),
+            ["content.priority: missing"]
+        );
+
+        let mut raw = valid(MessageContent::Notification {
+            text: "hi".to_string(),
+            priority: NotificationPriority::Normal,
+        });
+        raw["content"]["priority"] = json!("HIGH");
+        let errors = parse_strict(raw).unwrap_err();
+        assert!(errors[0].contains("unknown variant `HIGH`"), "{:?}", errors);
+    }
+}
@@ -0,0 +1,213 @@
+//! Team directory: stable names for the people who answer prompts
+//!
+//! Providers record who answered as a raw identity (`telegram:alice`, `twilio:+15550199`,
+//! `email:alice@example.com`, an SSO subject). The directory maps those handles to one member,
+//! so responses, metrics and routing rules can say `@alice` instead:
+//!
+//! ```toml
+//! [team.alice]
+//! name = "Alice Liddell"
+//! handles = ["telegram:alice", "email:alice@example.com"]
+//! roles = ["sre", "release-manager"]
+//! ```
+//!
+//! Members can also say they are away (`ailoop me away`); routing then passes them over until
+//! they are back.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One person in the directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TeamMember {
+    /// Display name; defaults to the member's key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Responder identities that are this person, as recorded in `metadata.responder`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub handles: Vec<String>,
+    /// Free-form roles, such as `sre`, for routing rules and approvals.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+}
+
+impl TeamMember {
+    /// Providers that reach this person: the prefixes of their handles (`telegram` for
+    /// `telegram:alice`).
+    pub fn providers(&self) -> Vec<String> {
+        let mut providers: Vec<String> = Vec::new();
+        for handle in &self.handles {
+            if let Some((provider, _)) = handle.split_once(':') {
+                if !providers.iter().any(|p| p == provider) {
+                    providers.push(provider.to_string());
+                }
+            }
+        }
+        providers
+    }
+}
+
+/// A member being away (`ailoop me away --until friday`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AwayStatus {
+    pub since: DateTime<Utc>,
+    /// When they are back; `None` until they say so with `ailoop me back`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl AwayStatus {
+    /// Whether this still holds at `now`.
+    pub fn is_away(&self, now: DateTime<Utc>) -> bool {
+        self.until.is_none_or(|until| now < until)
+    }
+}
+
+/// Members by key (e.g. [team.alice]).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TeamDirectory {
+    members: BTreeMap<String, TeamMember>,
+}
+
+impl TeamDirectory {
+    pub fn new(members: BTreeMap<String, TeamMember>) -> Self {
+        Self { members }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Members in key order.
+    pub fn members(&self) -> impl Iterator<Item = (&str, &TeamMember)> {
+        self.members
+            .iter()
+            .map(|(key, member)| (key.as_str(), member))
+    }
+
+    /// The member for `reference`, which may be written `@alice` or `alice`.
+    pub fn get(&self, reference: &str) -> Option<&TeamMember> {
+        self.members.get(reference.trim().trim_start_matches('@'))
+    }
+
+    /// Key of the member `responder` names: their key (`alice` or `@alice`) or one of their
+    /// handles.
+    pub fn resolve(&self, responder: &str) -> Option<&str> {
+        let key = responder.trim().trim_start_matches('@');
+        match self.members.get_key_value(key) {
+            Some((key, _)) => Some(key.as_str()),
+            None => self.member_for(responder),
+        }
+    }
+
+    /// Key of the member with `responder` among their handles. Handles compare
+    /// case-insensitively, and a leading `@` on the part after the provider is ignored.
+    pub fn member_for(&self, responder: &str) -> Option<&str> {
+        let wanted = normalize(responder);
+        self.members
+            .iter()
+            .find(|(_, member)| member.handles.iter().any(|h| normalize(h) == wanted))
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Keys of the members with `role`, in key order.
+    pub fn with_role(&self, role: &str) -> Vec<&str> {
+        self.members
+            .iter()
+            .filter(|(_, member)| member.roles.iter().any(|r| r.eq_ignore_ascii_case(role)))
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+
+    /// Problems as `team.<key>...: message` strings, as in `Configuration::validate`.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut owners: BTreeMap<String, &str> = BTreeMap::new();
+        for (key, member) in &self.members {
+            if key.is_empty()
+                || !key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+            {
+                errors.push(format!(
+                    "team.{}: keys may only use letters, digits, '-', '_' and '.'",
+                    key
+                ));
+            }
+            for handle in &member.handles {
+                if handle.trim().is_empty() {
+                    errors.push(format!("team.{}.handles: empty handle", key));
+                } else if let Some(other) = owners.insert(normalize(handle), key) {
+                    errors.push(format!(
+                        "team.{}.handles: '{}' also belongs to {}",
+                        key, handle, other
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
+
+fn normalize(handle: &str) -> String {
+    let handle = handle.trim().to_lowercase();
+    match handle.split_once(':') {
+        Some((provider, id)) => format!("{}:{}", provider, id.trim_start_matches('@')),
+        None => handle,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn team() -> TeamDirectory {
+        toml::from_str(
+            r#"
+            [alice]
+            handles = ["telegram:@Alice", "email:alice@example.com"]
+            roles = ["sre"]
+
+            [bob]
+            handles = ["twilio:+15550199", "bob@sso"]
+            roles = ["SRE", "release-manager"]
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn resolves_handles_and_roles() {
+        let team = team();
+        assert_eq!(team.member_for("telegram:alice"), Some("alice"));
+        assert_eq!(team.member_for("email:Alice@Example.com"), Some("alice"));
+        assert_eq!(team.member_for("bob@sso"), Some("bob"));
+        assert_eq!(team.member_for("telegram:mallory"), None);
+        assert!(team.get("@alice").is_some());
+        assert_eq!(team.resolve("@bob"), Some("bob"));
+        assert_eq!(team.resolve("telegram:alice"), Some("alice"));
+        assert_eq!(team.resolve("carol"), None);
+        assert_eq!(team.with_role("sre"), ["alice", "bob"]);
+        assert_eq!(team.get("bob").unwrap().providers(), ["twilio"]);
+        assert!(team.validate().is_empty());
+    }
+
+    #[test]
+    fn rejects_shared_handles() {
+        let mut members: BTreeMap<String, TeamMember> = team().members;
+        members
+            .get_mut("bob")
+            .unwrap()
+            .handles
+            .push("telegram:alice".into());
+        let errors = TeamDirectory::new(members).validate();
+        assert_eq!(
+            errors,
+            ["team.bob.handles: 'telegram:alice' also belongs to alice"]
+        );
+    }
+}
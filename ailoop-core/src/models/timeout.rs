@@ -0,0 +1,167 @@
+//! Prompt timeouts: how long a prompt waits and what it means when nobody answers
+//!
+//! Every prompt, in direct mode and through a server, goes by the same rules:
+//!
+//! | Prompt        | Timeout                                     | When it expires    |
+//! |---------------|---------------------------------------------|--------------------|
+//! | question      | `--timeout`, else payload `timeout_seconds` | `timeout` response |
+//! | authorization | `--timeout` (default 300)                   | denied             |
+//! | navigation    | `--timeout`                                 | not opened         |
+//!
+//! A timeout of 0 means "wait until answered". The server fills in a prompt that has none from
+//! `AILOOP_DEFAULT_PROMPT_TIMEOUT_SECS`, then `timeout_seconds` in its configuration, and
+//! answers the prompt itself when it expires; the agent waits for that answer. Only in direct
+//! mode, or when the server never answers, does `ailoop authorize --default yes` grant an
+//! expired authorization instead.
+
+use super::{Configuration, ResponseType};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+/// Environment variable with the server's timeout for prompts that carry none.
+pub const DEFAULT_PROMPT_TIMEOUT_ENV: &str = "AILOOP_DEFAULT_PROMPT_TIMEOUT_SECS";
+
+/// `--timeout` of `ailoop authorize` when neither the flag nor the project file sets one.
+pub const DEFAULT_AUTHORIZATION_TIMEOUT_SECS: u32 = 300;
+
+/// The kind of prompt a timeout applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptKind {
+    Question,
+    Authorization,
+    Navigation,
+}
+
+/// How long one prompt waits, and the answer it gets when the wait runs out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutPolicy {
+    kind: PromptKind,
+    timeout: Option<Duration>,
+    grant_on_expiry: bool,
+}
+
+impl TimeoutPolicy {
+    /// A policy waiting `secs` seconds; 0 waits until answered.
+    pub fn new(kind: PromptKind, secs: u32) -> Self {
+        Self {
+            kind,
+            timeout: (secs > 0).then(|| Duration::from_secs(secs as u64)),
+            grant_on_expiry: false,
+        }
+    }
+
+    /// The server's policy for a prompt asking for `secs` seconds: the prompt's own timeout,
+    /// else [`DEFAULT_PROMPT_TIMEOUT_ENV`], else `config.timeout_seconds`, else none. Zero and
+    /// unparseable values are skipped.
+    pub fn resolve(kind: PromptKind, secs: u32, config: Option<&Configuration>) -> Self {
+        if secs > 0 {
+            return Self::new(kind, secs);
+        }
+        if let Ok(value) = std::env::var(DEFAULT_PROMPT_TIMEOUT_ENV) {
+            match value.parse::<u32>() {
+                Ok(secs) if secs > 0 => return Self::new(kind, secs),
+                _ => tracing::warn!(
+                    "{} is set but not a valid positive integer: {:?}; ignoring",
+                    DEFAULT_PROMPT_TIMEOUT_ENV,
+                    value
+                ),
+            }
+        }
+        let secs = config.and_then(|c| c.timeout_seconds).unwrap_or(0);
+        Self::new(kind, secs)
+    }
+
+    /// Grant an authorization that times out instead of denying it (`--default yes`).
+    pub fn grant_on_expiry(mut self, grant: bool) -> Self {
+        self.grant_on_expiry = grant && self.kind == PromptKind::Authorization;
+        self
+    }
+
+    pub fn kind(&self) -> PromptKind {
+        self.kind
+    }
+
+    /// How long to wait, or `None` to wait until answered.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The timeout in whole seconds as sent on the wire, 0 for none.
+    pub fn secs(&self) -> u32 {
+        self.timeout.map_or(0, |t| t.as_secs() as u32)
+    }
+
+    /// The answer recorded when the prompt expires. An expired navigation gets `timeout`
+    /// rather than `cancelled`, which would show it again later.
+    pub fn expiry_response(&self) -> ResponseType {
+        match self.kind {
+            PromptKind::Question => ResponseType::Timeout,
+            PromptKind::Authorization if self.grant_on_expiry => {
+                ResponseType::AuthorizationApproved
+            }
+            PromptKind::Authorization => ResponseType::AuthorizationDenied,
+            PromptKind::Navigation => ResponseType::Timeout,
+        }
+    }
+
+    /// Run `future` within the timeout; `None` when it expired first.
+    pub async fn wait<F: Future>(&self, future: F) -> Option<F::Output> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future).await.ok(),
+            None => Some(future.await),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_matrix() {
+        let question = TimeoutPolicy::new(PromptKind::Question, 30);
+        assert_eq!(question.timeout(), Some(Duration::from_secs(30)));
+        assert_eq!(question.expiry_response(), ResponseType::Timeout);
+
+        let authorization = TimeoutPolicy::new(PromptKind::Authorization, 0);
+        assert_eq!(authorization.timeout(), None);
+        assert_eq!(authorization.secs(), 0);
+        assert_eq!(
+            authorization.expiry_response(),
+            ResponseType::AuthorizationDenied
+        );
+        assert_eq!(
+            authorization.grant_on_expiry(true).expiry_response(),
+            ResponseType::AuthorizationApproved
+        );
+
+        // Only authorizations can be granted by default.
+        let navigation = TimeoutPolicy::new(PromptKind::Navigation, 10).grant_on_expiry(true);
+        assert_eq!(navigation.expiry_response(), ResponseType::Timeout);
+    }
+
+    #[test]
+    fn test_prompt_timeout_overrides_config() {
+        let config = Configuration {
+            timeout_seconds: Some(0),
+            ..Configuration::default()
+        };
+        let policy = TimeoutPolicy::resolve(PromptKind::Question, 45, Some(&config));
+        assert_eq!(policy.secs(), 45);
+    }
+
+    #[tokio::test]
+    async fn test_wait_expires_or_completes() {
+        let policy = TimeoutPolicy::new(PromptKind::Question, 1);
+        assert_eq!(policy.wait(async { 7 }).await, Some(7));
+        let short = TimeoutPolicy {
+            timeout: Some(Duration::from_millis(10)),
+            ..policy
+        };
+        assert_eq!(short.wait(std::future::pending::<()>()).await, None);
+        let forever = TimeoutPolicy::new(PromptKind::Question, 0);
+        assert_eq!(forever.wait(async { 7 }).await, Some(7));
+    }
+}
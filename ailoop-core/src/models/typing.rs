@@ -0,0 +1,134 @@
+//! Answers streamed while they are typed
+//!
+//! With `[typing] enabled = true`, a human's unsent answer to a prompt is passed on as it is
+//! typed, so an agent UI can show "human is typing…" and the draft. The server advertises this
+//! in its capabilities (`typing`), and only connections that accept it get the frames: they
+//! send `{"type": "capabilities", "accept": ["typing"]}`. Updates are throttled to one per
+//! `interval_ms` per prompt; the submitted answer still arrives as the usual response.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Shortest time between two typing frames for the same prompt, by default.
+pub const DEFAULT_TYPING_INTERVAL_MS: u64 = 500;
+
+/// Value of `accept` in a capabilities request that asks for typing frames.
+pub const TYPING_FEATURE: &str = "typing";
+
+/// Streaming of answers as they are typed (e.g. [typing]); off by default.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct TypingConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Defaults to [`DEFAULT_TYPING_INTERVAL_MS`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_ms: Option<u64>,
+    /// Send the draft itself, not just its length (default true).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial_text: Option<bool>,
+}
+
+impl TypingConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms.unwrap_or(DEFAULT_TYPING_INTERVAL_MS))
+    }
+
+    pub fn partial_text(&self) -> bool {
+        self.partial_text.unwrap_or(true)
+    }
+
+    /// What to advertise in the capabilities, or `None` when streaming is off.
+    pub fn capability(&self) -> Option<TypingCapability> {
+        self.enabled.then(|| TypingCapability {
+            interval_ms: self.interval().as_millis() as u64,
+            partial_text: self.partial_text(),
+        })
+    }
+}
+
+/// The `typing` entry of a server's capabilities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypingCapability {
+    pub interval_ms: u64,
+    /// Whether frames carry the draft in `text`.
+    pub partial_text: bool,
+}
+
+/// One `{"type": "typing"}` frame: the state of an unsent answer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypingEvent {
+    /// The prompt (or chat line) being answered.
+    pub prompt_id: Uuid,
+    pub channel: String,
+    /// The draft so far, when the server sends partial text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Characters typed so far; 0 when the draft was cleared.
+    pub length: usize,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl TypingEvent {
+    /// The frame as sent over the WebSocket, tagged with `"type": "typing"`.
+    pub fn to_frame(&self) -> String {
+        #[derive(Serialize)]
+        struct Frame<'a> {
+            r#type: &'static str,
+            #[serde(flatten)]
+            event: &'a TypingEvent,
+        }
+        serde_json::to_string(&Frame {
+            r#type: TYPING_FEATURE,
+            event: self,
+        })
+        .unwrap_or_default()
+    }
+
+    /// A typing frame received over the WebSocket, or `None` for any other frame.
+    pub fn from_frame(frame: &str) -> Option<Self> {
+        let value: serde_json::Value = serde_json::from_str(frame).ok()?;
+        if value.get("type")?.as_str()? != TYPING_FEATURE {
+            return None;
+        }
+        serde_json::from_value(value).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let event = TypingEvent {
+            prompt_id: Uuid::new_v4(),
+            channel: "ops".to_string(),
+            text: Some("Roll back to".to_string()),
+            length: 12,
+            timestamp: Utc::now(),
+        };
+        let frame = event.to_frame();
+        assert!(frame.contains("\"type\":\"typing\""));
+        assert_eq!(TypingEvent::from_frame(&frame), Some(event));
+        assert_eq!(TypingEvent::from_frame("{\"type\":\"capabilities\"}"), None);
+    }
+
+    #[test]
+    fn test_capability_only_when_enabled() {
+        assert_eq!(TypingConfig::default().capability(), None);
+        let config = TypingConfig {
+            enabled: true,
+            partial_text: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.capability(),
+            Some(TypingCapability {
+                interval_ms: DEFAULT_TYPING_INTERVAL_MS,
+                partial_text: false,
+            })
+        );
+    }
+}
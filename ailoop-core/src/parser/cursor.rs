@@ -1,11 +1,30 @@
 //! Cursor CLI output parser
+//!
+//! Cursor's `--output-format stream-json` events are normalized to the shapes the rest of
+//! ailoop reads: `message` text for user/assistant/thinking events; `tool`, `status`, `args`,
+//! `call_id`, `result` and its output as `message` for tool calls; `result`, `duration` and `usage` for the final result.
+//! Failed runs and tool calls keep Cursor's error details. Unknown event types are passed
+//! through unchanged as custom events.
 
 use crate::parser::{AgentEvent, AgentParser, EventType, InputFormat};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
 use std::collections::HashMap;
 
+/// Token counters in `result.usage`, as (Cursor key, snake_case key, metadata key).
+const USAGE_KEYS: [(&str, &str, &str); 4] = [
+    ("inputTokens", "input_tokens", "input_tokens"),
+    ("outputTokens", "output_tokens", "output_tokens"),
+    ("cacheReadTokens", "cache_read_tokens", "cache_read_tokens"),
+    (
+        "cacheWriteTokens",
+        "cache_write_tokens",
+        "cache_write_tokens",
+    ),
+];
+
 /// Parser for Cursor CLI output formats
 pub struct CursorParser {
     format: InputFormat,
@@ -19,26 +38,53 @@ impl CursorParser {
 
     /// Parse Cursor stream-json format (NDJSON)
     fn parse_stream_json(&self, line: &str) -> Result<Option<AgentEvent>> {
-        let json: serde_json::Value =
-            serde_json::from_str(line).context("Failed to parse JSON line")?;
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        let json: Value = serde_json::from_str(line).context("Failed to parse JSON line")?;
 
-        let event_type = self.detect_event_type(&json);
         let mut metadata = HashMap::new();
 
         // Extract Cursor-specific metadata
-        if let Some(session_id) = json.get("session_id").and_then(|v| v.as_str()) {
-            metadata.insert("session_id".to_string(), session_id.to_string());
-        }
-        if let Some(request_id) = json.get("request_id").and_then(|v| v.as_str()) {
-            metadata.insert("request_id".to_string(), request_id.to_string());
+        for key in ["session_id", "request_id", "model_call_id", "model"] {
+            if let Some(value) = json.get(key).and_then(|v| v.as_str()) {
+                metadata.insert(key.to_string(), value.to_string());
+            }
         }
 
+        let timestamp = json
+            .get("timestamp_ms")
+            .and_then(|v| v.as_i64())
+            .and_then(|millis| Utc.timestamp_millis_opt(millis).single())
+            .or_else(|| Some(Utc::now()));
+
+        let (event_type, content) = match json.get("type").and_then(|v| v.as_str()) {
+            Some("system") => (EventType::System, system_content(&json)),
+            Some("user") => (
+                EventType::User,
+                json!({ "type": "user", "message": message_text(&json) }),
+            ),
+            Some("assistant") => (
+                EventType::Assistant,
+                json!({ "type": "assistant", "message": message_text(&json) }),
+            ),
+            Some("thinking") => thinking(&json),
+            Some("tool_call") => (EventType::ToolCall, tool_call_content(&json)),
+            Some("result") => {
+                usage_metadata(&json, &mut metadata);
+                result(&json)
+            }
+            Some("error") => (EventType::Error, error_content(&json)),
+            Some(other) => (EventType::Custom(other.to_string()), json),
+            None => (EventType::Assistant, json), // Default for Cursor output
+        };
+
         Ok(Some(AgentEvent {
             _agent_type: "cursor".to_string(),
             event_type,
-            content: json,
+            content,
             metadata,
-            timestamp: Some(Utc::now()),
+            timestamp,
         }))
     }
 
@@ -53,7 +99,7 @@ impl CursorParser {
             return Ok(None);
         }
 
-        let content = serde_json::json!({
+        let content = json!({
             "text": line,
             "format": "text"
         });
@@ -66,23 +112,270 @@ impl CursorParser {
             timestamp: Some(Utc::now()),
         }))
     }
+}
+
+/// `system` events (`subtype: init`) describe the run; they update state but are not shown.
+fn system_content(json: &Value) -> Value {
+    let mut content = json!({ "type": "system" });
+    for (from, to) in [
+        ("subtype", "subtype"),
+        ("model", "model"),
+        ("cwd", "cwd"),
+        ("permissionMode", "permission_mode"),
+        ("apiKeySource", "api_key_source"),
+    ] {
+        if let Some(value) = json.get(from) {
+            content[to] = value.clone();
+        }
+    }
+    content
+}
+
+/// Text of a user/assistant event: `message.content` text parts, or a plain string.
+fn message_text(json: &Value) -> String {
+    let message = json.get("message").unwrap_or(&Value::Null);
+    if let Some(text) = message.as_str() {
+        return text.to_string();
+    }
+    match message.get("content") {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter(|part| part.get("type").and_then(|t| t.as_str()).unwrap_or("text") == "text")
+            .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+            .collect(),
+        _ => json
+            .get("text")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+/// Reasoning deltas become `thinking` custom events; the closing `completed` marker carries no
+/// text and only updates state.
+fn thinking(json: &Value) -> (EventType, Value) {
+    let text = json
+        .get("text")
+        .and_then(|t| t.as_str())
+        .unwrap_or_default();
+    if text.is_empty() {
+        let subtype = json.get("subtype").cloned().unwrap_or(Value::Null);
+        return (
+            EventType::System,
+            json!({ "type": "thinking", "subtype": subtype }),
+        );
+    }
+    (
+        EventType::Custom("thinking".to_string()),
+        json!({ "type": "thinking", "message": text }),
+    )
+}
 
-    /// Detect event type from Cursor JSON structure
-    fn detect_event_type(&self, json: &serde_json::Value) -> EventType {
-        if let Some(typ) = json.get("type").and_then(|v| v.as_str()) {
-            match typ {
-                "system" => EventType::System,
-                "user" => EventType::User,
-                "assistant" => EventType::Assistant,
-                "tool_call" => EventType::ToolCall,
-                "result" => EventType::Result,
-                "error" => EventType::Error,
-                other => EventType::Custom(other.to_string()),
+/// `tool_call` events: `tool_call` holds one `<kind>ToolCall` object with `args` and, once
+/// completed, a `result` of `success`, `failure`, `error` or `rejected`. Other tools use
+/// `function` with a name and JSON-encoded arguments.
+fn tool_call_content(json: &Value) -> Value {
+    let subtype = json
+        .get("subtype")
+        .and_then(|v| v.as_str())
+        .unwrap_or("started");
+    let call = json
+        .get("tool_call")
+        .and_then(|v| v.as_object())
+        .and_then(|object| object.iter().next());
+
+    let (tool, args, result) = match call {
+        Some((kind, call)) if kind == "function" => {
+            let args = match call.get("arguments") {
+                Some(Value::String(raw)) => {
+                    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.clone()))
+                }
+                Some(other) => other.clone(),
+                None => Value::Null,
+            };
+            let name = call
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or("function");
+            (name.to_string(), args, call.get("result"))
+        }
+        Some((kind, call)) => (
+            tool_name(kind),
+            call.get("args").cloned().unwrap_or(Value::Null),
+            call.get("result"),
+        ),
+        None => ("tool".to_string(), Value::Null, None),
+    };
+
+    let (status, output) = match result.and_then(|r| r.as_object()?.iter().next()) {
+        Some((outcome, detail)) => {
+            let status = match outcome.as_str() {
+                "success" => "completed",
+                "rejected" => "rejected",
+                _ => "failed",
+            };
+            (status.to_string(), result_output(outcome, detail))
+        }
+        None => (subtype.to_string(), None),
+    };
+
+    let mut content = json!({
+        "type": "tool_call",
+        "tool": tool,
+        "status": status,
+        "args": args,
+    });
+    if let Some(call_id) = json.get("call_id") {
+        content["call_id"] = call_id.clone();
+    }
+    if let Some(output) = output {
+        content["message"] = json!(output);
+    }
+    if let Some(result) = result {
+        content["result"] = result.clone();
+    }
+    content
+}
+
+/// `shellToolCall` → `shell`, `readLintsToolCall` → `read_lints`.
+fn tool_name(kind: &str) -> String {
+    let base = kind.strip_suffix("ToolCall").unwrap_or(kind);
+    let mut name = String::new();
+    for c in base.chars() {
+        if c.is_ascii_uppercase() {
+            if !name.is_empty() {
+                name.push('_');
             }
+            name.push(c.to_ascii_lowercase());
         } else {
-            EventType::Assistant // Default for Cursor output
+            name.push(c);
         }
     }
+    name
+}
+
+/// Short text for a tool result: an error message or the command's output.
+fn result_output(outcome: &str, detail: &Value) -> Option<String> {
+    let text = |key: &str| {
+        detail
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    let output = match outcome {
+        "success" => text("stdout").or_else(|| text("content")).or_else(|| {
+            detail
+                .get("path")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        }),
+        "rejected" => text("reason").or_else(|| Some("rejected".to_string())),
+        _ => text("errorMessage")
+            .or_else(|| text("message"))
+            .or_else(|| text("error"))
+            .or_else(|| text("stderr"))
+            .or_else(|| text("stdout")),
+    };
+    if outcome != "success" || output.is_some() {
+        return output;
+    }
+    detail
+        .get("exitCode")
+        .and_then(|v| v.as_i64())
+        .map(|code| format!("exit code {}", code))
+}
+
+/// The final `result` event; failed runs (`is_error` or a non-success subtype) become errors.
+fn result(json: &Value) -> (EventType, Value) {
+    let subtype = json
+        .get("subtype")
+        .and_then(|v| v.as_str())
+        .unwrap_or("success");
+    let text = json
+        .get("result")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let is_error = json
+        .get("is_error")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+        || subtype != "success";
+
+    let mut content = json!({
+        "type": "result",
+        "subtype": subtype,
+        "result": text,
+        "duration": json.get("duration_ms").cloned().unwrap_or(Value::Null),
+    });
+    if let Some(api) = json.get("duration_api_ms") {
+        content["duration_api"] = api.clone();
+    }
+    if let Some(usage) = usage(json) {
+        content["usage"] = usage;
+    }
+    if !is_error {
+        return (EventType::Result, content);
+    }
+    let message = if text.is_empty() {
+        format!("Cursor run ended with {}", subtype)
+    } else {
+        text.to_string()
+    };
+    content["type"] = json!("error");
+    content["error"] = json!({ "message": message, "subtype": subtype });
+    content["message"] = json!(message);
+    (EventType::Error, content)
+}
+
+/// Token usage with snake_case keys; Cursor reports camelCase.
+fn usage(json: &Value) -> Option<Value> {
+    let raw = json.get("usage")?.as_object()?;
+    let mut usage = serde_json::Map::new();
+    for (camel, snake, _) in USAGE_KEYS {
+        if let Some(count) = raw.get(camel).or_else(|| raw.get(snake)) {
+            usage.insert(snake.to_string(), count.clone());
+        }
+    }
+    Some(Value::Object(usage))
+}
+
+fn usage_metadata(json: &Value, metadata: &mut HashMap<String, String>) {
+    let Some(usage) = usage(json) else {
+        return;
+    };
+    for (_, snake, key) in USAGE_KEYS {
+        if let Some(count) = usage.get(snake).and_then(|v| v.as_u64()) {
+            metadata.insert(key.to_string(), count.to_string());
+        }
+    }
+}
+
+/// Standalone `error` events: `error` as a string or object, or a top-level `message`.
+fn error_content(json: &Value) -> Value {
+    let error = json.get("error");
+    let message = error
+        .and_then(|e| e.as_str())
+        .or_else(|| {
+            error
+                .and_then(|e| e.get("message"))
+                .and_then(|m| m.as_str())
+        })
+        .or_else(|| json.get("message").and_then(|m| m.as_str()))
+        .unwrap_or("Unknown Cursor error")
+        .to_string();
+    let mut content = json!({
+        "type": "error",
+        "error": { "message": message },
+        "message": message,
+    });
+    if let Some(subtype) = json.get("subtype") {
+        content["subtype"] = subtype.clone();
+        content["error"]["subtype"] = subtype.clone();
+    }
+    content
 }
 
 #[async_trait]
@@ -107,3 +400,26 @@ impl AgentParser for CursorParser {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_name() {
+        assert_eq!(tool_name("shellToolCall"), "shell");
+        assert_eq!(tool_name("readLintsToolCall"), "read_lints");
+        assert_eq!(tool_name("custom"), "custom");
+    }
+
+    #[tokio::test]
+    async fn test_timestamp_ms() {
+        let mut parser = CursorParser::new(InputFormat::StreamJson).unwrap();
+        let line = r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Hi"}]},"timestamp_ms":1700000000000}"#;
+        let event = parser.parse_line(line).await.unwrap().unwrap();
+        assert_eq!(
+            event.timestamp,
+            Utc.timestamp_millis_opt(1_700_000_000_000).single()
+        );
+    }
+}
@@ -72,6 +72,22 @@ pub enum EventType {
     Custom(String),
 }
 
+impl EventType {
+    /// Name used in configuration: `system`, `user`, `assistant`, `tool_call`, `result`,
+    /// `error`, or the custom type itself.
+    pub fn name(&self) -> &str {
+        match self {
+            EventType::System => "system",
+            EventType::User => "user",
+            EventType::Assistant => "assistant",
+            EventType::ToolCall => "tool_call",
+            EventType::Result => "result",
+            EventType::Error => "error",
+            EventType::Custom(name) => name,
+        }
+    }
+}
+
 /// Unified agent event structure
 #[derive(Debug, Clone)]
 pub struct AgentEvent {
@@ -127,3 +143,4 @@ pub fn create_parser(
 pub mod cursor;
 pub mod jsonl;
 pub mod opencode;
+pub mod reassemble;
@@ -1,4 +1,9 @@
 //! OpenCode stream JSON parser
+//!
+//! Handles `opencode run --format json` output (`step_start`, `text`, `tool_use`, `step_finish`,
+//! `patch`, `error`) and the event-bus session lifecycle and file events (`session.created`,
+//! `session.idle`, `session.error`, `file.edited`, ...; underscore spellings are accepted too).
+//! File paths touched by an event are listed in the `files` metadata as a JSON array.
 
 use crate::parser::{AgentEvent, AgentParser, EventType, InputFormat};
 use anyhow::{anyhow, Context, Result};
@@ -53,6 +58,67 @@ impl OpenCodeParser {
         }
     }
 
+    /// Session id from `sessionID` (run output) or the event-bus `properties`.
+    fn session_id(json: &serde_json::Value) -> Option<&str> {
+        let properties = json.get("properties");
+        json.get("sessionID")
+            .or_else(|| properties.and_then(|p| p.get("sessionID")))
+            .or_else(|| {
+                properties
+                    .and_then(|p| p.get("info"))
+                    .and_then(|i| i.get("id"))
+            })
+            .and_then(|v| v.as_str())
+    }
+
+    fn string_list(value: Option<&serde_json::Value>) -> Vec<String> {
+        value
+            .and_then(|v| v.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Record `files` as a JSON array in the `files` metadata.
+    fn insert_files(metadata: &mut HashMap<String, String>, files: &[String]) {
+        if files.is_empty() {
+            return;
+        }
+        metadata.insert("files".to_string(), json!(files).to_string());
+    }
+
+    /// Step cost and token counts; `tokens` is a total or
+    /// `{input, output, reasoning, cache: {read, write}}`.
+    fn insert_cost(
+        metadata: &mut HashMap<String, String>,
+        cost: &serde_json::Value,
+        tokens: &serde_json::Value,
+    ) {
+        if cost.is_number() {
+            metadata.insert("cost".to_string(), cost.to_string());
+        }
+        if tokens.is_number() {
+            metadata.insert("tokens".to_string(), tokens.to_string());
+            return;
+        }
+        let cache = tokens.get("cache");
+        for (key, value) in [
+            ("input_tokens", tokens.get("input")),
+            ("output_tokens", tokens.get("output")),
+            ("reasoning_tokens", tokens.get("reasoning")),
+            ("cache_read_tokens", cache.and_then(|c| c.get("read"))),
+            ("cache_write_tokens", cache.and_then(|c| c.get("write"))),
+        ] {
+            if let Some(count) = value.filter(|v| v.is_number()) {
+                metadata.insert(key.to_string(), count.to_string());
+            }
+        }
+    }
+
     fn parse_json_line(&self, line: &str) -> Result<Option<AgentEvent>> {
         if line.trim().is_empty() {
             return Ok(None);
@@ -69,23 +135,20 @@ impl OpenCodeParser {
         let timestamp = Self::parse_timestamp(json.get("timestamp")).or_else(|| Some(Utc::now()));
 
         let mut metadata = HashMap::new();
+        if let Some(session_id) = Self::session_id(&json) {
+            metadata.insert("session_id".to_string(), session_id.to_string());
+        }
 
         let event = match typ {
-            "step_start" => {
-                if let Some(session_id) = json.get("sessionID").and_then(|v| v.as_str()) {
-                    metadata.insert("session_id".to_string(), session_id.to_string());
-                }
-
-                AgentEvent {
-                    _agent_type: "opencode".to_string(),
-                    event_type: EventType::System,
-                    content: json!({
-                        "type": "step_start"
-                    }),
-                    metadata,
-                    timestamp,
-                }
-            }
+            "step_start" => AgentEvent {
+                _agent_type: "opencode".to_string(),
+                event_type: EventType::System,
+                content: json!({
+                    "type": "step_start"
+                }),
+                metadata,
+                timestamp,
+            },
             "text" => {
                 let text = Self::value_to_string(
                     json.get("part")
@@ -116,6 +179,13 @@ impl OpenCodeParser {
                     .and_then(|s| s.get("input"))
                     .cloned()
                     .unwrap_or(json!(null));
+                // Edit/write/read tools name their file in `filePath` (or `path`).
+                let file = ["filePath", "path"]
+                    .iter()
+                    .find_map(|key| args.get(*key).and_then(|v| v.as_str()));
+                if let Some(file) = file {
+                    Self::insert_files(&mut metadata, &[file.to_string()]);
+                }
 
                 AgentEvent {
                     _agent_type: "opencode".to_string(),
@@ -148,6 +218,11 @@ impl OpenCodeParser {
                     .and_then(|p| p.get("cost"))
                     .cloned()
                     .unwrap_or(json!(null));
+                let tokens = part
+                    .and_then(|p| p.get("tokens"))
+                    .cloned()
+                    .unwrap_or(json!(null));
+                Self::insert_cost(&mut metadata, &duration, &tokens);
 
                 let result_text = if reason == "stop" {
                     "complete".to_string()
@@ -160,13 +235,98 @@ impl OpenCodeParser {
                     event_type,
                     content: json!({
                         "result": result_text,
+                        // Kept for existing consumers; OpenCode reports cost, not time.
                         "duration": duration,
+                        "cost": duration,
+                        "tokens": tokens,
+                    }),
+                    metadata,
+                    timestamp,
+                }
+            }
+            "patch" => {
+                let part = json.get("part");
+                let files = Self::string_list(part.and_then(|p| p.get("files")));
+                Self::insert_files(&mut metadata, &files);
+                let hash = part
+                    .and_then(|p| p.get("hash"))
+                    .cloned()
+                    .unwrap_or(json!(null));
+
+                AgentEvent {
+                    _agent_type: "opencode".to_string(),
+                    event_type: EventType::Custom("patch".to_string()),
+                    content: json!({
+                        "message": format!("Edited {}", files.join(", ")),
+                        "files": files,
+                        "hash": hash,
+                    }),
+                    metadata,
+                    timestamp,
+                }
+            }
+            "file.edited" | "file_edited" => {
+                let file = Self::value_to_string(
+                    json.get("properties")
+                        .and_then(|p| p.get("file"))
+                        .or_else(|| json.get("file")),
+                );
+                Self::insert_files(&mut metadata, std::slice::from_ref(&file));
+
+                AgentEvent {
+                    _agent_type: "opencode".to_string(),
+                    event_type: EventType::Custom("file_edited".to_string()),
+                    content: json!({
+                        "message": format!("Edited {}", file),
+                        "files": [file],
                     }),
                     metadata,
                     timestamp,
                 }
             }
-            "error" => {
+            "session.created" | "session_created" | "session.updated" | "session_updated" => {
+                let info = json.get("properties").and_then(|p| p.get("info"));
+                let title = Self::value_to_string(info.and_then(|i| i.get("title")));
+                if !title.is_empty() {
+                    metadata.insert("title".to_string(), title.clone());
+                }
+                let directory = info
+                    .and_then(|i| i.get("directory"))
+                    .cloned()
+                    .unwrap_or(json!(null));
+
+                AgentEvent {
+                    _agent_type: "opencode".to_string(),
+                    event_type: EventType::System,
+                    content: json!({
+                        "type": typ.replace('.', "_"),
+                        "title": title,
+                        "directory": directory,
+                    }),
+                    metadata,
+                    timestamp,
+                }
+            }
+            "session.idle" | "session_idle" | "session.deleted" | "session_deleted" => {
+                let kind = typ.replace('.', "_");
+                let message = if kind == "session_idle" {
+                    "Session is idle"
+                } else {
+                    "Session deleted"
+                };
+
+                AgentEvent {
+                    _agent_type: "opencode".to_string(),
+                    event_type: EventType::Custom(kind),
+                    content: json!({
+                        "message": message,
+                    }),
+                    metadata,
+                    timestamp,
+                }
+            }
+            "error" | "session.error" | "session_error" => {
+                let json = json.get("properties").unwrap_or(&json);
                 let message = Self::value_to_string(
                     json.get("error")
                         .and_then(|e| e.get("data"))
@@ -306,6 +466,79 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_parse_step_finish_cost_summary() {
+        let mut parser = OpenCodeParser::new(InputFormat::StreamJson).unwrap();
+        let line = r#"{"type":"step_finish","timestamp":1700000003000,"sessionID":"sess-1","part":{"type":"step-finish","reason":"stop","cost":0.0125,"tokens":{"input":1200,"output":340,"reasoning":0,"cache":{"read":9000,"write":12}}}}"#;
+
+        let event = parser.parse_line(line).await.unwrap().unwrap();
+        assert_eq!(event.event_type, EventType::Result);
+        assert_eq!(event.metadata.get("session_id").unwrap(), "sess-1");
+        assert_eq!(event.metadata.get("cost").unwrap(), "0.0125");
+        assert_eq!(event.metadata.get("input_tokens").unwrap(), "1200");
+        assert_eq!(event.metadata.get("cache_read_tokens").unwrap(), "9000");
+        assert_eq!(event.content["tokens"]["output"], 340);
+    }
+
+    #[tokio::test]
+    async fn test_parse_patch_and_file_edits() {
+        let mut parser = OpenCodeParser::new(InputFormat::StreamJson).unwrap();
+        let line = r#"{"type":"patch","timestamp":1700000005000,"sessionID":"sess-1","part":{"type":"patch","hash":"abc123","files":["src/main.rs","README.md"]}}"#;
+        let event = parser.parse_line(line).await.unwrap().unwrap();
+        assert_eq!(event.event_type, EventType::Custom("patch".to_string()));
+        assert_eq!(
+            event.metadata.get("files").unwrap(),
+            r#"["src/main.rs","README.md"]"#
+        );
+        assert_eq!(
+            event.content.get("message").and_then(|v| v.as_str()),
+            Some("Edited src/main.rs, README.md")
+        );
+
+        let line = r#"{"type":"file.edited","properties":{"file":"/repo/src/lib.rs"}}"#;
+        let event = parser.parse_line(line).await.unwrap().unwrap();
+        assert_eq!(
+            event.event_type,
+            EventType::Custom("file_edited".to_string())
+        );
+        assert_eq!(
+            event.metadata.get("files").unwrap(),
+            r#"["/repo/src/lib.rs"]"#
+        );
+
+        let line = r#"{"type":"tool_use","sessionID":"sess-1","part":{"tool":"edit","state":{"status":"completed","input":{"filePath":"/repo/a.rs"}}}}"#;
+        let event = parser.parse_line(line).await.unwrap().unwrap();
+        assert_eq!(event.event_type, EventType::ToolCall);
+        assert_eq!(event.metadata.get("files").unwrap(), r#"["/repo/a.rs"]"#);
+    }
+
+    #[tokio::test]
+    async fn test_parse_session_lifecycle() {
+        let mut parser = OpenCodeParser::new(InputFormat::StreamJson).unwrap();
+        let line = r#"{"type":"session.created","properties":{"info":{"id":"ses_1","title":"Fix tests","directory":"/repo"}}}"#;
+        let event = parser.parse_line(line).await.unwrap().unwrap();
+        assert_eq!(event.event_type, EventType::System);
+        assert_eq!(event.metadata.get("session_id").unwrap(), "ses_1");
+        assert_eq!(event.metadata.get("title").unwrap(), "Fix tests");
+        assert_eq!(event.content["type"], "session_created");
+
+        let line = r#"{"type":"session.idle","properties":{"sessionID":"ses_1"}}"#;
+        let event = parser.parse_line(line).await.unwrap().unwrap();
+        assert_eq!(
+            event.event_type,
+            EventType::Custom("session_idle".to_string())
+        );
+        assert_eq!(event.metadata.get("session_id").unwrap(), "ses_1");
+
+        let line = r#"{"type":"session.error","properties":{"sessionID":"ses_1","error":{"name":"ProviderAuthError","data":{"message":"invalid key"}}}}"#;
+        let event = parser.parse_line(line).await.unwrap().unwrap();
+        assert_eq!(event.event_type, EventType::Error);
+        assert_eq!(
+            event.content.get("message").and_then(|v| v.as_str()),
+            Some("invalid key")
+        );
+    }
+
     #[tokio::test]
     async fn test_parse_error() {
         let mut parser = OpenCodeParser::new(InputFormat::StreamJson).unwrap();
@@ -0,0 +1,208 @@
+//! Reassembly of JSON values that span several lines
+//!
+//! Line-based parsers expect one JSON value per line, but some agents pretty-print their
+//! events. [`JsonReassembler`] tracks nesting across lines and yields each complete top-level
+//! object or array as a single line, ready for [`AgentParser::parse_line`]. Lines that do not
+//! start a JSON value are passed through unchanged.
+//!
+//! [`AgentParser::parse_line`]: crate::parser::AgentParser::parse_line
+
+/// Default cap on the size of one reassembled value.
+pub const DEFAULT_MAX_VALUE_BYTES: usize = 1024 * 1024;
+
+/// A value that could not be reassembled.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ReassemblyError {
+    /// The value grew past the buffer limit; `partial` holds what was buffered. The rest of the
+    /// value is skipped.
+    #[error("JSON value exceeds {limit} bytes; skipping the rest of it")]
+    TooLarge { limit: usize, partial: String },
+    /// Input ended inside a value.
+    #[error("input ended inside a JSON value")]
+    Incomplete { partial: String },
+}
+
+impl ReassemblyError {
+    /// The text buffered before the error.
+    pub fn partial(&self) -> &str {
+        match self {
+            ReassemblyError::TooLarge { partial, .. } | ReassemblyError::Incomplete { partial } => {
+                partial
+            }
+        }
+    }
+}
+
+/// Joins the lines of multi-line JSON values.
+pub struct JsonReassembler {
+    max_bytes: usize,
+    buf: String,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// Skipping the rest of a value that exceeded `max_bytes`.
+    overflowed: bool,
+}
+
+impl JsonReassembler {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            buf: String::new(),
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            overflowed: false,
+        }
+    }
+
+    /// Feed one input line; returns the values it completes, in order.
+    pub fn push_line(&mut self, line: &str) -> Vec<Result<String, ReassemblyError>> {
+        let mut out = Vec::new();
+        if self.depth == 0 {
+            let trimmed = line.trim_start();
+            if !trimmed.starts_with(['{', '[']) {
+                if !trimmed.is_empty() {
+                    out.push(Ok(line.to_string()));
+                }
+                return out;
+            }
+        } else if !self.overflowed {
+            // Raw newlines are only valid as whitespace, so values come out as one line.
+            self.buf.push(' ');
+        }
+
+        for c in line.trim().chars() {
+            if self.depth == 0 && c.is_whitespace() {
+                continue;
+            }
+            if !self.overflowed {
+                self.buf.push(c);
+            }
+            if self.in_string {
+                match c {
+                    _ if self.escaped => self.escaped = false,
+                    '\\' => self.escaped = true,
+                    '"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => self.in_string = true,
+                '{' | '[' => self.depth += 1,
+                '}' | ']' => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+            if self.depth == 0 {
+                if self.overflowed {
+                    self.overflowed = false;
+                } else {
+                    out.push(Ok(std::mem::take(&mut self.buf)));
+                }
+            }
+        }
+
+        if !self.overflowed && self.buf.len() > self.max_bytes {
+            self.overflowed = true;
+            out.push(Err(ReassemblyError::TooLarge {
+                limit: self.max_bytes,
+                partial: std::mem::take(&mut self.buf),
+            }));
+        }
+        out
+    }
+
+    /// Whether no value is in progress.
+    pub fn is_idle(&self) -> bool {
+        self.depth == 0
+    }
+
+    /// End of input: reports a value that was never closed.
+    pub fn finish(&mut self) -> Option<ReassemblyError> {
+        let partial = std::mem::take(&mut self.buf);
+        let unfinished = self.depth > 0 && !self.overflowed;
+        *self = Self::new(self.max_bytes);
+        unfinished.then_some(ReassemblyError::Incomplete { partial })
+    }
+}
+
+impl Default for JsonReassembler {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_VALUE_BYTES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(reassembler: &mut JsonReassembler, text: &str) -> Vec<Result<String, ReassemblyError>> {
+        text.lines()
+            .flat_map(|line| reassembler.push_line(line))
+            .collect()
+    }
+
+    #[test]
+    fn joins_pretty_printed_objects() {
+        let mut reassembler = JsonReassembler::default();
+        let out = feed(
+            &mut reassembler,
+            "{\n  \"type\": \"text\",\n  \"part\": {\"text\": \"a } in [a] string\"}\n}\n{\"type\":\"step_start\"}",
+        );
+        assert_eq!(
+            out,
+            vec![
+                Ok(r#"{ "type": "text", "part": {"text": "a } in [a] string"} }"#.to_string()),
+                Ok(r#"{"type":"step_start"}"#.to_string()),
+            ]
+        );
+        let value: serde_json::Value = serde_json::from_str(out[0].as_ref().unwrap()).unwrap();
+        assert_eq!(value["part"]["text"], "a } in [a] string");
+        assert_eq!(reassembler.finish(), None);
+    }
+
+    #[test]
+    fn passes_other_lines_through() {
+        let mut reassembler = JsonReassembler::default();
+        let out = feed(
+            &mut reassembler,
+            "Starting agent...\n\n{\"a\":\"\\\"\"} {\"b\":1}",
+        );
+        assert_eq!(
+            out,
+            vec![
+                Ok("Starting agent...".to_string()),
+                Ok(r#"{"a":"\""}"#.to_string()),
+                Ok(r#"{"b":1}"#.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_value_is_skipped() {
+        let mut reassembler = JsonReassembler::new(16);
+        let out = feed(
+            &mut reassembler,
+            "{\n\"text\": \"0123456789abcdef\",\n\"more\": 1\n}\n{\"ok\":1}",
+        );
+        assert_eq!(out.len(), 2);
+        assert!(matches!(
+            &out[0],
+            Err(ReassemblyError::TooLarge { limit: 16, .. })
+        ));
+        assert_eq!(out[1], Ok(r#"{"ok":1}"#.to_string()));
+    }
+
+    #[test]
+    fn unfinished_value_is_reported() {
+        let mut reassembler = JsonReassembler::default();
+        assert!(feed(&mut reassembler, "{\n\"type\": \"text\"").is_empty());
+        assert_eq!(
+            reassembler.finish(),
+            Some(ReassemblyError::Incomplete {
+                partial: r#"{ "type": "text""#.to_string()
+            })
+        );
+    }
+}
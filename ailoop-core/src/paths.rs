@@ -0,0 +1,187 @@
+//! Where ailoop keeps files other than its configuration.
+//!
+//! State that has to survive a restart (the outbox, scheduled messages, `forward` checkpoints,
+//! the Telegram update offset, the server's event log) lives in `$XDG_STATE_HOME/ailoop`, by default
+//! `~/.local/state/ailoop`. Files that can be thrown away (attachments) live in
+//! `$XDG_CACHE_HOME/ailoop`, by default `~/.cache/ailoop`. Where there is no XDG state directory
+//! (macOS, Windows) state goes in the local data directory as before.
+//!
+//! `AILOOP_DATA_DIR`, or `--data-dir`, replaces both: state goes directly in it and cache in its
+//! `cache` subdirectory.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Environment variable overriding the state and cache directories.
+pub const DATA_DIR_ENV: &str = "AILOOP_DATA_DIR";
+
+/// Data directory given on the command line (`--data-dir`).
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keep this process's state and cache under `dir`, overriding `AILOOP_DATA_DIR`. Only the first
+/// call has an effect.
+pub fn set_data_dir(dir: impl Into<PathBuf>) {
+    let _ = DATA_DIR.set(dir.into());
+}
+
+/// The `--data-dir` or `AILOOP_DATA_DIR` directory, when one is set.
+pub fn data_dir_override() -> Option<PathBuf> {
+    override_dir(DATA_DIR.get(), std::env::var_os(DATA_DIR_ENV))
+}
+
+/// `flag`, else `env` (the value of `AILOOP_DATA_DIR`) when it is not empty.
+fn override_dir(flag: Option<&PathBuf>, env: Option<OsString>) -> Option<PathBuf> {
+    flag.cloned()
+        .or_else(|| env.filter(|v| !v.is_empty()).map(PathBuf::from))
+}
+
+/// Directory for state; `None` when no home directory is known.
+pub fn state_dir() -> Option<PathBuf> {
+    state_dir_from(data_dir_override(), std::env::var_os("XDG_STATE_HOME"))
+}
+
+/// `overridden`, else `ailoop` in `xdg_state_home` or the platform's state or local data
+/// directory.
+fn state_dir_from(
+    overridden: Option<PathBuf>,
+    xdg_state_home: Option<OsString>,
+) -> Option<PathBuf> {
+    if let Some(dir) = overridden {
+        return Some(dir);
+    }
+    xdg_dir(xdg_state_home)
+        .or_else(dirs::state_dir)
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("ailoop"))
+}
+
+/// Directory for disposable files; falls back to the system temporary directory.
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = data_dir_override() {
+        return dir.join("cache");
+    }
+    xdg_dir(std::env::var_os("XDG_CACHE_HOME"))
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("ailoop")
+}
+
+/// `name` in the state directory.
+///
+/// Without an override, a file or directory of that name left by an older release in the local
+/// data directory (`~/.local/share/ailoop`) or the config directory is used until one exists in
+/// the state directory, so queued messages and offsets are not lost on upgrade.
+pub fn state_path(name: &str) -> Option<PathBuf> {
+    let legacy_roots: Vec<PathBuf> = [dirs::data_local_dir(), dirs::config_dir()]
+        .into_iter()
+        .flatten()
+        .collect();
+    Some(state_path_in(
+        &state_dir()?,
+        data_dir_override().is_some(),
+        &legacy_roots,
+        name,
+    ))
+}
+
+/// `name` in `state_dir`, or in the first of `legacy_roots` that still has it when the state
+/// directory does not and was not `overridden`.
+fn state_path_in(
+    state_dir: &Path,
+    overridden: bool,
+    legacy_roots: &[PathBuf],
+    name: &str,
+) -> PathBuf {
+    let path = state_dir.join(name);
+    if overridden || path.exists() {
+        return path;
+    }
+    legacy_roots
+        .iter()
+        .map(|dir| dir.join("ailoop").join(name))
+        .find(|legacy| legacy.exists())
+        .unwrap_or(path)
+}
+
+/// `name` in the cache directory.
+pub fn cache_path(name: &str) -> PathBuf {
+    cache_dir().join(name)
+}
+
+/// The value of an XDG base directory variable; relative values are ignored, as the spec
+/// requires.
+fn xdg_dir(value: Option<OsString>) -> Option<PathBuf> {
+    value.map(PathBuf::from).filter(|dir| dir.is_absolute())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(value: &str) -> Option<OsString> {
+        Some(OsString::from(value))
+    }
+
+    #[test]
+    fn test_data_dir_flag_beats_env() {
+        let flag = PathBuf::from("/srv/flag");
+        assert_eq!(override_dir(Some(&flag), var("/srv/env")), Some(flag));
+    }
+
+    #[test]
+    fn test_data_dir_env_applies_without_flag() {
+        assert_eq!(
+            override_dir(None, var("/srv/env")),
+            Some(PathBuf::from("/srv/env"))
+        );
+        assert_eq!(override_dir(None, var("")), None);
+        assert_eq!(override_dir(None, None), None);
+    }
+
+    #[test]
+    fn test_override_beats_xdg_state_home() {
+        assert_eq!(
+            state_dir_from(Some(PathBuf::from("/srv/data")), var("/xdg/state")),
+            Some(PathBuf::from("/srv/data"))
+        );
+    }
+
+    #[test]
+    fn test_xdg_state_home_applies_without_override() {
+        assert_eq!(
+            state_dir_from(None, var("/xdg/state")),
+            Some(PathBuf::from("/xdg/state/ailoop"))
+        );
+        assert_ne!(
+            state_dir_from(None, var("relative/state")),
+            Some(PathBuf::from("relative/state/ailoop"))
+        );
+    }
+
+    #[test]
+    fn test_legacy_state_path_is_kept_until_the_new_one_exists() {
+        let root = tempfile::tempdir().unwrap();
+        let state = root.path().join("state");
+        let legacy_root = root.path().join("share");
+        let roots = [root.path().join("config"), legacy_root.clone()];
+        let legacy = legacy_root.join("ailoop").join("outbox");
+        std::fs::create_dir_all(&legacy).unwrap();
+
+        assert_eq!(state_path_in(&state, false, &roots, "outbox"), legacy);
+        assert_eq!(
+            state_path_in(&state, true, &roots, "outbox"),
+            state.join("outbox")
+        );
+
+        std::fs::create_dir_all(state.join("outbox")).unwrap();
+        assert_eq!(
+            state_path_in(&state, false, &roots, "outbox"),
+            state.join("outbox")
+        );
+        assert_eq!(
+            state_path_in(&state, false, &roots, "offset"),
+            state.join("offset")
+        );
+    }
+}
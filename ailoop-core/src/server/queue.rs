@@ -1,7 +1,8 @@
 //! Message queuing system
 
-use crate::models::Message;
+use crate::models::{Message, NotificationPriority};
 use std::collections::VecDeque;
+use uuid::Uuid;
 
 /// Message queue for handling incoming messages
 pub struct MessageQueue {
@@ -32,6 +33,16 @@ impl MessageQueue {
         self.queue.pop_front()
     }
 
+    /// Put a message back at the head of the queue, as the next one out
+    pub fn requeue_front(&mut self, message: Message) {
+        self.queue.push_front(message);
+    }
+
+    /// Remove and return the last message in the queue
+    pub fn dequeue_last(&mut self) -> Option<Message> {
+        self.queue.pop_back()
+    }
+
     /// Check if the queue is empty
     pub fn is_empty(&self) -> bool {
         self.queue.is_empty()
@@ -46,6 +57,47 @@ impl MessageQueue {
     pub fn clear(&mut self) {
         self.queue.clear();
     }
+
+    /// Queued messages, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &Message> {
+        self.queue.iter()
+    }
+
+    /// Remove the message with `id`, wherever it is in the queue
+    pub fn remove(&mut self, id: &Uuid) -> Option<Message> {
+        let index = self.queue.iter().position(|m| &m.id == id)?;
+        self.queue.remove(index)
+    }
+
+    /// Raise the queued message with `id` to `priority`, recorded in `metadata.boosted`, and
+    /// move it ahead of the messages of lower priority before it. Returns its new index, or
+    /// `None` when it is not queued. A priority that is not higher leaves it in place.
+    pub fn boost(&mut self, id: &Uuid, priority: NotificationPriority) -> Option<usize> {
+        let index = self.queue.iter().position(|m| &m.id == id)?;
+        if priority.rank() <= self.queue[index].queue_priority().rank() {
+            return Some(index);
+        }
+        let mut message = self.queue.remove(index)?;
+        let mut metadata = match message.metadata.take() {
+            Some(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        metadata.insert("boosted".to_string(), serde_json::json!(priority));
+        message.metadata = Some(serde_json::Value::Object(metadata));
+        let target = self
+            .queue
+            .iter()
+            .take(index)
+            .position(|m| m.queue_priority().rank() < priority.rank())
+            .unwrap_or(index);
+        self.queue.insert(target, message);
+        Some(target)
+    }
+
+    /// Remove and return all messages, oldest first
+    pub fn drain(&mut self) -> Vec<Message> {
+        self.queue.drain(..).collect()
+    }
 }
 
 impl Default for MessageQueue {
@@ -141,4 +193,104 @@ mod tests {
         assert!(first.is_some());
         assert_eq!(first.unwrap().channel, "channel-1");
     }
+
+    #[test]
+    fn test_message_queue_remove_and_drain() {
+        let mut queue = MessageQueue::new(10);
+        let content = MessageContent::Notification {
+            text: "build finished".to_string(),
+            priority: crate::models::NotificationPriority::Normal,
+        };
+        let messages: Vec<Message> = (0..3)
+            .map(|_| Message::new("ops".to_string(), SenderType::Agent, content.clone()))
+            .collect();
+        for message in &messages {
+            queue.enqueue(message.clone());
+        }
+
+        let removed = queue.remove(&messages[1].id).unwrap();
+        assert_eq!(removed.id, messages[1].id);
+        assert!(queue.remove(&messages[1].id).is_none());
+        let ids: Vec<_> = queue.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![messages[0].id, messages[2].id]);
+
+        assert_eq!(queue.drain().len(), 2);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_message_queue_requeue_front_and_dequeue_last() {
+        let mut queue = MessageQueue::new(10);
+        let messages: Vec<_> = (0..3)
+            .map(|i| crate::client::authorization("ops", &format!("deploy v{}", i), 60))
+            .collect();
+        for message in &messages {
+            queue.enqueue(message.clone());
+        }
+
+        let first = queue.dequeue().unwrap();
+        queue.requeue_front(first);
+        assert_eq!(queue.iter().next().unwrap().id, messages[0].id);
+        assert_eq!(queue.dequeue_last().unwrap().id, messages[2].id);
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn test_message_queue_boost() {
+        let mut queue = MessageQueue::new(10);
+        let notify = |priority| MessageContent::Notification {
+            text: "build finished".to_string(),
+            priority,
+        };
+        let messages = vec![
+            Message::new(
+                "ops".to_string(),
+                SenderType::Agent,
+                notify(NotificationPriority::Urgent),
+            ),
+            Message::new(
+                "ops".to_string(),
+                SenderType::Agent,
+                notify(NotificationPriority::Normal),
+            ),
+            crate::client::authorization("ops", "deploy v1", 60),
+            crate::client::authorization("ops", "deploy v2", 60),
+        ];
+        for message in &messages {
+            queue.enqueue(message.clone());
+        }
+
+        // Ahead of the normal messages, behind the urgent one.
+        assert_eq!(
+            queue.boost(&messages[3].id, NotificationPriority::High),
+            Some(1)
+        );
+        let ids: Vec<_> = queue.iter().map(|m| m.id).collect();
+        assert_eq!(
+            ids,
+            vec![
+                messages[0].id,
+                messages[3].id,
+                messages[1].id,
+                messages[2].id
+            ]
+        );
+        assert!(matches!(
+            queue.iter().nth(1).unwrap().queue_priority(),
+            NotificationPriority::High
+        ));
+        // Lowering does nothing, and an equal priority stays behind earlier boosts.
+        assert_eq!(
+            queue.boost(&messages[3].id, NotificationPriority::Low),
+            Some(1)
+        );
+        assert_eq!(
+            queue.boost(&messages[2].id, NotificationPriority::High),
+            Some(2)
+        );
+        assert_eq!(
+            queue.boost(&uuid::Uuid::new_v4(), NotificationPriority::Urgent),
+            None
+        );
+    }
 }
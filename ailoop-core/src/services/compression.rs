@@ -0,0 +1,108 @@
+//! zstd compression of files ailoop writes
+//!
+//! Rotated event log segments and exports (`--compress`) are written as zstd frames. Readers
+//! go through [`open`], which recognises zstd data by its magic number and decompresses it as
+//! it reads, so compressed and plain files can be mixed freely.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Extension appended to compressed files (`events.log.1.zst`).
+pub const EXTENSION: &str = "zst";
+
+/// zstd's default level: most of the size reduction for little CPU.
+const LEVEL: i32 = 3;
+
+/// First four bytes of every zstd frame.
+const MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Whether `data` starts with a zstd frame.
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::encode_all(data, LEVEL).context("Failed to compress")
+}
+
+/// `data` decompressed when it is zstd, else as is.
+pub fn decompress(data: Vec<u8>) -> Result<Vec<u8>> {
+    if !is_compressed(&data) {
+        return Ok(data);
+    }
+    zstd::decode_all(data.as_slice()).context("Failed to decompress")
+}
+
+/// A reader over `path`'s contents, decompressed when the file is zstd.
+pub fn open(path: &Path) -> Result<Box<dyn BufRead + Send>> {
+    let mut file = BufReader::new(
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+    );
+    let compressed = is_compressed(file.fill_buf()?);
+    if compressed {
+        let decoder = zstd::Decoder::with_buffer(file)?;
+        Ok(Box::new(BufReader::new(decoder)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+/// `path` with `.zst` appended.
+pub fn compressed_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", EXTENSION));
+    PathBuf::from(name)
+}
+
+/// Replace the file at `path` with a compressed copy at `<path>.zst`, returning the new path.
+/// The original is only removed once the copy is complete.
+pub fn compress_file(path: &Path) -> Result<PathBuf> {
+    let target = compressed_path(path);
+    let mut input =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let output =
+        File::create(&target).with_context(|| format!("Failed to create {}", target.display()))?;
+    let mut encoder = zstd::Encoder::new(output, LEVEL)?;
+    std::io::copy(&mut input, &mut encoder)
+        .with_context(|| format!("Failed to compress {}", path.display()))?;
+    encoder.finish()?.flush()?;
+    std::fs::remove_file(path)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_compressed_files_read_back_transparently() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = "{\"event\":\"prompt\"}\n".repeat(1000);
+        let plain = dir.path().join("events.log.1");
+        std::fs::write(&plain, &text).unwrap();
+
+        let compressed = compress_file(&plain).unwrap();
+        assert_eq!(compressed, dir.path().join("events.log.1.zst"));
+        assert!(!plain.exists());
+        assert!(std::fs::metadata(&compressed).unwrap().len() < text.len() as u64 / 10);
+
+        let mut read = String::new();
+        open(&compressed)
+            .unwrap()
+            .read_to_string(&mut read)
+            .unwrap();
+        assert_eq!(read, text);
+
+        let other = dir.path().join("other.log");
+        std::fs::write(&other, "plain\n").unwrap();
+        assert_eq!(open(&other).unwrap().lines().count(), 1);
+
+        let bytes = compress(text.as_bytes()).unwrap();
+        assert!(is_compressed(&bytes));
+        assert_eq!(decompress(bytes).unwrap(), text.as_bytes());
+        assert_eq!(decompress(b"plain".to_vec()).unwrap(), b"plain");
+    }
+}
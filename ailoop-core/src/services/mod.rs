@@ -1,4 +1,6 @@
 //! Business logic services
 
+pub mod compression;
 pub mod interaction;
 pub mod logging;
+pub mod signing;
@@ -0,0 +1,317 @@
+//! Ed25519 signatures on prompt responses
+//!
+//! A signed response carries `metadata.signature` with the responder identity, the signing
+//! public key and a signature over the response's id, channel, correlation id, timestamp,
+//! content and responder. Agents verify with [`verify_response`] and compare the returned
+//! public key against keys they trust, so an approval can be tied to a specific person.
+
+use crate::models::{Message, MessageContent, SigningConfig};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Signature algorithm recorded in response metadata.
+pub const SIGNATURE_ALG: &str = "ed25519";
+
+/// Responder recorded when nobody else is attributed (terminal answers, timeouts).
+pub const OPERATOR_RESPONDER: &str = "operator";
+
+/// Signing and verification errors
+#[derive(Debug, thiserror::Error)]
+pub enum SigningError {
+    #[error("key file {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid key file {0}: expected a base64-encoded 32-byte seed")]
+    InvalidKey(String),
+    #[error("response is not signed")]
+    Unsigned,
+    #[error("malformed signature: {0}")]
+    Malformed(String),
+    #[error("signature does not match response")]
+    Mismatch,
+}
+
+/// `metadata.signature` of a signed response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseSignature {
+    pub alg: String,
+    /// Who answered, e.g. `"operator"`, `"telegram:alice"` or a JWT subject.
+    pub signer: String,
+    /// Base64 ed25519 public key.
+    pub public_key: String,
+    /// Base64 signature.
+    pub value: String,
+}
+
+/// Exactly what gets signed. Field order is fixed so the encoding is stable.
+#[derive(Serialize)]
+struct SignedPayload<'a> {
+    id: &'a Uuid,
+    channel: &'a str,
+    correlation_id: &'a Option<Uuid>,
+    timestamp: &'a DateTime<Utc>,
+    content: &'a MessageContent,
+    signer: &'a str,
+}
+
+fn payload(message: &Message, signer: &str) -> Vec<u8> {
+    serde_json::to_vec(&SignedPayload {
+        id: &message.id,
+        channel: &message.channel,
+        correlation_id: &message.correlation_id,
+        timestamp: &message.timestamp,
+        content: &message.content,
+        signer,
+    })
+    .expect("signed payload serializes")
+}
+
+/// Operator key plus optional per-responder keys.
+pub struct ResponseSigner {
+    operator: SigningKey,
+    identities: HashMap<String, SigningKey>,
+}
+
+impl ResponseSigner {
+    pub fn new(operator: SigningKey) -> Self {
+        Self {
+            operator,
+            identities: HashMap::new(),
+        }
+    }
+
+    /// Use `key` for `identity` (a responder such as `"telegram:alice"`, or a provider name).
+    pub fn with_identity(mut self, identity: impl Into<String>, key: SigningKey) -> Self {
+        self.identities.insert(identity.into(), key);
+        self
+    }
+
+    /// Build from `[signing]`, loading (or generating) every configured key file.
+    /// Returns `Ok(None)` when signing is disabled.
+    pub fn from_config(config: &SigningConfig) -> Result<Option<Self>, SigningError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let operator_path = config
+            .key_file
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(default_key_path);
+        let mut signer = Self::new(load_or_generate_key(&operator_path)?);
+        for (identity, path) in &config.identities {
+            signer = signer.with_identity(identity, load_or_generate_key(Path::new(path))?);
+        }
+        Ok(Some(signer))
+    }
+
+    /// Key for `responder`: exact match, then its provider prefix, then the operator key.
+    fn key_for(&self, responder: &str) -> &SigningKey {
+        self.identities
+            .get(responder)
+            .or_else(|| {
+                responder
+                    .split_once(':')
+                    .and_then(|(provider, _)| self.identities.get(provider))
+            })
+            .unwrap_or(&self.operator)
+    }
+
+    /// Base64 public key of the operator key.
+    pub fn operator_public_key(&self) -> String {
+        STANDARD.encode(self.operator.verifying_key().as_bytes())
+    }
+
+    /// Sign `message` as `responder`, storing the result in `metadata.signature`.
+    pub fn sign(&self, message: &mut Message, responder: &str) -> ResponseSignature {
+        let key = self.key_for(responder);
+        let signature = ResponseSignature {
+            alg: SIGNATURE_ALG.to_string(),
+            signer: responder.to_string(),
+            public_key: STANDARD.encode(key.verifying_key().as_bytes()),
+            value: STANDARD.encode(key.sign(&payload(message, responder)).to_bytes()),
+        };
+        let value = serde_json::to_value(&signature).expect("signature serializes");
+        match message.metadata.as_mut().and_then(|m| m.as_object_mut()) {
+            Some(map) => {
+                map.insert("signature".to_string(), value);
+            }
+            None => message.metadata = Some(serde_json::json!({ "signature": value })),
+        }
+        signature
+    }
+}
+
+/// Check `metadata.signature` against the message. Returns the signature on success; callers
+/// should still compare `public_key` against the keys they trust.
+pub fn verify_response(message: &Message) -> Result<ResponseSignature, SigningError> {
+    let raw = message
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("signature"))
+        .ok_or(SigningError::Unsigned)?;
+    let signature: ResponseSignature =
+        serde_json::from_value(raw.clone()).map_err(|e| SigningError::Malformed(e.to_string()))?;
+    if signature.alg != SIGNATURE_ALG {
+        return Err(SigningError::Malformed(format!(
+            "unsupported alg {}",
+            signature.alg
+        )));
+    }
+
+    let key_bytes: [u8; 32] = decode_fixed(&signature.public_key)?;
+    let sig_bytes: [u8; 64] = decode_fixed(&signature.value)?;
+    let key =
+        VerifyingKey::from_bytes(&key_bytes).map_err(|e| SigningError::Malformed(e.to_string()))?;
+    key.verify(
+        &payload(message, &signature.signer),
+        &Signature::from_bytes(&sig_bytes),
+    )
+    .map_err(|_| SigningError::Mismatch)?;
+    Ok(signature)
+}
+
+fn decode_fixed<const N: usize>(encoded: &str) -> Result<[u8; N], SigningError> {
+    STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| SigningError::Malformed(format!("expected {} base64 bytes", N)))
+}
+
+/// Default operator key location.
+pub fn default_key_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("ailoop")
+        .join("signing.key")
+}
+
+/// Read a base64 seed from `path`, creating a fresh key (mode 0600 on Unix) if it is missing.
+pub fn load_or_generate_key(path: &Path) -> Result<SigningKey, SigningError> {
+    let io_err = |source| SigningError::Io {
+        path: path.display().to_string(),
+        source,
+    };
+    if path.exists() {
+        let content = std::fs::read_to_string(path).map_err(io_err)?;
+        let seed: [u8; 32] = decode_fixed(content.trim())
+            .map_err(|_| SigningError::InvalidKey(path.display().to_string()))?;
+        return Ok(SigningKey::from_bytes(&seed));
+    }
+
+    let key = SigningKey::generate(&mut rand_core::OsRng);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(io_err)?;
+    }
+    std::fs::write(path, STANDARD.encode(key.to_bytes())).map_err(io_err)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).map_err(io_err)?;
+    }
+    tracing::info!("Generated response signing key at {}", path.display());
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ResponseType;
+
+    fn response() -> Message {
+        Message::response(
+            "public".to_string(),
+            MessageContent::Response {
+                answer: None,
+                response_type: ResponseType::AuthorizationApproved,
+            },
+            Uuid::new_v4(),
+        )
+    }
+
+    #[test]
+    fn test_sign_and_verify_roundtrip() {
+        let signer = ResponseSigner::new(SigningKey::from_bytes(&[7u8; 32]));
+        let mut msg = response();
+        msg.metadata = Some(serde_json::json!({"responder": "operator"}));
+        let sig = signer.sign(&mut msg, OPERATOR_RESPONDER);
+
+        // Survives a JSON round trip, as agents receive it over the wire.
+        let wire: Message = serde_json::from_str(&serde_json::to_string(&msg).unwrap()).unwrap();
+        let verified = verify_response(&wire).unwrap();
+        assert_eq!(verified, sig);
+        assert_eq!(verified.public_key, signer.operator_public_key());
+        assert_eq!(wire.metadata.unwrap()["responder"], "operator");
+    }
+
+    #[test]
+    fn test_tampered_response_fails() {
+        let signer = ResponseSigner::new(SigningKey::from_bytes(&[7u8; 32]));
+        let mut msg = response();
+        signer.sign(&mut msg, "telegram:alice");
+        msg.content = MessageContent::Response {
+            answer: None,
+            response_type: ResponseType::AuthorizationDenied,
+        };
+        assert!(matches!(verify_response(&msg), Err(SigningError::Mismatch)));
+
+        let mut unsigned = response();
+        assert!(matches!(
+            verify_response(&unsigned),
+            Err(SigningError::Unsigned)
+        ));
+        unsigned.metadata = Some(serde_json::json!({"signature": {"alg": "rsa"}}));
+        assert!(matches!(
+            verify_response(&unsigned),
+            Err(SigningError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_identity_keys_selected_by_responder_or_provider() {
+        let alice = SigningKey::from_bytes(&[1u8; 32]);
+        let telegram = SigningKey::from_bytes(&[2u8; 32]);
+        let signer = ResponseSigner::new(SigningKey::from_bytes(&[3u8; 32]))
+            .with_identity("telegram:alice", alice.clone())
+            .with_identity("telegram", telegram.clone());
+
+        let b64 = |k: &SigningKey| STANDARD.encode(k.verifying_key().as_bytes());
+        assert_eq!(
+            signer.sign(&mut response(), "telegram:alice").public_key,
+            b64(&alice)
+        );
+        assert_eq!(
+            signer.sign(&mut response(), "telegram:bob").public_key,
+            b64(&telegram)
+        );
+        assert_eq!(
+            signer.sign(&mut response(), OPERATOR_RESPONDER).public_key,
+            signer.operator_public_key()
+        );
+    }
+
+    #[test]
+    fn test_key_file_generated_then_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys").join("operator.key");
+        let first = load_or_generate_key(&path).unwrap();
+        let second = load_or_generate_key(&path).unwrap();
+        assert_eq!(first.to_bytes(), second.to_bytes());
+
+        std::fs::write(&path, "not a key").unwrap();
+        assert!(matches!(
+            load_or_generate_key(&path),
+            Err(SigningError::InvalidKey(_))
+        ));
+
+        let disabled = SigningConfig::default();
+        assert!(ResponseSigner::from_config(&disabled).unwrap().is_none());
+    }
+}
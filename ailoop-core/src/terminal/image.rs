@@ -0,0 +1,111 @@
+//! Inline image thumbnails for terminals that can show them
+//!
+//! Kitty (graphics protocol, PNG only) and iTerm2 or WezTerm (inline images protocol) draw an
+//! image from an escape sequence. Other terminals get nothing from [`ImageProtocol::detect`],
+//! and callers print a link instead.
+
+use base64::Engine;
+
+/// Width of a thumbnail, in terminal cells.
+pub const THUMBNAIL_COLUMNS: u32 = 40;
+
+/// Larger images are linked rather than drawn.
+pub const MAX_THUMBNAIL_BYTES: usize = 5 * 1024 * 1024;
+
+/// Kitty sends base64 payloads in chunks of at most this many bytes.
+const KITTY_CHUNK: usize = 4096;
+
+/// How the terminal is asked to draw an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    Kitty,
+    Iterm2,
+}
+
+impl ImageProtocol {
+    /// The protocol the current terminal speaks, from `TERM`, `TERM_PROGRAM` and
+    /// `LC_TERMINAL`. Inside tmux or screen nothing is detected, since they do not pass the
+    /// sequences through.
+    pub fn detect() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        if std::env::var_os("TMUX").is_some() || var("TERM").starts_with("screen") {
+            return None;
+        }
+        if var("TERM") == "xterm-kitty" || std::env::var_os("KITTY_WINDOW_ID").is_some() {
+            return Some(ImageProtocol::Kitty);
+        }
+        if matches!(var("TERM_PROGRAM").as_str(), "iTerm.app" | "WezTerm")
+            || var("LC_TERMINAL") == "iTerm2"
+        {
+            return Some(ImageProtocol::Iterm2);
+        }
+        None
+    }
+
+    /// Whether the terminal can draw an image of `content_type`.
+    pub fn supports(&self, content_type: &str) -> bool {
+        match self {
+            ImageProtocol::Kitty => content_type == "image/png",
+            ImageProtocol::Iterm2 => matches!(
+                content_type,
+                "image/png" | "image/jpeg" | "image/gif" | "image/webp"
+            ),
+        }
+    }
+
+    /// Escape sequence drawing `bytes` as a thumbnail `columns` cells wide.
+    pub fn thumbnail(&self, bytes: &[u8], columns: u32) -> String {
+        let payload = base64::engine::general_purpose::STANDARD.encode(bytes);
+        match self {
+            ImageProtocol::Kitty => {
+                let chunks: Vec<&str> = payload
+                    .as_bytes()
+                    .chunks(KITTY_CHUNK)
+                    .map(|c| std::str::from_utf8(c).unwrap_or_default())
+                    .collect();
+                let mut out = String::new();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let more = u8::from(i + 1 < chunks.len());
+                    if i == 0 {
+                        out.push_str(&format!(
+                            "\x1b_Ga=T,f=100,c={},m={};{}\x1b\\",
+                            columns, more, chunk
+                        ));
+                    } else {
+                        out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+                    }
+                }
+                out
+            }
+            ImageProtocol::Iterm2 => format!(
+                "\x1b]1337;File=inline=1;size={};width={};preserveAspectRatio=1:{}\x07",
+                bytes.len(),
+                columns,
+                payload
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_sequences() {
+        let iterm = ImageProtocol::Iterm2.thumbnail(b"png", 40);
+        assert_eq!(
+            iterm,
+            "\x1b]1337;File=inline=1;size=3;width=40;preserveAspectRatio=1:cG5n\x07"
+        );
+
+        // Large images go to kitty in chunks; only the last one has m=0.
+        let kitty = ImageProtocol::Kitty.thumbnail(&[0u8; 6000], 40);
+        assert!(kitty.starts_with("\x1b_Ga=T,f=100,c=40,m=1;"));
+        assert_eq!(kitty.matches("\x1b_G").count(), 2);
+        assert!(kitty.contains("\x1b_Gm=0;"));
+
+        assert!(!ImageProtocol::Kitty.supports("image/jpeg"));
+        assert!(ImageProtocol::Iterm2.supports("image/jpeg"));
+    }
+}
@@ -1 +1,2 @@
 pub mod countdown;
+pub mod image;
@@ -0,0 +1,237 @@
+//! Chunking of large messages over WebSocket
+//!
+//! The server accepts WebSocket frames of at most [`MAX_FRAME_BYTES`], and proxies in between
+//! may allow less. A message whose JSON is longer than [`CHUNK_BYTES`] (a big diff, a
+//! transcript) is sent as a series of `chunk` frames instead, each carrying a slice of the JSON
+//! text, its position and the message id. The server joins them with a [`ChunkReassembler`]
+//! and handles the result as if it had arrived in one frame:
+//!
+//! ```json
+//! {"type": "chunk", "message_id": "<uuid>", "index": 0, "total": 3, "data": "{\"id\": ..."}
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Largest WebSocket frame the server accepts.
+pub const MAX_FRAME_BYTES: usize = 1024 * 1024;
+
+/// Messages longer than this are split into chunks of at most this many bytes of JSON. Escaping
+/// at most doubles a slice, so a chunk frame stays well under [`MAX_FRAME_BYTES`].
+pub const CHUNK_BYTES: usize = 256 * 1024;
+
+/// Largest message that may be sent in chunks, in bytes of JSON.
+pub const MAX_CHUNKED_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Most chunks one message may be split into, whatever their size.
+const MAX_CHUNKS: usize = 4096;
+
+/// How long the chunks of one message may take to arrive.
+const CHUNK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// One slice of a message's JSON text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "chunk")]
+pub struct Chunk {
+    /// Id of the message being sent.
+    pub message_id: Uuid,
+    /// Position of this slice, from 0.
+    pub index: usize,
+    /// Number of slices.
+    pub total: usize,
+    pub data: String,
+}
+
+/// Chunks that could not be joined. The partial message is discarded.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ChunkError {
+    #[error("chunk {index} of {total} for message {message_id} is out of range")]
+    InvalidIndex {
+        message_id: Uuid,
+        index: usize,
+        total: usize,
+    },
+    #[error("chunked message {message_id} exceeds {limit} bytes")]
+    TooLarge { message_id: Uuid, limit: usize },
+}
+
+/// Whether a WebSocket frame is a [`Chunk`].
+pub fn is_chunk(frame: &serde_json::Value) -> bool {
+    frame.get("type").and_then(|t| t.as_str()) == Some("chunk")
+}
+
+/// The frames to send for the message `message_id` serialized as `json`: the text itself when
+/// it fits in one chunk, else its chunks. Fails for messages over
+/// [`MAX_CHUNKED_MESSAGE_BYTES`].
+pub fn frames(message_id: Uuid, json: String) -> anyhow::Result<Vec<String>> {
+    if json.len() <= CHUNK_BYTES {
+        return Ok(vec![json]);
+    }
+    if json.len() > MAX_CHUNKED_MESSAGE_BYTES {
+        anyhow::bail!(
+            "Message is {} bytes; the limit is {} bytes",
+            json.len(),
+            MAX_CHUNKED_MESSAGE_BYTES
+        );
+    }
+    let slices = split(&json, CHUNK_BYTES);
+    let total = slices.len();
+    slices
+        .into_iter()
+        .enumerate()
+        .map(|(index, data)| {
+            let chunk = Chunk {
+                message_id,
+                index,
+                total,
+                data: data.to_string(),
+            };
+            Ok(serde_json::to_string(&chunk)?)
+        })
+        .collect()
+}
+
+/// Split `text` into slices of at most `max` bytes, on character boundaries.
+fn split(text: &str, max: usize) -> Vec<&str> {
+    let mut slices = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = max.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (slice, tail) = rest.split_at(end);
+        slices.push(slice);
+        rest = tail;
+    }
+    slices
+}
+
+struct Partial {
+    parts: Vec<Option<String>>,
+    received: usize,
+    bytes: usize,
+    started: Instant,
+}
+
+/// Joins the chunks received on one connection. Several messages may be in flight at once;
+/// together they may buffer at most `max_bytes`.
+pub struct ChunkReassembler {
+    max_bytes: usize,
+    partial: HashMap<Uuid, Partial>,
+}
+
+impl Default for ChunkReassembler {
+    fn default() -> Self {
+        Self::new(MAX_CHUNKED_MESSAGE_BYTES)
+    }
+}
+
+impl ChunkReassembler {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            partial: HashMap::new(),
+        }
+    }
+
+    /// Add `chunk`; returns the message's JSON text once its last chunk is in.
+    pub fn push(&mut self, chunk: Chunk) -> Result<Option<String>, ChunkError> {
+        self.partial
+            .retain(|_, p| p.started.elapsed() < CHUNK_TIMEOUT);
+        let id = chunk.message_id;
+        let invalid = ChunkError::InvalidIndex {
+            message_id: id,
+            index: chunk.index,
+            total: chunk.total,
+        };
+        if chunk.total == 0 || chunk.total > MAX_CHUNKS || chunk.index >= chunk.total {
+            self.partial.remove(&id);
+            return Err(invalid);
+        }
+        let buffered: usize = self.partial.values().map(|p| p.bytes).sum();
+        if buffered + chunk.data.len() > self.max_bytes {
+            self.partial.remove(&id);
+            return Err(ChunkError::TooLarge {
+                message_id: id,
+                limit: self.max_bytes,
+            });
+        }
+        let partial = self.partial.entry(id).or_insert_with(|| Partial {
+            parts: vec![None; chunk.total],
+            received: 0,
+            bytes: 0,
+            started: Instant::now(),
+        });
+        if partial.parts.len() != chunk.total {
+            self.partial.remove(&id);
+            return Err(invalid);
+        }
+        let slot = &mut partial.parts[chunk.index];
+        if slot.is_none() {
+            partial.received += 1;
+        }
+        partial.bytes += chunk.data.len();
+        partial.bytes -= slot.as_ref().map_or(0, String::len);
+        *slot = Some(chunk.data);
+        if partial.received < partial.parts.len() {
+            return Ok(None);
+        }
+        let partial = self.partial.remove(&id).expect("partial message present");
+        Ok(Some(partial.parts.into_iter().flatten().collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_message_round_trips_through_chunks() {
+        let id = Uuid::new_v4();
+        // Slice boundaries fall inside the two-byte characters.
+        let json = format!("{{\"text\":\"{}\\\"\"}}", "é".repeat(CHUNK_BYTES));
+        let sent = frames(id, json.clone()).unwrap();
+        assert!(sent.len() > 1);
+        assert!(sent.iter().all(|f| f.len() <= MAX_FRAME_BYTES));
+
+        let mut reassembler = ChunkReassembler::default();
+        let mut chunks: Vec<Chunk> = sent
+            .iter()
+            .map(|f| serde_json::from_str(f).unwrap())
+            .collect();
+        // Arrival order does not matter.
+        chunks.reverse();
+        let last = chunks.pop().unwrap();
+        for chunk in chunks {
+            assert_eq!(reassembler.push(chunk), Ok(None));
+        }
+        assert_eq!(reassembler.push(last), Ok(Some(json)));
+
+        assert_eq!(frames(id, "{}".to_string()).unwrap(), vec!["{}"]);
+    }
+
+    #[test]
+    fn test_chunk_limits_are_enforced() {
+        let id = Uuid::new_v4();
+        let chunk = |index, total, data: &str| Chunk {
+            message_id: id,
+            index,
+            total,
+            data: data.to_string(),
+        };
+        let mut reassembler = ChunkReassembler::new(8);
+        assert!(matches!(
+            reassembler.push(chunk(2, 2, "ab")),
+            Err(ChunkError::InvalidIndex { .. })
+        ));
+        assert_eq!(reassembler.push(chunk(0, 3, "abcd")), Ok(None));
+        assert!(matches!(
+            reassembler.push(chunk(1, 3, "efghi")),
+            Err(ChunkError::TooLarge { limit: 8, .. })
+        ));
+        assert!(frames(id, "x".repeat(MAX_CHUNKED_MESSAGE_BYTES + 1)).is_err());
+    }
+}
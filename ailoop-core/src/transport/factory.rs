@@ -1,5 +1,6 @@
 //! Transport factory for creating transport instances
 
+use super::outbox::{Outbox, OutboxTransport};
 use super::{file::FileTransport, websocket::WebSocketTransport, Transport};
 use anyhow::{Context, Result};
 
@@ -18,6 +19,8 @@ pub struct TransportConfig {
     pub file_path: Option<String>,
     pub channel: String,
     pub client_id: Option<String>,
+    /// Spool WebSocket messages here while the server is unreachable.
+    pub outbox: Option<Outbox>,
 }
 
 /// Create a transport instance based on configuration
@@ -25,11 +28,11 @@ pub fn create_transport(config: TransportConfig) -> Result<Box<dyn Transport>> {
     match config.transport_type {
         TransportType::WebSocket => {
             let url = config.url.context("WebSocket transport requires URL")?;
-            Ok(Box::new(WebSocketTransport::new(
-                url,
-                config.channel,
-                config.client_id,
-            )?))
+            let transport = WebSocketTransport::new(url.clone(), config.channel, config.client_id)?;
+            match config.outbox {
+                Some(outbox) => Ok(Box::new(OutboxTransport::new(transport, outbox, url))),
+                None => Ok(Box::new(transport)),
+            }
         }
         TransportType::File => {
             let file_path = config
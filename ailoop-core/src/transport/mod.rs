@@ -25,6 +25,9 @@ pub trait Transport: Send + Sync {
     fn name(&self) -> &str;
 }
 
+pub mod chunking;
 pub mod factory;
 pub mod file;
+pub mod outbox;
+pub mod proxy;
 pub mod websocket;
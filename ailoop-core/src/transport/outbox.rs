@@ -0,0 +1,246 @@
+//! Offline outbox: messages spooled to disk while the server is unreachable
+//!
+//! Each message is one JSON file (`<micros>-<message id>.json`) holding the server URL and the
+//! message, so entries from concurrent CLI invocations never interleave. Entries are sent in
+//! file-name order and removed once delivered; delivery is at least once.
+
+use super::websocket::WebSocketTransport;
+use super::Transport;
+use crate::models::{Message, OutboxConfig};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// One spooled message and the server it was meant for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub server: String,
+    pub message: Message,
+}
+
+/// Result of [`Outbox::flush`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlushReport {
+    pub sent: usize,
+    /// Entries left because their server was still unreachable.
+    pub remaining: usize,
+}
+
+/// Spool directory for undeliverable messages.
+#[derive(Debug, Clone)]
+pub struct Outbox {
+    dir: PathBuf,
+}
+
+impl Outbox {
+    /// Outbox in `dir` (created on first spool).
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Outbox at `[outbox] dir`, or `outbox` in the state directory.
+    pub fn from_config(config: &OutboxConfig) -> Result<Self> {
+        match &config.dir {
+            Some(dir) => Ok(Self::new(dir)),
+            None => {
+                let dir = crate::paths::state_path("outbox")
+                    .context("Could not determine state directory; set [outbox] dir")?;
+                Ok(Self::new(dir))
+            }
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Store `message` for `server`.
+    pub fn spool(&self, server: &str, message: &Message) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create outbox {:?}", self.dir))?;
+        let name = format!(
+            "{:020}-{}.json",
+            chrono::Utc::now().timestamp_micros(),
+            message.id
+        );
+        let entry = OutboxEntry {
+            server: server.to_string(),
+            message: message.clone(),
+        };
+        let json = serde_json::to_vec(&entry).context("Failed to serialize outbox entry")?;
+        // Write then rename so a flush never reads a partial entry.
+        let tmp = self.dir.join(format!(".{}.tmp", name));
+        std::fs::write(&tmp, json).with_context(|| format!("Failed to write {:?}", tmp))?;
+        std::fs::rename(&tmp, self.dir.join(&name))
+            .with_context(|| format!("Failed to store outbox entry {}", name))?;
+        Ok(())
+    }
+
+    /// Spooled entries, oldest first. Unreadable files are skipped with a warning.
+    pub fn entries(&self) -> Result<Vec<(PathBuf, OutboxEntry)>> {
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(&self.dir) {
+            Ok(dir) => dir
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", self.dir)),
+        };
+        paths.sort();
+        Ok(paths
+            .into_iter()
+            .filter_map(|path| {
+                let entry = std::fs::read(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|bytes| Ok(serde_json::from_slice::<OutboxEntry>(&bytes)?));
+                match entry {
+                    Ok(entry) => Some((path, entry)),
+                    Err(e) => {
+                        tracing::warn!(path = ?path, error = %e, "Skipping unreadable outbox entry");
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+
+    /// Number of spooled entries, optionally only those for `server`.
+    pub fn pending(&self, server: Option<&str>) -> Result<usize> {
+        Ok(self
+            .entries()?
+            .iter()
+            .filter(|(_, e)| server.is_none_or(|s| e.server == s))
+            .count())
+    }
+
+    /// Send spooled entries (only those for `server` when given), one connection per server.
+    /// A server that fails keeps its remaining entries, in order, for the next flush.
+    pub async fn flush(&self, server: Option<&str>) -> Result<FlushReport> {
+        let mut report = FlushReport::default();
+        let mut transports: HashMap<String, WebSocketTransport> = HashMap::new();
+        let mut unreachable: HashSet<String> = HashSet::new();
+
+        for (path, entry) in self.entries()? {
+            if server.is_some_and(|s| entry.server != s) {
+                continue;
+            }
+            if unreachable.contains(&entry.server) {
+                report.remaining += 1;
+                continue;
+            }
+            let transport = match transports.entry(entry.server.clone()) {
+                std::collections::hash_map::Entry::Occupied(t) => t.into_mut(),
+                std::collections::hash_map::Entry::Vacant(v) => v.insert(WebSocketTransport::new(
+                    entry.server.clone(),
+                    entry.message.channel.clone(),
+                    None,
+                )?),
+            };
+            match transport.send(entry.message).await {
+                Ok(()) => {
+                    std::fs::remove_file(&path)
+                        .with_context(|| format!("Failed to remove {:?}", path))?;
+                    report.sent += 1;
+                }
+                Err(e) => {
+                    tracing::warn!(server = %entry.server, error = %e, "Outbox flush failed");
+                    transport.take_buffered();
+                    unreachable.insert(entry.server);
+                    report.remaining += 1;
+                }
+            }
+        }
+
+        for (_, mut transport) in transports {
+            let _ = transport.close().await;
+        }
+        Ok(report)
+    }
+
+    /// Send `message` to `server`, spooling it instead when the server is unreachable or older
+    /// entries for it are still waiting (so order is kept). Returns `true` when sent now.
+    pub async fn send_or_spool(&self, server: &str, message: Message) -> Result<bool> {
+        if self.pending(Some(server))? > 0 && self.flush(Some(server)).await?.remaining > 0 {
+            self.spool(server, &message)?;
+            return Ok(false);
+        }
+        match super::websocket::send_message_no_response(
+            server.to_string(),
+            message.channel.clone(),
+            message.clone(),
+        )
+        .await
+        {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                tracing::warn!(server = %server, error = %e, "Server unreachable; spooling message");
+                self.spool(server, &message)?;
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// WebSocket transport that spools to an [`Outbox`] once the server stops answering.
+///
+/// The first send flushes older entries for the same server. After a failed send, that message
+/// and every later one go straight to the outbox for the rest of the stream.
+pub struct OutboxTransport {
+    inner: WebSocketTransport,
+    outbox: Outbox,
+    server: String,
+    flushed: bool,
+    offline: bool,
+}
+
+impl OutboxTransport {
+    pub fn new(inner: WebSocketTransport, outbox: Outbox, server: String) -> Self {
+        Self {
+            inner,
+            outbox,
+            server,
+            flushed: false,
+            offline: false,
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for OutboxTransport {
+    async fn send(&mut self, message: Message) -> Result<()> {
+        if !self.flushed {
+            self.flushed = true;
+            if self.outbox.pending(Some(&self.server))? > 0 {
+                self.offline = self.outbox.flush(Some(&self.server)).await?.remaining > 0;
+            }
+        }
+        if self.offline {
+            return self.outbox.spool(&self.server, &message);
+        }
+        if let Err(e) = self.inner.send(message).await {
+            tracing::warn!(server = %self.server, error = %e, "Server unreachable; spooling messages");
+            self.offline = true;
+            for message in self.inner.take_buffered() {
+                self.outbox.spool(&self.server, &message)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.offline {
+            return Ok(());
+        }
+        self.inner.flush().await
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.inner.close().await
+    }
+
+    fn name(&self) -> &str {
+        "outbox"
+    }
+}
@@ -5,11 +5,12 @@ use async_trait::async_trait;
 use futures_util::{SinkExt, StreamExt};
 use std::collections::VecDeque;
 use tokio::sync::Mutex;
-use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage, WebSocketStream};
+use tokio_tungstenite::{tungstenite::Message as WsMessage, WebSocketStream};
 use url::Url;
 
+use super::proxy::connect_websocket;
 use super::Transport;
-use crate::models::{Message, MessageContent};
+use crate::models::{Message, MessageContent, SenderType};
 
 /// WebSocket transport for sending messages to ailoop server
 pub struct WebSocketTransport {
@@ -35,6 +36,11 @@ impl WebSocketTransport {
         })
     }
 
+    /// Remove and return messages buffered after failed sends, oldest first.
+    pub fn take_buffered(&mut self) -> Vec<Message> {
+        self.buffer.drain(..).collect()
+    }
+
     /// Connect to the WebSocket server with retry logic and exponential backoff
     async fn connect_with_retry(&mut self) -> Result<()> {
         const MAX_RETRIES: u32 = 5;
@@ -58,7 +64,7 @@ impl WebSocketTransport {
             }
 
             // Try to connect
-            match connect_async(url.clone()).await {
+            match connect_websocket(&url).await {
                 Ok((ws_stream, _)) => {
                     self.connection = Some(Mutex::new(ws_stream));
                     return Ok(());
@@ -129,12 +135,12 @@ impl WebSocketTransport {
         let conn = self.connection.as_mut().unwrap();
         let mut stream = conn.lock().await;
 
-        let json = serde_json::to_string(&message).context("Failed to serialize message")?;
-
-        stream
-            .send(WsMessage::Text(json))
-            .await
-            .context("Failed to send message over WebSocket")?;
+        for frame in message_frames(&message)? {
+            stream
+                .send(frame)
+                .await
+                .context("Failed to send message over WebSocket")?;
+        }
 
         Ok(())
     }
@@ -190,6 +196,19 @@ impl Transport for WebSocketTransport {
     }
 }
 
+/// The text frames carrying `message`: one, or its chunks when it is large (see
+/// [`super::chunking`]).
+pub(crate) fn message_frames(message: &Message) -> Result<Vec<WsMessage>> {
+    let json = serde_json::to_string(message).context("Failed to serialize message")?;
+    Ok(super::chunking::frames(message.id, json)?
+        .into_iter()
+        .map(WsMessage::Text)
+        .collect())
+}
+
+/// How long past a prompt's timeout to wait for the server's own timeout answer.
+const SERVER_TIMEOUT_GRACE: tokio::time::Duration = tokio::time::Duration::from_secs(5);
+
 /// Send a message and wait for response (common function for ask/authorize commands)
 pub async fn send_message_and_wait_response(
     url: String,
@@ -200,7 +219,7 @@ pub async fn send_message_and_wait_response(
     // Connect to WebSocket
     let url_parsed = Url::parse(&url).with_context(|| format!("Invalid WebSocket URL: {}", url))?;
 
-    let (ws_stream, _) = connect_async(url_parsed)
+    let (ws_stream, _) = connect_websocket(&url_parsed)
         .await
         .context("Failed to connect to WebSocket server")?;
 
@@ -208,30 +227,23 @@ pub async fn send_message_and_wait_response(
     let (mut sender, mut receiver) = ws_stream.split();
 
     // Send the message
-    let json = serde_json::to_string(&message).context("Failed to serialize message")?;
-
-    sender
-        .send(WsMessage::Text(json))
-        .await
-        .context("Failed to send message")?;
+    for frame in message_frames(&message)? {
+        sender.send(frame).await.context("Failed to send message")?;
+    }
 
-    // Wait for response with timeout
-    let timeout_duration = if timeout_secs > 0 {
-        tokio::time::Duration::from_secs(timeout_secs as u64)
-    } else {
-        tokio::time::Duration::from_secs(3600) // 1 hour default
-    };
+    // The server answers an expired prompt itself (see `TimeoutPolicy`), so wait a little past
+    // the prompt's timeout for that answer; with 0 the server applies its own default, and
+    // there is no client-side limit.
+    let deadline = (timeout_secs > 0).then(|| {
+        tokio::time::Instant::now()
+            + tokio::time::Duration::from_secs(timeout_secs as u64)
+            + SERVER_TIMEOUT_GRACE
+    });
 
     let message_id = message.id;
-    let start_time = std::time::Instant::now();
 
     // Keep receiving messages until we find the response or timeout
     loop {
-        let remaining_time = timeout_duration.saturating_sub(start_time.elapsed());
-        if remaining_time.is_zero() {
-            return Ok(None); // Timeout
-        }
-
         tokio::select! {
             msg = receiver.next() => {
                 match msg {
@@ -266,6 +278,12 @@ pub async fn send_message_and_wait_response(
                                     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                                     return Ok(Some(message));
                                 }
+                                // Server notices (e.g. the channel was paused) explain a long wait
+                                if let (SenderType::System, MessageContent::Notification { text, .. }) =
+                                    (&message.sender_type, &message.content)
+                                {
+                                    eprintln!("Server: {}", text);
+                                }
                                 // Not a response, continue waiting
                                 continue;
                             }
@@ -295,7 +313,12 @@ pub async fn send_message_and_wait_response(
                     }
                 }
             }
-            _ = tokio::time::sleep(remaining_time) => {
+            _ = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            } => {
                 // Timeout - close connection gracefully
                 let _ = sender.close().await;
                 tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
@@ -320,7 +343,7 @@ pub async fn send_message_no_response(
     // Connect to WebSocket
     let url_parsed = Url::parse(&url).with_context(|| format!("Invalid WebSocket URL: {}", url))?;
 
-    let (ws_stream, _) = connect_async(url_parsed)
+    let (ws_stream, _) = connect_websocket(&url_parsed)
         .await
         .context("Failed to connect to WebSocket server")?;
 
@@ -328,12 +351,9 @@ pub async fn send_message_no_response(
     let (mut sender, _receiver) = ws_stream.split();
 
     // Send the message
-    let json = serde_json::to_string(&message).context("Failed to serialize message")?;
-
-    sender
-        .send(WsMessage::Text(json))
-        .await
-        .context("Failed to send message")?;
+    for frame in message_frames(&message)? {
+        sender.send(frame).await.context("Failed to send message")?;
+    }
 
     // Close the connection gracefully
     let _ = sender.close().await;
@@ -341,3 +361,64 @@ pub async fn send_message_no_response(
 
     Ok(())
 }
+
+/// How long the server must stay quiet after a batch before no more rejections are expected.
+const REJECTION_GRACE: tokio::time::Duration = tokio::time::Duration::from_millis(300);
+
+/// Longest wait for rejections after a batch is sent.
+const REJECTION_WAIT_MAX: tokio::time::Duration = tokio::time::Duration::from_secs(2);
+
+/// Send several messages over one connection without waiting for human responses.
+///
+/// The server answers a message it refuses (over quota, blocked by a hook) with a cancelled
+/// response. Returns, for each message in order, the reason it gave, or `None` when the message
+/// was accepted.
+pub async fn send_messages_no_response(
+    url: String,
+    messages: &[Message],
+) -> Result<Vec<Option<String>>> {
+    let url_parsed = Url::parse(&url).with_context(|| format!("Invalid WebSocket URL: {}", url))?;
+
+    let (ws_stream, _) = connect_websocket(&url_parsed)
+        .await
+        .context("Failed to connect to WebSocket server")?;
+    let (mut sender, mut receiver) = ws_stream.split();
+
+    for message in messages {
+        for frame in message_frames(message)? {
+            sender.send(frame).await.context("Failed to send message")?;
+        }
+    }
+
+    let mut rejections: Vec<Option<String>> = vec![None; messages.len()];
+    let deadline = tokio::time::Instant::now() + REJECTION_WAIT_MAX;
+    loop {
+        let wait =
+            REJECTION_GRACE.min(deadline.saturating_duration_since(tokio::time::Instant::now()));
+        let Ok(Some(Ok(frame))) = tokio::time::timeout(wait, receiver.next()).await else {
+            break;
+        };
+        let WsMessage::Text(text) = frame else {
+            continue;
+        };
+        let Ok(reply) = serde_json::from_str::<Message>(&text) else {
+            continue;
+        };
+        let MessageContent::Response {
+            answer,
+            response_type: crate::models::ResponseType::Cancelled,
+        } = reply.content
+        else {
+            continue;
+        };
+        if let Some(index) = messages
+            .iter()
+            .position(|m| Some(m.id) == reply.correlation_id)
+        {
+            rejections[index] = Some(answer.unwrap_or_else(|| "rejected".to_string()));
+        }
+    }
+
+    let _ = sender.close().await;
+    Ok(rejections)
+}
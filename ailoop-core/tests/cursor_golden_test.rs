@@ -0,0 +1,110 @@
+//! Golden tests for the Cursor stream-json parser.
+//!
+//! Each `tests/fixtures/cursor/<kind>.jsonl` holds sample events of one kind; the events they
+//! parse to are kept in `<kind>.golden.json`. Run with `UPDATE_GOLDEN=1` to rewrite the golden
+//! files after an intended change, and review the diff.
+
+use ailoop_core::parser::{create_parser, AgentEvent, InputFormat};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cursor")
+}
+
+/// Event as compared against the golden file; the timestamp is left out because events without
+/// `timestamp_ms` are stamped with the parse time.
+fn golden_view(event: &AgentEvent) -> Value {
+    json!({
+        "event_type": event.event_type.name(),
+        "content": event.content,
+        "metadata": event.metadata,
+    })
+}
+
+async fn parse_fixture(path: &Path) -> Value {
+    let mut parser = create_parser(Some("cursor".to_string()), InputFormat::StreamJson).unwrap();
+    let mut events = Vec::new();
+    for line in std::fs::read_to_string(path).unwrap().lines() {
+        let event = parser
+            .parse_line(line)
+            .await
+            .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        events.push(event.as_ref().map(golden_view).unwrap_or(Value::Null));
+    }
+    Value::Array(events)
+}
+
+#[tokio::test]
+async fn cursor_fixtures_match_golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(fixtures_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no Cursor fixtures found");
+
+    let mut mismatched = Vec::new();
+    for fixture in &fixtures {
+        let actual = parse_fixture(fixture).await;
+        let golden = fixture.with_extension("golden.json");
+        if update {
+            std::fs::write(
+                &golden,
+                serde_json::to_string_pretty(&actual).unwrap() + "\n",
+            )
+            .unwrap();
+            continue;
+        }
+        let expected: Value = std::fs::read_to_string(&golden)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_else(|| panic!("missing or invalid {}", golden.display()));
+        if actual != expected {
+            mismatched.push(format!(
+                "{}:\n{}",
+                fixture.display(),
+                serde_json::to_string_pretty(&actual).unwrap()
+            ));
+        }
+    }
+    assert!(
+        mismatched.is_empty(),
+        "parsed events differ from the golden files (rerun with UPDATE_GOLDEN=1 if intended):\n{}",
+        mismatched.join("\n")
+    );
+}
+
+#[tokio::test]
+async fn cursor_fixtures_cover_every_event_kind() {
+    let kinds = [
+        "system",
+        "user",
+        "assistant",
+        "thinking",
+        "tool_call",
+        "result",
+        "error",
+    ];
+    let mut seen = std::collections::HashSet::new();
+    for entry in std::fs::read_dir(fixtures_dir()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "jsonl") {
+            for line in std::fs::read_to_string(&path).unwrap().lines() {
+                let raw: Value = serde_json::from_str(line).unwrap();
+                if let Some(kind) = raw.get("type").and_then(|t| t.as_str()) {
+                    seen.insert(kind.to_string());
+                }
+            }
+        }
+    }
+    for kind in kinds {
+        assert!(
+            seen.contains(kind),
+            "no fixture for Cursor '{}' events",
+            kind
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use ailoop_core::client::notification;
+use ailoop_core::transport::outbox::{FlushReport, Outbox, OutboxTransport};
+use ailoop_core::transport::websocket::WebSocketTransport;
+use ailoop_core::transport::Transport;
+use futures_util::StreamExt;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_tungstenite::{accept_async, tungstenite::Message as WsMessage};
+
+/// Port with nothing listening on it.
+async fn closed_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+/// WebSocket server forwarding every text frame it receives.
+async fn collecting_server() -> (String, mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut ws = accept_async(stream).await.unwrap();
+                while let Some(Ok(WsMessage::Text(text))) = ws.next().await {
+                    let _ = tx.send(text);
+                }
+            });
+        }
+    });
+    (url, rx)
+}
+
+fn text_of(frame: &str) -> String {
+    let message: ailoop_core::models::Message = serde_json::from_str(frame).unwrap();
+    match message.content {
+        ailoop_core::models::MessageContent::Notification { text, .. } => text,
+        other => panic!("unexpected content: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn outbox_spools_while_down_and_flushes_in_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let outbox = Outbox::new(dir.path());
+    let down = format!("ws://127.0.0.1:{}", closed_port().await);
+
+    assert!(!outbox
+        .send_or_spool(&down, notification("public", "first", "normal"))
+        .await
+        .unwrap());
+    assert!(!outbox
+        .send_or_spool(&down, notification("public", "second", "high"))
+        .await
+        .unwrap());
+    assert_eq!(outbox.pending(None).unwrap(), 2);
+    assert_eq!(
+        outbox.flush(Some(&down)).await.unwrap(),
+        FlushReport {
+            sent: 0,
+            remaining: 2
+        }
+    );
+
+    // Re-point the entries at a live server, as if it came back on the same address.
+    let (up, mut received) = collecting_server().await;
+    for (path, mut entry) in outbox.entries().unwrap() {
+        entry.server = up.clone();
+        std::fs::write(path, serde_json::to_vec(&entry).unwrap()).unwrap();
+    }
+
+    assert!(outbox
+        .send_or_spool(&up, notification("public", "third", "normal"))
+        .await
+        .unwrap());
+    let texts: Vec<String> = [
+        received.recv().await,
+        received.recv().await,
+        received.recv().await,
+    ]
+    .into_iter()
+    .map(|frame| text_of(&frame.unwrap()))
+    .collect();
+    assert_eq!(texts, vec!["first", "second", "third"]);
+    assert_eq!(outbox.pending(None).unwrap(), 0);
+}
+
+#[tokio::test]
+async fn outbox_transport_spools_stream_after_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    let outbox = Outbox::new(dir.path());
+    let down = format!("ws://127.0.0.1:{}", closed_port().await);
+
+    let inner = WebSocketTransport::new(down.clone(), "public".to_string(), None).unwrap();
+    let mut transport = OutboxTransport::new(inner, outbox.clone(), down.clone());
+    for text in ["a", "b", "c"] {
+        transport
+            .send(notification("public", text, "normal"))
+            .await
+            .unwrap();
+    }
+    transport.flush().await.unwrap();
+    transport.close().await.unwrap();
+
+    let texts: Vec<String> = outbox
+        .entries()
+        .unwrap()
+        .into_iter()
+        .map(|(_, entry)| {
+            assert_eq!(entry.server, down);
+            text_of(&serde_json::to_string(&entry.message).unwrap())
+        })
+        .collect();
+    assert_eq!(texts, vec!["a", "b", "c"]);
+}
@@ -6,6 +6,7 @@ import {
   AiloopClientOptions,
   ConnectionState,
   HealthResponse,
+  Capabilities,
   VersionInfo,
   ConnectionError,
   ValidationError,
@@ -15,6 +16,18 @@ import {
 } from './types';
 import { Message, MessageFactory, ResponseType, NotificationPriority, Task, TaskState, DependencyType } from './models';
 
+/** Random id naming one client's stream of numbered messages. */
+function newStreamId(): string {
+  const crypto = (globalThis as any).crypto;
+  if (crypto?.randomUUID) {
+    return crypto.randomUUID();
+  }
+  return 'xxxxxxxx-xxxx-4xxx-yxxx-xxxxxxxxxxxx'.replace(/[xy]/g, (c) => {
+    const r = (Math.random() * 16) | 0;
+    return (c === 'x' ? r : (r & 0x3) | 0x8).toString(16);
+  });
+}
+
 export class AiloopClient {
   private httpClient: AxiosInstance;
   private wsClient?: WebSocket;
@@ -31,6 +44,10 @@ export class AiloopClient {
   private wsUrl?: string;
   private manualDisconnect = false;
   private reconnectTimeoutId: ReturnType<typeof setTimeout> | null = null;
+  // Messages are numbered so the server records them in the order they were sent, even when
+  // concurrent requests arrive out of order.
+  private streamId = newStreamId();
+  private lastSeq = 0;
 
   constructor(options: AiloopClientOptions = {}) {
     this.options = {
@@ -48,8 +65,9 @@ export class AiloopClient {
   }
 
   private async sendMessage(message: Omit<Message, 'id' | 'timestamp'>): Promise<Message> {
+    const sequence = { stream: this.streamId, seq: ++this.lastSeq };
     try {
-      const response = await this.httpClient.post('/api/v1/messages', message);
+      const response = await this.httpClient.post('/api/v1/messages', { ...message, sequence });
       return response.data;
     } catch (error) {
       if (axios.isAxiosError(error)) {
@@ -494,6 +512,18 @@ export class AiloopClient {
     }
   }
 
+  async getCapabilities(): Promise<Capabilities> {
+    try {
+      const response = await this.httpClient.get('/api/capabilities');
+      return response.data;
+    } catch (error) {
+      if (axios.isAxiosError(error)) {
+        throw new ConnectionError(`Capability discovery failed: ${error.message}`);
+      }
+      throw new ConnectionError('Capability discovery failed');
+    }
+  }
+
   async checkVersion(): Promise<VersionInfo> {
     try {
       const health = await this.checkHealth();
@@ -19,6 +19,13 @@ export interface Message {
   timestamp: string;
   correlation_id?: string;
   metadata?: Record<string, any>;
+  sequence?: Sequence;
+}
+
+/** Position of a message in the order its client produced it. */
+export interface Sequence {
+  stream: string;
+  seq: number;
 }
 
 export type MessageContent =
@@ -75,6 +82,7 @@ export interface ResponseContent {
 export interface NavigateContent {
   type: 'navigate';
   url: string;
+  timeout_seconds?: number | undefined;
 }
 
 export interface TaskCreateContent {
@@ -21,6 +21,28 @@ export interface HealthResponse {
   activeChannels: number;
 }
 
+/** Response of GET /api/capabilities (field names as sent by the server). */
+export interface Capabilities {
+  version: string;
+  providers: string[];
+  content_types: string[];
+  response_types: string[];
+  signed_responses: boolean;
+  typing?: {
+    interval_ms: number;
+    partial_text: boolean;
+  };
+  limits: {
+    max_message_size?: number;
+    max_attachment_bytes: number;
+    max_frame_bytes: number;
+    max_chunked_message_bytes: number;
+    max_history_per_channel: number;
+    max_messages_per_hour?: number;
+    max_prompts_per_hour?: number;
+  };
+}
+
 export interface VersionInfo {
   clientVersion: string;
   serverVersion: string;
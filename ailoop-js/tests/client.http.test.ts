@@ -194,6 +194,21 @@ describe('AiloopClient HTTP Methods', () => {
     });
   });
 
+  describe('message ordering', () => {
+    it('should number messages in the order they are sent', async () => {
+      mockAxiosInstance.post.mockResolvedValue({ data: {} });
+
+      await Promise.all([
+        client.say('general', 'first'),
+        client.say('general', 'second'),
+      ]);
+
+      const sequences = mockAxiosInstance.post.mock.calls.map(([, body]) => body.sequence);
+      expect(sequences.map((s) => s.seq)).toEqual([1, 2]);
+      expect(sequences[0].stream).toEqual(sequences[1].stream);
+    });
+  });
+
   describe('navigate', () => {
     it('should send a navigation message', async () => {
       const mockResponse = {
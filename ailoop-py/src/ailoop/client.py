@@ -17,6 +17,7 @@ import websockets
 from .exceptions import ConnectionError, TimeoutError
 from .exceptions import ValidationError as AiloopValidationError
 from .models import Message, NavigateContent, NotificationPriority, ResponseType, SenderType
+from .models import Sequence
 from .models import Task
 
 logger = logging.getLogger(__name__)
@@ -60,6 +61,11 @@ class AiloopClient:
         self._reconnect_attempts = 0
         self._subscribed_channels: set[str] = set()
 
+        # Messages are numbered so the server records them in the order they were sent,
+        # even when concurrent requests arrive out of order
+        self._stream_id = uuid.uuid4()
+        self._last_seq = 0
+
         # Event handlers
         self._message_handlers: List[Callable] = []
         self._connection_handlers: List[Callable] = []
@@ -488,6 +494,25 @@ class AiloopClient:
         except Exception as e:
             raise ConnectionError(f"Failed to check version compatibility: {e}") from e
 
+    async def get_capabilities(self) -> Dict[str, Any]:
+        """Get what the server supports: running providers, content types and limits.
+
+        Returns:
+            The server's capabilities (see ``GET /api/capabilities``)
+
+        Raises:
+            ConnectionError: If server connection fails
+        """
+        if not self._http_client:
+            raise ConnectionError("Client not connected")
+
+        try:
+            response = await self._http_client.get("/api/capabilities")
+            response.raise_for_status()
+            return cast(Dict[str, Any], response.json())
+        except Exception as e:
+            raise ConnectionError(f"Failed to get server capabilities: {e}") from e
+
     async def create_task(
         self,
         title: str,
@@ -888,6 +913,9 @@ class AiloopClient:
         if not self._http_client:
             raise ConnectionError("Client not connected")
 
+        self._last_seq += 1
+        message.sequence = Sequence(stream=self._stream_id, seq=self._last_seq)
+
         try:
             response = await self._http_client.post(
                 "/api/v1/messages",
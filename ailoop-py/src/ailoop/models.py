@@ -14,6 +14,7 @@ class SenderType(str, Enum):
 
     AGENT = "AGENT"
     HUMAN = "HUMAN"
+    SYSTEM = "SYSTEM"
 
 
 class NotificationPriority(str, Enum):
@@ -92,6 +93,7 @@ class NavigateContent(BaseModel):
 
     type: Literal["navigate"] = "navigate"
     url: str
+    timeout_seconds: int = 0
 
 
 class TaskState(str, Enum):
@@ -182,6 +184,13 @@ MessageContent = Union[
 ]
 
 
+class Sequence(BaseModel):
+    """Position of a message in the order its client produced it."""
+
+    stream: UUID
+    seq: int
+
+
 class Message(BaseModel):
     """Core message structure."""
 
@@ -192,6 +201,7 @@ class Message(BaseModel):
     timestamp: datetime
     correlation_id: Optional[UUID] = None
     metadata: Optional[Dict[str, Any]] = None
+    sequence: Optional[Sequence] = None
 
     model_config = ConfigDict(
         use_enum_values=True,
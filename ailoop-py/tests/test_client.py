@@ -76,6 +76,27 @@ class TestAiloopClient:
         call_args = client._http_client.post.call_args
         assert call_args[0][0] == "/api/v1/messages"
 
+    @pytest.mark.asyncio
+    async def test_messages_are_numbered_in_send_order(self, client):
+        """Test that each sent message carries the next sequence number of the client."""
+        mock_response = Mock()
+        mock_response.json.return_value = {
+            "id": "550e8400-e29b-41d4-a716-446655440000",
+            "channel": "test",
+            "sender_type": "AGENT",
+            "content": {"type": "notification", "text": "Hello", "priority": "normal"},
+            "timestamp": "2024-01-15T12:00:00Z",
+        }
+        mock_response.raise_for_status = Mock()
+        client._http_client.post = AsyncMock(return_value=mock_response)
+
+        await client.say("first", channel="test")
+        await client.say("second", channel="test")
+
+        sent = [call.kwargs["json"]["sequence"] for call in client._http_client.post.call_args_list]
+        assert [s["seq"] for s in sent] == [1, 2]
+        assert sent[0]["stream"] == sent[1]["stream"]
+
     @pytest.mark.asyncio
     async def test_ask_question(self, client):
         """Test asking a decision."""
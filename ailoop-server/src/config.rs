@@ -1,4 +1,5 @@
 use crate::error::AiloopError;
+use std::collections::HashMap;
 
 /// Configuration for starting or embedding an ailoop server.
 #[derive(Debug, Clone)]
@@ -18,13 +19,185 @@ pub struct ServeConfig {
     pub auth: Option<AuthConfig>,
     /// CORS policy; `None` means no CORS headers are added.
     pub cors: Option<CorsConfig>,
+    /// Peer IP allow/deny lists; `None` accepts every peer.
+    pub ip_filter: Option<IpFilterConfig>,
 }
 
 /// Authentication configuration: a list of accepted bearer tokens / API keys.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct AuthConfig {
     /// Accepted tokens. Checked against `Authorization: Bearer <t>` or `X-Api-Key: <t>`.
+    /// Tokens listed here carry the [`Role::Admin`] role.
     pub tokens: Vec<String>,
+    /// Additional tokens bound to a specific role. A token present in both `tokens` and
+    /// `roles` uses the role given here.
+    pub roles: HashMap<String, Role>,
+    /// When `Some`, bearer tokens that are not static tokens are validated as JWTs
+    /// issued by the configured OIDC provider.
+    pub jwt: Option<JwtConfig>,
+}
+
+/// OIDC / JWT validation settings.
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    /// Expected `iss` claim, e.g. `"https://login.example.com/"`.
+    pub issuer: String,
+    /// Expected `aud` claim.
+    pub audience: String,
+    /// JWKS endpoint. Defaults to `{issuer}/.well-known/jwks.json`.
+    pub jwks_url: Option<String>,
+    /// Claim holding the caller's role(s); a string or an array of strings.
+    pub role_claim: String,
+    /// Maps claim values (e.g. IdP group names) to roles. Values not listed here are parsed
+    /// as role names (`admin`, `responder`, `viewer`).
+    pub role_mapping: HashMap<String, Role>,
+    /// Role granted when the claim is missing or matches nothing. `None` rejects such tokens.
+    pub default_role: Option<Role>,
+}
+
+impl JwtConfig {
+    /// Create a config for `issuer`/`audience` reading roles from the `roles` claim.
+    pub fn new(issuer: impl Into<String>, audience: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            audience: audience.into(),
+            jwks_url: None,
+            role_claim: "roles".to_string(),
+            role_mapping: HashMap::new(),
+            default_role: None,
+        }
+    }
+
+    /// JWKS endpoint, falling back to the issuer's well-known path.
+    pub fn effective_jwks_url(&self) -> String {
+        self.jwks_url.clone().unwrap_or_else(|| {
+            format!(
+                "{}/.well-known/jwks.json",
+                self.issuer.trim_end_matches('/')
+            )
+        })
+    }
+
+    /// Resolve the highest role granted by a role claim value.
+    pub fn role_from_claim(&self, claim: Option<&serde_json::Value>) -> Option<Role> {
+        let values: Vec<&str> = match claim {
+            Some(serde_json::Value::String(s)) => vec![s.as_str()],
+            Some(serde_json::Value::Array(items)) => {
+                items.iter().filter_map(|v| v.as_str()).collect()
+            }
+            _ => Vec::new(),
+        };
+        values
+            .into_iter()
+            .filter_map(|v| {
+                self.role_mapping
+                    .get(v)
+                    .copied()
+                    .or_else(|| v.parse::<Role>().ok())
+            })
+            .max()
+            .or(self.default_role)
+    }
+}
+
+/// Caller identity resolved by the auth middleware, stored in request extensions.
+#[derive(Debug, Clone)]
+pub struct AuthIdentity {
+    /// JWT `sub` claim; `None` for static tokens.
+    pub subject: Option<String>,
+    /// Short non-reversible fingerprint of a static token; `None` for JWTs.
+    pub token_fingerprint: Option<String>,
+    /// Effective role.
+    pub role: Role,
+}
+
+impl AuthIdentity {
+    /// Key identifying this caller in usage accounting (`sub:<subject>` or `token:<fp>`).
+    pub fn usage_key(&self) -> Option<String> {
+        self.subject
+            .as_ref()
+            .map(|s| format!("sub:{}", s))
+            .or_else(|| {
+                self.token_fingerprint
+                    .as_ref()
+                    .map(|f| format!("token:{}", f))
+            })
+    }
+}
+
+impl AuthConfig {
+    /// Resolve every accepted token to its role.
+    pub fn token_roles(&self) -> HashMap<String, Role> {
+        let mut out: HashMap<String, Role> = self
+            .tokens
+            .iter()
+            .map(|t| (t.clone(), Role::Admin))
+            .collect();
+        out.extend(self.roles.iter().map(|(t, r)| (t.clone(), *r)));
+        out
+    }
+}
+
+/// Permission level bound to an API token.
+///
+/// Roles are ordered: `Viewer < Responder < Admin`; a higher role may do everything
+/// a lower one can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    /// Read-only access to history, stats and the viewer stream.
+    Viewer,
+    /// Viewer access plus answering prompts and posting messages.
+    Responder,
+    /// Full access, including channel and task management.
+    Admin,
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "viewer" => Ok(Role::Viewer),
+            "responder" => Ok(Role::Responder),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("unknown role: '{other}'")),
+        }
+    }
+}
+
+/// Peer IP filtering applied to every HTTP request and WS upgrade.
+///
+/// Requires the router to be served with
+/// `into_make_service_with_connect_info::<SocketAddr>()`; requests without a known peer
+/// address are rejected.
+#[derive(Debug, Clone, Default)]
+pub struct IpFilterConfig {
+    /// When non-empty, only peers inside one of these networks are accepted.
+    pub allowed: Vec<ipnet::IpNet>,
+    /// Peers inside any of these networks are rejected, even if also allowed.
+    pub denied: Vec<ipnet::IpNet>,
+}
+
+impl IpFilterConfig {
+    /// Build from CIDR strings (bare addresses allowed), e.g. the `[security]` config section.
+    pub fn from_cidrs(allowed: &[String], denied: &[String]) -> Result<Self, AiloopError> {
+        Ok(Self {
+            allowed: ailoop_core::models::parse_cidrs(allowed).map_err(AiloopError::InvalidCidr)?,
+            denied: ailoop_core::models::parse_cidrs(denied).map_err(AiloopError::InvalidCidr)?,
+        })
+    }
+
+    /// Why `ip` is rejected, or `None` if it is accepted.
+    pub fn rejection_reason(&self, ip: std::net::IpAddr) -> Option<&'static str> {
+        let ip = ip.to_canonical();
+        if self.denied.iter().any(|n| n.contains(&ip)) {
+            Some("address is in denied_cidrs")
+        } else if !self.allowed.is_empty() && !self.allowed.iter().any(|n| n.contains(&ip)) {
+            Some("address is not in allowed_cidrs")
+        } else {
+            None
+        }
+    }
 }
 
 /// CORS configuration applied as a `tower_http::cors::CorsLayer`.
@@ -89,6 +262,7 @@ impl Default for ServeConfig {
             web: false,
             auth: None,
             cors: None,
+            ip_filter: None,
         }
     }
 }
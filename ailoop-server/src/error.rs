@@ -4,6 +4,9 @@ pub enum AiloopError {
     #[error("Invalid base_path: {0}")]
     InvalidBasePath(String),
 
+    #[error("Invalid IP filter: {0}")]
+    InvalidCidr(String),
+
     #[error("Server is shutting down")]
     ServerShuttingDown,
 
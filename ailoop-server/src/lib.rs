@@ -1,4 +1,3 @@
-#[cfg(feature = "auth")]
 pub mod middleware;
 pub mod server;
 
@@ -7,7 +6,9 @@ mod error;
 mod state;
 
 // Composable library API
-pub use crate::config::{AuthConfig, CorsConfig, ServeConfig};
+pub use crate::config::{
+    AuthConfig, AuthIdentity, CorsConfig, IpFilterConfig, JwtConfig, Role, ServeConfig,
+};
 pub use crate::error::AiloopError;
 pub use crate::server::core::{router, spawn_background_tasks};
 pub use crate::state::AiloopAppState;
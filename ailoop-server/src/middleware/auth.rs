@@ -1,16 +1,29 @@
 //! Tower middleware that enforces `Authorization: Bearer <token>` or `X-Api-Key: <key>`.
 //!
 //! When the token list is empty every request passes through unchanged (auth disabled).
+//!
+//! Each token carries a [`Role`]. The role required for a request is derived from its method
+//! and path (see [`required_role`]); a valid token with an insufficient role gets `403`.
+//! The resolved [`AuthIdentity`] is inserted into the request extensions for downstream
+//! handlers.
+//!
+//! With a [`JwtConfig`], bearer tokens that are not static tokens are validated as OIDC JWTs.
+//! Browsers that cannot set headers (the web UI) may pass `?access_token=<t>` instead.
 
+use crate::config::{AuthIdentity, JwtConfig, Role};
+use crate::middleware::jwt::JwtValidator;
+use ailoop_core::services::logging::log_security_event;
 use axum::{
     body::Body,
-    http::{Request, StatusCode},
+    http::{Method, Request, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use std::{
+    collections::HashMap,
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 use tower::{Layer, Service};
@@ -18,15 +31,30 @@ use tower::{Layer, Service};
 /// Tower layer that wraps a service with bearer/API-key authentication.
 #[derive(Clone)]
 pub struct AuthLayer {
-    tokens: Vec<String>,
+    tokens: Arc<HashMap<String, Role>>,
+    jwt: Option<Arc<JwtValidator>>,
 }
 
 impl AuthLayer {
-    /// Create a new auth layer.
+    /// Create a new auth layer where every token has the [`Role::Admin`] role.
     ///
     /// When `tokens` is empty every request passes through (auth is effectively disabled).
     pub fn new(tokens: Vec<String>) -> Self {
-        Self { tokens }
+        Self::with_roles(tokens.into_iter().map(|t| (t, Role::Admin)).collect())
+    }
+
+    /// Create a new auth layer from tokens bound to explicit roles.
+    pub fn with_roles(tokens: HashMap<String, Role>) -> Self {
+        Self {
+            tokens: Arc::new(tokens),
+            jwt: None,
+        }
+    }
+
+    /// Additionally accept JWTs issued by the configured OIDC provider.
+    pub fn with_jwt(mut self, config: JwtConfig) -> Self {
+        self.jwt = Some(Arc::new(JwtValidator::new(config)));
+        self
     }
 }
 
@@ -36,7 +64,8 @@ impl<S> Layer<S> for AuthLayer {
     fn layer(&self, inner: S) -> Self::Service {
         AuthMiddleware {
             inner,
-            tokens: self.tokens.clone(),
+            tokens: Arc::clone(&self.tokens),
+            jwt: self.jwt.clone(),
         }
     }
 }
@@ -45,7 +74,8 @@ impl<S> Layer<S> for AuthLayer {
 #[derive(Clone)]
 pub struct AuthMiddleware<S> {
     inner: S,
-    tokens: Vec<String>,
+    tokens: Arc<HashMap<String, Role>>,
+    jwt: Option<Arc<JwtValidator>>,
 }
 
 impl<S> Service<Request<Body>> for AuthMiddleware<S>
@@ -61,33 +91,130 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, req: Request<Body>) -> Self::Future {
-        let tokens = self.tokens.clone();
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        let tokens = Arc::clone(&self.tokens);
+        let jwt = self.jwt.clone();
         let mut inner = self.inner.clone();
 
         Box::pin(async move {
-            // Empty token list → auth disabled, pass through.
-            if tokens.is_empty() {
+            // No tokens and no JWT issuer → auth disabled, pass through. Provider webhooks
+            // authenticate themselves (e.g. Telegram's secret token header), and break-glass
+            // approvals carry a break-glass token instead of an API token.
+            if (tokens.is_empty() && jwt.is_none())
+                || is_provider_webhook(req.uri().path())
+                || is_break_glass(req.uri().path())
+            {
                 return inner.call(req).await;
             }
 
-            let extracted = extract_token(req.headers());
-            if let Some(tok) = extracted {
-                if tokens.contains(&tok) {
-                    return inner.call(req).await;
-                }
+            let identity = match extract_token(&req) {
+                Some(tok) => match tokens.get(&tok) {
+                    Some(role) => Some(AuthIdentity {
+                        subject: None,
+                        token_fingerprint: Some(fingerprint(&tok)),
+                        role: *role,
+                    }),
+                    None => match &jwt {
+                        Some(validator) => match validator.validate(&tok).await {
+                            Ok(identity) => Some(identity),
+                            Err(e) => {
+                                tracing::debug!("JWT rejected: {}", e);
+                                None
+                            }
+                        },
+                        None => None,
+                    },
+                },
+                None => None,
+            };
+            let Some(identity) = identity else {
+                return Ok((
+                    StatusCode::UNAUTHORIZED,
+                    Json(serde_json::json!({"error": "unauthorized"})),
+                )
+                    .into_response());
+            };
+
+            if identity.role < required_role(req.method(), req.uri().path()) {
+                log_security_event(
+                    "forbidden",
+                    "-",
+                    identity.subject.as_deref(),
+                    &format!("{} {}", req.method(), req.uri().path()),
+                );
+                return Ok((
+                    StatusCode::FORBIDDEN,
+                    Json(serde_json::json!({"error": "forbidden"})),
+                )
+                    .into_response());
             }
 
-            Ok((
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({"error": "unauthorized"})),
-            )
-                .into_response())
+            req.extensions_mut().insert(identity);
+            inner.call(req).await
         })
     }
 }
 
-fn extract_token(headers: &axum::http::HeaderMap) -> Option<String> {
+/// Minimum role needed to perform `method` on `path`.
+///
+/// - Read-only requests (`GET`, `HEAD`, `OPTIONS`), including the WS upgrade: viewer.
+/// - Answering a prompt or posting a message, now or scheduled for later: responder.
+/// - Claiming or releasing a pending prompt: responder.
+/// - Streaming a draft answer to a pending prompt (`typing`): responder.
+/// - Saying you are away or back, or changing the keywords you watch (`/api/v1/me/*`):
+///   responder.
+/// - Marking notifications read: responder.
+/// - Uploading an attachment: responder. Downloading one is a read like any other, since
+///   viewers already see the messages it was sent with.
+/// - Acknowledging or skipping a reminder: responder.
+/// - Reading the server's event log (`/api/logs`): admin.
+/// - Everything else (task, channel and reminder management): admin.
+pub fn required_role(method: &Method, path: &str) -> Role {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return match segments.as_slice() {
+            ["api", "logs"] => Role::Admin,
+            _ => Role::Viewer,
+        };
+    }
+    match segments.as_slice() {
+        ["api", "v1", "messages"]
+        | ["api", "v1", "messages", _, "response" | "claim" | "release" | "read" | "typing"]
+        | ["api", "v1", "channels", _, "read"]
+        | ["api", "v1", "attachments"]
+        | ["api", "v1", "schedule"]
+        | ["api", "v1", "reminders", _, "ack" | "skip"]
+        | ["api", "v1", "me", "away" | "back" | "watch" | "unwatch"] => Role::Responder,
+        _ => Role::Admin,
+    }
+}
+
+/// Inbound provider webhooks (`/api/providers/{name}/webhook`), which carry no API token.
+fn is_provider_webhook(path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    matches!(segments.as_slice(), ["api", "providers", _, "webhook"])
+}
+
+/// Break-glass approvals (`/api/v1/messages/{id}/break-glass`), checked against the
+/// `[break_glass]` holders by the handler.
+fn is_break_glass(path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    matches!(
+        segments.as_slice(),
+        ["api", "v1", "messages", _, "break-glass"]
+    )
+}
+
+/// Short fingerprint identifying a static token in logs and usage reports without revealing it.
+fn fingerprint(token: &str) -> String {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    token.hash(&mut hasher);
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+fn extract_token(req: &Request<Body>) -> Option<String> {
+    let headers = req.headers();
     if let Some(auth) = headers.get("Authorization") {
         if let Ok(s) = auth.to_str() {
             if let Some(token) = s.strip_prefix("Bearer ") {
@@ -100,5 +227,9 @@ fn extract_token(headers: &axum::http::HeaderMap) -> Option<String> {
             return Some(s.to_string());
         }
     }
-    None
+    req.uri().query().and_then(|q| {
+        q.split('&')
+            .find_map(|pair| pair.strip_prefix("access_token="))
+            .map(str::to_string)
+    })
 }
@@ -0,0 +1,104 @@
+//! Tower middleware that rejects peers outside the configured IP allowlist or inside the denylist.
+//!
+//! The peer address comes from `ConnectInfo<SocketAddr>`. When it is missing (the router was
+//! not served with connect info) the request is rejected, so a misconfigured embedder fails
+//! closed instead of silently accepting everyone.
+
+use crate::config::IpFilterConfig;
+use ailoop_core::services::logging::log_security_event;
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Tower layer enforcing an [`IpFilterConfig`].
+#[derive(Clone)]
+pub struct IpFilterLayer {
+    filter: Arc<IpFilterConfig>,
+}
+
+impl IpFilterLayer {
+    pub fn new(filter: IpFilterConfig) -> Self {
+        Self {
+            filter: Arc::new(filter),
+        }
+    }
+}
+
+impl<S> Layer<S> for IpFilterLayer {
+    type Service = IpFilterMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        IpFilterMiddleware {
+            inner,
+            filter: Arc::clone(&self.filter),
+        }
+    }
+}
+
+/// Service produced by [`IpFilterLayer`].
+#[derive(Clone)]
+pub struct IpFilterMiddleware<S> {
+    inner: S,
+    filter: Arc<IpFilterConfig>,
+}
+
+impl<S> Service<Request<Body>> for IpFilterMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let filter = Arc::clone(&self.filter);
+        let mut inner = self.inner.clone();
+
+        Box::pin(async move {
+            let peer = req
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip());
+            let reason = match peer {
+                Some(ip) => filter.rejection_reason(ip),
+                None => Some("peer address unavailable"),
+            };
+
+            if let Some(reason) = reason {
+                let peer_label = peer.map(|ip| ip.to_string());
+                tracing::warn!(
+                    "Rejected {} {} from {}: {}",
+                    req.method(),
+                    req.uri().path(),
+                    peer_label.as_deref().unwrap_or("unknown peer"),
+                    reason
+                );
+                log_security_event("ip_rejected", "-", peer_label.as_deref(), reason);
+                return Ok((
+                    StatusCode::FORBIDDEN,
+                    Json(serde_json::json!({"error": "forbidden"})),
+                )
+                    .into_response());
+            }
+
+            inner.call(req).await
+        })
+    }
+}
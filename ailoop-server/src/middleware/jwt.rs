@@ -0,0 +1,184 @@
+//! JWT validation for OIDC single sign-on.
+//!
+//! Signing keys are fetched from the issuer's JWKS endpoint on first use and refetched when a
+//! token references an unknown `kid` (key rotation), at most once per [`JWKS_MIN_REFRESH`].
+//! The algorithm comes from the key, not the token: a token whose `alg` header does not match
+//! its key's `alg` (or, without one, its key type) is rejected.
+
+use crate::config::{AuthIdentity, JwtConfig};
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Minimum interval between JWKS refetches triggered by unknown key ids.
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(60);
+
+/// HTTP timeout for JWKS requests.
+const JWKS_HTTP_TIMEOUT_SECS: u64 = 10;
+
+/// Why a JWT was rejected.
+#[derive(Debug, thiserror::Error)]
+pub enum JwtError {
+    #[error("malformed token: {0}")]
+    Malformed(String),
+    #[error("no signing key for kid {0:?}")]
+    UnknownKey(Option<String>),
+    #[error("token algorithm {0:?} does not match its signing key")]
+    AlgorithmMismatch(Algorithm),
+    #[error("JWKS fetch failed: {0}")]
+    Jwks(String),
+    #[error("invalid token: {0}")]
+    Invalid(#[from] jsonwebtoken::errors::Error),
+    #[error("token grants no role")]
+    NoRole,
+}
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// Validates bearer JWTs against a configured issuer and audience.
+pub struct JwtValidator {
+    config: JwtConfig,
+    client: reqwest::Client,
+    cache: RwLock<Option<CachedJwks>>,
+}
+
+impl JwtValidator {
+    pub fn new(config: JwtConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(JWKS_HTTP_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+        Self {
+            config,
+            client,
+            cache: RwLock::new(None),
+        }
+    }
+
+    /// Validate `token` and resolve the caller's identity and role.
+    pub async fn validate(&self, token: &str) -> Result<AuthIdentity, JwtError> {
+        let header = decode_header(token).map_err(|e| JwtError::Malformed(e.to_string()))?;
+        let (key, algorithms) = self.decoding_key(header.kid.as_deref()).await?;
+        if !algorithms.contains(&header.alg) {
+            return Err(JwtError::AlgorithmMismatch(header.alg));
+        }
+
+        let mut validation = Validation::new(header.alg);
+        validation.algorithms = algorithms;
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let data = decode::<serde_json::Value>(token, &key, &validation)?;
+        let claims = data.claims;
+        let role = self
+            .config
+            .role_from_claim(claims.get(&self.config.role_claim))
+            .ok_or(JwtError::NoRole)?;
+
+        Ok(AuthIdentity {
+            subject: claims
+                .get("sub")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            token_fingerprint: None,
+            role,
+        })
+    }
+
+    async fn decoding_key(
+        &self,
+        kid: Option<&str>,
+    ) -> Result<(DecodingKey, Vec<Algorithm>), JwtError> {
+        if let Some(key) = self.cached_key(kid).await? {
+            return Ok(key);
+        }
+
+        let stale = match self.cache.read().await.as_ref() {
+            Some(cached) => cached.fetched_at.elapsed() >= JWKS_MIN_REFRESH,
+            None => true,
+        };
+        if stale {
+            self.refresh().await?;
+            if let Some(key) = self.cached_key(kid).await? {
+                return Ok(key);
+            }
+        }
+        Err(JwtError::UnknownKey(kid.map(str::to_string)))
+    }
+
+    async fn cached_key(
+        &self,
+        kid: Option<&str>,
+    ) -> Result<Option<(DecodingKey, Vec<Algorithm>)>, JwtError> {
+        let guard = self.cache.read().await;
+        let Some(cached) = guard.as_ref() else {
+            return Ok(None);
+        };
+        let jwk = match kid {
+            Some(kid) => cached.keys.find(kid),
+            // Tokens without `kid` are only accepted when the issuer publishes a single key.
+            None if cached.keys.keys.len() == 1 => cached.keys.keys.first(),
+            None => None,
+        };
+        jwk.map(|k| {
+            DecodingKey::from_jwk(k)
+                .map(|key| (key, key_algorithms(k)))
+                .map_err(JwtError::Invalid)
+        })
+        .transpose()
+    }
+
+    async fn refresh(&self) -> Result<(), JwtError> {
+        let url = self.config.effective_jwks_url();
+        let keys: JwkSet = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| JwtError::Jwks(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| JwtError::Jwks(e.to_string()))?;
+        tracing::debug!("Fetched {} JWKS key(s) from {}", keys.keys.len(), url);
+        *self.cache.write().await = Some(CachedJwks {
+            keys,
+            fetched_at: Instant::now(),
+        });
+        Ok(())
+    }
+}
+
+/// Algorithms a token signed with `jwk` may use: the key's `alg`, or without one, those of its
+/// key type.
+fn key_algorithms(jwk: &Jwk) -> Vec<Algorithm> {
+    if let Some(alg) = jwk.common.key_algorithm {
+        // Encryption algorithms (`RSA-OAEP`, ...) name no signature algorithm and allow nothing.
+        return Algorithm::from_str(&alg.to_string()).into_iter().collect();
+    }
+    match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => vec![
+            Algorithm::RS256,
+            Algorithm::RS384,
+            Algorithm::RS512,
+            Algorithm::PS256,
+            Algorithm::PS384,
+            Algorithm::PS512,
+        ],
+        AlgorithmParameters::EllipticCurve(params) => match params.curve {
+            EllipticCurve::P256 => vec![Algorithm::ES256],
+            EllipticCurve::P384 => vec![Algorithm::ES384],
+            _ => Vec::new(),
+        },
+        AlgorithmParameters::OctetKey(_) => {
+            vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512]
+        }
+        AlgorithmParameters::OctetKeyPair(_) => vec![Algorithm::EdDSA],
+    }
+}
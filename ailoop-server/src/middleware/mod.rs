@@ -1,2 +1,5 @@
 #[cfg(feature = "auth")]
 pub mod auth;
+pub mod ip_filter;
+#[cfg(feature = "auth")]
+pub mod jwt;
@@ -1,12 +1,15 @@
 //! HTTP API server for web clients
 
 use crate::server::core::AppState;
+use crate::server::hooks::HookOutcome;
+use crate::server::loops::{LoopViolation, Origin};
+use crate::server::usage::{ClientUsage, QuotaExceeded, UsageTracker};
 use ailoop_core::models::{DependencyType, Message, Task, TaskState};
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -66,6 +69,18 @@ pub struct StatsResponse {
     pub newest_message: Option<String>,
 }
 
+/// API response for GET /api/stats
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStatsResponse {
+    #[serde(flatten)]
+    pub broadcast: crate::server::broadcast::BroadcastStats,
+    /// Prompt-to-answer latency, timeouts and approvals per channel and responder, and
+    /// compliance per SLA
+    pub responses: ailoop_core::models::ResponseMetrics,
+    /// Background tasks by name, with how often each was restarted after a panic
+    pub tasks: std::collections::BTreeMap<String, crate::server::supervisor::TaskHealth>,
+}
+
 /// Health check response
 #[derive(Debug, Clone, Serialize)]
 pub struct HealthResponse {
@@ -74,6 +89,8 @@ pub struct HealthResponse {
     pub active_connections: usize,
     pub queue_size: usize,
     pub active_channels: usize,
+    /// Channels whose prompts are held back from humans
+    pub held_channels: Vec<String>,
 }
 
 /// Response request for POST /api/v1/messages/:id/response
@@ -81,6 +98,92 @@ pub struct HealthResponse {
 pub struct ResponseRequest {
     pub answer: Option<String>,
     pub response_type: ailoop_core::models::ResponseType,
+    /// File uploaded with `POST /api/v1/attachments` to send with the answer.
+    #[serde(default)]
+    pub attachment_id: Option<Uuid>,
+    /// The answering client's id, checked against the prompt's asker (see `[loop_detection]`).
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// Who answers, when not signed in through SSO; checked against the prompt's claim.
+    #[serde(default)]
+    pub responder: Option<String>,
+}
+
+/// Request body for POST /api/v1/messages/:id/claim and /release
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClaimRequest {
+    /// Who claims, when not signed in through SSO.
+    #[serde(default)]
+    pub responder: Option<String>,
+}
+
+/// Request body for POST /api/v1/messages/:id/read and /api/v1/channels/:channel/read
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReadRequest {
+    /// Who read, when not signed in through SSO.
+    #[serde(default)]
+    pub responder: Option<String>,
+}
+
+/// Request body for POST /api/v1/channels/:channel/incident/start
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct IncidentStartRequest {
+    /// Incident id; `<channel>-<YYYYMMDD-HHMM>` when absent.
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+/// Request body for POST /api/v1/me/away and /back
+#[derive(Debug, Clone, Deserialize)]
+pub struct AwayRequest {
+    /// Who is away, when not signed in through SSO: a `[team]` key or handle.
+    #[serde(default)]
+    pub responder: Option<String>,
+    /// When they are back; until `/back` when absent.
+    #[serde(default)]
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Request body for POST /api/v1/me/watch and /unwatch
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatchRequest {
+    /// Who watches, when not signed in through SSO: a `[team]` key or handle.
+    #[serde(default)]
+    pub responder: Option<String>,
+    /// Keywords to add or remove; `/unwatch` without any removes them all.
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+/// Query parameters for GET /api/v1/me/watch
+#[derive(Debug, Deserialize)]
+struct WatchQuery {
+    responder: Option<String>,
+}
+
+/// Request body for POST /api/v1/messages/:id/break-glass
+#[derive(Debug, Clone, Deserialize)]
+pub struct BreakGlassRequest {
+    /// Why the authorization is approved without its usual responders.
+    pub justification: String,
+}
+
+/// Request body for POST /api/v1/messages/:id/typing
+#[derive(Debug, Clone, Deserialize)]
+pub struct TypingRequest {
+    /// The unsent answer so far; empty when it was cleared.
+    #[serde(default)]
+    pub text: String,
+}
+
+/// Query parameters for POST /api/v1/attachments
+#[derive(Debug, Deserialize)]
+struct AttachmentQuery {
+    name: String,
 }
 
 /// Request body for creating a task
@@ -115,12 +218,60 @@ pub struct TasksResponse {
     pub total_count: usize,
 }
 
+/// Response for GET /api/usage
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageResponse {
+    pub max_messages_per_hour: Option<u32>,
+    pub max_prompts_per_hour: Option<u32>,
+    pub clients: Vec<ClientUsage>,
+}
+
 /// Query parameters for GET /api/v1/pending
 #[derive(Debug, Deserialize)]
 struct PendingQuery {
     channel: Option<String>,
 }
 
+/// Query parameters for GET /api/v1/unread
+#[derive(Debug, Deserialize)]
+struct UnreadQuery {
+    responder: Option<String>,
+}
+
+/// Query parameters for GET /api/stats
+#[derive(Debug, Deserialize)]
+struct ServerStatsQuery {
+    /// Only count prompts asked or settled from this time on
+    since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Query parameters for GET /api/logs
+#[derive(Debug, Deserialize)]
+struct LogsQuery {
+    /// Only events stamped after this time
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    /// Only events of this type (`message`, `prompt`, `delivery`, `break_glass`)
+    event: Option<String>,
+    /// Return at most this many of the newest events (default 1000)
+    limit: Option<usize>,
+}
+
+/// Events returned by GET /api/logs when no `limit` is given.
+const DEFAULT_LOGS_LIMIT: usize = 1000;
+
+/// Query parameters for GET /api/v1/schedule and GET /api/v1/reminders
+#[derive(Debug, Deserialize)]
+struct ScheduleQuery {
+    channel: Option<String>,
+}
+
+/// Query parameters for POST /api/v1/reminders/:id/skip
+#[derive(Debug, Deserialize)]
+struct SkipQuery {
+    /// Occurrences to skip (default 1)
+    count: Option<u32>,
+}
+
 /// Per-item shape in the pending list response
 #[derive(Debug, Clone, Serialize)]
 pub struct PendingItemResponse {
@@ -129,6 +280,14 @@ pub struct PendingItemResponse {
     pub channel: String,
     pub position: usize,
     pub label: String,
+    /// Per-provider delivery results; empty when no provider is configured.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub delivery: Vec<crate::server::providers::DeliveryReport>,
+    /// Responder who claimed the prompt; others leave it to them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claimed_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claimed_since: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 /// Top-level response for GET /api/v1/pending
@@ -136,6 +295,9 @@ pub struct PendingItemResponse {
 pub struct PendingListResponse {
     pub items: Vec<PendingItemResponse>,
     pub total_count: usize,
+    /// Held channels; their prompts stay queued and are not listed in `items`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub held_channels: Vec<ailoop_core::HeldChannelResponse>,
 }
 
 /// Query parameters for message history
@@ -143,6 +305,17 @@ pub struct PendingListResponse {
 struct MessagesQuery {
     limit: Option<usize>,
     _offset: Option<usize>,
+    /// Only messages sent at or after this time.
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    /// Only messages tagged with this incident id.
+    incident: Option<String>,
+}
+
+/// Query parameters for GET /api/sessions/:id/transcript
+#[derive(Debug, Deserialize)]
+struct TranscriptQuery {
+    /// `md` (default) or `json`
+    format: Option<String>,
 }
 
 /// Query parameters for task requests
@@ -177,8 +350,75 @@ pub(crate) fn create_api_router() -> axum::Router<AppState> {
             axum::routing::get(handle_get_channel_stats),
         )
         .route("/api/stats", axum::routing::get(handle_get_stats))
+        .route("/api/usage", axum::routing::get(handle_get_usage))
+        .route("/api/logs", axum::routing::get(handle_get_logs))
+        .route(
+            "/api/capabilities",
+            axum::routing::get(handle_get_capabilities),
+        )
+        .route(
+            "/api/sessions/{id}/transcript",
+            axum::routing::get(handle_get_session_transcript),
+        )
+        .route(
+            "/api/providers/telegram/webhook",
+            axum::routing::post(handle_telegram_webhook),
+        )
+        .route(
+            "/api/providers/twilio/webhook",
+            axum::routing::post(handle_twilio_webhook),
+        )
         .route("/api/v1/health", axum::routing::get(handle_get_health))
+        .route("/api/v1/team", axum::routing::get(handle_get_team))
+        .route(
+            "/api/v1/team/{name}",
+            axum::routing::get(handle_get_team_member),
+        )
+        .route("/api/v1/me/away", axum::routing::post(handle_post_away))
+        .route("/api/v1/me/back", axum::routing::post(handle_post_back))
+        .route(
+            "/api/v1/me/watch",
+            axum::routing::get(handle_get_watch).post(handle_post_watch),
+        )
+        .route(
+            "/api/v1/me/unwatch",
+            axum::routing::post(handle_post_unwatch),
+        )
+        .route("/api/v1/unread", axum::routing::get(handle_get_unread))
         .route("/api/v1/pending", axum::routing::get(handle_get_pending))
+        .route("/api/queues", axum::routing::get(handle_get_queues))
+        .route(
+            "/api/queues/messages/{id}",
+            axum::routing::delete(handle_drop_queued_message),
+        )
+        .route(
+            "/api/queues/{channel}",
+            axum::routing::delete(handle_clear_queue),
+        )
+        .route(
+            "/api/v1/channels/{channel}/read",
+            axum::routing::post(handle_post_channel_read),
+        )
+        .route(
+            "/api/v1/channels/{channel}/pause",
+            axum::routing::post(handle_pause_channel),
+        )
+        .route(
+            "/api/v1/channels/{channel}/resume",
+            axum::routing::post(handle_resume_channel),
+        )
+        .route(
+            "/api/v1/channels/{channel}/incident/start",
+            axum::routing::post(handle_start_incident),
+        )
+        .route(
+            "/api/v1/channels/{channel}/incident/stop",
+            axum::routing::post(handle_stop_incident),
+        )
+        .route(
+            "/api/v1/incidents",
+            axum::routing::get(handle_get_incidents),
+        )
         .route(
             "/api/v1/messages",
             axum::routing::post(handle_post_messages),
@@ -187,10 +427,86 @@ pub(crate) fn create_api_router() -> axum::Router<AppState> {
             "/api/v1/messages/{id}",
             axum::routing::get(handle_get_message),
         )
+        .route(
+            "/api/v1/schedule",
+            axum::routing::post(handle_post_schedule).get(handle_get_schedule),
+        )
+        .route(
+            "/api/v1/schedule/{id}",
+            axum::routing::delete(handle_delete_schedule),
+        )
+        .route(
+            "/api/v1/reminders",
+            axum::routing::post(handle_post_reminder).get(handle_get_reminders),
+        )
+        .route(
+            "/api/v1/reminders/{id}",
+            axum::routing::delete(handle_delete_reminder),
+        )
+        .route(
+            "/api/v1/reminders/{id}/skip",
+            axum::routing::post(handle_skip_reminder),
+        )
+        .route(
+            "/api/v1/reminders/{id}/ack",
+            axum::routing::post(handle_ack_reminder),
+        )
+        .route(
+            "/api/v1/history/purge",
+            axum::routing::post(handle_purge_history),
+        )
+        .route(
+            "/api/v1/privacy/export",
+            axum::routing::post(handle_privacy_export),
+        )
+        .route(
+            "/api/v1/privacy/erase",
+            axum::routing::post(handle_privacy_erase),
+        )
+        .route(
+            "/api/v1/messages/{id}/thread",
+            axum::routing::get(handle_get_thread),
+        )
+        .route(
+            "/api/v1/conversations/{id}",
+            axum::routing::get(handle_get_conversation),
+        )
         .route(
             "/api/v1/messages/{id}/response",
             axum::routing::post(handle_post_response),
         )
+        .route(
+            "/api/v1/messages/{id}/typing",
+            axum::routing::post(handle_post_typing),
+        )
+        .route(
+            "/api/v1/messages/{id}/claim",
+            axum::routing::post(handle_post_claim),
+        )
+        .route(
+            "/api/v1/messages/{id}/release",
+            axum::routing::post(handle_post_release),
+        )
+        .route(
+            "/api/v1/messages/{id}/read",
+            axum::routing::post(handle_post_read),
+        )
+        .route(
+            "/api/v1/messages/{id}/break-glass",
+            axum::routing::post(handle_post_break_glass),
+        )
+        .route(
+            "/api/v1/attachments",
+            axum::routing::post(handle_post_attachment).layer(
+                axum::extract::DefaultBodyLimit::max(
+                    crate::server::attachments::MAX_ATTACHMENT_BYTES,
+                ),
+            ),
+        )
+        .route(
+            "/api/v1/attachments/{id}",
+            axum::routing::get(handle_get_attachment),
+        )
         .route(
             "/api/v1/tasks",
             axum::routing::post(handle_post_tasks).get(handle_get_tasks),
@@ -223,6 +539,168 @@ pub(crate) fn create_api_router() -> axum::Router<AppState> {
         )
 }
 
+/// Handle POST /api/providers/telegram/webhook
+///
+/// Authenticated by the `X-Telegram-Bot-Api-Secret-Token` header registered via `setWebhook`,
+/// not by API tokens. Returns 404 unless the Telegram provider runs in webhook mode.
+async fn handle_telegram_webhook(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(update): Json<serde_json::Value>,
+) -> Response {
+    #[cfg(feature = "telegram")]
+    {
+        use crate::server::providers::WebhookRejection;
+
+        let secret = headers
+            .get("X-Telegram-Bot-Api-Secret-Token")
+            .and_then(|v| v.to_str().ok());
+        match state.telegram_webhook.accept(secret, update) {
+            Ok(()) => StatusCode::OK.into_response(),
+            Err(WebhookRejection::Disabled) => StatusCode::NOT_FOUND.into_response(),
+            Err(WebhookRejection::BadSecret) => {
+                ailoop_core::services::logging::log_security_event(
+                    "telegram_webhook_rejected",
+                    "-",
+                    None,
+                    "bad secret token",
+                );
+                (
+                    StatusCode::UNAUTHORIZED,
+                    Json(serde_json::json!({"error": "unauthorized"})),
+                )
+                    .into_response()
+            }
+            Err(WebhookRejection::Busy) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        }
+    }
+    #[cfg(not(feature = "telegram"))]
+    {
+        let _ = (state, headers, update);
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+/// Handle POST /api/providers/twilio/webhook
+///
+/// Authenticated by `X-Twilio-Signature`, not by API tokens. Answers the matched prompt and
+/// replies with TwiML; returns 404 unless the Twilio provider is running.
+async fn handle_twilio_webhook(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    axum::Form(params): axum::Form<Vec<(String, String)>>,
+) -> Response {
+    #[cfg(feature = "twilio")]
+    {
+        use crate::server::providers::TwilioRejection;
+
+        let Some(sink) = state.twilio.get() else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        let signature = headers
+            .get("X-Twilio-Signature")
+            .and_then(|v| v.to_str().ok());
+        if !sink.verify_signature(signature, &params) {
+            ailoop_core::services::logging::log_security_event(
+                "twilio_webhook_rejected",
+                "-",
+                None,
+                "bad signature",
+            );
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({"error": "unauthorized"})),
+            )
+                .into_response();
+        }
+
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str())
+                .unwrap_or_default()
+        };
+        let from = param("From");
+        let pending: Vec<Uuid> = state
+            .pending_prompt_registry
+            .snapshot_pending(None)
+            .await
+            .into_iter()
+            .map(|p| p.message_id)
+            .collect();
+        let text = match sink.parse_reply(from, param("Body"), &pending) {
+            Ok(reply) => {
+                let unverified = if reply.response_type
+                    == ailoop_core::models::ResponseType::AuthorizationApproved
+                {
+                    state
+                        .pending_prompt_registry
+                        .needs_terminal_approval(reply.message_id)
+                        .await
+                } else {
+                    None
+                };
+                let matched = state
+                    .pending_prompt_registry
+                    .submit_reply_for_message_as(
+                        reply.message_id,
+                        Some(reply.answer),
+                        reply.response_type,
+                        Some(reply.responder),
+                    )
+                    .await;
+                if matched {
+                    None
+                } else if let Some(level) = unverified {
+                    Some(format!(
+                        "{}-risk approvals must be given at the server terminal",
+                        level
+                    ))
+                } else {
+                    Some("That prompt is no longer open".to_string())
+                }
+            }
+            Err(TwilioRejection::UnknownSender) => {
+                ailoop_core::services::logging::log_security_event(
+                    "twilio_reply_rejected",
+                    "-",
+                    Some(from),
+                    "sender not in providers.twilio.to",
+                );
+                None
+            }
+            Err(rejection) => Some(rejection.to_string()),
+        };
+        twiml(text.as_deref())
+    }
+    #[cfg(not(feature = "twilio"))]
+    {
+        let _ = (state, headers, params);
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+/// TwiML response, optionally replying to the sender with `text`.
+#[cfg(feature = "twilio")]
+fn twiml(text: Option<&str>) -> Response {
+    let body = match text {
+        Some(text) => {
+            let escaped = text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            format!("<Response><Message>{}</Message></Response>", escaped)
+        }
+        None => "<Response/>".to_string(),
+    };
+    (
+        [(axum::http::header::CONTENT_TYPE, "text/xml")],
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>{}", body),
+    )
+        .into_response()
+}
+
 /// Handle POST /api/test
 async fn handle_post_test() -> Json<serde_json::Value> {
     Json(serde_json::json!({"test": "ok"}))
@@ -258,10 +736,23 @@ async fn handle_get_channel_messages(
     Query(query): Query<MessagesQuery>,
 ) -> Result<Json<MessagesResponse>, ApiError> {
     let limit = query.limit.unwrap_or(100);
-    let messages = state
-        .message_history
-        .get_messages(&channel, Some(limit))
-        .await;
+    let messages = match (query.since, query.incident.as_deref()) {
+        (None, None) => {
+            state
+                .message_history
+                .get_messages(&channel, Some(limit))
+                .await
+        }
+        (since, incident) => {
+            let mut messages = state.message_history.get_messages(&channel, None).await;
+            messages.retain(|m| {
+                since.is_none_or(|since| m.timestamp >= since)
+                    && incident.is_none_or(|id| m.incident() == Some(id))
+            });
+            let skip = messages.len().saturating_sub(limit);
+            messages.split_off(skip)
+        }
+    };
 
     let message_values: Vec<serde_json::Value> = messages
         .into_iter()
@@ -293,8 +784,235 @@ async fn handle_get_channel_stats(
 /// Handle GET /api/stats
 async fn handle_get_stats(
     State(state): State<AppState>,
-) -> Result<Json<crate::server::broadcast::BroadcastStats>, ApiError> {
-    Ok(Json(state.broadcast_manager.get_stats().await))
+    Query(query): Query<ServerStatsQuery>,
+) -> Result<Json<ServerStatsResponse>, ApiError> {
+    let mut responses = state.broadcast_manager.responses().metrics(query.since);
+    responses.slas = state.broadcast_manager.slas().summaries(query.since);
+    Ok(Json(ServerStatsResponse {
+        broadcast: state.broadcast_manager.get_stats().await,
+        responses,
+        tasks: state.supervisor.snapshot(),
+    }))
+}
+
+/// Handle GET /api/logs: the event log, for admins tailing it remotely
+async fn handle_get_logs(Query(query): Query<LogsQuery>) -> Response {
+    let Some(log) = crate::server::events::installed() else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "event log is disabled"})),
+        )
+            .into_response();
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_LOGS_LIMIT).max(1);
+    let events = match tokio::task::spawn_blocking(move || {
+        log.read_since(query.since, query.event.as_deref(), limit)
+    })
+    .await
+    {
+        Ok(events) => events,
+        Err(e) => return ApiError::InternalError(e.to_string()).into_response(),
+    };
+    let next_since = events
+        .last()
+        .and_then(|e| e["ts"].as_str())
+        .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+        .map(|ts| ts.with_timezone(&chrono::Utc))
+        .or(query.since);
+    Json(ailoop_core::EventLogPage { events, next_since }).into_response()
+}
+
+/// Handle GET /api/capabilities
+async fn handle_get_capabilities(
+    State(state): State<AppState>,
+) -> Json<ailoop_core::models::Capabilities> {
+    Json(state.broadcast_manager.capabilities().await)
+}
+
+/// A team directory entry as returned by `/api/v1/team`.
+#[derive(Debug, Serialize)]
+struct TeamMemberResponse {
+    /// `@name`, as used in `metadata.member`.
+    member: String,
+    #[serde(flatten)]
+    details: ailoop_core::models::TeamMember,
+    /// Set while the member is away.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    away: Option<ailoop_core::models::AwayStatus>,
+}
+
+fn team_directory(state: &AppState) -> ailoop_core::models::TeamDirectory {
+    state
+        .provider_config
+        .as_ref()
+        .map(|c| c.team.clone())
+        .unwrap_or_default()
+}
+
+/// Handle GET /api/v1/team
+async fn handle_get_team(State(state): State<AppState>) -> Json<Vec<TeamMemberResponse>> {
+    let team = team_directory(&state);
+    let away = state.broadcast_manager.away().list(chrono::Utc::now());
+    Json(
+        team.members()
+            .map(|(key, member)| TeamMemberResponse {
+                member: format!("@{}", key),
+                details: member.clone(),
+                away: away.get(key).cloned(),
+            })
+            .collect(),
+    )
+}
+
+/// Handle GET /api/v1/team/:name (`alice` or `@alice`)
+async fn handle_get_team_member(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<TeamMemberResponse>, ApiError> {
+    let team = team_directory(&state);
+    let member = team.get(&name).ok_or(ApiError::NotFound)?;
+    let key = name.trim().trim_start_matches('@');
+    Ok(Json(TeamMemberResponse {
+        member: format!("@{}", key),
+        details: member.clone(),
+        away: state
+            .broadcast_manager
+            .away()
+            .status(key, chrono::Utc::now()),
+    }))
+}
+
+/// Handle POST /api/v1/me/away: mark the caller away until `until`, or until they are back.
+/// The caller is the SSO subject, else `responder` from the body, and must be in `[team]`.
+async fn handle_post_away(
+    State(state): State<AppState>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Json(request): Json<AwayRequest>,
+) -> Result<Json<ailoop_core::AwayResponse>, ApiError> {
+    let now = chrono::Utc::now();
+    if request.until.is_some_and(|until| until <= now) {
+        return Err(ApiError::ValidationError(
+            "until must be in the future".to_string(),
+        ));
+    }
+    let member = me_member(&state, identity, request.responder)?;
+    let status = ailoop_core::models::AwayStatus {
+        since: now,
+        until: request.until,
+        note: request
+            .note
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty()),
+    };
+    state
+        .broadcast_manager
+        .away()
+        .set(&member, status.clone())
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    tracing::info!(member = %member, until = ?status.until, "responder is away");
+    Ok(Json(ailoop_core::AwayResponse {
+        member: format!("@{}", member),
+        away: Some(status),
+    }))
+}
+
+/// Handle POST /api/v1/me/back: end the caller's away status.
+async fn handle_post_back(
+    State(state): State<AppState>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Json(request): Json<AwayRequest>,
+) -> Result<Json<ailoop_core::AwayResponse>, ApiError> {
+    let member = me_member(&state, identity, request.responder)?;
+    if state
+        .broadcast_manager
+        .away()
+        .clear(&member, chrono::Utc::now())
+        .map_err(|e| ApiError::InternalError(e.to_string()))?
+        .is_some()
+    {
+        tracing::info!(member = %member, "responder is back");
+    }
+    Ok(Json(ailoop_core::AwayResponse {
+        member: format!("@{}", member),
+        away: None,
+    }))
+}
+
+/// Handle GET /api/v1/me/watch: the keywords the caller (the SSO subject, else
+/// `?responder=`) watches.
+async fn handle_get_watch(
+    State(state): State<AppState>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Query(query): Query<WatchQuery>,
+) -> Result<Json<ailoop_core::WatchResponse>, ApiError> {
+    let member = me_member(&state, identity, query.responder)?;
+    let keywords = state.broadcast_manager.subscriptions().keywords(&member);
+    Ok(Json(ailoop_core::WatchResponse {
+        member: format!("@{}", member),
+        keywords,
+    }))
+}
+
+/// Handle POST /api/v1/me/watch: alert the caller when a message contains one of `keywords`.
+async fn handle_post_watch(
+    State(state): State<AppState>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Json(request): Json<WatchRequest>,
+) -> Result<Json<ailoop_core::WatchResponse>, ApiError> {
+    if request.keywords.iter().all(|k| k.trim().is_empty()) {
+        return Err(ApiError::ValidationError(
+            "keywords is required".to_string(),
+        ));
+    }
+    let member = me_member(&state, identity, request.responder)?;
+    let keywords = state
+        .broadcast_manager
+        .subscriptions()
+        .watch(&member, &request.keywords)
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    tracing::info!(member = %member, keywords = ?keywords, "responder watches keywords");
+    Ok(Json(ailoop_core::WatchResponse {
+        member: format!("@{}", member),
+        keywords,
+    }))
+}
+
+/// Handle POST /api/v1/me/unwatch: stop watching `keywords`, or every keyword when empty.
+async fn handle_post_unwatch(
+    State(state): State<AppState>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Json(request): Json<WatchRequest>,
+) -> Result<Json<ailoop_core::WatchResponse>, ApiError> {
+    let member = me_member(&state, identity, request.responder)?;
+    let keywords = state
+        .broadcast_manager
+        .subscriptions()
+        .unwatch(&member, &request.keywords)
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    Ok(Json(ailoop_core::WatchResponse {
+        member: format!("@{}", member),
+        keywords,
+    }))
+}
+
+/// Team member key of the caller of `/api/v1/me/*`.
+fn me_member(
+    state: &AppState,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    responder: Option<String>,
+) -> Result<String, ApiError> {
+    let responder = identity
+        .and_then(|Extension(id)| id.subject)
+        .or(responder)
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .ok_or_else(|| ApiError::ValidationError("responder is required".to_string()))?;
+    team_directory(state)
+        .resolve(&responder)
+        .map(str::to_string)
+        .ok_or_else(|| {
+            ApiError::ValidationError(format!("'{}' is not in the team directory", responder))
+        })
 }
 
 /// Handle GET /api/v1/health
@@ -314,6 +1032,12 @@ async fn handle_get_health(
         active_connections: broadcast_stats.total_viewers,
         queue_size,
         active_channels: broadcast_stats.active_channels,
+        held_channels: state
+            .channel_manager
+            .get_held_channels()
+            .into_iter()
+            .map(|(channel, _)| channel)
+            .collect(),
     }))
 }
 
@@ -346,50 +1070,732 @@ async fn handle_get_pending(
                 channel: s.channel,
                 position: s.position + 1,
                 label: s.label,
+                delivery: state.broadcast_manager.delivery_status(&s.message_id),
+                claimed_by: s.claim.as_ref().map(|c| c.responder.clone()),
+                claimed_since: s.claim.map(|c| c.since),
             }
         })
         .collect();
 
+    let held_channels = state
+        .channel_manager
+        .get_held_channels()
+        .into_iter()
+        .filter(|(channel, _)| query.channel.as_deref().is_none_or(|ch| ch == channel))
+        .map(|(channel, held_since)| ailoop_core::HeldChannelResponse {
+            queued: state.channel_manager.get_queue_size(&channel),
+            channel,
+            held_since,
+        })
+        .collect();
+
     let total_count = items.len();
-    Ok(Json(PendingListResponse { items, total_count }))
+    Ok(Json(PendingListResponse {
+        items,
+        total_count,
+        held_channels,
+    }))
 }
 
-/// Handle POST /api/v1/messages
-async fn handle_post_messages(
+/// Handle GET /api/queues: messages waiting on each channel to be put to a human
+async fn handle_get_queues(
     State(state): State<AppState>,
-    Json(message): Json<Message>,
-) -> Result<Response, ApiError> {
-    if state
-        .is_shutting_down
-        .load(std::sync::atomic::Ordering::Relaxed)
-    {
-        return Ok((
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({"error": "server shutting down"})),
-        )
-            .into_response());
+    Query(query): Query<PendingQuery>,
+) -> Result<Json<ailoop_core::QueueListResponse>, ApiError> {
+    if let Some(ref ch) = query.channel {
+        ailoop_core::channel::validation::validate_channel_name(ch)
+            .map_err(|e| ApiError::ValidationError(e.to_string()))?;
     }
+    let now = chrono::Utc::now();
+    let channels: Vec<_> = state
+        .channel_manager
+        .get_queued_messages(query.channel.as_deref())
+        .into_iter()
+        .map(|(channel, messages)| ailoop_core::ChannelQueueResponse {
+            held_since: state.channel_manager.held_since(&channel),
+            messages: messages.iter().map(|m| queued_message(m, now)).collect(),
+            channel,
+        })
+        .collect();
+    let total_count = channels.iter().map(|c| c.messages.len()).sum();
+    Ok(Json(ailoop_core::QueueListResponse {
+        channels,
+        total_count,
+    }))
+}
 
-    ailoop_core::channel::validation::validate_channel_name(&message.channel)
-        .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+/// Handle DELETE /api/queues/messages/:id
+async fn handle_drop_queued_message(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ailoop_core::QueueDropResponse>, ApiError> {
+    let message = state
+        .channel_manager
+        .remove_queued_message(&id)
+        .ok_or(ApiError::NotFound)?;
+    Ok(Json(drop_queued(&state, vec![message]).await))
+}
 
-    state
-        .message_history
-        .add_message(&message.channel, message.clone())
-        .await;
+/// Handle DELETE /api/queues/:channel
+async fn handle_clear_queue(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+) -> Result<Json<ailoop_core::QueueDropResponse>, ApiError> {
+    ailoop_core::channel::validation::validate_channel_name(&channel)
+        .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+    let messages = state.channel_manager.clear_queue(&channel);
+    Ok(Json(drop_queued(&state, messages).await))
+}
 
-    state.broadcast_manager.broadcast_message(&message).await;
+/// Answer the prompts among `messages`, just removed from their queues, with `cancelled`
+/// so agents waiting on them unblock.
+async fn drop_queued(state: &AppState, messages: Vec<Message>) -> ailoop_core::QueueDropResponse {
+    let now = chrono::Utc::now();
+    let dropped = messages.iter().map(|m| queued_message(m, now)).collect();
+    for message in messages {
+        tracing::info!(channel = %message.channel, id = %message.id, "Dropped queued message");
+        if message.prompt_text().is_none() {
+            continue;
+        }
+        crate::server::events::prompt(message.id, &message.channel, "dropped");
+        let mut reply = Message::response(
+            message.channel.clone(),
+            ailoop_core::models::MessageContent::Response {
+                answer: Some("Dropped from the server's queue".to_string()),
+                response_type: ailoop_core::models::ResponseType::Cancelled,
+            },
+            message.id,
+        );
+        reply.metadata = Some(serde_json::json!({ "error": "dropped" }));
+        state
+            .message_history
+            .add_message(&reply.channel, reply.clone())
+            .await;
+        state.broadcast_manager.broadcast_message(&reply).await;
+    }
+    ailoop_core::QueueDropResponse { dropped }
+}
 
-    Ok((StatusCode::CREATED, Json(message)).into_response())
+/// How a queued message is listed: its content type, and the question, text or URL.
+fn queued_message(
+    message: &Message,
+    now: chrono::DateTime<chrono::Utc>,
+) -> ailoop_core::QueuedMessageResponse {
+    let kind = serde_json::to_value(&message.content)
+        .ok()
+        .and_then(|c| c["type"].as_str().map(str::to_string))
+        .unwrap_or_default();
+    let label = match &message.content {
+        ailoop_core::models::MessageContent::Notification { text, .. } => text.clone(),
+        _ => message.prompt_text().unwrap_or_default().to_string(),
+    };
+    ailoop_core::QueuedMessageResponse {
+        id: message.id,
+        kind,
+        channel: message.channel.clone(),
+        label,
+        queued_at: message.timestamp,
+        age_seconds: (now - message.timestamp).num_seconds().max(0),
+    }
 }
 
-/// Handle GET /api/v1/messages/:id
-async fn handle_get_message(
+/// Handle POST /api/v1/channels/:channel/pause
+async fn handle_pause_channel(
     State(state): State<AppState>,
-    Path(message_id): Path<Uuid>,
+    Path(channel): Path<String>,
+) -> Result<Json<ailoop_core::ChannelHoldResponse>, ApiError> {
+    set_channel_hold(&state, channel, true).await
+}
+
+/// Handle POST /api/v1/channels/:channel/resume
+async fn handle_resume_channel(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+) -> Result<Json<ailoop_core::ChannelHoldResponse>, ApiError> {
+    set_channel_hold(&state, channel, false).await
+}
+
+/// Hold or release prompt delivery on `channel`. Agents on the channel are told through a
+/// system notification; humans are not.
+async fn set_channel_hold(
+    state: &AppState,
+    channel: String,
+    held: bool,
+) -> Result<Json<ailoop_core::ChannelHoldResponse>, ApiError> {
+    ailoop_core::channel::validation::validate_channel_name(&channel)
+        .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+
+    let changed = if held {
+        state.channel_manager.hold(&channel)
+    } else {
+        state.channel_manager.release(&channel)
+    };
+    let queued = state.channel_manager.get_queue_size(&channel);
+    if changed {
+        let text = if held {
+            format!(
+                "Channel '{}' is on hold: prompts are queued and not shown to humans until it is resumed",
+                channel
+            )
+        } else {
+            format!(
+                "Channel '{}' resumed: {} queued message(s) are being delivered",
+                channel, queued
+            )
+        };
+        tracing::info!(channel = %channel, held, "{}", text);
+        let notice = Message::new(
+            channel.clone(),
+            ailoop_core::models::SenderType::System,
+            ailoop_core::models::MessageContent::Notification {
+                text,
+                priority: ailoop_core::models::NotificationPriority::Normal,
+            },
+        );
+        state
+            .message_history
+            .add_message(&channel, notice.clone())
+            .await;
+        state
+            .broadcast_manager
+            .broadcast_to_viewers_only(&notice)
+            .await;
+    }
+
+    Ok(Json(ailoop_core::ChannelHoldResponse {
+        held_since: state.channel_manager.held_since(&channel),
+        channel,
+        held,
+        queued,
+        changed,
+    }))
+}
+
+/// Handle POST /api/v1/channels/:channel/incident/start
+async fn handle_start_incident(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    Json(request): Json<IncidentStartRequest>,
+) -> Result<Json<ailoop_core::IncidentResponse>, ApiError> {
+    ailoop_core::channel::validation::validate_channel_name(&channel)
+        .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+    let id = request
+        .id
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty());
+    if let Some(id) = &id {
+        if id.len() > 64 || id.contains(char::is_whitespace) {
+            return Err(ApiError::ValidationError(
+                "Incident id must be at most 64 characters without spaces".to_string(),
+            ));
+        }
+    }
+    let title = request
+        .title
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    let incident = ailoop_core::models::Incident::new(&channel, id, title, chrono::Utc::now());
+    let (incident, changed) = state
+        .message_history
+        .incidents()
+        .start(&channel, incident)
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    if changed {
+        announce_incident(&state, &channel, &incident, true).await;
+    }
+    Ok(Json(ailoop_core::IncidentResponse {
+        channel,
+        incident: Some(incident),
+        changed,
+    }))
+}
+
+/// Handle POST /api/v1/channels/:channel/incident/stop
+async fn handle_stop_incident(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+) -> Result<Json<ailoop_core::IncidentResponse>, ApiError> {
+    ailoop_core::channel::validation::validate_channel_name(&channel)
+        .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+    let incident = state
+        .message_history
+        .incidents()
+        .stop(&channel)
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    if let Some(incident) = &incident {
+        announce_incident(&state, &channel, incident, false).await;
+    }
+    Ok(Json(ailoop_core::IncidentResponse {
+        channel,
+        changed: incident.is_some(),
+        incident,
+    }))
+}
+
+/// Handle GET /api/v1/incidents
+async fn handle_get_incidents(
+    State(state): State<AppState>,
+) -> Json<std::collections::BTreeMap<String, ailoop_core::models::Incident>> {
+    Json(state.message_history.incidents().list())
+}
+
+/// Tell everyone on `channel`, humans included, that `incident` started or ended.
+async fn announce_incident(
+    state: &AppState,
+    channel: &str,
+    incident: &ailoop_core::models::Incident,
+    started: bool,
+) {
+    tracing::info!(channel = %channel, incident = %incident.id, started, "Incident mode changed");
+    let notice = crate::server::incidents::notice(channel, incident, started, chrono::Utc::now());
+    state
+        .message_history
+        .add_message(channel, notice.clone())
+        .await;
+    state.broadcast_manager.broadcast_message(&notice).await;
+}
+
+/// Handle POST /api/v1/messages
+async fn handle_post_messages(
+    State(state): State<AppState>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Json(raw): Json<serde_json::Value>,
+) -> Result<Response, ApiError> {
+    if state
+        .is_shutting_down
+        .load(std::sync::atomic::Ordering::Relaxed)
+    {
+        return Ok((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({"error": "server shutting down"})),
+        )
+            .into_response());
+    }
+
+    let mut message: Message = if state.strict {
+        match ailoop_core::models::strict::parse_strict(raw) {
+            Ok(message) => message,
+            Err(errors) => {
+                return Ok((
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({"error": "invalid message", "details": errors})),
+                )
+                    .into_response())
+            }
+        }
+    } else {
+        serde_json::from_value(raw).map_err(|e| ApiError::ValidationError(e.to_string()))?
+    };
+
+    ailoop_core::channel::validation::validate_channel_name(&message.channel)
+        .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+    state.message_history.incidents().apply(&mut message);
+
+    // Held until the client's earlier numbered messages are handled, so they are recorded in
+    // the order it produced them.
+    let _turn = match message.sequence {
+        Some(sequence) => Some(state.sequencer.wait_turn(sequence).await),
+        None => None,
+    };
+
+    let subject = identity
+        .as_ref()
+        .and_then(|Extension(id)| id.subject.clone());
+    let auth_key = identity.and_then(|Extension(id)| id.usage_key());
+    let client = UsageTracker::client_key(auth_key.as_deref(), &message);
+    if let Err(exceeded) = state.usage.try_record(&client, &message).await {
+        return Ok(quota_exceeded_response(&exceeded));
+    }
+    let channel = message.channel.clone();
+    let origin = Origin::new(None, subject.as_deref(), Some(&message));
+    let message = match state.hooks.inbound(message).await {
+        HookOutcome::Pass(message) => *message,
+        HookOutcome::Blocked(reason) => {
+            ailoop_core::services::logging::log_security_event(
+                "message_blocked",
+                &channel,
+                auth_key.as_deref(),
+                &reason,
+            );
+            return Ok(blocked_response(&reason));
+        }
+    };
+
+    if message.is_status() {
+        crate::server::core::AiloopServer::show_status(
+            &state.channel_manager,
+            &state.broadcast_manager,
+            message.clone(),
+        );
+        return Ok((StatusCode::ACCEPTED, Json(message)).into_response());
+    }
+
+    if matches!(
+        message.content,
+        ailoop_core::models::MessageContent::Decision { .. }
+            | ailoop_core::models::MessageContent::Authorization { .. }
+            | ailoop_core::models::MessageContent::Navigate { .. }
+    ) {
+        state.loops.record_prompt(message.id, origin).await;
+    }
+
+    state
+        .message_history
+        .add_message(&message.channel, message.clone())
+        .await;
+
+    // Held channels still reach viewers, but providers are not pinged.
+    if state.channel_manager.held_since(&message.channel).is_some() {
+        state
+            .broadcast_manager
+            .broadcast_to_viewers_only(&message)
+            .await;
+    } else {
+        state.broadcast_manager.broadcast_message(&message).await;
+    }
+
+    Ok((StatusCode::CREATED, Json(message)).into_response())
+}
+
+/// Handle POST /api/v1/schedule
+///
+/// Quotas and the inbound hook apply now; the message is delivered once `at` is reached.
+async fn handle_post_schedule(
+    State(state): State<AppState>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Json(request): Json<ailoop_core::models::ScheduleRequest>,
+) -> Result<Response, ApiError> {
+    let message = request.message;
+    ailoop_core::channel::validation::validate_channel_name(&message.channel)
+        .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+    if message.is_status() {
+        return Err(ApiError::ValidationError(
+            "status pulses cannot be scheduled".to_string(),
+        ));
+    }
+
+    let auth_key = identity.and_then(|Extension(id)| id.usage_key());
+    let client = UsageTracker::client_key(auth_key.as_deref(), &message);
+    if let Err(exceeded) = state.usage.try_record(&client, &message).await {
+        return Ok(quota_exceeded_response(&exceeded));
+    }
+    let channel = message.channel.clone();
+    let message = match state.hooks.inbound(message).await {
+        HookOutcome::Pass(message) => *message,
+        HookOutcome::Blocked(reason) => {
+            ailoop_core::services::logging::log_security_event(
+                "message_blocked",
+                &channel,
+                auth_key.as_deref(),
+                &reason,
+            );
+            return Ok(blocked_response(&reason));
+        }
+    };
+
+    let entry = ailoop_core::models::ScheduledMessage::new(request.at, message);
+    state
+        .scheduler
+        .add(entry.clone())
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    Ok((StatusCode::CREATED, Json(entry)).into_response())
+}
+
+/// Handle GET /api/v1/schedule
+async fn handle_get_schedule(
+    State(state): State<AppState>,
+    Query(query): Query<ScheduleQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if let Some(ref ch) = query.channel {
+        ailoop_core::channel::validation::validate_channel_name(ch)
+            .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+    }
+    let scheduled = state.scheduler.list(query.channel.as_deref()).await;
+    Ok(Json(serde_json::json!({ "scheduled": scheduled })))
+}
+
+/// Handle DELETE /api/v1/schedule/:id
+async fn handle_delete_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ailoop_core::models::ScheduledMessage>, ApiError> {
+    state
+        .scheduler
+        .cancel(&id)
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?
+        .map(Json)
+        .ok_or(ApiError::NotFound)
+}
+
+/// Handle POST /api/v1/reminders
+async fn handle_post_reminder(
+    State(state): State<AppState>,
+    Json(request): Json<ailoop_core::models::ReminderRequest>,
+) -> Result<Response, ApiError> {
+    ailoop_core::channel::validation::validate_channel_name(&request.channel)
+        .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+    if request.text.trim().is_empty() {
+        return Err(ApiError::ValidationError(
+            "reminder text cannot be empty".to_string(),
+        ));
+    }
+    let reminder = ailoop_core::models::Reminder::new(
+        request.channel,
+        request.schedule,
+        request.text,
+        request
+            .priority
+            .unwrap_or(ailoop_core::models::NotificationPriority::Normal),
+    );
+    if reminder.next_due.is_none() {
+        return Err(ApiError::ValidationError(format!(
+            "schedule '{}' never matches",
+            reminder.schedule
+        )));
+    }
+    state
+        .scheduler
+        .add_reminder(reminder.clone())
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    Ok((StatusCode::CREATED, Json(reminder)).into_response())
+}
+
+/// Handle GET /api/v1/reminders
+async fn handle_get_reminders(
+    State(state): State<AppState>,
+    Query(query): Query<ScheduleQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if let Some(ref ch) = query.channel {
+        ailoop_core::channel::validation::validate_channel_name(ch)
+            .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+    }
+    let reminders = state.scheduler.reminders(query.channel.as_deref()).await;
+    Ok(Json(serde_json::json!({ "reminders": reminders })))
+}
+
+/// Handle DELETE /api/v1/reminders/:id
+///
+/// Reminders from `[scheduler] reminders` are rejected: they come back at the next start.
+async fn handle_delete_reminder(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ailoop_core::models::Reminder>, ApiError> {
+    let is_configured = state
+        .scheduler
+        .reminders(None)
+        .await
+        .iter()
+        .any(|r| r.id == id && r.from_config);
+    if is_configured {
+        return Err(ApiError::ValidationError(
+            "reminder is defined in the config file; remove it there".to_string(),
+        ));
+    }
+    state
+        .scheduler
+        .remove_reminder(&id)
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?
+        .map(Json)
+        .ok_or(ApiError::NotFound)
+}
+
+/// Handle POST /api/v1/reminders/:id/skip
+async fn handle_skip_reminder(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<SkipQuery>,
+) -> Result<Json<ailoop_core::models::Reminder>, ApiError> {
+    let count = query.count.unwrap_or(1);
+    if count == 0 {
+        return Err(ApiError::ValidationError(
+            "count must be at least 1".to_string(),
+        ));
+    }
+    state
+        .scheduler
+        .skip_reminder(&id, count)
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?
+        .map(Json)
+        .ok_or(ApiError::NotFound)
+}
+
+/// Handle POST /api/v1/reminders/:id/ack
+async fn handle_ack_reminder(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ailoop_core::models::Reminder>, ApiError> {
+    state
+        .scheduler
+        .acknowledge_reminder(&id)
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?
+        .map(Json)
+        .ok_or(ApiError::NotFound)
+}
+
+/// Handle POST /api/v1/history/purge
+async fn handle_purge_history(
+    State(state): State<AppState>,
+    Json(request): Json<ailoop_core::models::PurgeRequest>,
+) -> Result<Json<ailoop_core::models::PurgeReport>, ApiError> {
+    if let Some(channel) = &request.channel {
+        ailoop_core::channel::validation::validate_channel_name(channel)
+            .map_err(|e| ApiError::ValidationError(e.to_string()))?;
+    }
+    let report = state
+        .message_history
+        .purge(request.channel.as_deref(), request.before, request.dry_run)
+        .await;
+    if !request.dry_run {
+        tracing::info!(
+            channel = request.channel.as_deref().unwrap_or("*"),
+            before = %request.before,
+            messages = report.messages(),
+            session_events = report.session_events,
+            "Purged history"
+        );
+    }
+    Ok(Json(report))
+}
+
+/// Handle POST /api/v1/privacy/export
+async fn handle_privacy_export(
+    State(state): State<AppState>,
+    Json(request): Json<ailoop_core::models::ResponderRequest>,
+) -> Result<Json<ailoop_core::models::ResponderExport>, ApiError> {
+    let responder = non_empty_responder(&request)?;
+    Ok(Json(
+        crate::server::privacy::export_responder(&state, responder).await,
+    ))
+}
+
+/// Handle POST /api/v1/privacy/erase
+async fn handle_privacy_erase(
+    State(state): State<AppState>,
+    Json(request): Json<ailoop_core::models::ResponderRequest>,
+) -> Result<Json<ailoop_core::models::ResponderErasure>, ApiError> {
+    let responder = non_empty_responder(&request)?;
+    crate::server::privacy::erase_responder(&state, responder)
+        .await
+        .map(Json)
+        .map_err(|e| ApiError::InternalError(e.to_string()))
+}
+
+fn non_empty_responder(request: &ailoop_core::models::ResponderRequest) -> Result<&str, ApiError> {
+    match request.responder.trim() {
+        "" => Err(ApiError::ValidationError(
+            "responder cannot be empty".to_string(),
+        )),
+        responder => Ok(responder),
+    }
+}
+
+/// 403 response for a message or response a policy hook blocked.
+fn blocked_response(reason: &str) -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        Json(serde_json::json!({
+            "error": "blocked",
+            "reason": reason,
+        })),
+    )
+        .into_response()
+}
+
+/// 409 response for an answer refused by loop detection, e.g.
+/// `{"error": "self_answer", "identity": "client:a", "message": "..."}`.
+fn loop_violation_response(violation: &LoopViolation) -> Response {
+    let mut body = serde_json::to_value(violation).unwrap_or_default();
+    body["message"] = serde_json::json!(violation.to_string());
+    (StatusCode::CONFLICT, Json(body)).into_response()
+}
+
+/// 429 response carrying the structured quota error and a `Retry-After` header.
+fn quota_exceeded_response(exceeded: &QuotaExceeded) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(
+            axum::http::header::RETRY_AFTER,
+            exceeded.retry_after_seconds.to_string(),
+        )],
+        Json(serde_json::json!({
+            "error": "quota_exceeded",
+            "quota": exceeded,
+        })),
+    )
+        .into_response()
+}
+
+/// Handle GET /api/usage
+async fn handle_get_usage(State(state): State<AppState>) -> Json<UsageResponse> {
+    let quota = state.usage.quota();
+    Json(UsageResponse {
+        max_messages_per_hour: quota.max_messages_per_hour,
+        max_prompts_per_hour: quota.max_prompts_per_hour,
+        clients: state.usage.snapshot().await,
+    })
+}
+
+/// Handle GET /api/sessions/:id/transcript
+///
+/// Markdown by default; `?format=json` returns the recorded events.
+async fn handle_get_session_transcript(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(query): Query<TranscriptQuery>,
+) -> Result<Response, ApiError> {
+    let format = query.format.as_deref().unwrap_or("md");
+    if !matches!(format, "md" | "json") {
+        return Err(ApiError::ValidationError(format!(
+            "Invalid format '{}': expected md or json",
+            format
+        )));
+    }
+    let session = state
+        .message_history
+        .sessions()
+        .get(&session_id)
+        .await
+        .ok_or(ApiError::NotFound)?;
+    if format == "json" {
+        return Ok(Json(session).into_response());
+    }
+    Ok((
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/markdown; charset=utf-8",
+        )],
+        session.to_markdown(),
+    )
+        .into_response())
+}
+
+/// Handle GET /api/v1/messages/:id
+async fn handle_get_message(
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
 ) -> Result<Response, ApiError> {
     match state.message_history.get_message_by_id(&message_id).await {
-        Some(message) => Ok((StatusCode::OK, Json(message)).into_response()),
+        Some(mut message) => {
+            // Provider delivery results travel in metadata so the message schema is unchanged.
+            let delivery = state.broadcast_manager.delivery_status(&message_id);
+            if !delivery.is_empty() {
+                let delivery = serde_json::to_value(delivery).unwrap_or_default();
+                match message.metadata.as_mut().and_then(|m| m.as_object_mut()) {
+                    Some(map) => {
+                        map.insert("delivery".to_string(), delivery);
+                    }
+                    None => message.metadata = Some(serde_json::json!({ "delivery": delivery })),
+                }
+            }
+            Ok((StatusCode::OK, Json(message)).into_response())
+        }
         None => Ok((
             StatusCode::NOT_FOUND,
             Json(serde_json::json!({
@@ -401,10 +1807,61 @@ async fn handle_get_message(
     }
 }
 
+/// Handle GET /api/v1/messages/:id/thread
+///
+/// The prompts the message follows up on, oldest first, each followed by its responses.
+async fn handle_get_thread(
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+) -> Result<Response, ApiError> {
+    let thread = state.message_history.thread(&message_id).await;
+    if thread.is_empty() {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Message not found",
+                "message_id": message_id.to_string()
+            })),
+        )
+            .into_response());
+    }
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({ "messages": thread })),
+    )
+        .into_response())
+}
+
+/// Handle GET /api/v1/conversations/:id
+///
+/// Transcript of a chat conversation, oldest first.
+async fn handle_get_conversation(
+    State(state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+) -> Result<Response, ApiError> {
+    let messages = state.message_history.conversation(&conversation_id).await;
+    if messages.is_empty() {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "Conversation not found",
+                "conversation_id": conversation_id.to_string()
+            })),
+        )
+            .into_response());
+    }
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({ "messages": messages })),
+    )
+        .into_response())
+}
+
 /// Handle POST /api/v1/messages/:id/response
 async fn handle_post_response(
     State(state): State<AppState>,
     Path(message_id): Path<Uuid>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
     Json(response_request): Json<ResponseRequest>,
 ) -> Result<Response, ApiError> {
     let original_message = match state.message_history.get_message_by_id(&message_id).await {
@@ -421,7 +1878,49 @@ async fn handle_post_response(
         }
     };
 
-    let answer = response_request.answer.clone();
+    let responder = Origin::new(
+        None,
+        identity
+            .as_ref()
+            .and_then(|Extension(id)| id.subject.as_deref()),
+        None,
+    )
+    .with_client_id(response_request.client_id.as_deref());
+    if let Err(violation) = state.loops.check_answer(message_id, &responder).await {
+        ailoop_core::services::logging::log_security_event(
+            "response_rejected",
+            &original_message.channel,
+            None,
+            &format!("message {}: {}", message_id, violation),
+        );
+        return Ok(loop_violation_response(&violation));
+    }
+    let subject = identity
+        .as_ref()
+        .and_then(|Extension(id)| id.subject.clone());
+    let claimant = subject
+        .clone()
+        .or_else(|| response_request.responder.clone());
+    if let Some(claim) = state
+        .pending_prompt_registry
+        .claimed_by_other(message_id, claimant.as_deref())
+        .await
+    {
+        return Ok(claimed_response(&claim));
+    }
+
+    let attachment =
+        match response_request.attachment_id {
+            Some(id) => Some(state.attachments.get(id).await.ok_or_else(|| {
+                ApiError::ValidationError(format!("Unknown attachment_id: {}", id))
+            })?),
+            None => None,
+        };
+    // A file alone answers with its name.
+    let answer = response_request
+        .answer
+        .clone()
+        .or_else(|| attachment.as_ref().map(|a| a.name.clone()));
     let response_type = response_request.response_type.clone();
 
     let response_content = ailoop_core::models::MessageContent::Response {
@@ -434,6 +1933,71 @@ async fn handle_post_response(
         response_content,
         message_id,
     );
+    let mut response_message = match state.hooks.outbound(response_message).await {
+        HookOutcome::Pass(message) => *message,
+        HookOutcome::Blocked(reason) => {
+            ailoop_core::services::logging::log_security_event(
+                "response_blocked",
+                &original_message.channel,
+                None,
+                &format!("message {}: {}", message_id, reason),
+            );
+            return Ok(blocked_response(&reason));
+        }
+    };
+    // The hook may have rewritten the answer.
+    let (answer, response_type) = match &response_message.content {
+        ailoop_core::models::MessageContent::Response {
+            answer,
+            response_type,
+        } => (answer.clone(), response_type.clone()),
+        _ => (answer, response_type),
+    };
+    if response_type == ailoop_core::models::ResponseType::AuthorizationApproved {
+        if let Some(level) = state
+            .pending_prompt_registry
+            .needs_terminal_approval(message_id)
+            .await
+        {
+            return Ok(unverified_response(level));
+        }
+    }
+
+    // Record who answered when the caller authenticated via SSO.
+    if let Some(subject) = &subject {
+        ailoop_core::services::logging::log_security_event(
+            "prompt_answered",
+            &response_message.channel,
+            Some(subject),
+            &format!("message {}", message_id),
+        );
+        response_message.metadata = Some(serde_json::json!({ "responder": subject }));
+    }
+    if let Some(attachment) = &attachment {
+        response_message
+            .metadata
+            .get_or_insert_with(|| serde_json::json!({}))["attachment"] =
+            serde_json::json!(attachment);
+        state
+            .pending_prompt_registry
+            .attach(message_id, attachment.clone())
+            .await;
+    }
+    if let Some(signer) = &state.signer {
+        let responder = subject
+            .as_deref()
+            .unwrap_or(ailoop_core::services::signing::OPERATOR_RESPONDER);
+        let signature = signer.sign(&mut response_message, responder);
+        ailoop_core::services::logging::log_security_event(
+            "response_signed",
+            &response_message.channel,
+            Some(responder),
+            &format!(
+                "prompt {} key {} signature {}",
+                message_id, signature.public_key, signature.value
+            ),
+        );
+    }
 
     state
         .message_history
@@ -447,12 +2011,411 @@ async fn handle_post_response(
 
     state
         .pending_prompt_registry
-        .submit_reply_for_message(message_id, answer, response_type)
+        .submit_reply_for_message_as(message_id, answer, response_type, claimant)
         .await;
 
     Ok((StatusCode::OK, Json(response_message)).into_response())
 }
 
+/// 409 for a prompt claimed by another responder.
+fn claimed_response(claim: &crate::server::providers::Claim) -> Response {
+    (
+        StatusCode::CONFLICT,
+        Json(serde_json::json!({
+            "error": format!("Claimed by {}", claim.responder),
+            "claimed_by": claim.responder,
+            "claimed_since": claim.since,
+        })),
+    )
+        .into_response()
+}
+
+/// 403 response for an approval that `[verification]` requires at the server terminal.
+fn unverified_response(level: ailoop_core::models::RiskLevel) -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        Json(serde_json::json!({
+            "error": "unverified",
+            "reason": format!("{}-risk approvals must be given at the server terminal", level),
+        })),
+    )
+        .into_response()
+}
+
+/// Handle POST /api/v1/messages/:id/claim: take a pending prompt so other responders leave it
+/// to the caller. The responder is the SSO subject, else `responder` from the body. 409 when
+/// someone else holds it.
+async fn handle_post_claim(
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Json(request): Json<ClaimRequest>,
+) -> Result<Response, ApiError> {
+    change_claim(state, message_id, identity, request, true).await
+}
+
+/// Handle POST /api/v1/messages/:id/release: give a claimed prompt back to the shared queue.
+/// Only its claimant may release it; 409 otherwise or when it is not claimed.
+async fn handle_post_release(
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Json(request): Json<ClaimRequest>,
+) -> Result<Response, ApiError> {
+    change_claim(state, message_id, identity, request, false).await
+}
+
+async fn change_claim(
+    state: AppState,
+    message_id: Uuid,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    request: ClaimRequest,
+    claim: bool,
+) -> Result<Response, ApiError> {
+    let responder = identity
+        .and_then(|Extension(id)| id.subject)
+        .or(request.responder)
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .ok_or_else(|| ApiError::ValidationError("responder is required".to_string()))?;
+    let registry = &state.pending_prompt_registry;
+    let prompt = registry
+        .snapshot_pending(None)
+        .await
+        .into_iter()
+        .find(|p| p.message_id == message_id)
+        .ok_or(ApiError::NotFound)?;
+    let result = if claim {
+        registry.claim(message_id, &responder).await
+    } else {
+        registry.release(message_id, &responder).await
+    };
+    let taken = match result {
+        Ok(taken) => taken,
+        Err(crate::server::providers::ClaimError::NotPending) => return Err(ApiError::NotFound),
+        Err(crate::server::providers::ClaimError::ClaimedByOther(other)) => {
+            return Ok(claimed_response(&other))
+        }
+        Err(e @ crate::server::providers::ClaimError::NotClaimed) => {
+            return Ok((
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response())
+        }
+    };
+    // Claiming again is not news.
+    if !claim || prompt.claim.is_none() {
+        let notice = crate::server::claims::notice(
+            &prompt.channel,
+            message_id,
+            &prompt.label,
+            &taken,
+            claim,
+        );
+        crate::server::claims::announce(&state.message_history, &state.broadcast_manager, notice)
+            .await;
+    }
+    Ok(Json(ailoop_core::PromptClaimResponse {
+        message_id,
+        channel: prompt.channel,
+        responder: taken.responder,
+        since: taken.since,
+        claimed: claim,
+    })
+    .into_response())
+}
+
+/// Handle GET /api/v1/unread: agent notifications the caller (the SSO subject, else
+/// `?responder=`) has not read, per channel.
+async fn handle_get_unread(
+    State(state): State<AppState>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Query(query): Query<UnreadQuery>,
+) -> Result<Json<ailoop_core::UnreadCounts>, ApiError> {
+    let responder = reader(identity, query.responder)?;
+    Ok(Json(unread_counts(&state, &responder).await))
+}
+
+/// Handle POST /api/v1/messages/:id/read: mark an agent notification read by the caller.
+async fn handle_post_read(
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Json(request): Json<ReadRequest>,
+) -> Result<Json<ailoop_core::UnreadCounts>, ApiError> {
+    let responder = reader(identity, request.responder)?;
+    let message = state
+        .message_history
+        .get_message_by_id(&message_id)
+        .await
+        .ok_or(ApiError::NotFound)?;
+    if !crate::server::history::is_agent_notification(&message) {
+        return Err(ApiError::ValidationError(
+            "only agent notifications are marked read".to_string(),
+        ));
+    }
+    state.message_history.reads().mark(message_id, &responder);
+    Ok(Json(unread_counts(&state, &responder).await))
+}
+
+/// Handle POST /api/v1/channels/:channel/read: mark every agent notification on the channel
+/// read by the caller.
+async fn handle_post_channel_read(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    Json(request): Json<ReadRequest>,
+) -> Result<Json<ailoop_core::UnreadCounts>, ApiError> {
+    let responder = reader(identity, request.responder)?;
+    state
+        .message_history
+        .mark_channel_read(&channel, &responder)
+        .await;
+    Ok(Json(unread_counts(&state, &responder).await))
+}
+
+/// Who reads: the SSO subject, else the given responder.
+fn reader(
+    identity: Option<Extension<crate::config::AuthIdentity>>,
+    responder: Option<String>,
+) -> Result<String, ApiError> {
+    identity
+        .and_then(|Extension(id)| id.subject)
+        .or(responder)
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .ok_or_else(|| ApiError::ValidationError("responder is required".to_string()))
+}
+
+async fn unread_counts(state: &AppState, responder: &str) -> ailoop_core::UnreadCounts {
+    let channels = state.message_history.unread(responder).await;
+    ailoop_core::UnreadCounts {
+        reader: state.message_history.reads().reader(responder),
+        total: channels.iter().map(|c| c.unread).sum(),
+        channels,
+    }
+}
+
+/// Handle POST /api/v1/messages/:id/typing: pass a draft answer to a pending prompt on to
+/// agents that accepted typing frames. Answered prompts are not found.
+async fn handle_post_typing(
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    Json(request): Json<TypingRequest>,
+) -> Result<StatusCode, ApiError> {
+    let pending = state.pending_prompt_registry.snapshot_pending(None).await;
+    let prompt = pending
+        .iter()
+        .find(|p| p.message_id == message_id)
+        .ok_or(ApiError::NotFound)?;
+    state
+        .broadcast_manager
+        .broadcast_typing(message_id, &prompt.channel, &request.text)
+        .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handle POST /api/v1/messages/:id/break-glass
+///
+/// Emergency approval of an authorization nobody answered within `[break_glass]
+/// after_seconds`. Authenticated by a break-glass token, not by API tokens; returns 404 unless
+/// break-glass holders are configured. Recorded as a `break_glass_override` security event and
+/// a `break_glass` event, and announced on the channel.
+async fn handle_post_break_glass(
+    State(state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    headers: axum::http::HeaderMap,
+    Json(request): Json<BreakGlassRequest>,
+) -> Result<Response, ApiError> {
+    let config = state
+        .provider_config
+        .as_ref()
+        .map(|c| c.break_glass.clone())
+        .unwrap_or_default();
+    if config.holders.is_empty() {
+        return Err(ApiError::NotFound);
+    }
+    let token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    let Some(holder) = config.holder(token).map(str::to_string) else {
+        ailoop_core::services::logging::log_security_event(
+            "break_glass_rejected",
+            "-",
+            None,
+            &format!("message {}: bad break-glass token", message_id),
+        );
+        return Ok((
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "unauthorized"})),
+        )
+            .into_response());
+    };
+    let justification = request.justification.trim().to_string();
+    if justification.chars().count() < config.min_justification_chars() {
+        return Err(ApiError::ValidationError(format!(
+            "justification must be at least {} characters",
+            config.min_justification_chars()
+        )));
+    }
+
+    let prompt = state
+        .message_history
+        .get_message_by_id(&message_id)
+        .await
+        .filter(|m| {
+            matches!(
+                m.content,
+                ailoop_core::models::MessageContent::Authorization { .. }
+            )
+        })
+        .ok_or(ApiError::NotFound)?;
+    if !config.applies_to(&prompt.channel) {
+        return Ok((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "break-glass is not allowed on this channel"})),
+        )
+            .into_response());
+    }
+    let waited = (chrono::Utc::now() - prompt.timestamp)
+        .to_std()
+        .unwrap_or_default();
+    if waited < config.after() {
+        return Ok((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "authorization has not gone unanswered long enough",
+                "available_in_seconds": (config.after() - waited).as_secs(),
+            })),
+        )
+            .into_response());
+    }
+
+    // Submitted before anything is recorded, so an answer that lands first leaves no trace of
+    // an approval that never reached the agent. Break-glass answers whoever had claimed it.
+    let responder = format!("break-glass:{}", holder);
+    state.pending_prompt_registry.clear_claim(message_id).await;
+    let submitted = state
+        .pending_prompt_registry
+        .submit_break_glass(message_id, justification.clone(), responder.clone())
+        .await;
+    if !submitted {
+        return Ok((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": "authorization is not waiting for an answer"})),
+        )
+            .into_response());
+    }
+
+    let mut response_message = Message::response(
+        prompt.channel.clone(),
+        ailoop_core::models::MessageContent::Response {
+            answer: Some(justification.clone()),
+            response_type: ailoop_core::models::ResponseType::AuthorizationApproved,
+        },
+        message_id,
+    );
+    response_message.metadata = Some(serde_json::json!({
+        "responder": responder,
+        "break_glass": { "holder": holder, "justification": justification },
+    }));
+    if let Some(signer) = &state.signer {
+        signer.sign(&mut response_message, &responder);
+    }
+    ailoop_core::services::logging::log_security_event(
+        "break_glass_override",
+        &prompt.channel,
+        Some(&holder),
+        &format!(
+            "prompt {} approved: {} (justification: {})",
+            message_id,
+            prompt.prompt_text().unwrap_or_default(),
+            justification
+        ),
+    );
+    crate::server::events::break_glass(message_id, &prompt.channel, &holder, &justification);
+
+    let notice = Message::new(
+        prompt.channel.clone(),
+        ailoop_core::models::SenderType::System,
+        ailoop_core::models::MessageContent::Notification {
+            text: format!(
+                "Break-glass approval by {} of \"{}\": {}",
+                holder,
+                prompt.prompt_text().unwrap_or_default(),
+                justification
+            ),
+            priority: ailoop_core::models::NotificationPriority::Urgent,
+        },
+    );
+    for message in [&response_message, &notice] {
+        state
+            .message_history
+            .add_message(&message.channel, message.clone())
+            .await;
+        state.broadcast_manager.broadcast_message(message).await;
+    }
+
+    Ok((StatusCode::OK, Json(response_message)).into_response())
+}
+
+/// Handle POST /api/v1/attachments?name=FILE
+///
+/// The request body is the file. Returns the stored attachment; pass its `id` as
+/// `attachment_id` when answering a prompt.
+async fn handle_post_attachment(
+    State(state): State<AppState>,
+    Query(query): Query<AttachmentQuery>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<Response, ApiError> {
+    let content_type = headers
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let attachment = state
+        .attachments
+        .store(&query.name, content_type, &body)
+        .await
+        .map_err(|e| match e {
+            crate::server::attachments::AttachmentError::TooLarge { .. } => {
+                ApiError::ValidationError(e.to_string())
+            }
+            e => ApiError::InternalError(e.to_string()),
+        })?;
+    Ok((StatusCode::CREATED, Json(attachment)).into_response())
+}
+
+/// Handle GET /api/v1/attachments/:id
+async fn handle_get_attachment(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, ApiError> {
+    let attachment = state.attachments.get(id).await.ok_or(ApiError::NotFound)?;
+    let bytes = tokio::fs::read(&attachment.path)
+        .await
+        .map_err(|e| ApiError::InternalError(e.to_string()))?;
+    let content_type = attachment
+        .content_type
+        .clone()
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        attachment.name.replace(['"', '\\'], "_")
+    );
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, content_type),
+            (axum::http::header::CONTENT_DISPOSITION, disposition),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
 /// Handle POST /api/v1/tasks
 async fn handle_post_tasks(
     State(state): State<AppState>,
@@ -0,0 +1,240 @@
+//! Prompts assigned to one responder
+//!
+//! `ailoop ask --assign alice` sets `metadata.assignee`. When `alice` is in the `[team]`
+//! directory, the prompt goes only to the providers of her handles, and the server terminal
+//! reads an answer only when one of her handles is `terminal`. Until `[assignment]
+//! fallback_seconds` have passed, the prompt is held for `@alice` as if she had claimed it, so
+//! answers from anyone else are refused. Then the hold is dropped, the prompt is sent to the
+//! other providers and viewers are told it is open to everyone. An assignee who is not in the
+//! directory, whom no running provider reaches, or who is [away](crate::server::away), is
+//! ignored and the prompt goes to everyone.
+
+use crate::server::away::AwayBoard;
+use crate::server::broadcast::BroadcastManager;
+use crate::server::providers::PendingPromptRegistry;
+use ailoop_core::models::{
+    AwayStatus, Configuration, Message, MessageContent, NotificationPriority, SenderType,
+};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Handle that stands for the server terminal in a member's `handles`.
+pub const TERMINAL_HANDLE: &str = "terminal";
+
+/// Where an assigned prompt goes first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assignment {
+    /// Team directory key of the assignee.
+    pub member: String,
+    /// Providers of the assignee's handles.
+    pub providers: Vec<String>,
+    /// Whether the assignee answers at the server terminal.
+    pub terminal: bool,
+}
+
+/// The assignment of `message`, or `None` when it is not assigned to a known team member.
+pub fn assignment(message: &Message, config: Option<&Configuration>) -> Option<Assignment> {
+    let assignee = message.assignee()?;
+    let member = config.and_then(|c| c.team.get(assignee))?;
+    Some(Assignment {
+        member: assignee.to_string(),
+        providers: member.providers(),
+        terminal: member
+            .handles
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(TERMINAL_HANDLE)),
+    })
+}
+
+/// Whether the server terminal may answer `message`: it is not assigned, or assigned to
+/// someone at the terminal or someone away.
+pub fn terminal_may_answer(
+    message: &Message,
+    config: Option<&Configuration>,
+    away: &AwayBoard,
+) -> bool {
+    assignment(message, config).is_none_or(|a| a.terminal || away.is_away(&a.member, Utc::now()))
+}
+
+/// Send `message` to its assignee's providers and hold it for them, opening it to everyone
+/// after `fallback`. Returns the reply-to ID of the first delivery, like
+/// [`BroadcastManager::send_to_notification_sinks_and_get_reply_to_id`].
+pub async fn deliver(
+    assignment: Assignment,
+    message: &Message,
+    broadcast: &Arc<BroadcastManager>,
+    registry: &Arc<PendingPromptRegistry>,
+    fallback: Duration,
+) -> Option<String> {
+    let running = broadcast.sink_names().await;
+    if !assignment.terminal
+        && !running
+            .iter()
+            .any(|name| assignment.providers.contains(name))
+    {
+        tracing::warn!(
+            message_id = %message.id,
+            member = %assignment.member,
+            "No running provider reaches the assignee; sending to everyone"
+        );
+        return broadcast
+            .send_to_notification_sinks_and_get_reply_to_id(message)
+            .await;
+    }
+    tracing::info!(message_id = %message.id, member = %assignment.member, "routing prompt to assignee");
+    registry
+        .assign(message.id, &format!("@{}", assignment.member))
+        .await;
+    let reply_to = broadcast
+        .send_to_providers_and_get_reply_to_id(message, &assignment.providers, true)
+        .await;
+
+    let message = message.clone();
+    let broadcast = Arc::clone(broadcast);
+    let registry = Arc::clone(registry);
+    tokio::spawn(async move {
+        tokio::time::sleep(fallback).await;
+        registry.unassign(message.id).await;
+        if !registry.is_pending(message.id).await {
+            return;
+        }
+        tracing::info!(message_id = %message.id, member = %assignment.member, "assignee did not answer, opening prompt to everyone");
+        broadcast
+            .broadcast_to_viewers_only(&fallback_notice(&message, &assignment.member, fallback))
+            .await;
+        if let Some(reply_to) = broadcast
+            .send_to_providers_and_get_reply_to_id(&message, &assignment.providers, false)
+            .await
+        {
+            registry.set_reply_to(message.id, reply_to).await;
+        }
+    });
+    reply_to
+}
+
+/// System notification telling the asking agent that `member` is away, so the prompt assigned
+/// to them goes to everyone.
+pub fn away_notice(prompt: &Message, member: &str, away: &AwayStatus) -> Message {
+    let until = away
+        .until
+        .map(|until| format!(" until {}", until.format("%Y-%m-%d %H:%M UTC")))
+        .unwrap_or_default();
+    let note = away
+        .note
+        .as_ref()
+        .map(|note| format!(" ({})", note))
+        .unwrap_or_default();
+    let mut notice = Message::new(
+        prompt.channel.clone(),
+        SenderType::System,
+        MessageContent::Notification {
+            text: format!(
+                "@{} is away{}{}; \"{}\" goes to everyone instead",
+                member,
+                until,
+                note,
+                prompt.prompt_text().unwrap_or_default()
+            ),
+            priority: NotificationPriority::Normal,
+        },
+    );
+    notice.metadata = Some(serde_json::json!({
+        "assigned_prompt": prompt.id,
+        "assignee": member,
+        "away_until": away.until,
+    }));
+    notice
+}
+
+/// System notification telling viewers that the prompt assigned to `member` is open to
+/// everyone.
+fn fallback_notice(prompt: &Message, member: &str, fallback: Duration) -> Message {
+    let mut notice = Message::new(
+        prompt.channel.clone(),
+        SenderType::System,
+        MessageContent::Notification {
+            text: format!(
+                "@{} did not answer \"{}\" within {}s; anyone may answer it now",
+                member,
+                prompt.prompt_text().unwrap_or_default(),
+                fallback.as_secs()
+            ),
+            priority: NotificationPriority::Normal,
+        },
+    );
+    notice.metadata = Some(serde_json::json!({
+        "assigned_prompt": prompt.id,
+        "assignee": member,
+    }));
+    notice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{TeamDirectory, TeamMember};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_assignment_follows_team_handles() {
+        let mut members = BTreeMap::new();
+        members.insert(
+            "alice".to_string(),
+            TeamMember {
+                handles: vec![
+                    "telegram:alice".to_string(),
+                    "email:a@example.com".to_string(),
+                ],
+                ..TeamMember::default()
+            },
+        );
+        members.insert(
+            "bob".to_string(),
+            TeamMember {
+                handles: vec!["terminal".to_string()],
+                ..TeamMember::default()
+            },
+        );
+        let config = Configuration {
+            team: TeamDirectory::new(members),
+            ..Configuration::default()
+        };
+        let prompt = ailoop_core::client::authorization("ops", "deploy", 60);
+
+        let away = AwayBoard::new();
+        assert_eq!(assignment(&prompt, Some(&config)), None);
+        assert!(terminal_may_answer(&prompt, Some(&config), &away));
+
+        let assigned = prompt.clone().with_assignee("@alice");
+        assert_eq!(
+            assignment(&assigned, Some(&config)),
+            Some(Assignment {
+                member: "alice".to_string(),
+                providers: vec!["telegram".to_string(), "email".to_string()],
+                terminal: false,
+            })
+        );
+        assert!(!terminal_may_answer(&assigned, Some(&config), &away));
+        assert!(terminal_may_answer(
+            &prompt.clone().with_assignee("bob"),
+            Some(&config),
+            &away
+        ));
+        away.set(
+            "alice",
+            AwayStatus {
+                since: Utc::now(),
+                until: None,
+                note: None,
+            },
+        )
+        .unwrap();
+        assert!(terminal_may_answer(&assigned, Some(&config), &away));
+        // Unknown assignees are ignored.
+        assert_eq!(
+            assignment(&prompt.with_assignee("carol"), Some(&config)),
+            None
+        );
+    }
+}
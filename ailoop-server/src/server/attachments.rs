@@ -0,0 +1,253 @@
+//! Files attached to prompt responses
+//!
+//! A human can answer a prompt with a file ("here's the kubeconfig"): uploaded through
+//! `POST /api/v1/attachments` or given as `@PATH` at the terminal. Each file is copied to
+//! `<dir>/<id>/<name>` and described in the response's `metadata.attachment` with its local
+//! `path` and the `url` it can be downloaded from. Agents send files the same way with
+//! `ailoop image` and `ailoop say --attach`; providers show image attachments inline.
+
+use ailoop_core::models::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Largest file accepted.
+pub const MAX_ATTACHMENT_BYTES: usize = 25 * 1024 * 1024;
+
+/// A stored file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Attachment {
+    pub id: Uuid,
+    /// File name as given by the responder.
+    pub name: String,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Where the file is kept on the server's machine.
+    pub path: PathBuf,
+    /// Download path on the HTTP API.
+    pub url: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AttachmentError {
+    #[error("attachment exceeds {limit} bytes")]
+    TooLarge { limit: usize },
+    #[error("failed to store attachment: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Directory of attachments, indexed in memory for the lifetime of the server.
+#[derive(Debug)]
+pub struct AttachmentStore {
+    dir: PathBuf,
+    index: RwLock<HashMap<Uuid, Attachment>>,
+}
+
+impl AttachmentStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            index: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Store under `ailoop-attachments` in the system temporary directory.
+    pub fn in_temp_dir() -> Self {
+        Self::new(std::env::temp_dir().join("ailoop-attachments"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Keep `bytes` as a file called `name`.
+    pub async fn store(
+        &self,
+        name: &str,
+        content_type: Option<String>,
+        bytes: &[u8],
+    ) -> Result<Attachment, AttachmentError> {
+        if bytes.len() > MAX_ATTACHMENT_BYTES {
+            return Err(AttachmentError::TooLarge {
+                limit: MAX_ATTACHMENT_BYTES,
+            });
+        }
+        let id = Uuid::new_v4();
+        let name = file_name(name);
+        let dir = self.dir.join(id.to_string());
+        tokio::fs::create_dir_all(&dir).await?;
+        let path = dir.join(&name);
+        tokio::fs::write(&path, bytes).await?;
+
+        let attachment = Attachment {
+            id,
+            name,
+            size: bytes.len() as u64,
+            content_type,
+            path,
+            url: format!("/api/v1/attachments/{}", id),
+        };
+        self.index.write().await.insert(id, attachment.clone());
+        Ok(attachment)
+    }
+
+    /// Copy the file at `path` into the store.
+    pub async fn store_file(&self, path: &Path) -> Result<Attachment, AttachmentError> {
+        let size = tokio::fs::metadata(path).await?.len();
+        if size > MAX_ATTACHMENT_BYTES as u64 {
+            return Err(AttachmentError::TooLarge {
+                limit: MAX_ATTACHMENT_BYTES,
+            });
+        }
+        let bytes = tokio::fs::read(path).await?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.store(&name, None, &bytes).await
+    }
+
+    pub async fn get(&self, id: Uuid) -> Option<Attachment> {
+        self.index.read().await.get(&id).cloned()
+    }
+
+    /// Delete a stored file. Files stored before a restart are no longer indexed but are
+    /// still found in the directory. Returns whether there was one.
+    pub async fn remove(&self, id: Uuid) -> std::io::Result<bool> {
+        let indexed = self.index.write().await.remove(&id).is_some();
+        match tokio::fs::remove_dir_all(self.dir.join(id.to_string())).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(indexed),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// The file a message carries in `metadata.attachment`: one stored here (with its `id`), or
+/// just a link.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MessageAttachment {
+    #[serde(default)]
+    pub id: Option<Uuid>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    pub url: String,
+}
+
+impl MessageAttachment {
+    pub fn of(message: &Message) -> Option<Self> {
+        let value = message.metadata.as_ref()?.get("attachment")?;
+        serde_json::from_value(value.clone()).ok()
+    }
+
+    /// The image type (`image/png`, ...), when the attachment is an image.
+    pub fn image_type(&self) -> Option<&str> {
+        self.content_type
+            .as_deref()
+            .filter(|t| t.starts_with("image/"))
+    }
+
+    /// Contents of the file, when it is stored in `store`.
+    pub async fn read(&self, store: &AttachmentStore) -> Option<Vec<u8>> {
+        let attachment = store.get(self.id?).await?;
+        tokio::fs::read(&attachment.path).await.ok()
+    }
+}
+
+/// Last component of `name` without characters that are unsafe in file names.
+fn file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .collect();
+    match cleaned.trim() {
+        "" | "." | ".." => "attachment".to_string(),
+        name => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_are_sanitized() {
+        assert_eq!(file_name("kubeconfig"), "kubeconfig");
+        assert_eq!(file_name("../../etc/passwd"), "passwd");
+        assert_eq!(file_name("C:\\Users\\me\\id_rsa.pub"), "id_rsa.pub");
+        assert_eq!(file_name(".."), "attachment");
+        assert_eq!(file_name(""), "attachment");
+    }
+
+    #[tokio::test]
+    async fn stores_and_indexes_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AttachmentStore::new(dir.path());
+        let attachment = store
+            .store(
+                "config.yaml",
+                Some("text/yaml".to_string()),
+                b"apiVersion: v1\n",
+            )
+            .await
+            .unwrap();
+        assert_eq!(attachment.size, 15);
+        assert_eq!(
+            attachment.url,
+            format!("/api/v1/attachments/{}", attachment.id)
+        );
+        assert_eq!(
+            std::fs::read(&attachment.path).unwrap(),
+            b"apiVersion: v1\n"
+        );
+        assert_eq!(store.get(attachment.id).await.unwrap().name, "config.yaml");
+
+        let copied = store.store_file(&attachment.path).await.unwrap();
+        assert_ne!(copied.id, attachment.id);
+        assert_eq!(copied.name, "config.yaml");
+    }
+
+    #[tokio::test]
+    async fn message_attachments_are_read_from_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AttachmentStore::new(dir.path());
+        let stored = store
+            .store("plot.png", Some("image/png".to_string()), b"\x89PNG")
+            .await
+            .unwrap();
+        let mut message = Message::new(
+            "ops".to_string(),
+            ailoop_core::models::SenderType::Agent,
+            ailoop_core::models::MessageContent::Notification {
+                text: "Image: plot.png".to_string(),
+                priority: ailoop_core::models::NotificationPriority::Normal,
+            },
+        );
+        assert!(MessageAttachment::of(&message).is_none());
+
+        message.metadata = Some(serde_json::json!({ "attachment": {
+            "id": stored.id,
+            "name": "plot.png",
+            "content_type": "image/png",
+            "url": "http://127.0.0.1:8080/api/v1/attachments/x",
+        }}));
+        let attachment = MessageAttachment::of(&message).unwrap();
+        assert_eq!(attachment.image_type(), Some("image/png"));
+        assert_eq!(attachment.read(&store).await.unwrap(), b"\x89PNG");
+
+        let link = MessageAttachment {
+            id: None,
+            name: "notes.txt".to_string(),
+            content_type: Some("text/plain".to_string()),
+            url: "https://example.com/notes.txt".to_string(),
+        };
+        assert_eq!(link.image_type(), None);
+        assert!(link.read(&store).await.is_none());
+    }
+}
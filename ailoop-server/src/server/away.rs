@@ -0,0 +1,175 @@
+//! Responders who are away
+//!
+//! A `[team]` member says they are away with `ailoop me away --until friday` (`POST
+//! /api/v1/me/away`) and back with `ailoop me back`, or simply by letting `until` pass. While
+//! they are away, on-call routing passes over them to the next in line, and a prompt asked
+//! with `--assign` them goes to everyone at once, with a notice to the asking agent. Statuses
+//! are saved to `away.json` in the state directory, so a restart does not bring anyone back
+//! from vacation.
+
+use ailoop_core::models::AwayStatus;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct Board {
+    file: Option<PathBuf>,
+    members: BTreeMap<String, AwayStatus>,
+}
+
+/// Away statuses by team member key.
+#[derive(Default)]
+pub struct AwayBoard {
+    board: Mutex<Board>,
+}
+
+impl AwayBoard {
+    /// A board that forgets its statuses when the server stops.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Save statuses to `file` from now on, starting with the ones already in it.
+    pub fn open(&self, file: impl Into<PathBuf>) -> Result<()> {
+        let file = file.into();
+        let members = match std::fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid away file {:?}", file))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read away file {:?}", file))
+            }
+        };
+        *self.lock() = Board {
+            file: Some(file),
+            members,
+        };
+        Ok(())
+    }
+
+    /// `member`'s status, while they are away at `now`.
+    pub fn status(&self, member: &str, now: DateTime<Utc>) -> Option<AwayStatus> {
+        self.lock()
+            .members
+            .get(member)
+            .filter(|status| status.is_away(now))
+            .cloned()
+    }
+
+    pub fn is_away(&self, member: &str, now: DateTime<Utc>) -> bool {
+        self.status(member, now).is_some()
+    }
+
+    /// Members away at `now`, by key.
+    pub fn list(&self, now: DateTime<Utc>) -> BTreeMap<String, AwayStatus> {
+        self.lock()
+            .members
+            .iter()
+            .filter(|(_, status)| status.is_away(now))
+            .map(|(member, status)| (member.clone(), status.clone()))
+            .collect()
+    }
+
+    /// Mark `member` away, replacing any earlier status.
+    pub fn set(&self, member: &str, status: AwayStatus) -> Result<()> {
+        let mut board = self.lock();
+        let previous = board.members.insert(member.to_string(), status.clone());
+        board.members.retain(|_, s| s.is_away(status.since));
+        if let Err(e) = board.save() {
+            match previous {
+                Some(previous) => board.members.insert(member.to_string(), previous),
+                None => board.members.remove(member),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Mark `member` back; returns the status they had, if they were still away at `now`.
+    pub fn clear(&self, member: &str, now: DateTime<Utc>) -> Result<Option<AwayStatus>> {
+        let mut board = self.lock();
+        let Some(previous) = board.members.remove(member) else {
+            return Ok(None);
+        };
+        if let Err(e) = board.save() {
+            board.members.insert(member.to_string(), previous);
+            return Err(e);
+        }
+        Ok(Some(previous).filter(|status| status.is_away(now)))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Board> {
+        self.board.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Board {
+    fn save(&self) -> Result<()> {
+        match &self.file {
+            Some(file) => save_to(file, &self.members),
+            None => Ok(()),
+        }
+    }
+}
+
+fn save_to(file: &Path, members: &BTreeMap<String, AwayStatus>) -> Result<()> {
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {:?}", parent))?;
+    }
+    let tmp = file.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(members)?)
+        .with_context(|| format!("Failed to write {:?}", tmp))?;
+    std::fs::rename(&tmp, file).with_context(|| format!("Failed to write {:?}", file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_away_statuses_expire_and_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("away.json");
+        let now = Utc::now();
+        let board = AwayBoard::new();
+        board.open(&file).unwrap();
+        board
+            .set(
+                "alice",
+                AwayStatus {
+                    since: now,
+                    until: Some(now + Duration::days(3)),
+                    note: Some("vacation".to_string()),
+                },
+            )
+            .unwrap();
+        board
+            .set(
+                "bob",
+                AwayStatus {
+                    since: now,
+                    until: None,
+                    note: None,
+                },
+            )
+            .unwrap();
+        assert!(board.is_away("alice", now));
+        assert!(!board.is_away("alice", now + Duration::days(3)));
+        assert!(!board.is_away("carol", now));
+
+        let restarted = AwayBoard::new();
+        restarted.open(&file).unwrap();
+        assert_eq!(restarted.list(now).len(), 2);
+        assert!(restarted.clear("bob", now).unwrap().is_some());
+        assert!(restarted.clear("bob", now).unwrap().is_none());
+        assert_eq!(
+            restarted.list(now).keys().collect::<Vec<_>>(),
+            vec!["alice"]
+        );
+    }
+}
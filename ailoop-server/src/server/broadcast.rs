@@ -1,18 +1,73 @@
 //! Broadcast manager for WebSocket viewer connections and notification sinks
 
-use crate::server::providers::NotificationSink;
-use ailoop_core::models::{Message, MessageContent};
+use crate::config::Role;
+use crate::server::away::AwayBoard;
+use crate::server::jira::JiraSink;
+use crate::server::oncall::OnCallRouter;
+use crate::server::providers::{
+    DeliveryLog, DeliveryPolicy, DeliveryReport, DeliveryStatus, NotificationSink,
+};
+use crate::server::queue_position::QueuePositions;
+use crate::server::sampling::{Sampler, Sampling};
+use crate::server::sla::SlaTracker;
+use crate::server::stats::ResponseLog;
+use crate::server::subscriptions::{self, Subscriptions};
+use crate::server::typing::TypingRelay;
+use ailoop_core::channel::pattern::matches_pattern;
+use ailoop_core::models::{
+    Capabilities, Message, MessageContent, MirrorConfig, NotificationPriority, SenderType,
+};
 use axum::extract::ws::Message as WsMessage;
+use chrono::Utc;
 use std::collections::{HashMap, HashSet};
+use std::error::Error;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-/// Connection type for WebSocket clients
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Connection type for WebSocket clients, declared in the handshake as
+/// `?connection=agent|viewer|responder`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConnectionType {
+    /// Sends messages and prompts; may not answer prompts.
     Agent,
+    /// Receives the message stream; read-only.
     Viewer,
+    /// Receives the message stream and answers prompts with `response` frames.
+    Responder,
+}
+
+impl ConnectionType {
+    /// Minimum token role allowed to open a connection of this type.
+    pub fn required_role(self) -> Role {
+        match self {
+            ConnectionType::Viewer => Role::Viewer,
+            ConnectionType::Agent | ConnectionType::Responder => Role::Responder,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConnectionType::Agent => "agent",
+            ConnectionType::Viewer => "viewer",
+            ConnectionType::Responder => "responder",
+        }
+    }
+}
+
+impl std::str::FromStr for ConnectionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "agent" => Ok(ConnectionType::Agent),
+            "viewer" => Ok(ConnectionType::Viewer),
+            "responder" => Ok(ConnectionType::Responder),
+            other => Err(format!(
+                "unknown connection type '{other}' (expected agent, viewer or responder)"
+            )),
+        }
+    }
 }
 
 /// Viewer connection information
@@ -22,6 +77,8 @@ pub struct ViewerConnection {
     pub connection_type: ConnectionType,
     pub subscribed_channels: HashSet<String>,
     pub sender: tokio::sync::mpsc::UnboundedSender<WsMessage>,
+    /// Drops messages the viewer asked not to receive, when it asked
+    pub sampler: Option<Arc<Sampler>>,
 }
 
 /// Broadcast manager for handling viewer connections and message distribution
@@ -33,6 +90,38 @@ pub struct BroadcastManager {
     channel_subscriptions: Arc<RwLock<HashMap<String, HashSet<Uuid>>>>,
     /// Notification sinks (e.g. Telegram)
     notification_sinks: Arc<RwLock<Vec<Arc<dyn NotificationSink>>>>,
+    /// Retry and failover rules for prompt delivery
+    delivery_policy: Arc<RwLock<DeliveryPolicy>>,
+    /// Per-sink delivery results of recent messages
+    deliveries: Arc<DeliveryLog>,
+    /// Routes prompts to whoever is on call, when configured
+    on_call: Arc<RwLock<Option<Arc<OnCallRouter>>>>,
+    /// Channels whose traffic is copied to aggregate channels
+    mirrors: Arc<RwLock<Vec<MirrorConfig>>>,
+    /// Prompts and the responses that settled them, for response metrics
+    responses: Arc<ResponseLog>,
+    /// Clocks of the response SLAs
+    slas: Arc<SlaTracker>,
+    /// Capabilities advertised to clients, except the providers
+    capabilities: Arc<RwLock<Capabilities>>,
+    /// Drafts of answers, for connections that accepted typing frames
+    typing: Arc<TypingRelay>,
+    /// Agent connections waiting on queued prompts, told where they wait
+    queue_positions: Arc<QueuePositions>,
+    /// Team members who are away, passed over by routing
+    away: Arc<AwayBoard>,
+    /// Keywords team members watch, alerted on by the filter stage
+    subscriptions: Arc<Subscriptions>,
+    /// Files Jira issues for denied risky authorizations and overdue prompts, when configured
+    jira: Arc<RwLock<Option<Arc<JiraSink>>>>,
+}
+
+/// Outcome of sending a message to a group of sinks.
+#[derive(Default)]
+struct SinkRound {
+    first_reply_to: Option<String>,
+    delivered: bool,
+    failures: Vec<String>,
 }
 
 impl BroadcastManager {
@@ -42,14 +131,139 @@ impl BroadcastManager {
             viewers: Arc::new(RwLock::new(HashMap::new())),
             channel_subscriptions: Arc::new(RwLock::new(HashMap::new())),
             notification_sinks: Arc::new(RwLock::new(Vec::new())),
+            delivery_policy: Arc::new(RwLock::new(DeliveryPolicy::default())),
+            deliveries: Arc::new(DeliveryLog::default()),
+            on_call: Arc::new(RwLock::new(None)),
+            mirrors: Arc::new(RwLock::new(Vec::new())),
+            responses: Arc::new(ResponseLog::default()),
+            slas: Arc::new(SlaTracker::new()),
+            capabilities: Arc::new(RwLock::new(crate::server::capabilities::defaults())),
+            typing: Arc::new(TypingRelay::new()),
+            queue_positions: Arc::new(QueuePositions::new()),
+            away: Arc::new(AwayBoard::new()),
+            subscriptions: Arc::new(Subscriptions::new()),
+            jira: Arc::new(RwLock::new(None)),
         }
     }
 
+    /// Replace the advertised capabilities; the providers are filled in from the sinks.
+    pub async fn set_capabilities(&self, capabilities: Capabilities) {
+        *self.capabilities.write().await = capabilities;
+    }
+
+    /// The capabilities to advertise, listing the notification sinks as providers.
+    pub async fn capabilities(&self) -> Capabilities {
+        let mut capabilities = self.capabilities.read().await.clone();
+        capabilities.providers = self
+            .notification_sinks
+            .read()
+            .await
+            .iter()
+            .map(|sink| sink.name().to_string())
+            .collect();
+        capabilities
+    }
+
+    /// Prompts broadcast so far and how they were answered.
+    pub fn responses(&self) -> &ResponseLog {
+        &self.responses
+    }
+
+    /// Prompts followed against the response SLAs.
+    pub fn slas(&self) -> &SlaTracker {
+        &self.slas
+    }
+
+    /// Agent connections told where their queued prompts wait.
+    pub fn queue_positions(&self) -> &QueuePositions {
+        &self.queue_positions
+    }
+
+    /// Team members who are away.
+    pub fn away(&self) -> &Arc<AwayBoard> {
+        &self.away
+    }
+
+    /// Replace the mirroring rules.
+    /// Keywords team members watch
+    pub fn subscriptions(&self) -> &Arc<Subscriptions> {
+        &self.subscriptions
+    }
+
+    pub async fn set_mirror_rules(&self, rules: Vec<MirrorConfig>) {
+        *self.mirrors.write().await = rules;
+    }
+
+    /// Route prompts through `router` (or stop routing with `None`).
+    pub async fn set_on_call_router(&self, router: Option<OnCallRouter>) {
+        *self.on_call.write().await = router.map(Arc::new);
+    }
+
+    /// Escalate to Jira through `jira` (or stop with `None`).
+    pub async fn set_jira(&self, jira: Option<JiraSink>) {
+        *self.jira.write().await = jira.map(Arc::new);
+    }
+
+    /// Replace the retry/failover rules used for prompts.
+    pub async fn set_delivery_policy(&self, policy: DeliveryPolicy) {
+        *self.delivery_policy.write().await = policy;
+    }
+
+    /// Per-sink delivery results for `message_id` (empty if it was never sent to a sink).
+    pub fn delivery_status(&self, message_id: &Uuid) -> Vec<DeliveryReport> {
+        self.deliveries.get(message_id)
+    }
+
+    fn record_delivery(
+        &self,
+        message: &Message,
+        sink: &dyn NotificationSink,
+        status: DeliveryStatus,
+        attempts: u32,
+        error: Option<String>,
+    ) {
+        self.deliveries.record(
+            message.id,
+            DeliveryReport {
+                provider: sink.name().to_string(),
+                status,
+                attempts,
+                error,
+                at: Utc::now(),
+            },
+        );
+    }
+
     /// Add a notification sink (e.g. Telegram). Failures do not block other delivery.
     pub async fn add_notification_sink(&self, sink: Arc<dyn NotificationSink>) {
         self.notification_sinks.write().await.push(sink);
     }
 
+    pub fn typing(&self) -> &TypingRelay {
+        &self.typing
+    }
+
+    /// Pass on the draft of an answer to `prompt_id`, throttled, to the connection that asked
+    /// it and to viewers of `channel` that accepted typing frames.
+    pub async fn broadcast_typing(&self, prompt_id: Uuid, channel: &str, draft: &str) {
+        let Some((event, asker)) = self.typing.update(prompt_id, channel, draft) else {
+            return;
+        };
+        let mut targets: HashSet<Uuid> = {
+            let channel_subs = self.channel_subscriptions.read().await;
+            [channel, "*"]
+                .iter()
+                .filter_map(|c| channel_subs.get(*c))
+                .flatten()
+                .copied()
+                .collect()
+        };
+        targets.extend(asker);
+        targets.retain(|id| self.typing.is_listener(id));
+        let frame = WsMessage::Text(event.to_frame().into());
+        self.send_to_viewers(targets, &frame).await;
+    }
+
     /// Add a new viewer connection
     pub async fn add_viewer(
         &self,
@@ -62,6 +276,7 @@ impl BroadcastManager {
             connection_type,
             subscribed_channels: HashSet::new(),
             sender,
+            sampler: None,
         };
 
         self.viewers.write().await.insert(connection_id, viewer);
@@ -70,6 +285,8 @@ impl BroadcastManager {
 
     /// Remove a viewer connection
     pub async fn remove_viewer(&self, connection_id: &Uuid) {
+        self.typing.remove_connection(connection_id);
+        self.queue_positions.remove_connection(connection_id);
         // Get the viewer before removing
         if let Some(viewer) = self.viewers.write().await.remove(connection_id) {
             // Remove from all channel subscriptions
@@ -137,11 +354,34 @@ impl BroadcastManager {
 
     /// Switch a connection to Viewer mode (called when browser sends hello frame)
     pub async fn set_viewer_mode(&self, connection_id: &Uuid) -> Result<(), String> {
+        self.set_connection_type(connection_id, ConnectionType::Viewer)
+            .await
+    }
+
+    pub async fn set_connection_type(
+        &self,
+        connection_id: &Uuid,
+        connection_type: ConnectionType,
+    ) -> Result<(), String> {
+        let mut viewers = self.viewers.write().await;
+        let viewer = viewers
+            .get_mut(connection_id)
+            .ok_or_else(|| format!("Viewer {} not found", connection_id))?;
+        viewer.connection_type = connection_type;
+        Ok(())
+    }
+
+    /// Sample the messages broadcast to a viewer, or with `None` send it everything again.
+    pub async fn set_sampling(
+        &self,
+        connection_id: &Uuid,
+        sampling: Option<Sampling>,
+    ) -> Result<(), String> {
         let mut viewers = self.viewers.write().await;
         let viewer = viewers
             .get_mut(connection_id)
             .ok_or_else(|| format!("Viewer {} not found", connection_id))?;
-        viewer.connection_type = ConnectionType::Viewer;
+        viewer.sampler = sampling.map(|s| Arc::new(Sampler::new(s)));
         Ok(())
     }
 
@@ -177,6 +417,48 @@ impl BroadcastManager {
         self.broadcast_message_internal(message, false).await;
     }
 
+    /// Broadcast a message to viewers and to the notification sink named `provider` only.
+    /// Returns false when no such sink is running.
+    pub async fn broadcast_to_provider(&self, message: &Message, provider: &str) -> bool {
+        self.broadcast_message_internal(message, false).await;
+        let sink = self
+            .notification_sinks
+            .read()
+            .await
+            .iter()
+            .find(|sink| sink.name() == provider)
+            .cloned();
+        let Some(sink) = sink else {
+            return false;
+        };
+        match sink.send(message).await {
+            Ok(()) => self.record_delivery(message, &*sink, DeliveryStatus::Delivered, 1, None),
+            Err(e) => {
+                tracing::error!(provider = sink.name(), error = %e, "provider delivery failed");
+                self.record_delivery(
+                    message,
+                    &*sink,
+                    DeliveryStatus::Failed,
+                    1,
+                    Some(e.to_string()),
+                );
+            }
+        }
+        true
+    }
+
+    /// Pass a status pulse to viewers and to the sinks' transient indicators. Nothing is
+    /// recorded: failures are only logged and no delivery reports are kept.
+    pub async fn broadcast_status(&self, message: &Message) {
+        self.broadcast_message_internal(message, false).await;
+        let sinks: Vec<Arc<dyn NotificationSink>> = self.notification_sinks.read().await.clone();
+        for sink in sinks.iter().filter(|sink| sink.handles(message)) {
+            if let Err(e) = sink.send_status(message).await {
+                tracing::debug!(provider = sink.name(), error = %e, "status pulse failed");
+            }
+        }
+    }
+
     /// Internal broadcast implementation with optional notification sink delivery
     async fn broadcast_message_internal(
         &self,
@@ -184,6 +466,11 @@ impl BroadcastManager {
         include_notification_sinks: bool,
     ) {
         let channel = &message.channel;
+        self.responses.observe(message);
+        self.slas.observe(message);
+        if let Some(jira) = self.jira.read().await.as_ref() {
+            jira.observe(message);
+        }
 
         // Prepare JSON message
         let json_message = match serde_json::to_string(message) {
@@ -207,62 +494,371 @@ impl BroadcastManager {
             all_subscribers.extend(all_channel_subs);
         }
 
-        // Send to all subscribers
-        let viewers = self.viewers.read().await;
-        for connection_id in all_subscribers {
-            if let Some(viewer) = viewers.get(&connection_id) {
-                if let Err(e) = viewer.sender.send(ws_message.clone()) {
-                    eprintln!("Failed to send message to viewer {}: {}", connection_id, e);
-                }
-            }
+        // Send to all subscribers whose sampling lets the message through
+        let all_subscribers = self.sampled(all_subscribers, message).await;
+        self.send_to_viewers(all_subscribers, &ws_message).await;
+
+        // Mirrored copies go to the aggregate channels' own subscribers; "*" viewers already
+        // have the original.
+        for copy in self.mirror_copies(message).await {
+            let Ok(json) = serde_json::to_string(&copy) else {
+                continue;
+            };
+            let subscribers = {
+                let channel_subs = self.channel_subscriptions.read().await;
+                channel_subs.get(&copy.channel).cloned().unwrap_or_default()
+            };
+            let subscribers = self.sampled(subscribers, &copy).await;
+            self.send_to_viewers(subscribers, &WsMessage::Text(json.into()))
+                .await;
         }
 
+        self.alert_watchers(message).await;
+
         // Send to notification sinks (e.g. Telegram). Per FR-011: log on failure.
         if include_notification_sinks {
             let sinks: Vec<Arc<dyn NotificationSink>> =
                 self.notification_sinks.read().await.clone();
             let msg_type = message_content_type(message);
             for sink in sinks {
-                if let Err(e) = sink.send(message).await {
-                    tracing::error!(
-                        provider = sink.name(),
-                        message_type = %msg_type,
-                        error = %e,
-                        "provider delivery failed"
-                    );
+                if !sink.handles(message) {
+                    self.record_delivery(message, &*sink, DeliveryStatus::Skipped, 0, None);
+                    continue;
+                }
+                match sink.send(message).await {
+                    Ok(()) => {
+                        self.record_delivery(message, &*sink, DeliveryStatus::Delivered, 1, None)
+                    }
+                    Err(e) => {
+                        tracing::error!(
+                            provider = sink.name(),
+                            message_type = %msg_type,
+                            error = %e,
+                            "provider delivery failed"
+                        );
+                        self.record_delivery(
+                            message,
+                            &*sink,
+                            DeliveryStatus::Failed,
+                            1,
+                            Some(e.to_string()),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Filter stage: send a high-priority alert to each team member whose watched keywords
+    /// `message` contains, on their own providers, wherever they otherwise get messages from.
+    async fn alert_watchers(&self, message: &Message) {
+        let watchers = self.subscriptions.watchers(message);
+        if watchers.is_empty() {
+            return;
+        }
+        let now = Utc::now();
+        let sinks: Vec<Arc<dyn NotificationSink>> = self.notification_sinks.read().await.clone();
+        for watcher in watchers {
+            if self.away.is_away(&watcher.member, now) {
+                continue;
+            }
+            let alert = subscriptions::alert(message, &watcher);
+            tracing::info!(message_id = %message.id, member = %watcher.member, keyword = %watcher.keyword, "keyword alert");
+            for sink in sinks
+                .iter()
+                .filter(|sink| watcher.providers.iter().any(|p| p == sink.name()))
+            {
+                match sink.send(&alert).await {
+                    Ok(()) => {
+                        self.record_delivery(&alert, &**sink, DeliveryStatus::Delivered, 1, None)
+                    }
+                    Err(e) => {
+                        tracing::warn!(provider = sink.name(), error = %e, "keyword alert failed");
+                        self.record_delivery(
+                            &alert,
+                            &**sink,
+                            DeliveryStatus::Failed,
+                            1,
+                            Some(e.to_string()),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// `connection_ids` without the viewers whose sampling drops `message`.
+    async fn sampled(&self, mut connection_ids: HashSet<Uuid>, message: &Message) -> HashSet<Uuid> {
+        let viewers = self.viewers.read().await;
+        connection_ids.retain(|id| {
+            viewers
+                .get(id)
+                .and_then(|viewer| viewer.sampler.as_ref())
+                .is_none_or(|sampler| sampler.admits(message))
+        });
+        connection_ids
+    }
+
+    /// Send `message` to one connection only, without recording or relaying it.
+    pub async fn send_to_connection(&self, connection_id: Uuid, message: &Message) {
+        match serde_json::to_string(message) {
+            Ok(json) => {
+                let ws_message = WsMessage::Text(json.into());
+                self.send_to_viewers(HashSet::from([connection_id]), &ws_message)
+                    .await;
+            }
+            Err(e) => eprintln!("Failed to serialize message for {}: {}", connection_id, e),
+        }
+    }
+
+    async fn send_to_viewers(&self, connection_ids: HashSet<Uuid>, ws_message: &WsMessage) {
+        let viewers = self.viewers.read().await;
+        for connection_id in connection_ids {
+            if let Some(viewer) = viewers.get(&connection_id) {
+                if let Err(e) = viewer.sender.send(ws_message.clone()) {
+                    eprintln!("Failed to send message to viewer {}: {}", connection_id, e);
                 }
             }
         }
     }
 
-    /// Send message to notification sinks and return the first reply-to ID received.
+    /// Copies of `message` for the aggregate channels mirroring its channel, one per channel.
+    /// Copies get their own id and `metadata.mirrored_from`, and are never mirrored again, so
+    /// rules such as `a -> b` plus `b -> a`, or `*` -> `audit`, cannot loop.
+    async fn mirror_copies(&self, message: &Message) -> Vec<Message> {
+        let is_copy = message
+            .metadata
+            .as_ref()
+            .is_some_and(|m| m.get("mirrored_from").is_some());
+        if is_copy {
+            return Vec::new();
+        }
+        let mut targets: Vec<&str> = Vec::new();
+        let rules = self.mirrors.read().await;
+        for rule in rules.iter() {
+            if rule.to != message.channel
+                && matches_pattern(&rule.from, &message.channel)
+                && !targets.contains(&rule.to.as_str())
+            {
+                targets.push(&rule.to);
+            }
+        }
+        targets
+            .into_iter()
+            .map(|target| {
+                let mut copy = message.clone();
+                copy.id = Uuid::new_v4();
+                copy.channel = target.to_string();
+                let mut metadata = match copy.metadata.take() {
+                    Some(serde_json::Value::Object(map)) => map,
+                    _ => serde_json::Map::new(),
+                };
+                metadata.insert(
+                    "mirrored_from".to_string(),
+                    serde_json::json!({ "channel": message.channel, "message_id": message.id }),
+                );
+                copy.metadata = Some(serde_json::Value::Object(metadata));
+                copy
+            })
+            .collect()
+    }
+
+    /// Send a prompt to the notification sinks and return the first reply-to ID received.
     /// This is used for interactive messages (Question/Authorization/Navigate) to
     /// enable reply-to matching when users reply to Telegram messages.
+    ///
+    /// Failed sends are retried with backoff per the [`DeliveryPolicy`]. In failover mode sinks
+    /// are tried in policy order until one delivers; otherwise every sink gets the prompt. With
+    /// an [`OnCallRouter`], the on-call person's providers are tried first, then the next in
+    /// line's, then the rest. When no sink delivers, viewers are alerted so the prompt is not
+    /// silently lost.
     pub async fn send_to_notification_sinks_and_get_reply_to_id(
         &self,
         message: &Message,
     ) -> Option<String> {
-        let sinks: Vec<Arc<dyn NotificationSink>> = self.notification_sinks.read().await.clone();
+        let policy = self.delivery_policy.read().await.clone();
+        let mut sinks: Vec<Arc<dyn NotificationSink>> =
+            self.notification_sinks.read().await.clone();
+        if policy.failover {
+            sinks.sort_by_key(|sink| policy.rank(sink.name()));
+        }
+
+        let router = self.on_call.read().await.clone();
+        let route = router.and_then(|router| router.route(message, Utc::now()));
+        let Some(route) = route else {
+            let round = self.send_to_sinks(message, sinks, &policy).await;
+            return self.finish_delivery(message, round).await;
+        };
 
+        // On-call first, then the next in line, then everyone else.
+        let message = &route.message;
+        let mut round = SinkRound::default();
+        for tier in &route.tiers {
+            let (tier_sinks, rest): (Vec<_>, Vec<_>) = sinks
+                .into_iter()
+                .partition(|sink| tier.providers.iter().any(|p| p == sink.name()));
+            sinks = rest;
+            if tier_sinks.is_empty() {
+                continue;
+            }
+            tracing::info!(message_id = %message.id, person = %tier.person, "routing prompt to on-call");
+            round.absorb(self.send_to_sinks(message, tier_sinks, &policy).await);
+            if round.delivered {
+                return self.finish_delivery(message, round).await;
+            }
+        }
+        if !sinks.is_empty() {
+            tracing::warn!(message_id = %message.id, "on-call not reached, escalating to all providers");
+            round.absorb(self.send_to_sinks(message, sinks, &policy).await);
+        }
+        self.finish_delivery(message, round).await
+    }
+
+    /// Names of the running notification sinks.
+    pub async fn sink_names(&self) -> Vec<String> {
+        self.notification_sinks
+            .read()
+            .await
+            .iter()
+            .map(|sink| sink.name().to_string())
+            .collect()
+    }
+
+    /// Like [`Self::send_to_notification_sinks_and_get_reply_to_id`], but only to the sinks
+    /// named in `providers` (`include`) or to all others (`!include`), without on-call routing.
+    pub async fn send_to_providers_and_get_reply_to_id(
+        &self,
+        message: &Message,
+        providers: &[String],
+        include: bool,
+    ) -> Option<String> {
+        let policy = self.delivery_policy.read().await.clone();
+        let mut sinks: Vec<Arc<dyn NotificationSink>> = self
+            .notification_sinks
+            .read()
+            .await
+            .iter()
+            .filter(|sink| providers.iter().any(|p| p == sink.name()) == include)
+            .cloned()
+            .collect();
+        if policy.failover {
+            sinks.sort_by_key(|sink| policy.rank(sink.name()));
+        }
+        let round = self.send_to_sinks(message, sinks, &policy).await;
+        self.finish_delivery(message, round).await
+    }
+
+    /// Send `message` to `sinks` (in order, stopping after the first delivery in failover
+    /// mode), recording each result.
+    async fn send_to_sinks(
+        &self,
+        message: &Message,
+        sinks: Vec<Arc<dyn NotificationSink>>,
+        policy: &DeliveryPolicy,
+    ) -> SinkRound {
+        let mut first_reply_to = None;
+        let mut delivered = false;
+        let mut failures = Vec::new();
         for sink in sinks {
-            match sink.send_and_get_reply_to_id(message).await {
-                Ok(Some(reply_to_id)) => {
-                    return Some(reply_to_id);
-                }
-                Ok(None) => {
-                    // Sink doesn't support reply-to IDs, continue to next sink
+            if policy.failover && delivered {
+                break;
+            }
+            if !sink.handles(message) {
+                self.record_delivery(message, &*sink, DeliveryStatus::Skipped, 0, None);
+                continue;
+            }
+            let (result, attempts) = Self::send_with_retry(&*sink, message, policy).await;
+            match result {
+                Ok(reply_to_id) => {
+                    // Sinks without reply-to IDs (e.g. SMS matching by prompt code) return None
+                    if first_reply_to.is_none() {
+                        first_reply_to = reply_to_id;
+                    }
+                    delivered = true;
+                    self.record_delivery(
+                        message,
+                        &*sink,
+                        DeliveryStatus::Delivered,
+                        attempts,
+                        None,
+                    );
                 }
                 Err(e) => {
                     tracing::error!(
                         provider = sink.name(),
+                        attempts,
                         error = %e,
                         "Failed to send message and get reply-to ID"
                     );
+                    failures.push(format!("{}: {}", sink.name(), e));
+                    self.record_delivery(
+                        message,
+                        &*sink,
+                        DeliveryStatus::Failed,
+                        attempts,
+                        Some(e.to_string()),
+                    );
                 }
             }
         }
+        SinkRound {
+            first_reply_to,
+            delivered,
+            failures,
+        }
+    }
 
-        None
+    /// Alert viewers when nothing delivered; returns the reply-to ID of the first delivery.
+    async fn finish_delivery(&self, message: &Message, round: SinkRound) -> Option<String> {
+        let SinkRound {
+            first_reply_to,
+            delivered,
+            failures,
+        } = round;
+        if !delivered && !failures.is_empty() {
+            tracing::error!(message_id = %message.id, "prompt not delivered to any provider");
+            let alert = Message::new(
+                message.channel.clone(),
+                SenderType::Agent,
+                MessageContent::Notification {
+                    text: format!(
+                        "Prompt {} could not be delivered ({}); answer it here or via the API",
+                        message.id,
+                        failures.join("; ")
+                    ),
+                    priority: NotificationPriority::Urgent,
+                },
+            );
+            self.broadcast_to_viewers_only(&alert).await;
+        }
+
+        first_reply_to
+    }
+
+    /// Send to one sink, retrying failures with backoff. Returns the result and attempt count.
+    async fn send_with_retry(
+        sink: &dyn NotificationSink,
+        message: &Message,
+        policy: &DeliveryPolicy,
+    ) -> (Result<Option<String>, Box<dyn Error + Send + Sync>>, u32) {
+        let mut attempt = 1;
+        loop {
+            match sink.send_and_get_reply_to_id(message).await {
+                Err(e) if attempt <= policy.retries => {
+                    let delay = policy.delay_before(attempt);
+                    tracing::warn!(
+                        provider = sink.name(),
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %e,
+                        "prompt delivery failed, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return (result, attempt),
+            }
+        }
     }
 
     /// Get statistics about viewer connections
@@ -293,6 +889,16 @@ impl BroadcastManager {
     }
 }
 
+impl SinkRound {
+    fn absorb(&mut self, other: SinkRound) {
+        if self.first_reply_to.is_none() {
+            self.first_reply_to = other.first_reply_to;
+        }
+        self.delivered |= other.delivered;
+        self.failures.extend(other.failures);
+    }
+}
+
 impl Default for BroadcastManager {
     fn default() -> Self {
         Self::new()
@@ -310,13 +916,15 @@ pub struct BroadcastStats {
 
 use serde::{Deserialize, Serialize};
 
-fn message_content_type(message: &Message) -> &'static str {
+pub(crate) fn message_content_type(message: &Message) -> &'static str {
     match &message.content {
         MessageContent::Decision { .. } => "decision",
         MessageContent::Authorization { .. } => "authorization",
         MessageContent::Notification { .. } => "notification",
         MessageContent::Response { .. } => "response",
         MessageContent::Navigate { .. } => "navigate",
+        MessageContent::Chat { .. } => "chat",
+        MessageContent::Status { .. } => "status",
         _ => "other",
     }
 }
@@ -0,0 +1,106 @@
+//! Server capability discovery
+//!
+//! `GET /api/capabilities` and the WebSocket frame `{"type": "capabilities"}` tell clients
+//! which providers are running, which content types are accepted and which limits apply. The
+//! limits are fixed when the server starts; providers are read from the running sinks each
+//! time.
+
+use crate::server::attachments::MAX_ATTACHMENT_BYTES;
+use crate::server::history::MAX_MESSAGES_PER_CHANNEL;
+use crate::state::AiloopAppState;
+use ailoop_core::models::capabilities::{CONTENT_TYPES, RESPONSE_TYPES};
+use ailoop_core::models::{Capabilities, ServerLimits, TYPING_FEATURE};
+use ailoop_core::transport::chunking::{MAX_CHUNKED_MESSAGE_BYTES, MAX_FRAME_BYTES};
+use serde::Serialize;
+
+/// Capabilities of a server started without configuration, before any provider.
+pub fn defaults() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        providers: Vec::new(),
+        content_types: CONTENT_TYPES.iter().map(|t| t.to_string()).collect(),
+        response_types: RESPONSE_TYPES.iter().map(|t| t.to_string()).collect(),
+        signed_responses: false,
+        limits: ServerLimits {
+            max_attachment_bytes: MAX_ATTACHMENT_BYTES,
+            max_frame_bytes: MAX_FRAME_BYTES,
+            max_chunked_message_bytes: MAX_CHUNKED_MESSAGE_BYTES,
+            max_history_per_channel: MAX_MESSAGES_PER_CHANNEL,
+            ..Default::default()
+        },
+        typing: None,
+    }
+}
+
+/// Capabilities from `state`'s configuration, quotas and signer; providers are left empty.
+pub fn from_state(state: &AiloopAppState) -> Capabilities {
+    let mut capabilities = defaults();
+    let quota = state.usage.quota();
+    capabilities.signed_responses = state.signer.is_some();
+    capabilities.limits.max_message_size = state
+        .provider_config
+        .as_ref()
+        .map(|cfg| cfg.max_message_size);
+    capabilities.limits.max_messages_per_hour = quota.max_messages_per_hour;
+    capabilities.limits.max_prompts_per_hour = quota.max_prompts_per_hour;
+    capabilities.typing = state
+        .provider_config
+        .as_ref()
+        .and_then(|cfg| cfg.typing.capability());
+    capabilities
+}
+
+/// Whether a WebSocket frame asks for the capabilities.
+pub fn is_request(frame: &serde_json::Value) -> bool {
+    frame.get("type").and_then(|t| t.as_str()) == Some("capabilities")
+}
+
+/// Whether a capabilities request lists `typing` in `accept`, asking for typing frames.
+pub fn accepts_typing(frame: &serde_json::Value) -> bool {
+    frame["accept"]
+        .as_array()
+        .is_some_and(|features| features.iter().any(|f| f == TYPING_FEATURE))
+}
+
+/// The WebSocket reply: the capabilities tagged with `"type": "capabilities"`.
+pub fn reply(capabilities: Capabilities) -> String {
+    #[derive(Serialize)]
+    struct Reply {
+        r#type: &'static str,
+        #[serde(flatten)]
+        capabilities: Capabilities,
+    }
+    serde_json::to_string(&Reply {
+        r#type: "capabilities",
+        capabilities,
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reply_is_tagged_and_lists_content_types() {
+        let frame: serde_json::Value = serde_json::from_str(&reply(defaults())).unwrap();
+        assert!(is_request(&frame));
+        assert!(frame["content_types"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|t| t == "decision"));
+        assert_eq!(
+            frame["limits"]["max_attachment_bytes"],
+            MAX_ATTACHMENT_BYTES
+        );
+        assert!(frame["limits"].get("max_messages_per_hour").is_none());
+        assert!(!is_request(&serde_json::json!({ "subscribe": "*" })));
+        assert!(frame.get("typing").is_none());
+        assert!(accepts_typing(&serde_json::json!({
+            "type": "capabilities",
+            "accept": ["typing"],
+        })));
+        assert!(!accepts_typing(&frame));
+    }
+}
@@ -0,0 +1,84 @@
+//! Claiming pending prompts between several responders
+//!
+//! Pending prompts are one shared queue. A responder takes one with `/claim` in a provider
+//! (replying to the prompt, or bare for the oldest unclaimed one of the chat's channel),
+//! `ailoop queue claim <id>` or `POST /api/v1/messages/{id}/claim`, and gives it back with
+//! `/release`. Each change is announced on the prompt's channel, so the other responders see
+//! who is on it. The claims themselves live in the
+//! [`PendingPromptRegistry`](crate::server::providers::PendingPromptRegistry).
+
+use crate::server::broadcast::BroadcastManager;
+use crate::server::history::MessageHistory;
+use crate::server::providers::Claim;
+use ailoop_core::models::{Message, MessageContent, NotificationPriority, SenderType};
+use uuid::Uuid;
+
+/// A claim command typed as a provider reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ClaimCommand {
+    Claim,
+    Release,
+}
+
+/// Parse `/claim` or `/release` (any case, surrounding space ignored).
+pub(crate) fn parse_command(answer: &str) -> Option<ClaimCommand> {
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "/claim" => Some(ClaimCommand::Claim),
+        "/release" | "/unclaim" => Some(ClaimCommand::Release),
+        _ => None,
+    }
+}
+
+/// System notification telling the channel that `prompt_id` was claimed or released.
+pub(crate) fn notice(
+    channel: &str,
+    prompt_id: Uuid,
+    label: &str,
+    claim: &Claim,
+    claimed: bool,
+) -> Message {
+    let text = if claimed {
+        format!("{} is answering \"{}\"", claim.responder, label)
+    } else {
+        format!(
+            "{} released \"{}\"; it is open again",
+            claim.responder, label
+        )
+    };
+    let mut notice = Message::new(
+        channel.to_string(),
+        SenderType::System,
+        MessageContent::Notification {
+            text,
+            priority: NotificationPriority::Low,
+        },
+    );
+    notice.metadata = Some(serde_json::json!({
+        "claimed_prompt": prompt_id,
+        "claimed_by": claimed.then_some(&claim.responder),
+    }));
+    notice
+}
+
+/// Record `notice` and send it to the channel's viewers and providers.
+pub(crate) async fn announce(
+    history: &MessageHistory,
+    broadcast: &BroadcastManager,
+    notice: Message,
+) {
+    history.add_message(&notice.channel, notice.clone()).await;
+    broadcast.broadcast_message(&notice).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_claim_commands() {
+        assert_eq!(parse_command(" /Claim "), Some(ClaimCommand::Claim));
+        assert_eq!(parse_command("/release"), Some(ClaimCommand::Release));
+        assert_eq!(parse_command("claim"), None);
+        assert_eq!(parse_command("/claim it"), None);
+    }
+}
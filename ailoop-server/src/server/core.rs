@@ -1,11 +1,35 @@
 //! Main server integration for ailoop
 
-#[cfg(feature = "telegram")]
-use crate::server::providers::ReplySource;
-use crate::server::providers::{resolve_effective_timeout, PendingPromptRegistry, PromptType};
+use crate::server::assignment;
+use crate::server::attachments::{AttachmentStore, MessageAttachment};
+use crate::server::broadcast::ConnectionType;
+use crate::server::claims;
+use crate::server::echo;
+use crate::server::events;
+use crate::server::history::is_agent_notification;
+use crate::server::hooks::{HookOutcome, MessageHooks};
+use crate::server::jira::JiraSink;
+use crate::server::loops::{LoopGuard, Origin};
+use crate::server::oncall::OnCallRouter;
+use crate::server::providers::{
+    reply_reason, DeliveryPolicy, PendingPromptRegistry, PromptType, Provider, ProviderSink,
+};
+use crate::server::reads;
+use crate::server::snooze;
+use crate::server::subscriptions::searchable_text;
+use crate::server::terminal::{self, Navigation, RawMode};
+use crate::server::verification;
+use crate::server::watchdog;
 use ailoop_core::channel::ChannelIsolation;
-use ailoop_core::models::{Configuration, Message, MessageContent, ResponseType};
+use ailoop_core::models::{
+    AssignmentConfig, Configuration, EchoConfig, InvalidInputPolicy, Message, MessageContent,
+    PromptKind, QueueBoost, ResponseType, SenderType, TimeoutPolicy,
+};
+use ailoop_core::services::logging::log_security_event;
+use ailoop_core::services::signing::{ResponseSigner, OPERATOR_RESPONDER};
 use ailoop_core::terminal::countdown::CountdownRenderer;
+use ailoop_core::terminal::image::{ImageProtocol, MAX_THUMBNAIL_BYTES, THUMBNAIL_COLUMNS};
+use ailoop_core::transport::chunking::{self, Chunk, ChunkReassembler, MAX_FRAME_BYTES};
 use anyhow::{Context, Result};
 use axum::extract::ws::{Message as WsMessage, WebSocket, WebSocketUpgrade};
 use axum::extract::State;
@@ -15,9 +39,9 @@ use axum::response::Html;
 use axum::response::IntoResponse;
 use crossterm::{
     event::{self, Event, KeyCode, KeyEventKind},
-    terminal::{disable_raw_mode, enable_raw_mode},
+    terminal::is_raw_mode_enabled,
 };
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
 use std::future::Future;
 use std::io::{self, IsTerminal, Write};
 use std::sync::{
@@ -29,9 +53,73 @@ use tokio_util::sync::CancellationToken;
 
 pub use crate::state::AiloopAppState;
 
+/// Whether prompts are echoed to (and answered from) the server's terminal. Cleared in stdio
+/// mode, where stdin/stdout carry the protocol.
+static CONSOLE_OUTPUT: AtomicBool = AtomicBool::new(true);
+
+/// How long the server terminal waits for the operator to answer a chat line.
+const CHAT_REPLY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// `println!` that is silenced while console output is off.
+macro_rules! console_println {
+    ($($arg:tt)*) => {
+        if CONSOLE_OUTPUT.load(Ordering::Relaxed) {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// `print!` that is silenced while console output is off.
+macro_rules! console_print {
+    ($($arg:tt)*) => {
+        if CONSOLE_OUTPUT.load(Ordering::Relaxed) {
+            print!($($arg)*);
+        }
+    };
+}
+
+/// Turn terminal prompting and echo on or off for this process.
+pub(crate) fn set_console_output(enabled: bool) {
+    CONSOLE_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+/// Called with the terminal draft after each edit.
+type DraftHook = Box<dyn FnMut(&str) + Send>;
+
+/// Whether prompts can be answered interactively on this process's terminal.
+fn use_terminal() -> bool {
+    CONSOLE_OUTPUT.load(Ordering::Relaxed)
+        && io::stdin().is_terminal()
+        && io::stdout().is_terminal()
+}
+
 /// Backward-compatible type alias kept for existing callers.
 pub type AppState = AiloopAppState;
 
+/// How a prompt left the queue
+#[derive(Debug, Clone)]
+pub(crate) enum PromptResult {
+    Answered(ResponseType),
+    /// Set aside at the terminal; present it again after this long.
+    Snoozed(Duration),
+    /// Left unanswered at the terminal for another prompt, or for a history search.
+    Moved(Navigation),
+}
+
+/// Input at a yes/no terminal prompt
+enum YesNoInput {
+    /// The decision and the reason typed after it (`n: too risky`).
+    Answer(ResponseType, Option<String>),
+    Snooze(Duration),
+    Move(Navigation),
+}
+
+/// Input at a terminal prompt that takes a line of text
+enum LineInput {
+    Line(String),
+    Move(Navigation),
+}
+
 /// Main ailoop server builder (convenience wrapper over the composable library API).
 pub struct AiloopServer {
     host: String,
@@ -108,6 +196,7 @@ impl AiloopServer {
             web: self.state.web,
             auth: None,
             cors: None,
+            ip_filter: None,
         };
 
         let state_arc = Arc::new(self.state);
@@ -132,9 +221,12 @@ impl AiloopServer {
             token_for_shutdown.cancel();
         };
 
-        axum::serve(listener, built_router)
-            .with_graceful_shutdown(shutdown_future)
-            .await?;
+        axum::serve(
+            listener,
+            built_router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown_future)
+        .await?;
 
         token.cancel();
         let _ = task_handle.await;
@@ -143,80 +235,244 @@ impl AiloopServer {
         Ok(())
     }
 
-    /// Handle a single WebSocket connection upgraded by Axum
+    /// Handle a single WebSocket connection upgraded by Axum.
+    ///
+    /// `auth` is the identity resolved by the auth middleware (`None` when auth is off), and
+    /// `declared` the connection type given in the handshake, already checked against it.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn handle_ws_connection_inner(
         ws: WebSocket,
         channel_manager: Arc<ChannelIsolation>,
         default_channel: String,
         message_history: Arc<crate::server::history::MessageHistory>,
         broadcast_manager: Arc<crate::server::broadcast::BroadcastManager>,
+        pending_registry: Arc<PendingPromptRegistry>,
+        loops: Arc<LoopGuard>,
+        usage: Arc<crate::server::usage::UsageTracker>,
+        hooks: Arc<MessageHooks>,
+        auth: Option<crate::config::AuthIdentity>,
+        declared: Option<ConnectionType>,
+        strict: bool,
     ) {
-        let (mut ws_sender, mut ws_receiver) = ws.split();
+        let (ws_sender, ws_receiver) = ws.split();
+        // Read text frames until the peer closes or the socket fails.
+        let incoming = ws_receiver
+            .take_while(|msg| {
+                let open = match msg {
+                    Ok(WsMessage::Close(_)) => false,
+                    Ok(_) => true,
+                    Err(e) => {
+                        tracing::warn!("WebSocket error: {}", e);
+                        false
+                    }
+                };
+                futures_util::future::ready(open)
+            })
+            .filter_map(|msg| {
+                futures_util::future::ready(match msg {
+                    Ok(WsMessage::Text(text)) => Some(text.to_string()),
+                    _ => None,
+                })
+            });
+        Self::handle_connection(
+            incoming,
+            ws_sender,
+            channel_manager,
+            default_channel,
+            message_history,
+            broadcast_manager,
+            pending_registry,
+            loops,
+            usage,
+            hooks,
+            auth,
+            declared,
+            strict,
+        )
+        .await;
+    }
+
+    /// Serve one client connection, independent of the listener it arrived on.
+    ///
+    /// `incoming` yields the peer's text frames until it disconnects; frames addressed to the
+    /// peer (responses, broadcasts, history replay) are written to `outgoing`.
+    /// Viewer-role connections may only subscribe; agent messages they send are dropped.
+    /// Agent messages are counted against the caller's usage quota, then run through the
+    /// inbound hook. With `strict`, messages failing schema validation are answered with a
+    /// cancelled response listing the problems instead of being accepted or dropped.
+    ///
+    /// A connection that declared its type (`declared`) gets a protocol error frame for
+    /// anything that type may not send: viewers send nothing, agents may not answer prompts
+    /// and responders may only answer them. Answers are checked by `loops` for self-answers
+    /// and answer loops.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn handle_connection<I, O>(
+        incoming: I,
+        outgoing: O,
+        channel_manager: Arc<ChannelIsolation>,
+        default_channel: String,
+        message_history: Arc<crate::server::history::MessageHistory>,
+        broadcast_manager: Arc<crate::server::broadcast::BroadcastManager>,
+        pending_registry: Arc<PendingPromptRegistry>,
+        loops: Arc<LoopGuard>,
+        usage: Arc<crate::server::usage::UsageTracker>,
+        hooks: Arc<MessageHooks>,
+        auth: Option<crate::config::AuthIdentity>,
+        declared: Option<ConnectionType>,
+        strict: bool,
+    ) where
+        I: Stream<Item = String>,
+        O: Sink<WsMessage> + Send + 'static,
+    {
+        let read_only = auth
+            .as_ref()
+            .is_some_and(|a| a.role == crate::config::Role::Viewer);
+        let auth_key = auth.as_ref().and_then(|a| a.usage_key());
+        let subject = auth.as_ref().and_then(|a| a.subject.clone());
+        futures_util::pin_mut!(incoming);
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<WsMessage>();
         let tx_replay = tx.clone();
+        let tx_quota = tx.clone();
         let mut channel_name = default_channel.clone();
 
-        // Connections start as Agent; the browser sends a hello frame to become a Viewer
-        let connection_type = crate::server::broadcast::ConnectionType::Agent;
+        // Undeclared connections start as Agent; the browser sends a hello frame to become a
+        // Viewer
+        let connection_type = declared.unwrap_or(ConnectionType::Agent);
         let connection_id = broadcast_manager.add_viewer(connection_type, tx).await;
 
-        // Track connection
-        channel_manager.add_connection(&channel_name);
+        // Track agent connections
+        let mut is_viewer = connection_type != ConnectionType::Agent;
+        if is_viewer {
+            Self::start_viewing(
+                &broadcast_manager,
+                &message_history,
+                &connection_id,
+                &tx_replay,
+            )
+            .await;
+        } else {
+            channel_manager.add_connection(&channel_name);
+        }
 
-        // Forward outgoing messages to the WebSocket
+        // Forward outgoing messages to the peer
         let forward_task = tokio::spawn(async move {
             let mut rx = rx;
+            let mut outgoing = Box::pin(outgoing);
             while let Some(msg) = rx.recv().await {
-                if SinkExt::send(&mut ws_sender, msg).await.is_err() {
+                if outgoing.send(msg).await.is_err() {
                     break;
                 }
             }
         });
 
         // Handle incoming messages
-        let mut is_viewer = false;
-        while let Some(msg) = ws_receiver.next().await {
-            let msg = match msg {
-                Ok(m) => m,
-                Err(e) => {
-                    tracing::warn!("WebSocket error: {}", e);
-                    break;
+        let mut chunks = ChunkReassembler::default();
+        while let Some(mut text) = incoming.next().await {
+            let mut frame = serde_json::from_str::<serde_json::Value>(&text).ok();
+            // A large message arrives in chunks; it is handled once the last one is in.
+            if frame.as_ref().is_some_and(chunking::is_chunk) {
+                let joined = serde_json::from_value::<Chunk>(frame.take().unwrap_or_default())
+                    .map_err(|e| format!("Invalid chunk: {}", e))
+                    .and_then(|chunk| chunks.push(chunk).map_err(|e| e.to_string()));
+                match joined {
+                    Ok(Some(message)) => {
+                        text = message;
+                        frame = serde_json::from_str(&text).ok();
+                    }
+                    Ok(None) => continue,
+                    Err(error) => {
+                        let _ = tx_quota.send(Self::protocol_error("chunk_rejected", &error));
+                        continue;
+                    }
                 }
-            };
-
-            if matches!(msg, WsMessage::Close(_)) {
-                break;
             }
-
-            let text = if let WsMessage::Text(t) = msg {
-                t
-            } else {
+            if frame
+                .as_ref()
+                .is_some_and(crate::server::capabilities::is_request)
+            {
+                if frame
+                    .as_ref()
+                    .is_some_and(crate::server::capabilities::accepts_typing)
+                {
+                    broadcast_manager.typing().accept(connection_id);
+                }
+                let capabilities = broadcast_manager.capabilities().await;
+                let reply = crate::server::capabilities::reply(capabilities);
+                let _ = tx_replay.send(WsMessage::Text(reply.into()));
                 continue;
-            };
+            }
 
-            // Check for viewer hello frame: {"subscribe": "*"} or {"subscribe": [...]}
-            if !is_viewer {
-                if let Ok(val) = serde_json::from_str::<serde_json::Value>(&text) {
-                    if val.get("subscribe").is_some() {
+            // Check for viewer hello frame: {"subscribe": "*"} or {"subscribe": [...]}, with
+            // an optional "sample" asking for less of the stream
+            if frame.as_ref().is_some_and(|f| f.get("subscribe").is_some()) {
+                let sample = frame
+                    .as_ref()
+                    .and_then(|f| f.get("sample"))
+                    .filter(|_| declared != Some(ConnectionType::Agent));
+                if let Some(sample) = sample {
+                    match crate::server::sampling::Sampling::parse(sample) {
+                        Ok(sampling) => {
+                            broadcast_manager
+                                .set_sampling(&connection_id, Some(sampling))
+                                .await
+                                .ok();
+                        }
+                        Err(e) => {
+                            let _ = tx_quota.send(Self::protocol_error("invalid_sampling", &e));
+                        }
+                    }
+                }
+                match declared {
+                    Some(ConnectionType::Agent) => {
+                        let _ = tx_quota.send(Self::protocol_error(
+                            "forbidden",
+                            "agent connections cannot subscribe; connect with ?connection=viewer",
+                        ));
+                    }
+                    None if !is_viewer => {
                         is_viewer = true;
                         broadcast_manager.set_viewer_mode(&connection_id).await.ok();
-                        broadcast_manager
-                            .subscribe_to_all(&connection_id)
-                            .await
-                            .ok();
-                        // Replay history so the page is not blank on connect
-                        let channels = message_history.get_channels().await;
-                        for ch in channels {
-                            let msgs = message_history.get_messages(&ch, Some(500)).await;
-                            for m in msgs {
-                                if let Ok(j) = serde_json::to_string(&m) {
-                                    let _ = tx_replay.send(WsMessage::Text(j.into()));
-                                }
-                            }
-                        }
+                        Self::start_viewing(
+                            &broadcast_manager,
+                            &message_history,
+                            &connection_id,
+                            &tx_replay,
+                        )
+                        .await;
+                    }
+                    // Declared viewers and responders already receive everything.
+                    _ => {}
+                }
+                continue;
+            }
+
+            match declared {
+                Some(ConnectionType::Viewer) => {
+                    let _ = tx_quota.send(Self::protocol_error(
+                        "read_only",
+                        "viewer connections are read-only",
+                    ));
+                    continue;
+                }
+                Some(ConnectionType::Responder) => {
+                    if let Some(frame) = frame.as_ref().filter(|f| f["type"] == "typing") {
+                        Self::relay_draft(&broadcast_manager, &pending_registry, frame).await;
                         continue;
                     }
+                    if let Err((code, error)) = Self::answer_prompt(
+                        &pending_registry,
+                        &loops,
+                        &text,
+                        connection_id,
+                        subject.clone(),
+                    )
+                    .await
+                    {
+                        let _ = tx_quota.send(Self::protocol_error(code, &error));
+                    }
+                    continue;
                 }
+                _ => {}
             }
 
             if is_viewer {
@@ -224,10 +480,138 @@ impl AiloopServer {
                 continue;
             }
 
+            if read_only {
+                tracing::warn!("Dropping message from read-only (viewer role) connection");
+                continue;
+            }
+
+            if frame.as_ref().is_some_and(QueueBoost::is_frame) {
+                if let Err((code, error)) = Self::boost_prompt(
+                    &channel_manager,
+                    &broadcast_manager,
+                    connection_id,
+                    frame.take().unwrap_or_default(),
+                ) {
+                    let _ = tx_quota.send(Self::protocol_error(code, &error));
+                }
+                report_queue_positions(&channel_manager, &broadcast_manager).await;
+                continue;
+            }
+
             // Agent path: parse and enqueue the message
-            match serde_json::from_str::<Message>(&text) {
-                Ok(message) => {
+            let parsed = if strict {
+                frame
+                    .ok_or_else(|| vec!["message: not valid JSON".to_string()])
+                    .and_then(ailoop_core::models::strict::parse_strict)
+                    .map_err(|errors| {
+                        let reply = Self::invalid_reply(&text, &channel_name, &errors);
+                        if let Ok(j) = serde_json::to_string(&reply) {
+                            let _ = tx_quota.send(WsMessage::Text(j.into()));
+                        }
+                        errors.join("; ")
+                    })
+            } else {
+                serde_json::from_str::<Message>(&text).map_err(|e| e.to_string())
+            };
+            match parsed {
+                Ok(message)
+                    if declared == Some(ConnectionType::Agent)
+                        && matches!(message.content, MessageContent::Response { .. }) =>
+                {
+                    log_security_event(
+                        "response_rejected",
+                        &message.channel,
+                        auth_key.as_deref(),
+                        "response sent on an agent connection",
+                    );
+                    let _ = tx_quota.send(Self::protocol_error(
+                        "forbidden",
+                        "agent connections may not answer prompts",
+                    ));
+                }
+                Ok(mut message) => {
+                    message_history.incidents().apply(&mut message);
+                    let origin =
+                        Origin::new(Some(connection_id), subject.as_deref(), Some(&message));
+                    if let (MessageContent::Response { .. }, Some(prompt_id)) =
+                        (&message.content, message.correlation_id)
+                    {
+                        if let Err(violation) = loops.check_answer(prompt_id, &origin).await {
+                            log_security_event(
+                                "response_rejected",
+                                &message.channel,
+                                auth_key.as_deref(),
+                                &violation.to_string(),
+                            );
+                            let _ = tx_quota.send(Self::protocol_error(
+                                violation.code(),
+                                &violation.to_string(),
+                            ));
+                            continue;
+                        }
+                    }
+                    let client = crate::server::usage::UsageTracker::client_key(
+                        auth_key.as_deref(),
+                        &message,
+                    );
+                    if let Err(exceeded) = usage.try_record(&client, &message).await {
+                        tracing::warn!("{}", exceeded);
+                        // Answer with a cancelled response so a waiting agent unblocks.
+                        let mut reply = Message::response(
+                            message.channel.clone(),
+                            MessageContent::Response {
+                                answer: Some(exceeded.to_string()),
+                                response_type: ResponseType::Cancelled,
+                            },
+                            message.id,
+                        );
+                        reply.metadata = Some(serde_json::json!({
+                            "error": "quota_exceeded",
+                            "quota": exceeded,
+                        }));
+                        if let Ok(j) = serde_json::to_string(&reply) {
+                            let _ = tx_quota.send(WsMessage::Text(j.into()));
+                        }
+                        continue;
+                    }
+                    let (message_id, message_channel) = (message.id, message.channel.clone());
+                    let message = match hooks.inbound(message).await {
+                        HookOutcome::Pass(message) => *message,
+                        HookOutcome::Blocked(reason) => {
+                            log_security_event(
+                                "message_blocked",
+                                &message_channel,
+                                auth_key.as_deref(),
+                                &reason,
+                            );
+                            let reply = Self::blocked_reply(message_channel, message_id, &reason);
+                            if let Ok(j) = serde_json::to_string(&reply) {
+                                let _ = tx_quota.send(WsMessage::Text(j.into()));
+                            }
+                            continue;
+                        }
+                    };
+
                     channel_name = message.channel.clone();
+                    if matches!(
+                        message.content,
+                        MessageContent::Decision { .. }
+                            | MessageContent::Authorization { .. }
+                            | MessageContent::Navigate { .. }
+                    ) {
+                        loops.record_prompt(message.id, origin).await;
+                        broadcast_manager
+                            .queue_positions()
+                            .record_prompt(message.id, connection_id);
+                    }
+                    if matches!(
+                        message.content,
+                        MessageContent::Decision { .. } | MessageContent::Chat { .. }
+                    ) {
+                        broadcast_manager
+                            .typing()
+                            .record_prompt(message.id, connection_id);
+                    }
 
                     let broadcast_clone = Arc::clone(&broadcast_manager);
                     let connection_id_clone = connection_id;
@@ -239,30 +623,13 @@ impl AiloopServer {
                         tracing::warn!("Failed to subscribe to channel: {}", e);
                     }
 
-                    let history_clone = Arc::clone(&message_history);
-                    let broadcast_clone2 = Arc::clone(&broadcast_manager);
-                    let channel_clone2 = channel_name.clone();
-                    let message_clone = message.clone();
-                    let is_interactive = matches!(
-                        message_clone.content,
-                        MessageContent::Decision { .. }
-                            | MessageContent::Authorization { .. }
-                            | MessageContent::Navigate { .. }
+                    Self::accept_agent_message(
+                        &channel_manager,
+                        &message_history,
+                        &broadcast_manager,
+                        message,
                     );
-                    tokio::spawn(async move {
-                        history_clone
-                            .add_message(&channel_clone2, message_clone.clone())
-                            .await;
-                        if is_interactive {
-                            broadcast_clone2
-                                .broadcast_to_viewers_only(&message_clone)
-                                .await;
-                        } else {
-                            broadcast_clone2.broadcast_message(&message_clone).await;
-                        }
-                    });
-
-                    channel_manager.enqueue_message(&channel_name, message);
+                    report_queue_positions(&channel_manager, &broadcast_manager).await;
                 }
                 Err(e) => {
                     tracing::warn!("Failed to parse message: {}", e);
@@ -278,6 +645,225 @@ impl AiloopServer {
         }
     }
 
+    /// Subscribe a viewer connection to every channel and replay history, so the page is not
+    /// blank on connect.
+    async fn start_viewing(
+        broadcast_manager: &crate::server::broadcast::BroadcastManager,
+        message_history: &crate::server::history::MessageHistory,
+        connection_id: &uuid::Uuid,
+        tx: &tokio::sync::mpsc::UnboundedSender<WsMessage>,
+    ) {
+        broadcast_manager.subscribe_to_all(connection_id).await.ok();
+        let channels = message_history.get_channels().await;
+        for ch in channels {
+            let msgs = message_history.get_messages(&ch, Some(500)).await;
+            for m in msgs {
+                if let Ok(j) = serde_json::to_string(&m) {
+                    let _ = tx.send(WsMessage::Text(j.into()));
+                }
+            }
+        }
+    }
+
+    /// Submit a `response` frame from a responder connection as the answer to the pending
+    /// prompt named by its `correlation_id`. Errors are a protocol error code and message.
+    async fn answer_prompt(
+        pending_registry: &PendingPromptRegistry,
+        loops: &LoopGuard,
+        text: &str,
+        connection_id: uuid::Uuid,
+        subject: Option<String>,
+    ) -> std::result::Result<(), (&'static str, String)> {
+        let message = serde_json::from_str::<Message>(text)
+            .map_err(|e| ("invalid_message", format!("Invalid message: {}", e)))?;
+        let origin = Origin::new(Some(connection_id), subject.as_deref(), Some(&message));
+        let (
+            Some(prompt_id),
+            MessageContent::Response {
+                answer,
+                response_type,
+            },
+        ) = (message.correlation_id, message.content)
+        else {
+            return Err((
+                "forbidden",
+                "responder connections may only send responses to prompts".to_string(),
+            ));
+        };
+        if let Err(violation) = loops.check_answer(prompt_id, &origin).await {
+            log_security_event(
+                "response_rejected",
+                &message.channel,
+                subject.as_deref(),
+                &violation.to_string(),
+            );
+            return Err((violation.code(), violation.to_string()));
+        }
+        if let Some(subject) = &subject {
+            log_security_event(
+                "prompt_answered",
+                &message.channel,
+                Some(subject),
+                &format!("message {}", prompt_id),
+            );
+        }
+        if let Some(claim) = pending_registry
+            .claimed_by_other(prompt_id, subject.as_deref())
+            .await
+        {
+            return Err((
+                "claimed",
+                format!("Prompt {} is claimed by {}", prompt_id, claim.responder),
+            ));
+        }
+        if response_type == ResponseType::AuthorizationApproved {
+            if let Some(level) = pending_registry.needs_terminal_approval(prompt_id).await {
+                return Err((
+                    "unverified",
+                    format!(
+                        "{}-risk approvals must be given at the server terminal",
+                        level
+                    ),
+                ));
+            }
+        }
+        if pending_registry
+            .submit_reply_for_message_as(prompt_id, answer, response_type, subject)
+            .await
+        {
+            Ok(())
+        } else {
+            Err(("not_pending", format!("No pending prompt {}", prompt_id)))
+        }
+    }
+
+    /// A protocol error frame: `{"type": "error", "error": <code>, "message": <text>}`.
+    fn protocol_error(code: &str, message: &str) -> WsMessage {
+        let frame = serde_json::json!({
+            "type": "error",
+            "error": code,
+            "message": message,
+        });
+        WsMessage::Text(frame.to_string().into())
+    }
+
+    /// Cancelled response to an agent message the inbound hook blocked, so a waiting agent
+    /// unblocks.
+    pub(crate) fn blocked_reply(channel: String, message_id: uuid::Uuid, reason: &str) -> Message {
+        let mut reply = Message::response(
+            channel,
+            MessageContent::Response {
+                answer: Some(reason.to_string()),
+                response_type: ResponseType::Cancelled,
+            },
+            message_id,
+        );
+        reply.metadata = Some(serde_json::json!({
+            "error": "blocked",
+            "reason": reason,
+        }));
+        reply
+    }
+
+    /// The cancelled response sent back for a message failing strict validation, correlated to
+    /// its `id` and `channel` when those can be read from `text`.
+    pub(crate) fn invalid_reply(text: &str, fallback_channel: &str, errors: &[String]) -> Message {
+        let raw: serde_json::Value = serde_json::from_str(text).unwrap_or_default();
+        let id = raw["id"]
+            .as_str()
+            .and_then(|id| id.parse().ok())
+            .unwrap_or_default();
+        let channel = raw["channel"].as_str().unwrap_or(fallback_channel);
+        let mut reply = Message::response(
+            channel.to_string(),
+            MessageContent::Response {
+                answer: Some(format!("Invalid message: {}", errors.join("; "))),
+                response_type: ResponseType::Cancelled,
+            },
+            id,
+        );
+        reply.metadata = Some(serde_json::json!({
+            "error": "invalid_message",
+            "details": errors,
+        }));
+        reply
+    }
+
+    /// Record an agent message in history, broadcast it (prompts to viewers only; providers get
+    /// them once registered as pending) and queue it for processing on its channel. On a held
+    /// channel, notifications also skip providers. Status pulses are only shown, never recorded
+    /// or queued.
+    pub(crate) fn accept_agent_message(
+        channel_manager: &ChannelIsolation,
+        message_history: &Arc<crate::server::history::MessageHistory>,
+        broadcast_manager: &Arc<crate::server::broadcast::BroadcastManager>,
+        message: Message,
+    ) {
+        if message.is_status() {
+            Self::show_status(channel_manager, broadcast_manager, message);
+            return;
+        }
+        let history = Arc::clone(message_history);
+        let broadcast = Arc::clone(broadcast_manager);
+        let recorded = message.clone();
+        let viewers_only = matches!(
+            recorded.content,
+            MessageContent::Decision { .. }
+                | MessageContent::Authorization { .. }
+                | MessageContent::Navigate { .. }
+        ) || channel_manager.held_since(&recorded.channel).is_some();
+        tokio::spawn(async move {
+            history
+                .add_message(&recorded.channel, recorded.clone())
+                .await;
+            if viewers_only {
+                broadcast.broadcast_to_viewers_only(&recorded).await;
+            } else {
+                broadcast.broadcast_message(&recorded).await;
+            }
+        });
+
+        let channel = message.channel.clone();
+        if matches!(
+            message.content,
+            MessageContent::Decision { .. }
+                | MessageContent::Authorization { .. }
+                | MessageContent::Navigate { .. }
+        ) {
+            events::prompt(message.id, &channel, "queued");
+        }
+        channel_manager.enqueue_message(&channel, message);
+    }
+
+    /// Show a status pulse: a spinner line on the server terminal (unless a prompt is being
+    /// answered there), viewers, and the providers' indicators unless the channel is held.
+    pub(crate) fn show_status(
+        channel_manager: &ChannelIsolation,
+        broadcast_manager: &Arc<crate::server::broadcast::BroadcastManager>,
+        message: Message,
+    ) {
+        if let MessageContent::Status { text } = &message.content {
+            if use_terminal() && !is_raw_mode_enabled().unwrap_or(true) {
+                console_print!(
+                    "\r\x1B[2K⏳ [{}] {}: {}",
+                    message.channel,
+                    message.speaker(),
+                    text
+                );
+                let _ = io::stdout().flush();
+            }
+        }
+        let held = channel_manager.held_since(&message.channel).is_some();
+        let broadcast = Arc::clone(broadcast_manager);
+        tokio::spawn(async move {
+            if held {
+                broadcast.broadcast_to_viewers_only(&message).await;
+            } else {
+                broadcast.broadcast_status(&message).await;
+            }
+        });
+    }
+
     /// Resolve a human answer string to a canonical decision option id.
     /// Returns (option_id, label, 0-based index) on success, None if no match.
     fn resolve_decision_answer(
@@ -308,6 +894,65 @@ impl AiloopServer {
         None
     }
 
+    /// Send a prompt to the notification sinks, or only to its assignee's when it was asked with
+    /// `--assign` someone who is not away. Returns the reply-to ID for provider answers.
+    async fn deliver_prompt(
+        message: &Message,
+        broadcast_manager: &Arc<crate::server::broadcast::BroadcastManager>,
+        pending_registry: &Arc<PendingPromptRegistry>,
+        config: Option<&Configuration>,
+    ) -> Option<String> {
+        let Some(assigned) = assignment::assignment(message, config) else {
+            if let Some(assignee) = message.assignee() {
+                tracing::warn!(
+                    message_id = %message.id,
+                    assignee,
+                    "Assignee is not in [team]; sending to everyone"
+                );
+            }
+            return broadcast_manager
+                .send_to_notification_sinks_and_get_reply_to_id(message)
+                .await;
+        };
+        if let Some(away) = broadcast_manager
+            .away()
+            .status(&assigned.member, chrono::Utc::now())
+        {
+            let notice = assignment::away_notice(message, &assigned.member, &away);
+            if let MessageContent::Notification { text, .. } = &notice.content {
+                console_println!("{}", text);
+            }
+            broadcast_manager.broadcast_to_viewers_only(&notice).await;
+            return broadcast_manager
+                .send_to_notification_sinks_and_get_reply_to_id(message)
+                .await;
+        }
+        let mut fallback = config
+            .map(|c| c.assignment.fallback())
+            .unwrap_or_else(|| AssignmentConfig::default().fallback());
+        if message.incident().is_some() {
+            fallback = config
+                .map(|c| c.incident.clone())
+                .unwrap_or_default()
+                .escalation(fallback);
+        }
+        if !assigned.terminal {
+            console_println!(
+                "Assigned to @{}; open to everyone in {}s",
+                assigned.member,
+                fallback.as_secs()
+            );
+        }
+        assignment::deliver(
+            assigned,
+            message,
+            broadcast_manager,
+            pending_registry,
+            fallback,
+        )
+        .await
+    }
+
     /// Handle a structured decision message. First valid response (terminal or provider) wins.
     #[allow(clippy::too_many_arguments)]
     async fn handle_decision(
@@ -320,19 +965,43 @@ impl AiloopServer {
         timeout_secs: u32,
         broadcast_manager: Arc<crate::server::broadcast::BroadcastManager>,
         pending_registry: Arc<PendingPromptRegistry>,
+        attachments: Arc<AttachmentStore>,
+        signer: Option<Arc<ResponseSigner>>,
+        hooks: &MessageHooks,
         config: Option<&Configuration>,
-    ) -> ResponseType {
-        let use_terminal = io::stdin().is_terminal() && io::stdout().is_terminal();
+    ) -> PromptResult {
+        let use_terminal = use_terminal()
+            && assignment::terminal_may_answer(&message, config, broadcast_manager.away());
+        // `ask --expect-file`: the answer may be a file instead of an option.
+        let expects_file = message
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get("expect_file"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let input_hint = if expects_file {
+            "Enter option id, label, number, or @FILE to attach a file (s to snooze, ESC to skip, Tab/↑/↓ to move, /text to search): "
+        } else {
+            "Enter option id, label, or number (s to snooze, ESC to skip, Tab/↑/↓ to move, /text to search): "
+        };
 
-        println!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!(
+        console_println!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        if let Some(heading) = message.agent_heading() {
+            console_println!("{}", heading);
+        }
+        if let Some(thread) = message.thread_summary() {
+            console_println!("{}", thread);
+        }
+        console_println!(
             "Decision [{}] ({}): {}",
-            message.channel, decision_id, summary
+            message.channel,
+            decision_id,
+            summary
         );
         if timeout_secs > 0 {
-            println!("Timeout: {} seconds", timeout_secs);
+            console_println!("Timeout: {} seconds", timeout_secs);
         }
-        println!("\nOptions:");
+        console_println!("\nOptions:");
         for (idx, opt) in options.iter().enumerate() {
             let rec_marker = recommendation
                 .as_ref()
@@ -341,22 +1010,22 @@ impl AiloopServer {
                 .unwrap_or("");
             if let Some(detail) = &opt.detail_markdown {
                 let truncated: String = detail.chars().take(80).collect();
-                println!("  {}. {}{} — {}", idx + 1, opt.label, rec_marker, truncated);
+                console_println!("  {}. {}{} — {}", idx + 1, opt.label, rec_marker, truncated);
             } else {
-                println!("  {}. {}{}", idx + 1, opt.label, rec_marker);
+                console_println!("  {}. {}{}", idx + 1, opt.label, rec_marker);
             }
         }
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        console_println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
         if use_terminal {
-            print!("Enter option id, label, or number (ESC to skip): ");
+            console_print!("{}", input_hint);
             let _ = io::stdout().flush();
         }
 
-        let reply_to_id = broadcast_manager
-            .send_to_notification_sinks_and_get_reply_to_id(&message)
-            .await;
+        let reply_to_id =
+            Self::deliver_prompt(&message, &broadcast_manager, &pending_registry, config).await;
 
-        let timeout_duration = resolve_effective_timeout(timeout_secs, config);
+        let policy = TimeoutPolicy::resolve(PromptKind::Question, timeout_secs, config);
+        let timeout_duration = policy.timeout();
 
         let (resolved_id, resolved_label, resolved_index, response_type) = loop {
             let (rx, completer) = pending_registry
@@ -372,20 +1041,40 @@ impl AiloopServer {
             enum Outcome {
                 Raw(String),
                 Done(Option<String>, ResponseType),
+                Snooze(Duration),
+                Move(Navigation),
             }
 
             let outcome = if use_terminal {
                 let terminal_cancelled = Arc::new(AtomicBool::new(false));
+                let on_edit = Self::typing_hook(&message, &broadcast_manager);
                 let mut terminal_input = tokio::task::spawn_blocking({
                     let terminal_cancelled = Arc::clone(&terminal_cancelled);
-                    move || Self::read_user_input_with_esc(timeout_duration, terminal_cancelled)
+                    move || {
+                        Self::read_user_input_with_esc(
+                            input_hint,
+                            timeout_duration,
+                            terminal_cancelled,
+                            on_edit,
+                            true,
+                        )
+                    }
                 });
                 tokio::select! {
                     result = &mut terminal_input => {
                         match result {
-                            Ok(Ok(Some(text))) => Outcome::Raw(text),
+                            Ok(Ok(Some(LineInput::Move(navigation)))) => Outcome::Move(navigation),
+                            Ok(Ok(Some(LineInput::Line(text)))) => match snooze::parse_snooze(&text) {
+                                // An option literally named "s" still wins.
+                                Some(duration)
+                                    if Self::resolve_decision_answer(&text, &options).is_none() =>
+                                {
+                                    Outcome::Snooze(duration)
+                                }
+                                _ => Outcome::Raw(text),
+                            },
                             Ok(Ok(None)) => {
-                                println!("\nDecision skipped");
+                                console_println!("\nDecision skipped");
                                 completer.complete(MessageContent::Response {
                                     answer: None,
                                     response_type: ResponseType::Cancelled,
@@ -395,13 +1084,13 @@ impl AiloopServer {
                             _ => {
                                 completer.complete(MessageContent::Response {
                                     answer: None,
-                                    response_type: ResponseType::Timeout,
+                                    response_type: policy.expiry_response(),
                                 }).await;
-                                Outcome::Done(None, ResponseType::Timeout)
+                                Outcome::Done(None, policy.expiry_response())
                             }
                         }
                     }
-                    result = PendingPromptRegistry::recv_maybe_timeout(rx, timeout_duration) => {
+                    result = PendingPromptRegistry::recv_with_policy(rx, &policy) => {
                         Self::stop_terminal_prompt(&terminal_cancelled, &mut terminal_input).await;
                         match result {
                             Ok(MessageContent::Response { answer: Some(a), response_type: ResponseType::Text }) => {
@@ -413,15 +1102,15 @@ impl AiloopServer {
                             _ => {
                                 completer.complete(MessageContent::Response {
                                     answer: None,
-                                    response_type: ResponseType::Timeout,
+                                    response_type: policy.expiry_response(),
                                 }).await;
-                                Outcome::Done(None, ResponseType::Timeout)
+                                Outcome::Done(None, policy.expiry_response())
                             }
                         }
                     }
                     _ = tokio::signal::ctrl_c() => {
                         Self::stop_terminal_prompt(&terminal_cancelled, &mut terminal_input).await;
-                        println!("\n Cancelled");
+                        console_println!("\n Cancelled");
                         completer.complete(MessageContent::Response {
                             answer: None,
                             response_type: ResponseType::Cancelled,
@@ -431,7 +1120,7 @@ impl AiloopServer {
                 }
             } else {
                 tokio::select! {
-                    result = PendingPromptRegistry::recv_maybe_timeout(rx, timeout_duration) => {
+                    result = PendingPromptRegistry::recv_with_policy(rx, &policy) => {
                         match result {
                             Ok(MessageContent::Response { answer: Some(a), response_type: ResponseType::Text }) => {
                                 Outcome::Raw(a)
@@ -442,14 +1131,14 @@ impl AiloopServer {
                             _ => {
                                 completer.complete(MessageContent::Response {
                                     answer: None,
-                                    response_type: ResponseType::Timeout,
+                                    response_type: policy.expiry_response(),
                                 }).await;
-                                Outcome::Done(None, ResponseType::Timeout)
+                                Outcome::Done(None, policy.expiry_response())
                             }
                         }
                     }
                     _ = tokio::signal::ctrl_c() => {
-                        println!("\n Cancelled");
+                        console_println!("\n Cancelled");
                         completer.complete(MessageContent::Response {
                             answer: None,
                             response_type: ResponseType::Cancelled,
@@ -461,6 +1150,25 @@ impl AiloopServer {
 
             match outcome {
                 Outcome::Done(id, rt) => break (id, String::new(), 0, rt),
+                Outcome::Snooze(duration) => {
+                    // Drop the registration without answering; the agent keeps waiting.
+                    completer
+                        .complete(MessageContent::Response {
+                            answer: None,
+                            response_type: ResponseType::Cancelled,
+                        })
+                        .await;
+                    return PromptResult::Snoozed(duration);
+                }
+                Outcome::Move(navigation) => {
+                    completer
+                        .complete(MessageContent::Response {
+                            answer: None,
+                            response_type: ResponseType::Cancelled,
+                        })
+                        .await;
+                    return PromptResult::Moved(navigation);
+                }
                 Outcome::Raw(raw) => {
                     if let Some((oid, lbl, idx)) = Self::resolve_decision_answer(&raw, &options) {
                         completer
@@ -470,17 +1178,33 @@ impl AiloopServer {
                             })
                             .await;
                         break (Some(oid), lbl, idx, ResponseType::Text);
+                    } else if let Some(name) = Self::resolve_file_answer(
+                        &raw,
+                        expects_file,
+                        message.id,
+                        &pending_registry,
+                        &attachments,
+                    )
+                    .await
+                    {
+                        completer
+                            .complete(MessageContent::Response {
+                                answer: Some(name.clone()),
+                                response_type: ResponseType::Text,
+                            })
+                            .await;
+                        break (Some(name), String::new(), 0, ResponseType::Text);
                     } else {
                         tracing::warn!(
                             "DECISION_UNKNOWN_ANSWER: '{}' does not match any option id, label, or index",
                             raw
                         );
-                        println!(
+                        console_println!(
                             "\nDECISION_UNKNOWN_ANSWER: '{}' does not match any option. Try again.",
                             raw
                         );
                         if use_terminal {
-                            print!("Enter option id, label, or number (ESC to skip): ");
+                            console_print!("{}", input_hint);
                             let _ = io::stdout().flush();
                         }
                         continue;
@@ -516,44 +1240,62 @@ impl AiloopServer {
         if !response_metadata.is_empty() {
             response_message.metadata = Some(serde_json::Value::Object(response_metadata));
         }
+        Self::attribute_response(
+            &mut response_message,
+            &pending_registry,
+            signer.as_deref(),
+            hooks,
+        )
+        .await;
 
         broadcast_manager.broadcast_message(&response_message).await;
 
         if let Some(text) = &resolved_id {
-            println!("\nDecision resolved: {}", text);
+            console_println!("\nDecision resolved: {}", text);
         } else {
-            println!("\nDecision response: {:?}", response_type);
+            console_println!("\nDecision response: {:?}", response_type);
         }
-        println!();
+        console_println!();
 
-        response_type
+        PromptResult::Answered(response_type)
     }
 
     /// Handle an authorization message. First response (terminal or provider) wins.
+    #[allow(clippy::too_many_arguments)]
     async fn handle_authorization(
         message: Message,
         action: String,
         timeout_secs: u32,
         broadcast_manager: Arc<crate::server::broadcast::BroadcastManager>,
         pending_registry: Arc<PendingPromptRegistry>,
+        signer: Option<Arc<ResponseSigner>>,
+        hooks: &MessageHooks,
         config: Option<&Configuration>,
-    ) -> ResponseType {
-        let use_terminal = io::stdin().is_terminal() && io::stdout().is_terminal();
+    ) -> PromptResult {
+        let use_terminal = use_terminal()
+            && assignment::terminal_may_answer(&message, config, broadcast_manager.away());
 
-        println!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!("Authorization Request [{}]: {}", message.channel, action);
+        console_println!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        if let Some(heading) = message.agent_heading() {
+            console_println!("{}", heading);
+        }
+        if let Some(thread) = message.thread_summary() {
+            console_println!("{}", thread);
+        }
+        console_println!("Authorization Request [{}]: {}", message.channel, action);
         if timeout_secs > 0 {
-            println!("Timeout: {} seconds", timeout_secs);
+            console_println!("Timeout: {} seconds", timeout_secs);
         }
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        console_println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        const AUTHORIZE_HINT: &str =
+            "Authorize? (Y/a=yes, n/d/Enter=no, n: reason, s=snooze, ESC=skip, Tab/↑/↓=move, /text=search): ";
         if use_terminal {
-            print!("Authorize? (Y=yes, n/Enter=no, ESC=skip): ");
+            console_print!("{}", AUTHORIZE_HINT);
             let _ = io::stdout().flush();
         }
 
-        let reply_to_id = broadcast_manager
-            .send_to_notification_sinks_and_get_reply_to_id(&message)
-            .await;
+        let reply_to_id =
+            Self::deliver_prompt(&message, &broadcast_manager, &pending_registry, config).await;
 
         let (rx, completer) = pending_registry
             .register(
@@ -564,43 +1306,81 @@ impl AiloopServer {
                 action.clone(),
             )
             .await;
-        let timeout_duration = resolve_effective_timeout(timeout_secs, config);
+        let policy = TimeoutPolicy::resolve(PromptKind::Authorization, timeout_secs, config);
+        let timeout_duration = policy.timeout();
 
-        let decision = if use_terminal {
+        let (decision, reason) = if use_terminal {
             let terminal_cancelled = Arc::new(AtomicBool::new(false));
+            let invalid_input = config
+                .map(|c| c.authorization.invalid_input)
+                .unwrap_or_default();
+            let confirm_quick = Self::confirm_quick_approval(&message, &action, config);
             let mut terminal_input = tokio::task::spawn_blocking({
                 let terminal_cancelled = Arc::clone(&terminal_cancelled);
-                move || Self::read_authorization_with_esc(timeout_duration, terminal_cancelled)
+                move || {
+                    Self::read_authorization_with_esc(
+                        AUTHORIZE_HINT,
+                        timeout_duration,
+                        invalid_input,
+                        terminal_cancelled,
+                        confirm_quick,
+                    )
+                }
             });
             tokio::select! {
                 result = &mut terminal_input => {
                     match result {
-                        Ok(Ok(Some(response_type))) => {
+                        Ok(Ok(Some(YesNoInput::Answer(response_type, reason)))) => {
+                            let (response_type, reason) =
+                                if response_type == ResponseType::AuthorizationApproved {
+                                    Self::verify_approval(&message, &action, config, reason).await
+                                } else {
+                                    (response_type, reason)
+                                };
                             let content = MessageContent::Response {
-                                answer: None,
+                                answer: reason.clone(),
                                 response_type: response_type.clone(),
                             };
                             completer.complete(content).await;
-                            response_type
+                            (response_type, reason)
+                        }
+                        Ok(Ok(Some(YesNoInput::Snooze(duration)))) => {
+                            // Drop the registration without answering; the agent keeps waiting.
+                            completer
+                                .complete(MessageContent::Response {
+                                    answer: None,
+                                    response_type: ResponseType::Cancelled,
+                                })
+                                .await;
+                            return PromptResult::Snoozed(duration);
+                        }
+                        Ok(Ok(Some(YesNoInput::Move(navigation)))) => {
+                            completer
+                                .complete(MessageContent::Response {
+                                    answer: None,
+                                    response_type: ResponseType::Cancelled,
+                                })
+                                .await;
+                            return PromptResult::Moved(navigation);
                         }
                         Ok(Ok(None)) => {
-                            println!("\nAuthorization skipped");
+                            console_println!("\nAuthorization skipped");
                             completer
                                 .complete(MessageContent::Response {
                                     answer: None,
                                     response_type: ResponseType::Cancelled,
                                 })
                                 .await;
-                            ResponseType::Cancelled
+                            (ResponseType::Cancelled, None)
                         }
                         Ok(Err(_)) => {
                             completer
                                 .complete(MessageContent::Response {
                                     answer: None,
-                                    response_type: ResponseType::AuthorizationDenied,
+                                    response_type: policy.expiry_response(),
                                 })
                                 .await;
-                            ResponseType::AuthorizationDenied
+                            (policy.expiry_response(), None)
                         }
                         Err(_) => {
                             completer
@@ -609,51 +1389,55 @@ impl AiloopServer {
                                     response_type: ResponseType::AuthorizationDenied,
                                 })
                                 .await;
-                            ResponseType::AuthorizationDenied
+                            (ResponseType::AuthorizationDenied, None)
                         }
                     }
                 }
-                result = PendingPromptRegistry::recv_maybe_timeout(rx, timeout_duration) => {
+                result = PendingPromptRegistry::recv_with_policy(rx, &policy) => {
                     Self::stop_terminal_prompt(&terminal_cancelled, &mut terminal_input).await;
                     match result {
-                        Ok(MessageContent::Response { response_type, .. }) => response_type,
+                        Ok(MessageContent::Response { response_type, answer }) => {
+                            (response_type, answer.as_deref().and_then(reply_reason))
+                        }
                         _ => {
-                            println!("\nTimeout - DENIED");
+                            console_println!("\nTimeout - DENIED");
                             completer
                                 .complete(MessageContent::Response {
                                     answer: None,
-                                    response_type: ResponseType::AuthorizationDenied,
+                                    response_type: policy.expiry_response(),
                                 })
                                 .await;
-                            ResponseType::AuthorizationDenied
+                            (policy.expiry_response(), None)
                         }
                     }
                 }
                 _ = tokio::signal::ctrl_c() => {
                     Self::stop_terminal_prompt(&terminal_cancelled, &mut terminal_input).await;
-                    println!("\nCancelled - DENIED");
+                    console_println!("\nCancelled - DENIED");
                     completer
                         .complete(MessageContent::Response {
                             answer: None,
                             response_type: ResponseType::AuthorizationDenied,
                         })
                         .await;
-                    ResponseType::AuthorizationDenied
+                    (ResponseType::AuthorizationDenied, None)
                 }
             }
         } else {
             tokio::select! {
-                result = PendingPromptRegistry::recv_maybe_timeout(rx, timeout_duration) => {
+                result = PendingPromptRegistry::recv_with_policy(rx, &policy) => {
                     match result {
-                        Ok(MessageContent::Response { response_type, .. }) => response_type,
+                        Ok(MessageContent::Response { response_type, answer }) => {
+                            (response_type, answer.as_deref().and_then(reply_reason))
+                        }
                         _ => {
                             completer
                                 .complete(MessageContent::Response {
                                     answer: None,
-                                    response_type: ResponseType::AuthorizationDenied,
+                                    response_type: policy.expiry_response(),
                                 })
                                 .await;
-                            ResponseType::AuthorizationDenied
+                            (policy.expiry_response(), None)
                         }
                     }
                 }
@@ -664,66 +1448,401 @@ impl AiloopServer {
                             response_type: ResponseType::AuthorizationDenied,
                         })
                         .await;
-                    ResponseType::AuthorizationDenied
+                    (ResponseType::AuthorizationDenied, None)
                 }
             }
         };
 
-        let response_content = MessageContent::Response {
-            answer: None,
-            response_type: decision.clone(),
-        };
+        let (decision, reason) = match decision {
+            ResponseType::AuthorizationApproved => match Self::undo_window(
+                &message,
+                &action,
+                &broadcast_manager,
+                &pending_registry,
+                config,
+                use_terminal,
+            )
+            .await
+            {
+                Some(undo_reason) => (ResponseType::AuthorizationDenied, undo_reason),
+                None => (decision, reason),
+            },
+            other => (other, reason),
+        };
+
+        let response_content = MessageContent::Response {
+            answer: reason.clone(),
+            response_type: decision.clone(),
+        };
 
-        let response_message =
+        let mut response_message =
             Message::response(message.channel.clone(), response_content, message.id);
+        Self::attribute_response(
+            &mut response_message,
+            &pending_registry,
+            signer.as_deref(),
+            hooks,
+        )
+        .await;
+        let responder = response_message
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get("responder"))
+            .and_then(|r| r.as_str());
+        log_security_event(
+            "authorization_decided",
+            &message.channel,
+            responder,
+            &format!(
+                "prompt {} {:?}: {}{}",
+                message.id,
+                decision,
+                action,
+                reason
+                    .as_deref()
+                    .map(|r| format!(" (reason: {})", r))
+                    .unwrap_or_default()
+            ),
+        );
 
         broadcast_manager.broadcast_message(&response_message).await;
 
         match decision {
             ResponseType::AuthorizationApproved => {
-                println!("\nAuthorization GRANTED");
+                console_println!("\nAuthorization GRANTED");
             }
             ResponseType::AuthorizationDenied => {
-                println!("\nAuthorization DENIED");
+                console_println!("\nAuthorization DENIED");
             }
             ResponseType::Cancelled => {
-                println!("\nAuthorization CANCELLED");
+                console_println!("\nAuthorization CANCELLED");
             }
             _ => {
-                println!("\nAuthorization response: {:?}", decision);
+                console_println!("\nAuthorization response: {:?}", decision);
+            }
+        }
+        if let Some(reason) = &reason {
+            console_println!("Reason: {}", reason);
+        }
+        console_println!();
+
+        PromptResult::Answered(decision)
+    }
+
+    /// Hold an approval for the grace period configured in `[undo]`, during which it can be
+    /// undone with `u` at the terminal or a "no" reply from a provider. Returns `Some` with the
+    /// reason given, if any, when the approval was undone.
+    async fn undo_window(
+        message: &Message,
+        action: &str,
+        broadcast_manager: &crate::server::broadcast::BroadcastManager,
+        pending_registry: &PendingPromptRegistry,
+        config: Option<&Configuration>,
+        use_terminal: bool,
+    ) -> Option<Option<String>> {
+        let grace = config.and_then(|c| c.undo.grace_for(&message.channel, action))?;
+
+        let notice = Message::new(
+            message.channel.clone(),
+            ailoop_core::models::SenderType::System,
+            MessageContent::Notification {
+                text: format!(
+                    "Approved: {}. Reply \"no\" within {} seconds to undo.",
+                    action,
+                    grace.as_secs()
+                ),
+                priority: ailoop_core::models::NotificationPriority::High,
+            },
+        );
+        let reply_to_id = broadcast_manager
+            .send_to_notification_sinks_and_get_reply_to_id(&notice)
+            .await;
+        let (rx, completer) = pending_registry
+            .register(
+                message.id,
+                reply_to_id,
+                PromptType::Authorization,
+                message.channel.clone(),
+                format!("Undo approval: {}", action),
+            )
+            .await;
+        let undo_reason = |reply: MessageContent| match reply {
+            MessageContent::Response {
+                response_type: ResponseType::AuthorizationDenied | ResponseType::Cancelled,
+                answer,
+            } => Some(answer.as_deref().and_then(reply_reason)),
+            _ => None,
+        };
+
+        let undone = if use_terminal {
+            console_println!(
+                "Approved. Press u within {} seconds to undo.",
+                grace.as_secs()
+            );
+            let terminal_cancelled = Arc::new(AtomicBool::new(false));
+            let mut terminal_input = tokio::task::spawn_blocking({
+                let terminal_cancelled = Arc::clone(&terminal_cancelled);
+                move || Self::read_undo_key(grace, terminal_cancelled)
+            });
+            tokio::select! {
+                result = &mut terminal_input => {
+                    matches!(result, Ok(Ok(Some(())))).then_some(None)
+                }
+                reply = rx => {
+                    Self::stop_terminal_prompt(&terminal_cancelled, &mut terminal_input).await;
+                    reply.ok().and_then(undo_reason)
+                }
+            }
+        } else {
+            match tokio::time::timeout(grace, rx).await {
+                Ok(Ok(reply)) => undo_reason(reply),
+                _ => None,
             }
+        };
+        // Release the registration if nobody replied.
+        completer
+            .complete(MessageContent::Response {
+                answer: None,
+                response_type: ResponseType::AuthorizationApproved,
+            })
+            .await;
+
+        if undone.is_some() {
+            console_println!("\nApproval undone");
         }
-        println!();
+        undone
+    }
 
-        decision
+    /// A file answer to a decision: a reply that came with an attachment (HTTP API), or
+    /// `@PATH` typed at the terminal, which is copied into the attachment store. Returns the
+    /// file name used as the answer.
+    async fn resolve_file_answer(
+        raw: &str,
+        expects_file: bool,
+        message_id: uuid::Uuid,
+        pending_registry: &PendingPromptRegistry,
+        attachments: &AttachmentStore,
+    ) -> Option<String> {
+        if pending_registry.has_attachment(message_id).await {
+            return Some(raw.to_string());
+        }
+        let path = raw.strip_prefix('@').filter(|_| expects_file)?.trim();
+        match attachments.store_file(std::path::Path::new(path)).await {
+            Ok(attachment) => {
+                let name = attachment.name.clone();
+                pending_registry.attach(message_id, attachment).await;
+                Some(name)
+            }
+            Err(e) => {
+                console_println!("\nCould not attach {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Run the outbound hook on a response, record who answered the prompt and, when signing
+    /// is enabled, sign the response. Prompts completed at the terminal (or by timeout) are
+    /// attributed to the operator. A blocked response is replaced by a cancellation.
+    async fn attribute_response(
+        response: &mut Message,
+        pending_registry: &PendingPromptRegistry,
+        signer: Option<&ResponseSigner>,
+        hooks: &MessageHooks,
+    ) {
+        let Some(prompt_id) = response.correlation_id else {
+            return;
+        };
+        match hooks.outbound(response.clone()).await {
+            HookOutcome::Pass(message) => *response = *message,
+            HookOutcome::Blocked(reason) => {
+                console_println!("\nResponse blocked by hook: {}", reason);
+                log_security_event(
+                    "response_blocked",
+                    &response.channel,
+                    None,
+                    &format!("prompt {}: {}", prompt_id, reason),
+                );
+                *response = Self::blocked_reply(response.channel.clone(), prompt_id, &reason);
+            }
+        }
+        let responder = pending_registry.take_responder(prompt_id).await;
+        if let Some(ref responder) = responder {
+            let member = pending_registry.team_member(responder).await;
+            let metadata = response
+                .metadata
+                .get_or_insert_with(|| serde_json::json!({}));
+            if let Some(map) = metadata.as_object_mut() {
+                map.insert("responder".to_string(), serde_json::json!(responder));
+                if let Some(member) = member {
+                    map.insert("member".to_string(), serde_json::json!(member));
+                }
+            }
+        }
+        if let Some(attachment) = pending_registry.take_attachment(prompt_id).await {
+            let metadata = response
+                .metadata
+                .get_or_insert_with(|| serde_json::json!({}));
+            if let Some(map) = metadata.as_object_mut() {
+                map.insert("attachment".to_string(), serde_json::json!(attachment));
+            }
+        }
+        if let Some(signer) = signer {
+            let responder = responder.as_deref().unwrap_or(OPERATOR_RESPONDER);
+            let signature = signer.sign(response, responder);
+            log_security_event(
+                "response_signed",
+                &response.channel,
+                Some(responder),
+                &format!(
+                    "prompt {} key {} signature {}",
+                    prompt_id, signature.public_key, signature.value
+                ),
+            );
+        }
     }
 
     /// Handle a notification message
-    fn handle_notification(text: String, _priority: ailoop_core::models::NotificationPriority) {
-        println!("\n {}", text);
+    fn handle_notification(
+        heading: Option<String>,
+        text: String,
+        _priority: ailoop_core::models::NotificationPriority,
+    ) {
+        match heading {
+            Some(heading) => console_println!("\n {} {}", heading, text),
+            None => console_println!("\n {}", text),
+        }
+    }
+
+    /// Show a notification's attachment: a thumbnail when the terminal can draw the image,
+    /// otherwise a link.
+    async fn show_attachment(attachment: &MessageAttachment, store: &AttachmentStore) {
+        let protocol = ImageProtocol::detect().filter(|_| use_terminal());
+        if let (Some(protocol), Some(image_type)) = (protocol, attachment.image_type()) {
+            if protocol.supports(image_type) {
+                if let Some(bytes) = attachment
+                    .read(store)
+                    .await
+                    .filter(|b| b.len() <= MAX_THUMBNAIL_BYTES)
+                {
+                    console_println!("{}", protocol.thumbnail(&bytes, THUMBNAIL_COLUMNS));
+                    return;
+                }
+            }
+        }
+        console_println!(" 📎 {}: {}", attachment.name, attachment.url);
+    }
+
+    /// Show a chat line. On a terminal the operator may answer the agent's line: the reply is
+    /// sent back on the channel, where the agent's `ailoop chat` picks it up, and kept in
+    /// history. Chat lines never block on a provider; humans elsewhere answer through the API.
+    async fn handle_chat(
+        message: &Message,
+        conversation_id: uuid::Uuid,
+        text: &str,
+        closed: bool,
+        broadcast_manager: &crate::server::broadcast::BroadcastManager,
+        message_history: &crate::server::history::MessageHistory,
+        hooks: &MessageHooks,
+    ) -> PromptResult {
+        let speaker = message.speaker();
+        if !text.is_empty() {
+            console_println!("\n💬 [{}] {}: {}", message.channel, speaker, text);
+        }
+        if closed {
+            console_println!(
+                "💬 [{}] {} closed the conversation",
+                message.channel,
+                speaker
+            );
+            return PromptResult::Answered(ResponseType::Text);
+        }
+        if !matches!(message.sender_type, SenderType::Agent) || !use_terminal() {
+            return PromptResult::Answered(ResponseType::Text);
+        }
+
+        const REPLY_HINT: &str = "Reply (ENTER to skip, /close to end the conversation): ";
+        console_print!("{}", REPLY_HINT);
+        let _ = io::stdout().flush();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let on_edit = Self::typing_hook(message, broadcast_manager);
+        let line = tokio::task::spawn_blocking(move || {
+            Self::read_user_input_with_esc(
+                REPLY_HINT,
+                Some(CHAT_REPLY_TIMEOUT),
+                cancelled,
+                on_edit,
+                false,
+            )
+        })
+        .await;
+        let line = match line {
+            Ok(Ok(Some(LineInput::Line(line)))) => line.trim().to_string(),
+            _ => String::new(),
+        };
+        let (text, closed) = match line.strip_prefix("/close") {
+            Some(rest) => (rest.trim().to_string(), true),
+            None => (line, false),
+        };
+        if text.is_empty() && !closed {
+            return PromptResult::Answered(ResponseType::Text);
+        }
+
+        let reply = Message::new(
+            message.channel.clone(),
+            SenderType::Human,
+            MessageContent::Chat {
+                conversation_id,
+                text,
+                closed,
+            },
+        );
+        let reply = match hooks.outbound(reply).await {
+            HookOutcome::Pass(reply) => *reply,
+            HookOutcome::Blocked(reason) => {
+                console_println!("Reply blocked: {}", reason);
+                return PromptResult::Answered(ResponseType::Text);
+            }
+        };
+        message_history
+            .add_message(&reply.channel, reply.clone())
+            .await;
+        broadcast_manager.broadcast_to_viewers_only(&reply).await;
+        PromptResult::Answered(ResponseType::Text)
     }
 
     /// Handle a navigate message. First response (terminal or provider) wins.
+    #[allow(clippy::too_many_arguments)]
     async fn handle_navigate(
         message: Message,
         url: String,
+        timeout_secs: u32,
         broadcast_manager: Arc<crate::server::broadcast::BroadcastManager>,
         pending_registry: Arc<PendingPromptRegistry>,
+        signer: Option<Arc<ResponseSigner>>,
+        hooks: &MessageHooks,
         config: Option<&Configuration>,
-    ) -> ResponseType {
-        let use_terminal = io::stdin().is_terminal() && io::stdout().is_terminal();
+    ) -> PromptResult {
+        let use_terminal = use_terminal()
+            && assignment::terminal_may_answer(&message, config, broadcast_manager.away());
 
-        println!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!("Navigation Request [{}]: {}", message.channel, url);
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        console_println!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        if let Some(heading) = message.agent_heading() {
+            console_println!("{}", heading);
+        }
+        if let Some(thread) = message.thread_summary() {
+            console_println!("{}", thread);
+        }
+        console_println!("Navigation Request [{}]: {}", message.channel, url);
+        console_println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        const NAVIGATE_HINT: &str =
+            "Open in browser? (Y=yes, n/Enter=no, s=snooze, ESC=skip, Tab/↑/↓=move, /text=search): ";
         if use_terminal {
-            print!("Open in browser? (Y=yes, n/Enter=no, ESC=skip): ");
+            console_print!("{}", NAVIGATE_HINT);
             let _ = io::stdout().flush();
         }
 
-        let reply_to_id = broadcast_manager
-            .send_to_notification_sinks_and_get_reply_to_id(&message)
-            .await;
+        let reply_to_id =
+            Self::deliver_prompt(&message, &broadcast_manager, &pending_registry, config).await;
 
         let (rx, completer) = pending_registry
             .register(
@@ -734,18 +1853,30 @@ impl AiloopServer {
                 url.clone(),
             )
             .await;
-        let timeout_duration = resolve_effective_timeout(0, config);
+        let policy = TimeoutPolicy::resolve(PromptKind::Navigation, timeout_secs, config);
+        let timeout_duration = policy.timeout();
 
         let decision = if use_terminal {
             let terminal_cancelled = Arc::new(AtomicBool::new(false));
+            let invalid_input = config
+                .map(|c| c.authorization.invalid_input)
+                .unwrap_or_default();
             let mut terminal_input = tokio::task::spawn_blocking({
                 let terminal_cancelled = Arc::clone(&terminal_cancelled);
-                move || Self::read_authorization_with_esc(timeout_duration, terminal_cancelled)
+                move || {
+                    Self::read_authorization_with_esc(
+                        NAVIGATE_HINT,
+                        timeout_duration,
+                        invalid_input,
+                        terminal_cancelled,
+                        false,
+                    )
+                }
             });
             tokio::select! {
                 result = &mut terminal_input => {
                     match result {
-                        Ok(Ok(Some(response_type))) => {
+                        Ok(Ok(Some(YesNoInput::Answer(response_type, _)))) => {
                             let content = MessageContent::Response {
                                 answer: None,
                                 response_type: response_type.clone(),
@@ -753,8 +1884,27 @@ impl AiloopServer {
                             completer.complete(content).await;
                             response_type
                         }
+                        Ok(Ok(Some(YesNoInput::Snooze(duration)))) => {
+                            // Drop the registration without answering; the agent keeps waiting.
+                            completer
+                                .complete(MessageContent::Response {
+                                    answer: None,
+                                    response_type: ResponseType::Cancelled,
+                                })
+                                .await;
+                            return PromptResult::Snoozed(duration);
+                        }
+                        Ok(Ok(Some(YesNoInput::Move(navigation)))) => {
+                            completer
+                                .complete(MessageContent::Response {
+                                    answer: None,
+                                    response_type: ResponseType::Cancelled,
+                                })
+                                .await;
+                            return PromptResult::Moved(navigation);
+                        }
                         Ok(Ok(None)) => {
-                            println!("\nNavigation skipped");
+                            console_println!("\nNavigation skipped");
                             completer
                                 .complete(MessageContent::Response {
                                     answer: None,
@@ -767,10 +1917,10 @@ impl AiloopServer {
                             completer
                                 .complete(MessageContent::Response {
                                     answer: None,
-                                    response_type: ResponseType::Cancelled,
+                                    response_type: policy.expiry_response(),
                                 })
                                 .await;
-                            ResponseType::Cancelled
+                            policy.expiry_response()
                         }
                         Err(_) => {
                             completer
@@ -783,7 +1933,7 @@ impl AiloopServer {
                         }
                     }
                 }
-                result = PendingPromptRegistry::recv_maybe_timeout(rx, timeout_duration) => {
+                result = PendingPromptRegistry::recv_with_policy(rx, &policy) => {
                     Self::stop_terminal_prompt(&terminal_cancelled, &mut terminal_input).await;
                     match result {
                         Ok(MessageContent::Response { response_type, .. }) => response_type,
@@ -791,16 +1941,16 @@ impl AiloopServer {
                             completer
                                 .complete(MessageContent::Response {
                                     answer: None,
-                                    response_type: ResponseType::Cancelled,
+                                    response_type: policy.expiry_response(),
                                 })
                                 .await;
-                            ResponseType::Cancelled
+                            policy.expiry_response()
                         }
                     }
                 }
                 _ = tokio::signal::ctrl_c() => {
                     Self::stop_terminal_prompt(&terminal_cancelled, &mut terminal_input).await;
-                    println!("\n Cancelled - DENIED");
+                    console_println!("\n Cancelled - DENIED");
                     completer
                         .complete(MessageContent::Response {
                             answer: None,
@@ -812,22 +1962,22 @@ impl AiloopServer {
             }
         } else {
             tokio::select! {
-                result = PendingPromptRegistry::recv_maybe_timeout(rx, timeout_duration) => {
+                result = PendingPromptRegistry::recv_with_policy(rx, &policy) => {
                     match result {
                         Ok(MessageContent::Response { response_type, .. }) => response_type,
                         _ => {
                             completer
                                 .complete(MessageContent::Response {
                                     answer: None,
-                                    response_type: ResponseType::Cancelled,
+                                    response_type: policy.expiry_response(),
                                 })
                                 .await;
-                            ResponseType::Cancelled
+                            policy.expiry_response()
                         }
                     }
                 }
                 _ = tokio::signal::ctrl_c() => {
-                    println!("\nCancelled - DENIED");
+                    console_println!("\nCancelled - DENIED");
                     completer
                         .complete(MessageContent::Response {
                             answer: None,
@@ -839,7 +1989,7 @@ impl AiloopServer {
             }
         };
 
-        let response_message = Message::response(
+        let mut response_message = Message::response(
             message.channel.clone(),
             MessageContent::Response {
                 answer: None,
@@ -847,10 +1997,17 @@ impl AiloopServer {
             },
             message.id,
         );
+        Self::attribute_response(
+            &mut response_message,
+            &pending_registry,
+            signer.as_deref(),
+            hooks,
+        )
+        .await;
         broadcast_manager.broadcast_message(&response_message).await;
 
         if matches!(decision, ResponseType::AuthorizationApproved) {
-            println!("\nOpening browser...");
+            console_println!("\nOpening browser...");
 
             #[cfg(target_os = "linux")]
             {
@@ -867,11 +2024,11 @@ impl AiloopServer {
                 let _ = std::process::Command::new("open").arg(&url).spawn();
             }
         } else {
-            println!("\nBrowser not opened");
+            console_println!("\nBrowser not opened");
         }
-        println!();
+        console_println!();
 
-        decision
+        PromptResult::Answered(decision)
     }
 
     async fn stop_terminal_prompt<T>(
@@ -882,31 +2039,143 @@ impl AiloopServer {
         let _ = handle.await;
     }
 
+    /// Pass the operator's draft of an answer to `message` on as typing frames, when typing
+    /// is streamed.
+    fn typing_hook(
+        message: &Message,
+        broadcast_manager: &crate::server::broadcast::BroadcastManager,
+    ) -> Option<DraftHook> {
+        if !broadcast_manager.typing().is_enabled() {
+            return None;
+        }
+        let runtime = tokio::runtime::Handle::current();
+        let broadcast_manager = broadcast_manager.clone();
+        let (prompt_id, channel) = (message.id, message.channel.clone());
+        Some(Box::new(move |draft: &str| {
+            let (broadcast_manager, channel) = (broadcast_manager.clone(), channel.clone());
+            let draft = draft.to_string();
+            runtime.spawn(async move {
+                broadcast_manager
+                    .broadcast_typing(prompt_id, &channel, &draft)
+                    .await;
+            });
+        }))
+    }
+
+    /// A responder's draft (`{"type": "typing", "prompt_id": ..., "text": ...}`) of an answer
+    /// to a pending prompt; drafts for unknown prompts are dropped.
+    /// Move a queued prompt ahead at the request of the agent connection that sent it.
+    fn boost_prompt(
+        channel_manager: &ChannelIsolation,
+        broadcast_manager: &crate::server::broadcast::BroadcastManager,
+        connection_id: uuid::Uuid,
+        frame: serde_json::Value,
+    ) -> Result<(), (&'static str, String)> {
+        let boost: QueueBoost = serde_json::from_value(frame)
+            .map_err(|e| ("invalid_boost", format!("Invalid boost: {}", e)))?;
+        let current_priority = channel_manager
+            .get_queued_messages(None)
+            .into_iter()
+            .flat_map(|(_, messages)| messages)
+            .find(|m| m.id == boost.prompt_id)
+            .map(|m| m.queue_priority())
+            .ok_or_else(|| {
+                (
+                    "not_queued",
+                    format!("prompt {} is not queued", boost.prompt_id),
+                )
+            })?;
+        if boost.priority.rank() <= current_priority.rank() {
+            return Err((
+                "invalid_boost",
+                format!(
+                    "prompt {} is already queued at this priority or higher",
+                    boost.prompt_id
+                ),
+            ));
+        }
+        broadcast_manager
+            .queue_positions()
+            .try_boost(boost.prompt_id, connection_id, chrono::Utc::now())
+            .map_err(|refused| (refused.code(), refused.to_string()))?;
+        let (channel, ahead) = channel_manager
+            .boost_queued_message(&boost.prompt_id, boost.priority.clone())
+            .ok_or_else(|| {
+                (
+                    "not_queued",
+                    format!("prompt {} is not queued", boost.prompt_id),
+                )
+            })?;
+        tracing::info!(
+            prompt_id = %boost.prompt_id,
+            channel = %channel,
+            priority = ?boost.priority,
+            ahead,
+            reason = boost.reason.as_deref().unwrap_or_default(),
+            "Prompt boosted"
+        );
+        events::boost(
+            boost.prompt_id,
+            &channel,
+            &boost.priority,
+            ahead,
+            boost.reason.as_deref(),
+        );
+        Ok(())
+    }
+
+    async fn relay_draft(
+        broadcast_manager: &crate::server::broadcast::BroadcastManager,
+        pending_registry: &PendingPromptRegistry,
+        frame: &serde_json::Value,
+    ) {
+        let Some(prompt_id) = frame["prompt_id"]
+            .as_str()
+            .and_then(|id| uuid::Uuid::parse_str(id).ok())
+        else {
+            return;
+        };
+        let pending = pending_registry.snapshot_pending(None).await;
+        if let Some(prompt) = pending.iter().find(|p| p.message_id == prompt_id) {
+            let draft = frame["text"].as_str().unwrap_or_default();
+            broadcast_manager
+                .broadcast_typing(prompt_id, &prompt.channel, draft)
+                .await;
+        }
+    }
+
+    /// Read a line of text. With `navigable`, Tab, Up and Down at an empty input and a `/text`
+    /// search leave the prompt instead (see [`Navigation`]).
     fn read_user_input_with_esc(
+        hint: &str,
         timeout: Option<Duration>,
         cancelled: Arc<AtomicBool>,
-    ) -> Result<Option<String>> {
-        enable_raw_mode().context("Failed to enable raw mode")?;
-        let _guard = RawModeGuard;
+        mut on_edit: Option<DraftHook>,
+        navigable: bool,
+    ) -> Result<Option<LineInput>> {
+        let mut raw = RawMode::enable()?;
 
         let mut buffer = String::new();
         let mut countdown: Option<CountdownRenderer> = timeout.map(CountdownRenderer::new);
         let mut countdown_enabled = true;
 
-        println!("\x1B[s");
+        console_println!("\x1B[s");
         io::stdout().flush()?;
 
         loop {
+            if raw.take_resumed() {
+                redraw_input(hint, &buffer);
+            }
             if cancelled.load(Ordering::Relaxed) {
-                print!("\r\x1B[2K\x1B[u");
+                console_print!("\r\x1B[2K\x1B[u");
                 io::stdout().flush().ok();
-                println!();
+                console_println!();
                 return Ok(None);
             }
 
             if let Some(cd) = &mut countdown {
                 if cd.remaining_secs() == 0 {
-                    print!("{}", cd.render_final());
+                    console_print!("{}", cd.render_final());
                     io::stdout().flush().ok();
                     return Err(anyhow::anyhow!("Question timed out"));
                 }
@@ -915,29 +2184,52 @@ impl AiloopServer {
             if event::poll(Duration::from_millis(100))? {
                 if let Event::Key(key_event) = event::read()? {
                     if key_event.kind == KeyEventKind::Press {
+                        let navigation = (navigable && buffer.is_empty())
+                            .then(|| terminal::navigation_key(&key_event))
+                            .flatten();
+                        if let Some(navigation) = navigation {
+                            console_print!("\r\x1B[2K\x1B[u");
+                            io::stdout().flush().ok();
+                            console_println!();
+                            return Ok(Some(LineInput::Move(navigation)));
+                        }
                         match key_event.code {
                             KeyCode::Esc => {
-                                print!("\r\x1B[2K\x1B[u");
+                                console_print!("\r\x1B[2K\x1B[u");
                                 io::stdout().flush().ok();
-                                println!();
+                                console_println!();
                                 return Ok(None);
                             }
                             KeyCode::Enter => {
-                                print!("\r\x1B[2K\x1B[u");
+                                console_print!("\r\x1B[2K\x1B[u");
                                 io::stdout().flush().ok();
-                                println!();
+                                console_println!();
                                 let answer = buffer.trim().to_string();
-                                return Ok(Some(answer));
+                                if let Some(search) =
+                                    navigable.then(|| terminal::search_query(&answer)).flatten()
+                                {
+                                    return Ok(Some(LineInput::Move(search)));
+                                }
+                                return Ok(Some(LineInput::Line(answer)));
+                            }
+                            KeyCode::Char(_) if terminal::is_suspend_key(&key_event) => {
+                                terminal::suspend();
                             }
                             KeyCode::Char(c) => {
                                 buffer.push(c);
-                                print!("\x1B[u{}\x1B[s\x1B[B\r", c);
+                                console_print!("\x1B[u{}\x1B[s\x1B[B\r", c);
                                 io::stdout().flush()?;
+                                if let Some(on_edit) = on_edit.as_mut() {
+                                    on_edit(&buffer);
+                                }
                             }
                             KeyCode::Backspace if !buffer.is_empty() => {
                                 buffer.pop();
-                                print!("\x1B[u\x08 \x08\x1B[s\x1B[B\r");
+                                console_print!("\x1B[u\x08 \x08\x1B[s\x1B[B\r");
                                 io::stdout().flush()?;
+                                if let Some(on_edit) = on_edit.as_mut() {
+                                    on_edit(&buffer);
+                                }
                             }
                             _ => {}
                         }
@@ -958,31 +2250,191 @@ impl AiloopServer {
         }
     }
 
+    /// Wait up to `grace` for `u` (undo). `Some(())` when it was pressed.
+    fn read_undo_key(grace: Duration, cancelled: Arc<AtomicBool>) -> Result<Option<()>> {
+        let mut raw = RawMode::enable()?;
+
+        let countdown = CountdownRenderer::new(grace);
+        let mut last_rendered = None;
+        loop {
+            if raw.take_resumed() {
+                console_println!();
+                last_rendered = None;
+            }
+            let remaining = countdown.remaining_secs();
+            if cancelled.load(Ordering::Relaxed) || remaining == 0 {
+                console_print!("\r\x1B[2K");
+                io::stdout().flush().ok();
+                return Ok(None);
+            }
+            if last_rendered != Some(remaining) {
+                last_rendered = Some(remaining);
+                console_print!(
+                    "\r\x1B[2KReleasing approval in {} seconds (u=undo)",
+                    remaining
+                );
+                io::stdout().flush().ok();
+            }
+
+            if event::poll(Duration::from_millis(100))? {
+                if let Event::Key(key_event) = event::read()? {
+                    if key_event.kind == KeyEventKind::Press && terminal::is_suspend_key(&key_event)
+                    {
+                        terminal::suspend();
+                    } else if key_event.kind == KeyEventKind::Press
+                        && matches!(key_event.code, KeyCode::Char('u' | 'U'))
+                    {
+                        console_print!("\r\x1B[2K");
+                        io::stdout().flush().ok();
+                        return Ok(Some(()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether a one-key approval of `action` must be confirmed: the action is at a
+    /// `[verification]` risk level, or matches the channel's `[undo] risky_patterns`.
+    fn confirm_quick_approval(
+        message: &Message,
+        action: &str,
+        config: Option<&Configuration>,
+    ) -> bool {
+        config.is_some_and(|c| {
+            c.verification.rule_for(action).is_some() || c.undo.is_risky(&message.channel, action)
+        })
+    }
+
+    /// Second-factor check (`[verification]`) of an approval typed at the terminal. An approval
+    /// that fails it, or whose method is not set up, is turned into a denial.
+    async fn verify_approval(
+        message: &Message,
+        action: &str,
+        config: Option<&Configuration>,
+        reason: Option<String>,
+    ) -> (ResponseType, Option<String>) {
+        let Some(rules) = config.map(|c| &c.verification) else {
+            return (ResponseType::AuthorizationApproved, reason);
+        };
+        let Some((level, verifier)) = verification::verifier_for(rules, action) else {
+            return (ResponseType::AuthorizationApproved, reason);
+        };
+        let outcome = match verifier {
+            Ok(verifier) => {
+                console_print!("{}-risk action. {}: ", level, verifier.instruction());
+                let _ = io::stdout().flush();
+                let code = if verifier.needs_code() {
+                    let timeout = rules.timeout();
+                    tokio::task::spawn_blocking(move || Self::read_verification_code(timeout))
+                        .await
+                        .ok()
+                        .and_then(|read| read.ok())
+                        .flatten()
+                } else {
+                    None
+                };
+                if verifier.needs_code() && code.is_none() {
+                    Ok(false)
+                } else {
+                    verifier.verify(code.as_deref()).await
+                }
+            }
+            Err(e) => Err(e),
+        };
+        match outcome {
+            Ok(true) => {
+                console_println!("\nVerified");
+                (ResponseType::AuthorizationApproved, reason)
+            }
+            outcome => {
+                let detail = match outcome {
+                    Err(e) => format!("{}-risk verification unavailable: {:#}", level, e),
+                    _ => format!("{}-risk verification failed", level),
+                };
+                console_println!("\n{} - DENIED", detail);
+                log_security_event("authorization_unverified", &message.channel, None, &detail);
+                (
+                    ResponseType::AuthorizationDenied,
+                    Some(format!("Not verified: {}", detail)),
+                )
+            }
+        }
+    }
+
+    /// Read a verification code typed at the terminal; `None` on ESC or once `timeout` passes.
+    fn read_verification_code(timeout: Duration) -> Result<Option<String>> {
+        let mut raw = RawMode::enable()?;
+
+        let deadline = std::time::Instant::now() + timeout;
+        let mut buffer = String::new();
+        while std::time::Instant::now() < deadline {
+            if raw.take_resumed() {
+                console_print!("\r\nVerification code: {}", buffer);
+                io::stdout().flush()?;
+            }
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
+            if let Event::Key(key_event) = event::read()? {
+                if key_event.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key_event.code {
+                    KeyCode::Esc => return Ok(None),
+                    KeyCode::Enter => return Ok(Some(buffer)),
+                    KeyCode::Char(_) if terminal::is_suspend_key(&key_event) => terminal::suspend(),
+                    KeyCode::Char(c) if c.is_ascii_digit() => {
+                        buffer.push(c);
+                        console_print!("{}", c);
+                        io::stdout().flush()?;
+                    }
+                    KeyCode::Backspace if !buffer.is_empty() => {
+                        buffer.pop();
+                        console_print!("\x08 \x08");
+                        io::stdout().flush()?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read a yes/no answer. Anything else is handled by `invalid_input`: asked again, or
+    /// taken as the policy's decision. Tab, Up, Down and `/text` searches leave the prompt (see
+    /// [`Navigation`]). `a` and `d` followed by Enter answer too; with `confirm_quick`, the
+    /// approval `a` must then be confirmed with another `a`.
     fn read_authorization_with_esc(
+        hint: &str,
         timeout: Option<Duration>,
+        invalid_input: InvalidInputPolicy,
         cancelled: Arc<AtomicBool>,
-    ) -> Result<Option<ResponseType>> {
-        enable_raw_mode().context("Failed to enable raw mode")?;
-        let _guard = RawModeGuard;
+        confirm_quick: bool,
+    ) -> Result<Option<YesNoInput>> {
+        let mut raw = RawMode::enable()?;
 
         let mut buffer = String::new();
+        let mut confirming = false;
         let mut countdown: Option<CountdownRenderer> = timeout.map(CountdownRenderer::new);
         let mut countdown_enabled = true;
 
-        println!("\x1B[s");
+        console_println!("\x1B[s");
         io::stdout().flush()?;
 
         loop {
+            if raw.take_resumed() {
+                redraw_input(hint, &buffer);
+            }
             if cancelled.load(Ordering::Relaxed) {
-                print!("\r\x1B[2K\x1B[u");
+                console_print!("\r\x1B[2K\x1B[u");
                 io::stdout().flush().ok();
-                println!();
+                console_println!();
                 return Ok(None);
             }
 
             if let Some(cd) = &mut countdown {
                 if cd.remaining_secs() == 0 {
-                    print!("{}", cd.render_final());
+                    console_print!("{}", cd.render_final());
                     io::stdout().flush().ok();
                     return Err(anyhow::anyhow!("Authorization timed out"));
                 }
@@ -991,44 +2443,101 @@ impl AiloopServer {
             if event::poll(Duration::from_millis(100))? {
                 if let Event::Key(key_event) = event::read()? {
                     if key_event.kind == KeyEventKind::Press {
+                        if std::mem::take(&mut confirming) {
+                            if terminal::is_confirm_key(&key_event) {
+                                console_print!("\r\x1B[2K\x1B[u");
+                                io::stdout().flush().ok();
+                                console_println!();
+                                terminal::drain_input();
+                                return Ok(Some(YesNoInput::Answer(
+                                    ResponseType::AuthorizationApproved,
+                                    None,
+                                )));
+                            }
+                            // Any other key backs out of the approval.
+                            redraw_input(hint, &buffer);
+                            continue;
+                        }
+                        let navigation = buffer
+                            .is_empty()
+                            .then(|| terminal::navigation_key(&key_event))
+                            .flatten();
+                        if let Some(navigation) = navigation {
+                            console_print!("\r\x1B[2K\x1B[u");
+                            io::stdout().flush().ok();
+                            console_println!();
+                            return Ok(Some(YesNoInput::Move(navigation)));
+                        }
                         match key_event.code {
                             KeyCode::Esc => {
-                                print!("\r\x1B[2K\x1B[u");
+                                console_print!("\r\x1B[2K\x1B[u");
                                 io::stdout().flush().ok();
-                                println!();
+                                console_println!();
                                 return Ok(None);
                             }
                             KeyCode::Enter => {
-                                print!("\r\x1B[2K\x1B[u");
+                                console_print!("\r\x1B[2K\x1B[u");
                                 io::stdout().flush().ok();
-                                println!();
-
-                                let normalized = buffer.trim().to_lowercase();
-                                let decision = match normalized.as_str() {
-                                    "y" | "yes" | "authorized" | "approve" | "ok" => {
-                                        ResponseType::AuthorizationApproved
-                                    }
-                                    "n" | "no" | "denied" | "deny" | "reject" | "" => {
-                                        ResponseType::AuthorizationDenied
-                                    }
-                                    _ => {
-                                        eprintln!(
-                                            "Invalid input '{}'. Expected Y/n. Defaulting to DENIED.",
-                                            buffer.trim()
-                                        );
-                                        ResponseType::AuthorizationDenied
-                                    }
+                                console_println!();
+
+                                if let Some(duration) = snooze::parse_snooze(&buffer) {
+                                    return Ok(Some(YesNoInput::Snooze(duration)));
+                                }
+                                if let Some(search) = terminal::search_query(buffer.trim()) {
+                                    return Ok(Some(YesNoInput::Move(search)));
+                                }
+                                if confirm_quick && terminal::is_quick_approval(&buffer) {
+                                    confirming = true;
+                                    buffer.clear();
+                                    terminal::drain_input();
+                                    console_print!(
+                                        "High-risk action: press a to approve, any other key to go back: "
+                                    );
+                                    io::stdout().flush()?;
+                                    continue;
+                                }
+                                let (decision, reason) = match terminal::typed_decision(&buffer) {
+                                    Some(answer) => answer,
+                                    None => match invalid_input {
+                                        InvalidInputPolicy::Deny => {
+                                            eprintln!(
+                                                "Invalid input '{}'. Expected Y/n. Defaulting to DENIED.",
+                                                buffer.trim()
+                                            );
+                                            (ResponseType::AuthorizationDenied, None)
+                                        }
+                                        InvalidInputPolicy::Approve => {
+                                            eprintln!(
+                                                "Invalid input '{}'. Expected Y/n. Defaulting to APPROVED.",
+                                                buffer.trim()
+                                            );
+                                            (ResponseType::AuthorizationApproved, None)
+                                        }
+                                        InvalidInputPolicy::Reprompt => {
+                                            console_print!(
+                                                "Invalid input '{}'. Expected y or n.",
+                                                buffer.trim()
+                                            );
+                                            buffer.clear();
+                                            redraw_input(hint, &buffer);
+                                            continue;
+                                        }
+                                    },
                                 };
-                                return Ok(Some(decision));
+                                terminal::drain_input();
+                                return Ok(Some(YesNoInput::Answer(decision, reason)));
+                            }
+                            KeyCode::Char(_) if terminal::is_suspend_key(&key_event) => {
+                                terminal::suspend();
                             }
                             KeyCode::Char(c) => {
                                 buffer.push(c);
-                                print!("\x1B[u{}\x1B[s\x1B[B\r", c);
+                                console_print!("\x1B[u{}\x1B[s\x1B[B\r", c);
                                 io::stdout().flush()?;
                             }
                             KeyCode::Backspace if !buffer.is_empty() => {
                                 buffer.pop();
-                                print!("\x1B[u\x08 \x08\x1B[s\x1B[B\r");
+                                console_print!("\x1B[u\x08 \x08\x1B[s\x1B[B\r");
                                 io::stdout().flush()?;
                             }
                             _ => {}
@@ -1051,12 +2560,11 @@ impl AiloopServer {
     }
 }
 
-struct RawModeGuard;
-
-impl Drop for RawModeGuard {
-    fn drop(&mut self) {
-        disable_raw_mode().ok();
-    }
+/// Show the input line again after the process was resumed: the shell wrote over the old one.
+fn redraw_input(hint: &str, buffer: &str) {
+    console_print!("\r\n{}{}", hint, buffer);
+    console_println!("\x1B[s");
+    io::stdout().flush().ok();
 }
 
 /// Strip common Markdown syntax to produce a plain-text label for display.
@@ -1095,11 +2603,27 @@ async fn root_handler(
 
     match WebSocketUpgrade::from_request_parts(&mut parts, &state).await {
         Ok(upgrade) => {
+            // Set by the auth middleware; absent when auth is disabled.
+            let auth = parts
+                .extensions
+                .get::<crate::config::AuthIdentity>()
+                .cloned();
+            let declared = match declared_connection_type(parts.uri.query(), auth.as_ref()) {
+                Ok(declared) => declared,
+                Err(rejection) => return rejection.into_response(),
+            };
             let channel_manager = Arc::clone(&state.channel_manager);
             let default_channel = state.default_channel.clone();
             let message_history = Arc::clone(&state.message_history);
             let broadcast_manager = Arc::clone(&state.broadcast_manager);
+            let pending_registry = Arc::clone(&state.pending_prompt_registry);
+            let loops = Arc::clone(&state.loops);
+            let usage = Arc::clone(&state.usage);
+            let hooks = Arc::clone(&state.hooks);
+            let strict = state.strict;
             upgrade
+                .max_frame_size(MAX_FRAME_BYTES)
+                .max_message_size(MAX_FRAME_BYTES)
                 .on_upgrade(move |socket| {
                     AiloopServer::handle_ws_connection_inner(
                         socket,
@@ -1107,6 +2631,13 @@ async fn root_handler(
                         default_channel,
                         message_history,
                         broadcast_manager,
+                        pending_registry,
+                        loops,
+                        usage,
+                        hooks,
+                        auth,
+                        declared,
+                        strict,
                     )
                 })
                 .into_response()
@@ -1115,6 +2646,46 @@ async fn root_handler(
     }
 }
 
+/// The `connection` query parameter of a WS upgrade, checked against the caller's role.
+/// Unknown types are rejected with 400 and types the role does not allow with 403.
+fn declared_connection_type(
+    query: Option<&str>,
+    auth: Option<&crate::config::AuthIdentity>,
+) -> std::result::Result<Option<ConnectionType>, (StatusCode, axum::Json<serde_json::Value>)> {
+    let Some(value) = query.and_then(|q| {
+        q.split('&')
+            .find_map(|pair| pair.strip_prefix("connection="))
+    }) else {
+        return Ok(None);
+    };
+    let declared: ConnectionType = value.parse().map_err(|e: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({"error": e})),
+        )
+    })?;
+    if let Some(auth) = auth {
+        if auth.role < declared.required_role() {
+            log_security_event(
+                "connection_forbidden",
+                "*",
+                auth.usage_key().as_deref(),
+                &format!(
+                    "{:?} role may not connect as {}",
+                    auth.role,
+                    declared.as_str()
+                ),
+            );
+            let error = format!("Token may not open a {} connection", declared.as_str());
+            return Err((
+                StatusCode::FORBIDDEN,
+                axum::Json(serde_json::json!({"error": error})),
+            ));
+        }
+    }
+    Ok(Some(declared))
+}
+
 #[cfg(feature = "web-ui")]
 fn serve_embedded_ui_or_404(web_enabled: bool) -> axum::response::Response {
     if web_enabled {
@@ -1169,6 +2740,14 @@ pub fn router(
     let cors_layer = build_cors_layer(config.cors.as_ref());
     let inner = inner.layer(cors_layer);
 
+    // Peer IP filtering runs first, before auth and CORS.
+    let inner = match &config.ip_filter {
+        Some(filter) => inner.layer(crate::middleware::ip_filter::IpFilterLayer::new(
+            filter.clone(),
+        )),
+        None => inner,
+    };
+
     // Nest under base_path prefix if configured.
     // In axum 0.8, nest("/hil/", inner) matches both /hil/ and /hil/foo; nest("/hil", inner)
     // would not match /hil/ (only /hil/foo). Appending a trailing slash ensures the WS root
@@ -1185,9 +2764,13 @@ fn apply_auth_layer(router: axum::Router, config: &crate::config::ServeConfig) -
     let effective_tokens = config
         .auth
         .as_ref()
-        .map(|a| a.tokens.clone())
+        .map(|a| a.token_roles())
         .unwrap_or_default();
-    router.layer(crate::middleware::auth::AuthLayer::new(effective_tokens))
+    let mut layer = crate::middleware::auth::AuthLayer::with_roles(effective_tokens);
+    if let Some(jwt) = config.auth.as_ref().and_then(|a| a.jwt.clone()) {
+        layer = layer.with_jwt(jwt);
+    }
+    router.layer(layer)
 }
 
 #[cfg(not(feature = "auth"))]
@@ -1219,176 +2802,667 @@ fn build_cors_layer(
 
 /// Register providers and spawn background tasks.
 ///
-/// The returned handle resolves when all tasks have exited (after `token` is cancelled).
+/// Every task runs under the state's [`Supervisor`](crate::server::supervisor::Supervisor),
+/// which restarts it when it panics. The returned handle resolves when all tasks have exited
+/// (after `token` is cancelled).
 pub fn spawn_background_tasks(
     state: Arc<AiloopAppState>,
     _config: &crate::config::ServeConfig,
     token: CancellationToken,
 ) -> tokio::task::JoinHandle<()> {
-    let channel_manager = Arc::clone(&state.channel_manager);
     let broadcast_manager = Arc::clone(&state.broadcast_manager);
     let pending_registry = Arc::clone(&state.pending_prompt_registry);
+    let message_history = Arc::clone(&state.message_history);
     let provider_config = state.provider_config.clone();
+    let supervisor = Arc::clone(&state.supervisor);
 
     let is_shutting_down = Arc::clone(&state.is_shutting_down);
 
     tokio::spawn(async move {
-        // Register Telegram provider if configured (gated by `telegram` feature).
-        #[cfg(feature = "telegram")]
+        broadcast_manager
+            .set_capabilities(crate::server::capabilities::from_state(&state))
+            .await;
+        let mut handles = Vec::new();
+        // Start configured providers; each reads its replies in its own task.
         if let Some(ref cfg) = provider_config {
-            if cfg.providers.telegram.enabled {
-                let tok = std::env::var("AILOOP_TELEGRAM_BOT_TOKEN").ok();
-                let chat_id = cfg
-                    .providers
-                    .telegram
-                    .chat_id
-                    .as_ref()
-                    .filter(|s| !s.is_empty())
-                    .cloned();
-                match (tok, chat_id) {
-                    (Some(t), Some(c)) => {
-                        match crate::server::providers::TelegramSink::new(t.clone(), c) {
-                            Ok(sink) => {
-                                broadcast_manager
-                                    .add_notification_sink(Arc::new(sink))
-                                    .await;
-                                let reply_source: Arc<dyn ReplySource> =
-                                    Arc::new(crate::server::providers::TelegramReplySource::new(t));
-                                let registry = Arc::clone(&pending_registry);
-                                let token_tg = token.clone();
-                                tokio::spawn(async move {
-                                    loop {
-                                        tokio::select! {
-                                            _ = token_tg.cancelled() => break,
-                                            maybe = reply_source.next_reply() => {
-                                                if let Some(reply) = maybe {
-                                                    registry
-                                                        .submit_reply(
-                                                            reply.reply_to_message_id,
-                                                            reply.answer,
-                                                            reply.response_type,
-                                                        )
-                                                        .await;
-                                                }
-                                            }
-                                        }
-                                    }
-                                });
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to create Telegram sink: {}", e);
-                            }
-                        }
-                    }
-                    (None, _) => {
-                        tracing::warn!("Telegram provider skipped: token not set");
-                    }
-                    (_, None) => {
-                        tracing::warn!("Telegram provider skipped: chat_id not configured");
-                    }
-                }
+            broadcast_manager
+                .set_delivery_policy(DeliveryPolicy::from(&cfg.providers.delivery))
+                .await;
+            pending_registry.set_team(cfg.team.clone()).await;
+            pending_registry
+                .set_invalid_input(cfg.authorization.invalid_input)
+                .await;
+            pending_registry
+                .set_verification(cfg.verification.clone())
+                .await;
+            message_history.reads().set_team(cfg.team.clone());
+            broadcast_manager.subscriptions().set_team(cfg.team.clone());
+            broadcast_manager
+                .set_on_call_router(OnCallRouter::from_config(&cfg.oncall).map(|r| {
+                    r.with_team(cfg.team.clone())
+                        .with_away(Arc::clone(broadcast_manager.away()))
+                }))
+                .await;
+            broadcast_manager.set_mirror_rules(cfg.mirror.clone()).await;
+            match JiraSink::from_config(cfg) {
+                Ok(jira) => broadcast_manager.set_jira(jira).await,
+                Err(e) => tracing::error!(error = %e, "Jira escalation disabled"),
+            }
+            broadcast_manager.slas().set_rules(cfg.sla.clone());
+            broadcast_manager.typing().set_config(cfg.typing.clone());
+            broadcast_manager
+                .queue_positions()
+                .set_boost_config(cfg.boost.clone());
+            message_history.set_retention(cfg.history.clone()).await;
+            message_history.incidents().set_config(cfg.incident.clone());
+            state.usage.set_incident_config(cfg.incident.clone()).await;
+            for provider in state.providers.start(cfg, &state) {
+                broadcast_manager
+                    .add_notification_sink(Arc::new(ProviderSink(Arc::clone(&provider))))
+                    .await;
+                let registry = Arc::clone(&pending_registry);
+                let history = Arc::clone(&message_history);
+                let broadcast = Arc::clone(&broadcast_manager);
+                let replies_token = token.clone();
+                supervisor.supervise(
+                    format!("provider:{}", provider.name()),
+                    token.clone(),
+                    move || {
+                        tokio::spawn(run_provider_replies(
+                            Arc::clone(&provider),
+                            Arc::clone(&registry),
+                            Arc::clone(&history),
+                            Arc::clone(&broadcast),
+                            replies_token.clone(),
+                        ))
+                    },
+                );
             }
         }
 
-        // Main message processing loop with cancellation support.
-        let mut check_interval = interval(Duration::from_millis(100));
+        handles.push(supervisor.supervise("messages", token.clone(), {
+            let state = Arc::clone(&state);
+            let token = token.clone();
+            move || spawn_message_loop(Arc::clone(&state), token.clone())
+        }));
+        handles.push(supervisor.supervise("scheduler", token.clone(), {
+            let state = Arc::clone(&state);
+            let token = token.clone();
+            move || crate::server::scheduler::spawn_scheduler(Arc::clone(&state), token.clone())
+        }));
+        if use_terminal() {
+            handles.push(supervisor.supervise("suspend", token.clone(), {
+                let token = token.clone();
+                move || terminal::spawn_suspend_handler(token.clone())
+            }));
+        }
+        handles.push(supervisor.supervise("retention", token.clone(), {
+            let history = Arc::clone(&message_history);
+            let token = token.clone();
+            move || crate::server::history::spawn_retention(Arc::clone(&history), token.clone())
+        }));
+        if provider_config
+            .as_ref()
+            .is_some_and(|cfg| !cfg.sla.is_empty())
+        {
+            let state = Arc::clone(&state);
+            let sla_token = token.clone();
+            handles.push(supervisor.supervise("sla", token.clone(), move || {
+                crate::server::sla::spawn_sla_monitor(Arc::clone(&state), sla_token.clone())
+            }));
+        }
+        let digest = provider_config.as_ref().filter(|cfg| cfg.digest.enabled);
+        if let Some(cfg) = digest {
+            if let Some(schedule) = crate::server::digest::digest_schedule(&cfg.digest) {
+                let state = Arc::clone(&state);
+                let config = cfg.digest.clone();
+                let digest_token = token.clone();
+                handles.push(supervisor.supervise("digest", token.clone(), move || {
+                    crate::server::digest::spawn_digest(
+                        Arc::clone(&state),
+                        config.clone(),
+                        schedule.clone(),
+                        digest_token.clone(),
+                    )
+                }));
+            }
+        }
 
+        token.cancelled().await;
+        is_shutting_down.store(true, std::sync::atomic::Ordering::Relaxed);
+        tracing::info!("Background task loop stopping: shutdown signal received");
+        for handle in handles {
+            let _ = handle.await;
+        }
+        tracing::info!("Background tasks stopped");
+    })
+}
+
+/// Take queued messages off each channel and put them to the humans every 100ms until `token`
+/// is cancelled.
+fn spawn_message_loop(
+    state: Arc<AiloopAppState>,
+    token: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut check_interval = interval(Duration::from_millis(100));
         loop {
             tokio::select! {
-                _ = token.cancelled() => {
-                    is_shutting_down.store(true, std::sync::atomic::Ordering::Relaxed);
-                    tracing::info!("Background task loop stopping: shutdown signal received");
-                    break;
-                }
+                _ = token.cancelled() => break,
                 _ = check_interval.tick() => {
                     process_messages_tick(
-                        &channel_manager,
-                        &broadcast_manager,
-                        &pending_registry,
-                        provider_config.as_ref(),
+                        &state.channel_manager,
+                        &state.message_history,
+                        &state.broadcast_manager,
+                        &state.pending_prompt_registry,
+                        &state.attachments,
+                        state.signer.as_ref(),
+                        &state.hooks,
+                        state.provider_config.as_ref(),
+                        state.echo.as_ref(),
                     )
                     .await;
                 }
             }
         }
-
-        tracing::info!("Background tasks stopped");
     })
 }
 
+/// Initialise `provider`, then feed its replies into the pending prompt registry until
+/// `token` is cancelled. Replies are attributed to `<provider>:<username or id>`; `/claim` and
+/// `/release` claim or release a prompt instead of answering it, and `/read <id>` (sent by a
+/// *Mark read* button) marks a notification read.
+async fn run_provider_replies(
+    provider: Arc<dyn Provider>,
+    registry: Arc<PendingPromptRegistry>,
+    history: Arc<crate::server::history::MessageHistory>,
+    broadcast: Arc<crate::server::broadcast::BroadcastManager>,
+    token: CancellationToken,
+) {
+    if let Err(e) = provider.init().await {
+        tracing::error!(provider = provider.name(), error = %e, "provider init failed");
+    }
+    let Some(mut replies) = provider.reply_stream() else {
+        return;
+    };
+    loop {
+        tokio::select! {
+            _ = token.cancelled() => break,
+            maybe = replies.next() => {
+                let Some(reply) = maybe else { break };
+                let responder = format!(
+                    "{}:{}",
+                    provider.name(),
+                    reply
+                        .sender_name
+                        .or(reply.sender_id)
+                        .unwrap_or_else(|| "unknown".to_string())
+                );
+                if let Some(message_id) = reply.answer.as_deref().and_then(reads::parse_command) {
+                    history.reads().mark(message_id, &responder);
+                    continue;
+                }
+                if let Some(command) = reply.answer.as_deref().and_then(claims::parse_command) {
+                    claim_from_provider(
+                        &registry,
+                        &history,
+                        &broadcast,
+                        command,
+                        reply.prompt_id,
+                        reply.reply_to_message_id.as_deref(),
+                        reply.channel.as_deref(),
+                        &responder,
+                    )
+                    .await;
+                    continue;
+                }
+                if let Some(prompt_id) = reply.prompt_id {
+                    registry
+                        .submit_reply_for_message_as(
+                            prompt_id,
+                            reply.answer,
+                            reply.response_type,
+                            Some(responder),
+                        )
+                        .await;
+                    continue;
+                }
+                registry
+                    .submit_reply_as(
+                        reply.reply_to_message_id,
+                        reply.answer,
+                        reply.response_type,
+                        Some(responder),
+                        reply.channel.as_deref(),
+                    )
+                    .await;
+            }
+        }
+    }
+}
+
+/// Claim or release the prompt a provider's `/claim` or `/release` refers to (`prompt_id` when
+/// the reply names it), announcing the change on its channel.
+#[allow(clippy::too_many_arguments)]
+async fn claim_from_provider(
+    registry: &PendingPromptRegistry,
+    history: &crate::server::history::MessageHistory,
+    broadcast: &crate::server::broadcast::BroadcastManager,
+    command: claims::ClaimCommand,
+    prompt_id: Option<uuid::Uuid>,
+    reply_to_message_id: Option<&str>,
+    channel: Option<&str>,
+    responder: &str,
+) {
+    let release = command == claims::ClaimCommand::Release;
+    let target = match prompt_id {
+        Some(prompt_id) => Some(prompt_id),
+        None => {
+            registry
+                .claim_target(reply_to_message_id, channel, responder, release)
+                .await
+        }
+    };
+    let Some(prompt_id) = target else {
+        tracing::debug!(responder, "No prompt to claim or release");
+        return;
+    };
+    let result = if release {
+        registry.release(prompt_id, responder).await
+    } else {
+        registry.claim(prompt_id, responder).await
+    };
+    let claim = match result {
+        Ok(claim) => claim,
+        Err(e) => {
+            tracing::info!(responder, prompt_id = %prompt_id, error = %e, "Claim refused");
+            return;
+        }
+    };
+    let pending = registry.snapshot_pending(None).await;
+    if let Some(prompt) = pending.iter().find(|p| p.message_id == prompt_id) {
+        let notice = claims::notice(&prompt.channel, prompt_id, &prompt.label, &claim, !release);
+        claims::announce(history, broadcast, notice).await;
+    }
+}
+
+/// Show a follow-up prompt with the earlier questions and answers of its thread
+/// (`metadata.thread`), at the terminal and in providers.
+async fn attach_thread(
+    message_history: &crate::server::history::MessageHistory,
+    message: &mut Message,
+) {
+    let thread = message_history.thread_context(message).await;
+    if thread.is_empty() {
+        return;
+    }
+    let thread = serde_json::to_value(thread).unwrap_or_default();
+    match message.metadata.as_mut().and_then(|m| m.as_object_mut()) {
+        Some(map) => {
+            map.insert("thread".to_string(), thread);
+        }
+        None => message.metadata = Some(serde_json::json!({ "thread": thread })),
+    }
+}
+
 /// Process one batch of queued messages across all active channels.
+#[allow(clippy::too_many_arguments)]
 async fn process_messages_tick(
     channel_manager: &Arc<ChannelIsolation>,
+    message_history: &Arc<crate::server::history::MessageHistory>,
     broadcast_manager: &Arc<crate::server::broadcast::BroadcastManager>,
     pending_registry: &Arc<PendingPromptRegistry>,
+    attachments: &Arc<AttachmentStore>,
+    signer: Option<&Arc<ResponseSigner>>,
+    hooks: &MessageHooks,
     config: Option<&Configuration>,
+    echo: Option<&EchoConfig>,
 ) {
+    // Snoozed prompts whose time is up go back in line, unless answered meanwhile.
+    for prompt in channel_manager.take_due_snoozed(chrono::Utc::now()) {
+        if !message_history.has_response(&prompt.id).await {
+            let channel = prompt.channel.clone();
+            channel_manager.enqueue_message(&channel, prompt);
+        }
+    }
+
     let active_channels = channel_manager.get_active_channels();
 
-    for channel_name in active_channels {
-        if let Some(message) = channel_manager.dequeue_message(&channel_name) {
+    'channels: for channel_name in active_channels {
+        // Held channels keep their queue until resumed.
+        if channel_manager.held_since(&channel_name).is_some() {
+            continue;
+        }
+        // The operator may move on to another prompt of the channel from the terminal.
+        let mut next = channel_manager.dequeue_message(&channel_name);
+        while let Some(mut message) = next.take() {
             tracing::debug!("Processing message from queue [{}]", channel_name);
+            attach_thread(message_history, &mut message).await;
+            if let Some(echo) = echo {
+                answer_with_echo(
+                    &message,
+                    echo,
+                    message_history,
+                    broadcast_manager,
+                    pending_registry,
+                    signer,
+                    hooks,
+                )
+                .await;
+                continue 'channels;
+            }
 
-            let response_type = match &message.content {
-                MessageContent::Decision {
-                    decision_id,
-                    summary,
-                    context_markdown,
-                    options,
-                    recommendation,
-                    timeout_seconds,
-                } => {
-                    AiloopServer::handle_decision(
-                        message.clone(),
-                        decision_id.clone(),
-                        summary.clone(),
-                        context_markdown.clone(),
-                        options.clone(),
-                        recommendation.clone(),
-                        *timeout_seconds,
-                        Arc::clone(broadcast_manager),
-                        Arc::clone(pending_registry),
-                        config,
-                    )
-                    .await
+            broadcast_manager.queue_positions().set_showing(true);
+            report_queue_positions(channel_manager, broadcast_manager).await;
+            let deadline = watchdog::deadline(&message, config, CHAT_REPLY_TIMEOUT);
+            let processing = async {
+                match &message.content {
+                    MessageContent::Decision {
+                        decision_id,
+                        summary,
+                        context_markdown,
+                        options,
+                        recommendation,
+                        timeout_seconds,
+                    } => {
+                        AiloopServer::handle_decision(
+                            message.clone(),
+                            decision_id.clone(),
+                            summary.clone(),
+                            context_markdown.clone(),
+                            options.clone(),
+                            recommendation.clone(),
+                            *timeout_seconds,
+                            Arc::clone(broadcast_manager),
+                            Arc::clone(pending_registry),
+                            Arc::clone(attachments),
+                            signer.cloned(),
+                            hooks,
+                            config,
+                        )
+                        .await
+                    }
+                    MessageContent::Authorization {
+                        action,
+                        timeout_seconds,
+                        ..
+                    } => {
+                        AiloopServer::handle_authorization(
+                            message.clone(),
+                            action.clone(),
+                            *timeout_seconds,
+                            Arc::clone(broadcast_manager),
+                            Arc::clone(pending_registry),
+                            signer.cloned(),
+                            hooks,
+                            config,
+                        )
+                        .await
+                    }
+                    MessageContent::Notification { text, priority } => {
+                        AiloopServer::handle_notification(
+                            message.agent_heading(),
+                            text.clone(),
+                            priority.clone(),
+                        );
+                        if let Some(attachment) = MessageAttachment::of(&message) {
+                            AiloopServer::show_attachment(&attachment, attachments).await;
+                        }
+                        // Shown to the operator at the terminal, so read by them.
+                        if use_terminal() && is_agent_notification(&message) {
+                            message_history.reads().mark(message.id, OPERATOR_RESPONDER);
+                        }
+                        PromptResult::Answered(ResponseType::Text)
+                    }
+                    MessageContent::Chat {
+                        conversation_id,
+                        text,
+                        closed,
+                    } => {
+                        AiloopServer::handle_chat(
+                            &message,
+                            *conversation_id,
+                            text,
+                            *closed,
+                            broadcast_manager,
+                            message_history,
+                            hooks,
+                        )
+                        .await
+                    }
+                    MessageContent::Navigate {
+                        url,
+                        timeout_seconds,
+                    } => {
+                        AiloopServer::handle_navigate(
+                            message.clone(),
+                            url.clone(),
+                            *timeout_seconds,
+                            Arc::clone(broadcast_manager),
+                            Arc::clone(pending_registry),
+                            signer.cloned(),
+                            hooks,
+                            config,
+                        )
+                        .await
+                    }
+                    _ => PromptResult::Answered(ResponseType::Text),
                 }
-                MessageContent::Authorization {
-                    action,
-                    timeout_seconds,
-                    ..
-                } => {
-                    AiloopServer::handle_authorization(
-                        message.clone(),
-                        action.clone(),
-                        *timeout_seconds,
-                        Arc::clone(broadcast_manager),
-                        Arc::clone(pending_registry),
-                        config,
-                    )
-                    .await
+            };
+            let finished = watchdog::run(deadline, processing).await;
+            broadcast_manager.queue_positions().set_showing(false);
+            let Some(result) = finished else {
+                give_up(
+                    &message,
+                    deadline.unwrap_or_default(),
+                    message_history,
+                    broadcast_manager,
+                    pending_registry,
+                )
+                .await;
+                continue 'channels;
+            };
+
+            let is_prompt = matches!(
+                message.content,
+                MessageContent::Decision { .. }
+                    | MessageContent::Authorization { .. }
+                    | MessageContent::Navigate { .. }
+            );
+            if is_prompt {
+                let state = match &result {
+                    PromptResult::Answered(ResponseType::Cancelled) => "cancelled",
+                    PromptResult::Answered(ResponseType::Timeout) => "timeout",
+                    PromptResult::Answered(_) => "answered",
+                    PromptResult::Snoozed(_) => "snoozed",
+                    PromptResult::Moved(_) => "queued",
+                };
+                events::prompt(message.id, &channel_name, state);
+            }
+            match result {
+                PromptResult::Answered(ResponseType::Cancelled) => {
+                    channel_manager.enqueue_message(&channel_name, message);
                 }
-                MessageContent::Notification { text, priority } => {
-                    AiloopServer::handle_notification(text.clone(), priority.clone());
-                    ResponseType::Text
+                PromptResult::Snoozed(duration) => {
+                    let until =
+                        chrono::Utc::now() + chrono::Duration::seconds(duration.as_secs() as i64);
+                    console_println!("\nSnoozed until {}\n", until.format("%H:%M UTC"));
+                    let notice = snooze::snooze_notice(&message, until);
+                    message_history
+                        .add_message(&channel_name, notice.clone())
+                        .await;
+                    broadcast_manager.broadcast_to_viewers_only(&notice).await;
+                    channel_manager.snooze(&channel_name, message, until);
                 }
-                MessageContent::Navigate { url } => {
-                    AiloopServer::handle_navigate(
-                        message.clone(),
-                        url.clone(),
-                        Arc::clone(broadcast_manager),
-                        Arc::clone(pending_registry),
-                        config,
+                PromptResult::Moved(navigation) => {
+                    next = move_prompt(
+                        channel_manager,
+                        message_history,
+                        &channel_name,
+                        message,
+                        navigation,
                     )
-                    .await
+                    .await;
                 }
-                _ => ResponseType::Text,
-            };
+                PromptResult::Answered(_) => {}
+            }
+        }
+    }
+}
 
-            if matches!(response_type, ResponseType::Cancelled) {
-                channel_manager.enqueue_message(&channel_name, message);
+/// Messages listed for a `/text` search at a terminal prompt.
+const SEARCH_RESULTS: usize = 10;
+
+/// Put a prompt the operator moved away from at the terminal back in its channel's queue, and
+/// return the one to show next on the same channel, if any (see [`Navigation`]).
+async fn move_prompt(
+    channel_manager: &ChannelIsolation,
+    message_history: &crate::server::history::MessageHistory,
+    channel: &str,
+    message: Message,
+    navigation: Navigation,
+) -> Option<Message> {
+    match navigation {
+        Navigation::NextChannel => {
+            channel_manager.requeue_front(channel, message);
+            None
+        }
+        Navigation::NextPrompt => {
+            channel_manager.enqueue_message(channel, message);
+            channel_manager.dequeue_message(channel)
+        }
+        Navigation::PreviousPrompt => {
+            let previous = channel_manager.dequeue_last(channel);
+            channel_manager.requeue_front(channel, message);
+            previous.or_else(|| channel_manager.dequeue_message(channel))
+        }
+        Navigation::Search(query) => {
+            let found = message_history.search(&query, SEARCH_RESULTS).await;
+            if found.is_empty() {
+                console_println!("\nNothing in history mentions \"{}\"\n", query);
+            } else {
+                console_println!("\nHistory mentioning \"{}\", newest first:", query);
+                for m in &found {
+                    let text = searchable_text(m).unwrap_or_default().replace('\n', " ");
+                    console_println!(
+                        "  {} [{}] {}: {}",
+                        m.timestamp.format("%m-%d %H:%M"),
+                        m.channel,
+                        m.speaker(),
+                        text
+                    );
+                }
+                console_println!();
             }
+            Some(message)
+        }
+    }
+}
+
+/// Tell agents whose prompts wait in a queue where they now stand, if that changed.
+async fn report_queue_positions(
+    channel_manager: &ChannelIsolation,
+    broadcast_manager: &crate::server::broadcast::BroadcastManager,
+) {
+    let positions = broadcast_manager.queue_positions();
+    if !positions.is_tracking() {
+        return;
+    }
+    let metrics = broadcast_manager.responses().metrics(None);
+    let notices = positions.updates(&channel_manager.get_queued_messages(None), |channel| {
+        metrics
+            .channels
+            .get(channel)
+            .and_then(|summary| summary.median_latency_seconds)
+    });
+    for (connection_id, notice) in notices {
+        broadcast_manager
+            .send_to_connection(connection_id, &notice)
+            .await;
+    }
+}
+
+/// Echo mode: log `message` and answer it at once from `canned`, without a human.
+async fn answer_with_echo(
+    message: &Message,
+    canned: &EchoConfig,
+    message_history: &crate::server::history::MessageHistory,
+    broadcast_manager: &crate::server::broadcast::BroadcastManager,
+    pending_registry: &PendingPromptRegistry,
+    signer: Option<&Arc<ResponseSigner>>,
+    hooks: &MessageHooks,
+) {
+    let logged = |label: &str, message: &Message| {
+        let json = serde_json::to_string(message).unwrap_or_default();
+        tracing::info!(channel = %message.channel, message_id = %message.id, "echo {}: {}", label, json);
+        console_println!("[echo] {} [{}] {}", label, message.channel, json);
+    };
+    logged("received", message);
+    let Some(mut reply) = echo::reply(message, canned) else {
+        return;
+    };
+    if reply.correlation_id.is_none() {
+        // A chat reply, shown to viewers like one typed at the terminal.
+        let reply = match hooks.outbound(reply).await {
+            HookOutcome::Pass(reply) => *reply,
+            HookOutcome::Blocked(reason) => {
+                console_println!("[echo] Reply blocked: {}", reason);
+                return;
+            }
+        };
+        logged("replied", &reply);
+        message_history
+            .add_message(&reply.channel, reply.clone())
+            .await;
+        broadcast_manager.broadcast_to_viewers_only(&reply).await;
+        return;
+    }
+    AiloopServer::attribute_response(
+        &mut reply,
+        pending_registry,
+        signer.map(|s| s.as_ref()),
+        hooks,
+    )
+    .await;
+    events::prompt(message.id, &message.channel, "answered");
+    logged("answered", &reply);
+    message_history
+        .add_message(&reply.channel, reply.clone())
+        .await;
+    broadcast_manager.broadcast_message(&reply).await;
+}
+
+/// End `message`, still being processed past its `deadline`, as errored: forget its pending
+/// entry, unblock the agent waiting on it and alert the channel.
+async fn give_up(
+    message: &Message,
+    deadline: Duration,
+    message_history: &crate::server::history::MessageHistory,
+    broadcast_manager: &crate::server::broadcast::BroadcastManager,
+    pending_registry: &PendingPromptRegistry,
+) {
+    tracing::error!(
+        channel = %message.channel,
+        message_id = %message.id,
+        deadline_secs = deadline.as_secs(),
+        "Message still being processed past its deadline; ending it as errored"
+    );
+    pending_registry.discard(message.id).await;
+    if message.prompt_text().is_some() {
+        events::prompt(message.id, &message.channel, "errored");
+        if !message_history.has_response(&message.id).await {
+            let reply = watchdog::errored_reply(message, deadline);
+            message_history
+                .add_message(&reply.channel, reply.clone())
+                .await;
+            broadcast_manager.broadcast_message(&reply).await;
         }
     }
+    let alert = watchdog::alert(message, deadline);
+    message_history
+        .add_message(&alert.channel, alert.clone())
+        .await;
+    broadcast_manager.broadcast_message(&alert).await;
 }
 
 /// Composes WS upgrade + optional UI + REST into a single Axum Router (legacy convenience wrapper).
@@ -1401,6 +3475,7 @@ pub fn create_server_router(state: AiloopAppState, web: bool) -> axum::Router {
         web,
         auth: None,
         cors: None,
+        ip_filter: None,
     };
     let state_arc = Arc::new(AiloopAppState { web, ..state });
     router(state_arc, &serve_config).expect("create_server_router: invalid config")
@@ -0,0 +1,265 @@
+//! Periodic digest of response metrics
+//!
+//! With `[digest] enabled = true`, a notification summarising the prompts asked since the
+//! previous digest is posted on the configured channel every time the schedule matches (weekly
+//! by default): how many were answered or timed out, approvals and denials, the busiest
+//! channels and the slowest responses. It is built from the [`ResponseLog`] the broadcast
+//! manager keeps.
+//!
+//! [`ResponseLog`]: crate::server::stats::ResponseLog
+
+use crate::server::stats::ResponseRecord;
+use crate::state::AiloopAppState;
+use ailoop_core::models::{
+    CronSchedule, DigestConfig, Message, MessageContent, NotificationPriority, ResponseMetrics,
+    ResponseType, SenderType,
+};
+use chrono::{DateTime, Local, Utc};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+/// Channels and responses listed in each section.
+const TOP: usize = 5;
+/// Prompt text shown for a slow response, in characters.
+const PROMPT_CHARS: usize = 60;
+
+/// The digest notification for the period `metrics` covers, up to `now`.
+pub fn digest_message(
+    config: &DigestConfig,
+    metrics: &ResponseMetrics,
+    records: &[ResponseRecord],
+    now: DateTime<Utc>,
+) -> Message {
+    let mut message = Message::new(
+        config.channel().to_string(),
+        SenderType::System,
+        MessageContent::Notification {
+            text: compose(metrics, records, now),
+            priority: NotificationPriority::Low,
+        },
+    );
+    message.timestamp = now;
+    message.metadata = Some(serde_json::json!({
+        "digest": { "since": metrics.since, "until": now }
+    }));
+    message
+}
+
+fn compose(metrics: &ResponseMetrics, records: &[ResponseRecord], now: DateTime<Utc>) -> String {
+    let local = |t: DateTime<Utc>| t.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+    let mut lines = vec![match metrics.since {
+        Some(since) => format!("Digest for {} to {}", local(since), local(now)),
+        None => format!("Digest up to {}", local(now)),
+    }];
+
+    let total = |f: fn(&ailoop_core::models::ResponseSummary) -> u64| -> u64 {
+        metrics.channels.values().map(f).sum()
+    };
+    let asked = total(|s| s.asked);
+    if asked == 0 && records.is_empty() {
+        lines.push("No prompts were asked.".to_string());
+        return lines.join("\n");
+    }
+    lines.push(format!(
+        "Prompts: {} asked, {} answered, {} timed out",
+        asked,
+        total(|s| s.answered),
+        total(|s| s.timed_out)
+    ));
+    lines.push(format!(
+        "Authorizations: {} approved, {} denied",
+        total(|s| s.approved),
+        total(|s| s.denied)
+    ));
+
+    let mut busiest: Vec<(&String, u64)> = metrics
+        .channels
+        .iter()
+        .map(|(channel, s)| (channel, s.asked))
+        .filter(|(_, asked)| *asked > 0)
+        .collect();
+    busiest.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    if !busiest.is_empty() {
+        let list: Vec<String> = busiest
+            .iter()
+            .take(TOP)
+            .map(|(channel, asked)| format!("{} ({})", channel, asked))
+            .collect();
+        lines.push(format!("Busiest channels: {}", list.join(", ")));
+    }
+
+    let mut slowest: Vec<&ResponseRecord> = records
+        .iter()
+        .filter(|r| r.outcome != ResponseType::Timeout)
+        .collect();
+    slowest.sort_by(|a, b| b.latency_seconds().total_cmp(&a.latency_seconds()));
+    if !slowest.is_empty() {
+        lines.push("Slowest responses:".to_string());
+        for record in slowest.into_iter().take(TOP) {
+            let mut prompt: String = record.prompt.chars().take(PROMPT_CHARS).collect();
+            if record.prompt.chars().count() > PROMPT_CHARS {
+                prompt.push('…');
+            }
+            lines.push(format!(
+                "  {} [{}] {} ({})",
+                duration(record.latency_seconds()),
+                record.channel,
+                prompt,
+                record.responder.as_deref().unwrap_or("unknown")
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+/// `42s`, `3m10s` or `2h05m`.
+pub(crate) fn duration(seconds: f64) -> String {
+    let secs = seconds.round() as u64;
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Post the digest on its channel, recorded in history, to the configured provider only or
+/// else to every provider.
+pub async fn send_digest(
+    state: &AiloopAppState,
+    config: &DigestConfig,
+    since: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) {
+    let log = state.broadcast_manager.responses();
+    let message = digest_message(config, &log.metrics(since), &log.records(since), now);
+    tracing::info!(message_id = %message.id, channel = %message.channel, "Sending digest");
+    state
+        .message_history
+        .add_message(&message.channel, message.clone())
+        .await;
+    match &config.provider {
+        Some(provider) => {
+            if !state
+                .broadcast_manager
+                .broadcast_to_provider(&message, provider)
+                .await
+            {
+                tracing::warn!(provider = %provider, "Digest provider is not running");
+            }
+        }
+        None => state.broadcast_manager.broadcast_message(&message).await,
+    }
+}
+
+/// The parsed `config.schedule`, or `None` (logged) when it does not parse.
+pub(crate) fn digest_schedule(config: &DigestConfig) -> Option<CronSchedule> {
+    match config.schedule().parse() {
+        Ok(schedule) => Some(schedule),
+        Err(e) => {
+            tracing::error!(error = %e, "Invalid digest schedule; digest disabled");
+            None
+        }
+    }
+}
+
+/// Send a digest every time `schedule` matches until `token` is cancelled.
+pub(crate) fn spawn_digest(
+    state: Arc<AiloopAppState>,
+    config: DigestConfig,
+    schedule: CronSchedule,
+    token: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut since = None;
+        loop {
+            let Some(next) = schedule.next_after(&Local::now()) else {
+                tracing::warn!(schedule = %schedule, "Digest schedule never matches");
+                break;
+            };
+            let wait = (next.with_timezone(&Utc) - Utc::now())
+                .to_std()
+                .unwrap_or_default();
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tokio::time::sleep(wait) => {
+                    let now = Utc::now();
+                    send_digest(&state, &config, since, now).await;
+                    since = Some(now);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::ResponseSummary;
+    use uuid::Uuid;
+
+    fn record(channel: &str, prompt: &str, latency: i64, outcome: ResponseType) -> ResponseRecord {
+        let asked_at = Utc::now() - chrono::Duration::hours(3);
+        ResponseRecord {
+            prompt_id: Uuid::new_v4(),
+            channel: channel.to_string(),
+            prompt: prompt.to_string(),
+            responder: (outcome != ResponseType::Timeout).then(|| "telegram:alice".to_string()),
+            asked_at,
+            settled_at: asked_at + chrono::Duration::seconds(latency),
+            outcome,
+        }
+    }
+
+    fn summary(asked: u64, answered: u64, approved: u64, denied: u64) -> ResponseSummary {
+        ResponseSummary {
+            asked,
+            answered,
+            approved,
+            denied,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn digest_lists_totals_busiest_channels_and_slowest_responses() {
+        let mut metrics = ResponseMetrics::default();
+        metrics.channels.insert("dev".into(), summary(2, 2, 0, 0));
+        metrics.channels.insert("prod".into(), summary(5, 4, 3, 1));
+        let mut timed_out = summary(0, 0, 0, 0);
+        timed_out.timed_out = 1;
+        metrics.channels.insert("quiet".into(), timed_out);
+        let records = vec![
+            record(
+                "prod",
+                "Deploy v2?",
+                190,
+                ResponseType::AuthorizationApproved,
+            ),
+            record("dev", "Which branch?", 7500, ResponseType::Text),
+            record("quiet", "Anyone?", 9000, ResponseType::Timeout),
+        ];
+        let config = DigestConfig {
+            channel: Some("ops".into()),
+            ..Default::default()
+        };
+
+        let message = digest_message(&config, &metrics, &records, Utc::now());
+        assert_eq!(message.channel, "ops");
+        let MessageContent::Notification { text, .. } = &message.content else {
+            panic!("expected a notification");
+        };
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[1], "Prompts: 7 asked, 6 answered, 1 timed out");
+        assert_eq!(lines[2], "Authorizations: 3 approved, 1 denied");
+        assert_eq!(lines[3], "Busiest channels: prod (5), dev (2)");
+        assert_eq!(lines[5], "  2h05m [dev] Which branch? (telegram:alice)");
+        assert_eq!(lines[6], "  3m10s [prod] Deploy v2? (telegram:alice)");
+        assert_eq!(lines.len(), 7);
+
+        let empty = digest_message(&config, &ResponseMetrics::default(), &[], Utc::now());
+        let MessageContent::Notification { text, .. } = &empty.content else {
+            panic!("expected a notification");
+        };
+        assert!(text.ends_with("No prompts were asked."));
+    }
+}
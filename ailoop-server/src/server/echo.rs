@@ -0,0 +1,165 @@
+//! Local echo mode (`ailoop serve --echo`)
+//!
+//! For developing agents without a human at the terminal or a provider set up: every message is
+//! logged as it comes off the queue, and prompts are answered on the spot from `[echo]`
+//! instead of being put to anyone. Decisions get the configured option, else the recommended
+//! one, else the first; authorizations and navigation requests are approved unless configured
+//! otherwise; agent chat lines get a canned reply. Answers carry `metadata.echo: true`.
+
+use ailoop_core::models::{EchoConfig, Message, MessageContent, ResponseType, SenderType};
+
+/// The canned answer to `message`, or `None` for messages nobody answers (notifications,
+/// responses, chat lines from humans or closing a conversation).
+pub fn reply(message: &Message, config: &EchoConfig) -> Option<Message> {
+    match &message.content {
+        MessageContent::Decision {
+            options,
+            recommendation,
+            ..
+        } => {
+            let index = config
+                .decision
+                .as_ref()
+                .and_then(|id| options.iter().position(|o| &o.id == id))
+                .or_else(|| {
+                    recommendation
+                        .as_ref()
+                        .and_then(|r| options.iter().position(|o| o.id == r.option_id))
+                })
+                .unwrap_or(0);
+            Some(match options.get(index) {
+                Some(option) => response(
+                    message,
+                    ResponseType::Text,
+                    Some(option.id.clone()),
+                    serde_json::json!({
+                        "option_id": option.id,
+                        "label": option.label,
+                        "index": index,
+                    }),
+                ),
+                None => response(
+                    message,
+                    ResponseType::Cancelled,
+                    None,
+                    serde_json::json!({}),
+                ),
+            })
+        }
+        MessageContent::Authorization { .. } => Some(response(
+            message,
+            approval(config.approve()),
+            Some("echo".to_string()),
+            serde_json::json!({}),
+        )),
+        MessageContent::Navigate { .. } => Some(response(
+            message,
+            approval(config.navigate()),
+            None,
+            serde_json::json!({}),
+        )),
+        MessageContent::Chat {
+            conversation_id,
+            text,
+            closed: false,
+        } if matches!(message.sender_type, SenderType::Agent) => {
+            let mut reply = Message::new(
+                message.channel.clone(),
+                SenderType::Human,
+                MessageContent::Chat {
+                    conversation_id: *conversation_id,
+                    text: config.chat_reply(text),
+                    closed: false,
+                },
+            );
+            reply.metadata = Some(serde_json::json!({ "echo": true }));
+            Some(reply)
+        }
+        _ => None,
+    }
+}
+
+fn approval(approve: bool) -> ResponseType {
+    if approve {
+        ResponseType::AuthorizationApproved
+    } else {
+        ResponseType::AuthorizationDenied
+    }
+}
+
+fn response(
+    prompt: &Message,
+    response_type: ResponseType,
+    answer: Option<String>,
+    mut metadata: serde_json::Value,
+) -> Message {
+    let mut response = Message::response(
+        prompt.channel.clone(),
+        MessageContent::Response {
+            answer,
+            response_type,
+        },
+        prompt.id,
+    );
+    metadata["echo"] = serde_json::json!(true);
+    response.metadata = Some(metadata);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{DecisionOption, DecisionRecommendation};
+
+    #[test]
+    fn test_echo_answers_prompts_from_config() {
+        let option = |id: &str| DecisionOption {
+            id: id.to_string(),
+            label: id.to_uppercase(),
+            detail_markdown: None,
+        };
+        let decision = ailoop_core::client::decision(
+            "dev",
+            "d1".to_string(),
+            "Which region?".to_string(),
+            None,
+            vec![option("eu"), option("us"), option("ap")],
+            Some(DecisionRecommendation {
+                option_id: "us".to_string(),
+                rationale_markdown: None,
+            }),
+            0,
+            false,
+            None,
+        );
+        let mut config = EchoConfig::default();
+        let answer = reply(&decision, &config).unwrap();
+        assert_eq!(answer.correlation_id, Some(decision.id));
+        assert_eq!(answer.metadata.as_ref().unwrap()["option_id"], "us");
+
+        config.decision = Some("ap".to_string());
+        let metadata = reply(&decision, &config).unwrap().metadata.unwrap();
+        assert_eq!(metadata["index"], 2);
+        assert_eq!(metadata["echo"], true);
+
+        let authorization = ailoop_core::client::authorization("dev", "deploy", 300);
+        config.approve = Some(false);
+        assert!(matches!(
+            reply(&authorization, &config).unwrap().content,
+            MessageContent::Response {
+                response_type: ResponseType::AuthorizationDenied,
+                ..
+            }
+        ));
+
+        let notification = Message::new(
+            "dev".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "built".to_string(),
+                priority: ailoop_core::models::NotificationPriority::Normal,
+            },
+        );
+        assert!(reply(&notification, &config).is_none());
+    }
+}
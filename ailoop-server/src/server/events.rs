@@ -0,0 +1,376 @@
+//! Structured event log (`events.log`)
+//!
+//! The terminal shows what is happening now; the event log keeps it for later. Every message
+//! recorded in history, every prompt state change (`queued`, `pending`, `answered`, `timeout`,
+//! `cancelled`, `snoozed`) and every provider delivery attempt is appended as one JSON object
+//! per line:
+//!
+//! ```text
+//! {"ts":"2026-10-16T09:12:03.120Z","event":"prompt","state":"pending","prompt_id":"...","channel":"ops"}
+//! ```
+//!
+//! The file is rotated by size: `events.log` becomes `events.log.1`, the previous `.1` becomes
+//! `.2`, and so on up to `keep` files. With `compress`, rotated files are zstd-compressed
+//! (`events.log.1.zst`); reading handles either form. Nothing is written until [`install`] is called, so
+//! embedders and tests are unaffected. Admins read the log remotely with `GET /api/logs`
+//! (`ailoop logs`). The same calls feed the OpenTelemetry metrics and spans in
+//! [`crate::server::telemetry`].
+
+use ailoop_core::models::{EventLogConfig, Message, NotificationPriority};
+use ailoop_core::services::compression;
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+static EVENT_LOG: OnceLock<EventLog> = OnceLock::new();
+
+/// An append-only NDJSON file with size-based rotation.
+pub struct EventLog {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: u32,
+    compress: bool,
+    file: Mutex<Option<(File, u64)>>,
+}
+
+impl EventLog {
+    pub fn open(path: impl Into<PathBuf>, max_bytes: u64, keep: u32) -> Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let file = open_append(&path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            path,
+            max_bytes,
+            keep,
+            compress: false,
+            file: Mutex::new(Some((file, size))),
+        })
+    }
+
+    /// Compress files as they are rotated.
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// The log from `[event_log]`, or `None` when it is disabled or has no path.
+    pub fn from_config(config: &EventLogConfig) -> Result<Option<Self>> {
+        if !config.enabled() {
+            return Ok(None);
+        }
+        let Some(path) = config.path() else {
+            return Ok(None);
+        };
+        Self::open(path, config.max_size_bytes(), config.keep())
+            .map(|log| Some(log.with_compression(config.compress())))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `fields` (a JSON object) as an `event` line stamped with the current time.
+    pub fn write(&self, event: &str, fields: serde_json::Value) {
+        let mut line = serde_json::json!({
+            "ts": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            "event": event,
+        });
+        if let (Some(line), serde_json::Value::Object(fields)) = (line.as_object_mut(), fields) {
+            line.extend(fields);
+        }
+        let mut line = line.to_string();
+        line.push('\n');
+
+        let mut guard = self.file.lock().expect("event log lock");
+        if guard
+            .as_ref()
+            .is_some_and(|(_, size)| *size > 0 && size + line.len() as u64 > self.max_bytes)
+        {
+            *guard = None;
+            if let Err(e) = self.rotate() {
+                tracing::warn!("Failed to rotate {}: {:#}", self.path.display(), e);
+            }
+        }
+        if guard.is_none() {
+            match open_append(&self.path) {
+                Ok(file) => *guard = Some((file, 0)),
+                Err(e) => {
+                    tracing::warn!("Event log unavailable: {:#}", e);
+                    return;
+                }
+            }
+        }
+        if let Some((file, size)) = guard.as_mut() {
+            match file.write_all(line.as_bytes()) {
+                Ok(()) => *size += line.len() as u64,
+                Err(e) => tracing::warn!("Failed to write {}: {}", self.path.display(), e),
+            }
+        }
+    }
+
+    /// Events stamped after `since`, oldest first, from the rotated files and the current one.
+    /// With `event`, only events of that type. At most the newest `limit` are returned.
+    pub fn read_since(
+        &self,
+        since: Option<DateTime<Utc>>,
+        event: Option<&str>,
+        limit: usize,
+    ) -> Vec<serde_json::Value> {
+        let mut files: Vec<PathBuf> = (1..=self.keep)
+            .rev()
+            .flat_map(|n| {
+                let rotated = self.rotated(n);
+                [compression::compressed_path(&rotated), rotated]
+            })
+            .collect();
+        files.push(self.path.clone());
+        let mut events = std::collections::VecDeque::new();
+        for path in files {
+            // A file last written before `since` holds nothing newer.
+            let Ok(modified) = std::fs::metadata(&path).and_then(|m| m.modified()) else {
+                continue;
+            };
+            if since.is_some_and(|since| DateTime::<Utc>::from(modified) < since) {
+                continue;
+            }
+            let Ok(reader) = compression::open(&path) else {
+                continue;
+            };
+            for line in reader.lines().map_while(|l| l.ok()) {
+                let Ok(value) = serde_json::from_str::<serde_json::Value>(&line) else {
+                    continue;
+                };
+                if event.is_some_and(|e| value["event"] != e) {
+                    continue;
+                }
+                let ts = value["ts"]
+                    .as_str()
+                    .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok());
+                if let (Some(since), Some(ts)) = (since, ts) {
+                    if ts <= since {
+                        continue;
+                    }
+                }
+                events.push_back(value);
+                if events.len() > limit {
+                    events.pop_front();
+                }
+            }
+        }
+        events.into()
+    }
+
+    /// Shift `events.log.N` to `.N+1`, dropping the oldest, and move the current file to `.1`,
+    /// compressing it when enabled. Rotated files keep whichever form they were written in.
+    fn rotate(&self) -> Result<()> {
+        if self.keep == 0 {
+            std::fs::remove_file(&self.path)?;
+            return Ok(());
+        }
+        let oldest = self.rotated(self.keep);
+        let _ = std::fs::remove_file(compression::compressed_path(&oldest));
+        let _ = std::fs::remove_file(oldest);
+        for n in (1..self.keep).rev() {
+            let (from, to) = (self.rotated(n), self.rotated(n + 1));
+            for (from, to) in [
+                (
+                    compression::compressed_path(&from),
+                    compression::compressed_path(&to),
+                ),
+                (from, to),
+            ] {
+                if from.exists() {
+                    std::fs::rename(&from, to)?;
+                }
+            }
+        }
+        let first = self.rotated(1);
+        std::fs::rename(&self.path, &first)?;
+        if self.compress {
+            compression::compress_file(&first)?;
+        }
+        Ok(())
+    }
+
+    fn rotated(&self, n: u32) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))
+}
+
+/// Send events from this process to `log`. Only the first call has an effect.
+pub fn install(log: EventLog) {
+    let _ = EVENT_LOG.set(log);
+}
+
+/// The log installed in this process, if any.
+pub fn installed() -> Option<&'static EventLog> {
+    EVENT_LOG.get()
+}
+
+/// Record `event` with `fields` in the installed log, if any.
+pub fn emit(event: &str, fields: serde_json::Value) {
+    if let Some(log) = EVENT_LOG.get() {
+        log.write(event, fields);
+    }
+}
+
+/// Record a message added to history.
+pub fn message(message: &Message) {
+    let content_type = crate::server::broadcast::message_content_type(message);
+    crate::server::telemetry::message(&message.channel, content_type);
+    if EVENT_LOG.get().is_none() {
+        return;
+    }
+    emit(
+        "message",
+        serde_json::json!({
+            "id": message.id,
+            "channel": message.channel,
+            "type": content_type,
+            "sender": message.sender_type,
+            "correlation_id": message.correlation_id,
+        }),
+    );
+}
+
+/// Record a prompt moving to `state`.
+pub fn prompt(prompt_id: uuid::Uuid, channel: &str, state: &str) {
+    crate::server::telemetry::prompt(prompt_id, channel, state);
+    emit(
+        "prompt",
+        serde_json::json!({
+            "state": state,
+            "prompt_id": prompt_id,
+            "channel": channel,
+        }),
+    );
+}
+
+/// Record an authorization approved through break-glass by `holder`.
+pub fn break_glass(prompt_id: uuid::Uuid, channel: &str, holder: &str, justification: &str) {
+    emit(
+        "break_glass",
+        serde_json::json!({
+            "prompt_id": prompt_id,
+            "channel": channel,
+            "holder": holder,
+            "justification": justification,
+        }),
+    );
+}
+
+/// Record a queued prompt moved ahead at its agent's request, now `ahead` places from the front.
+pub fn boost(
+    prompt_id: uuid::Uuid,
+    channel: &str,
+    priority: &NotificationPriority,
+    ahead: usize,
+    reason: Option<&str>,
+) {
+    emit(
+        "boost",
+        serde_json::json!({
+            "prompt_id": prompt_id,
+            "channel": channel,
+            "priority": priority,
+            "ahead": ahead,
+            "reason": reason,
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_are_json_and_files_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.log");
+        let log = EventLog::open(&path, 200, 2).unwrap();
+        for i in 0..12 {
+            log.write("prompt", serde_json::json!({"state": "pending", "n": i}));
+        }
+
+        let current = std::fs::read_to_string(&path).unwrap();
+        let last: serde_json::Value =
+            serde_json::from_str(current.lines().last().unwrap()).unwrap();
+        assert_eq!(last["event"], "prompt");
+        assert_eq!(last["n"], 11);
+        assert!(last["ts"].as_str().unwrap().ends_with('Z'));
+        assert!(std::fs::metadata(&path).unwrap().len() <= 200);
+        assert!(dir.path().join("events.log.1").exists());
+        assert!(dir.path().join("events.log.2").exists());
+        assert!(!dir.path().join("events.log.3").exists());
+    }
+
+    #[test]
+    fn test_read_since_spans_rotated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = EventLog::open(dir.path().join("events.log"), 200, 2).unwrap();
+        for i in 0..6 {
+            log.write("prompt", serde_json::json!({"n": i}));
+        }
+        log.write("delivery", serde_json::json!({"n": 6}));
+
+        let all = log.read_since(None, None, 100);
+        let numbers: Vec<i64> = all.iter().map(|e| e["n"].as_i64().unwrap()).collect();
+        assert!(numbers.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(numbers.last(), Some(&6));
+
+        let newest = log.read_since(None, Some("prompt"), 2);
+        assert_eq!(newest.len(), 2);
+        assert_eq!(newest[1]["n"], 5);
+
+        let since = DateTime::parse_from_rfc3339(all.last().unwrap()["ts"].as_str().unwrap())
+            .unwrap()
+            .with_timezone(&Utc);
+        assert!(log.read_since(Some(since), None, 100).is_empty());
+    }
+
+    #[test]
+    fn test_rotated_files_are_compressed_and_still_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.log");
+        // A plain segment rotated before compression was turned on.
+        let plain = EventLog::open(&path, 200, 3).unwrap();
+        for i in 0..6 {
+            plain.write("prompt", serde_json::json!({"n": i}));
+        }
+        drop(plain);
+
+        let log = EventLog::open(&path, 200, 3)
+            .unwrap()
+            .with_compression(true);
+        for i in 6..9 {
+            log.write("prompt", serde_json::json!({"n": i}));
+        }
+        assert!(dir.path().join("events.log.1.zst").exists());
+        assert!(!dir.path().join("events.log.1").exists());
+        assert!(dir.path().join("events.log.2").exists());
+
+        let numbers: Vec<i64> = log
+            .read_since(None, None, 100)
+            .iter()
+            .map(|e| e["n"].as_i64().unwrap())
+            .collect();
+        assert_eq!(numbers, (0..9).collect::<Vec<_>>());
+    }
+}
@@ -1,17 +1,42 @@
 //! Message history storage with per-channel FIFO eviction
+//!
+//! Retention rules (`[history]`) cap how many messages a channel keeps and purge those older
+//! than its maximum age; `ailoop history purge` removes old messages on demand. Read
+//! receipts of agent notifications are kept alongside, in [`MessageHistory::reads`], and
+//! messages recorded on a channel in an [incident](crate::server::incidents) are tagged with it.
 
-use ailoop_core::models::Message;
+use crate::server::incidents::IncidentBoard;
+use crate::server::reads::ReadLog;
+use crate::server::sessions::SessionLog;
+use crate::server::subscriptions::searchable_text;
+use ailoop_core::models::{
+    HistoryConfig, Message, MessageContent, NotificationPriority, PurgeReport, ResponseType,
+    SenderType, ThreadEntry,
+};
+use ailoop_core::ChannelUnread;
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 
 /// Maximum number of messages to store per channel
-const MAX_MESSAGES_PER_CHANNEL: usize = 1000;
+pub const MAX_MESSAGES_PER_CHANNEL: usize = 1000;
+
+/// Most earlier prompts followed when assembling a thread.
+const MAX_THREAD_DEPTH: usize = 20;
+
+/// How often retention rules are applied.
+const RETENTION_TICK: std::time::Duration = std::time::Duration::from_secs(60);
 
 /// Message history storage with per-channel FIFO eviction
 #[derive(Clone)]
 pub struct MessageHistory {
     inner: Arc<RwLock<HashMap<String, VecDeque<Message>>>>,
+    sessions: Arc<SessionLog>,
+    reads: Arc<ReadLog>,
+    incidents: Arc<IncidentBoard>,
+    retention: Arc<RwLock<HistoryConfig>>,
 }
 
 impl MessageHistory {
@@ -19,11 +44,26 @@ impl MessageHistory {
     pub fn new() -> Self {
         Self {
             inner: Arc::new(RwLock::new(HashMap::new())),
+            sessions: Arc::new(SessionLog::new()),
+            reads: Arc::new(ReadLog::new()),
+            incidents: Arc::new(IncidentBoard::new()),
+            retention: Arc::new(RwLock::new(HistoryConfig::default())),
         }
     }
 
+    /// Replace the retention rules; they apply from the next message or retention pass.
+    pub async fn set_retention(&self, config: HistoryConfig) {
+        *self.retention.write().await = config;
+    }
+
     /// Add a message to the history for a channel
-    pub async fn add_message(&self, channel: &str, message: Message) {
+    ///
+    /// Messages forwarded from an agent session are also recorded in [`Self::sessions`].
+    pub async fn add_message(&self, channel: &str, mut message: Message) {
+        self.incidents.tag(&mut message);
+        crate::server::events::message(&message);
+        self.sessions.record(&message).await;
+        let max_entries = self.max_entries(channel).await;
         let mut history = self.inner.write().await;
         let channel_messages = history
             .entry(channel.to_string())
@@ -33,11 +73,139 @@ impl MessageHistory {
         channel_messages.push_back(message);
 
         // Evict oldest messages if limit exceeded (FIFO)
-        while channel_messages.len() > MAX_MESSAGES_PER_CHANNEL {
+        while channel_messages.len() > max_entries {
             channel_messages.pop_front();
         }
     }
 
+    /// Messages kept on `channel`: its `max_entries`, never more than the built-in cap.
+    async fn max_entries(&self, channel: &str) -> usize {
+        self.retention
+            .read()
+            .await
+            .rules_for(channel)
+            .max_entries
+            .map_or(MAX_MESSAGES_PER_CHANNEL, |n| {
+                n.min(MAX_MESSAGES_PER_CHANNEL)
+            })
+    }
+
+    /// Remove the messages stamped before `before` on `channel` (every channel when `None`),
+    /// along with the session events recorded from them. With `dry_run`, only count them.
+    pub async fn purge(
+        &self,
+        channel: Option<&str>,
+        before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> PurgeReport {
+        let mut report = PurgeReport {
+            dry_run,
+            ..Default::default()
+        };
+        let mut history = self.inner.write().await;
+        for (name, messages) in history
+            .iter_mut()
+            .filter(|(name, _)| channel.is_none_or(|ch| *name == ch))
+        {
+            let total = messages.len();
+            let purged = if dry_run {
+                messages.iter().filter(|m| m.timestamp < before).count()
+            } else {
+                messages.retain(|m| m.timestamp >= before);
+                total - messages.len()
+            };
+            if purged > 0 {
+                report.channels.insert(name.clone(), purged);
+            }
+        }
+        if !dry_run {
+            history.retain(|_, messages| !messages.is_empty());
+        }
+        drop(history);
+        report.session_events = self.sessions.purge(channel, before, dry_run).await;
+        report
+    }
+
+    /// Apply the retention rules at `now`: purge messages past each channel's maximum age and
+    /// trim channels down to their `max_entries`.
+    pub async fn apply_retention(&self, now: DateTime<Utc>) -> PurgeReport {
+        let config = self.retention.read().await.clone();
+        let mut report = PurgeReport::default();
+        for channel in self.get_channels().await {
+            let rules = config.rules_for(&channel);
+            if let Some(days) = rules.max_age_days {
+                let cutoff = now - chrono::Duration::days(days.into());
+                let purged = self.purge(Some(&channel), cutoff, false).await;
+                report.channels.extend(purged.channels);
+                report.session_events += purged.session_events;
+            }
+            if let Some(max_entries) = rules.max_entries {
+                let mut history = self.inner.write().await;
+                let Some(messages) = history.get_mut(&channel) else {
+                    continue;
+                };
+                let excess = messages.len().saturating_sub(max_entries);
+                messages.drain(..excess);
+                if excess > 0 {
+                    *report.channels.entry(channel.clone()).or_default() += excess;
+                }
+            }
+        }
+        report
+    }
+
+    /// Agent sessions seen in forwarded messages
+    pub fn sessions(&self) -> &SessionLog {
+        &self.sessions
+    }
+
+    /// Read receipts of agent notifications
+    pub fn reads(&self) -> &ReadLog {
+        &self.reads
+    }
+
+    /// Incidents open on channels
+    pub fn incidents(&self) -> &IncidentBoard {
+        &self.incidents
+    }
+
+    /// Agent notifications `responder` has not read, per channel (channels with none left
+    /// out), by channel name.
+    pub async fn unread(&self, responder: &str) -> Vec<ChannelUnread> {
+        let history = self.inner.read().await;
+        let mut channels: Vec<ChannelUnread> = history
+            .iter()
+            .filter_map(|(channel, messages)| {
+                let unread: Vec<&Message> = messages
+                    .iter()
+                    .filter(|m| is_agent_notification(m) && !self.reads.has_read(m.id, responder))
+                    .collect();
+                let oldest = unread.iter().map(|m| m.timestamp).min()?;
+                Some(ChannelUnread {
+                    channel: channel.clone(),
+                    unread: unread.len(),
+                    urgent: unread.iter().filter(|m| is_urgent(m)).count(),
+                    oldest: Some(oldest),
+                })
+            })
+            .collect();
+        channels.sort_by(|a, b| a.channel.cmp(&b.channel));
+        channels
+    }
+
+    /// Mark every agent notification on `channel` read by `responder`; returns how many were
+    /// unread.
+    pub async fn mark_channel_read(&self, channel: &str, responder: &str) -> usize {
+        let history = self.inner.read().await;
+        history
+            .get(channel)
+            .into_iter()
+            .flatten()
+            .filter(|m| is_agent_notification(m))
+            .filter(|m| self.reads.mark(m.id, responder))
+            .count()
+    }
+
     /// Get recent messages for a channel
     pub async fn get_messages(&self, channel: &str, limit: Option<usize>) -> Vec<Message> {
         let history = self.inner.read().await;
@@ -49,6 +217,20 @@ impl MessageHistory {
         }
     }
 
+    /// Messages on any channel whose text contains `query`, ignoring case; newest first, at
+    /// most `limit`. Server notices have no text to match.
+    pub async fn search(&self, query: &str, limit: usize) -> Vec<Message> {
+        let query = query.to_lowercase();
+        let history = self.inner.read().await;
+        let mut found: Vec<&Message> = history
+            .values()
+            .flatten()
+            .filter(|m| searchable_text(m).is_some_and(|t| t.to_lowercase().contains(&query)))
+            .collect();
+        found.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        found.into_iter().take(limit).cloned().collect()
+    }
+
     /// Get all channels with messages
     pub async fn get_channels(&self) -> Vec<String> {
         let history = self.inner.read().await;
@@ -99,6 +281,116 @@ impl MessageHistory {
         }
         None
     }
+
+    /// The thread `message_id` belongs to, oldest first: each prompt it follows up on (through
+    /// `parent_id`), then the message itself, each followed by its responses. Prompts evicted
+    /// from history end the thread early.
+    pub async fn thread(&self, message_id: &uuid::Uuid) -> Vec<Message> {
+        let history = self.inner.read().await;
+        let find = |id: &uuid::Uuid| history.values().flatten().find(|m| &m.id == id);
+
+        let mut chain = Vec::new();
+        let mut next = find(message_id);
+        while let Some(message) = next {
+            if chain.len() > MAX_THREAD_DEPTH || chain.iter().any(|m: &&Message| m.id == message.id)
+            {
+                break;
+            }
+            chain.push(message);
+            next = message.parent_id.as_ref().and_then(find);
+        }
+
+        let mut thread = Vec::new();
+        for prompt in chain.into_iter().rev() {
+            thread.push(prompt.clone());
+            thread.extend(
+                history
+                    .values()
+                    .flatten()
+                    .filter(|m| m.correlation_id == Some(prompt.id))
+                    .cloned(),
+            );
+        }
+        thread
+    }
+
+    /// Transcript of a chat conversation, oldest first.
+    pub async fn conversation(&self, conversation_id: &uuid::Uuid) -> Vec<Message> {
+        let history = self.inner.read().await;
+        let mut transcript: Vec<Message> = history
+            .values()
+            .flatten()
+            .filter(|m| {
+                matches!(&m.content, MessageContent::Chat { conversation_id: id, .. } if id == conversation_id)
+            })
+            .cloned()
+            .collect();
+        transcript.sort_by_key(|m| m.timestamp);
+        transcript
+    }
+
+    /// Questions and answers of the prompts `message` follows up on, for display with it.
+    pub async fn thread_context(&self, message: &Message) -> Vec<ThreadEntry> {
+        let Some(parent_id) = message.parent_id else {
+            return Vec::new();
+        };
+        let thread = self.thread(&parent_id).await;
+        thread
+            .iter()
+            .filter_map(|prompt| {
+                let question = prompt.prompt_text()?.to_string();
+                let answer = thread
+                    .iter()
+                    .find(|m| m.correlation_id == Some(prompt.id))
+                    .and_then(answer_text);
+                Some(ThreadEntry {
+                    id: prompt.id,
+                    question,
+                    answer,
+                })
+            })
+            .collect()
+    }
+
+    /// Messages attributed to `responder` (`metadata.responder`), oldest first.
+    pub async fn by_responder(&self, responder: &str) -> Vec<Message> {
+        let history = self.inner.read().await;
+        let mut messages: Vec<Message> = history
+            .values()
+            .flatten()
+            .filter(|m| m.responder() == Some(responder))
+            .cloned()
+            .collect();
+        messages.sort_by_key(|m| m.timestamp);
+        messages
+    }
+
+    /// Remove and return the messages attributed to `responder`.
+    pub async fn erase_responder(&self, responder: &str) -> Vec<Message> {
+        let mut history = self.inner.write().await;
+        let mut erased = Vec::new();
+        for messages in history.values_mut() {
+            let (theirs, kept): (VecDeque<Message>, VecDeque<Message>) = messages
+                .drain(..)
+                .partition(|m| m.responder() == Some(responder));
+            *messages = kept;
+            erased.extend(theirs);
+        }
+        history.retain(|_, messages| !messages.is_empty());
+        erased
+    }
+
+    /// Whether a response to `message_id` has been recorded
+    pub async fn has_response(&self, message_id: &uuid::Uuid) -> bool {
+        let history = self.inner.read().await;
+        history.values().flatten().any(|message| {
+            message.correlation_id.as_ref() == Some(message_id)
+                && matches!(
+                    message.content,
+                    ailoop_core::models::MessageContent::Response { .. }
+                )
+        })
+    }
 }
 
 impl Default for MessageHistory {
@@ -107,6 +399,31 @@ impl Default for MessageHistory {
     }
 }
 
+/// Apply the retention rules every minute until `token` is cancelled.
+pub(crate) fn spawn_retention(
+    history: Arc<MessageHistory>,
+    token: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(RETENTION_TICK);
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tick.tick() => {
+                    let report = history.apply_retention(Utc::now()).await;
+                    if report.messages() > 0 || report.session_events > 0 {
+                        tracing::info!(
+                            messages = report.messages(),
+                            session_events = report.session_events,
+                            "Purged history past its retention"
+                        );
+                    }
+                }
+            }
+        }
+    })
+}
+
 /// Statistics for a channel
 #[derive(Debug, Clone)]
 pub struct ChannelStats {
@@ -117,3 +434,243 @@ pub struct ChannelStats {
 }
 
 use std::collections::VecDeque;
+
+/// Whether `message` is a notification sent by an agent, which responders may leave unread.
+pub fn is_agent_notification(message: &Message) -> bool {
+    matches!(message.sender_type, SenderType::Agent)
+        && matches!(message.content, MessageContent::Notification { .. })
+}
+
+fn is_urgent(message: &Message) -> bool {
+    matches!(
+        message.content,
+        MessageContent::Notification {
+            priority: NotificationPriority::High | NotificationPriority::Urgent,
+            ..
+        }
+    )
+}
+
+/// How a response reads in a thread: the chosen option's label, the text, or the outcome.
+fn answer_text(response: &Message) -> Option<String> {
+    let MessageContent::Response {
+        answer,
+        response_type,
+    } = &response.content
+    else {
+        return None;
+    };
+    let label = response
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("label"))
+        .and_then(|l| l.as_str());
+    let outcome = match response_type {
+        ResponseType::Text => return label.map(str::to_string).or_else(|| answer.clone()),
+        ResponseType::AuthorizationApproved => "approved",
+        ResponseType::AuthorizationDenied => "denied",
+        ResponseType::Timeout => "timed out",
+        ResponseType::Cancelled => "cancelled",
+    };
+    Some(match answer {
+        Some(reason) => format!("{}: {}", outcome, reason),
+        None => outcome.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::RetentionRules;
+
+    fn authorization(action: &str) -> Message {
+        Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Authorization {
+                action: action.to_string(),
+                context: None,
+                timeout_seconds: 60,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn follow_ups_carry_earlier_answers() {
+        let history = MessageHistory::new();
+        let first = authorization("drop the staging table");
+        let answer = Message::response(
+            "ops".to_string(),
+            MessageContent::Response {
+                answer: Some("restore it after".to_string()),
+                response_type: ResponseType::AuthorizationApproved,
+            },
+            first.id,
+        );
+        let second = authorization("restore from last night's backup").with_parent(first.id);
+        let third = authorization("notify the team").with_parent(second.id);
+        for message in [&first, &answer, &second] {
+            history.add_message("ops", message.clone()).await;
+        }
+
+        let context = history.thread_context(&third).await;
+        assert_eq!(context.len(), 2);
+        assert_eq!(context[0].question, "drop the staging table");
+        assert_eq!(
+            context[0].answer.as_deref(),
+            Some("approved: restore it after")
+        );
+        assert_eq!(context[1].id, second.id);
+        assert_eq!(context[1].answer, None);
+
+        let thread = history.thread(&second.id).await;
+        let ids: Vec<_> = thread.iter().map(|m| m.id).collect();
+        assert_eq!(ids, [first.id, answer.id, second.id]);
+        assert!(history.thread_context(&first).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_finds_messages_on_every_channel_newest_first() {
+        let history = MessageHistory::new();
+        let older = authorization("Deploy v1 to prod");
+        let mut newer = authorization("deploy v2 to staging");
+        newer.channel = "web".to_string();
+        newer.timestamp = older.timestamp + chrono::Duration::seconds(1);
+        history.add_message("ops", older.clone()).await;
+        history.add_message("web", newer.clone()).await;
+        history
+            .add_message("ops", authorization("restart the cache"))
+            .await;
+
+        let found: Vec<_> = history
+            .search("DEPLOY", 10)
+            .await
+            .iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(found, [newer.id, older.id]);
+        assert_eq!(history.search("deploy", 1).await.len(), 1);
+        assert!(history.search("rollback", 10).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn purge_and_retention_remove_old_messages() {
+        let history = MessageHistory::new();
+        let now = Utc::now();
+        let aged = |channel: &str, days: i64| {
+            let mut message = authorization(&format!("{} days ago", days));
+            message.channel = channel.to_string();
+            message.timestamp = now - chrono::Duration::days(days);
+            message
+        };
+        for days in [40, 20, 1] {
+            history.add_message("ops", aged("ops", days)).await;
+            history.add_message("dev", aged("dev", days)).await;
+        }
+
+        let cutoff = now - chrono::Duration::days(30);
+        let dry_run = history.purge(Some("ops"), cutoff, true).await;
+        assert_eq!(dry_run.messages(), 1);
+        assert_eq!(history.get_message_count("ops").await, 3);
+        let purged = history.purge(Some("ops"), cutoff, false).await;
+        assert_eq!(purged.channels.get("ops"), Some(&1));
+        assert_eq!(history.get_message_count("ops").await, 2);
+        assert_eq!(history.get_message_count("dev").await, 3);
+
+        history
+            .set_retention(HistoryConfig {
+                defaults: RetentionRules {
+                    max_age_days: Some(30),
+                    max_entries: None,
+                },
+                channels: HashMap::from([(
+                    "ops".to_string(),
+                    RetentionRules {
+                        max_age_days: None,
+                        max_entries: Some(1),
+                    },
+                )]),
+            })
+            .await;
+        let report = history.apply_retention(now).await;
+        assert_eq!(report.channels.get("dev"), Some(&1));
+        assert_eq!(report.channels.get("ops"), Some(&1));
+        let ops = history.get_messages("ops", None).await;
+        assert_eq!(ops.len(), 1);
+        assert_eq!(ops[0].timestamp, now - chrono::Duration::days(1));
+
+        // New messages are held to `max_entries` as they arrive.
+        history.add_message("ops", aged("ops", 0)).await;
+        assert_eq!(history.get_message_count("ops").await, 1);
+    }
+
+    #[tokio::test]
+    async fn conversation_collects_both_sides_in_order() {
+        let history = MessageHistory::new();
+        let conversation_id = uuid::Uuid::new_v4();
+        let line = |sender_type, text: &str, closed| {
+            Message::new(
+                "ops".to_string(),
+                sender_type,
+                MessageContent::Chat {
+                    conversation_id,
+                    text: text.to_string(),
+                    closed,
+                },
+            )
+        };
+        let opening = line(SenderType::Agent, "the migration failed", false);
+        let reply = line(SenderType::Human, "roll it back", false);
+        let closing = line(SenderType::Agent, "done", true);
+        history.add_message("ops", opening.clone()).await;
+        history.add_message("ops", authorization("unrelated")).await;
+        history.add_message("ops", reply.clone()).await;
+        history.add_message("ops", closing.clone()).await;
+
+        let ids: Vec<_> = history
+            .conversation(&conversation_id)
+            .await
+            .iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(ids, [opening.id, reply.id, closing.id]);
+        assert!(history.conversation(&uuid::Uuid::new_v4()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unread_counts_agent_notifications_per_reader() {
+        let history = MessageHistory::new();
+        let say = |channel: &str, priority| {
+            Message::new(
+                channel.to_string(),
+                SenderType::Agent,
+                MessageContent::Notification {
+                    text: "build finished".to_string(),
+                    priority,
+                },
+            )
+        };
+        let first = say("ops", NotificationPriority::Normal);
+        history.add_message("ops", first.clone()).await;
+        history
+            .add_message("ops", say("ops", NotificationPriority::Urgent))
+            .await;
+        history
+            .add_message("dev", say("dev", NotificationPriority::Low))
+            .await;
+        history.add_message("ops", authorization("deploy")).await;
+
+        let unread = history.unread("telegram:alice").await;
+        assert_eq!(unread.len(), 2);
+        assert_eq!(unread[1].channel, "ops");
+        assert_eq!((unread[1].unread, unread[1].urgent), (2, 1));
+        assert_eq!(unread[1].oldest, Some(first.timestamp));
+
+        history.reads().mark(first.id, "telegram:alice");
+        assert_eq!(history.mark_channel_read("dev", "telegram:alice").await, 1);
+        let unread = history.unread("telegram:alice").await;
+        assert_eq!(unread.len(), 1);
+        assert_eq!((unread[0].unread, unread[0].urgent), (1, 1));
+        assert_eq!(history.unread("operator").await.len(), 2);
+    }
+}
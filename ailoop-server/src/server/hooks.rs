@@ -0,0 +1,245 @@
+//! Local policy hooks on messages
+//!
+//! `[hooks]` in `config.toml` names executables run on every message received from an agent
+//! (`inbound`) and on every prompt response before it is released (`outbound`). A hook is
+//! called with `inbound` or `outbound` as its only argument and the message as JSON on stdin,
+//! and decides by its exit status:
+//!
+//! - exit 0 with nothing on stdout: the message passes unchanged;
+//! - exit 0 with a JSON message on stdout: it replaces the message (id, channel and
+//!   correlation id are kept from the original);
+//! - any other exit status: the message is blocked, with stderr as the reason.
+//!
+//! A hook that cannot be started, runs past its timeout or prints something other than a
+//! message is logged and the message passes unchanged, so a broken hook never stalls agents.
+
+use ailoop_core::models::{HooksConfig, Message};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// How long a hook may run when `timeout_ms` is unset.
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a hook decided.
+#[derive(Debug)]
+pub enum HookOutcome {
+    /// Deliver this message (the original, or the hook's rewrite).
+    Pass(Box<Message>),
+    /// Drop the message; the hook's reason.
+    Blocked(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+enum HookError {
+    #[error("failed to run hook: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("hook timed out after {0:?}")]
+    TimedOut(Duration),
+    #[error("hook printed an invalid message: {0}")]
+    InvalidOutput(#[from] serde_json::Error),
+}
+
+/// The configured hooks; without any, every message passes.
+#[derive(Debug, Clone)]
+pub struct MessageHooks {
+    inbound: Option<PathBuf>,
+    outbound: Option<PathBuf>,
+    timeout: Duration,
+}
+
+impl Default for MessageHooks {
+    fn default() -> Self {
+        Self {
+            inbound: None,
+            outbound: None,
+            timeout: DEFAULT_HOOK_TIMEOUT,
+        }
+    }
+}
+
+impl MessageHooks {
+    pub fn new(inbound: Option<PathBuf>, outbound: Option<PathBuf>) -> Self {
+        Self {
+            inbound,
+            outbound,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn from_config(config: &HooksConfig) -> Self {
+        let path = |p: &Option<String>| {
+            p.as_deref()
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(PathBuf::from)
+        };
+        let hooks = Self::new(path(&config.inbound), path(&config.outbound));
+        match config.timeout_ms {
+            Some(ms) => hooks.with_timeout(Duration::from_millis(ms)),
+            None => hooks,
+        }
+    }
+
+    /// Whether any hook is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.inbound.is_some() || self.outbound.is_some()
+    }
+
+    /// Run the inbound hook on a message received from an agent.
+    pub async fn inbound(&self, message: Message) -> HookOutcome {
+        match &self.inbound {
+            Some(hook) => self.run(hook, "inbound", message).await,
+            None => HookOutcome::Pass(Box::new(message)),
+        }
+    }
+
+    /// Run the outbound hook on a response about to be released.
+    pub async fn outbound(&self, message: Message) -> HookOutcome {
+        match &self.outbound {
+            Some(hook) => self.run(hook, "outbound", message).await,
+            None => HookOutcome::Pass(Box::new(message)),
+        }
+    }
+
+    async fn run(&self, hook: &Path, direction: &str, message: Message) -> HookOutcome {
+        match tokio::time::timeout(self.timeout, run_hook(hook, direction, &message)).await {
+            Ok(Ok(Some(outcome))) => outcome,
+            Ok(Ok(None)) => HookOutcome::Pass(Box::new(message)),
+            Ok(Err(e)) => {
+                tracing::warn!(hook = %hook.display(), direction, error = %e, "Hook failed; message passes unchanged");
+                HookOutcome::Pass(Box::new(message))
+            }
+            Err(_) => {
+                let e = HookError::TimedOut(self.timeout);
+                tracing::warn!(hook = %hook.display(), direction, error = %e, "Hook failed; message passes unchanged");
+                HookOutcome::Pass(Box::new(message))
+            }
+        }
+    }
+}
+
+/// Run `hook` on `message`; `None` when it passed the message unchanged.
+async fn run_hook(
+    hook: &Path,
+    direction: &str,
+    message: &Message,
+) -> Result<Option<HookOutcome>, HookError> {
+    let mut child = tokio::process::Command::new(hook)
+        .arg(direction)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let input = serde_json::to_vec(message)?;
+        // A hook that decides without reading its input closes the pipe early.
+        if let Err(e) = stdin.write_all(&input).await {
+            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+    }
+    let output = child.wait_with_output().await?;
+
+    if !output.status.success() {
+        let reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let reason = if reason.is_empty() {
+            format!("blocked by {} hook", direction)
+        } else {
+            reason
+        };
+        return Ok(Some(HookOutcome::Blocked(reason)));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(None);
+    }
+    let mut rewritten: Message = serde_json::from_str(stdout.trim())?;
+    rewritten.id = message.id;
+    rewritten.channel = message.channel.clone();
+    rewritten.correlation_id = message.correlation_id;
+    Ok(Some(HookOutcome::Pass(Box::new(rewritten))))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{MessageContent, NotificationPriority, SenderType};
+    use std::os::unix::fs::PermissionsExt;
+
+    fn script(dir: &Path, name: &str, body: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn notification(text: &str) -> Message {
+        Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: text.to_string(),
+                priority: NotificationPriority::Normal,
+            },
+        )
+    }
+
+    fn text_of(outcome: HookOutcome) -> String {
+        match outcome {
+            HookOutcome::Pass(message) => match message.content {
+                MessageContent::Notification { text, .. } => text,
+                other => panic!("unexpected content: {:?}", other),
+            },
+            HookOutcome::Blocked(reason) => panic!("blocked: {}", reason),
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks_pass_rewrite_and_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let message = notification("deploy done");
+
+        let pass = script(dir.path(), "pass", "cat > /dev/null");
+        let hooks = MessageHooks::new(Some(pass), None);
+        assert_eq!(text_of(hooks.inbound(message.clone()).await), "deploy done");
+
+        let rewrite = script(dir.path(), "rewrite", "sed 's/deploy/DEPLOY/'");
+        let hooks = MessageHooks::new(Some(rewrite), None);
+        let outcome = hooks.inbound(message.clone()).await;
+        assert!(matches!(&outcome, HookOutcome::Pass(m) if m.id == message.id));
+        assert_eq!(text_of(outcome), "DEPLOY done");
+
+        let block = script(dir.path(), "block", "echo \"no deploys on $1\" >&2; exit 1");
+        let hooks = MessageHooks::new(None, Some(block));
+        assert!(matches!(
+            hooks.outbound(message.clone()).await,
+            HookOutcome::Blocked(reason) if reason == "no deploys on outbound"
+        ));
+    }
+
+    #[tokio::test]
+    async fn broken_hooks_pass_the_message() {
+        let dir = tempfile::tempdir().unwrap();
+        let message = notification("hello");
+
+        let missing = MessageHooks::new(Some(dir.path().join("missing")), None);
+        assert_eq!(text_of(missing.inbound(message.clone()).await), "hello");
+
+        let garbage = script(dir.path(), "garbage", "echo not json");
+        let hooks = MessageHooks::new(Some(garbage), None);
+        assert_eq!(text_of(hooks.inbound(message.clone()).await), "hello");
+
+        let slow = script(dir.path(), "slow", "sleep 5");
+        let hooks = MessageHooks::new(Some(slow), None).with_timeout(Duration::from_millis(100));
+        assert_eq!(text_of(hooks.inbound(message).await), "hello");
+    }
+}
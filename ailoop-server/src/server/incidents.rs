@@ -0,0 +1,259 @@
+//! Incident mode
+//!
+//! `ailoop incident start --channel ops` (`POST /api/v1/channels/{channel}/incident/start`)
+//! opens an incident on a channel until `ailoop incident stop`. While it is open, agent
+//! notifications on the channel go out at the `[incident] priority` or above, assignment
+//! fallbacks and Jira SLAs of its prompts are shortened, its hourly quotas are raised, and
+//! every message recorded on it carries the incident id in `metadata.incident`, so
+//! `ailoop export github --incident <id>` can pick them out later. The escalation timers and
+//! quotas read the tag, which the server sets or removes on every agent message. Open
+//! incidents are saved to `incidents.json` in the state directory.
+
+use ailoop_core::models::{
+    Incident, IncidentConfig, Message, MessageContent, NotificationPriority, SenderType,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct Board {
+    file: Option<PathBuf>,
+    config: IncidentConfig,
+    channels: BTreeMap<String, Incident>,
+}
+
+/// Open incidents by channel.
+#[derive(Default)]
+pub struct IncidentBoard {
+    board: Mutex<Board>,
+}
+
+impl IncidentBoard {
+    /// A board that forgets its incidents when the server stops.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Save incidents to `file` from now on, starting with the ones already in it.
+    pub fn open(&self, file: impl Into<PathBuf>) -> Result<()> {
+        let file = file.into();
+        let channels = match std::fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid incidents file {:?}", file))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read incidents file {:?}", file))
+            }
+        };
+        let mut board = self.lock();
+        board.file = Some(file);
+        board.channels = channels;
+        Ok(())
+    }
+
+    pub fn set_config(&self, config: IncidentConfig) {
+        self.lock().config = config;
+    }
+
+    /// The incident open on `channel`.
+    pub fn active(&self, channel: &str) -> Option<Incident> {
+        self.lock().channels.get(channel).cloned()
+    }
+
+    /// Open incidents by channel.
+    pub fn list(&self) -> BTreeMap<String, Incident> {
+        self.lock().channels.clone()
+    }
+
+    /// Open `incident` on `channel`. Returns the incident open there afterwards and whether it
+    /// is the new one; an incident already open is kept.
+    pub fn start(&self, channel: &str, incident: Incident) -> Result<(Incident, bool)> {
+        let mut board = self.lock();
+        if let Some(open) = board.channels.get(channel) {
+            return Ok((open.clone(), false));
+        }
+        board.channels.insert(channel.to_string(), incident.clone());
+        if let Err(e) = board.save() {
+            board.channels.remove(channel);
+            return Err(e);
+        }
+        Ok((incident, true))
+    }
+
+    /// End the incident on `channel`; returns it, if one was open.
+    pub fn stop(&self, channel: &str) -> Result<Option<Incident>> {
+        let mut board = self.lock();
+        let Some(incident) = board.channels.remove(channel) else {
+            return Ok(None);
+        };
+        if let Err(e) = board.save() {
+            board.channels.insert(channel.to_string(), incident);
+            return Err(e);
+        }
+        Ok(Some(incident))
+    }
+
+    /// Prepare an agent message: tag it with its channel's incident, or remove a tag the agent
+    /// set itself when there is none, and raise a notification to the incident priority.
+    pub fn apply(&self, message: &mut Message) {
+        let board = self.lock();
+        let Some(incident) = board.channels.get(&message.channel) else {
+            if let Some(metadata) = message.metadata.as_mut().and_then(|m| m.as_object_mut()) {
+                metadata.remove("incident");
+            }
+            return;
+        };
+        tag_with(message, &incident.id);
+        let floor = board.config.priority();
+        if let MessageContent::Notification { priority, .. } = &mut message.content {
+            if priority.rank() < floor.rank() {
+                *priority = floor;
+            }
+        }
+    }
+
+    /// Tag `message` with its channel's incident, if one is open.
+    pub fn tag(&self, message: &mut Message) {
+        if message.incident().is_some() {
+            return;
+        }
+        if let Some(incident) = self.lock().channels.get(&message.channel) {
+            tag_with(message, &incident.id);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Board> {
+        self.board.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Board {
+    fn save(&self) -> Result<()> {
+        match &self.file {
+            Some(file) => save_to(file, &self.channels),
+            None => Ok(()),
+        }
+    }
+}
+
+fn save_to(file: &Path, channels: &BTreeMap<String, Incident>) -> Result<()> {
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {:?}", parent))?;
+    }
+    let tmp = file.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(channels)?)
+        .with_context(|| format!("Failed to write {:?}", tmp))?;
+    std::fs::rename(&tmp, file).with_context(|| format!("Failed to write {:?}", file))
+}
+
+fn tag_with(message: &mut Message, id: &str) {
+    let mut metadata = match message.metadata.take() {
+        Some(serde_json::Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    metadata.insert("incident".to_string(), serde_json::json!(id));
+    message.metadata = Some(serde_json::Value::Object(metadata));
+}
+
+/// Urgent system notification announcing that `incident` started (or ended at `now`) on
+/// `channel`.
+pub fn notice(channel: &str, incident: &Incident, started: bool, now: DateTime<Utc>) -> Message {
+    let text = if started {
+        match &incident.title {
+            Some(title) => format!("🚨 Incident {} started: {}", incident.id, title),
+            None => format!("🚨 Incident {} started", incident.id),
+        }
+    } else {
+        let minutes = (now - incident.started_at).num_minutes().max(0);
+        format!(
+            "✅ Incident {} ended after {} minute(s)",
+            incident.id, minutes
+        )
+    };
+    let mut notice = Message::new(
+        channel.to_string(),
+        SenderType::System,
+        MessageContent::Notification {
+            text,
+            priority: NotificationPriority::Urgent,
+        },
+    );
+    tag_with(&mut notice, &incident.id);
+    notice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn say(channel: &str, priority: NotificationPriority) -> Message {
+        Message::new(
+            channel.to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "db latency up".to_string(),
+                priority,
+            },
+        )
+    }
+
+    #[test]
+    fn test_incidents_tag_and_raise_messages_and_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("incidents.json");
+        let now = Utc::now();
+        let board = IncidentBoard::new();
+        board.open(&file).unwrap();
+        let incident = Incident::new("ops", Some("INC-7".to_string()), None, now);
+        assert_eq!(
+            board.start("ops", incident.clone()).unwrap(),
+            (incident.clone(), true)
+        );
+        let other = Incident::new("ops", None, None, now);
+        assert!(other.id.starts_with("ops-"));
+        assert_eq!(
+            board.start("ops", other).unwrap(),
+            (incident.clone(), false)
+        );
+
+        let mut message = say("ops", NotificationPriority::Low);
+        board.apply(&mut message);
+        assert_eq!(message.incident(), Some("INC-7"));
+        assert!(matches!(
+            message.content,
+            MessageContent::Notification {
+                priority: NotificationPriority::High,
+                ..
+            }
+        ));
+        let mut urgent = say("ops", NotificationPriority::Urgent);
+        board.apply(&mut urgent);
+        assert!(matches!(
+            urgent.content,
+            MessageContent::Notification {
+                priority: NotificationPriority::Urgent,
+                ..
+            }
+        ));
+
+        // A tag an agent sets itself is removed outside an incident.
+        let mut forged = say("dev", NotificationPriority::Low);
+        forged.metadata = Some(serde_json::json!({"incident": "INC-7", "client_id": "a"}));
+        board.apply(&mut forged);
+        assert_eq!(forged.incident(), None);
+        assert_eq!(forged.metadata, Some(serde_json::json!({"client_id": "a"})));
+
+        let reopened = IncidentBoard::new();
+        reopened.open(&file).unwrap();
+        assert_eq!(reopened.active("ops"), Some(incident.clone()));
+        assert_eq!(reopened.stop("ops").unwrap(), Some(incident));
+        assert_eq!(reopened.stop("ops").unwrap(), None);
+        let mut late = say("ops", NotificationPriority::Low);
+        reopened.tag(&mut late);
+        assert_eq!(late.incident(), None);
+    }
+}
@@ -0,0 +1,175 @@
+//! Directory ingestion: NDJSON message files dropped into a directory (e.g. rsync'd from an
+//! air-gapped host) are processed as if they arrived over WebSocket, then archived.
+//!
+//! Files ending in `.ndjson` or `.jsonl` are picked up; dotfiles are skipped so partial rsync
+//! transfers are never read. A processed file moves to `processed/`, or to `failed/` when any
+//! of its lines could not be ingested (the valid lines are still delivered).
+
+use crate::server::core::AiloopServer;
+use crate::server::hooks::HookOutcome;
+use crate::server::usage::UsageTracker;
+use crate::state::AiloopAppState;
+use ailoop_core::models::Message;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// How often the directory is scanned by default.
+pub const DEFAULT_INGEST_POLL: Duration = Duration::from_secs(2);
+
+/// Where to look for message files and how often.
+#[derive(Debug, Clone)]
+pub struct IngestConfig {
+    pub dir: PathBuf,
+    pub poll_interval: Duration,
+}
+
+impl IngestConfig {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            poll_interval: DEFAULT_INGEST_POLL,
+        }
+    }
+}
+
+/// Counts from one scan of the ingest directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngestReport {
+    pub files: usize,
+    pub messages: usize,
+    /// Lines that were not valid messages, had a bad channel or exceeded a quota.
+    pub rejected: usize,
+}
+
+/// Ingest every message file currently in `config.dir`, oldest name first.
+pub async fn ingest_once(
+    state: &AiloopAppState,
+    config: &IngestConfig,
+) -> std::io::Result<IngestReport> {
+    let mut report = IngestReport::default();
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&config.dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| is_message_file(p))
+        .collect();
+    files.sort();
+
+    for path in files {
+        let content = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(e) => {
+                tracing::warn!(path = ?path, error = %e, "Failed to read ingest file");
+                continue;
+            }
+        };
+        let mut rejected = 0;
+        for (n, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match ingest_line(state, line).await {
+                Ok(()) => report.messages += 1,
+                Err(reason) => {
+                    rejected += 1;
+                    tracing::warn!(path = ?path, line = n + 1, "Rejected ingested message: {}", reason);
+                }
+            }
+        }
+        report.rejected += rejected;
+        report.files += 1;
+
+        let archive = if rejected == 0 { "processed" } else { "failed" };
+        if let Err(e) = archive_file(&config.dir.join(archive), &path) {
+            // Leaving the file in place would ingest it again on the next scan.
+            tracing::error!(path = ?path, error = %e, "Failed to archive ingest file; removing it");
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+    Ok(report)
+}
+
+/// Scan `config.dir` every `poll_interval` until `token` is cancelled.
+pub fn spawn_ingest(
+    state: Arc<AiloopAppState>,
+    config: IngestConfig,
+    token: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(config.poll_interval);
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tick.tick() => {
+                    match ingest_once(&state, &config).await {
+                        Ok(report) if report.files > 0 => tracing::info!(
+                            files = report.files,
+                            messages = report.messages,
+                            rejected = report.rejected,
+                            "Ingested message files"
+                        ),
+                        Ok(_) => {}
+                        Err(e) => tracing::warn!(dir = ?config.dir, error = %e, "Ingest scan failed"),
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Apply the WebSocket agent path to one NDJSON line.
+async fn ingest_line(state: &AiloopAppState, line: &str) -> Result<(), String> {
+    let mut message: Message = if state.strict {
+        let raw = serde_json::from_str(line).map_err(|e| e.to_string())?;
+        ailoop_core::models::strict::parse_strict(raw).map_err(|errors| errors.join("; "))?
+    } else {
+        serde_json::from_str(line).map_err(|e| e.to_string())?
+    };
+    ailoop_core::channel::validation::validate_channel_name(&message.channel)
+        .map_err(|e| format!("invalid channel '{}': {}", message.channel, e))?;
+    state.message_history.incidents().apply(&mut message);
+    let client = UsageTracker::client_key(None, &message);
+    state
+        .usage
+        .try_record(&client, &message)
+        .await
+        .map_err(|e| e.to_string())?;
+    let message = match state.hooks.inbound(message).await {
+        HookOutcome::Pass(message) => *message,
+        HookOutcome::Blocked(reason) => return Err(format!("blocked by hook: {}", reason)),
+    };
+    AiloopServer::accept_agent_message(
+        &state.channel_manager,
+        &state.message_history,
+        &state.broadcast_manager,
+        message,
+    );
+    Ok(())
+}
+
+fn is_message_file(path: &Path) -> bool {
+    let visible = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| !n.starts_with('.'));
+    visible
+        && path.is_file()
+        && path
+            .extension()
+            .is_some_and(|ext| ext == "ndjson" || ext == "jsonl")
+}
+
+/// Move `path` into `archive`, adding a timestamp when the name is already taken.
+fn archive_file(archive: &Path, path: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(archive)?;
+    let name = path.file_name().unwrap_or_default();
+    let mut target = archive.join(name);
+    if target.exists() {
+        target = archive.join(format!(
+            "{}.{}",
+            name.to_string_lossy(),
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.f")
+        ));
+    }
+    std::fs::rename(path, target)
+}
@@ -0,0 +1,600 @@
+//! Jira escalation of denied risky authorizations and overdue prompts
+//!
+//! Each `[[jira]]` rule watches the channels it names. It files an issue in its `project`, or
+//! comments on its `issue`, when an authorization at `min_risk` or above under
+//! [verification](crate::server::verification) is denied, and when a prompt has gone
+//! unanswered for `sla_seconds` (or timed out before then), a fraction of that for prompts
+//! asked during an [incident](crate::server::incidents). Issues carry a summary and a
+//! description written by ailoop, plus the rule's `fields` with placeholders filled in.
+//!
+//! The sink observes the broadcast stream next to the response metrics rather than being a
+//! provider, so it never counts as having delivered a prompt. Jira requests run in the
+//! background; failures are logged and do not hold up delivery.
+
+use ailoop_core::channel::pattern::matches_pattern;
+use ailoop_core::models::{
+    Configuration, IncidentConfig, JiraConfig, Message, MessageContent, ResponseType, RiskLevel,
+    SenderType, VerificationConfig,
+};
+use ailoop_core::transport::proxy::ProxySettings;
+use anyhow::{Context, Result};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Environment variable holding the Jira API token.
+pub const JIRA_TOKEN_ENV: &str = "AILOOP_JIRA_API_TOKEN";
+
+/// Prompts followed at once (oldest forgotten first).
+const MAX_OPEN: usize = 1000;
+
+/// Seconds one Jira request may take.
+const HTTP_TIMEOUT_SECS: u64 = 30;
+
+/// Why a prompt is escalated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Escalation {
+    /// A risky authorization was denied.
+    Denied {
+        risk: RiskLevel,
+        responder: Option<String>,
+        reason: Option<String>,
+    },
+    /// Nobody answered within the rule's SLA, or the prompt timed out first.
+    Unanswered { waited: Duration },
+}
+
+impl Escalation {
+    fn event(&self) -> &'static str {
+        match self {
+            Escalation::Denied { .. } => "denied",
+            Escalation::Unanswered { .. } => "unanswered",
+        }
+    }
+}
+
+struct OpenPrompt {
+    prompt: Message,
+    /// Rules whose SLA has not yet been escalated for this prompt.
+    awaiting_sla: Vec<usize>,
+}
+
+#[derive(Default)]
+struct Tracked {
+    open: HashMap<Uuid, OpenPrompt>,
+    order: VecDeque<Uuid>,
+}
+
+/// Files Jira issues for the `[[jira]]` rules.
+pub struct JiraSink {
+    rules: Vec<JiraConfig>,
+    verification: VerificationConfig,
+    incident: IncidentConfig,
+    token: String,
+    client: reqwest::Client,
+    tracked: Mutex<Tracked>,
+}
+
+impl JiraSink {
+    /// A sink for the `[[jira]]` rules of `config`, with the token from [`JIRA_TOKEN_ENV`];
+    /// `None` without rules.
+    pub fn from_config(config: &Configuration) -> Result<Option<Self>> {
+        if config.jira.is_empty() {
+            return Ok(None);
+        }
+        let token = std::env::var(JIRA_TOKEN_ENV)
+            .ok()
+            .filter(|t| !t.is_empty())
+            .with_context(|| format!("[[jira]] is configured but {} is not set", JIRA_TOKEN_ENV))?;
+        let client = ProxySettings::resolve(&config.proxy)?
+            .apply(reqwest::Client::builder())?
+            .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+            .build()?;
+        Ok(Some(Self {
+            client,
+            incident: config.incident.clone(),
+            ..Self::new(config.jira.clone(), config.verification.clone(), token)
+        }))
+    }
+
+    pub fn new(rules: Vec<JiraConfig>, verification: VerificationConfig, token: String) -> Self {
+        Self {
+            rules,
+            verification,
+            incident: IncidentConfig::default(),
+            token,
+            client: ailoop_core::transport::proxy::http_client(),
+            tracked: Mutex::new(Tracked::default()),
+        }
+    }
+
+    /// Note a broadcast message: an agent prompt starts waiting, a response settles it and
+    /// may be escalated. Escalations are filed in the background.
+    pub fn observe(self: &Arc<Self>, message: &Message) {
+        for (rule, prompt, escalation) in self.escalations(message) {
+            let sink = Arc::clone(self);
+            tokio::spawn(async move { sink.file(rule, &prompt, &escalation).await });
+        }
+        for (rule, sla) in self.start_waiting(message) {
+            let sink = Arc::clone(self);
+            let prompt_id = message.id;
+            tokio::spawn(async move {
+                tokio::time::sleep(sla).await;
+                if let Some(prompt) = sink.overdue(prompt_id, rule) {
+                    let escalation = Escalation::Unanswered { waited: sla };
+                    sink.file(rule, &prompt, &escalation).await;
+                }
+            });
+        }
+    }
+
+    /// Rules that watch `channel`, by index.
+    fn rules_for<'a>(
+        &'a self,
+        channel: &'a str,
+    ) -> impl Iterator<Item = (usize, &'a JiraConfig)> + 'a {
+        self.rules.iter().enumerate().filter(move |(_, rule)| {
+            rule.channels.is_empty() || rule.channels.iter().any(|p| matches_pattern(p, channel))
+        })
+    }
+
+    /// Follow `message` when it is a new agent prompt on a watched channel; returns the SLAs to
+    /// time, by rule.
+    fn start_waiting(&self, message: &Message) -> Vec<(usize, Duration)> {
+        if !matches!(message.sender_type, SenderType::Agent)
+            || !crate::server::providers::is_prompt(&message.content)
+        {
+            return Vec::new();
+        }
+        let rules: Vec<(usize, &JiraConfig)> = self.rules_for(&message.channel).collect();
+        if rules.is_empty() {
+            return Vec::new();
+        }
+        let mut tracked = self.lock();
+        if tracked.open.contains_key(&message.id) {
+            return Vec::new();
+        }
+        if tracked.order.len() >= MAX_OPEN {
+            if let Some(oldest) = tracked.order.pop_front() {
+                tracked.open.remove(&oldest);
+            }
+        }
+        let slas: Vec<(usize, Duration)> = rules
+            .iter()
+            .filter_map(|(i, rule)| {
+                let sla = rule.sla()?;
+                match message.incident() {
+                    Some(_) => Some((*i, self.incident.escalation(sla))),
+                    None => Some((*i, sla)),
+                }
+            })
+            .collect();
+        tracked.order.push_back(message.id);
+        tracked.open.insert(
+            message.id,
+            OpenPrompt {
+                prompt: message.clone(),
+                awaiting_sla: slas.iter().map(|(i, _)| *i).collect(),
+            },
+        );
+        slas
+    }
+
+    /// The prompt `prompt_id` if its SLA under `rule` has passed unescalated.
+    fn overdue(&self, prompt_id: Uuid, rule: usize) -> Option<Message> {
+        let mut tracked = self.lock();
+        let open = tracked.open.get_mut(&prompt_id)?;
+        let before = open.awaiting_sla.len();
+        open.awaiting_sla.retain(|r| *r != rule);
+        (open.awaiting_sla.len() < before).then(|| open.prompt.clone())
+    }
+
+    /// What `message` settles: for a response to a followed prompt, the escalations it calls
+    /// for under each rule.
+    pub fn escalations(&self, message: &Message) -> Vec<(usize, Message, Escalation)> {
+        let MessageContent::Response {
+            answer,
+            response_type,
+        } = &message.content
+        else {
+            return Vec::new();
+        };
+        if *response_type == ResponseType::Cancelled {
+            // The prompt goes back in line.
+            return Vec::new();
+        }
+        let Some(open) = message
+            .correlation_id
+            .and_then(|id| self.lock().open.remove(&id))
+        else {
+            return Vec::new();
+        };
+
+        let mut escalations = Vec::new();
+        match response_type {
+            ResponseType::AuthorizationDenied => {
+                let MessageContent::Authorization { action, .. } = &open.prompt.content else {
+                    return escalations;
+                };
+                let Some((risk, _)) = self.verification.rule_for(action) else {
+                    return escalations;
+                };
+                for (i, rule) in self.rules_for(&open.prompt.channel) {
+                    if rule.denials() && risk >= rule.min_risk() {
+                        escalations.push((
+                            i,
+                            open.prompt.clone(),
+                            Escalation::Denied {
+                                risk,
+                                responder: message
+                                    .team_member()
+                                    .or(message.responder())
+                                    .map(str::to_string),
+                                reason: answer.clone().filter(|a| !a.trim().is_empty()),
+                            },
+                        ));
+                    }
+                }
+            }
+            ResponseType::Timeout => {
+                let waited = (message.timestamp - open.prompt.timestamp)
+                    .to_std()
+                    .unwrap_or_default();
+                for i in open.awaiting_sla {
+                    escalations.push((i, open.prompt.clone(), Escalation::Unanswered { waited }));
+                }
+            }
+            _ => {}
+        }
+        escalations
+    }
+
+    async fn file(&self, rule: usize, prompt: &Message, escalation: &Escalation) {
+        let config = &self.rules[rule];
+        let (path, body) = request(config, prompt, escalation);
+        let url = format!("{}/rest/api/2/{}", config.url.trim_end_matches('/'), path);
+        let request = self.client.post(&url).json(&body);
+        let request = match &config.email {
+            Some(email) => request.basic_auth(email, Some(&self.token)),
+            None => request.bearer_auth(&self.token),
+        };
+        let result = match request.send().await {
+            Ok(resp) if resp.status().is_success() => Ok(()),
+            Ok(resp) => Err(format!(
+                "Jira returned {}: {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            )),
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(()) => tracing::info!(
+                prompt_id = %prompt.id,
+                event = escalation.event(),
+                jira = %config.url,
+                "escalated prompt to Jira"
+            ),
+            Err(e) => tracing::error!(
+                prompt_id = %prompt.id,
+                event = escalation.event(),
+                jira = %config.url,
+                error = %e,
+                "Jira escalation failed"
+            ),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Tracked> {
+        self.tracked.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The Jira REST v2 path (under `/rest/api/2/`) and body escalating `prompt` under `rule`: a
+/// comment on `rule.issue`, else a new issue.
+pub fn request(
+    rule: &JiraConfig,
+    prompt: &Message,
+    escalation: &Escalation,
+) -> (String, serde_json::Value) {
+    let values = placeholders(prompt, escalation);
+    let summary = summary(prompt, escalation);
+    let description = description(prompt, escalation, &summary);
+    if let Some(issue) = &rule.issue {
+        return (
+            format!("issue/{}/comment", issue),
+            serde_json::json!({ "body": description }),
+        );
+    }
+    let mut fields = serde_json::Map::new();
+    fields.insert(
+        "project".to_string(),
+        serde_json::json!({ "key": rule.project.as_deref().unwrap_or_default() }),
+    );
+    fields.insert(
+        "issuetype".to_string(),
+        serde_json::json!({ "name": rule.issue_type() }),
+    );
+    fields.insert("summary".to_string(), serde_json::json!(summary));
+    fields.insert("description".to_string(), serde_json::json!(description));
+    for (field, value) in &rule.fields {
+        fields.insert(field.clone(), fill(value, &values));
+    }
+    ("issue".to_string(), serde_json::json!({ "fields": fields }))
+}
+
+fn summary(prompt: &Message, escalation: &Escalation) -> String {
+    let text: String = prompt
+        .prompt_text()
+        .unwrap_or_default()
+        .chars()
+        .take(120)
+        .collect();
+    match escalation {
+        Escalation::Denied { risk, .. } => format!(
+            "[ailoop] {}-risk authorization denied on {}: {}",
+            risk, prompt.channel, text
+        ),
+        Escalation::Unanswered { waited } => format!(
+            "[ailoop] Prompt unanswered for {} on {}: {}",
+            waited_label(*waited),
+            prompt.channel,
+            text
+        ),
+    }
+}
+
+fn description(prompt: &Message, escalation: &Escalation, summary: &str) -> String {
+    let mut lines = vec![
+        summary.trim_start_matches("[ailoop] ").to_string(),
+        String::new(),
+        format!("Channel: {}", prompt.channel),
+        format!("Agent: {}", prompt.speaker()),
+        format!(
+            "Asked: {}",
+            prompt.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+        ),
+        format!("Prompt ID: {}", prompt.id),
+    ];
+    if let Escalation::Denied {
+        responder, reason, ..
+    } = escalation
+    {
+        if let Some(responder) = responder {
+            lines.push(format!("Denied by: {}", responder));
+        }
+        if let Some(reason) = reason {
+            lines.push(format!("Reason: {}", reason));
+        }
+    }
+    lines.join("\n")
+}
+
+fn placeholders(prompt: &Message, escalation: &Escalation) -> Vec<(&'static str, String)> {
+    let (risk, responder, reason) = match escalation {
+        Escalation::Denied {
+            risk,
+            responder,
+            reason,
+        } => (
+            risk.to_string(),
+            responder.clone().unwrap_or_default(),
+            reason.clone().unwrap_or_default(),
+        ),
+        Escalation::Unanswered { .. } => Default::default(),
+    };
+    vec![
+        ("{channel}", prompt.channel.clone()),
+        ("{event}", escalation.event().to_string()),
+        (
+            "{prompt}",
+            prompt.prompt_text().unwrap_or_default().to_string(),
+        ),
+        ("{prompt_id}", prompt.id.to_string()),
+        ("{agent}", prompt.speaker()),
+        ("{risk}", risk),
+        ("{responder}", responder),
+        ("{reason}", reason),
+    ]
+}
+
+/// `value` with the placeholders in its strings replaced, at any depth.
+fn fill(value: &serde_json::Value, values: &[(&str, String)]) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) => serde_json::Value::String(
+            values
+                .iter()
+                .fold(s.clone(), |s, (key, value)| s.replace(key, value)),
+        ),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(|v| fill(v, values)).collect())
+        }
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), fill(v, values)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn waited_label(waited: Duration) -> String {
+    let secs = waited.as_secs();
+    if secs >= 3600 && secs.is_multiple_of(3600) {
+        format!("{}h", secs / 3600)
+    } else if secs >= 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{VerificationMethod, VerificationRule};
+
+    fn sink(rules: Vec<JiraConfig>) -> JiraSink {
+        let verification = VerificationConfig {
+            high: Some(VerificationRule {
+                patterns: vec!["deploy".to_string()],
+                method: VerificationMethod::Totp,
+            }),
+            ..VerificationConfig::default()
+        };
+        JiraSink::new(rules, verification, "token".to_string())
+    }
+
+    fn answer(prompt: &Message, response_type: ResponseType, text: Option<&str>) -> Message {
+        let mut answer = Message::response(
+            prompt.channel.clone(),
+            MessageContent::Response {
+                answer: text.map(str::to_string),
+                response_type,
+            },
+            prompt.id,
+        );
+        answer.metadata = Some(serde_json::json!({"responder": "telegram:alice"}));
+        answer
+    }
+
+    #[test]
+    fn test_escalates_risky_denials_on_watched_channels() {
+        let jira = sink(vec![JiraConfig {
+            url: "https://example.atlassian.net".to_string(),
+            channels: vec!["prod-*".to_string()],
+            project: Some("OPS".to_string()),
+            ..JiraConfig::default()
+        }]);
+        let risky = ailoop_core::client::authorization("prod-eu", "deploy v2", 60);
+        assert!(jira.start_waiting(&risky).is_empty());
+        let escalations = jira.escalations(&answer(
+            &risky,
+            ResponseType::AuthorizationDenied,
+            Some("not now"),
+        ));
+        assert_eq!(
+            escalations
+                .iter()
+                .map(|(i, _, e)| (*i, e.clone()))
+                .collect::<Vec<_>>(),
+            vec![(
+                0,
+                Escalation::Denied {
+                    risk: RiskLevel::High,
+                    responder: Some("telegram:alice".to_string()),
+                    reason: Some("not now".to_string()),
+                }
+            )]
+        );
+
+        // Harmless actions, approvals and other channels are not escalated.
+        let harmless = ailoop_core::client::authorization("prod-eu", "restart cache", 60);
+        jira.start_waiting(&harmless);
+        assert!(jira
+            .escalations(&answer(&harmless, ResponseType::AuthorizationDenied, None))
+            .is_empty());
+        let approved = ailoop_core::client::authorization("prod-eu", "deploy v3", 60);
+        jira.start_waiting(&approved);
+        assert!(jira
+            .escalations(&answer(
+                &approved,
+                ResponseType::AuthorizationApproved,
+                None
+            ))
+            .is_empty());
+        let elsewhere = ailoop_core::client::authorization("dev", "deploy v2", 60);
+        jira.start_waiting(&elsewhere);
+        assert!(jira
+            .escalations(&answer(&elsewhere, ResponseType::AuthorizationDenied, None))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_sla_escalates_once() {
+        let jira = sink(vec![JiraConfig {
+            url: "https://jira.example.com".to_string(),
+            issue: Some("OPS-42".to_string()),
+            sla_seconds: Some(600),
+            ..JiraConfig::default()
+        }]);
+        let prompt = ailoop_core::client::authorization("ops", "rotate keys", 0);
+        assert_eq!(
+            jira.start_waiting(&prompt),
+            vec![(0, Duration::from_secs(600))]
+        );
+        assert!(jira.start_waiting(&prompt).is_empty());
+        assert!(jira.overdue(prompt.id, 0).is_some());
+        assert!(jira.overdue(prompt.id, 0).is_none());
+        assert!(jira
+            .escalations(&answer(&prompt, ResponseType::Timeout, None))
+            .is_empty());
+
+        let timed_out = ailoop_core::client::authorization("ops", "rotate keys", 0);
+        jira.start_waiting(&timed_out);
+        let escalations = jira.escalations(&answer(&timed_out, ResponseType::Timeout, None));
+        assert_eq!(escalations.len(), 1);
+        assert!(jira.overdue(timed_out.id, 0).is_none());
+
+        let mut during_incident = ailoop_core::client::authorization("ops", "fail over", 0);
+        during_incident.metadata = Some(serde_json::json!({"incident": "INC-7"}));
+        assert_eq!(
+            jira.start_waiting(&during_incident),
+            vec![(0, Duration::from_secs(150))]
+        );
+    }
+
+    #[test]
+    fn test_request_files_issue_with_mapped_fields() {
+        let mut fields = HashMap::new();
+        fields.insert(
+            "labels".to_string(),
+            serde_json::json!(["ailoop", "{event}"]),
+        );
+        fields.insert(
+            "customfield_10010".to_string(),
+            serde_json::json!("{channel}/{risk}"),
+        );
+        let rule = JiraConfig {
+            url: "https://example.atlassian.net".to_string(),
+            project: Some("OPS".to_string()),
+            fields,
+            ..JiraConfig::default()
+        };
+        let prompt = ailoop_core::client::authorization("ops", "deploy v2", 60);
+        let denied = Escalation::Denied {
+            risk: RiskLevel::Critical,
+            responder: Some("@alice".to_string()),
+            reason: None,
+        };
+        let (path, body) = request(&rule, &prompt, &denied);
+        assert_eq!(path, "issue");
+        let fields = &body["fields"];
+        assert_eq!(fields["project"]["key"], "OPS");
+        assert_eq!(fields["issuetype"]["name"], "Task");
+        assert_eq!(
+            fields["summary"],
+            "[ailoop] critical-risk authorization denied on ops: deploy v2"
+        );
+        assert!(fields["description"]
+            .as_str()
+            .unwrap()
+            .contains("Denied by: @alice"));
+        assert_eq!(fields["labels"], serde_json::json!(["ailoop", "denied"]));
+        assert_eq!(fields["customfield_10010"], "ops/critical");
+
+        let comment = JiraConfig {
+            issue: Some("OPS-42".to_string()),
+            ..rule
+        };
+        let unanswered = Escalation::Unanswered {
+            waited: Duration::from_secs(1800),
+        };
+        let (path, body) = request(&comment, &prompt, &unanswered);
+        assert_eq!(path, "issue/OPS-42/comment");
+        assert!(body["body"]
+            .as_str()
+            .unwrap()
+            .starts_with("Prompt unanswered for 30m on ops: deploy v2"));
+    }
+}
@@ -0,0 +1,328 @@
+//! Listening endpoints beyond plain TCP, so platform service managers can own the socket.
+//!
+//! An endpoint is written as `HOST:PORT` (TCP), `unix:PATH` (Unix domain socket),
+//! `pipe:NAME` (Windows named pipe `\\.\pipe\NAME`) or `launchd:NAME` (the socket named `NAME`
+//! in the launchd job's `Sockets` dictionary, inherited on macOS). Only TCP connections carry a
+//! peer address, so IP filtering requires a TCP endpoint.
+
+use anyhow::{bail, Context, Result};
+use axum::Router;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio_util::sync::CancellationToken;
+
+/// Where the server accepts HTTP and WebSocket connections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenEndpoint {
+    Tcp(SocketAddr),
+    /// Unix domain socket path (Unix only). A stale socket file is replaced on bind.
+    Unix(PathBuf),
+    /// Full pipe name, e.g. `\\.\pipe\ailoop` (Windows only).
+    NamedPipe(String),
+    /// Socket name from the launchd job's `Sockets` dictionary (macOS only).
+    Launchd(String),
+}
+
+impl ListenEndpoint {
+    /// Whether connections on this endpoint carry a peer IP address.
+    pub fn has_peer_addr(&self) -> bool {
+        matches!(self, ListenEndpoint::Tcp(_))
+    }
+}
+
+impl FromStr for ListenEndpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            if path.is_empty() {
+                bail!("unix endpoint needs a socket path");
+            }
+            return Ok(ListenEndpoint::Unix(PathBuf::from(path)));
+        }
+        if let Some(name) = s.strip_prefix("pipe:") {
+            if name.is_empty() {
+                bail!("pipe endpoint needs a pipe name");
+            }
+            let name = if name.starts_with(r"\\") {
+                name.to_string()
+            } else {
+                format!(r"\\.\pipe\{}", name)
+            };
+            return Ok(ListenEndpoint::NamedPipe(name));
+        }
+        if let Some(name) = s.strip_prefix("launchd:") {
+            if name.is_empty() {
+                bail!("launchd endpoint needs a socket name");
+            }
+            return Ok(ListenEndpoint::Launchd(name.to_string()));
+        }
+        s.parse()
+            .map(ListenEndpoint::Tcp)
+            .with_context(|| format!("Invalid listen endpoint '{}'", s))
+    }
+}
+
+impl fmt::Display for ListenEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenEndpoint::Tcp(addr) => write!(f, "{}", addr),
+            ListenEndpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+            ListenEndpoint::NamedPipe(name) => write!(f, "pipe:{}", name),
+            ListenEndpoint::Launchd(name) => write!(f, "launchd:{}", name),
+        }
+    }
+}
+
+/// Serve `router` on `endpoint` until `shutdown` completes.
+pub async fn serve_endpoint<F>(router: Router, endpoint: &ListenEndpoint, shutdown: F) -> Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let token = CancellationToken::new();
+    let trigger = token.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        trigger.cancel();
+    });
+
+    match endpoint {
+        ListenEndpoint::Tcp(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind to {}", addr))?;
+            serve_tcp(listener, router, token).await
+        }
+        ListenEndpoint::Unix(path) => serve_unix_path(path, router, token).await,
+        ListenEndpoint::NamedPipe(name) => serve_named_pipe(name, router, token).await,
+        ListenEndpoint::Launchd(name) => serve_launchd(name, router, token).await,
+    }
+}
+
+async fn serve_tcp(
+    listener: tokio::net::TcpListener,
+    router: Router,
+    token: CancellationToken,
+) -> Result<()> {
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move { token.cancelled().await })
+    .await?;
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn serve_unix(
+    listener: tokio::net::UnixListener,
+    router: Router,
+    token: CancellationToken,
+) -> Result<()> {
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move { token.cancelled().await })
+        .await?;
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn serve_unix_path(path: &Path, router: Router, token: CancellationToken) -> Result<()> {
+    // A socket left behind by a previous run would make bind fail.
+    if path.exists() {
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("Failed to bind to {}", path.display()))?;
+    let served = serve_unix(listener, router, token).await;
+    let _ = std::fs::remove_file(path);
+    served
+}
+
+#[cfg(not(unix))]
+async fn serve_unix_path(_path: &Path, _router: Router, _token: CancellationToken) -> Result<()> {
+    bail!("Unix domain sockets are not supported on this platform")
+}
+
+#[cfg(windows)]
+async fn serve_named_pipe(name: &str, router: Router, token: CancellationToken) -> Result<()> {
+    let listener = pipe::NamedPipeListener::bind(name)
+        .with_context(|| format!("Failed to create named pipe {}", name))?;
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move { token.cancelled().await })
+        .await?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+async fn serve_named_pipe(_name: &str, _router: Router, _token: CancellationToken) -> Result<()> {
+    bail!("Named pipes are only supported on Windows")
+}
+
+/// Serve every socket launchd passes for `name` (e.g. one per address family).
+#[cfg(target_os = "macos")]
+async fn serve_launchd(name: &str, router: Router, token: CancellationToken) -> Result<()> {
+    let listeners = launchd::activate(name)
+        .with_context(|| format!("launchd did not pass socket '{}'", name))?;
+    let served = listeners.into_iter().map(|listener| {
+        let router = router.clone();
+        let token = token.clone();
+        async move {
+            match listener {
+                launchd::Inherited::Tcp(l) => serve_tcp(l, router, token).await,
+                launchd::Inherited::Unix(l) => serve_unix(l, router, token).await,
+            }
+        }
+    });
+    for result in futures_util::future::join_all(served).await {
+        result?;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+async fn serve_launchd(_name: &str, _router: Router, _token: CancellationToken) -> Result<()> {
+    bail!("launchd socket activation is only supported on macOS")
+}
+
+#[cfg(windows)]
+mod pipe {
+    use std::io;
+    use std::time::Duration;
+    use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+
+    /// Accepts clients on a named pipe. One pipe instance always waits for the next client.
+    pub(super) struct NamedPipeListener {
+        name: String,
+        next: NamedPipeServer,
+    }
+
+    impl NamedPipeListener {
+        pub(super) fn bind(name: &str) -> io::Result<Self> {
+            let next = ServerOptions::new()
+                .first_pipe_instance(true)
+                .create(name)?;
+            Ok(Self {
+                name: name.to_string(),
+                next,
+            })
+        }
+    }
+
+    impl axum::serve::Listener for NamedPipeListener {
+        type Io = NamedPipeServer;
+        type Addr = String;
+
+        async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+            loop {
+                if let Err(e) = self.next.connect().await {
+                    tracing::warn!(pipe = %self.name, error = %e, "Named pipe connect failed");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+                match ServerOptions::new().create(&self.name) {
+                    Ok(fresh) => {
+                        let connected = std::mem::replace(&mut self.next, fresh);
+                        return (connected, self.name.clone());
+                    }
+                    Err(e) => {
+                        // Keep the connected instance; retry creating its successor.
+                        tracing::warn!(pipe = %self.name, error = %e, "Failed to create pipe instance");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        }
+
+        fn local_addr(&self) -> io::Result<Self::Addr> {
+            Ok(self.name.clone())
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod launchd {
+    use std::ffi::{c_char, c_int, c_void, CString};
+    use std::io;
+    use std::os::fd::{FromRawFd, IntoRawFd};
+
+    extern "C" {
+        // <launch.h>; `fds` is allocated by launchd and must be freed by the caller.
+        fn launch_activate_socket(
+            name: *const c_char,
+            fds: *mut *mut c_int,
+            cnt: *mut usize,
+        ) -> c_int;
+        fn free(ptr: *mut c_void);
+    }
+
+    pub(super) enum Inherited {
+        Tcp(tokio::net::TcpListener),
+        Unix(tokio::net::UnixListener),
+    }
+
+    /// Take ownership of the listening sockets launchd created for `name`.
+    pub(super) fn activate(name: &str) -> io::Result<Vec<Inherited>> {
+        let name = CString::new(name)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "socket name contains NUL"))?;
+        let mut fds: *mut c_int = std::ptr::null_mut();
+        let mut count: usize = 0;
+        // SAFETY: out-pointers are valid; on success `fds` points to `count` descriptors.
+        let err = unsafe { launch_activate_socket(name.as_ptr(), &mut fds, &mut count) };
+        if err != 0 {
+            return Err(io::Error::from_raw_os_error(err));
+        }
+        // SAFETY: as above; the array is copied before it is freed.
+        let raw = unsafe { std::slice::from_raw_parts(fds, count) }.to_vec();
+        unsafe { free(fds.cast()) };
+        raw.into_iter().map(inherit).collect()
+    }
+
+    fn inherit(fd: c_int) -> io::Result<Inherited> {
+        // SAFETY: launchd hands over ownership of each descriptor.
+        let unix = unsafe { std::os::unix::net::UnixListener::from_raw_fd(fd) };
+        // `local_addr` fails for sockets that are not AF_UNIX.
+        if unix.local_addr().is_ok() {
+            unix.set_nonblocking(true)?;
+            return Ok(Inherited::Unix(tokio::net::UnixListener::from_std(unix)?));
+        }
+        // SAFETY: same descriptor, released from the Unix wrapper above.
+        let tcp = unsafe { std::net::TcpListener::from_raw_fd(unix.into_raw_fd()) };
+        tcp.set_nonblocking(true)?;
+        Ok(Inherited::Tcp(tokio::net::TcpListener::from_std(tcp)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_endpoints() {
+        assert_eq!(
+            "127.0.0.1:8080".parse::<ListenEndpoint>().unwrap(),
+            ListenEndpoint::Tcp("127.0.0.1:8080".parse().unwrap())
+        );
+        assert_eq!(
+            "unix:/run/ailoop.sock".parse::<ListenEndpoint>().unwrap(),
+            ListenEndpoint::Unix(PathBuf::from("/run/ailoop.sock"))
+        );
+        assert_eq!(
+            "pipe:ailoop".parse::<ListenEndpoint>().unwrap(),
+            ListenEndpoint::NamedPipe(r"\\.\pipe\ailoop".to_string())
+        );
+        assert_eq!(
+            r"pipe:\\.\pipe\other".parse::<ListenEndpoint>().unwrap(),
+            ListenEndpoint::NamedPipe(r"\\.\pipe\other".to_string())
+        );
+        assert_eq!(
+            "launchd:Listeners".parse::<ListenEndpoint>().unwrap(),
+            ListenEndpoint::Launchd("Listeners".to_string())
+        );
+        assert!("unix:".parse::<ListenEndpoint>().is_err());
+        assert!("localhost".parse::<ListenEndpoint>().is_err());
+    }
+}
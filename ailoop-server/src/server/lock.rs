@@ -0,0 +1,173 @@
+//! Advisory lock on the data directory
+//!
+//! Two servers writing the same schedule, outbox and offset files overwrite each other's
+//! changes. `ailoop serve` takes an OS lock on `server.lock` in the state directory and writes
+//! its pid, start time and endpoint into it, so a second server can say who is already running.
+//! The OS drops the lock when a process dies, so a lock file left by a crashed server is
+//! recognized as stale and taken over.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the lock file in the data directory.
+pub const LOCK_FILE: &str = "server.lock";
+
+/// The data directory lock, held until dropped.
+#[derive(Debug)]
+pub struct DataDirLock {
+    file: File,
+    path: PathBuf,
+    /// False when started with `--force` while another server held the lock.
+    held: bool,
+}
+
+impl DataDirLock {
+    /// Lock `dir` for a server listening on `endpoint`.
+    ///
+    /// Fails while another live server holds the lock, unless `force` is set, in which case the
+    /// server runs without it.
+    pub fn acquire(dir: &Path, endpoint: &str, force: bool) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create data directory {}", dir.display()))?;
+        let path = dir.join(LOCK_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut previous = String::new();
+        let _ = file.read_to_string(&mut previous);
+        let previous = previous.trim().to_string();
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder = describe(&previous);
+                if !force {
+                    bail!(
+                        "Another ailoop server ({}) is using {}. Stop it, give this one its own \
+                         --data-dir, or pass --force to start anyway",
+                        holder,
+                        dir.display()
+                    );
+                }
+                tracing::warn!(
+                    dir = %dir.display(),
+                    holder = %holder,
+                    "Data directory is locked by another server; continuing because of --force"
+                );
+                return Ok(Self {
+                    file,
+                    path,
+                    held: false,
+                });
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+            }
+        }
+
+        if !previous.is_empty() {
+            tracing::warn!(
+                path = %path.display(),
+                holder = %describe(&previous),
+                "Taking over a stale lock left by a server that did not shut down cleanly"
+            );
+        }
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        writeln!(
+            file,
+            "{}\n{}\n{}",
+            std::process::id(),
+            Utc::now().to_rfc3339(),
+            endpoint
+        )?;
+        file.flush()?;
+        Ok(Self {
+            file,
+            path,
+            held: true,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether this server holds the lock (false after `--force` on a locked directory).
+    pub fn is_held(&self) -> bool {
+        self.held
+    }
+}
+
+impl Drop for DataDirLock {
+    fn drop(&mut self) {
+        if self.held {
+            // Clear the holder before unlocking, so a clean shutdown leaves nothing that looks
+            // stale. The file itself stays: removing it would let a server that opened it just
+            // before lock a file no other server can see.
+            let _ = self.file.set_len(0);
+            let _ = self.file.unlock();
+        }
+    }
+}
+
+/// Human-readable holder from the lock file's pid, start time and endpoint lines.
+fn describe(contents: &str) -> String {
+    let mut lines = contents.lines();
+    match (lines.next(), lines.next(), lines.next()) {
+        (Some(pid), Some(started), Some(endpoint)) => {
+            format!("pid {}, started {}, on {}", pid, started, endpoint)
+        }
+        (Some(pid), _, _) if !pid.is_empty() => format!("pid {}", pid),
+        _ => "unknown process".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_lock_fails_unless_forced() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = DataDirLock::acquire(dir.path(), "127.0.0.1:8080", false).unwrap();
+        assert!(first.is_held());
+
+        let err = DataDirLock::acquire(dir.path(), "127.0.0.1:8081", false).unwrap_err();
+        assert!(err.to_string().contains("127.0.0.1:8080"), "{}", err);
+
+        let forced = DataDirLock::acquire(dir.path(), "127.0.0.1:8081", true).unwrap();
+        assert!(!forced.is_held());
+        drop(forced);
+        assert!(first.path().exists());
+
+        drop(first);
+        let contents = std::fs::read_to_string(dir.path().join(LOCK_FILE)).unwrap();
+        assert!(contents.is_empty(), "{}", contents);
+        assert!(DataDirLock::acquire(dir.path(), "127.0.0.1:8082", false)
+            .unwrap()
+            .is_held());
+    }
+
+    #[test]
+    fn test_stale_lock_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(LOCK_FILE),
+            "999999\n2025-01-01T00:00:00+00:00\n127.0.0.1:8080\n",
+        )
+        .unwrap();
+        let lock = DataDirLock::acquire(dir.path(), "127.0.0.1:9090", false).unwrap();
+        assert!(lock.is_held());
+        let contents = std::fs::read_to_string(lock.path()).unwrap();
+        assert!(contents.starts_with(&std::process::id().to_string()));
+        assert!(contents.trim_end().ends_with("127.0.0.1:9090"));
+    }
+}
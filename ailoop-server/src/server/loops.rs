@@ -0,0 +1,278 @@
+//! Self-answer prevention and answer-loop detection
+//!
+//! A prompt is for a human. When the client that asked it also answers it, or two agents keep
+//! answering each other's prompts, nobody is in the loop. The server remembers who asked each
+//! prompt and checks every answer given over the API or a responder connection:
+//!
+//! - the asker and the responder share an identity (the same WebSocket connection, the same
+//!   `client_id`, or the same SSO subject): refused as `self_answer`, unless
+//!   `[loop_detection] allow_self_answers` is set;
+//! - more than `max_exchanges` answers between the same two clients, in either direction,
+//!   within `window_seconds`: refused as `loop_detected`.
+//!
+//! Static API tokens are not an identity here, since agents and dashboards often share one.
+//! Answers typed at the server terminal or sent through providers are never checked.
+
+use ailoop_core::models::{LoopDetectionConfig, Message};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Prompts whose askers are remembered; the oldest are forgotten first.
+const MAX_TRACKED_PROMPTS: usize = 10_000;
+
+/// Who sent a message or an answer: every identity known for the caller, such as
+/// `conn:<uuid>`, `client:<id>` or `sub:<subject>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Origin(Vec<String>);
+
+impl Origin {
+    /// Identities of a caller on `connection` (if any) with SSO `subject` (if any), plus the
+    /// `client_id` the message declares in its metadata.
+    pub fn new(connection: Option<Uuid>, subject: Option<&str>, message: Option<&Message>) -> Self {
+        let mut ids = Vec::new();
+        if let Some(id) = message
+            .and_then(|m| m.metadata.as_ref())
+            .and_then(|m| m.get("client_id"))
+            .and_then(|v| v.as_str())
+        {
+            ids.push(format!("client:{}", id));
+        }
+        if let Some(subject) = subject {
+            ids.push(format!("sub:{}", subject));
+        }
+        if let Some(connection) = connection {
+            ids.push(format!("conn:{}", connection));
+        }
+        Self(ids)
+    }
+
+    /// Add a `client_id` given outside a message, e.g. with an API answer.
+    pub fn with_client_id(mut self, client_id: Option<&str>) -> Self {
+        if let Some(id) = client_id.filter(|id| !id.is_empty()) {
+            self.0.insert(0, format!("client:{}", id));
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn shares_identity(&self, other: &Origin) -> bool {
+        self.0.iter().any(|id| other.0.contains(id))
+    }
+
+    /// The most stable identity, used to name the client in loop counts.
+    fn primary(&self) -> Option<&str> {
+        self.0.first().map(String::as_str)
+    }
+}
+
+/// Why an answer was refused.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum LoopViolation {
+    /// The responder asked the prompt.
+    SelfAnswer { identity: String },
+    /// The two clients exchanged too many answers within the window.
+    LoopDetected {
+        between: [String; 2],
+        exchanges: usize,
+        window_seconds: u64,
+    },
+}
+
+impl LoopViolation {
+    /// Protocol error code, as in the API's `error` field.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LoopViolation::SelfAnswer { .. } => "self_answer",
+            LoopViolation::LoopDetected { .. } => "loop_detected",
+        }
+    }
+}
+
+impl fmt::Display for LoopViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoopViolation::SelfAnswer { identity } => {
+                write!(f, "{} may not answer its own prompt", identity)
+            }
+            LoopViolation::LoopDetected {
+                between,
+                exchanges,
+                window_seconds,
+            } => write!(
+                f,
+                "{} and {} answered each other's prompts {} times in {}s; refusing further answers",
+                between[0], between[1], exchanges, window_seconds
+            ),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    askers: HashMap<Uuid, Origin>,
+    order: VecDeque<Uuid>,
+    /// Recent answers as (time, unordered client pair).
+    exchanges: VecDeque<(Instant, [String; 2])>,
+}
+
+/// Remembers prompt askers and checks answers against them.
+pub struct LoopGuard {
+    config: LoopDetectionConfig,
+    state: Mutex<State>,
+}
+
+impl Default for LoopGuard {
+    fn default() -> Self {
+        Self::new(LoopDetectionConfig::default())
+    }
+}
+
+impl LoopGuard {
+    pub fn new(config: LoopDetectionConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Remember that `origin` asked `prompt_id`.
+    pub async fn record_prompt(&self, prompt_id: Uuid, origin: Origin) {
+        if origin.is_empty() {
+            return;
+        }
+        let mut state = self.state.lock().await;
+        if state.askers.insert(prompt_id, origin).is_none() {
+            state.order.push_back(prompt_id);
+        }
+        while state.order.len() > MAX_TRACKED_PROMPTS {
+            if let Some(old) = state.order.pop_front() {
+                state.askers.remove(&old);
+            }
+        }
+    }
+
+    /// Check an answer to `prompt_id` from `responder`, counting it towards the loop limit
+    /// when it is allowed.
+    pub async fn check_answer(
+        &self,
+        prompt_id: Uuid,
+        responder: &Origin,
+    ) -> Result<(), LoopViolation> {
+        let mut state = self.state.lock().await;
+        let Some(asker) = state.askers.get(&prompt_id).cloned() else {
+            return Ok(());
+        };
+        if !self.config.allow_self_answers && asker.shares_identity(responder) {
+            return Err(LoopViolation::SelfAnswer {
+                identity: asker
+                    .0
+                    .iter()
+                    .find(|id| responder.0.contains(id))
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        }
+        let max = self.config.max_exchanges() as usize;
+        let (Some(a), Some(b)) = (asker.primary(), responder.primary()) else {
+            return Ok(());
+        };
+        if max == 0 || a == b {
+            return Ok(());
+        }
+        let pair = if a < b {
+            [a.to_string(), b.to_string()]
+        } else {
+            [b.to_string(), a.to_string()]
+        };
+        let window = Duration::from_secs(self.config.window_seconds());
+        let now = Instant::now();
+        while state
+            .exchanges
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > window)
+        {
+            state.exchanges.pop_front();
+        }
+        let exchanges = state.exchanges.iter().filter(|(_, p)| *p == pair).count();
+        if exchanges >= max {
+            return Err(LoopViolation::LoopDetected {
+                between: pair,
+                exchanges,
+                window_seconds: window.as_secs(),
+            });
+        }
+        state.exchanges.push_back((now, pair));
+        state.askers.remove(&prompt_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin(ids: &[&str]) -> Origin {
+        Origin(ids.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[tokio::test]
+    async fn test_self_answer_is_refused_unless_allowed() {
+        let guard = LoopGuard::default();
+        let prompt = Uuid::new_v4();
+        guard
+            .record_prompt(prompt, origin(&["client:a", "conn:1"]))
+            .await;
+        let err = guard
+            .check_answer(prompt, &origin(&["conn:1"]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "self_answer");
+        assert!(guard
+            .check_answer(prompt, &origin(&["sub:bob"]))
+            .await
+            .is_ok());
+
+        let lenient = LoopGuard::new(LoopDetectionConfig {
+            allow_self_answers: true,
+            ..Default::default()
+        });
+        lenient.record_prompt(prompt, origin(&["client:a"])).await;
+        assert!(lenient
+            .check_answer(prompt, &origin(&["client:a"]))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_bouncing_agents_are_stopped() {
+        let guard = LoopGuard::new(LoopDetectionConfig {
+            max_exchanges: Some(3),
+            ..Default::default()
+        });
+        let (a, b) = (origin(&["client:a"]), origin(&["client:b"]));
+        for i in 0..3 {
+            let prompt = Uuid::new_v4();
+            let (asker, responder) = if i % 2 == 0 { (&a, &b) } else { (&b, &a) };
+            guard.record_prompt(prompt, asker.clone()).await;
+            assert!(guard.check_answer(prompt, responder).await.is_ok());
+        }
+        let prompt = Uuid::new_v4();
+        guard.record_prompt(prompt, b.clone()).await;
+        let err = guard.check_answer(prompt, &a).await.unwrap_err();
+        assert_eq!(err.code(), "loop_detected");
+
+        // Unrelated pairs and untracked prompts are unaffected.
+        let prompt = Uuid::new_v4();
+        guard.record_prompt(prompt, origin(&["client:c"])).await;
+        assert!(guard.check_answer(prompt, &a).await.is_ok());
+        assert!(guard.check_answer(Uuid::new_v4(), &a).await.is_ok());
+    }
+}
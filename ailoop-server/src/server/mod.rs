@@ -1,8 +1,43 @@
 pub mod api;
+pub mod assignment;
+pub mod attachments;
+pub mod away;
 pub mod broadcast;
+pub mod capabilities;
+pub mod claims;
 pub mod core;
+pub mod digest;
+pub mod echo;
+pub mod events;
 pub mod history;
+pub mod hooks;
+pub mod incidents;
+pub mod ingest;
+pub mod jira;
+pub mod listen;
+pub mod lock;
+pub mod loops;
+pub mod oncall;
+pub mod privacy;
 pub mod providers;
+pub mod queue_position;
+pub mod reads;
+pub mod sampling;
+pub mod scheduler;
+pub mod sequencer;
+pub mod sessions;
+pub mod sla;
+pub mod snooze;
+pub mod stats;
+pub mod stdio;
+pub mod subscriptions;
+pub mod supervisor;
+pub mod telemetry;
+pub mod terminal;
+pub mod typing;
+pub mod usage;
+pub mod verification;
+pub mod watchdog;
 #[cfg(feature = "web-ui")]
 pub mod web;
 
@@ -0,0 +1,245 @@
+//! Routing prompts to whoever is on call
+//!
+//! With `[oncall] rotation_file` set, a prompt is first sent only to the providers of the
+//! person the rotation says is on call, then to the next person in line if none of those
+//! delivers, and finally to every provider. The routed prompt carries `metadata.oncall` with
+//! the person's name and contact, so exec sinks can page the right number. The rotation file
+//! is re-read whenever it changes.
+//!
+//! People without a `[people.<name>]` entry in the rotation are looked up in the `[team]`
+//! directory: its handles give their providers and contact.
+//!
+//! People who are [away](crate::server::away) are skipped: the next in line is treated as on
+//! call, and when everyone in line is away the prompt goes to every provider.
+
+use ailoop_core::models::{Message, OnCallConfig, Rotation, TeamDirectory};
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::server::away::AwayBoard;
+use crate::server::providers::is_prompt;
+
+/// One person to try, with the providers that reach them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnCallTier {
+    pub person: String,
+    pub providers: Vec<String>,
+}
+
+/// Where a prompt goes: the prompt to send and who to try, in order.
+#[derive(Debug, Clone)]
+pub struct OnCallRoute {
+    pub message: Message,
+    pub tiers: Vec<OnCallTier>,
+}
+
+/// Picks on-call recipients for prompts from a rotation file.
+pub struct OnCallRouter {
+    path: PathBuf,
+    channels: Vec<String>,
+    team: TeamDirectory,
+    away: Option<Arc<AwayBoard>>,
+    /// Last loaded rotation and the file's modification time when it was read.
+    cache: Mutex<Option<(Option<SystemTime>, Rotation)>>,
+}
+
+impl OnCallRouter {
+    pub fn new(path: impl Into<PathBuf>, channels: Vec<String>) -> Self {
+        Self {
+            path: path.into(),
+            channels,
+            team: TeamDirectory::default(),
+            away: None,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Fall back to `team` for people the rotation file does not describe.
+    pub fn with_team(mut self, team: TeamDirectory) -> Self {
+        self.team = team;
+        self
+    }
+
+    /// Pass over the people `away` says are away.
+    pub fn with_away(mut self, away: Arc<AwayBoard>) -> Self {
+        self.away = Some(away);
+        self
+    }
+
+    /// A router for `config`, or `None` when no rotation file is set.
+    pub fn from_config(config: &OnCallConfig) -> Option<Self> {
+        let path = config.rotation_file.as_deref()?;
+        Some(Self::new(path, config.channels.clone()))
+    }
+
+    /// Route `message` as of `now`. `None` for non-prompts, other channels, nobody on call
+    /// (or everyone in line away) or an unreadable rotation file; those go to every provider
+    /// as usual.
+    pub fn route(&self, message: &Message, now: DateTime<Utc>) -> Option<OnCallRoute> {
+        if !is_prompt(&message.content)
+            || !(self.channels.is_empty() || self.channels.contains(&message.channel))
+        {
+            return None;
+        }
+        let rotation = self.rotation()?;
+        let mut people = rotation.on_call(now);
+        if let Some(away) = &self.away {
+            people.retain(|name| {
+                let member = self.team.resolve(name).unwrap_or(*name);
+                !away.is_away(member, now)
+            });
+        }
+        let current = *people.first()?;
+
+        let tiers: Vec<OnCallTier> = people
+            .iter()
+            .map(|name| OnCallTier {
+                person: name.to_string(),
+                providers: match rotation.person(name) {
+                    Some(person) => person.providers.clone(),
+                    None => self
+                        .team
+                        .get(name)
+                        .map(|m| m.providers())
+                        .unwrap_or_default(),
+                },
+            })
+            .collect();
+
+        let mut message = message.clone();
+        let contact = match rotation.person(current) {
+            Some(person) => person.contact.clone(),
+            None => self
+                .team
+                .get(current)
+                .and_then(|m| m.handles.first().cloned()),
+        };
+        let oncall = serde_json::json!({
+            "name": current,
+            "contact": contact,
+        });
+        match message.metadata {
+            Some(serde_json::Value::Object(ref mut map)) => {
+                map.insert("oncall".to_string(), oncall);
+            }
+            _ => message.metadata = Some(serde_json::json!({ "oncall": oncall })),
+        }
+        Some(OnCallRoute { message, tiers })
+    }
+
+    fn rotation(&self) -> Option<Rotation> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((loaded_at, rotation)) = cache.as_ref() {
+            if *loaded_at == modified && modified.is_some() {
+                return Some(rotation.clone());
+            }
+        }
+        match Rotation::load(&self.path) {
+            Ok(rotation) => {
+                *cache = Some((modified, rotation.clone()));
+                Some(rotation)
+            }
+            Err(e) => {
+                tracing::warn!(path = %self.path.display(), error = %e, "failed to load on-call rotation");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{MessageContent, NotificationPriority, SenderType};
+
+    #[test]
+    fn routes_prompts_to_the_current_shift() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rotation.toml");
+        std::fs::write(
+            &path,
+            r#"
+            start = "2026-10-05T09:00:00Z"
+            order = ["alice", "bob"]
+
+            [people.alice]
+            providers = ["telegram"]
+            contact = "@alice"
+            "#,
+        )
+        .unwrap();
+        let router = OnCallRouter::new(&path, vec!["ops".to_string()]);
+        let now: DateTime<Utc> = "2026-10-06T10:00:00Z".parse().unwrap();
+
+        let prompt = Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Authorization {
+                action: "roll back".to_string(),
+                context: None,
+                timeout_seconds: 60,
+            },
+        );
+        let route = router.route(&prompt, now).unwrap();
+        assert_eq!(
+            route.tiers,
+            vec![
+                OnCallTier {
+                    person: "alice".to_string(),
+                    providers: vec!["telegram".to_string()],
+                },
+                OnCallTier {
+                    person: "bob".to_string(),
+                    providers: vec![],
+                },
+            ]
+        );
+        let oncall = &route.message.metadata.unwrap()["oncall"];
+        assert_eq!(oncall["name"], "alice");
+        assert_eq!(oncall["contact"], "@alice");
+
+        let mut elsewhere = prompt.clone();
+        elsewhere.channel = "dev".to_string();
+        assert!(router.route(&elsewhere, now).is_none());
+        let notification = Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "done".to_string(),
+                priority: NotificationPriority::Normal,
+            },
+        );
+        assert!(router.route(&notification, now).is_none());
+
+        let bob = ailoop_core::models::TeamMember {
+            handles: vec!["twilio:+15550199".to_string()],
+            ..Default::default()
+        };
+        let team = TeamDirectory::new([("bob".to_string(), bob)].into());
+        let away = Arc::new(AwayBoard::new());
+        let router = OnCallRouter::new(&path, vec![])
+            .with_team(team)
+            .with_away(Arc::clone(&away));
+        let route = router.route(&prompt, now).unwrap();
+        assert_eq!(route.tiers[1].providers, ["twilio"]);
+
+        // Alice being away makes bob the one on call.
+        away.set(
+            "alice",
+            ailoop_core::models::AwayStatus {
+                since: now,
+                until: None,
+                note: None,
+            },
+        )
+        .unwrap();
+        let route = router.route(&prompt, now).unwrap();
+        assert_eq!(route.tiers.len(), 1);
+        assert_eq!(route.message.metadata.unwrap()["oncall"]["name"], "bob");
+    }
+}
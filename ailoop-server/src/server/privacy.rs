@@ -0,0 +1,122 @@
+//! Export and erasure of a responder's data
+//!
+//! Responses are attributed to whoever gave them in `metadata.responder` (`telegram:alice`,
+//! an SSO subject, ...). An admin can export everything the server holds under that identity,
+//! or erase it: the responses leave the history and the files sent with them are deleted.
+//! Security events are written to the server log only, so they are not covered here.
+
+use crate::state::AiloopAppState;
+use ailoop_core::models::{ResponderErasure, ResponderExport};
+use ailoop_core::services::logging::log_security_event;
+use anyhow::{Context, Result};
+use chrono::Utc;
+
+/// Everything attributed to `responder`.
+pub async fn export_responder(state: &AiloopAppState, responder: &str) -> ResponderExport {
+    ResponderExport {
+        responder: responder.to_string(),
+        exported_at: Utc::now(),
+        messages: state.message_history.by_responder(responder).await,
+    }
+}
+
+/// Remove the messages attributed to `responder` from history and delete the files they sent.
+pub async fn erase_responder(state: &AiloopAppState, responder: &str) -> Result<ResponderErasure> {
+    let erased = state.message_history.erase_responder(responder).await;
+    let mut erasure = ResponderErasure {
+        responder: responder.to_string(),
+        messages: erased.len(),
+        attachments: 0,
+    };
+    for id in erased.iter().filter_map(|m| m.attachment_id()) {
+        if state
+            .attachments
+            .remove(id)
+            .await
+            .with_context(|| format!("Failed to delete attachment {}", id))?
+        {
+            erasure.attachments += 1;
+        }
+    }
+    log_security_event(
+        "responder_erased",
+        "*",
+        Some(responder),
+        &format!(
+            "{} message(s), {} attachment(s)",
+            erasure.messages, erasure.attachments
+        ),
+    );
+    Ok(erasure)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{Message, MessageContent, ResponseType, SenderType};
+
+    fn response(responder: &str, attachment: Option<serde_json::Value>) -> Message {
+        let mut message = Message::response(
+            "ops".to_string(),
+            MessageContent::Response {
+                answer: Some("yes".to_string()),
+                response_type: ResponseType::Text,
+            },
+            uuid::Uuid::new_v4(),
+        );
+        let mut metadata = serde_json::json!({ "responder": responder });
+        if let Some(attachment) = attachment {
+            metadata["attachment"] = attachment;
+        }
+        message.metadata = Some(metadata);
+        message
+    }
+
+    #[tokio::test]
+    async fn erase_removes_responses_and_their_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = AiloopAppState::new("public").with_attachment_dir(dir.path());
+        let file = state
+            .attachments
+            .store("notes.txt", None, b"secret")
+            .await
+            .unwrap();
+        let alice = response("telegram:alice", Some(serde_json::json!(file)));
+        let bob = response("telegram:bob", None);
+        let prompt = Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "deployed".to_string(),
+                priority: ailoop_core::models::NotificationPriority::Normal,
+            },
+        );
+        for message in [&prompt, &alice, &bob] {
+            state
+                .message_history
+                .add_message("ops", message.clone())
+                .await;
+        }
+
+        let export = export_responder(&state, "telegram:alice").await;
+        assert_eq!(export.messages.len(), 1);
+        assert_eq!(export.messages[0].id, alice.id);
+
+        let erasure = erase_responder(&state, "telegram:alice").await.unwrap();
+        assert_eq!((erasure.messages, erasure.attachments), (1, 1));
+        assert!(!file.path.exists());
+        assert!(state.attachments.get(file.id).await.is_none());
+        assert_eq!(state.message_history.get_message_count("ops").await, 2);
+        assert!(export_responder(&state, "telegram:alice")
+            .await
+            .messages
+            .is_empty());
+        assert_eq!(
+            export_responder(&state, "telegram:bob")
+                .await
+                .messages
+                .len(),
+            1
+        );
+    }
+}
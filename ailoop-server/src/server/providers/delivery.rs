@@ -0,0 +1,173 @@
+//! Delivery tracking: per-provider outcome of each message sent to notification sinks
+
+use ailoop_core::models::{
+    ProviderDeliveryConfig, DEFAULT_DELIVERY_BACKOFF_MS, DEFAULT_DELIVERY_RETRIES,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Messages whose delivery reports are kept (oldest evicted first).
+const MAX_TRACKED_MESSAGES: usize = 1024;
+
+/// Outcome of delivering one message to one provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Delivered,
+    Failed,
+    /// The provider does not take this message (e.g. channel not opted in).
+    Skipped,
+}
+
+/// Delivery result for one provider.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryReport {
+    pub provider: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// How prompts are delivered: retries per provider, and fan-out or failover.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryPolicy {
+    pub retries: u32,
+    pub backoff: Duration,
+    /// Stop at the first provider that delivers instead of sending to all.
+    pub failover: bool,
+    /// Provider names tried first, in this order.
+    pub order: Vec<String>,
+}
+
+impl Default for DeliveryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_DELIVERY_RETRIES,
+            backoff: Duration::from_millis(DEFAULT_DELIVERY_BACKOFF_MS),
+            failover: false,
+            order: Vec::new(),
+        }
+    }
+}
+
+impl From<&ProviderDeliveryConfig> for DeliveryPolicy {
+    fn from(config: &ProviderDeliveryConfig) -> Self {
+        Self {
+            retries: config.retries.unwrap_or(DEFAULT_DELIVERY_RETRIES),
+            backoff: Duration::from_millis(
+                config
+                    .retry_backoff_ms
+                    .unwrap_or(DEFAULT_DELIVERY_BACKOFF_MS),
+            ),
+            failover: config.failover,
+            order: config.order.clone(),
+        }
+    }
+}
+
+impl DeliveryPolicy {
+    /// Delay before retry number `attempt` (1-based): `backoff`, doubled each time.
+    pub fn delay_before(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(1u32 << attempt.saturating_sub(1).min(16))
+    }
+
+    /// Position of `provider` in the failover order; unlisted providers sort last.
+    pub fn rank(&self, provider: &str) -> usize {
+        self.order
+            .iter()
+            .position(|p| p == provider)
+            .unwrap_or(self.order.len())
+    }
+}
+
+/// Recent delivery reports keyed by message id.
+#[derive(Debug, Default)]
+pub struct DeliveryLog {
+    inner: Mutex<LogEntries>,
+}
+
+#[derive(Debug, Default)]
+struct LogEntries {
+    reports: HashMap<Uuid, Vec<DeliveryReport>>,
+    /// Message ids in first-seen order, for eviction.
+    order: VecDeque<Uuid>,
+}
+
+impl DeliveryLog {
+    pub fn record(&self, message_id: Uuid, report: DeliveryReport) {
+        let mut event = serde_json::to_value(&report).unwrap_or_default();
+        event["message_id"] = serde_json::json!(message_id);
+        crate::server::telemetry::delivery(
+            &report.provider,
+            event["status"].as_str().unwrap_or_default(),
+        );
+        crate::server::events::emit("delivery", event);
+        let mut log = self.inner.lock().expect("delivery log lock");
+        if !log.reports.contains_key(&message_id) {
+            if log.order.len() >= MAX_TRACKED_MESSAGES {
+                if let Some(oldest) = log.order.pop_front() {
+                    log.reports.remove(&oldest);
+                }
+            }
+            log.order.push_back(message_id);
+        }
+        log.reports.entry(message_id).or_default().push(report);
+    }
+
+    /// Reports for `message_id`, in delivery order (empty when nothing was sent).
+    pub fn get(&self, message_id: &Uuid) -> Vec<DeliveryReport> {
+        let log = self.inner.lock().expect("delivery log lock");
+        log.reports.get(message_id).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_from_config() {
+        let policy = DeliveryPolicy::from(&ProviderDeliveryConfig {
+            retries: Some(3),
+            retry_backoff_ms: Some(500),
+            failover: true,
+            order: vec!["twilio".to_string()],
+        });
+        assert_eq!(policy.delay_before(1), Duration::from_millis(500));
+        assert_eq!(policy.delay_before(3), Duration::from_millis(2000));
+        assert_eq!(policy.rank("twilio"), 0);
+        assert_eq!(policy.rank("telegram"), 1);
+        assert_eq!(
+            DeliveryPolicy::from(&ProviderDeliveryConfig::default()),
+            DeliveryPolicy::default()
+        );
+    }
+
+    #[test]
+    fn test_log_evicts_oldest_message() {
+        let log = DeliveryLog::default();
+        let report = |provider: &str| DeliveryReport {
+            provider: provider.to_string(),
+            status: DeliveryStatus::Delivered,
+            attempts: 1,
+            error: None,
+            at: Utc::now(),
+        };
+        let first = Uuid::new_v4();
+        log.record(first, report("telegram"));
+        log.record(first, report("twilio"));
+        assert_eq!(log.get(&first).len(), 2);
+
+        for _ in 0..MAX_TRACKED_MESSAGES {
+            log.record(Uuid::new_v4(), report("telegram"));
+        }
+        assert!(log.get(&first).is_empty());
+    }
+}
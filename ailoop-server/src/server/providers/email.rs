@@ -0,0 +1,1306 @@
+//! Email provider: prompts and notifications sent over SMTP, replies read over IMAP
+//!
+//! [`EmailSink`] mails prompts with `[ailoop <prompt id>]` in the subject (an exec sink can do
+//! the same when the server may not send mail itself). Replies keep the subject, so each poll
+//! searches the mailbox for unseen messages carrying that token, answers the prompt with the
+//! first line of the body (`yes`, `no: wrong cluster`, `2`), and marks them seen. The first line
+//! is taken from the `text/plain` part with its transfer encoding undone, skipping quoted text.
+//! [`EmailProvider`] joins the two halves; either may be left unset.
+//!
+//! Anyone can forge a `From` header, so without a `responders` list email replies may answer
+//! questions but not settle authorizations.
+
+use crate::server::providers::tls::connect_tls;
+use crate::server::providers::{
+    infer_response_type, is_prompt, PendingPromptRegistry, PromptType, Provider, ProviderReply,
+    ProviderResult,
+};
+use ailoop_core::models::{
+    EmailProviderConfig, Message, MessageContent, DEFAULT_EMAIL_POLL_SECONDS, DEFAULT_IMAP_PORT,
+    DEFAULT_SMTP_PORT,
+};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures_util::stream::{BoxStream, StreamExt};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use uuid::Uuid;
+
+/// Subject prefix of the prompt token: `[ailoop <prompt id>]`.
+const TOKEN_PREFIX: &str = "[ailoop ";
+
+/// Longest subject text after the token, in characters.
+const MAX_SUBJECT_CHARS: usize = 120;
+
+/// Most UTF-8 bytes in one RFC 2047 encoded word: 45 bytes are 60 base64 characters, which keeps
+/// the word with its `=?UTF-8?B?` and `?=` under the 75-character limit.
+const MAX_ENCODED_WORD_BYTES: usize = 45;
+
+/// Time one mail may take, from connecting to `QUIT`.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error("mail server connection failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("mail server said: {0}")]
+    Rejected(String),
+    #[error("mail server closed the connection")]
+    Closed,
+}
+
+/// A reply found in the mailbox.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailReply {
+    pub prompt_id: Uuid,
+    /// Sender address, lower-cased.
+    pub from: String,
+    pub answer: String,
+}
+
+/// Polls an IMAP mailbox for replies to prompts.
+#[derive(Clone)]
+pub struct EmailReplySource {
+    host: String,
+    port: u16,
+    username: String,
+    password: String,
+    mailbox: String,
+    interval: Duration,
+    responders: Vec<String>,
+}
+
+impl EmailReplySource {
+    /// Build from `[providers.email]` with the password from `AILOOP_IMAP_PASSWORD`. `None`
+    /// when disabled or `imap_host` is unset.
+    pub fn from_config(config: &EmailProviderConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let host = config.imap_host.as_ref()?;
+        let Some(username) = &config.username else {
+            tracing::warn!("Email replies skipped: username is required");
+            return None;
+        };
+        let Ok(password) = std::env::var("AILOOP_IMAP_PASSWORD") else {
+            tracing::warn!("Email replies skipped: AILOOP_IMAP_PASSWORD not set");
+            return None;
+        };
+        Some(Self {
+            host: host.clone(),
+            port: config.imap_port.unwrap_or(DEFAULT_IMAP_PORT),
+            username: username.clone(),
+            password,
+            mailbox: config
+                .mailbox
+                .clone()
+                .unwrap_or_else(|| "INBOX".to_string()),
+            interval: Duration::from_secs(
+                config
+                    .poll_interval_seconds
+                    .unwrap_or(DEFAULT_EMAIL_POLL_SECONDS)
+                    .max(1),
+            ),
+            responders: config
+                .responders
+                .iter()
+                .map(|r| r.trim().to_lowercase())
+                .collect(),
+        })
+    }
+
+    /// Poll every interval until dropped, yielding the replies `registry` should get. Failed
+    /// polls are logged and retried on the next tick.
+    fn replies(self, registry: Arc<PendingPromptRegistry>) -> BoxStream<'static, ProviderReply> {
+        let interval = tokio::time::interval(self.interval);
+        let state = (self, registry, interval, VecDeque::new());
+        futures_util::stream::unfold(
+            state,
+            |(source, registry, mut interval, mut found)| async move {
+                loop {
+                    while let Some(reply) = found.pop_front() {
+                        if source.accepts(&reply, &registry).await {
+                            let reply = provider_reply(reply);
+                            return Some((reply, (source, registry, interval, found)));
+                        }
+                    }
+                    interval.tick().await;
+                    match source.fetch().await {
+                        Ok(replies) => found.extend(replies),
+                        Err(e) => {
+                            tracing::warn!(host = %source.host, error = %e, "Email poll failed")
+                        }
+                    }
+                }
+            },
+        )
+        .boxed()
+    }
+
+    /// Whether `reply` may answer its prompt: the sender must be in `responders`, and with no
+    /// `responders` a reply cannot settle an authorization.
+    async fn accepts(&self, reply: &EmailReply, registry: &PendingPromptRegistry) -> bool {
+        if !self.responders.is_empty() {
+            let listed = self.responders.contains(&reply.from);
+            if !listed {
+                tracing::warn!("Ignoring email reply from non-responder {}", reply.from);
+            }
+            return listed;
+        }
+        let authorization =
+            registry.prompt_type(reply.prompt_id).await == Some(PromptType::Authorization);
+        if authorization {
+            tracing::warn!(
+                prompt = %reply.prompt_id,
+                "Ignoring email reply from {} to an authorization: no responders configured",
+                reply.from
+            );
+        }
+        !authorization
+    }
+
+    /// One poll: connect, collect unseen replies and mark them seen.
+    async fn fetch(&self) -> Result<Vec<EmailReply>, EmailError> {
+        let tcp = tokio::net::TcpStream::connect((self.host.as_str(), self.port)).await?;
+        let tls = connect_tls(&self.host, tcp).await?;
+
+        let mut session = ImapSession::new(tls);
+        session.greeting().await?;
+        session
+            .command(&format!(
+                "LOGIN {} {}",
+                quote(&self.username),
+                quote(&self.password)
+            ))
+            .await?;
+        let replies = poll_mailbox(&mut session, &self.mailbox).await;
+        let _ = session.command("LOGOUT").await;
+        replies
+    }
+}
+
+/// `reply` as answered by `email:<address>`.
+fn provider_reply(reply: EmailReply) -> ProviderReply {
+    ProviderReply {
+        reply_to_message_id: None,
+        prompt_id: Some(reply.prompt_id),
+        response_type: infer_response_type(&reply.answer),
+        answer: Some(reply.answer),
+        sender_id: Some(reply.from),
+        sender_name: None,
+        chat_id: None,
+        channel: None,
+    }
+}
+
+/// Select `mailbox`, read unseen replies carrying a prompt token and mark them seen.
+async fn poll_mailbox<S: AsyncRead + AsyncWrite + Unpin>(
+    session: &mut ImapSession<S>,
+    mailbox: &str,
+) -> Result<Vec<EmailReply>, EmailError> {
+    session
+        .command(&format!("SELECT {}", quote(mailbox)))
+        .await?;
+    let found = session
+        .command(&format!(
+            "UID SEARCH UNSEEN SUBJECT {}",
+            quote(TOKEN_PREFIX)
+        ))
+        .await?;
+    let uids: Vec<u32> = found
+        .iter()
+        .filter_map(|r| r.text.strip_prefix("* SEARCH"))
+        .flat_map(|rest| rest.split_whitespace().filter_map(|uid| uid.parse().ok()))
+        .collect();
+
+    let mut replies = Vec::new();
+    for uid in uids {
+        let fetched = session
+            .command(&format!(
+                "UID FETCH {} (BODY.PEEK[HEADER.FIELDS (FROM SUBJECT CONTENT-TYPE CONTENT-TRANSFER-ENCODING)] BODY.PEEK[TEXT])",
+                uid
+            ))
+            .await?;
+        let literal = |section: &str| {
+            fetched
+                .iter()
+                .flat_map(|r| &r.literals)
+                .find(|(label, _)| label.to_uppercase().contains(section))
+                .map(|(_, body)| body.as_str())
+        };
+        let headers = literal("HEADER").unwrap_or_default();
+        let body = literal("TEXT").unwrap_or_default();
+        match parse_reply(headers, body) {
+            Some(reply) => replies.push(reply),
+            None => tracing::debug!(uid, "Email without a prompt token or answer"),
+        }
+        session
+            .command(&format!("UID STORE {} +FLAGS (\\Seen)", uid))
+            .await?;
+    }
+    Ok(replies)
+}
+
+/// One untagged response line, with its literals (`{n}` payloads) split out and labelled by
+/// the text that announced them.
+#[derive(Debug, Default)]
+struct Untagged {
+    text: String,
+    literals: Vec<(String, String)>,
+}
+
+/// Minimal IMAP4rev1 client: tagged commands, untagged responses with literals.
+struct ImapSession<S> {
+    stream: BufReader<S>,
+    next_tag: u32,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> ImapSession<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+            next_tag: 1,
+        }
+    }
+
+    async fn greeting(&mut self) -> Result<(), EmailError> {
+        let line = self.read_line().await?;
+        if line.starts_with("* OK") || line.starts_with("* PREAUTH") {
+            Ok(())
+        } else {
+            Err(EmailError::Rejected(line))
+        }
+    }
+
+    /// Send `command` and collect untagged responses until its tagged completion.
+    async fn command(&mut self, command: &str) -> Result<Vec<Untagged>, EmailError> {
+        let tag = format!("a{}", self.next_tag);
+        self.next_tag += 1;
+        let stream = self.stream.get_mut();
+        stream
+            .write_all(format!("{} {}\r\n", tag, command).as_bytes())
+            .await?;
+        stream.flush().await?;
+
+        let mut responses = Vec::new();
+        let mut current = Untagged::default();
+        loop {
+            let line = self.read_line().await?;
+            if let Some(len) = literal_len(&line) {
+                let mut bytes = vec![0; len];
+                self.stream.read_exact(&mut bytes).await?;
+                let label = line[..line.rfind('{').unwrap_or(0)].to_string();
+                current.text.push_str(&label);
+                current
+                    .literals
+                    .push((label, String::from_utf8_lossy(&bytes).into_owned()));
+                continue;
+            }
+            current.text.push_str(&line);
+            let done = std::mem::take(&mut current);
+            if let Some(status) = done.text.strip_prefix(&format!("{} ", tag)) {
+                return if status.starts_with("OK") {
+                    Ok(responses)
+                } else {
+                    Err(EmailError::Rejected(status.to_string()))
+                };
+            }
+            responses.push(done);
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<String, EmailError> {
+        let mut line = String::new();
+        if self.stream.read_line(&mut line).await? == 0 {
+            return Err(EmailError::Closed);
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Length of the literal announced at the end of `line` (`... {42}`).
+fn literal_len(line: &str) -> Option<usize> {
+    line.strip_suffix('}')?.rsplit_once('{')?.1.parse().ok()
+}
+
+/// IMAP quoted string.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Reply from the `From`/`Subject` header block and the body text.
+fn parse_reply(headers: &str, body: &str) -> Option<EmailReply> {
+    let prompt_id = prompt_id_from_subject(&decode_header(&header_value(headers, "Subject")?))?;
+    let from = sender_address(&header_value(headers, "From")?);
+    let answer = first_line(&plain_text(headers, body))?;
+    Some(EmailReply {
+        prompt_id,
+        from,
+        answer,
+    })
+}
+
+/// The value of header `name` in `headers`.
+fn header_value(headers: &str, name: &str) -> Option<String> {
+    unfold_headers(headers).into_iter().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// The text a reply was written in, with its transfer encoding undone: the body itself, or
+/// in a multipart body the first `text/plain` part (else the first part).
+fn plain_text(headers: &str, body: &str) -> String {
+    let content_type = header_value(headers, "Content-Type").unwrap_or_default();
+    if let Some(boundary) = boundary(&content_type) {
+        let delimiter = format!("--{}", boundary);
+        let parts: Vec<(&str, &str)> = body
+            .split(delimiter.as_str())
+            .skip(1)
+            .take_while(|part| !part.starts_with("--"))
+            .map(split_part)
+            .collect();
+        let readable = |(headers, _): &&(&str, &str)| {
+            let content_type = header_value(headers, "Content-Type")
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            content_type.is_empty()
+                || content_type.starts_with("text/plain")
+                || content_type.starts_with("multipart/")
+        };
+        return parts
+            .iter()
+            .find(readable)
+            .or(parts.first())
+            .map(|(headers, body)| plain_text(headers, body))
+            .unwrap_or_default();
+    }
+    let encoding = header_value(headers, "Content-Transfer-Encoding").unwrap_or_default();
+    let bytes = match encoding.to_ascii_lowercase().as_str() {
+        "base64" => {
+            let compact: String = body.split_whitespace().collect();
+            match STANDARD.decode(compact) {
+                Ok(bytes) => bytes,
+                Err(_) => return body.to_string(),
+            }
+        }
+        "quoted-printable" => quoted_printable(body),
+        _ => return body.to_string(),
+    };
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// The `boundary` parameter of a multipart `Content-Type`.
+fn boundary(content_type: &str) -> Option<&str> {
+    if !content_type.to_ascii_lowercase().starts_with("multipart/") {
+        return None;
+    }
+    content_type.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// A MIME part after its delimiter line, split into its headers and its content.
+fn split_part(part: &str) -> (&str, &str) {
+    let part = part.split_once('\n').map_or("", |(_, rest)| rest);
+    if let Some(content) = part
+        .strip_prefix("\r\n")
+        .or_else(|| part.strip_prefix('\n'))
+    {
+        return ("", content);
+    }
+    match (part.find("\r\n\r\n"), part.find("\n\n")) {
+        (Some(end), _) => (&part[..end], &part[end + 4..]),
+        (None, Some(end)) => (&part[..end], &part[end + 2..]),
+        (None, None) => (part, ""),
+    }
+}
+
+/// Quoted-printable `text` decoded: `=XX` escapes become bytes and soft line breaks (`=` at
+/// the end of a line) are removed. Malformed escapes are kept as they are.
+fn quoted_printable(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte != b'=' {
+            bytes.push(byte);
+            continue;
+        }
+        if let Some(after) = rest
+            .strip_prefix(b"\r\n")
+            .or_else(|| rest.strip_prefix(b"\n"))
+        {
+            rest = after;
+            continue;
+        }
+        let escaped = rest
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &rest[2..];
+            }
+            None => bytes.push(byte),
+        }
+    }
+    bytes
+}
+
+/// Header lines with continuation lines joined.
+fn unfold_headers(headers: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in headers.lines() {
+        match lines.last_mut() {
+            Some(last) if line.starts_with([' ', '\t']) => {
+                last.push(' ');
+                last.push_str(line.trim());
+            }
+            _ if !line.trim().is_empty() => lines.push(line.to_string()),
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// Prompt id from a subject such as `Re: [ailoop 550e8400-...] Deploy?`.
+pub fn prompt_id_from_subject(subject: &str) -> Option<Uuid> {
+    // ASCII lower-casing keeps byte offsets valid in `subject`.
+    let lower = subject.to_ascii_lowercase();
+    let start = lower.find(TOKEN_PREFIX)? + TOKEN_PREFIX.len();
+    let rest = &subject[start..];
+    let end = rest.find(']')?;
+    Uuid::parse_str(rest[..end].trim()).ok()
+}
+
+/// `Alice <alice@example.com>` -> `alice@example.com`.
+fn sender_address(from: &str) -> String {
+    let address = match (from.rfind('<'), from.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &from[start + 1..end],
+        _ => from,
+    };
+    address.trim().to_lowercase()
+}
+
+/// First line of the reply itself: MIME boundaries and part headers, blank lines and the
+/// quoted original are skipped.
+pub fn first_line(body: &str) -> Option<String> {
+    let mut in_part_headers = false;
+    for line in body.lines() {
+        let line = line.trim();
+        if line.starts_with("--") {
+            // MIME boundary: part headers follow until a blank line.
+            in_part_headers = true;
+            continue;
+        }
+        if in_part_headers {
+            in_part_headers = !line.is_empty();
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('>') || (line.starts_with("On ") && line.ends_with("wrote:")) {
+            return None;
+        }
+        return Some(line.to_string());
+    }
+    None
+}
+
+/// Mails prompts and notifications over SMTP.
+pub struct EmailSink {
+    host: String,
+    port: u16,
+    starttls: bool,
+    /// Login for `AUTH PLAIN`; none for relays that take mail without one.
+    login: Option<(String, String)>,
+    from: String,
+    to: Vec<String>,
+    channels: Vec<String>,
+    prompts_only: bool,
+}
+
+impl EmailSink {
+    /// Build from `[providers.email]` with the password from `AILOOP_SMTP_PASSWORD` (or
+    /// `AILOOP_IMAP_PASSWORD` when both servers share the account). Without a password mail is
+    /// sent unauthenticated. `None` when disabled or `smtp_host` is unset.
+    pub fn from_config(config: &EmailProviderConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let host = config.smtp_host.clone()?;
+        let Some(from) = config.from.clone().or_else(|| config.username.clone()) else {
+            tracing::warn!("Email sending skipped: from (or username) is required");
+            return None;
+        };
+        if config.to.is_empty() {
+            tracing::warn!("Email sending skipped: no `to` addresses");
+            return None;
+        }
+        let password = ["AILOOP_SMTP_PASSWORD", "AILOOP_IMAP_PASSWORD"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|p| !p.is_empty()));
+        let username = config
+            .username
+            .clone()
+            .unwrap_or_else(|| sender_address(&from));
+        Some(Self {
+            host,
+            port: config.smtp_port.unwrap_or(DEFAULT_SMTP_PORT),
+            starttls: config.smtp_starttls,
+            login: password.map(|password| (username, password)),
+            from,
+            to: config.to.clone(),
+            channels: config.channels.clone(),
+            prompts_only: config.prompts_only,
+        })
+    }
+
+    /// The whole mail for `message`, headers included, with CRLF line ends.
+    fn mail(&self, message: &Message) -> String {
+        let (subject, body) = compose(message);
+        let mut mail = String::new();
+        let _ = write!(
+            mail,
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMessage-ID: <{}@ailoop>\r\n",
+            self.from,
+            self.to.join(", "),
+            encode_header(&subject),
+            message.timestamp.to_rfc2822(),
+            message.id
+        );
+        mail.push_str("MIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\n");
+        mail.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+        let encoded = STANDARD.encode(body.replace('\n', "\r\n"));
+        for line in encoded.as_bytes().chunks(76) {
+            mail.push_str(std::str::from_utf8(line).unwrap_or_default());
+            mail.push_str("\r\n");
+        }
+        mail
+    }
+
+    /// Connect, log in when there is a password, and send one mail to every recipient.
+    async fn send(&self, mail: &str) -> Result<(), EmailError> {
+        let envelope = Envelope {
+            login: self.login.as_ref().map(|(u, p)| (u.as_str(), p.as_str())),
+            from: sender_address(&self.from),
+            to: self.to.iter().map(|to| sender_address(to)).collect(),
+        };
+        let tcp = tokio::net::TcpStream::connect((self.host.as_str(), self.port)).await?;
+        if self.starttls {
+            let mut plain = SmtpSession::new(tcp);
+            plain.reply(220).await?;
+            plain.command(&envelope.ehlo(), 250).await?;
+            plain.command("STARTTLS", 220).await?;
+            let tls = connect_tls(&self.host, plain.into_inner()).await?;
+            submit(&mut SmtpSession::new(tls), &envelope, mail).await
+        } else {
+            let mut session = SmtpSession::new(connect_tls(&self.host, tcp).await?);
+            session.reply(220).await?;
+            submit(&mut session, &envelope, mail).await
+        }
+    }
+
+    async fn deliver(&self, message: &Message) -> ProviderResult<()> {
+        let mail = self.mail(message);
+        match tokio::time::timeout(SMTP_TIMEOUT, self.send(&mail)).await {
+            Ok(sent) => Ok(sent?),
+            Err(_) => {
+                Err(format!("SMTP to {} timed out after {:?}", self.host, SMTP_TIMEOUT).into())
+            }
+        }
+    }
+}
+
+impl EmailSink {
+    fn handles(&self, message: &Message) -> bool {
+        if !self.channels.is_empty() && !self.channels.contains(&message.channel) {
+            return false;
+        }
+        match message.content {
+            MessageContent::Notification { .. } => !self.prompts_only,
+            ref content => is_prompt(content),
+        }
+    }
+}
+
+/// `[providers.email]`: mail sent by an [`EmailSink`], replies read by an [`EmailReplySource`].
+pub struct EmailProvider {
+    sink: Option<EmailSink>,
+    replies: Option<EmailReplySource>,
+    registry: Arc<PendingPromptRegistry>,
+}
+
+impl EmailProvider {
+    /// Build from `[providers.email]`; replies are checked against prompts in `registry`.
+    /// `None` when disabled or neither `smtp_host` nor `imap_host` is usable.
+    pub fn from_config(
+        config: &EmailProviderConfig,
+        registry: Arc<PendingPromptRegistry>,
+    ) -> Option<Self> {
+        let sink = EmailSink::from_config(config);
+        let replies = EmailReplySource::from_config(config);
+        if sink.is_none() && replies.is_none() {
+            return None;
+        }
+        if let Some(source) = &replies {
+            tracing::info!(host = %source.host, mailbox = %source.mailbox, "Polling email replies");
+            if source.responders.is_empty() {
+                tracing::warn!(
+                    "Email replies cannot settle authorizations: no responders configured"
+                );
+            }
+        }
+        Some(Self {
+            sink,
+            replies,
+            registry,
+        })
+    }
+}
+
+/// Mail cannot be edited.
+#[async_trait]
+impl Provider for EmailProvider {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn handles(&self, message: &Message) -> bool {
+        self.sink.as_ref().is_some_and(|sink| sink.handles(message))
+    }
+
+    async fn send_prompt(&self, message: &Message) -> ProviderResult<Option<String>> {
+        if let Some(sink) = &self.sink {
+            sink.deliver(message).await?;
+        }
+        Ok(None)
+    }
+
+    async fn send_notification(&self, message: &Message) -> ProviderResult<()> {
+        match &self.sink {
+            Some(sink) => sink.deliver(message).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Replies from senders outside `responders` are dropped; see [`EmailReplySource`].
+    fn reply_stream(&self) -> Option<BoxStream<'static, ProviderReply>> {
+        let source = self.replies.clone()?;
+        Some(source.replies(Arc::clone(&self.registry)))
+    }
+}
+
+/// Subject and plain-text body of the mail for `message`. Prompts carry the reply token in the
+/// subject and say how to answer at the end of the body.
+fn compose(message: &Message) -> (String, String) {
+    let channel = &message.channel;
+    let mut body = String::new();
+    if let Some(heading) = message.agent_heading() {
+        let _ = writeln!(body, "{}\n", heading);
+    }
+    let (title, hint) = match &message.content {
+        MessageContent::Decision {
+            summary,
+            context_markdown,
+            options,
+            recommendation,
+            ..
+        } => {
+            let _ = writeln!(body, "{}\n", summary);
+            if let Some(context) = context_markdown {
+                let _ = writeln!(body, "{}\n", context.trim());
+            }
+            for (i, option) in options.iter().enumerate() {
+                let recommended = recommendation
+                    .as_ref()
+                    .is_some_and(|r| r.option_id == option.id);
+                let _ = writeln!(
+                    body,
+                    "{}) {}{}",
+                    i + 1,
+                    option.label,
+                    if recommended { " (recommended)" } else { "" }
+                );
+                if let Some(detail) = &option.detail_markdown {
+                    let _ = writeln!(body, "   {}", detail.trim().replace('\n', "\n   "));
+                }
+            }
+            body.push('\n');
+            (
+                format!("Decision [{}]: {}", channel, summary),
+                Some("Reply with the number of your choice, e.g. \"1\"."),
+            )
+        }
+        MessageContent::Authorization {
+            action, context, ..
+        } => {
+            let _ = writeln!(body, "Authorize: {}\n", action);
+            if let Some(context) = context {
+                let context = serde_json::to_string_pretty(context).unwrap_or_default();
+                let _ = writeln!(body, "{}\n", context);
+            }
+            (
+                format!("Authorization [{}]: {}", channel, action),
+                Some("Reply \"yes\" to approve or \"no: <reason>\" to deny."),
+            )
+        }
+        MessageContent::Navigate { url, .. } => {
+            let _ = writeln!(body, "Open: {}\n", url);
+            (
+                format!("Navigation [{}]: {}", channel, url),
+                Some("Reply \"yes\" once it is open or \"no\" to decline."),
+            )
+        }
+        MessageContent::Notification { text, .. } => {
+            let _ = writeln!(body, "{}\n", text.trim());
+            (format!("Notification [{}]: {}", channel, text), None)
+        }
+        _ => (format!("Message [{}]", channel), None),
+    };
+    if let Some(summary) = message.thread_summary() {
+        let _ = writeln!(body, "{}\n", summary);
+    }
+    if let Some(hint) = hint {
+        let _ = writeln!(
+            body,
+            "{} Put the answer on the first line and keep the subject as it is.",
+            hint
+        );
+    }
+
+    let title = title.lines().next().unwrap_or_default();
+    let mut subject: String = title.chars().take(MAX_SUBJECT_CHARS).collect();
+    if title.chars().count() > MAX_SUBJECT_CHARS {
+        subject.push_str("...");
+    }
+    if hint.is_some() {
+        subject = format!("{}{}] {}", TOKEN_PREFIX, message.id, subject);
+    }
+    (subject, body.trim_end().to_string() + "\n")
+}
+
+/// Header value as is when ASCII. Otherwise the ASCII words before the first non-ASCII one
+/// (the `[ailoop <id>]` token among them) stay plain and the rest becomes RFC 2047 encoded
+/// words, one per continuation line.
+fn encode_header(value: &str) -> String {
+    let Some(first) = value.find(|c: char| !c.is_ascii()) else {
+        return value.to_string();
+    };
+    let (plain, rest) = value.split_at(value[..first].rfind(' ').map_or(0, |i| i + 1));
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in rest.chars() {
+        if chunk.len() + c.len_utf8() > MAX_ENCODED_WORD_BYTES {
+            words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+            chunk.clear();
+        }
+        chunk.push(c);
+    }
+    words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+    format!("{}{}", plain, words.join("\r\n "))
+}
+
+/// Header value with its RFC 2047 encoded words decoded (as UTF-8); the space between two
+/// encoded words is dropped, as the RFC requires.
+fn decode_header(value: &str) -> String {
+    let mut decoded = String::new();
+    let mut after_word = false;
+    for (i, token) in value.split(' ').enumerate() {
+        let word = decode_word(token);
+        if i > 0 && !(after_word && word.is_some()) {
+            decoded.push(' ');
+        }
+        after_word = word.is_some();
+        decoded.push_str(word.as_deref().unwrap_or(token));
+    }
+    decoded
+}
+
+/// The text of one `=?charset?B|Q?text?=` encoded word.
+fn decode_word(token: &str) -> Option<String> {
+    let inner = token.strip_prefix("=?")?.strip_suffix("?=")?;
+    let mut parts = inner.splitn(3, '?');
+    let (_charset, encoding, text) = (parts.next()?, parts.next()?, parts.next()?);
+    let bytes = match encoding {
+        "B" | "b" => STANDARD.decode(text).ok()?,
+        "Q" | "q" => quoted_printable(&text.replace('_', " ")),
+        _ => return None,
+    };
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Who a mail is from and to, and the login to send it with.
+struct Envelope<'a> {
+    login: Option<(&'a str, &'a str)>,
+    from: String,
+    to: Vec<String>,
+}
+
+impl Envelope<'_> {
+    fn ehlo(&self) -> String {
+        let domain = self.from.rsplit_once('@').map_or("localhost", |(_, d)| d);
+        format!("EHLO {}", domain)
+    }
+}
+
+/// After the greeting: say hello, log in, and send `mail` (CRLF line ends) to every recipient.
+async fn submit<S: AsyncRead + AsyncWrite + Unpin>(
+    session: &mut SmtpSession<S>,
+    envelope: &Envelope<'_>,
+    mail: &str,
+) -> Result<(), EmailError> {
+    session.command(&envelope.ehlo(), 250).await?;
+    if let Some((username, password)) = envelope.login {
+        let token = STANDARD.encode(format!("\0{}\0{}", username, password));
+        session
+            .command(&format!("AUTH PLAIN {}", token), 235)
+            .await?;
+    }
+    session
+        .command(&format!("MAIL FROM:<{}>", envelope.from), 250)
+        .await?;
+    for to in &envelope.to {
+        session.command(&format!("RCPT TO:<{}>", to), 250).await?;
+    }
+    session.command("DATA", 354).await?;
+    // A line starting with a dot gets a second one, so it cannot end the mail early. The
+    // first line is a header and never does.
+    let data = mail.trim_end_matches("\r\n").replace("\r\n.", "\r\n..");
+    session.command(&format!("{}\r\n.", data), 250).await?;
+    let _ = session.command("QUIT", 221).await;
+    Ok(())
+}
+
+/// Minimal SMTP client: commands and their (possibly multi-line) replies.
+struct SmtpSession<S> {
+    stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SmtpSession<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    /// Send `command` and read the reply, which must have code `expected`.
+    async fn command(&mut self, command: &str, expected: u16) -> Result<String, EmailError> {
+        let stream = self.stream.get_mut();
+        stream
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .await?;
+        stream.flush().await?;
+        self.reply(expected).await
+    }
+
+    /// Read one reply (`250-...` lines continue it, `250 ...` ends it); anything but
+    /// `expected` is an error carrying the server's last line.
+    async fn reply(&mut self, expected: u16) -> Result<String, EmailError> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(EmailError::Closed);
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+            text.push_str(line.get(4..).unwrap_or_default());
+            if line.as_bytes().get(3) == Some(&b'-') {
+                text.push('\n');
+                continue;
+            }
+            return match line.get(..3).and_then(|code| code.parse::<u16>().ok()) {
+                Some(code) if code == expected => Ok(text),
+                _ => Err(EmailError::Rejected(line.to_string())),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROMPT: &str = "550e8400-e29b-41d4-a716-446655440000";
+
+    #[test]
+    fn subject_tokens_and_first_lines() {
+        assert_eq!(
+            prompt_id_from_subject(&format!("Re: [ailoop {}] Deploy v2?", PROMPT)),
+            Some(Uuid::parse_str(PROMPT).unwrap())
+        );
+        assert_eq!(prompt_id_from_subject("Re: Deploy v2?"), None);
+        assert_eq!(prompt_id_from_subject("[ailoop not-an-id]"), None);
+        assert_eq!(prompt_id_from_subject("İİİ [ailoop x]"), None);
+        assert_eq!(
+            prompt_id_from_subject(&format!("İİİ [AILOOP {}]", PROMPT)),
+            Some(Uuid::parse_str(PROMPT).unwrap())
+        );
+
+        assert_eq!(
+            first_line("\r\nno: wrong cluster\r\n\r\nOn Mon, Bob wrote:\r\n> Deploy?\r\n"),
+            Some("no: wrong cluster".to_string())
+        );
+        assert_eq!(
+            first_line(
+                "--b1\r\nContent-Type: text/plain; charset=utf-8\r\n\r\nyes\r\n--b1\r\nContent-Type: text/html\r\n\r\n<p>yes</p>\r\n--b1--\r\n"
+            ),
+            Some("yes".to_string())
+        );
+        assert_eq!(first_line("> quoted only\r\n"), None);
+        assert_eq!(
+            sender_address("Alice Ops <Alice@Example.com>"),
+            "alice@example.com"
+        );
+    }
+
+    fn sink() -> EmailSink {
+        EmailSink {
+            host: "smtp.example.com".to_string(),
+            port: DEFAULT_SMTP_PORT,
+            starttls: false,
+            login: Some(("ailoop@example.com".to_string(), "secret".to_string())),
+            from: "ailoop <ailoop@example.com>".to_string(),
+            to: vec!["oncall@example.com".to_string()],
+            channels: vec!["ops".to_string()],
+            prompts_only: false,
+        }
+    }
+
+    fn message(channel: &str, content: MessageContent) -> Message {
+        Message::new(
+            channel.to_string(),
+            ailoop_core::models::SenderType::Agent,
+            content,
+        )
+    }
+
+    #[test]
+    fn prompts_carry_the_reply_token() {
+        let prompt = message(
+            "ops",
+            MessageContent::Authorization {
+                action: "deploy v2".to_string(),
+                context: None,
+                timeout_seconds: 60,
+            },
+        );
+        let (subject, body) = compose(&prompt);
+        assert_eq!(
+            subject,
+            format!("[ailoop {}] Authorization [ops]: deploy v2", prompt.id)
+        );
+        assert_eq!(prompt_id_from_subject(&subject), Some(prompt.id));
+        assert!(body.starts_with("Authorize: deploy v2\n"));
+        assert!(body.contains("Reply \"yes\" to approve"));
+
+        let note = message(
+            "ops",
+            MessageContent::Notification {
+                text: "disk full".to_string(),
+                priority: Default::default(),
+            },
+        );
+        assert_eq!(compose(&note).0, "Notification [ops]: disk full");
+        assert_eq!(encode_header("Déployé"), "=?UTF-8?B?RMOpcGxvecOp?=");
+
+        let sink = sink();
+        assert!(sink.handles(&prompt));
+        assert!(sink.handles(&note));
+        assert!(!sink.handles(&message("dev", note.content.clone())));
+        assert!(!sink.handles(&message(
+            "ops",
+            MessageContent::Status {
+                text: "working".to_string()
+            }
+        )));
+        let mail = sink.mail(&prompt);
+        assert!(mail.contains(&format!("\r\nMessage-ID: <{}@ailoop>\r\n", prompt.id)));
+        assert!(mail.contains("\r\nContent-Transfer-Encoding: base64\r\n\r\n"));
+    }
+
+    #[test]
+    fn encoded_reply_bodies_are_decoded() {
+        let subject = format!(
+            "From: alice@example.com\r\nSubject: Re: [ailoop {}]\r\n",
+            PROMPT
+        );
+        let answer = |headers: &str, body: &str| {
+            parse_reply(&format!("{}{}", subject, headers), body).map(|reply| reply.answer)
+        };
+        assert_eq!(
+            answer(
+                "Content-Transfer-Encoding: base64\r\n",
+                &format!(
+                    "{}\r\n",
+                    STANDARD.encode("no: wrong cluster\r\n\r\n> Deploy?\r\n")
+                )
+            ),
+            Some("no: wrong cluster".to_string())
+        );
+        assert_eq!(
+            answer(
+                "Content-Transfer-Encoding: quoted-printable\r\n",
+                "no: n=C3=A9 =3D too ri=\r\nsky\r\n"
+            ),
+            Some("no: né = too risky".to_string())
+        );
+        assert_eq!(
+            answer(
+                "Content-Type: multipart/alternative; boundary=\"b1\"\r\n",
+                "--b1\r\nContent-Type: text/html\r\nContent-Transfer-Encoding: base64\r\n\r\nPHA+bm88L3A+\r\n--b1\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\nyes =3D go\r\n--b1--\r\n"
+            ),
+            Some("yes = go".to_string())
+        );
+    }
+
+    #[test]
+    fn non_ascii_subjects_keep_the_token_plain() {
+        let prompt = message(
+            "ops",
+            MessageContent::Authorization {
+                action: "déployer la version 2 sur les serveurs de production ".repeat(4),
+                context: None,
+                timeout_seconds: 60,
+            },
+        );
+        let mail = sink().mail(&prompt);
+        let headers = mail.split("\r\n\r\n").next().unwrap();
+        let subject = unfold_headers(headers)
+            .into_iter()
+            .find_map(|line| line.strip_prefix("Subject: ").map(str::to_string))
+            .unwrap();
+        assert!(subject.starts_with(&format!(
+            "[ailoop {}] Authorization [ops]: =?UTF-8?B?",
+            prompt.id
+        )));
+        assert!(subject
+            .split(' ')
+            .filter(|word| word.starts_with("=?"))
+            .all(|word| word.len() <= 75));
+
+        let decoded = decode_header(&subject);
+        assert!(decoded.starts_with(&format!(
+            "[ailoop {}] Authorization [ops]: déployer la version 2",
+            prompt.id
+        )));
+        assert!(decoded.ends_with("..."));
+
+        let reply = format!("From: alice@example.com\r\nSubject: Re: {}\r\n", subject);
+        assert_eq!(parse_reply(&reply, "yes\r\n").unwrap().prompt_id, prompt.id);
+        let reencoded = format!(
+            "From: alice@example.com\r\nSubject: =?UTF-8?B?{}?=\r\n",
+            STANDARD.encode(format!("Re: {}", decoded))
+        );
+        assert_eq!(
+            parse_reply(&reencoded, "yes\r\n").unwrap().prompt_id,
+            prompt.id
+        );
+        assert_eq!(
+            decode_header("=?utf-8?Q?D=C3=A9ploy=C3=A9_v2?= now"),
+            "Déployé v2 now"
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_mail_to_an_smtp_server() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let fake = tokio::spawn(async move {
+            let mut server = BufReader::new(server);
+            server
+                .get_mut()
+                .write_all(b"220 smtp.example.com ready\r\n")
+                .await
+                .unwrap();
+            let (mut seen, mut data) = (Vec::new(), String::new());
+            loop {
+                let mut line = String::new();
+                if server.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let command = line.trim_end().to_string();
+                let reply = match command.split(' ').next().unwrap() {
+                    "EHLO" => "250-smtp.example.com\r\n250 AUTH PLAIN\r\n",
+                    "AUTH" => "235 ok\r\n",
+                    "DATA" => "354 go ahead\r\n",
+                    "QUIT" => "221 bye\r\n",
+                    _ => "250 ok\r\n",
+                };
+                seen.push(command.clone());
+                server.get_mut().write_all(reply.as_bytes()).await.unwrap();
+                if command == "DATA" {
+                    loop {
+                        let mut line = String::new();
+                        server.read_line(&mut line).await.unwrap();
+                        if line == ".\r\n" {
+                            break;
+                        }
+                        data.push_str(&line);
+                    }
+                    server.get_mut().write_all(b"250 queued\r\n").await.unwrap();
+                }
+                if command == "QUIT" {
+                    break;
+                }
+            }
+            (seen, data)
+        });
+
+        let sink = sink();
+        let prompt = message(
+            "ops",
+            MessageContent::Navigate {
+                url: "https://example.com/pr/1".to_string(),
+                timeout_seconds: 0,
+            },
+        );
+        let envelope = Envelope {
+            login: Some(("ailoop@example.com", "secret")),
+            from: sender_address(&sink.from),
+            to: sink.to.clone(),
+        };
+        let mut session = SmtpSession::new(client);
+        session.reply(220).await.unwrap();
+        submit(&mut session, &envelope, &sink.mail(&prompt))
+            .await
+            .unwrap();
+
+        let (seen, data) = fake.await.unwrap();
+        assert_eq!(
+            seen,
+            vec![
+                "EHLO example.com".to_string(),
+                format!(
+                    "AUTH PLAIN {}",
+                    STANDARD.encode("\0ailoop@example.com\0secret")
+                ),
+                "MAIL FROM:<ailoop@example.com>".to_string(),
+                "RCPT TO:<oncall@example.com>".to_string(),
+                "DATA".to_string(),
+                "QUIT".to_string(),
+            ]
+        );
+        assert!(data.starts_with("From: ailoop <ailoop@example.com>\r\n"));
+        assert!(data.contains(&format!("Subject: [ailoop {}] Navigation [ops]", prompt.id)));
+    }
+
+    #[tokio::test]
+    async fn authorizations_need_listed_responders() {
+        let registry = PendingPromptRegistry::new();
+        let (authorization, question) = (Uuid::new_v4(), Uuid::new_v4());
+        let _auth = registry
+            .register(
+                authorization,
+                None,
+                PromptType::Authorization,
+                "ops".to_string(),
+                "deploy".to_string(),
+            )
+            .await;
+        let _question = registry
+            .register(
+                question,
+                None,
+                PromptType::Decision,
+                "ops".to_string(),
+                "region".to_string(),
+            )
+            .await;
+        let reply = |prompt_id| EmailReply {
+            prompt_id,
+            from: "alice@example.com".to_string(),
+            answer: "yes".to_string(),
+        };
+        let mut source = EmailReplySource {
+            host: "imap.example.com".to_string(),
+            port: DEFAULT_IMAP_PORT,
+            username: "ailoop".to_string(),
+            password: "secret".to_string(),
+            mailbox: "INBOX".to_string(),
+            interval: Duration::from_secs(60),
+            responders: Vec::new(),
+        };
+        assert!(!source.accepts(&reply(authorization), &registry).await);
+        assert!(source.accepts(&reply(question), &registry).await);
+
+        source.responders = vec!["alice@example.com".to_string()];
+        assert!(source.accepts(&reply(authorization), &registry).await);
+        source.responders = vec!["bob@example.com".to_string()];
+        assert!(!source.accepts(&reply(question), &registry).await);
+
+        let answered = provider_reply(reply(question));
+        assert_eq!(answered.prompt_id, Some(question));
+        assert_eq!(answered.sender_id.as_deref(), Some("alice@example.com"));
+    }
+
+    #[tokio::test]
+    async fn polls_replies_from_an_imap_server() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let headers = format!(
+            "From: Alice <alice@example.com>\r\nSubject: Re: [ailoop {}]\r\n Deploy v2?\r\n\r\n",
+            PROMPT
+        );
+        let body = "approve\r\n\r\n> Deploy v2?\r\n";
+        let fake = tokio::spawn(async move {
+            let mut server = BufReader::new(server);
+            let mut seen = Vec::new();
+            loop {
+                let mut line = String::new();
+                if server.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let (tag, command) = line.trim_end().split_once(' ').unwrap();
+                seen.push(command.to_string());
+                let reply = if command.starts_with("UID SEARCH") {
+                    "* SEARCH 7\r\n".to_string()
+                } else if command.starts_with("UID FETCH") {
+                    format!(
+                        "* 1 FETCH (UID 7 BODY[HEADER.FIELDS (FROM SUBJECT)] {{{}}}\r\n{} BODY[TEXT] {{{}}}\r\n{})\r\n",
+                        headers.len(),
+                        headers,
+                        body.len(),
+                        body
+                    )
+                } else {
+                    String::new()
+                };
+                let done = format!("{}{} OK done\r\n", reply, tag);
+                server.get_mut().write_all(done.as_bytes()).await.unwrap();
+                if command == "LOGOUT" {
+                    break;
+                }
+            }
+            seen
+        });
+
+        let mut session = ImapSession::new(client);
+        let replies = poll_mailbox(&mut session, "INBOX").await.unwrap();
+        session.command("LOGOUT").await.unwrap();
+        assert_eq!(
+            replies,
+            vec![EmailReply {
+                prompt_id: Uuid::parse_str(PROMPT).unwrap(),
+                from: "alice@example.com".to_string(),
+                answer: "approve".to_string(),
+            }]
+        );
+        let seen = fake.await.unwrap();
+        assert_eq!(seen[0], "SELECT \"INBOX\"");
+        assert_eq!(seen[3], "UID STORE 7 +FLAGS (\\Seen)");
+    }
+}
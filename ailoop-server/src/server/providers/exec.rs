@@ -0,0 +1,229 @@
+//! Exec provider: deliver messages by running a configured command
+//!
+//! Each `[[providers.exec]]` entry runs its command once per message, with the message as one
+//! line of JSON on stdin and `AILOOP_EVENT` set to `prompt` or `notification`. Exit status 0
+//! means delivered; anything else, or running past the timeout, is a failed delivery with the
+//! command's stderr as the error, retried and reported like any other provider.
+
+use crate::server::providers::{is_prompt, Provider, ProviderResult};
+use ailoop_core::models::{ExecProviderConfig, Message, DEFAULT_EXEC_TIMEOUT_MS};
+use async_trait::async_trait;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// Longest stderr excerpt kept in a delivery error.
+const MAX_ERROR_CHARS: usize = 500;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExecError {
+    #[error("exec provider needs a name and a command")]
+    Incomplete,
+    #[error("failed to run {command}: {source}")]
+    Spawn {
+        command: String,
+        source: std::io::Error,
+    },
+    #[error("{command} timed out after {timeout:?}")]
+    TimedOut { command: String, timeout: Duration },
+    #[error("{command} exited with {status}: {stderr}")]
+    Failed {
+        command: String,
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+}
+
+/// Runs a command for each message it takes.
+pub struct ExecProvider {
+    name: String,
+    command: String,
+    args: Vec<String>,
+    channels: Vec<String>,
+    prompts_only: bool,
+    timeout: Duration,
+}
+
+impl ExecProvider {
+    pub fn from_config(config: &ExecProviderConfig) -> Result<Self, ExecError> {
+        let name = config.name.trim();
+        let command = config.command.trim();
+        if name.is_empty() || command.is_empty() {
+            return Err(ExecError::Incomplete);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            args: config.args.clone(),
+            channels: config.channels.clone(),
+            prompts_only: config.prompts_only,
+            timeout: Duration::from_millis(config.timeout_ms.unwrap_or(DEFAULT_EXEC_TIMEOUT_MS)),
+        })
+    }
+
+    async fn run(&self, message: &Message, event: &str) -> Result<(), ExecError> {
+        let spawn_error = |source| ExecError::Spawn {
+            command: self.command.clone(),
+            source,
+        };
+        let mut child = tokio::process::Command::new(&self.command)
+            .args(&self.args)
+            .env("AILOOP_EVENT", event)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(spawn_error)?;
+
+        let mut line = serde_json::to_vec(message).unwrap_or_default();
+        line.push(b'\n');
+        let stdin = child.stdin.take();
+        let run = async move {
+            if let Some(mut stdin) = stdin {
+                // A command that ignores its input may exit before reading it.
+                match stdin.write_all(&line).await {
+                    Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e),
+                    _ => {}
+                }
+            }
+            child.wait_with_output().await
+        };
+        let output = match tokio::time::timeout(self.timeout, run).await {
+            Ok(output) => output.map_err(spawn_error)?,
+            Err(_) => {
+                return Err(ExecError::TimedOut {
+                    command: self.command.clone(),
+                    timeout: self.timeout,
+                })
+            }
+        };
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr: String = String::from_utf8_lossy(&output.stderr)
+            .trim()
+            .chars()
+            .take(MAX_ERROR_CHARS)
+            .collect();
+        Err(ExecError::Failed {
+            command: self.command.clone(),
+            status: output.status,
+            stderr,
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for ExecProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn handles(&self, message: &Message) -> bool {
+        (self.channels.is_empty() || self.channels.contains(&message.channel))
+            && (!self.prompts_only || is_prompt(&message.content))
+    }
+
+    async fn send_prompt(&self, message: &Message) -> ProviderResult<Option<String>> {
+        self.run(message, "prompt").await?;
+        Ok(None)
+    }
+
+    async fn send_notification(&self, message: &Message) -> ProviderResult<()> {
+        self.run(message, "notification").await?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{MessageContent, SenderType};
+
+    fn provider(command: &str, args: &[&str]) -> ExecProvider {
+        ExecProvider::from_config(&ExecProviderConfig {
+            name: "chat".to_string(),
+            command: command.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            timeout_ms: Some(500),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn prompt(channel: &str) -> Message {
+        Message::new(
+            channel.to_string(),
+            SenderType::Agent,
+            MessageContent::Authorization {
+                action: "deploy".to_string(),
+                context: None,
+                timeout_seconds: 60,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn pipes_message_json_to_the_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.json");
+        let sink = provider(
+            "sh",
+            &[
+                "-c",
+                &format!(
+                    "cat > {}; echo $AILOOP_EVENT >> {}",
+                    out.display(),
+                    out.display()
+                ),
+            ],
+        );
+        let message = prompt("ops");
+        sink.send_prompt(&message).await.unwrap();
+
+        let written = std::fs::read_to_string(&out).unwrap();
+        let (json, event) = written.split_once('\n').unwrap();
+        let sent: Message = serde_json::from_str(json).unwrap();
+        assert_eq!(sent.id, message.id);
+        assert_eq!(event.trim(), "prompt");
+    }
+
+    #[tokio::test]
+    async fn failures_and_timeouts_are_errors() {
+        let failing = provider("sh", &["-c", "echo 'chat is down' >&2; exit 3"]);
+        let err = failing.send_prompt(&prompt("ops")).await.unwrap_err();
+        assert!(err.to_string().contains("chat is down"), "{}", err);
+
+        let slow = provider("sleep", &["5"]);
+        let err = slow.send_prompt(&prompt("ops")).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+
+        let missing = provider("/nonexistent/ailoop-sink", &[]);
+        assert!(missing.send_prompt(&prompt("ops")).await.is_err());
+    }
+
+    #[test]
+    fn channel_and_prompt_filters() {
+        let sink = ExecProvider::from_config(&ExecProviderConfig {
+            name: "chat".to_string(),
+            command: "true".to_string(),
+            channels: vec!["ops".to_string()],
+            prompts_only: true,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(sink.handles(&prompt("ops")));
+        assert!(!sink.handles(&prompt("dev")));
+        let note = Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "done".to_string(),
+                priority: Default::default(),
+            },
+        );
+        assert!(!sink.handles(&note));
+        assert!(ExecProvider::from_config(&ExecProviderConfig::default()).is_err());
+    }
+}
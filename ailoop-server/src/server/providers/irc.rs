@@ -0,0 +1,585 @@
+//! IRC provider: prompts posted to one channel, answered there with `!` commands.
+//!
+//! IRC has no reply-to, so every prompt carries a short id (`[K3F9] Authorization ...`) that
+//! the answer names: `!yes K3F9`, `!no K3F9 too risky` or `!answer K3F9 2`. One task keeps the
+//! connection: it registers, joins the channel, answers PINGs and reconnects with backoff when
+//! the server goes away. Messages sent meanwhile wait until the channel is joined again.
+
+use crate::server::providers::tls::connect_tls;
+use crate::server::providers::{
+    infer_response_type, is_prompt, Provider, ProviderReply, ProviderResult,
+};
+use ailoop_core::models::{IrcProviderConfig, Message, MessageContent};
+use async_trait::async_trait;
+use futures_util::stream::{BoxStream, StreamExt};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Prompt ids remembered for reply matching (oldest evicted first).
+const MAX_PROMPT_CODES: usize = 256;
+const CODE_LEN: usize = 4;
+/// Longest text of one PRIVMSG, in bytes; servers cut lines at 512 including the prefix.
+const MAX_TEXT_BYTES: usize = 400;
+/// Messages waiting for the channel to be joined.
+const OUTBOUND_CAPACITY: usize = 256;
+/// Pause after each message, to stay under the server's flood limits.
+const SEND_INTERVAL: Duration = Duration::from_millis(500);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+const ELLIPSIS: char = '…';
+
+/// Outstanding prompt ids: code, prompt message id and ailoop channel, in send order.
+#[derive(Debug, Default)]
+struct PromptCodes(Mutex<VecDeque<(String, Uuid, String)>>);
+
+impl PromptCodes {
+    /// Assign a code to prompt `message`: the first hex digits of its id, lengthened until
+    /// unique among outstanding codes.
+    fn assign(&self, message: &Message) -> String {
+        let hex = message.id.simple().to_string().to_uppercase();
+        let mut codes = self.0.lock().expect("irc codes lock");
+        if let Some((code, _, _)) = codes.iter().find(|(_, id, _)| *id == message.id) {
+            return code.clone();
+        }
+        let code = (CODE_LEN..=hex.len())
+            .map(|n| hex[..n].to_string())
+            .find(|c| !codes.iter().any(|(existing, _, _)| existing == c))
+            .unwrap_or(hex);
+        if codes.len() >= MAX_PROMPT_CODES {
+            codes.pop_front();
+        }
+        codes.push_back((code.clone(), message.id, message.channel.clone()));
+        code
+    }
+
+    /// ailoop channel of the prompt `code` was assigned to.
+    fn channel(&self, code: &str) -> Option<String> {
+        let codes = self.0.lock().expect("irc codes lock");
+        codes
+            .iter()
+            .find(|(c, _, _)| c == code)
+            .map(|(_, _, channel)| channel.clone())
+    }
+}
+
+/// IRC provider: one connection to `server`, posting to and reading from `channel`.
+pub struct IrcProvider {
+    config: IrcProviderConfig,
+    password: Option<String>,
+    codes: Arc<PromptCodes>,
+    outbound: mpsc::Sender<String>,
+    /// Taken by the connection task when `init` starts it.
+    connection: Mutex<Option<(mpsc::Receiver<String>, mpsc::Sender<ProviderReply>)>>,
+    replies: Arc<tokio::sync::Mutex<mpsc::Receiver<ProviderReply>>>,
+}
+
+impl IrcProvider {
+    /// Build from `[providers.irc]`; `password` (server PASS) comes from `AILOOP_IRC_PASSWORD`.
+    pub fn new(
+        config: &IrcProviderConfig,
+        password: Option<String>,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        for (field, value) in [
+            ("server", &config.server),
+            ("nick", &config.nick),
+            ("channel", &config.channel),
+        ] {
+            if value.as_deref().is_none_or(|v| v.trim().is_empty()) {
+                return Err(format!("providers.irc: {} is required", field).into());
+            }
+        }
+        let (outbound, outbound_rx) = mpsc::channel(OUTBOUND_CAPACITY);
+        let (replies_tx, replies) = mpsc::channel(OUTBOUND_CAPACITY);
+        Ok(Self {
+            config: config.clone(),
+            password,
+            codes: Arc::new(PromptCodes::default()),
+            outbound,
+            connection: Mutex::new(Some((outbound_rx, replies_tx))),
+            replies: Arc::new(tokio::sync::Mutex::new(replies)),
+        })
+    }
+
+    /// Build from `[providers.irc]`. Returns `Ok(None)` when the provider is disabled.
+    pub fn from_config(
+        config: &IrcProviderConfig,
+    ) -> Result<Option<Self>, Box<dyn Error + Send + Sync>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let password = std::env::var("AILOOP_IRC_PASSWORD")
+            .ok()
+            .filter(|p| !p.is_empty());
+        Self::new(config, password).map(Some)
+    }
+
+    /// Queue `message` for the channel, with a prompt id when it is a prompt.
+    async fn post(&self, message: &Message) -> ProviderResult<Option<String>> {
+        let code = is_prompt(&message.content).then(|| self.codes.assign(message));
+        self.outbound
+            .send(format_message(message, code.as_deref()))
+            .await
+            .map_err(|_| "IRC connection task has stopped")?;
+        Ok(code)
+    }
+}
+
+/// Render `message` as one line of at most [`MAX_TEXT_BYTES`]. The body is cut at a character
+/// boundary and marked with `…`; the id prefix and answer hint are never cut.
+fn format_message(message: &Message, code: Option<&str>) -> String {
+    let channel = &message.channel;
+    let (body, hint) = match &message.content {
+        MessageContent::Decision {
+            summary, options, ..
+        } => {
+            let opts: Vec<String> = options
+                .iter()
+                .enumerate()
+                .map(|(i, o)| format!("{}) {}", i + 1, o.label))
+                .collect();
+            (
+                format!("Decision [{}]: {} {}", channel, summary, opts.join(" ")),
+                code.map(|c| format!("!answer {} <n>", c)),
+            )
+        }
+        MessageContent::Authorization { action, .. } => (
+            format!("Authorization [{}]: {}", channel, action),
+            code.map(|c| format!("!yes {} / !no {} [reason]", c, c)),
+        ),
+        MessageContent::Navigate { url, .. } => (
+            format!("Navigation [{}]: {}", channel, url),
+            code.map(|c| format!("!yes {} / !no {}", c, c)),
+        ),
+        MessageContent::Notification { text, .. } => {
+            (format!("Notification [{}]: {}", channel, text), None)
+        }
+        _ => (format!("Message [{}]", channel), None),
+    };
+    let body = match message.agent_heading() {
+        Some(heading) => format!("{} {}", heading, body),
+        None => body,
+    };
+    let body = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    let prefix = code.map(|c| format!("[{}] ", c)).unwrap_or_default();
+    let suffix = hint.map(|h| format!(" — {}", h)).unwrap_or_default();
+    let budget = MAX_TEXT_BYTES.saturating_sub(prefix.len() + suffix.len());
+    format!("{}{}{}", prefix, truncate_bytes(&body, budget), suffix)
+}
+
+/// Longest prefix of `text` within `max` bytes, ending in `…` when cut.
+fn truncate_bytes(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut end = max.saturating_sub(ELLIPSIS.len_utf8());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", &text[..end], ELLIPSIS)
+}
+
+/// A line from the server, as far as the provider cares.
+#[derive(Debug, PartialEq)]
+enum Line<'a> {
+    Ping(&'a str),
+    /// `001`: registration is complete.
+    Welcome,
+    /// `433`: the nick is taken.
+    NickInUse,
+    Join {
+        nick: &'a str,
+        channel: &'a str,
+    },
+    Kick {
+        channel: &'a str,
+        nick: &'a str,
+    },
+    Privmsg {
+        nick: &'a str,
+        target: &'a str,
+        text: &'a str,
+    },
+    Error(&'a str),
+    Other,
+}
+
+/// Parse `[:<prefix> ]<command> <params>[ :<trailing>]`.
+fn parse_line(line: &str) -> Line<'_> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let (prefix, rest) = match line.strip_prefix(':') {
+        Some(rest) => rest.split_once(' ').unwrap_or((rest, "")),
+        None => ("", line),
+    };
+    let (params, trailing) = match rest.split_once(" :") {
+        Some((params, trailing)) => (params, Some(trailing)),
+        None => (rest, None),
+    };
+    let mut words = params.split(' ').filter(|w| !w.is_empty());
+    let command = words.next().unwrap_or_default();
+    let args: Vec<&str> = words.chain(trailing).collect();
+    let arg = |i: usize| args.get(i).copied().unwrap_or_default();
+    let nick = prefix.split('!').next().unwrap_or_default();
+    match command {
+        "PING" => Line::Ping(arg(0)),
+        "001" => Line::Welcome,
+        "433" => Line::NickInUse,
+        "JOIN" => Line::Join {
+            nick,
+            channel: arg(0),
+        },
+        "KICK" => Line::Kick {
+            channel: arg(0),
+            nick: arg(1),
+        },
+        "PRIVMSG" => Line::Privmsg {
+            nick,
+            target: arg(0),
+            text: arg(1),
+        },
+        "ERROR" => Line::Error(arg(0)),
+        _ => Line::Other,
+    }
+}
+
+/// The prompt id and answer of `!yes <id>`, `!no <id> [reason]` or `!answer <id> <text>`.
+fn parse_command(text: &str) -> Option<(String, String)> {
+    let mut words = text.trim().splitn(3, char::is_whitespace);
+    let command = words.next()?.to_lowercase();
+    let code = words.next().filter(|c| !c.is_empty())?.to_uppercase();
+    let rest = words.next().map(str::trim).unwrap_or_default();
+    let answer = match (command.as_str(), rest) {
+        ("!yes", _) => "yes".to_string(),
+        ("!no", "") => "no".to_string(),
+        ("!no", reason) => format!("no: {}", reason),
+        ("!answer", "") => return None,
+        ("!answer", answer) => answer.to_string(),
+        _ => return None,
+    };
+    Some((code, answer))
+}
+
+/// Keep a connection open until the provider is dropped, reconnecting with backoff.
+async fn run_connection(
+    config: IrcProviderConfig,
+    password: Option<String>,
+    codes: Arc<PromptCodes>,
+    mut outbound: mpsc::Receiver<String>,
+    replies: mpsc::Sender<ProviderReply>,
+) {
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        let started = Instant::now();
+        let result = connect(
+            &config,
+            password.as_deref(),
+            &codes,
+            &mut outbound,
+            &replies,
+        )
+        .await;
+        let error = match result {
+            Ok(()) => return,
+            Err(e) => e,
+        };
+        if started.elapsed() > MAX_BACKOFF {
+            backoff = INITIAL_BACKOFF;
+        }
+        tracing::warn!(
+            error = %error,
+            "IRC connection lost; reconnecting in {}s",
+            backoff.as_secs()
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// One connection: `Ok` when the provider is gone, `Err` when the connection is lost.
+async fn connect(
+    config: &IrcProviderConfig,
+    password: Option<&str>,
+    codes: &PromptCodes,
+    outbound: &mut mpsc::Receiver<String>,
+    replies: &mpsc::Sender<ProviderReply>,
+) -> io::Result<()> {
+    let host = config.server.as_deref().unwrap_or_default().trim();
+    let tcp = TcpStream::connect((host, config.port())).await?;
+    if config.plaintext {
+        converse(tcp, config, password, codes, outbound, replies).await
+    } else {
+        let tls = connect_tls(host, tcp).await?;
+        converse(tls, config, password, codes, outbound, replies).await
+    }
+}
+
+/// Register, join the channel, then relay: queued messages out, answers in.
+async fn converse<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    config: &IrcProviderConfig,
+    password: Option<&str>,
+    codes: &PromptCodes,
+    outbound: &mut mpsc::Receiver<String>,
+    replies: &mpsc::Sender<ProviderReply>,
+) -> io::Result<()> {
+    let (reader, writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    let mut session = Session {
+        writer,
+        config,
+        codes,
+        channel: config.channel.as_deref().unwrap_or_default().trim(),
+        nick: config
+            .nick
+            .as_deref()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        joined: false,
+    };
+    if let Some(password) = password {
+        session.send(&format!("PASS {}", password)).await?;
+    }
+    let nick = session.nick.clone();
+    session.send(&format!("NICK {}", nick)).await?;
+    session.send(&format!("USER {} 0 * :ailoop", nick)).await?;
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                };
+                if let Some(reply) = session.handle(&line).await? {
+                    if replies.send(reply).await.is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            text = outbound.recv(), if session.joined => {
+                let Some(text) = text else {
+                    let _ = session.send("QUIT :ailoop stopped").await;
+                    return Ok(());
+                };
+                let channel = session.channel;
+                session.send(&format!("PRIVMSG {} :{}", channel, text)).await?;
+                tokio::time::sleep(SEND_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// State of one registered connection.
+struct Session<'a, W> {
+    writer: W,
+    config: &'a IrcProviderConfig,
+    codes: &'a PromptCodes,
+    channel: &'a str,
+    nick: String,
+    joined: bool,
+}
+
+impl<W: AsyncWrite + Unpin> Session<'_, W> {
+    async fn send(&mut self, line: &str) -> io::Result<()> {
+        self.writer.write_all(line.as_bytes()).await?;
+        self.writer.write_all(b"\r\n").await?;
+        self.writer.flush().await
+    }
+
+    /// React to a line from the server; returns the answer it carries, if any.
+    async fn handle(&mut self, line: &str) -> io::Result<Option<ProviderReply>> {
+        let channel = self.channel;
+        match parse_line(line) {
+            Line::Ping(token) => self.send(&format!("PONG :{}", token)).await?,
+            Line::Welcome => self.send(&format!("JOIN {}", channel)).await?,
+            Line::NickInUse => {
+                self.nick.push('_');
+                let nick = self.nick.clone();
+                self.send(&format!("NICK {}", nick)).await?;
+            }
+            Line::Join { nick, channel: to }
+                if nick.eq_ignore_ascii_case(&self.nick) && to.eq_ignore_ascii_case(channel) =>
+            {
+                tracing::info!(channel, nick = %self.nick, "Joined IRC channel");
+                self.joined = true;
+            }
+            Line::Kick {
+                channel: from,
+                nick,
+            } if nick.eq_ignore_ascii_case(&self.nick) && from.eq_ignore_ascii_case(channel) => {
+                tracing::warn!(channel, "Kicked from IRC channel; rejoining");
+                self.joined = false;
+                self.send(&format!("JOIN {}", channel)).await?;
+            }
+            Line::Privmsg { nick, target, text } if target.eq_ignore_ascii_case(channel) => {
+                return self.answer(nick, text).await;
+            }
+            Line::Error(reason) => return Err(io::Error::other(reason.to_string())),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    /// The reply `text` from `nick` gives, when it is a command naming an open prompt.
+    async fn answer(&mut self, nick: &str, text: &str) -> io::Result<Option<ProviderReply>> {
+        let Some((code, answer)) = parse_command(text) else {
+            return Ok(None);
+        };
+        if !self.config.is_responder(nick) {
+            tracing::warn!("Ignoring IRC answer from non-responder {}", nick);
+            return Ok(None);
+        }
+        let Some(prompt_channel) = self.codes.channel(&code) else {
+            self.send(&format!("NOTICE {} :No open prompt {}", nick, code))
+                .await?;
+            return Ok(None);
+        };
+        Ok(Some(ProviderReply {
+            reply_to_message_id: Some(code),
+            prompt_id: None,
+            response_type: infer_response_type(&answer),
+            answer: Some(answer),
+            sender_id: None,
+            sender_name: Some(nick.to_string()),
+            chat_id: Some(self.channel.to_string()),
+            channel: Some(prompt_channel),
+        }))
+    }
+}
+
+/// Answers are read from the channel; IRC lines cannot be edited or deleted.
+#[async_trait]
+impl Provider for IrcProvider {
+    fn name(&self) -> &str {
+        "irc"
+    }
+
+    /// Prompts and notifications of the configured `channels` (all when empty).
+    fn handles(&self, message: &Message) -> bool {
+        (self.config.channels.is_empty() || self.config.channels.contains(&message.channel))
+            && (is_prompt(&message.content)
+                || matches!(message.content, MessageContent::Notification { .. }))
+    }
+
+    /// Start the connection task (once; `init` runs again when the reply task restarts).
+    async fn init(&self) -> ProviderResult<()> {
+        let taken = self.connection.lock().expect("irc connection lock").take();
+        if let Some((outbound, replies)) = taken {
+            tokio::spawn(run_connection(
+                self.config.clone(),
+                self.password.clone(),
+                Arc::clone(&self.codes),
+                outbound,
+                replies,
+            ));
+        }
+        Ok(())
+    }
+
+    async fn send_prompt(&self, message: &Message) -> ProviderResult<Option<String>> {
+        self.post(message).await
+    }
+
+    async fn send_notification(&self, message: &Message) -> ProviderResult<()> {
+        self.post(message).await.map(|_| ())
+    }
+
+    fn reply_stream(&self) -> Option<BoxStream<'static, ProviderReply>> {
+        let replies =
+            futures_util::stream::unfold(Arc::clone(&self.replies), |replies| async move {
+                let reply = replies.lock().await.recv().await?;
+                Some((reply, replies))
+            });
+        Some(replies.boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::ResponseType;
+
+    #[test]
+    fn test_lines_and_commands_are_parsed() {
+        assert_eq!(
+            parse_line("PING :tungsten.libera.chat"),
+            Line::Ping("tungsten.libera.chat")
+        );
+        assert_eq!(
+            parse_line(":irc.example.net 001 ailoop :Welcome to the network\r\n"),
+            Line::Welcome
+        );
+        assert_eq!(
+            parse_line(":alice!~a@host PRIVMSG #ops :!no k3f9 too risky"),
+            Line::Privmsg {
+                nick: "alice",
+                target: "#ops",
+                text: "!no k3f9 too risky"
+            }
+        );
+        assert_eq!(
+            parse_line(":op!o@host KICK #ops ailoop :bye"),
+            Line::Kick {
+                channel: "#ops",
+                nick: "ailoop"
+            }
+        );
+
+        assert_eq!(
+            parse_command("!yes k3f9"),
+            Some(("K3F9".to_string(), "yes".to_string()))
+        );
+        let (_, answer) = parse_command("!no K3F9 too risky").unwrap();
+        assert_eq!(answer, "no: too risky");
+        assert_eq!(
+            infer_response_type(&answer),
+            ResponseType::AuthorizationDenied
+        );
+        assert_eq!(
+            parse_command("!answer K3F9 use postgres"),
+            Some(("K3F9".to_string(), "use postgres".to_string()))
+        );
+        assert_eq!(parse_command("!answer K3F9"), None);
+        assert_eq!(parse_command("!yes"), None);
+        assert_eq!(parse_command("yes K3F9"), None);
+    }
+
+    #[test]
+    fn test_prompts_carry_a_short_id() {
+        let provider = IrcProvider::new(
+            &IrcProviderConfig {
+                enabled: true,
+                server: Some("irc.example.net".to_string()),
+                nick: Some("ailoop".to_string()),
+                channel: Some("#ops".to_string()),
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap();
+        let mut prompt = ailoop_core::client::authorization("prod", "deploy\nv2", 60);
+        prompt.agent = None;
+        let code = provider.codes.assign(&prompt);
+        assert_eq!(code.len(), CODE_LEN);
+        assert_eq!(provider.codes.assign(&prompt), code);
+        assert_eq!(provider.codes.channel(&code).as_deref(), Some("prod"));
+        assert_eq!(
+            format_message(&prompt, Some(&code)),
+            format!(
+                "[{0}] Authorization [prod]: deploy v2 — !yes {0} / !no {0} [reason]",
+                code
+            )
+        );
+
+        let note = ailoop_core::client::notification("prod", &"é".repeat(500), "normal");
+        let line = format_message(&note, None);
+        assert!(line.len() <= MAX_TEXT_BYTES);
+        assert!(line.ends_with(ELLIPSIS));
+        assert!(provider.handles(&note));
+    }
+}
@@ -0,0 +1,587 @@
+//! Mattermost provider: prompts and notifications are posted through the REST API (v4), replies
+//! are read by polling the mapped channels.
+//!
+//! A post in a prompt's thread answers that prompt; a top-level post answers the oldest prompt
+//! of the ailoop channel mapped to its Mattermost channel. Answers go through the same registry
+//! as every other provider, so the first one wins wherever it was given.
+
+use crate::server::providers::{
+    infer_response_type, is_prompt, Provider, ProviderReply, ProviderResult, ReplySource,
+};
+use ailoop_core::models::{MattermostProviderConfig, Message, MessageContent};
+use ailoop_core::transport::proxy::ProxySettings;
+use async_trait::async_trait;
+use futures_util::stream::{BoxStream, StreamExt};
+use reqwest::{Client, Method, RequestBuilder};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::Write as _;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+const HTTP_TIMEOUT_SECS: u64 = 30;
+/// Usernames remembered for responder checks.
+const MAX_CACHED_USERS: usize = 1024;
+
+/// Authenticated access to `{url}/api/v4`.
+#[derive(Debug)]
+struct Api {
+    base: String,
+    token: String,
+    client: Client,
+}
+
+impl Api {
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, format!("{}/{}", self.base, path))
+            .bearer_auth(&self.token)
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> ProviderResult<T> {
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!("Mattermost API error: {} – {}", status, text).into());
+        }
+        Ok(response.json().await?)
+    }
+}
+
+/// A post as returned by the API; only the fields ailoop reads.
+#[derive(Debug, Clone, Deserialize)]
+struct Post {
+    id: String,
+    #[serde(default)]
+    create_at: i64,
+    #[serde(default)]
+    user_id: String,
+    #[serde(default)]
+    channel_id: String,
+    /// Thread the post belongs to; empty for top-level posts.
+    #[serde(default)]
+    root_id: String,
+    #[serde(default)]
+    message: String,
+    /// Non-empty for system posts (joins, header changes, ...).
+    #[serde(default, rename = "type")]
+    kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PostList {
+    #[serde(default)]
+    posts: HashMap<String, Post>,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: String,
+    #[serde(default)]
+    username: String,
+}
+
+/// Where each polled Mattermost channel stands.
+#[derive(Default)]
+struct PollState {
+    /// Newest `create_at` seen per channel, in milliseconds.
+    since: HashMap<String, i64>,
+    /// Replies read but not yet handed out.
+    pending: VecDeque<ProviderReply>,
+    usernames: HashMap<String, String>,
+}
+
+/// Polls the mapped Mattermost channels for posts from people.
+pub struct MattermostReplySource {
+    api: Arc<Api>,
+    /// Mattermost channel id, and the ailoop channel its top-level posts answer (None when it
+    /// serves several or unmapped ones).
+    channels: Vec<(String, Option<String>)>,
+    poll_interval: Duration,
+    /// The bot's own user id, learned in `init`; its posts are skipped.
+    bot_user_id: OnceLock<String>,
+    state: tokio::sync::Mutex<PollState>,
+}
+
+impl MattermostReplySource {
+    fn new(api: Arc<Api>, config: &MattermostProviderConfig) -> Self {
+        let now = chrono::Utc::now().timestamp_millis();
+        let channels = polled_channels(config);
+        let since = channels.iter().map(|(id, _)| (id.clone(), now)).collect();
+        Self {
+            api,
+            channels,
+            poll_interval: Duration::from_secs(config.poll_interval_seconds()),
+            bot_user_id: OnceLock::new(),
+            state: tokio::sync::Mutex::new(PollState {
+                since,
+                ..PollState::default()
+            }),
+        }
+    }
+
+    /// Read new posts from every polled channel into `pending`.
+    async fn poll(&self, state: &mut PollState) {
+        for (channel_id, channel) in &self.channels {
+            let since = state.since.get(channel_id).copied().unwrap_or_default();
+            let request = self
+                .api
+                .request(Method::GET, &format!("channels/{}/posts", channel_id))
+                .query(&[("since", since)]);
+            let list: PostList = match self.api.send(request).await {
+                Ok(list) => list,
+                Err(e) => {
+                    tracing::warn!(
+                        provider = "mattermost",
+                        channel_id = %channel_id,
+                        error = %e,
+                        "Polling failed"
+                    );
+                    continue;
+                }
+            };
+            let posts = new_posts(list, since, self.bot_user_id.get().map(String::as_str));
+            if let Some(newest) = posts.iter().map(|p| p.create_at).max() {
+                state.since.insert(channel_id.clone(), newest);
+            }
+            for post in posts {
+                let username = self.username(state, &post.user_id).await;
+                state
+                    .pending
+                    .push_back(reply_from_post(&post, username, channel.clone()));
+            }
+        }
+    }
+
+    /// Username of `user_id`, looked up once.
+    async fn username(&self, state: &mut PollState, user_id: &str) -> Option<String> {
+        if let Some(name) = state.usernames.get(user_id) {
+            return Some(name.clone());
+        }
+        let request = self.api.request(Method::GET, &format!("users/{}", user_id));
+        let user: User = match self.api.send(request).await {
+            Ok(user) => user,
+            Err(e) => {
+                tracing::warn!(
+                    provider = "mattermost",
+                    user_id = %user_id,
+                    error = %e,
+                    "User lookup failed"
+                );
+                return None;
+            }
+        };
+        if state.usernames.len() >= MAX_CACHED_USERS {
+            state.usernames.clear();
+        }
+        state
+            .usernames
+            .insert(user.id.clone(), user.username.clone());
+        Some(user.username)
+    }
+}
+
+#[async_trait]
+impl ReplySource for MattermostReplySource {
+    async fn next_reply(&self) -> Option<ProviderReply> {
+        let mut state = self.state.lock().await;
+        if let Some(reply) = state.pending.pop_front() {
+            return Some(reply);
+        }
+        tokio::time::sleep(self.poll_interval).await;
+        self.poll(&mut state).await;
+        state.pending.pop_front()
+    }
+}
+
+/// Mattermost channels to poll, each with the ailoop channel its top-level posts answer.
+fn polled_channels(config: &MattermostProviderConfig) -> Vec<(String, Option<String>)> {
+    let mut channels: Vec<(String, Option<String>)> = Vec::new();
+    let mut mapped: Vec<(&String, &String)> = config.channel_map.iter().collect();
+    mapped.sort();
+    for (channel, target) in mapped {
+        match channels.iter_mut().find(|(id, _)| id == target) {
+            Some((_, served)) => *served = None,
+            None => channels.push((target.clone(), Some(channel.clone()))),
+        }
+    }
+    if let Some(default) = &config.channel_id {
+        match channels.iter_mut().find(|(id, _)| id == default) {
+            Some((_, served)) => *served = None,
+            None => channels.push((default.clone(), None)),
+        }
+    }
+    channels
+}
+
+/// Posts in `list` created after `since` by people other than the bot, oldest first.
+fn new_posts(list: PostList, since: i64, bot_user_id: Option<&str>) -> Vec<Post> {
+    let mut posts: Vec<Post> = list
+        .posts
+        .into_values()
+        .filter(|p| p.create_at > since)
+        .filter(|p| p.kind.is_empty() && !p.message.trim().is_empty())
+        .filter(|p| bot_user_id != Some(p.user_id.as_str()))
+        .collect();
+    posts.sort_by_key(|p| p.create_at);
+    posts
+}
+
+/// The reply a post gives: in a thread it answers the thread's root post.
+fn reply_from_post(
+    post: &Post,
+    username: Option<String>,
+    channel: Option<String>,
+) -> ProviderReply {
+    let answer = post.message.trim();
+    ProviderReply {
+        reply_to_message_id: (!post.root_id.is_empty()).then(|| post.root_id.clone()),
+        prompt_id: None,
+        answer: Some(answer.to_string()),
+        response_type: infer_response_type(answer),
+        sender_id: Some(post.user_id.clone()),
+        sender_name: username,
+        chat_id: Some(post.channel_id.clone()),
+        channel,
+    }
+}
+
+/// Mattermost provider: posts to the mapped channels and reads replies from them.
+pub struct MattermostProvider {
+    api: Arc<Api>,
+    config: MattermostProviderConfig,
+    source: Arc<MattermostReplySource>,
+}
+
+impl MattermostProvider {
+    /// Build from `[providers.mattermost]`; `token` comes from `AILOOP_MATTERMOST_TOKEN`.
+    pub fn new(
+        config: &MattermostProviderConfig,
+        token: String,
+        proxy: &ProxySettings,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let Some(url) = config.url.as_deref().filter(|u| !u.is_empty()) else {
+            return Err("providers.mattermost: url is required".into());
+        };
+        let client = proxy
+            .apply(Client::builder())?
+            .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+            .build()?;
+        let api = Arc::new(Api {
+            base: format!("{}/api/v4", url.trim_end_matches('/')),
+            token,
+            client,
+        });
+        Ok(Self {
+            source: Arc::new(MattermostReplySource::new(Arc::clone(&api), config)),
+            api,
+            config: config.clone(),
+        })
+    }
+
+    /// Build from `[providers.mattermost]` with the token from `AILOOP_MATTERMOST_TOKEN`.
+    /// Returns `Ok(None)` when the provider is disabled or the token is not set.
+    pub fn from_config(
+        config: &MattermostProviderConfig,
+        proxy: &ProxySettings,
+    ) -> Result<Option<Self>, Box<dyn Error + Send + Sync>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        match std::env::var("AILOOP_MATTERMOST_TOKEN") {
+            Ok(token) => Self::new(config, token, proxy).map(Some),
+            Err(_) => {
+                tracing::warn!("Mattermost provider skipped: token not set");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Post `message` to its channel's target; returns the post id.
+    async fn post(&self, message: &Message) -> ProviderResult<String> {
+        let Some(channel_id) = self.config.target(&message.channel) else {
+            return Err(format!("no Mattermost channel for '{}'", message.channel).into());
+        };
+        let request = self
+            .api
+            .request(Method::POST, "posts")
+            .json(&serde_json::json!({
+                "channel_id": channel_id,
+                "message": format_post(message),
+            }));
+        let post: Post = self.api.send(request).await?;
+        Ok(post.id)
+    }
+}
+
+/// Markdown text of the post for `message`. Prompts end with how to answer.
+fn format_post(message: &Message) -> String {
+    let channel = &message.channel;
+    let mut text = String::new();
+    if let Some(heading) = message.agent_heading() {
+        let _ = writeln!(text, "{}\n", heading);
+    }
+    let hint = match &message.content {
+        MessageContent::Decision {
+            summary,
+            context_markdown,
+            options,
+            recommendation,
+            ..
+        } => {
+            let _ = writeln!(text, "**Decision** [{}]: {}\n", channel, summary);
+            if let Some(context) = context_markdown {
+                let _ = writeln!(text, "{}\n", context.trim());
+            }
+            for (i, option) in options.iter().enumerate() {
+                let recommended = recommendation
+                    .as_ref()
+                    .is_some_and(|r| r.option_id == option.id);
+                let _ = writeln!(
+                    text,
+                    "{}. {}{}",
+                    i + 1,
+                    option.label,
+                    if recommended { " _(recommended)_" } else { "" }
+                );
+                if let Some(detail) = &option.detail_markdown {
+                    let _ = writeln!(text, "   {}", detail.trim().replace('\n', "\n   "));
+                }
+            }
+            text.push('\n');
+            Some("Reply in this thread with the number of your choice, e.g. `1`.")
+        }
+        MessageContent::Authorization {
+            action, context, ..
+        } => {
+            let _ = writeln!(text, "**Authorization** [{}]: {}\n", channel, action);
+            if let Some(context) = context {
+                let context = serde_json::to_string_pretty(context).unwrap_or_default();
+                let _ = writeln!(text, "```json\n{}\n```\n", context);
+            }
+            Some("Reply in this thread with `yes` to approve or `no: <reason>` to deny.")
+        }
+        MessageContent::Navigate { url, .. } => {
+            let _ = writeln!(text, "**Navigation** [{}]: {}\n", channel, url);
+            Some("Reply in this thread with `yes` once it is open or `no` to decline.")
+        }
+        MessageContent::Notification { text: body, .. } => {
+            let _ = writeln!(text, "**Notification** [{}]: {}\n", channel, body.trim());
+            None
+        }
+        _ => {
+            let _ = writeln!(text, "**Message** [{}]\n", channel);
+            None
+        }
+    };
+    if let Some(summary) = message.thread_summary() {
+        let _ = writeln!(text, "{}\n", summary);
+    }
+    if let Some(hint) = hint {
+        let _ = writeln!(text, "_{}_", hint);
+    }
+    text.trim_end().to_string()
+}
+
+#[async_trait]
+impl Provider for MattermostProvider {
+    fn name(&self) -> &str {
+        "mattermost"
+    }
+
+    /// Prompts and notifications of channels with a Mattermost target.
+    fn handles(&self, message: &Message) -> bool {
+        self.config.target(&message.channel).is_some()
+            && (is_prompt(&message.content)
+                || matches!(message.content, MessageContent::Notification { .. }))
+    }
+
+    /// Learn the bot's user id so its own posts are not read as replies.
+    async fn init(&self) -> ProviderResult<()> {
+        let me: User = self
+            .api
+            .send(self.api.request(Method::GET, "users/me"))
+            .await?;
+        let _ = self.source.bot_user_id.set(me.id);
+        Ok(())
+    }
+
+    async fn send_prompt(&self, message: &Message) -> ProviderResult<Option<String>> {
+        self.post(message).await.map(Some)
+    }
+
+    async fn send_notification(&self, message: &Message) -> ProviderResult<()> {
+        self.post(message).await.map(|_| ())
+    }
+
+    /// Posts from users allowed by `responders`; others are dropped.
+    fn reply_stream(&self) -> Option<BoxStream<'static, ProviderReply>> {
+        let config = self.config.clone();
+        let replies = futures_util::stream::unfold(Arc::clone(&self.source), |source| async move {
+            loop {
+                if let Some(reply) = source.next_reply().await {
+                    return Some((reply, source));
+                }
+            }
+        })
+        .filter(move |reply| {
+            let accepted =
+                config.is_responder(reply.sender_id.as_deref(), reply.sender_name.as_deref());
+            if !accepted {
+                tracing::warn!(
+                    "Ignoring Mattermost post from non-responder {:?}",
+                    reply.sender_name.as_ref().or(reply.sender_id.as_ref())
+                );
+            }
+            futures_util::future::ready(accepted)
+        });
+        Some(replies.boxed())
+    }
+
+    async fn edit(&self, reply_to_id: &str, message: &Message) -> ProviderResult<()> {
+        let request = self
+            .api
+            .request(Method::PUT, &format!("posts/{}/patch", reply_to_id))
+            .json(&serde_json::json!({ "message": format_post(message) }));
+        self.api.send::<Post>(request).await.map(|_| ())
+    }
+
+    async fn delete(&self, reply_to_id: &str) -> ProviderResult<()> {
+        let request = self
+            .api
+            .request(Method::DELETE, &format!("posts/{}", reply_to_id));
+        self.api
+            .send::<serde_json::Value>(request)
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{ResponseType, SenderType};
+
+    fn config() -> MattermostProviderConfig {
+        MattermostProviderConfig {
+            enabled: true,
+            url: Some("https://chat.example.com/".to_string()),
+            channel_id: Some("town".to_string()),
+            channel_map: HashMap::from([
+                ("deploys".to_string(), "ops".to_string()),
+                ("ci".to_string(), "builds".to_string()),
+                ("nightly".to_string(), "builds".to_string()),
+            ]),
+            ..MattermostProviderConfig::default()
+        }
+    }
+
+    fn post(id: &str, create_at: i64, user_id: &str, root_id: &str, message: &str) -> Post {
+        Post {
+            id: id.to_string(),
+            create_at,
+            user_id: user_id.to_string(),
+            channel_id: "ops".to_string(),
+            root_id: root_id.to_string(),
+            message: message.to_string(),
+            kind: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_channels_route_to_their_mapping() {
+        let provider =
+            MattermostProvider::new(&config(), "token".to_string(), &ProxySettings::default())
+                .unwrap();
+        assert_eq!(provider.api.base, "https://chat.example.com/api/v4");
+        let authorize = |channel: &str| {
+            Message::new(
+                channel.to_string(),
+                SenderType::Agent,
+                MessageContent::Authorization {
+                    action: "deploy v2".to_string(),
+                    context: None,
+                    timeout_seconds: 60,
+                },
+            )
+        };
+        assert!(provider.handles(&authorize("deploys")));
+        assert!(provider.handles(&authorize("public")));
+
+        let mut mapped_only = config();
+        mapped_only.channel_id = None;
+        let provider =
+            MattermostProvider::new(&mapped_only, "token".to_string(), &ProxySettings::default())
+                .unwrap();
+        assert!(!provider.handles(&authorize("public")));
+
+        assert_eq!(
+            polled_channels(&config()),
+            vec![
+                ("builds".to_string(), None),
+                ("ops".to_string(), Some("deploys".to_string())),
+                ("town".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_authorization_post_says_how_to_answer() {
+        let message = Message::new(
+            "deploys".to_string(),
+            SenderType::Agent,
+            MessageContent::Authorization {
+                action: "deploy v2".to_string(),
+                context: None,
+                timeout_seconds: 60,
+            },
+        );
+        assert_eq!(
+            format_post(&message),
+            "**Authorization** [deploys]: deploy v2\n\n\
+             _Reply in this thread with `yes` to approve or `no: <reason>` to deny._"
+        );
+    }
+
+    #[test]
+    fn test_posts_become_replies() {
+        let list = PostList {
+            posts: HashMap::from([
+                (
+                    "a".to_string(),
+                    post("a", 30, "alice", "p1", " no: too risky "),
+                ),
+                ("b".to_string(), post("b", 20, "bob", "", "yes")),
+                ("c".to_string(), post("c", 40, "bot", "p1", "✅ Approved")),
+                ("d".to_string(), post("d", 10, "alice", "", "old")),
+                (
+                    "e".to_string(),
+                    Post {
+                        kind: "system_join_channel".to_string(),
+                        ..post("e", 50, "carol", "", "carol joined")
+                    },
+                ),
+            ]),
+        };
+        let posts = new_posts(list, 10, Some("bot"));
+        let ids: Vec<&str> = posts.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["b", "a"]);
+
+        let top_level = reply_from_post(&posts[0], None, Some("deploys".to_string()));
+        assert_eq!(top_level.reply_to_message_id, None);
+        assert_eq!(top_level.channel.as_deref(), Some("deploys"));
+        assert_eq!(top_level.response_type, ResponseType::AuthorizationApproved);
+
+        let threaded = reply_from_post(&posts[1], Some("alice".to_string()), None);
+        assert_eq!(threaded.reply_to_message_id.as_deref(), Some("p1"));
+        assert_eq!(threaded.answer.as_deref(), Some("no: too risky"));
+        assert_eq!(threaded.response_type, ResponseType::AuthorizationDenied);
+        assert_eq!(threaded.sender_name.as_deref(), Some("alice"));
+        assert_eq!(threaded.chat_id.as_deref(), Some("ops"));
+    }
+}
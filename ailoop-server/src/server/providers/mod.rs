@@ -1,25 +1,81 @@
-//! Communication providers: notification sinks and reply sources
+//! Communication providers: the `Provider` trait and registry, notification sinks and reply
+//! sources
 //!
 //! **Pending prompt timeout**: `DEFAULT_PROMPT_TIMEOUT_SECS` (300 s) is retained for reference
 //! but is no longer the runtime fallback. Effective timeout is resolved by
-//! `resolve_effective_timeout`: message field → env var `AILOOP_DEFAULT_PROMPT_TIMEOUT_SECS`
-//! → `Configuration.timeout_seconds` → `None` (infinite wait).
+//! `TimeoutPolicy::resolve` (or `resolve_effective_timeout`): message field → env var
+//! `AILOOP_DEFAULT_PROMPT_TIMEOUT_SECS` → `Configuration.timeout_seconds` → `None` (infinite
+//! wait). The policy also gives the answer an expired prompt gets: timeout for questions,
+//! denied for authorizations, not opened for navigations.
 //!
 //! **Invalid provider reply**: Unparseable or invalid replies from a provider (e.g. gibberish
 //! for yes/no) are treated as: authorization/navigation -> deny; question -> empty or error.
-//! See FR-010 in spec and `infer_response_type` in `telegram`.
+//! See FR-010 in spec and `infer_response_type` in `reply_source`.
 
+mod delivery;
+#[cfg(feature = "email")]
+mod email;
+mod exec;
+#[cfg(feature = "irc")]
+mod irc;
+#[cfg(feature = "mattermost")]
+mod mattermost;
 mod pending_prompt;
+mod probe;
+mod provider;
+#[cfg(feature = "pushover")]
+mod pushover;
+mod registry;
 mod reply_source;
+#[cfg(feature = "signal")]
+mod signal;
 mod sink;
 #[cfg(feature = "telegram")]
 mod telegram;
+#[cfg(any(feature = "email", feature = "irc"))]
+mod tls;
+#[cfg(feature = "twilio")]
+mod twilio;
+mod webhook;
 
+pub(crate) use delivery::DeliveryLog;
+pub use delivery::{DeliveryPolicy, DeliveryReport, DeliveryStatus};
+#[cfg(feature = "email")]
+pub use email::{EmailError, EmailProvider, EmailReply, EmailReplySource, EmailSink};
+pub use exec::{ExecError, ExecProvider};
+#[cfg(feature = "irc")]
+pub use irc::IrcProvider;
+#[cfg(feature = "mattermost")]
+pub use mattermost::{MattermostProvider, MattermostReplySource};
 pub use pending_prompt::{
-    resolve_effective_timeout, PendingPromptCompleter, PendingPromptRegistry, PendingSnapshot,
-    PromptType, RecvTimeoutError, DEFAULT_PROMPT_TIMEOUT_SECS,
+    resolve_effective_timeout, Claim, ClaimError, PendingPromptCompleter, PendingPromptRegistry,
+    PendingSnapshot, PromptType, RecvTimeoutError, DEFAULT_PROMPT_TIMEOUT_SECS,
 };
+pub use probe::{probe, ProbeDelivery, ProbeReply, ProbeReport};
+pub(crate) use provider::is_prompt;
+pub use provider::{Provider, ProviderResult, ProviderSink, Unsupported};
+#[cfg(feature = "pushover")]
+pub use pushover::PushoverSink;
+pub use registry::{BuildOutcome, ProviderFactory, ProviderRegistry};
+#[cfg(any(
+    feature = "telegram",
+    feature = "twilio",
+    feature = "email",
+    feature = "mattermost",
+    feature = "irc",
+    feature = "signal"
+))]
+pub(crate) use reply_source::infer_response_type;
+pub(crate) use reply_source::{reply_reason, split_reason};
 pub use reply_source::{ProviderReply, ReplySource};
+#[cfg(feature = "signal")]
+pub use signal::{SignalProvider, SignalReplySource};
 pub use sink::NotificationSink;
 #[cfg(feature = "telegram")]
-pub use telegram::{TelegramReplySource, TelegramSink};
+pub use telegram::{
+    ReplyAliases, TelegramProvider, TelegramReplySource, TelegramSink, TelegramWebhookInbox,
+    WebhookRejection,
+};
+#[cfg(feature = "twilio")]
+pub use twilio::{TwilioRejection, TwilioReply, TwilioSink};
+pub use webhook::{WebhookError, WebhookProvider};
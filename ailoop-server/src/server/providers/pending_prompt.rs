@@ -1,14 +1,30 @@
 //! Pending prompt registry: match provider replies to waiting prompts
+//!
+//! Pending prompts form one shared queue for all responders. A responder may claim a prompt
+//! (`/claim` from a provider, `ailoop queue claim`, the web UI) so the others leave it to them:
+//! until the claim is released, replies from anyone else are refused and replies matched by age
+//! skip it. A claim does not change the prompt's timeout, and the server terminal can always
+//! answer.
+//!
+//! The second factor `[verification]` asks for risky approvals is only checked at the server
+//! terminal, so approvals of those actions submitted here (providers, HTTP API, stdio) are
+//! refused; denials still count.
 
-use ailoop_core::models::{Configuration, MessageContent, ResponseType};
+use crate::server::attachments::Attachment;
+use ailoop_core::models::{
+    Configuration, InvalidInputPolicy, MessageContent, PromptKind, ResponseType, RiskLevel,
+    TeamDirectory, TimeoutPolicy, VerificationConfig,
+};
+use ailoop_core::services::logging::log_security_event;
+use chrono::{DateTime, Utc};
 use serde::Serialize;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::{oneshot, RwLock};
 use uuid::Uuid;
 
 /// Default timeout in seconds for pending prompts — retained for reference, no longer used as
-/// the runtime fallback in prompt dispatch.
+/// the runtime fallback in prompt dispatch (see [`TimeoutPolicy`]).
 pub const DEFAULT_PROMPT_TIMEOUT_SECS: u64 = 300;
 
 /// Type of interactive prompt
@@ -29,9 +45,28 @@ struct PendingEntry {
     channel: String,
     label: String,
     _created_at: std::time::Instant,
+    claim: Option<Claim>,
     tx: oneshot::Sender<MessageContent>,
 }
 
+/// A responder's hold on a pending prompt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Claim {
+    pub responder: String,
+    pub since: DateTime<Utc>,
+}
+
+/// Why a prompt could not be claimed or released.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ClaimError {
+    #[error("no pending prompt with that id")]
+    NotPending,
+    #[error("already claimed by {}", .0.responder)]
+    ClaimedByOther(Claim),
+    #[error("the prompt is not claimed")]
+    NotClaimed,
+}
+
 /// Read-only clone of a pending entry for introspection.
 #[derive(Debug, Clone, Serialize)]
 pub struct PendingSnapshot {
@@ -41,6 +76,7 @@ pub struct PendingSnapshot {
     pub channel: String,
     pub label: String,
     pub position: usize,
+    pub claim: Option<Claim>,
 }
 
 /// Completer for a single registered prompt (e.g. terminal response wins).
@@ -66,15 +102,92 @@ impl PendingPromptCompleter {
 #[derive(Clone)]
 pub struct PendingPromptRegistry {
     inner: Arc<RwLock<VecDeque<PendingEntry>>>,
+    /// Who answered, by prompt message ID, for replies submitted with a responder.
+    responders: Arc<RwLock<HashMap<Uuid, String>>>,
+    /// Files sent with replies, by prompt message ID.
+    attachments: Arc<RwLock<HashMap<Uuid, Attachment>>>,
+    /// Names responders by their handles.
+    team: Arc<RwLock<TeamDirectory>>,
+    /// Claims held for assignees (`ask --assign`), by prompt message ID; applied whenever the
+    /// prompt is registered.
+    assignments: Arc<RwLock<HashMap<Uuid, Claim>>>,
+    /// What replies to authorizations that are neither yes nor no do.
+    invalid_input: Arc<RwLock<InvalidInputPolicy>>,
+    /// Risk levels whose approvals need a second factor at the terminal.
+    verification: Arc<RwLock<VerificationConfig>>,
 }
 
 impl PendingPromptRegistry {
     pub fn new() -> Self {
         Self {
             inner: Arc::new(RwLock::new(VecDeque::new())),
+            responders: Arc::new(RwLock::new(HashMap::new())),
+            attachments: Arc::new(RwLock::new(HashMap::new())),
+            team: Arc::new(RwLock::new(TeamDirectory::default())),
+            assignments: Arc::new(RwLock::new(HashMap::new())),
+            invalid_input: Arc::new(RwLock::new(InvalidInputPolicy::default())),
+            verification: Arc::new(RwLock::new(VerificationConfig::default())),
         }
     }
 
+    pub async fn set_team(&self, team: TeamDirectory) {
+        *self.team.write().await = team;
+    }
+
+    pub async fn set_invalid_input(&self, policy: InvalidInputPolicy) {
+        *self.invalid_input.write().await = policy;
+    }
+
+    pub async fn set_verification(&self, verification: VerificationConfig) {
+        *self.verification.write().await = verification;
+    }
+
+    /// The risk level of pending authorization `message_id` when approving it needs the second
+    /// factor, which only the server terminal can check.
+    pub async fn needs_terminal_approval(&self, message_id: Uuid) -> Option<RiskLevel> {
+        let verification = self.verification.read().await;
+        self.inner
+            .read()
+            .await
+            .iter()
+            .find(|e| e.message_id == message_id && e.prompt_type == PromptType::Authorization)
+            .and_then(|e| verification.rule_for(&e.label))
+            .map(|(level, _)| level)
+    }
+
+    /// The team member whose handle `responder` is, as `@name`.
+    pub async fn team_member(&self, responder: &str) -> Option<String> {
+        self.team
+            .read()
+            .await
+            .member_for(responder)
+            .map(|key| format!("@{}", key))
+    }
+
+    /// Record a file sent with the reply to `message_id`; call before submitting the reply.
+    pub async fn attach(&self, message_id: Uuid, attachment: Attachment) {
+        self.attachments
+            .write()
+            .await
+            .insert(message_id, attachment);
+    }
+
+    /// Whether a file was sent with the reply to `message_id`.
+    pub async fn has_attachment(&self, message_id: Uuid) -> bool {
+        self.attachments.read().await.contains_key(&message_id)
+    }
+
+    /// Take the file recorded for `message_id` by [`Self::attach`].
+    pub async fn take_attachment(&self, message_id: Uuid) -> Option<Attachment> {
+        self.attachments.write().await.remove(&message_id)
+    }
+
+    /// Take the responder recorded for `message_id` by `submit_reply_as` /
+    /// `submit_reply_for_message_as`. `None` means the terminal (or a timeout) completed it.
+    pub async fn take_responder(&self, message_id: Uuid) -> Option<String> {
+        self.responders.write().await.remove(&message_id)
+    }
+
     /// Register a pending prompt. Returns a receiver and a completer (for terminal).
     /// First response (terminal via completer or provider via submit_reply) wins.
     pub async fn register(
@@ -87,6 +200,7 @@ impl PendingPromptRegistry {
     ) -> (oneshot::Receiver<MessageContent>, PendingPromptCompleter) {
         let entry_id = Uuid::new_v4();
         let (tx, rx) = oneshot::channel();
+        crate::server::events::prompt(message_id, &channel, "pending");
         let entry = PendingEntry {
             entry_id,
             message_id,
@@ -95,6 +209,7 @@ impl PendingPromptRegistry {
             channel,
             label,
             _created_at: std::time::Instant::now(),
+            claim: self.assignments.read().await.get(&message_id).cloned(),
             tx,
         };
         self.inner.write().await.push_back(entry);
@@ -105,6 +220,15 @@ impl PendingPromptRegistry {
         (rx, completer)
     }
 
+    /// Drop the entries registered for `message_id` without answering them, e.g. once the
+    /// watchdog gave up on the prompt. Returns how many were dropped.
+    pub async fn discard(&self, message_id: Uuid) -> usize {
+        let mut guard = self.inner.write().await;
+        let before = guard.len();
+        guard.retain(|e| e.message_id != message_id);
+        before - guard.len()
+    }
+
     /// Return a read-only snapshot of all pending entries, optionally filtered by channel.
     /// Acquires a read lock; does not modify the deque or any oneshot channel.
     pub async fn snapshot_pending(&self, channel_filter: Option<&str>) -> Vec<PendingSnapshot> {
@@ -120,9 +244,218 @@ impl PendingPromptRegistry {
                 channel: e.channel.clone(),
                 label: e.label.clone(),
                 position: idx,
+                claim: e.claim.clone(),
             })
             .collect()
     }
+
+    /// Claim the pending prompt `message_id` for `responder`. Claiming again renews nothing but
+    /// succeeds; a prompt claimed by someone else stays theirs.
+    pub async fn claim(&self, message_id: Uuid, responder: &str) -> Result<Claim, ClaimError> {
+        let team = self.team.read().await;
+        let mut guard = self.inner.write().await;
+        let entry = guard
+            .iter_mut()
+            .find(|e| e.message_id == message_id)
+            .ok_or(ClaimError::NotPending)?;
+        if let Some(claim) = &entry.claim {
+            if !same_responder(&team, &claim.responder, responder) {
+                return Err(ClaimError::ClaimedByOther(claim.clone()));
+            }
+            return Ok(claim.clone());
+        }
+        let claim = Claim {
+            responder: responder.to_string(),
+            since: Utc::now(),
+        };
+        entry.claim = Some(claim.clone());
+        crate::server::events::prompt(message_id, &entry.channel, "claimed");
+        Ok(claim)
+    }
+
+    /// Give up `responder`'s claim on `message_id`, putting it back in the shared queue.
+    pub async fn release(&self, message_id: Uuid, responder: &str) -> Result<Claim, ClaimError> {
+        let team = self.team.read().await;
+        let mut guard = self.inner.write().await;
+        let entry = guard
+            .iter_mut()
+            .find(|e| e.message_id == message_id)
+            .ok_or(ClaimError::NotPending)?;
+        match &entry.claim {
+            None => Err(ClaimError::NotClaimed),
+            Some(claim) if !same_responder(&team, &claim.responder, responder) => {
+                Err(ClaimError::ClaimedByOther(claim.clone()))
+            }
+            Some(_) => {
+                crate::server::events::prompt(message_id, &entry.channel, "released");
+                Ok(entry.claim.take().expect("claim present"))
+            }
+        }
+    }
+
+    /// Hold `message_id` for `responder` (e.g. `@alice`) from when it is registered until
+    /// [`Self::unassign`], as if they had claimed it.
+    pub async fn assign(&self, message_id: Uuid, responder: &str) {
+        let claim = Claim {
+            responder: responder.to_string(),
+            since: Utc::now(),
+        };
+        self.assignments.write().await.insert(message_id, claim);
+    }
+
+    /// End the assignment of `message_id`, opening it to everyone unless its assignee has
+    /// claimed it since.
+    pub async fn unassign(&self, message_id: Uuid) {
+        let Some(assigned) = self.assignments.write().await.remove(&message_id) else {
+            return;
+        };
+        let mut guard = self.inner.write().await;
+        for entry in guard.iter_mut().filter(|e| e.message_id == message_id) {
+            if entry.claim.as_ref() == Some(&assigned) {
+                entry.claim = None;
+            }
+        }
+    }
+
+    /// Whether a prompt is waiting for an answer to `message_id`.
+    pub async fn is_pending(&self, message_id: Uuid) -> bool {
+        self.inner
+            .read()
+            .await
+            .iter()
+            .any(|e| e.message_id == message_id)
+    }
+
+    /// The type of the prompt waiting for an answer to `message_id`.
+    pub async fn prompt_type(&self, message_id: Uuid) -> Option<PromptType> {
+        self.inner
+            .read()
+            .await
+            .iter()
+            .find(|e| e.message_id == message_id)
+            .map(|e| e.prompt_type)
+    }
+
+    /// Match replies to `reply_to_message_id` to the prompt `message_id` when it was sent to
+    /// providers without one, e.g. only to an assignee at the terminal at first.
+    pub async fn set_reply_to(&self, message_id: Uuid, reply_to_message_id: String) {
+        let mut guard = self.inner.write().await;
+        for entry in guard.iter_mut().filter(|e| e.message_id == message_id) {
+            entry
+                .reply_to_message_id
+                .get_or_insert_with(|| reply_to_message_id.clone());
+        }
+    }
+
+    /// Drop any claim on `message_id`, whoever holds it (e.g. for a break-glass approval).
+    pub async fn clear_claim(&self, message_id: Uuid) -> Option<Claim> {
+        let mut guard = self.inner.write().await;
+        guard
+            .iter_mut()
+            .find(|e| e.message_id == message_id)
+            .and_then(|e| e.claim.take())
+    }
+
+    /// The claim on `message_id` held by someone other than `responder` (anonymous callers
+    /// are never the holder).
+    pub async fn claimed_by_other(
+        &self,
+        message_id: Uuid,
+        responder: Option<&str>,
+    ) -> Option<Claim> {
+        let team = self.team.read().await;
+        let guard = self.inner.read().await;
+        guard
+            .iter()
+            .find(|e| e.message_id == message_id)
+            .and_then(|e| e.claim.clone())
+            .filter(|claim| !holds(&team, claim, responder))
+    }
+
+    /// The prompt a provider's `/claim` (or, with `release`, `/release`) refers to: the one
+    /// replied to, else the oldest in `channel` that is unclaimed (or claimed by `responder`).
+    pub async fn claim_target(
+        &self,
+        reply_to_message_id: Option<&str>,
+        channel: Option<&str>,
+        responder: &str,
+        release: bool,
+    ) -> Option<Uuid> {
+        let team = self.team.read().await;
+        let guard = self.inner.read().await;
+        if let Some(reply_to) = reply_to_message_id {
+            if let Some(entry) = guard
+                .iter()
+                .find(|e| e.reply_to_message_id.as_deref() == Some(reply_to))
+            {
+                return Some(entry.message_id);
+            }
+        }
+        guard
+            .iter()
+            .filter(|e| channel.is_none_or(|ch| e.channel == ch))
+            .find(|e| match &e.claim {
+                None => !release,
+                Some(claim) => release && holds(&team, claim, Some(responder)),
+            })
+            .map(|e| e.message_id)
+    }
+}
+
+/// The answer `entry` gets from a reply of `response_type`; `None` refuses the reply and keeps
+/// the prompt open. A reply to an authorization that is neither yes nor no is decided by
+/// `invalid_input`, and an approval `verification` asks a second factor for is refused.
+fn settle(
+    entry: &PendingEntry,
+    response_type: ResponseType,
+    invalid_input: InvalidInputPolicy,
+    verification: &VerificationConfig,
+) -> Option<ResponseType> {
+    if entry.prompt_type != PromptType::Authorization {
+        return Some(response_type);
+    }
+    let decision = match response_type {
+        ResponseType::Text => invalid_input.decision(),
+        other => Some(other),
+    };
+    match decision {
+        None => {
+            tracing::warn!(
+                message_id = %entry.message_id,
+                "Ignoring a reply to an authorization that is neither yes nor no"
+            );
+            None
+        }
+        Some(ResponseType::AuthorizationApproved) => match verification.rule_for(&entry.label) {
+            Some((level, _)) => {
+                log_security_event(
+                    "authorization_unverified",
+                    &entry.channel,
+                    None,
+                    &format!(
+                        "message {}: {}-risk approval refused away from the server terminal",
+                        entry.message_id, level
+                    ),
+                );
+                None
+            }
+            None => decision,
+        },
+        decision => decision,
+    }
+}
+
+/// Whether `a` and `b` name the same responder: the same handle, or handles of the same team
+/// member (who may also be named by their key, `@alice`).
+fn same_responder(team: &TeamDirectory, a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+        || team
+            .resolve(a)
+            .is_some_and(|key| team.resolve(b) == Some(key))
+}
+
+fn holds(team: &TeamDirectory, claim: &Claim, responder: Option<&str>) -> bool {
+    responder.is_some_and(|r| same_responder(team, &claim.responder, r))
 }
 
 impl PendingPromptRegistry {
@@ -151,6 +484,14 @@ impl PendingPromptRegistry {
         }
     }
 
+    /// Wait for the prompt response as long as `policy` allows.
+    pub async fn recv_with_policy(
+        rx: oneshot::Receiver<MessageContent>,
+        policy: &TimeoutPolicy,
+    ) -> Result<MessageContent, RecvTimeoutError> {
+        Self::recv_maybe_timeout(rx, policy.timeout()).await
+    }
+
     /// Submit a reply from a provider. Matches by reply_to_message_id or oldest first.
     /// Returns true if matched and response was sent to the waiting task.
     pub async fn submit_reply(
@@ -159,26 +500,60 @@ impl PendingPromptRegistry {
         answer: Option<String>,
         response_type: ResponseType,
     ) -> bool {
-        let content = MessageContent::Response {
-            answer,
-            response_type,
-        };
+        self.submit_reply_as(reply_to_message_id, answer, response_type, None, None)
+            .await
+    }
+
+    /// Like `submit_reply`, recording `responder` for `take_responder`. When `channel` is set,
+    /// a reply without a matching reply-to only answers the oldest prompt in that channel.
+    /// Prompts claimed by another responder are not answered: a reply to one is refused and
+    /// matching by age skips them.
+    pub async fn submit_reply_as(
+        &self,
+        reply_to_message_id: Option<String>,
+        answer: Option<String>,
+        response_type: ResponseType,
+        responder: Option<String>,
+        channel: Option<&str>,
+    ) -> bool {
+        let invalid_input = *self.invalid_input.read().await;
+        let verification = self.verification.read().await;
+        let team = self.team.read().await;
         let mut guard = self.inner.write().await;
-        if let Some(reply_to) = &reply_to_message_id {
-            if let Some(pos) = guard
-                .iter()
-                .position(|e| e.reply_to_message_id.as_deref() == Some(reply_to.as_str()))
-            {
-                let entry = guard.remove(pos).expect("position exists");
-                let _ = entry.tx.send(content);
-                return true;
-            }
-        }
-        if let Some(entry) = guard.pop_front() {
-            let _ = entry.tx.send(content);
-            return true;
+        let may_answer = |e: &PendingEntry| {
+            e.claim
+                .as_ref()
+                .is_none_or(|claim| holds(&team, claim, responder.as_deref()))
+        };
+        let pos = reply_to_message_id
+            .as_ref()
+            .and_then(|reply_to| {
+                guard
+                    .iter()
+                    .position(|e| e.reply_to_message_id.as_deref() == Some(reply_to.as_str()))
+            })
+            .or_else(|| {
+                guard
+                    .iter()
+                    .position(|e| channel.is_none_or(|ch| e.channel == ch) && may_answer(e))
+            });
+        let Some(pos) = pos else {
+            return false;
+        };
+        if !may_answer(&guard[pos]) {
+            return false;
         }
-        false
+        let Some(response_type) = settle(&guard[pos], response_type, invalid_input, &verification)
+        else {
+            return false;
+        };
+        let entry = guard.remove(pos).expect("position exists");
+        self.record_responder(entry.message_id, responder).await;
+        let _ = entry.tx.send(MessageContent::Response {
+            answer,
+            response_type,
+        });
+        true
     }
 
     /// Submit a reply that targets a specific message ID (e.g. via HTTP API).
@@ -189,17 +564,81 @@ impl PendingPromptRegistry {
         answer: Option<String>,
         response_type: ResponseType,
     ) -> bool {
-        let content = MessageContent::Response {
+        self.submit_reply_for_message_as(message_id, answer, response_type, None)
+            .await
+    }
+
+    /// Like `submit_reply_for_message`, recording `responder` for `take_responder`. Refused
+    /// when the prompt is claimed by another responder.
+    pub async fn submit_reply_for_message_as(
+        &self,
+        message_id: Uuid,
+        answer: Option<String>,
+        response_type: ResponseType,
+        responder: Option<String>,
+    ) -> bool {
+        let verification = self.verification.read().await.clone();
+        self.submit_for_message(message_id, answer, response_type, responder, &verification)
+            .await
+    }
+
+    /// Break-glass approval of `message_id` by `responder`. The holder's token stands in for
+    /// the second factor, so `[verification]` does not apply.
+    pub async fn submit_break_glass(
+        &self,
+        message_id: Uuid,
+        justification: String,
+        responder: String,
+    ) -> bool {
+        self.submit_for_message(
+            message_id,
+            Some(justification),
+            ResponseType::AuthorizationApproved,
+            Some(responder),
+            &VerificationConfig::default(),
+        )
+        .await
+    }
+
+    async fn submit_for_message(
+        &self,
+        message_id: Uuid,
+        answer: Option<String>,
+        response_type: ResponseType,
+        responder: Option<String>,
+        verification: &VerificationConfig,
+    ) -> bool {
+        let invalid_input = *self.invalid_input.read().await;
+        let team = self.team.read().await;
+        let mut guard = self.inner.write().await;
+        let Some(pos) = guard.iter().position(|e| {
+            e.message_id == message_id
+                && e.claim
+                    .as_ref()
+                    .is_none_or(|claim| holds(&team, claim, responder.as_deref()))
+        }) else {
+            return false;
+        };
+        let Some(response_type) = settle(&guard[pos], response_type, invalid_input, verification)
+        else {
+            return false;
+        };
+        let entry = guard.remove(pos).expect("position exists");
+        self.record_responder(entry.message_id, responder).await;
+        let _ = entry.tx.send(MessageContent::Response {
             answer,
             response_type,
-        };
-        let mut guard = self.inner.write().await;
-        if let Some(pos) = guard.iter().position(|e| e.message_id == message_id) {
-            let entry = guard.remove(pos).expect("position exists");
-            let _ = entry.tx.send(content);
-            return true;
+        });
+        true
+    }
+}
+
+impl PendingPromptRegistry {
+    /// Recorded before the oneshot fires so the waiting task always sees it.
+    async fn record_responder(&self, message_id: Uuid, responder: Option<String>) {
+        if let Some(responder) = responder {
+            self.responders.write().await.insert(message_id, responder);
         }
-        false
     }
 }
 
@@ -226,32 +665,12 @@ impl Default for PendingPromptRegistry {
 ///
 /// `timeout_seconds = Some(0)` in config means "infinite" (consistent with the 0 = no timeout
 /// convention). The `> 3600` guard in `Configuration::validate()` already accepts `Some(0)`.
+/// Shorthand for [`TimeoutPolicy::resolve`], which also says what expiry means per prompt.
 pub fn resolve_effective_timeout(
     message_timeout_secs: u32,
     config: Option<&Configuration>,
 ) -> Option<std::time::Duration> {
-    if message_timeout_secs > 0 {
-        return Some(std::time::Duration::from_secs(message_timeout_secs as u64));
-    }
-    if let Ok(val_str) = std::env::var("AILOOP_DEFAULT_PROMPT_TIMEOUT_SECS") {
-        match val_str.parse::<u64>() {
-            Ok(val) if val > 0 => return Some(std::time::Duration::from_secs(val)),
-            _ => {
-                tracing::warn!(
-                    "AILOOP_DEFAULT_PROMPT_TIMEOUT_SECS is set but not a valid positive integer: {:?}; ignoring",
-                    val_str
-                );
-            }
-        }
-    }
-    if let Some(cfg) = config {
-        if let Some(t) = cfg.timeout_seconds {
-            if t > 0 {
-                return Some(std::time::Duration::from_secs(t as u64));
-            }
-        }
-    }
-    None
+    TimeoutPolicy::resolve(PromptKind::Question, message_timeout_secs, config).timeout()
 }
 
 #[cfg(test)]
@@ -403,4 +822,249 @@ mod tests {
         let result = PendingPromptRegistry::recv_maybe_timeout(rx, None).await;
         assert!(matches!(result, Err(RecvTimeoutError::Closed)));
     }
+
+    // --- responder attribution ---
+
+    #[tokio::test]
+    async fn test_submit_reply_as_records_responder() {
+        let registry = PendingPromptRegistry::new();
+        let message_id = Uuid::new_v4();
+        let (rx, _completer) = registry
+            .register(
+                message_id,
+                Some("42".to_string()),
+                PromptType::Authorization,
+                "public".to_string(),
+                "deploy".to_string(),
+            )
+            .await;
+        assert!(
+            registry
+                .submit_reply_as(
+                    Some("42".to_string()),
+                    None,
+                    ResponseType::AuthorizationApproved,
+                    Some("telegram:alice".to_string()),
+                    None,
+                )
+                .await
+        );
+        assert!(rx.await.is_ok());
+        assert_eq!(
+            registry.take_responder(message_id).await.as_deref(),
+            Some("telegram:alice")
+        );
+        assert_eq!(registry.take_responder(message_id).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_free_text_answer_to_authorization_follows_policy() {
+        let registry = PendingPromptRegistry::new();
+        let register = || {
+            registry.register(
+                Uuid::new_v4(),
+                Some("7".to_string()),
+                PromptType::Authorization,
+                "ops".to_string(),
+                "deploy".to_string(),
+            )
+        };
+        let reply = |response_type| {
+            registry.submit_reply_as(
+                Some("7".to_string()),
+                Some("maybe".to_string()),
+                response_type,
+                None,
+                None,
+            )
+        };
+
+        let (rx, _denied) = register().await;
+        assert!(reply(ResponseType::Text).await);
+        assert!(matches!(
+            rx.await.unwrap(),
+            MessageContent::Response {
+                response_type: ResponseType::AuthorizationDenied,
+                ..
+            }
+        ));
+
+        registry
+            .set_invalid_input(InvalidInputPolicy::Reprompt)
+            .await;
+        let (rx, _asked_again) = register().await;
+        assert!(!reply(ResponseType::Text).await);
+        assert!(reply(ResponseType::AuthorizationApproved).await);
+        assert!(matches!(
+            rx.await.unwrap(),
+            MessageContent::Response {
+                response_type: ResponseType::AuthorizationApproved,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_provider_approval_of_verified_action_is_refused() {
+        let registry = PendingPromptRegistry::new();
+        registry
+            .set_verification(VerificationConfig {
+                critical: Some(ailoop_core::models::VerificationRule {
+                    patterns: vec!["drop table".to_string()],
+                    method: ailoop_core::models::VerificationMethod::Totp,
+                }),
+                ..VerificationConfig::default()
+            })
+            .await;
+        let message_id = Uuid::new_v4();
+        let (rx, _completer) = registry
+            .register(
+                message_id,
+                Some("9".to_string()),
+                PromptType::Authorization,
+                "ops".to_string(),
+                "drop table users".to_string(),
+            )
+            .await;
+        assert_eq!(
+            registry.needs_terminal_approval(message_id).await,
+            Some(RiskLevel::Critical)
+        );
+
+        let reply = |response_type| {
+            registry.submit_reply_as(Some("9".to_string()), None, response_type, None, None)
+        };
+        assert!(!reply(ResponseType::AuthorizationApproved).await);
+        assert!(
+            !registry
+                .submit_reply_for_message(message_id, None, ResponseType::AuthorizationApproved)
+                .await
+        );
+        assert!(reply(ResponseType::AuthorizationDenied).await);
+        assert!(matches!(
+            rx.await.unwrap(),
+            MessageContent::Response {
+                response_type: ResponseType::AuthorizationDenied,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_channel_scoped_reply_skips_other_channels() {
+        let registry = PendingPromptRegistry::new();
+        let register = |channel: &str| {
+            registry.register(
+                Uuid::new_v4(),
+                None,
+                PromptType::Authorization,
+                channel.to_string(),
+                "deploy".to_string(),
+            )
+        };
+        let (mut ops_rx, _ops) = register("ops").await;
+        let (deploys_rx, _deploys) = register("deploys").await;
+
+        let reply = |channel| {
+            registry.submit_reply_as(
+                None,
+                None,
+                ResponseType::AuthorizationApproved,
+                None,
+                channel,
+            )
+        };
+        assert!(reply(Some("deploys")).await);
+        assert!(deploys_rx.await.is_ok());
+        assert!(ops_rx.try_recv().is_err());
+        assert!(!reply(Some("deploys")).await);
+    }
+
+    #[tokio::test]
+    async fn test_claimed_prompt_is_left_to_its_claimant() {
+        let registry = PendingPromptRegistry::new();
+        let claimed = Uuid::new_v4();
+        let (claimed_rx, _c1) = registry
+            .register(
+                claimed,
+                None,
+                PromptType::Decision,
+                "ops".to_string(),
+                "region?".to_string(),
+            )
+            .await;
+        let (mut other_rx, _c2) = registry
+            .register(
+                Uuid::new_v4(),
+                None,
+                PromptType::Decision,
+                "ops".to_string(),
+                "size?".to_string(),
+            )
+            .await;
+
+        let claim = registry.claim(claimed, "web:alice").await.unwrap();
+        assert_eq!(claim.responder, "web:alice");
+        assert!(matches!(
+            registry.claim(claimed, "telegram:bob").await,
+            Err(ClaimError::ClaimedByOther(_))
+        ));
+        assert_eq!(
+            registry.claimed_by_other(claimed, None).await,
+            Some(claim.clone())
+        );
+        assert_eq!(
+            registry
+                .claim_target(None, Some("ops"), "telegram:bob", false)
+                .await,
+            registry
+                .snapshot_pending(None)
+                .await
+                .get(1)
+                .map(|p| p.message_id)
+        );
+
+        // Bob's unaddressed reply skips Alice's prompt, and he cannot answer it directly.
+        let text = |who: &str| Some(who.to_string());
+        assert!(
+            registry
+                .submit_reply_as(None, None, ResponseType::Text, text("telegram:bob"), None)
+                .await
+        );
+        assert!(other_rx.try_recv().is_ok());
+        assert!(
+            !registry
+                .submit_reply_for_message_as(
+                    claimed,
+                    None,
+                    ResponseType::Text,
+                    text("telegram:bob")
+                )
+                .await
+        );
+        assert_eq!(
+            registry.release(claimed, "telegram:bob").await,
+            Err(ClaimError::ClaimedByOther(claim))
+        );
+        assert!(registry.release(claimed, "web:alice").await.is_ok());
+        assert_eq!(
+            registry.release(claimed, "web:alice").await,
+            Err(ClaimError::NotClaimed)
+        );
+        assert!(
+            registry
+                .submit_reply_for_message_as(
+                    claimed,
+                    None,
+                    ResponseType::Text,
+                    text("telegram:bob")
+                )
+                .await
+        );
+        assert!(claimed_rx.await.is_ok());
+        assert_eq!(
+            registry.claim(claimed, "web:alice").await,
+            Err(ClaimError::NotPending)
+        );
+    }
 }
@@ -0,0 +1,181 @@
+//! Provider checks for `ailoop notify-test`: a test prompt sent through one provider, and the
+//! answer that comes back through it.
+
+use crate::server::providers::{Provider, ProviderReply};
+use ailoop_core::models::Message;
+use futures_util::StreamExt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How the test prompt went out.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProbeDelivery {
+    Delivered,
+    /// The provider does not take the test prompt (e.g. its channel is not routed there).
+    Skipped,
+    Failed(String),
+}
+
+/// What came back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProbeReply {
+    /// Answered by `responder` after `elapsed`.
+    Received {
+        responder: String,
+        answer: Option<String>,
+        elapsed: Duration,
+    },
+    TimedOut,
+    /// The provider cannot read answers from here (e.g. send-only, or replies arrive on a
+    /// server endpoint).
+    Unsupported,
+    /// Not waited for: the prompt did not go out, or no wait was asked for.
+    NotWaited,
+}
+
+/// Outcome of the test prompt for one provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeReport {
+    pub provider: String,
+    pub delivery: ProbeDelivery,
+    pub reply: ProbeReply,
+}
+
+impl ProbeReport {
+    /// Whether the provider needs attention: delivery failed or no answer came in time.
+    pub fn failed(&self) -> bool {
+        matches!(self.delivery, ProbeDelivery::Failed(_)) || self.reply == ProbeReply::TimedOut
+    }
+}
+
+/// Send `message` through `provider`, then wait up to `wait` for the first answer it reads.
+pub async fn probe(provider: Arc<dyn Provider>, message: Message, wait: Duration) -> ProbeReport {
+    let report = |delivery, reply| ProbeReport {
+        provider: provider.name().to_string(),
+        delivery,
+        reply,
+    };
+    if let Err(e) = provider.init().await {
+        return report(
+            ProbeDelivery::Failed(format!("init: {}", e)),
+            ProbeReply::NotWaited,
+        );
+    }
+    if !provider.handles(&message) {
+        return report(ProbeDelivery::Skipped, ProbeReply::NotWaited);
+    }
+    // Listen before sending, so a quick answer is not missed.
+    let replies = provider.reply_stream();
+    let started = Instant::now();
+    if let Err(e) = provider.send_prompt(&message).await {
+        return report(ProbeDelivery::Failed(e.to_string()), ProbeReply::NotWaited);
+    }
+    let reply = match replies {
+        None => ProbeReply::Unsupported,
+        Some(_) if wait.is_zero() => ProbeReply::NotWaited,
+        Some(mut replies) => match tokio::time::timeout(wait, replies.next()).await {
+            Ok(Some(reply)) => received(reply, started.elapsed()),
+            Ok(None) | Err(_) => ProbeReply::TimedOut,
+        },
+    };
+    report(ProbeDelivery::Delivered, reply)
+}
+
+fn received(reply: ProviderReply, elapsed: Duration) -> ProbeReply {
+    ProbeReply::Received {
+        responder: reply
+            .sender_name
+            .or(reply.sender_id)
+            .unwrap_or_else(|| "unknown".to_string()),
+        answer: reply.answer,
+        elapsed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::providers::ProviderResult;
+    use ailoop_core::models::ResponseType;
+    use async_trait::async_trait;
+    use futures_util::stream::BoxStream;
+
+    /// Takes prompts of channel `ops` only, and answers each with `yes` when `answers`.
+    struct Fake {
+        answers: bool,
+        fails: bool,
+    }
+
+    #[async_trait]
+    impl Provider for Fake {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        fn handles(&self, message: &Message) -> bool {
+            message.channel == "ops"
+        }
+
+        async fn send_prompt(&self, _message: &Message) -> ProviderResult<Option<String>> {
+            if self.fails {
+                return Err("chat not found".into());
+            }
+            Ok(None)
+        }
+
+        async fn send_notification(&self, _message: &Message) -> ProviderResult<()> {
+            Ok(())
+        }
+
+        fn reply_stream(&self) -> Option<BoxStream<'static, ProviderReply>> {
+            let reply = ProviderReply {
+                reply_to_message_id: None,
+                prompt_id: None,
+                answer: Some("yes".to_string()),
+                response_type: ResponseType::AuthorizationApproved,
+                sender_id: Some("42".to_string()),
+                sender_name: Some("alice".to_string()),
+                chat_id: None,
+                channel: None,
+            };
+            if self.answers {
+                Some(futures_util::stream::iter([reply]).boxed())
+            } else {
+                Some(futures_util::stream::pending::<ProviderReply>().boxed())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_delivery_and_answer() {
+        let prompt = ailoop_core::client::authorization("ops", "notify-test", 60);
+        let wait = Duration::from_millis(50);
+        let run = |answers, fails, message: &Message| {
+            probe(Arc::new(Fake { answers, fails }), message.clone(), wait)
+        };
+
+        let answered = run(true, false, &prompt).await;
+        assert_eq!(answered.delivery, ProbeDelivery::Delivered);
+        assert!(matches!(
+            answered.reply,
+            ProbeReply::Received { ref responder, .. } if responder == "alice"
+        ));
+        assert!(!answered.failed());
+
+        let silent = run(false, false, &prompt).await;
+        assert_eq!(silent.reply, ProbeReply::TimedOut);
+        assert!(silent.failed());
+
+        let broken = run(true, true, &prompt).await;
+        assert_eq!(
+            broken.delivery,
+            ProbeDelivery::Failed("chat not found".to_string())
+        );
+        assert!(broken.failed());
+
+        let elsewhere = ailoop_core::client::authorization("dev", "notify-test", 60);
+        let skipped = run(true, false, &elsewhere).await;
+        assert_eq!(skipped.delivery, ProbeDelivery::Skipped);
+        assert!(!skipped.failed());
+    }
+}
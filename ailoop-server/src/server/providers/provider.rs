@@ -0,0 +1,117 @@
+//! Provider: one implementation per chat service (Telegram, Twilio, ...)
+//!
+//! A provider covers both directions: outbound prompts and notifications, and inbound replies.
+//! Providers are created from config by the [`ProviderRegistry`](super::ProviderRegistry) and
+//! attached to the broadcast manager through [`ProviderSink`].
+
+use crate::server::providers::{NotificationSink, ProviderReply};
+use ailoop_core::models::{Message, MessageContent};
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use std::error::Error;
+use std::sync::Arc;
+
+/// Result type of provider operations.
+pub type ProviderResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Returned by the default `edit` / `delete` of providers that cannot change sent messages.
+#[derive(Debug, thiserror::Error)]
+#[error("{provider} does not support {operation}")]
+pub struct Unsupported {
+    pub provider: String,
+    pub operation: &'static str,
+}
+
+/// A chat service ailoop can deliver prompts to and take replies from.
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Registry key and log name (e.g. "telegram"). Also the responder prefix for replies.
+    fn name(&self) -> &str;
+
+    /// Whether this provider takes `message` at all (e.g. only opted-in channels).
+    fn handles(&self, _message: &Message) -> bool {
+        true
+    }
+
+    /// One-time setup before replies are read (e.g. registering a webhook). Delivery may start
+    /// before `init` finishes.
+    async fn init(&self) -> ProviderResult<()> {
+        Ok(())
+    }
+
+    /// Deliver a prompt (decision, authorization, navigation). Returns the provider's reply-to
+    /// id when replies can be matched to the sent message.
+    async fn send_prompt(&self, message: &Message) -> ProviderResult<Option<String>>;
+
+    /// Deliver a notification, response or task update.
+    async fn send_notification(&self, message: &Message) -> ProviderResult<()>;
+
+    /// Show a status pulse as a transient indicator. Providers without one ignore it.
+    async fn send_status(&self, _message: &Message) -> ProviderResult<()> {
+        Ok(())
+    }
+
+    /// Inbound replies. `None` for send-only providers and those receiving replies through
+    /// their own HTTP endpoint.
+    fn reply_stream(&self) -> Option<BoxStream<'static, ProviderReply>> {
+        None
+    }
+
+    /// Replace the text of a sent message, identified by its reply-to id.
+    async fn edit(&self, reply_to_id: &str, message: &Message) -> ProviderResult<()> {
+        let _ = (reply_to_id, message);
+        Err(Box::new(Unsupported {
+            provider: self.name().to_string(),
+            operation: "edit",
+        }))
+    }
+
+    /// Delete a sent message, identified by its reply-to id.
+    async fn delete(&self, reply_to_id: &str) -> ProviderResult<()> {
+        let _ = reply_to_id;
+        Err(Box::new(Unsupported {
+            provider: self.name().to_string(),
+            operation: "delete",
+        }))
+    }
+}
+
+/// Whether `content` awaits a human answer.
+pub(crate) fn is_prompt(content: &MessageContent) -> bool {
+    matches!(
+        content,
+        MessageContent::Decision { .. }
+            | MessageContent::Authorization { .. }
+            | MessageContent::Navigate { .. }
+    )
+}
+
+/// Adapts a [`Provider`] to the broadcast manager's [`NotificationSink`] interface.
+pub struct ProviderSink(pub Arc<dyn Provider>);
+
+#[async_trait]
+impl NotificationSink for ProviderSink {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn handles(&self, message: &Message) -> bool {
+        self.0.handles(message)
+    }
+
+    async fn send(&self, message: &Message) -> ProviderResult<()> {
+        self.send_and_get_reply_to_id(message).await.map(|_| ())
+    }
+
+    async fn send_and_get_reply_to_id(&self, message: &Message) -> ProviderResult<Option<String>> {
+        if is_prompt(&message.content) {
+            self.0.send_prompt(message).await
+        } else {
+            self.0.send_notification(message).await.map(|_| None)
+        }
+    }
+
+    async fn send_status(&self, message: &Message) -> ProviderResult<()> {
+        self.0.send_status(message).await
+    }
+}
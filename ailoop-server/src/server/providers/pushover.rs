@@ -0,0 +1,270 @@
+//! Pushover provider: loud phone alerts for high and urgent messages.
+//!
+//! Send-only. A notification alerts at its own priority, a prompt at the priority it was
+//! boosted to (see [`Message::queue_priority`]); everything below `high` is left to the other
+//! providers. `urgent` goes out as a Pushover emergency alert, which repeats until someone
+//! acknowledges it on the phone. The prompt itself is answered elsewhere.
+
+use crate::server::providers::{is_prompt, Provider, ProviderResult};
+use ailoop_core::models::{Message, MessageContent, NotificationPriority, PushoverProviderConfig};
+use ailoop_core::transport::proxy::ProxySettings;
+use async_trait::async_trait;
+use reqwest::Client;
+use std::collections::HashSet;
+use std::error::Error;
+use std::time::Duration;
+
+const PUSHOVER_API_URL: &str = "https://api.pushover.net/1/messages.json";
+const HTTP_TIMEOUT_SECS: u64 = 30;
+/// Pushover's limits on title and message length, in characters.
+const MAX_TITLE_CHARS: usize = 250;
+const MAX_MESSAGE_CHARS: usize = 1024;
+/// Emergency alerts repeat every `EMERGENCY_RETRY_SECS` until acknowledged, for at most
+/// `EMERGENCY_EXPIRE_SECS`.
+const EMERGENCY_RETRY_SECS: u32 = 60;
+const EMERGENCY_EXPIRE_SECS: u32 = 3600;
+
+/// Pushover notification sink.
+#[derive(Debug)]
+pub struct PushoverSink {
+    app_token: String,
+    user_key: String,
+    device: Option<String>,
+    channels: HashSet<String>,
+    client: Client,
+}
+
+impl PushoverSink {
+    /// Build from `[providers.pushover]`; `app_token` comes from `AILOOP_PUSHOVER_APP_TOKEN`.
+    pub fn new(
+        config: &PushoverProviderConfig,
+        app_token: String,
+        proxy: &ProxySettings,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let Some(user_key) = config.user_key.clone().filter(|k| !k.is_empty()) else {
+            return Err("providers.pushover: user_key is required".into());
+        };
+        let client = proxy
+            .apply(Client::builder())?
+            .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+            .build()?;
+        Ok(Self {
+            app_token,
+            user_key,
+            device: config.device.clone(),
+            channels: config.channels.iter().cloned().collect(),
+            client,
+        })
+    }
+
+    /// Build from `[providers.pushover]` with the app token from `AILOOP_PUSHOVER_APP_TOKEN`.
+    /// Returns `Ok(None)` when the provider is disabled or the token is not set.
+    pub fn from_config(
+        config: &PushoverProviderConfig,
+        proxy: &ProxySettings,
+    ) -> Result<Option<Self>, Box<dyn Error + Send + Sync>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        match std::env::var("AILOOP_PUSHOVER_APP_TOKEN") {
+            Ok(app_token) => Self::new(config, app_token, proxy).map(Some),
+            Err(_) => {
+                tracing::warn!("Pushover provider skipped: app token not set");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Pushover priority for `message`: 1 (high) or 2 (emergency), or None when it should not
+    /// alert at all.
+    fn alert_priority(&self, message: &Message) -> Option<i8> {
+        if !self.channels.is_empty() && !self.channels.contains(&message.channel) {
+            return None;
+        }
+        let alerts = is_prompt(&message.content)
+            || matches!(message.content, MessageContent::Notification { .. });
+        match message.queue_priority() {
+            NotificationPriority::High if alerts => Some(1),
+            NotificationPriority::Urgent if alerts => Some(2),
+            _ => None,
+        }
+    }
+
+    async fn deliver(&self, message: &Message) -> ProviderResult<()> {
+        let Some(priority) = self.alert_priority(message) else {
+            return Ok(());
+        };
+        let (title, body, url) = compose(message);
+        let mut form = vec![
+            ("token", self.app_token.clone()),
+            ("user", self.user_key.clone()),
+            ("title", title),
+            ("message", body),
+            ("priority", priority.to_string()),
+        ];
+        if priority == 2 {
+            form.push(("retry", EMERGENCY_RETRY_SECS.to_string()));
+            form.push(("expire", EMERGENCY_EXPIRE_SECS.to_string()));
+        }
+        if let Some(device) = &self.device {
+            form.push(("device", device.clone()));
+        }
+        if let Some(url) = url {
+            form.push(("url", url));
+        }
+        let response = self
+            .client
+            .post(PUSHOVER_API_URL)
+            .form(&form)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!("Pushover API error: {} – {}", status, text).into());
+        }
+        Ok(())
+    }
+}
+
+/// Title, message and link of the alert for `message`, within Pushover's length limits.
+fn compose(message: &Message) -> (String, String, Option<String>) {
+    let channel = &message.channel;
+    let (title, text, url) = match &message.content {
+        MessageContent::Decision { summary, .. } => {
+            (format!("Decision [{}]", channel), summary.clone(), None)
+        }
+        MessageContent::Authorization { action, .. } => {
+            (format!("Authorization [{}]", channel), action.clone(), None)
+        }
+        MessageContent::Navigate { url, .. } => (
+            format!("Navigation [{}]", channel),
+            url.clone(),
+            Some(url.clone()),
+        ),
+        MessageContent::Notification { text, .. } => {
+            (format!("Notification [{}]", channel), text.clone(), None)
+        }
+        _ => (format!("Message [{}]", channel), String::new(), None),
+    };
+    let text = match message.agent_heading() {
+        Some(heading) => format!("{}\n{}", heading, text.trim()),
+        None => text.trim().to_string(),
+    };
+    (
+        truncate_chars(&title, MAX_TITLE_CHARS),
+        truncate_chars(&text, MAX_MESSAGE_CHARS),
+        url,
+    )
+}
+
+/// First `max` characters of `text`, ending in `…` when cut.
+fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+/// Replies are not possible through Pushover; alerts cannot be edited.
+#[async_trait]
+impl Provider for PushoverSink {
+    fn name(&self) -> &str {
+        "pushover"
+    }
+
+    fn handles(&self, message: &Message) -> bool {
+        self.alert_priority(message).is_some()
+    }
+
+    async fn send_prompt(&self, message: &Message) -> ProviderResult<Option<String>> {
+        self.deliver(message).await?;
+        Ok(None)
+    }
+
+    async fn send_notification(&self, message: &Message) -> ProviderResult<()> {
+        self.deliver(message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::SenderType;
+
+    fn sink(channels: &[&str]) -> PushoverSink {
+        PushoverSink::new(
+            &PushoverProviderConfig {
+                enabled: true,
+                user_key: Some("u123".to_string()),
+                device: None,
+                channels: channels.iter().map(|c| c.to_string()).collect(),
+            },
+            "a456".to_string(),
+            &ProxySettings::default(),
+        )
+        .unwrap()
+    }
+
+    fn notify(channel: &str, priority: NotificationPriority) -> Message {
+        Message::new(
+            channel.to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "disk full".to_string(),
+                priority,
+            },
+        )
+    }
+
+    #[test]
+    fn test_only_high_and_urgent_messages_alert() {
+        let all = sink(&[]);
+        assert_eq!(
+            all.alert_priority(&notify("ops", NotificationPriority::Normal)),
+            None
+        );
+        assert_eq!(
+            all.alert_priority(&notify("ops", NotificationPriority::High)),
+            Some(1)
+        );
+        assert_eq!(
+            all.alert_priority(&notify("ops", NotificationPriority::Urgent)),
+            Some(2)
+        );
+
+        let mut authorization = ailoop_core::client::authorization("ops", "drop table users", 60);
+        authorization.agent = None;
+        assert_eq!(all.alert_priority(&authorization), None);
+        authorization.metadata = Some(serde_json::json!({ "boosted": "urgent" }));
+        assert_eq!(all.alert_priority(&authorization), Some(2));
+        let (title, text, url) = compose(&authorization);
+        assert_eq!(
+            (title.as_str(), text.as_str(), url),
+            ("Authorization [ops]", "drop table users", None)
+        );
+
+        let oncall_only = sink(&["oncall"]);
+        assert_eq!(
+            oncall_only.alert_priority(&notify("ops", NotificationPriority::Urgent)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_long_text_is_cut_to_pushover_limits() {
+        let message = notify("ops", NotificationPriority::High);
+        let message = Message {
+            content: MessageContent::Notification {
+                text: "é".repeat(2000),
+                priority: NotificationPriority::High,
+            },
+            ..message
+        };
+        let (_, text, _) = compose(&message);
+        assert_eq!(text.chars().count(), MAX_MESSAGE_CHARS);
+        assert!(text.ends_with('…'));
+    }
+}
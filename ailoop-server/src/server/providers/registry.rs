@@ -0,0 +1,209 @@
+//! Provider registry: factories keyed by provider name, and the providers they started.
+
+use crate::server::providers::{ExecProvider, Provider, ProviderResult, WebhookProvider};
+use crate::state::AiloopAppState;
+use ailoop_core::models::Configuration;
+use ailoop_core::transport::proxy::ProxySettings;
+use std::sync::{Arc, RwLock};
+
+/// A provider built from config: `Ok(None)` when it is disabled or not configured, `Err` when
+/// it is enabled but cannot start.
+pub type BuildOutcome = ProviderResult<Option<Arc<dyn Provider>>>;
+
+/// Builds a provider from config; see [`BuildOutcome`]. Log why when returning `Ok(None)`.
+pub type ProviderFactory =
+    Arc<dyn Fn(&Configuration, &AiloopAppState) -> BuildOutcome + Send + Sync>;
+
+/// Provider factories in registration order, plus the providers started from them.
+pub struct ProviderRegistry {
+    factories: RwLock<Vec<(String, ProviderFactory)>>,
+    active: RwLock<Vec<Arc<dyn Provider>>>,
+}
+
+impl ProviderRegistry {
+    /// Empty registry.
+    pub fn new() -> Self {
+        Self {
+            factories: RwLock::new(Vec::new()),
+            active: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Registry with the providers compiled into this build (feature-gated).
+    pub fn with_builtin() -> Self {
+        let registry = Self::new();
+        #[cfg(feature = "telegram")]
+        registry.register(
+            "telegram",
+            Arc::new(|config: &Configuration, state: &AiloopAppState| {
+                let provider = crate::server::providers::TelegramProvider::from_config(
+                    &config.providers.telegram,
+                    &ProxySettings::resolve(&config.proxy)?,
+                    Arc::clone(&state.telegram_webhook),
+                )?;
+                Ok(provider.map(|p| {
+                    Arc::new(p.with_attachments(Arc::clone(&state.attachments)))
+                        as Arc<dyn Provider>
+                }))
+            }),
+        );
+        #[cfg(feature = "twilio")]
+        registry.register(
+            "twilio",
+            Arc::new(|config: &Configuration, state: &AiloopAppState| {
+                let sink = crate::server::providers::TwilioSink::from_config(
+                    &config.providers.twilio,
+                    &ProxySettings::resolve(&config.proxy)?,
+                )?;
+                Ok(sink.map(|sink| {
+                    let sink = Arc::new(sink);
+                    let _ = state.twilio.set(Arc::clone(&sink));
+                    sink as Arc<dyn Provider>
+                }))
+            }),
+        );
+        #[cfg(feature = "email")]
+        registry.register(
+            "email",
+            Arc::new(|config: &Configuration, state: &AiloopAppState| {
+                let provider = crate::server::providers::EmailProvider::from_config(
+                    &config.providers.email,
+                    Arc::clone(&state.pending_prompt_registry),
+                );
+                Ok(provider.map(|p| Arc::new(p) as Arc<dyn Provider>))
+            }),
+        );
+        #[cfg(feature = "mattermost")]
+        registry.register(
+            "mattermost",
+            Arc::new(|config: &Configuration, _state: &AiloopAppState| {
+                let provider = crate::server::providers::MattermostProvider::from_config(
+                    &config.providers.mattermost,
+                    &ProxySettings::resolve(&config.proxy)?,
+                )?;
+                Ok(provider.map(|p| Arc::new(p) as Arc<dyn Provider>))
+            }),
+        );
+        #[cfg(feature = "pushover")]
+        registry.register(
+            "pushover",
+            Arc::new(|config: &Configuration, _state: &AiloopAppState| {
+                let sink = crate::server::providers::PushoverSink::from_config(
+                    &config.providers.pushover,
+                    &ProxySettings::resolve(&config.proxy)?,
+                )?;
+                Ok(sink.map(|sink| Arc::new(sink) as Arc<dyn Provider>))
+            }),
+        );
+        #[cfg(feature = "irc")]
+        registry.register(
+            "irc",
+            Arc::new(|config: &Configuration, _state: &AiloopAppState| {
+                let provider =
+                    crate::server::providers::IrcProvider::from_config(&config.providers.irc)?;
+                Ok(provider.map(|p| Arc::new(p) as Arc<dyn Provider>))
+            }),
+        );
+        #[cfg(feature = "signal")]
+        registry.register(
+            "signal",
+            Arc::new(|config: &Configuration, _state: &AiloopAppState| {
+                let provider = crate::server::providers::SignalProvider::from_config(
+                    &config.providers.signal,
+                    &ProxySettings::resolve(&config.proxy)?,
+                )?;
+                Ok(provider.map(|p| Arc::new(p) as Arc<dyn Provider>))
+            }),
+        );
+        registry
+    }
+
+    /// Add `factory` under `name`, replacing any factory already registered with that name.
+    pub fn register(&self, name: impl Into<String>, factory: ProviderFactory) {
+        let name = name.into();
+        let mut factories = self.factories.write().expect("provider factories lock");
+        match factories.iter_mut().find(|(n, _)| *n == name) {
+            Some(entry) => entry.1 = factory,
+            None => factories.push((name, factory)),
+        }
+    }
+
+    /// Registered provider names, in registration order.
+    pub fn names(&self) -> Vec<String> {
+        self.factories
+            .read()
+            .expect("provider factories lock")
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// A started provider by name.
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Provider>> {
+        self.active
+            .read()
+            .expect("active providers lock")
+            .iter()
+            .find(|p| p.name() == name)
+            .cloned()
+    }
+
+    /// Run every factory against `config`, then start the `[[providers.exec]]` commands and
+    /// `[[providers.webhook]]` URLs, remembering and returning the providers that started.
+    /// Failures are logged and skipped so one provider cannot block the others.
+    pub fn start(&self, config: &Configuration, state: &AiloopAppState) -> Vec<Arc<dyn Provider>> {
+        let mut started = Vec::new();
+        for (name, outcome) in self.build(config, state) {
+            match outcome {
+                Ok(Some(provider)) => started.push(provider),
+                Ok(None) => {}
+                Err(e) => tracing::error!(provider = %name, error = %e, "provider failed to start"),
+            }
+        }
+        *self.active.write().expect("active providers lock") = started.clone();
+        started
+    }
+
+    /// Every provider `config` describes, by name, without remembering them.
+    pub fn build(
+        &self,
+        config: &Configuration,
+        state: &AiloopAppState,
+    ) -> Vec<(String, BuildOutcome)> {
+        let factories = self
+            .factories
+            .read()
+            .expect("provider factories lock")
+            .clone();
+        let mut built = Vec::new();
+        for (name, factory) in factories {
+            let outcome = factory(config, state);
+            built.push((name, outcome));
+        }
+        for exec in &config.providers.exec {
+            let outcome = ExecProvider::from_config(exec)
+                .map(|p| Some(Arc::new(p) as Arc<dyn Provider>))
+                .map_err(Into::into);
+            built.push((exec.name.clone(), outcome));
+        }
+        if !config.providers.webhook.is_empty() {
+            let proxy = ProxySettings::resolve(&config.proxy);
+            for webhook in &config.providers.webhook {
+                let outcome = match &proxy {
+                    Ok(proxy) => WebhookProvider::from_config(webhook, proxy)
+                        .map(|p| Some(Arc::new(p) as Arc<dyn Provider>))
+                        .map_err(Into::into),
+                    Err(e) => Err(format!("{:#}", e).into()),
+                };
+                built.push((webhook.name.clone(), outcome));
+            }
+        }
+        built
+    }
+}
+
+impl Default for ProviderRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
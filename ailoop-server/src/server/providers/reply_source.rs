@@ -2,14 +2,27 @@
 
 use ailoop_core::models::ResponseType;
 use async_trait::async_trait;
+use uuid::Uuid;
 
 /// Inbound reply from a provider (e.g. Telegram), to be matched to a pending prompt.
 #[derive(Debug, Clone)]
 pub struct ProviderReply {
     /// Provider message id this reply refers to (reply-to); None = use oldest pending
     pub reply_to_message_id: Option<String>,
+    /// ailoop id of the prompt answered, when the reply carries it (e.g. an email subject
+    /// token); matched before `reply_to_message_id`.
+    pub prompt_id: Option<Uuid>,
     pub answer: Option<String>,
     pub response_type: ResponseType,
+    /// Provider-specific id of the user who replied, if known
+    pub sender_id: Option<String>,
+    /// Provider-specific handle of the user who replied (e.g. Telegram username), if known
+    pub sender_name: Option<String>,
+    /// Provider-specific conversation the reply was posted in (e.g. Telegram chat id), if known
+    pub chat_id: Option<String>,
+    /// ailoop channel the reply was posted in (e.g. a routed Telegram topic), if known.
+    /// Replies without a matching reply-to then only answer prompts from this channel.
+    pub channel: Option<String>,
 }
 
 /// Source of operator replies from a provider (e.g. Telegram getUpdates).
@@ -18,3 +31,78 @@ pub trait ReplySource: Send + Sync {
     /// Poll for the next reply, if any. Returns None when no reply available.
     async fn next_reply(&self) -> Option<ProviderReply>;
 }
+
+/// Splits a decision and its reason: `n: too risky` -> (`n`, Some(`too risky`)). Text without
+/// a `:` after a yes/no word has no reason.
+pub(crate) fn split_reason(text: &str) -> (&str, Option<String>) {
+    if let Some((head, reason)) = text.split_once(':') {
+        let word = head.trim().to_lowercase();
+        let reason = reason.trim();
+        if is_decision_word(&word) && !reason.is_empty() {
+            return (head.trim(), Some(reason.to_string()));
+        }
+    }
+    (text.trim(), None)
+}
+
+/// Reason carried by a reply to an authorization: the text after `yes:`/`no:`, or the whole
+/// reply when it is not a bare decision word (e.g. an answer posted through the API).
+pub(crate) fn reply_reason(answer: &str) -> Option<String> {
+    match split_reason(answer) {
+        (_, Some(reason)) => Some(reason),
+        (word, None) if word.is_empty() || is_decision_word(&word.to_lowercase()) => None,
+        (text, None) => Some(text.to_string()),
+    }
+}
+
+fn is_decision_word(word: &str) -> bool {
+    matches!(
+        word,
+        "y" | "yes" | "ok" | "authorized" | "approve" | "n" | "no" | "deny" | "denied" | "reject"
+    )
+}
+
+/// Infers response type from text: y/yes/ok -> Approved, n/no -> Denied, else Text.
+/// A reason may follow the decision after a colon (`no: too risky`).
+/// Empty string is treated as Denied for safety (changed from previous behavior).
+/// Invalid or unparseable provider reply: for authorization/navigation treated as deny (FR-010);
+/// for question the answer is used as-is (empty or error handled by caller).
+#[cfg(any(
+    feature = "telegram",
+    feature = "twilio",
+    feature = "email",
+    feature = "mattermost",
+    feature = "irc",
+    feature = "signal"
+))]
+pub(crate) fn infer_response_type(text: &str) -> ResponseType {
+    let t = split_reason(text).0.to_lowercase();
+    match t.as_str() {
+        "y" | "yes" | "ok" => ResponseType::AuthorizationApproved,
+        "n" | "no" | "deny" | "denied" | "" => ResponseType::AuthorizationDenied,
+        _ => ResponseType::Text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reasons_follow_the_decision() {
+        assert_eq!(
+            split_reason("n: touches prod"),
+            ("n", Some("touches prod".to_string()))
+        );
+        assert_eq!(split_reason("yes"), ("yes", None));
+        assert_eq!(split_reason("see: the docs"), ("see: the docs", None));
+        assert_eq!(split_reason("no:"), ("no:", None));
+
+        assert_eq!(reply_reason("No: too risky"), Some("too risky".to_string()));
+        assert_eq!(reply_reason("yes"), None);
+        assert_eq!(
+            reply_reason("wrong cluster"),
+            Some("wrong cluster".to_string())
+        );
+    }
+}
@@ -0,0 +1,515 @@
+//! Signal provider: prompts and notifications are sent through a local signal-cli REST daemon
+//! (`bbernhard/signal-cli-rest-api`), replies are read by polling its receive endpoint.
+//!
+//! A reply quoting a prompt answers that prompt; any other message answers the oldest prompt of
+//! the ailoop channel mapped to its conversation. Messages from conversations that are not
+//! configured are dropped. The daemon must run in `normal` or `native` mode: in `json-rpc` mode
+//! it hands out messages over a websocket only.
+
+use crate::server::providers::{
+    infer_response_type, is_prompt, Provider, ProviderReply, ProviderResult, ReplySource,
+};
+use ailoop_core::models::{Message, MessageContent, SignalProviderConfig};
+use ailoop_core::transport::proxy::ProxySettings;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures_util::stream::{BoxStream, StreamExt};
+use reqwest::{Client, Method, RequestBuilder};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+
+const HTTP_TIMEOUT_SECS: u64 = 30;
+
+/// The daemon's REST API, for one registered account.
+#[derive(Debug)]
+struct Api {
+    base: String,
+    number: String,
+    client: Client,
+}
+
+impl Api {
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, format!("{}/{}", self.base, path))
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> ProviderResult<T> {
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!("signal-cli API error: {} – {}", status, text).into());
+        }
+        Ok(response.json().await?)
+    }
+}
+
+/// An entry of `GET /v1/receive/{number}`; only the fields ailoop reads.
+#[derive(Debug, Deserialize)]
+struct Received {
+    envelope: Envelope,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    #[serde(default)]
+    source_number: Option<String>,
+    #[serde(default)]
+    source_uuid: Option<String>,
+    /// Profile name of the sender.
+    #[serde(default)]
+    source_name: Option<String>,
+    /// Absent for receipts, typing indicators and sync messages.
+    #[serde(default)]
+    data_message: Option<DataMessage>,
+}
+
+impl Envelope {
+    /// The conversation the message came from, in the form recipients are configured in: the
+    /// sender's number, or `group.<id>` for group messages.
+    fn conversation(&self) -> Option<String> {
+        match &self.data_message.as_ref()?.group_info {
+            Some(group) => Some(format!("group.{}", STANDARD.encode(&group.group_id))),
+            None => self.source_number.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataMessage {
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    group_info: Option<GroupInfo>,
+    #[serde(default)]
+    quote: Option<Quote>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupInfo {
+    /// signal-cli's group id; the REST API's `group.<id>` encodes it once more in base64.
+    group_id: String,
+}
+
+/// The message a reply quotes, identified by the timestamp it was sent at.
+#[derive(Debug, Deserialize)]
+struct Quote {
+    id: i64,
+}
+
+/// Answer of `POST /v2/send`.
+#[derive(Debug, Deserialize)]
+struct Sent {
+    /// Send timestamp, the id quotes refer to; a string in current daemons.
+    timestamp: serde_json::Value,
+}
+
+/// Reads new messages from the daemon for the configured conversations.
+pub struct SignalReplySource {
+    api: Arc<Api>,
+    /// Conversation, and the ailoop channel its unquoted messages answer (None when it serves
+    /// several or unmapped ones).
+    conversations: Vec<(String, Option<String>)>,
+    poll_interval: Duration,
+    /// Replies read but not yet handed out.
+    pending: tokio::sync::Mutex<VecDeque<ProviderReply>>,
+}
+
+impl SignalReplySource {
+    fn new(api: Arc<Api>, config: &SignalProviderConfig) -> Self {
+        Self {
+            api,
+            conversations: conversations(config),
+            poll_interval: Duration::from_secs(config.poll_interval_seconds()),
+            pending: tokio::sync::Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Read the messages waiting at the daemon into `pending`.
+    async fn poll(&self, pending: &mut VecDeque<ProviderReply>) {
+        let request = self
+            .api
+            .request(Method::GET, &format!("v1/receive/{}", self.api.number));
+        let received: Vec<Received> = match self.api.send(request).await {
+            Ok(received) => received,
+            Err(e) => {
+                tracing::warn!(provider = "signal", error = %e, "Polling failed");
+                return;
+            }
+        };
+        pending.extend(
+            received
+                .into_iter()
+                .filter_map(|r| reply_from_envelope(r.envelope, &self.conversations)),
+        );
+    }
+}
+
+#[async_trait]
+impl ReplySource for SignalReplySource {
+    async fn next_reply(&self) -> Option<ProviderReply> {
+        let mut pending = self.pending.lock().await;
+        if let Some(reply) = pending.pop_front() {
+            return Some(reply);
+        }
+        tokio::time::sleep(self.poll_interval).await;
+        self.poll(&mut pending).await;
+        pending.pop_front()
+    }
+}
+
+/// Configured conversations, each with the ailoop channel its unquoted messages answer.
+fn conversations(config: &SignalProviderConfig) -> Vec<(String, Option<String>)> {
+    let mut conversations: Vec<(String, Option<String>)> = Vec::new();
+    let mut mapped: Vec<(&String, &String)> = config.channel_map.iter().collect();
+    mapped.sort();
+    for (channel, target) in mapped {
+        match conversations.iter_mut().find(|(id, _)| id == target) {
+            Some((_, served)) => *served = None,
+            None => conversations.push((target.clone(), Some(channel.clone()))),
+        }
+    }
+    if let Some(default) = &config.recipient {
+        match conversations.iter_mut().find(|(id, _)| id == default) {
+            Some((_, served)) => *served = None,
+            None => conversations.push((default.clone(), None)),
+        }
+    }
+    conversations
+}
+
+/// The reply a received message gives, if it is a text message in a configured conversation.
+/// When it quotes a message, it answers the quoted one.
+fn reply_from_envelope(
+    envelope: Envelope,
+    conversations: &[(String, Option<String>)],
+) -> Option<ProviderReply> {
+    let conversation = envelope.conversation()?;
+    let Some((_, channel)) = conversations.iter().find(|(id, _)| *id == conversation) else {
+        tracing::debug!(
+            provider = "signal",
+            conversation = %conversation,
+            "Ignoring message from an unconfigured conversation"
+        );
+        return None;
+    };
+    let data = envelope.data_message?;
+    let answer = data.message.as_deref().map(str::trim).unwrap_or_default();
+    if answer.is_empty() {
+        return None;
+    }
+    Some(ProviderReply {
+        reply_to_message_id: data.quote.map(|q| q.id.to_string()),
+        prompt_id: None,
+        answer: Some(answer.to_string()),
+        response_type: infer_response_type(answer),
+        sender_id: envelope.source_number.or(envelope.source_uuid),
+        sender_name: envelope.source_name,
+        chat_id: Some(conversation),
+        channel: channel.clone(),
+    })
+}
+
+/// Signal provider: sends to the mapped conversations and reads replies from them.
+pub struct SignalProvider {
+    api: Arc<Api>,
+    config: SignalProviderConfig,
+    source: Arc<SignalReplySource>,
+}
+
+impl SignalProvider {
+    /// Build from `[providers.signal]`.
+    pub fn new(
+        config: &SignalProviderConfig,
+        proxy: &ProxySettings,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let Some(number) = config.number.as_deref().filter(|n| !n.is_empty()) else {
+            return Err("providers.signal: number is required".into());
+        };
+        let client = proxy
+            .apply(Client::builder())?
+            .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+            .build()?;
+        let api = Arc::new(Api {
+            base: config.url().trim_end_matches('/').to_string(),
+            number: number.to_string(),
+            client,
+        });
+        Ok(Self {
+            source: Arc::new(SignalReplySource::new(Arc::clone(&api), config)),
+            api,
+            config: config.clone(),
+        })
+    }
+
+    /// Build from `[providers.signal]`. Returns `Ok(None)` when the provider is disabled.
+    pub fn from_config(
+        config: &SignalProviderConfig,
+        proxy: &ProxySettings,
+    ) -> Result<Option<Self>, Box<dyn Error + Send + Sync>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        Self::new(config, proxy).map(Some)
+    }
+
+    /// Send `message` to its channel's recipient; returns the send timestamp.
+    async fn send_to(&self, message: &Message) -> ProviderResult<Option<String>> {
+        let Some(recipient) = self.config.target(&message.channel) else {
+            return Err(format!("no Signal recipient for '{}'", message.channel).into());
+        };
+        let request = self
+            .api
+            .request(Method::POST, "v2/send")
+            .json(&serde_json::json!({
+                "message": format_text(message),
+                "number": self.api.number,
+                "recipients": [recipient],
+            }));
+        let sent: Sent = self.api.send(request).await?;
+        Ok(match sent.timestamp {
+            serde_json::Value::String(timestamp) => Some(timestamp),
+            serde_json::Value::Number(timestamp) => Some(timestamp.to_string()),
+            _ => None,
+        })
+    }
+}
+
+/// Plain text of the Signal message for `message`. Prompts end with how to answer.
+fn format_text(message: &Message) -> String {
+    let channel = &message.channel;
+    let mut text = String::new();
+    if let Some(heading) = message.agent_heading() {
+        let _ = writeln!(text, "{}\n", heading);
+    }
+    let hint = match &message.content {
+        MessageContent::Decision {
+            summary,
+            context_markdown,
+            options,
+            recommendation,
+            ..
+        } => {
+            let _ = writeln!(text, "Decision [{}]: {}\n", channel, summary);
+            if let Some(context) = context_markdown {
+                let _ = writeln!(text, "{}\n", context.trim());
+            }
+            for (i, option) in options.iter().enumerate() {
+                let recommended = recommendation
+                    .as_ref()
+                    .is_some_and(|r| r.option_id == option.id);
+                let _ = writeln!(
+                    text,
+                    "{}. {}{}",
+                    i + 1,
+                    option.label,
+                    if recommended { " (recommended)" } else { "" }
+                );
+                if let Some(detail) = &option.detail_markdown {
+                    let _ = writeln!(text, "   {}", detail.trim().replace('\n', "\n   "));
+                }
+            }
+            text.push('\n');
+            Some("Reply to this message with the number of your choice, e.g. 1.")
+        }
+        MessageContent::Authorization {
+            action, context, ..
+        } => {
+            let _ = writeln!(text, "Authorization [{}]: {}\n", channel, action);
+            if let Some(context) = context {
+                let context = serde_json::to_string_pretty(context).unwrap_or_default();
+                let _ = writeln!(text, "{}\n", context);
+            }
+            Some("Reply to this message with yes to approve or no: <reason> to deny.")
+        }
+        MessageContent::Navigate { url, .. } => {
+            let _ = writeln!(text, "Navigation [{}]: {}\n", channel, url);
+            Some("Reply to this message with yes once it is open or no to decline.")
+        }
+        MessageContent::Notification { text: body, .. } => {
+            let _ = writeln!(text, "Notification [{}]: {}\n", channel, body.trim());
+            None
+        }
+        _ => {
+            let _ = writeln!(text, "Message [{}]\n", channel);
+            None
+        }
+    };
+    if let Some(summary) = message.thread_summary() {
+        let _ = writeln!(text, "{}\n", summary);
+    }
+    if let Some(hint) = hint {
+        let _ = writeln!(text, "{}", hint);
+    }
+    text.trim_end().to_string()
+}
+
+#[async_trait]
+impl Provider for SignalProvider {
+    fn name(&self) -> &str {
+        "signal"
+    }
+
+    /// Prompts and notifications of channels with a Signal recipient.
+    fn handles(&self, message: &Message) -> bool {
+        self.config.target(&message.channel).is_some()
+            && (is_prompt(&message.content)
+                || matches!(message.content, MessageContent::Notification { .. }))
+    }
+
+    /// Check that the daemon is up and knows the account.
+    async fn init(&self) -> ProviderResult<()> {
+        let accounts: Vec<String> = self
+            .api
+            .send(self.api.request(Method::GET, "v1/accounts"))
+            .await?;
+        if !accounts.contains(&self.api.number) {
+            return Err(format!("{} is not registered with signal-cli", self.api.number).into());
+        }
+        Ok(())
+    }
+
+    async fn send_prompt(&self, message: &Message) -> ProviderResult<Option<String>> {
+        self.send_to(message).await
+    }
+
+    async fn send_notification(&self, message: &Message) -> ProviderResult<()> {
+        self.send_to(message).await.map(|_| ())
+    }
+
+    /// Messages from users allowed by `responders`; others are dropped.
+    fn reply_stream(&self) -> Option<BoxStream<'static, ProviderReply>> {
+        let config = self.config.clone();
+        let replies = futures_util::stream::unfold(Arc::clone(&self.source), |source| async move {
+            loop {
+                if let Some(reply) = source.next_reply().await {
+                    return Some((reply, source));
+                }
+            }
+        })
+        .filter(move |reply| {
+            let accepted =
+                config.is_responder(reply.sender_id.as_deref(), reply.sender_name.as_deref());
+            if !accepted {
+                tracing::warn!(
+                    "Ignoring Signal message from non-responder {:?}",
+                    reply.sender_name.as_ref().or(reply.sender_id.as_ref())
+                );
+            }
+            futures_util::future::ready(accepted)
+        });
+        Some(replies.boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{ResponseType, SenderType};
+    use std::collections::HashMap;
+
+    fn config() -> SignalProviderConfig {
+        SignalProviderConfig {
+            enabled: true,
+            url: Some("http://127.0.0.1:8080/".to_string()),
+            number: Some("+15550100".to_string()),
+            recipient: Some("+15550199".to_string()),
+            channel_map: HashMap::from([
+                ("deploys".to_string(), "group.b3Bz".to_string()),
+                ("ci".to_string(), "group.YnVpbGRz".to_string()),
+                ("nightly".to_string(), "group.YnVpbGRz".to_string()),
+            ]),
+            ..SignalProviderConfig::default()
+        }
+    }
+
+    fn authorization(channel: &str) -> Message {
+        Message::new(
+            channel.to_string(),
+            SenderType::Agent,
+            MessageContent::Authorization {
+                action: "deploy v2".to_string(),
+                context: None,
+                timeout_seconds: 60,
+            },
+        )
+    }
+
+    #[test]
+    fn test_channels_route_to_their_conversation() {
+        let provider = SignalProvider::new(&config(), &ProxySettings::default()).unwrap();
+        assert_eq!(provider.api.base, "http://127.0.0.1:8080");
+        assert!(provider.handles(&authorization("deploys")));
+        assert!(provider.handles(&authorization("public")));
+
+        let mut mapped_only = config();
+        mapped_only.recipient = None;
+        let provider = SignalProvider::new(&mapped_only, &ProxySettings::default()).unwrap();
+        assert!(!provider.handles(&authorization("public")));
+
+        assert_eq!(
+            conversations(&config()),
+            vec![
+                ("group.YnVpbGRz".to_string(), None),
+                ("group.b3Bz".to_string(), Some("deploys".to_string())),
+                ("+15550199".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_authorization_text_says_how_to_answer() {
+        assert_eq!(
+            format_text(&authorization("deploys")),
+            "Authorization [deploys]: deploy v2\n\n\
+             Reply to this message with yes to approve or no: <reason> to deny."
+        );
+    }
+
+    #[test]
+    fn test_received_messages_become_replies() {
+        let received: Vec<Received> = serde_json::from_str(
+            r#"[
+                {"envelope": {"sourceNumber": "+15550111", "sourceName": "Alice",
+                    "dataMessage": {"message": " no: too risky ", "groupInfo": {"groupId": "ops"},
+                        "quote": {"id": 1700000000000}}}},
+                {"envelope": {"sourceNumber": "+15550199",
+                    "dataMessage": {"message": "yes"}}},
+                {"envelope": {"sourceNumber": "+15550222",
+                    "dataMessage": {"message": "yes"}}},
+                {"envelope": {"sourceNumber": "+15550199", "typingMessage": {}}}
+            ]"#,
+        )
+        .unwrap();
+        let replies: Vec<ProviderReply> = received
+            .into_iter()
+            .filter_map(|r| reply_from_envelope(r.envelope, &conversations(&config())))
+            .collect();
+        assert_eq!(replies.len(), 2);
+
+        let quoted = &replies[0];
+        assert_eq!(quoted.reply_to_message_id.as_deref(), Some("1700000000000"));
+        assert_eq!(quoted.answer.as_deref(), Some("no: too risky"));
+        assert_eq!(quoted.response_type, ResponseType::AuthorizationDenied);
+        assert_eq!(quoted.sender_name.as_deref(), Some("Alice"));
+        assert_eq!(quoted.chat_id.as_deref(), Some("group.b3Bz"));
+        assert_eq!(quoted.channel.as_deref(), Some("deploys"));
+
+        let direct = &replies[1];
+        assert_eq!(direct.reply_to_message_id, None);
+        assert_eq!(direct.sender_id.as_deref(), Some("+15550199"));
+        assert_eq!(direct.response_type, ResponseType::AuthorizationApproved);
+        assert_eq!(direct.channel, None);
+    }
+}
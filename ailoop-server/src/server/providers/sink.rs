@@ -10,6 +10,12 @@ pub trait NotificationSink: Send + Sync {
     /// Provider name for logging (e.g. "telegram").
     fn name(&self) -> &str;
 
+    /// Whether this sink takes `message` at all (e.g. only opted-in channels). Messages it does
+    /// not take are reported as skipped rather than delivered.
+    fn handles(&self, _message: &Message) -> bool {
+        true
+    }
+
     /// Send a message to the provider. Failures must not block other delivery paths.
     async fn send(&self, message: &Message) -> Result<(), Box<dyn Error + Send + Sync>>;
 
@@ -23,4 +29,10 @@ pub trait NotificationSink: Send + Sync {
         self.send(message).await?;
         Ok(None)
     }
+
+    /// Show a status pulse as a transient indicator (e.g. a typing action). Sinks without one
+    /// ignore it.
+    async fn send_status(&self, _message: &Message) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Ok(())
+    }
 }
@@ -1,32 +1,161 @@
 //! Telegram communication provider: send messages via Bot API and receive replies via getUpdates.
 
-use crate::server::providers::{NotificationSink, ProviderReply, ReplySource};
-use ailoop_core::models::{Message, MessageContent, ResponseType};
+use crate::server::attachments::{AttachmentStore, MessageAttachment};
+use crate::server::history::is_agent_notification;
+use crate::server::providers::{
+    infer_response_type, is_prompt, NotificationSink, Provider, ProviderReply, ProviderResult,
+    ReplySource,
+};
+use ailoop_core::channel::pattern::matches_pattern;
+use ailoop_core::models::{
+    Message, MessageContent, ResponseType, TelegramChannelTarget, TelegramChatConfig,
+    TelegramChatRole, TelegramProviderConfig, TelegramUpdatesMode,
+};
+use ailoop_core::transport::proxy::ProxySettings;
 use async_trait::async_trait;
+use futures_util::stream::{BoxStream, StreamExt};
 use reqwest::{Client, StatusCode};
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicI64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use subtle::ConstantTimeEq;
+use tokio::sync::mpsc;
 use tokio::time::sleep;
 
 const TELEGRAM_API_BASE: &str = "https://api.telegram.org/bot";
 const LONG_POLL_TIMEOUT_SECS: u64 = 30;
 const TELEGRAM_MAX_MESSAGE_LENGTH: usize = 4096;
+const TELEGRAM_MAX_CAPTION_LENGTH: usize = 1024;
+/// Largest photo the Bot API accepts as an upload.
+const TELEGRAM_MAX_PHOTO_BYTES: usize = 10 * 1024 * 1024;
 const SEND_RETRY_ATTEMPTS: u32 = 3;
 const SEND_RETRY_BASE_DELAY_MS: u64 = 1000;
 const GETUPDATES_BACKOFF_BASE_SECS: u64 = 5;
 const GETUPDATES_BACKOFF_MAX_SECS: u64 = 60;
 const HTTP_TIMEOUT_SECS: u64 = 30;
+/// Webhook updates buffered before the reply loop picks them up; Telegram retries when full.
+const WEBHOOK_QUEUE_CAPACITY: usize = 256;
+/// Reply aliases kept for prompt copies in extra responder chats (oldest evicted first).
+const MAX_REPLY_ALIASES: usize = 1024;
+/// Unresolved prompts whose messages are remembered for outcome edits (oldest evicted first).
+const MAX_SENT_PROMPTS: usize = 1024;
+/// `callback_data` of a *Mark read* button, followed by the notification id.
+const READ_CALLBACK_PREFIX: &str = "read:";
+/// Update types the bot asks Telegram for.
+const ALLOWED_UPDATES: &str = r#"["message","callback_query"]"#;
 
 /// Telegram notification sink (sendMessage). Token and chat_id from config/env.
 #[derive(Debug)]
 pub struct TelegramSink {
     token: String,
+    /// Bot API URL the token is appended to; [`TELEGRAM_API_BASE`] outside tests.
+    api_base: String,
     chat_id: String,
+    /// Per-channel chat/topic overrides; see `providers.telegram.channel_map`.
+    channel_map: HashMap<String, TelegramChannelTarget>,
+    /// Extra chats receiving copies; see `providers.telegram.chats`.
+    chats: Vec<TelegramChatConfig>,
+    aliases: Arc<ReplyAliases>,
+    /// Where image attachments are read from to upload them as photos.
+    attachments: Option<Arc<AttachmentStore>>,
+    /// Channel patterns whose prompts are pinned; see `providers.telegram.pin_prompts`.
+    pin_prompts: Vec<String>,
+    /// Edit prompts to show their outcome instead of sending it as a new message; see
+    /// `providers.telegram.edit_outcomes`.
+    edit_outcomes: bool,
+    /// Put a *Mark read* button under agent notifications; see
+    /// `providers.telegram.read_buttons`.
+    read_buttons: bool,
+    /// Messages of prompts not yet resolved, for editing in their outcome.
+    sent_prompts: SentPrompts,
     client: Arc<Client>,
 }
 
+/// An image sent with sendPhoto: uploaded from the attachment store, or fetched by Telegram.
+enum Photo {
+    Upload {
+        name: String,
+        content_type: String,
+        bytes: Vec<u8>,
+    },
+    Link(String),
+}
+
+/// Provider reply-to id for a sent message. Telegram message ids are only unique per chat.
+fn reply_key(chat_id: &str, message_id: i64) -> String {
+    format!("{}:{}", chat_id, message_id)
+}
+
+/// Split a reply-to id from [`reply_key`] back into chat id and message id.
+fn parse_reply_key(key: &str) -> ProviderResult<(&str, i64)> {
+    key.rsplit_once(':')
+        .and_then(|(chat, id)| Some((chat, id.parse().ok()?)))
+        .ok_or_else(|| format!("invalid Telegram reply-to id '{}'", key).into())
+}
+
+/// Maps reply-to ids of prompt copies in extra responder chats to the primary message's id, so
+/// a reply to any copy matches the pending prompt. Shared by the sink and the reply source.
+#[derive(Debug, Default)]
+pub struct ReplyAliases {
+    inner: Mutex<(HashMap<String, String>, VecDeque<String>)>,
+}
+
+impl ReplyAliases {
+    fn insert(&self, alias: String, primary: String) {
+        let mut guard = self.inner.lock().expect("reply aliases lock");
+        let (map, order) = &mut *guard;
+        if order.len() >= MAX_REPLY_ALIASES {
+            if let Some(oldest) = order.pop_front() {
+                map.remove(&oldest);
+            }
+        }
+        order.push_back(alias.clone());
+        map.insert(alias, primary);
+    }
+
+    /// The primary reply-to id for `key` (itself when it is not an alias).
+    fn resolve(&self, key: String) -> String {
+        let guard = self.inner.lock().expect("reply aliases lock");
+        guard.0.get(&key).cloned().unwrap_or(key)
+    }
+}
+
+/// The Telegram messages a prompt was sent as: its own and the copies in extra chats, each
+/// with the text it was sent with.
+#[derive(Debug, Default)]
+struct SentPrompt {
+    messages: Vec<(String, String)>,
+    pinned: bool,
+}
+
+/// Prompts sent and not yet resolved, by prompt id.
+#[derive(Debug, Default)]
+struct SentPrompts {
+    inner: Mutex<(HashMap<uuid::Uuid, SentPrompt>, VecDeque<uuid::Uuid>)>,
+}
+
+impl SentPrompts {
+    fn insert(&self, prompt_id: uuid::Uuid, prompt: SentPrompt) {
+        let mut guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let (map, order) = &mut *guard;
+        if order.len() >= MAX_SENT_PROMPTS {
+            if let Some(oldest) = order.pop_front() {
+                map.remove(&oldest);
+            }
+        }
+        order.push_back(prompt_id);
+        map.insert(prompt_id, prompt);
+    }
+
+    fn take(&self, prompt_id: uuid::Uuid) -> Option<SentPrompt> {
+        let mut guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        guard.0.remove(&prompt_id)
+    }
+}
+
 /// Response from Telegram sendMessage API
 #[derive(serde::Deserialize, Debug)]
 struct SendMessageResponse {
@@ -74,11 +203,111 @@ impl TelegramSink {
 
         Ok(Self {
             token,
+            api_base: TELEGRAM_API_BASE.to_string(),
             chat_id,
+            channel_map: HashMap::new(),
+            chats: Vec::new(),
+            aliases: Arc::new(ReplyAliases::default()),
+            attachments: None,
+            pin_prompts: Vec::new(),
+            edit_outcomes: true,
+            read_buttons: true,
+            sent_prompts: SentPrompts::default(),
             client,
         })
     }
 
+    /// Send Bot API requests through the proxy `proxy` selects for api.telegram.org.
+    pub fn with_proxy(
+        mut self,
+        proxy: &ProxySettings,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        self.client = Arc::new(
+            proxy
+                .apply(Client::builder())?
+                .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+                .build()?,
+        );
+        Ok(self)
+    }
+
+    /// Also deliver every message to `chats`. Watch-only chats see prompts marked read-only.
+    pub fn with_chats(mut self, chats: Vec<TelegramChatConfig>) -> Self {
+        self.chats = chats;
+        self
+    }
+
+    /// Upload image attachments kept in `store` as photos instead of linking them.
+    pub fn with_attachments(mut self, store: Arc<AttachmentStore>) -> Self {
+        self.attachments = Some(store);
+        self
+    }
+
+    /// Pin prompts on channels matching `patterns` until they are answered.
+    pub fn with_pinned_prompts(mut self, patterns: Vec<String>) -> Self {
+        self.pin_prompts = patterns;
+        self
+    }
+
+    /// Send outcomes as new messages instead of editing them into the prompts.
+    pub fn with_edit_outcomes(mut self, edit_outcomes: bool) -> Self {
+        self.edit_outcomes = edit_outcomes;
+        self
+    }
+
+    /// Leave agent notifications without a *Mark read* button.
+    pub fn with_read_buttons(mut self, read_buttons: bool) -> Self {
+        self.read_buttons = read_buttons;
+        self
+    }
+
+    /// The *Mark read* button for `message`, when it is an agent notification. Pressing it
+    /// sends `read:<id>`, which the reply source turns into `/read <id>`.
+    fn read_button(&self, message: &Message) -> Option<serde_json::Value> {
+        (self.read_buttons && is_agent_notification(message)).then(|| {
+            serde_json::json!({
+                "inline_keyboard": [[{
+                    "text": "✓ Mark read",
+                    "callback_data": format!("{}{}", READ_CALLBACK_PREFIX, message.id),
+                }]]
+            })
+        })
+    }
+
+    #[cfg(test)]
+    fn with_api_base(mut self, api_base: String) -> Self {
+        self.api_base = api_base;
+        self
+    }
+
+    fn pins_prompts(&self, channel: &str) -> bool {
+        self.pin_prompts
+            .iter()
+            .any(|pattern| matches_pattern(pattern, channel))
+    }
+
+    /// Alias table to hand to the [`TelegramReplySource`].
+    pub fn reply_aliases(&self) -> Arc<ReplyAliases> {
+        Arc::clone(&self.aliases)
+    }
+
+    /// Route channels to their own chats or forum topics.
+    pub fn with_channel_map(mut self, channel_map: HashMap<String, TelegramChannelTarget>) -> Self {
+        self.channel_map = channel_map;
+        self
+    }
+
+    /// Chat and optional topic that messages for `channel` are sent to.
+    fn target_for(&self, channel: &str) -> (&str, Option<i64>) {
+        match self.channel_map.get(channel) {
+            Some(target) => (
+                target.chat_id.as_deref().unwrap_or(&self.chat_id),
+                target.topic_id,
+            ),
+            None => (&self.chat_id, None),
+        }
+    }
+
     fn format_message(message: &Message) -> String {
         let channel = &message.channel;
         let content = match &message.content {
@@ -97,7 +326,15 @@ impl TelegramSink {
             MessageContent::Notification { text, .. } => {
                 format!("Notification [{}]: {}", channel, text)
             }
-            MessageContent::Navigate { url } => format!("Navigation [{}]: {}", channel, url),
+            MessageContent::Navigate { url, .. } => format!("Navigation [{}]: {}", channel, url),
+            MessageContent::Chat { text, closed, .. } => {
+                let mut line = format!("Chat [{}] {}: {}", channel, message.speaker(), text);
+                if *closed {
+                    line.push_str("\n(conversation closed)");
+                }
+                line
+            }
+            MessageContent::Status { text } => format!("Status [{}]: {}", channel, text),
             MessageContent::Response {
                 answer,
                 response_type,
@@ -141,6 +378,19 @@ impl TelegramSink {
             }
         };
 
+        let content = match message.thread_summary() {
+            Some(thread) => format!("{}\n\n{}", thread, content),
+            None => content,
+        };
+        let content = match message.agent_heading() {
+            Some(heading) => format!("{}\n{}", heading, content),
+            None => content,
+        };
+        let content = match MessageAttachment::of(message) {
+            Some(attachment) => format!("{}\n📎 {}: {}", content, attachment.name, attachment.url),
+            None => content,
+        };
+
         // Truncate if exceeds Telegram limit
         Self::truncate_message(&content)
     }
@@ -154,23 +404,209 @@ impl TelegramSink {
         }
     }
 
-    /// Send a message to Telegram with retry logic and return the message_id on success.
+    /// Send `message` to its channel's chat, then copies to the extra chats. Returns the primary
+    /// reply-to id; replies to copies in responder chats resolve to it via [`ReplyAliases`].
+    ///
+    /// The response to a prompt sent here is edited into the prompt's messages instead, when
+    /// that succeeds.
+    async fn deliver(
+        &self,
+        message: &Message,
+    ) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        if let (Some(prompt_id), MessageContent::Response { .. }) =
+            (message.correlation_id, &message.content)
+        {
+            if self.resolve_prompt(prompt_id, &outcome(message)).await {
+                return Ok(None);
+            }
+        }
+
+        let text = Self::format_message(message);
+        let photo = self.photo_for(message).await;
+        let markup = self.read_button(message);
+        let (chat_id, topic_id) = self.target_for(&message.channel);
+        let primary = self
+            .send_text_or_photo(chat_id, topic_id, &text, photo.as_ref(), markup.as_ref())
+            .await?;
+
+        let is_prompt = is_prompt(&message.content);
+        let mut sent = SentPrompt::default();
+        if let Some(key) = primary.clone().filter(|_| is_prompt) {
+            if self.pins_prompts(&message.channel) {
+                sent.pinned = self.pin_prompt(message.id, &key).await;
+            }
+            sent.messages.push((key, text.clone()));
+        }
+        for chat in self.chats.iter().filter(|c| c.chat_id != chat_id) {
+            let copy = match chat.role {
+                TelegramChatRole::Watch if is_prompt => {
+                    Self::truncate_message(&format!("[read-only] {}", text))
+                }
+                _ => text.clone(),
+            };
+            match self
+                .send_text_or_photo(&chat.chat_id, None, &copy, photo.as_ref(), markup.as_ref())
+                .await
+            {
+                Ok(Some(key)) => {
+                    if let Some(primary) = primary
+                        .as_ref()
+                        .filter(|_| chat.role == TelegramChatRole::Responder)
+                    {
+                        self.aliases.insert(key.clone(), primary.clone());
+                    }
+                    if !sent.messages.is_empty() {
+                        sent.messages.push((key, copy));
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Telegram copy to chat {} failed: {}", chat.chat_id, e),
+            }
+        }
+        if !sent.messages.is_empty() && (self.edit_outcomes || sent.pinned) {
+            self.sent_prompts.insert(message.id, sent);
+        }
+        Ok(primary)
+    }
+
+    /// The photo to send for a message with an image attachment: the stored file when this
+    /// server has it, else its URL. SVG is not a photo to Telegram and stays a link.
+    async fn photo_for(&self, message: &Message) -> Option<Photo> {
+        let attachment = MessageAttachment::of(message)?;
+        let content_type = attachment
+            .image_type()
+            .filter(|t| *t != "image/svg+xml")?
+            .to_string();
+        if let Some(store) = &self.attachments {
+            if let Some(bytes) = attachment
+                .read(store)
+                .await
+                .filter(|b| b.len() <= TELEGRAM_MAX_PHOTO_BYTES)
+            {
+                return Some(Photo::Upload {
+                    name: attachment.name,
+                    content_type,
+                    bytes,
+                });
+            }
+        }
+        attachment
+            .url
+            .starts_with("https://")
+            .then_some(Photo::Link(attachment.url))
+    }
+
+    /// Send `photo` with `text` as its caption when there is one, falling back to the text
+    /// (which links the attachment) when Telegram refuses the photo. `markup` is an inline
+    /// keyboard to show under the message.
+    async fn send_text_or_photo(
+        &self,
+        chat_id: &str,
+        topic_id: Option<i64>,
+        text: &str,
+        photo: Option<&Photo>,
+        markup: Option<&serde_json::Value>,
+    ) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        if let Some(photo) = photo {
+            match self
+                .send_photo(chat_id, topic_id, text, photo, markup)
+                .await
+            {
+                Ok(message_id) => return Ok(Some(reply_key(chat_id, message_id))),
+                Err(e) => tracing::warn!("Telegram sendPhoto failed, sending a link: {}", e),
+            }
+        }
+        self.send_message_with_retry(chat_id, topic_id, text, markup)
+            .await
+    }
+
+    /// Send a photo with a caption (no retry).
+    async fn send_photo(
+        &self,
+        chat_id: &str,
+        topic_id: Option<i64>,
+        text: &str,
+        photo: &Photo,
+        markup: Option<&serde_json::Value>,
+    ) -> Result<i64, Box<dyn Error + Send + Sync>> {
+        let url = format!("{}{}/sendPhoto", self.api_base, self.token);
+        let caption = Self::truncate_caption(text);
+        match photo {
+            Photo::Link(link) => {
+                let mut body = serde_json::json!({
+                    "chat_id": chat_id,
+                    "photo": link,
+                    "caption": caption,
+                });
+                if let Some(topic_id) = topic_id {
+                    body["message_thread_id"] = serde_json::json!(topic_id);
+                }
+                if let Some(markup) = markup {
+                    body["reply_markup"] = markup.clone();
+                }
+                self.try_send_message(&url, &body).await
+            }
+            Photo::Upload {
+                name,
+                content_type,
+                bytes,
+            } => {
+                let part = reqwest::multipart::Part::bytes(bytes.clone())
+                    .file_name(name.clone())
+                    .mime_str(content_type)?;
+                let mut form = reqwest::multipart::Form::new()
+                    .text("chat_id", chat_id.to_string())
+                    .text("caption", caption)
+                    .part("photo", part);
+                if let Some(topic_id) = topic_id {
+                    form = form.text("message_thread_id", topic_id.to_string());
+                }
+                if let Some(markup) = markup {
+                    form = form.text("reply_markup", markup.to_string());
+                }
+                let res = self.client.post(&url).multipart(form).send().await?;
+                Self::read_sent_message(res, chat_id).await
+            }
+        }
+    }
+
+    /// Truncate a photo caption to Telegram's 1024 character limit
+    fn truncate_caption(text: &str) -> String {
+        if text.chars().count() <= TELEGRAM_MAX_CAPTION_LENGTH {
+            text.to_string()
+        } else {
+            let cut: String = text.chars().take(TELEGRAM_MAX_CAPTION_LENGTH - 3).collect();
+            format!("{}...", cut)
+        }
+    }
+
+    /// Send text to a chat (and optional forum topic) with retry logic and return its reply-to
+    /// id on success.
     async fn send_message_with_retry(
         &self,
+        chat_id: &str,
+        topic_id: Option<i64>,
         text: &str,
+        markup: Option<&serde_json::Value>,
     ) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
-        let url = format!("{}{}/sendMessage", TELEGRAM_API_BASE, self.token);
+        let url = format!("{}{}/sendMessage", self.api_base, self.token);
 
-        let body = serde_json::json!({
-            "chat_id": self.chat_id,
+        let mut body = serde_json::json!({
+            "chat_id": chat_id,
             "text": text,
         });
+        if let Some(topic_id) = topic_id {
+            body["message_thread_id"] = serde_json::json!(topic_id);
+        }
+        if let Some(markup) = markup {
+            body["reply_markup"] = markup.clone();
+        }
 
         let mut last_error: Option<Box<dyn Error + Send + Sync>> = None;
 
         for attempt in 0..SEND_RETRY_ATTEMPTS {
             match self.try_send_message(&url, &body).await {
-                Ok(message_id) => return Ok(Some(message_id)),
+                Ok(message_id) => return Ok(Some(reply_key(chat_id, message_id))),
                 Err(e) => {
                     // Check if error is retryable
                     if Self::is_retryable_error(&*e) && attempt < SEND_RETRY_ATTEMPTS - 1 {
@@ -199,8 +635,16 @@ impl TelegramSink {
         &self,
         url: &str,
         body: &serde_json::Value,
-    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+    ) -> Result<i64, Box<dyn Error + Send + Sync>> {
         let res = self.client.post(url).json(body).send().await?;
+        Self::read_sent_message(res, body["chat_id"].as_str().unwrap_or_default()).await
+    }
+
+    /// The message_id of a sendMessage or sendPhoto response, or the API error.
+    async fn read_sent_message(
+        res: reqwest::Response,
+        chat_id: &str,
+    ) -> Result<i64, Box<dyn Error + Send + Sync>> {
         let status = res.status();
         let response_text = res.text().await?;
 
@@ -222,7 +666,7 @@ impl TelegramSink {
                 tracing::error!(
                     "Telegram chat not found: ensure the user has started the bot and chat_id is correct"
                 );
-                return Err(format!("Telegram chat not found ({}): verify chat_id '{}' is correct and user has started the bot", status, self.chat_id).into());
+                return Err(format!("Telegram chat not found ({}): verify chat_id '{}' is correct and user has started the bot", status, chat_id).into());
             }
 
             return Err(format!("Telegram API error {}: {}", status, description).into());
@@ -230,11 +674,116 @@ impl TelegramSink {
 
         // Extract message_id from successful response
         match response.result {
-            Some(result) => Ok(result.message_id.to_string()),
+            Some(result) => Ok(result.message_id),
             None => Err("Telegram API returned ok=true but no result".into()),
         }
     }
 
+    /// Show "typing…" in the channel's chat for a status pulse. Telegram clears it after a few
+    /// seconds or when the next message arrives.
+    async fn send_chat_action(&self, message: &Message) -> ProviderResult<()> {
+        let (chat_id, topic_id) = self.target_for(&message.channel);
+        let mut body = serde_json::json!({ "chat_id": chat_id, "action": "typing" });
+        if let Some(topic_id) = topic_id {
+            body["message_thread_id"] = serde_json::json!(topic_id);
+        }
+        self.call_api("sendChatAction", body).await
+    }
+
+    /// Replace the text of the message behind `reply_to_id` (`<chat_id>:<message_id>`).
+    async fn edit_message(&self, reply_to_id: &str, text: &str) -> ProviderResult<()> {
+        let (chat_id, message_id) = parse_reply_key(reply_to_id)?;
+        self.call_api(
+            "editMessageText",
+            serde_json::json!({
+                "chat_id": chat_id,
+                "message_id": message_id,
+                "text": Self::truncate_message(text),
+            }),
+        )
+        .await
+    }
+
+    /// Pin the prompt sent as `reply_to_id` without notifying the chat. Returns whether it was
+    /// pinned.
+    async fn pin_prompt(&self, prompt_id: uuid::Uuid, reply_to_id: &str) -> bool {
+        let Ok((chat_id, message_id)) = parse_reply_key(reply_to_id) else {
+            return false;
+        };
+        let body = serde_json::json!({
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "disable_notification": true,
+        });
+        match self.call_api("pinChatMessage", body).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!(
+                    "Telegram could not pin prompt {} (may the bot pin messages?): {}",
+                    prompt_id,
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    /// Unpin `prompt_id` if it was pinned and add `outcome` to the text of its messages.
+    /// Returns whether the outcome was edited into the prompt and needs no message of its own.
+    async fn resolve_prompt(&self, prompt_id: uuid::Uuid, outcome: &str) -> bool {
+        let Some(sent) = self.sent_prompts.take(prompt_id) else {
+            return false;
+        };
+        if sent.pinned {
+            let (reply_to_id, _) = &sent.messages[0];
+            if let Ok((chat_id, message_id)) = parse_reply_key(reply_to_id) {
+                let body = serde_json::json!({ "chat_id": chat_id, "message_id": message_id });
+                if let Err(e) = self.call_api("unpinChatMessage", body).await {
+                    tracing::warn!("Telegram could not unpin prompt {}: {}", prompt_id, e);
+                }
+            }
+        }
+        let mut edited = false;
+        for (i, (reply_to_id, text)) in sent.messages.iter().enumerate() {
+            let resolved = format!("{}\n\n{}", text, outcome);
+            match self.edit_message(reply_to_id, &resolved).await {
+                Ok(()) => edited |= i == 0,
+                Err(e) => tracing::warn!(
+                    "Telegram could not edit the outcome into prompt {}: {}",
+                    prompt_id,
+                    e
+                ),
+            }
+        }
+        edited && self.edit_outcomes
+    }
+
+    /// Delete the message behind `reply_to_id` (`<chat_id>:<message_id>`).
+    async fn delete_message(&self, reply_to_id: &str) -> ProviderResult<()> {
+        let (chat_id, message_id) = parse_reply_key(reply_to_id)?;
+        self.call_api(
+            "deleteMessage",
+            serde_json::json!({ "chat_id": chat_id, "message_id": message_id }),
+        )
+        .await
+    }
+
+    async fn call_api(&self, method: &str, body: serde_json::Value) -> ProviderResult<()> {
+        let url = format!("{}{}/{}", self.api_base, self.token, method);
+        let res = self.client.post(&url).json(&body).send().await?;
+        if !res.status().is_success() {
+            let status = res.status();
+            return Err(format!(
+                "{} {}: {}",
+                method,
+                status,
+                res.text().await.unwrap_or_default()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
     /// Determine if an error is retryable (network errors, 5xx, 429)
     fn is_retryable_error(error: &(dyn Error + Send + Sync)) -> bool {
         let error_str = error.to_string().to_lowercase();
@@ -260,12 +809,6 @@ impl TelegramSink {
 
         false
     }
-
-    /// Legacy send_message for backward compatibility (simple send without message_id)
-    async fn send_message(&self, text: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
-        self.send_message_with_retry(text).await?;
-        Ok(())
-    }
 }
 
 #[async_trait]
@@ -275,8 +818,12 @@ impl NotificationSink for TelegramSink {
     }
 
     async fn send(&self, message: &Message) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let text = Self::format_message(message);
-        self.send_message(&text).await
+        self.deliver(message).await?;
+        Ok(())
+    }
+
+    async fn send_status(&self, message: &Message) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.send_chat_action(message).await
     }
 
     /// Send message and return Telegram message_id for reply-to matching
@@ -284,8 +831,46 @@ impl NotificationSink for TelegramSink {
         &self,
         message: &Message,
     ) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
-        let text = Self::format_message(message);
-        self.send_message_with_retry(&text).await
+        self.deliver(message).await
+    }
+}
+
+/// The line added to a prompt once `response` resolves it, e.g. `✅ Approved by @alice at
+/// 14:02 UTC`.
+fn outcome(response: &Message) -> String {
+    let MessageContent::Response {
+        answer,
+        response_type,
+    } = &response.content
+    else {
+        return String::new();
+    };
+    let mut line = match response_type {
+        ResponseType::Text => format!("✅ Answered \"{}\"", answer.as_deref().unwrap_or_default()),
+        ResponseType::AuthorizationApproved => "✅ Approved".to_string(),
+        ResponseType::AuthorizationDenied => "❌ Denied".to_string(),
+        ResponseType::Timeout => "⌛ Timed out".to_string(),
+        ResponseType::Cancelled => "🚫 Cancelled".to_string(),
+    };
+    let metadata = response.metadata.as_ref();
+    let member = metadata.and_then(|m| m["member"].as_str());
+    let responder = metadata.and_then(|m| m["responder"].as_str());
+    match (member, responder) {
+        (Some(member), _) => line.push_str(&format!(" by {}", member)),
+        (None, Some(responder)) => line.push_str(&format!(" by {}", responder_name(responder))),
+        (None, None) => {}
+    }
+    line.push_str(&response.timestamp.format(" at %H:%M UTC").to_string());
+    line
+}
+
+/// How a responder identity is shown: Telegram usernames as `@name`, others unchanged.
+fn responder_name(responder: &str) -> String {
+    match responder.strip_prefix("telegram:") {
+        Some(name) if !name.chars().all(|c| c.is_ascii_digit()) => {
+            format!("@{}", name.trim_start_matches('@'))
+        }
+        _ => responder.to_string(),
     }
 }
 
@@ -298,11 +883,38 @@ struct GetUpdatesResponse {
     result: Vec<TelegramUpdate>,
 }
 
+/// Error body of a failed Bot API call; `retry_after` is set on 429.
+#[derive(serde::Deserialize, Default)]
+struct ApiErrorResponse {
+    #[serde(default)]
+    parameters: Option<ResponseParameters>,
+}
+
+#[derive(serde::Deserialize, Default)]
+struct ResponseParameters {
+    #[serde(default)]
+    retry_after: Option<u64>,
+}
+
 #[derive(serde::Deserialize)]
 struct TelegramUpdate {
     update_id: i64,
     #[serde(default)]
     message: Option<TelegramMessage>,
+    /// A press of an inline button, such as *Mark read*.
+    #[serde(default)]
+    callback_query: Option<CallbackQuery>,
+}
+
+#[derive(serde::Deserialize)]
+struct CallbackQuery {
+    id: String,
+    from: TelegramUser,
+    #[serde(default)]
+    data: Option<String>,
+    /// The message the button is under.
+    #[serde(default)]
+    message: Option<TelegramMessage>,
 }
 
 #[derive(serde::Deserialize)]
@@ -313,32 +925,46 @@ struct TelegramMessage {
     text: Option<String>,
     #[serde(default)]
     reply_to_message: Option<ReplyToMessage>,
+    #[serde(default)]
+    from: Option<TelegramUser>,
+    #[serde(default)]
+    chat: Option<TelegramChat>,
+    /// Forum topic the message was posted in.
+    #[serde(default)]
+    message_thread_id: Option<i64>,
 }
 
 #[derive(serde::Deserialize)]
-struct ReplyToMessage {
-    message_id: i64,
+struct TelegramChat {
+    id: i64,
 }
 
-/// Infers response type from text: y/yes/ok -> Approved, n/no -> Denied, else Text.
-/// Empty string is treated as Denied for safety (changed from previous behavior).
-/// Invalid or unparseable provider reply: for authorization/navigation treated as deny (FR-010);
-/// for question the answer is used as-is (empty or error handled by caller).
-pub(crate) fn infer_response_type(text: &str) -> ResponseType {
-    let t = text.trim().to_lowercase();
-    match t.as_str() {
-        "y" | "yes" | "ok" => ResponseType::AuthorizationApproved,
-        "n" | "no" | "deny" | "denied" | "" => ResponseType::AuthorizationDenied,
-        _ => ResponseType::Text,
-    }
+#[derive(serde::Deserialize)]
+struct TelegramUser {
+    id: i64,
+    #[serde(default)]
+    username: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ReplyToMessage {
+    message_id: i64,
 }
 
 /// Telegram reply source (getUpdates long poll). Returns replies for matching to pending prompts.
 pub struct TelegramReplySource {
     token: String,
     client: Arc<Client>,
+    /// Default chat and per-channel overrides, used to map replies back to channels.
+    chat_id: Option<String>,
+    channel_map: HashMap<String, TelegramChannelTarget>,
+    aliases: Option<Arc<ReplyAliases>>,
     /// Next offset for getUpdates (last_update_id + 1).
     next_offset: AtomicI64,
+    /// Where `next_offset` is persisted across restarts.
+    offset_file: Option<PathBuf>,
+    /// Webhook mode: updates pushed by `/api/providers/telegram/webhook` instead of polled.
+    webhook_updates: Option<tokio::sync::Mutex<mpsc::Receiver<serde_json::Value>>>,
     /// Current backoff delay for error handling
     backoff_secs: AtomicI64,
 }
@@ -356,11 +982,195 @@ impl TelegramReplySource {
         Self {
             token,
             client,
+            chat_id: None,
+            channel_map: HashMap::new(),
+            aliases: None,
             next_offset: AtomicI64::new(0),
+            offset_file: None,
+            webhook_updates: None,
             backoff_secs: AtomicI64::new(GETUPDATES_BACKOFF_BASE_SECS as i64),
         }
     }
 
+    /// Poll getUpdates through the proxy `proxy` selects for api.telegram.org.
+    pub fn with_proxy(
+        mut self,
+        proxy: &ProxySettings,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        self.client = Arc::new(
+            proxy
+                .apply(Client::builder())?
+                .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+                .build()?,
+        );
+        Ok(self)
+    }
+
+    /// Map replies in routed chats/topics back to their ailoop channel.
+    pub fn with_channel_map(
+        mut self,
+        chat_id: impl Into<String>,
+        channel_map: HashMap<String, TelegramChannelTarget>,
+    ) -> Self {
+        self.chat_id = Some(chat_id.into());
+        self.channel_map = channel_map;
+        self
+    }
+
+    /// Resolve replies to prompt copies through the sink's [`ReplyAliases`].
+    pub fn with_reply_aliases(mut self, aliases: Arc<ReplyAliases>) -> Self {
+        self.aliases = Some(aliases);
+        self
+    }
+
+    /// Persist the getUpdates offset in `path` so a restart neither replays nor drops replies.
+    pub fn with_offset_file(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        if let Some(offset) = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| s.trim().parse::<i64>().ok())
+        {
+            self.next_offset.store(offset, Ordering::Relaxed);
+        }
+        self.offset_file = Some(path);
+        self
+    }
+
+    /// Take updates from the webhook inbox instead of long polling.
+    pub fn with_webhook(mut self, updates: mpsc::Receiver<serde_json::Value>) -> Self {
+        self.webhook_updates = Some(tokio::sync::Mutex::new(updates));
+        self
+    }
+
+    /// Register `url` as the bot's webhook; Telegram echoes `secret` in every delivery.
+    pub async fn set_webhook(
+        &self,
+        url: &str,
+        secret: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.call_api(
+            "setWebhook",
+            serde_json::json!({
+                "url": url,
+                "secret_token": secret,
+                "allowed_updates": ["message", "callback_query"],
+            }),
+        )
+        .await
+    }
+
+    /// Remove any webhook so getUpdates polling works (Telegram rejects both at once).
+    pub async fn delete_webhook(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.call_api("deleteWebhook", serde_json::json!({})).await
+    }
+
+    async fn call_api(
+        &self,
+        method: &str,
+        body: serde_json::Value,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let url = format!("{}{}/{}", TELEGRAM_API_BASE, self.token, method);
+        let res = self.client.post(&url).json(&body).send().await?;
+        if !res.status().is_success() {
+            let status = res.status();
+            return Err(format!(
+                "{} {}: {}",
+                method,
+                status,
+                res.text().await.unwrap_or_default()
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn store_offset(&self, offset: i64) {
+        self.next_offset.store(offset, Ordering::Relaxed);
+        if let Some(path) = &self.offset_file {
+            if let Err(e) = std::fs::write(path, offset.to_string()) {
+                tracing::warn!(
+                    "Failed to persist Telegram offset to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Convert an update into a reply; `None` for updates without a message. A *Mark read*
+    /// press becomes `/read <id>`.
+    fn reply_from_update(&self, upd: &TelegramUpdate) -> Option<ProviderReply> {
+        if let Some(query) = &upd.callback_query {
+            return Self::reply_from_button(query);
+        }
+        let msg = upd.message.as_ref()?;
+        let text = msg.text.as_deref().unwrap_or("").to_string();
+        let chat_id = msg.chat.as_ref().map(|c| c.id.to_string());
+        let reply_to_message_id = msg
+            .reply_to_message
+            .as_ref()
+            .zip(chat_id.as_deref())
+            .map(|(r, chat)| reply_key(chat, r.message_id))
+            .map(|key| match &self.aliases {
+                Some(aliases) => aliases.resolve(key),
+                None => key,
+            });
+        let response_type = infer_response_type(&text);
+        Some(ProviderReply {
+            reply_to_message_id,
+            prompt_id: None,
+            answer: Some(text),
+            response_type,
+            sender_id: msg.from.as_ref().map(|u| u.id.to_string()),
+            sender_name: msg.from.as_ref().and_then(|u| u.username.clone()),
+            channel: chat_id
+                .as_deref()
+                .and_then(|chat| self.channel_for(chat, msg.message_thread_id)),
+            chat_id,
+        })
+    }
+
+    fn reply_from_button(query: &CallbackQuery) -> Option<ProviderReply> {
+        let message_id = query.data.as_deref()?.strip_prefix(READ_CALLBACK_PREFIX)?;
+        Some(ProviderReply {
+            reply_to_message_id: None,
+            prompt_id: None,
+            answer: Some(format!("/read {}", message_id)),
+            response_type: ResponseType::Text,
+            sender_id: Some(query.from.id.to_string()),
+            sender_name: query.from.username.clone(),
+            chat_id: query
+                .message
+                .as_ref()
+                .and_then(|m| m.chat.as_ref())
+                .map(|c| c.id.to_string()),
+            channel: None,
+        })
+    }
+
+    /// Stop the button's spinner; Telegram shows it until a press is answered.
+    async fn answer_button(&self, upd: &TelegramUpdate) {
+        let Some(query) = &upd.callback_query else {
+            return;
+        };
+        let body = serde_json::json!({ "callback_query_id": query.id, "text": "Marked read" });
+        if let Err(e) = self.call_api("answerCallbackQuery", body).await {
+            tracing::debug!("Telegram answerCallbackQuery failed: {}", e);
+        }
+    }
+
+    /// The channel routed to `chat_id` / `topic_id`, if exactly one is.
+    fn channel_for(&self, chat_id: &str, topic_id: Option<i64>) -> Option<String> {
+        let mut matches = self.channel_map.iter().filter(|(_, target)| {
+            target.chat_id.as_deref().or(self.chat_id.as_deref()) == Some(chat_id)
+                && target.topic_id == topic_id
+        });
+        match (matches.next(), matches.next()) {
+            (Some((channel, _)), None) => Some(channel.clone()),
+            _ => None,
+        }
+    }
+
     /// Long poll getUpdates; returns first message as ProviderReply if any.
     /// Includes exponential backoff on errors.
     async fn get_updates(&self) -> Result<Option<ProviderReply>, Box<dyn Error + Send + Sync>> {
@@ -370,10 +1180,16 @@ impl TelegramReplySource {
             TELEGRAM_API_BASE, self.token, offset, LONG_POLL_TIMEOUT_SECS
         );
 
-        let res = match self.client.get(&url).send().await {
+        let res = match self
+            .client
+            .get(&url)
+            .query(&[("allowed_updates", ALLOWED_UPDATES)])
+            .send()
+            .await
+        {
             Ok(r) => r,
             Err(e) => {
-                self.handle_error_backoff().await;
+                self.handle_error_backoff(None).await;
                 return Err(format!("getUpdates request failed: {}", e).into());
             }
         };
@@ -381,14 +1197,23 @@ impl TelegramReplySource {
         if !res.status().is_success() {
             let status = res.status();
             let body = res.text().await.unwrap_or_default();
-            self.handle_error_backoff().await;
+            if status == StatusCode::CONFLICT {
+                tracing::error!(
+                    "Telegram getUpdates conflict: a webhook is set or another poller is running"
+                );
+            }
+            let retry_after = serde_json::from_str::<ApiErrorResponse>(&body)
+                .unwrap_or_default()
+                .parameters
+                .and_then(|p| p.retry_after);
+            self.handle_error_backoff(retry_after).await;
             return Err(format!("getUpdates {}: {}", status, body).into());
         }
 
         let body: GetUpdatesResponse = match res.json().await {
             Ok(b) => b,
             Err(e) => {
-                self.handle_error_backoff().await;
+                self.handle_error_backoff(None).await;
                 return Err(format!("getUpdates JSON parse error: {}", e).into());
             }
         };
@@ -403,34 +1228,22 @@ impl TelegramReplySource {
         let mut last_id = offset;
         for upd in &body.result {
             last_id = upd.update_id;
-            if let Some(ref msg) = upd.message {
-                let text = msg.text.as_deref().unwrap_or("").to_string();
-                let reply_to_message_id = msg
-                    .reply_to_message
-                    .as_ref()
-                    .map(|r| r.message_id.to_string());
-                let response_type = infer_response_type(&text);
-                let reply = ProviderReply {
-                    reply_to_message_id,
-                    answer: Some(text),
-                    response_type,
-                };
-                self.next_offset.store(last_id + 1, Ordering::Relaxed);
+            self.answer_button(upd).await;
+            if let Some(reply) = self.reply_from_update(upd) {
+                self.store_offset(last_id + 1);
                 return Ok(Some(reply));
             }
         }
-        self.next_offset.store(last_id + 1, Ordering::Relaxed);
+        self.store_offset(last_id + 1);
         Ok(None)
     }
 
-    /// Apply exponential backoff on error
-    async fn handle_error_backoff(&self) {
+    /// Apply exponential backoff on error, or wait `retry_after` seconds when Telegram asks to.
+    async fn handle_error_backoff(&self, retry_after: Option<u64>) {
         let current_backoff = self.backoff_secs.load(Ordering::Relaxed) as u64;
-        tracing::warn!(
-            "Telegram getUpdates error, backing off for {}s",
-            current_backoff
-        );
-        sleep(Duration::from_secs(current_backoff)).await;
+        let delay = retry_after.unwrap_or(current_backoff);
+        tracing::warn!("Telegram getUpdates error, backing off for {}s", delay);
+        sleep(Duration::from_secs(delay)).await;
 
         // Increase backoff for next time (capped at max)
         let next_backoff = (current_backoff * 2).min(GETUPDATES_BACKOFF_MAX_SECS);
@@ -448,13 +1261,261 @@ impl TelegramReplySource {
 #[async_trait]
 impl ReplySource for TelegramReplySource {
     async fn next_reply(&self) -> Option<ProviderReply> {
-        self.get_updates().await.ok().flatten()
+        let Some(updates) = &self.webhook_updates else {
+            return self.get_updates().await.ok().flatten();
+        };
+        let raw = updates.lock().await.recv().await?;
+        match serde_json::from_value::<TelegramUpdate>(raw) {
+            Ok(upd) => {
+                self.answer_button(&upd).await;
+                self.reply_from_update(&upd)
+            }
+            Err(e) => {
+                tracing::warn!("Ignoring malformed Telegram webhook update: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Why the webhook endpoint rejected an update.
+#[derive(Debug, PartialEq, Eq)]
+pub enum WebhookRejection {
+    /// Webhook mode is not active.
+    Disabled,
+    /// Missing or wrong `X-Telegram-Bot-Api-Secret-Token`.
+    BadSecret,
+    /// The reply loop is behind; Telegram will redeliver.
+    Busy,
+}
+
+/// Hand-off between `/api/providers/telegram/webhook` and the webhook-mode reply source.
+/// Disabled until the background task enables it with the secret registered via `setWebhook`.
+pub struct TelegramWebhookInbox {
+    secret: std::sync::RwLock<Option<String>>,
+    tx: mpsc::Sender<serde_json::Value>,
+    rx: Mutex<Option<mpsc::Receiver<serde_json::Value>>>,
+}
+
+impl TelegramWebhookInbox {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel(WEBHOOK_QUEUE_CAPACITY);
+        Self {
+            secret: std::sync::RwLock::new(None),
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Start accepting updates carrying `secret`; returns the receiver (first call only).
+    pub fn enable(&self, secret: String) -> Option<mpsc::Receiver<serde_json::Value>> {
+        *self.secret.write().expect("webhook secret lock") = Some(secret);
+        self.rx.lock().expect("webhook receiver lock").take()
+    }
+
+    /// Queue an update delivered with the given secret header.
+    pub fn accept(
+        &self,
+        secret: Option<&str>,
+        update: serde_json::Value,
+    ) -> Result<(), WebhookRejection> {
+        match self.secret.read().expect("webhook secret lock").as_deref() {
+            None => return Err(WebhookRejection::Disabled),
+            // Compared in constant time, so response timing does not reveal the secret.
+            Some(expected)
+                if !secret.is_some_and(|s| bool::from(s.as_bytes().ct_eq(expected.as_bytes()))) =>
+            {
+                return Err(WebhookRejection::BadSecret)
+            }
+            Some(_) => {}
+        }
+        self.tx.try_send(update).map_err(|_| WebhookRejection::Busy)
+    }
+}
+
+impl Default for TelegramWebhookInbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Telegram as a [`Provider`]: the sink delivers, the reply source reads replies, and the
+/// config's responder rules decide which replies count.
+pub struct TelegramProvider {
+    sink: TelegramSink,
+    source: Arc<TelegramReplySource>,
+    config: TelegramProviderConfig,
+    /// Webhook URL and secret registered by `init`; `None` in poll mode.
+    webhook: Option<(String, String)>,
+}
+
+impl TelegramProvider {
+    /// Build from `[providers.telegram]` with the bot token from `AILOOP_TELEGRAM_BOT_TOKEN`.
+    /// Returns `Ok(None)` when the provider is disabled or the token or chat_id is missing.
+    pub fn from_config(
+        config: &TelegramProviderConfig,
+        proxy: &ProxySettings,
+        inbox: Arc<TelegramWebhookInbox>,
+    ) -> Result<Option<Self>, Box<dyn Error + Send + Sync>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let token = std::env::var("AILOOP_TELEGRAM_BOT_TOKEN").ok();
+        let chat_id = config.chat_id.clone().filter(|s| !s.is_empty());
+        let (token, chat_id) = match (token, chat_id) {
+            (Some(t), Some(c)) => (t, c),
+            (None, _) => {
+                tracing::warn!("Telegram provider skipped: token not set");
+                return Ok(None);
+            }
+            (_, None) => {
+                tracing::warn!("Telegram provider skipped: chat_id not configured");
+                return Ok(None);
+            }
+        };
+
+        let sink = TelegramSink::new(token.clone(), chat_id.clone())?
+            .with_proxy(proxy)?
+            .with_channel_map(config.channel_map.clone())
+            .with_chats(config.chats.clone())
+            .with_pinned_prompts(config.pin_prompts.clone())
+            .with_edit_outcomes(config.edit_outcomes())
+            .with_read_buttons(config.read_buttons());
+        let source = TelegramReplySource::new(token)
+            .with_proxy(proxy)?
+            .with_channel_map(chat_id, config.channel_map.clone())
+            .with_reply_aliases(sink.reply_aliases());
+        let (source, webhook) = Self::configure_updates(source, config, &inbox);
+        Ok(Some(Self {
+            sink,
+            source: Arc::new(source),
+            config: config.clone(),
+            webhook,
+        }))
+    }
+
+    /// Send image attachments kept in `store` as photos.
+    pub fn with_attachments(mut self, store: Arc<AttachmentStore>) -> Self {
+        self.sink = self.sink.with_attachments(store);
+        self
+    }
+
+    /// Put the reply source in poll or webhook mode.
+    ///
+    /// Webhook mode takes a secret from `AILOOP_TELEGRAM_WEBHOOK_SECRET` (random when unset) and
+    /// reads updates from the webhook inbox. Poll mode persists the getUpdates offset.
+    fn configure_updates(
+        source: TelegramReplySource,
+        config: &TelegramProviderConfig,
+        inbox: &TelegramWebhookInbox,
+    ) -> (TelegramReplySource, Option<(String, String)>) {
+        if let (Some(TelegramUpdatesMode::Webhook), Some(url)) =
+            (&config.updates, &config.webhook_url)
+        {
+            let secret = std::env::var("AILOOP_TELEGRAM_WEBHOOK_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+            if let Some(updates) = inbox.enable(secret.clone()) {
+                return (source.with_webhook(updates), Some((url.clone(), secret)));
+            }
+            tracing::warn!("Telegram webhook inbox already in use; falling back to polling");
+        }
+
+        let offset_file = config
+            .offset_file
+            .as_ref()
+            .map(PathBuf::from)
+            .or_else(|| ailoop_core::paths::state_path("telegram.offset"));
+        match offset_file {
+            Some(path) => {
+                if let Some(parent) = path.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                (source.with_offset_file(path), None)
+            }
+            None => (source, None),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for TelegramProvider {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    /// Register the webhook, or remove a stale one so getUpdates polling works.
+    async fn init(&self) -> ProviderResult<()> {
+        match &self.webhook {
+            Some((url, secret)) => {
+                self.source.set_webhook(url, secret).await?;
+                tracing::info!("Telegram webhook registered at {}", url);
+            }
+            None => {
+                if let Err(e) = self.source.delete_webhook().await {
+                    tracing::warn!("Telegram deleteWebhook failed: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_prompt(&self, message: &Message) -> ProviderResult<Option<String>> {
+        self.sink.deliver(message).await
+    }
+
+    async fn send_notification(&self, message: &Message) -> ProviderResult<()> {
+        self.sink.deliver(message).await.map(|_| ())
+    }
+
+    async fn send_status(&self, message: &Message) -> ProviderResult<()> {
+        self.sink.send_chat_action(message).await
+    }
+
+    /// Replies from chats and users allowed by `responders` / `chats`; others are dropped.
+    fn reply_stream(&self) -> Option<BoxStream<'static, ProviderReply>> {
+        let config = self.config.clone();
+        let replies = futures_util::stream::unfold(Arc::clone(&self.source), |source| async move {
+            loop {
+                if let Some(reply) = source.next_reply().await {
+                    return Some((reply, source));
+                }
+            }
+        })
+        .filter(move |reply| {
+            let accepted = config.accepts_reply(
+                reply.chat_id.as_deref(),
+                reply.sender_id.as_deref(),
+                reply.sender_name.as_deref(),
+            );
+            if !accepted {
+                tracing::warn!(
+                    "Ignoring Telegram reply from non-responder {:?} in chat {:?}",
+                    reply.sender_id,
+                    reply.chat_id
+                );
+            }
+            futures_util::future::ready(accepted)
+        });
+        Some(replies.boxed())
+    }
+
+    async fn edit(&self, reply_to_id: &str, message: &Message) -> ProviderResult<()> {
+        self.sink
+            .edit_message(reply_to_id, &TelegramSink::format_message(message))
+            .await
+    }
+
+    async fn delete(&self, reply_to_id: &str) -> ProviderResult<()> {
+        self.sink.delete_message(reply_to_id).await
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ailoop_core::models::ResponseType;
 
     #[test]
     fn test_infer_response_type() {
@@ -479,6 +1540,10 @@ mod tests {
             ResponseType::AuthorizationDenied
         );
         assert_eq!(infer_response_type("hello"), ResponseType::Text);
+        assert_eq!(
+            infer_response_type("no: wrong cluster"),
+            ResponseType::AuthorizationDenied
+        );
     }
 
     #[test]
@@ -515,6 +1580,144 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    fn channel_map() -> HashMap<String, TelegramChannelTarget> {
+        HashMap::from([
+            (
+                "deploys".to_string(),
+                TelegramChannelTarget {
+                    chat_id: None,
+                    topic_id: Some(42),
+                },
+            ),
+            (
+                "ci".to_string(),
+                TelegramChannelTarget {
+                    chat_id: Some("-200".to_string()),
+                    topic_id: None,
+                },
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_channel_map_routes_to_chat_and_topic() {
+        let sink = TelegramSink::new("token".into(), "-100".into())
+            .unwrap()
+            .with_channel_map(channel_map());
+        assert_eq!(sink.target_for("deploys"), ("-100", Some(42)));
+        assert_eq!(sink.target_for("ci"), ("-200", None));
+        assert_eq!(sink.target_for("other"), ("-100", None));
+    }
+
+    #[test]
+    fn test_reply_source_maps_topic_back_to_channel() {
+        let source =
+            TelegramReplySource::new("token".into()).with_channel_map("-100", channel_map());
+        assert_eq!(
+            source.channel_for("-100", Some(42)).as_deref(),
+            Some("deploys")
+        );
+        assert_eq!(source.channel_for("-200", None).as_deref(), Some("ci"));
+        assert_eq!(source.channel_for("-100", None), None);
+        assert_eq!(source.channel_for("-100", Some(7)), None);
+    }
+
+    #[test]
+    fn test_reply_aliases_resolve_and_evict() {
+        let aliases = ReplyAliases::default();
+        aliases.insert("-300:5".to_string(), "-100:9".to_string());
+        assert_eq!(aliases.resolve("-300:5".to_string()), "-100:9");
+        assert_eq!(aliases.resolve("-100:9".to_string()), "-100:9");
+
+        for i in 0..MAX_REPLY_ALIASES {
+            aliases.insert(format!("-300:{}", 1000 + i), "-100:1".to_string());
+        }
+        assert_eq!(aliases.resolve("-300:5".to_string()), "-300:5");
+    }
+
+    #[test]
+    fn test_offset_file_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("telegram.offset");
+        let source = TelegramReplySource::new("token".into()).with_offset_file(&path);
+        assert_eq!(source.next_offset.load(Ordering::Relaxed), 0);
+        source.store_offset(1234);
+
+        let restarted = TelegramReplySource::new("token".into()).with_offset_file(&path);
+        assert_eq!(restarted.next_offset.load(Ordering::Relaxed), 1234);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_inbox_feeds_reply_source() {
+        let inbox = TelegramWebhookInbox::new();
+        let update = serde_json::json!({
+            "update_id": 10,
+            "message": {
+                "message_id": 5,
+                "text": "yes",
+                "chat": {"id": -100},
+                "from": {"id": 42, "username": "alice"},
+                "reply_to_message": {"message_id": 3}
+            }
+        });
+        assert_eq!(
+            inbox.accept(Some("s3cret"), update.clone()),
+            Err(WebhookRejection::Disabled)
+        );
+
+        let rx = inbox.enable("s3cret".to_string()).unwrap();
+        assert_eq!(inbox.accept(Some("s3cret"), update.clone()), Ok(()));
+        assert_eq!(
+            inbox.accept(Some("wrong"), update.clone()),
+            Err(WebhookRejection::BadSecret)
+        );
+        assert_eq!(inbox.accept(None, update), Err(WebhookRejection::BadSecret));
+
+        let source = TelegramReplySource::new("token".into()).with_webhook(rx);
+        let reply = source.next_reply().await.unwrap();
+        assert_eq!(reply.reply_to_message_id.as_deref(), Some("-100:3"));
+        assert_eq!(reply.response_type, ResponseType::AuthorizationApproved);
+        assert_eq!(reply.chat_id.as_deref(), Some("-100"));
+        assert_eq!(reply.sender_name.as_deref(), Some("alice"));
+    }
+
+    #[test]
+    fn test_read_button_press_becomes_read_command() {
+        let sink = TelegramSink::new("token".into(), "-100".into()).unwrap();
+        let say = Message::new(
+            "ops".to_string(),
+            ailoop_core::models::SenderType::Agent,
+            MessageContent::Notification {
+                text: "deployed".to_string(),
+                priority: Default::default(),
+            },
+        );
+        let button = sink.read_button(&say).unwrap();
+        let data = button["inline_keyboard"][0][0]["callback_data"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(sink.with_read_buttons(false).read_button(&say).is_none());
+
+        let update: TelegramUpdate = serde_json::from_value(serde_json::json!({
+            "update_id": 11,
+            "callback_query": {
+                "id": "q1",
+                "from": {"id": 42, "username": "alice"},
+                "data": data,
+                "message": {"message_id": 7, "chat": {"id": -100}}
+            }
+        }))
+        .unwrap();
+        let reply = TelegramReplySource::new("token".into())
+            .reply_from_update(&update)
+            .unwrap();
+        assert_eq!(reply.answer, Some(format!("/read {}", say.id)));
+        assert_eq!(reply.reply_to_message_id, None);
+        assert_eq!(reply.chat_id.as_deref(), Some("-100"));
+        assert_eq!(reply.sender_name.as_deref(), Some("alice"));
+    }
+
     #[test]
     fn test_truncate_message() {
         let short = "Short message";
@@ -542,6 +1745,199 @@ mod tests {
         assert!(formatted.contains("pending"));
     }
 
+    #[test]
+    fn test_format_message_names_agent() {
+        let message = Message::new(
+            "ops".to_string(),
+            ailoop_core::models::SenderType::Agent,
+            MessageContent::Authorization {
+                action: "Deploy to production".to_string(),
+                context: None,
+                timeout_seconds: 60,
+            },
+        )
+        .with_agent(ailoop_core::models::AgentIdentity {
+            name: Some("deploy-bot".to_string()),
+            version: None,
+            host: Some("host-a".to_string()),
+        });
+        assert_eq!(
+            TelegramSink::format_message(&message),
+            "deploy-bot on host-a asks:\nAuthorization [ops]: Deploy to production"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_image_attachment_is_sent_as_photo() {
+        let sink = TelegramSink::new("token".into(), "123456789".into()).unwrap();
+        let mut message = Message::new(
+            "design".to_string(),
+            ailoop_core::models::SenderType::Agent,
+            MessageContent::Notification {
+                text: "Image: plot.png".to_string(),
+                priority: ailoop_core::models::NotificationPriority::Normal,
+            },
+        );
+        assert!(sink.photo_for(&message).await.is_none());
+
+        message.metadata = Some(serde_json::json!({ "attachment": {
+            "name": "plot.png",
+            "content_type": "image/png",
+            "url": "https://example.com/plot.png",
+        }}));
+        assert_eq!(
+            TelegramSink::format_message(&message),
+            "Notification [design]: Image: plot.png\n📎 plot.png: https://example.com/plot.png"
+        );
+        assert!(matches!(
+            sink.photo_for(&message).await,
+            Some(Photo::Link(url)) if url == "https://example.com/plot.png"
+        ));
+
+        message.metadata.as_mut().unwrap()["attachment"]["content_type"] =
+            serde_json::json!("image/svg+xml");
+        assert!(sink.photo_for(&message).await.is_none());
+
+        let caption = TelegramSink::truncate_caption(&"é".repeat(2000));
+        assert_eq!(caption.chars().count(), TELEGRAM_MAX_CAPTION_LENGTH);
+    }
+
+    #[tokio::test]
+    async fn test_outcome_is_edited_into_sent_prompts() {
+        let sink = TelegramSink::new("token".into(), "123456789".into())
+            .unwrap()
+            .with_pinned_prompts(vec!["deploy-*".to_string()]);
+        assert!(sink.pins_prompts("deploy-prod"));
+        assert!(!sink.pins_prompts("builds"));
+
+        // A prompt that was never sent here gets its outcome as a message of its own.
+        assert!(
+            !sink
+                .resolve_prompt(uuid::Uuid::new_v4(), "✅ Approved")
+                .await
+        );
+
+        let prompt = ailoop_core::client::authorization("deploy-prod", "deploy", 60);
+        let mut response = Message::response(
+            prompt.channel.clone(),
+            MessageContent::Response {
+                answer: None,
+                response_type: ResponseType::AuthorizationApproved,
+            },
+            prompt.id,
+        );
+        response.timestamp = chrono::DateTime::parse_from_rfc3339("2026-10-16T14:02:31Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        response.metadata = Some(serde_json::json!({ "responder": "telegram:alice" }));
+        assert_eq!(outcome(&response), "✅ Approved by @alice at 14:02 UTC");
+
+        response.metadata = Some(serde_json::json!({
+            "responder": "telegram:123456",
+            "member": "@bob",
+        }));
+        assert_eq!(outcome(&response), "✅ Approved by @bob at 14:02 UTC");
+        assert_eq!(responder_name("telegram:123456"), "telegram:123456");
+        assert_eq!(responder_name("sso:carol"), "sso:carol");
+    }
+
+    #[test]
+    fn test_outcome_lines() {
+        let prompt = ailoop_core::client::authorization("ops", "deploy", 60);
+        let respond = |response_type, answer: Option<&str>| {
+            let mut response = Message::response(
+                prompt.channel.clone(),
+                MessageContent::Response {
+                    answer: answer.map(str::to_string),
+                    response_type,
+                },
+                prompt.id,
+            );
+            response.timestamp = chrono::DateTime::parse_from_rfc3339("2026-10-16T09:05:59Z")
+                .unwrap()
+                .with_timezone(&chrono::Utc);
+            response
+        };
+
+        let mut denied = respond(ResponseType::AuthorizationDenied, None);
+        denied.metadata = Some(serde_json::json!({ "responder": "sso:carol" }));
+        assert_eq!(outcome(&denied), "❌ Denied by sso:carol at 09:05 UTC");
+
+        let answered = respond(ResponseType::Text, Some("blue"));
+        assert_eq!(outcome(&answered), "✅ Answered \"blue\" at 09:05 UTC");
+        assert_eq!(
+            outcome(&respond(ResponseType::Timeout, None)),
+            "⌛ Timed out at 09:05 UTC"
+        );
+        assert_eq!(outcome(&prompt), "");
+    }
+
+    /// A Bot API on localhost that records the methods called. `editMessageText` fails when
+    /// `fail_edits` is set; `sendMessage` always succeeds.
+    async fn mock_bot_api(fail_edits: bool) -> (String, Arc<Mutex<Vec<String>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&calls);
+        let app = axum::Router::new().fallback(move |uri: axum::http::Uri| {
+            let calls = Arc::clone(&recorded);
+            async move {
+                let method = uri
+                    .path()
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+                calls.lock().unwrap().push(method.clone());
+                if fail_edits && method == "editMessageText" {
+                    return (
+                        axum::http::StatusCode::BAD_REQUEST,
+                        axum::Json(serde_json::json!({ "ok": false })),
+                    );
+                }
+                (
+                    axum::http::StatusCode::OK,
+                    axum::Json(serde_json::json!({ "ok": true, "result": { "message_id": 7 } })),
+                )
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{}/bot", addr), calls)
+    }
+
+    #[tokio::test]
+    async fn test_outcome_is_edited_in_or_sent_when_the_edit_fails() {
+        for fail_edits in [false, true] {
+            let (api_base, calls) = mock_bot_api(fail_edits).await;
+            let sink = TelegramSink::new("token".into(), "123456789".into())
+                .unwrap()
+                .with_api_base(api_base);
+            let prompt = ailoop_core::client::authorization("ops", "deploy", 60);
+            assert_eq!(
+                sink.deliver(&prompt).await.unwrap().as_deref(),
+                Some("123456789:7")
+            );
+
+            let response = Message::response(
+                prompt.channel.clone(),
+                MessageContent::Response {
+                    answer: None,
+                    response_type: ResponseType::AuthorizationApproved,
+                },
+                prompt.id,
+            );
+            let sent = sink.deliver(&response).await.unwrap();
+            let calls = calls.lock().unwrap().clone();
+            if fail_edits {
+                assert!(sent.is_some());
+                assert_eq!(calls, ["sendMessage", "editMessageText", "sendMessage"]);
+            } else {
+                assert!(sent.is_none());
+                assert_eq!(calls, ["sendMessage", "editMessageText"]);
+            }
+        }
+    }
+
     #[test]
     fn test_is_retryable_error() {
         let timeout_err: Box<dyn Error + Send + Sync> = "Request timeout".into();
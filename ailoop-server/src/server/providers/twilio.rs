@@ -0,0 +1,513 @@
+//! Twilio SMS / WhatsApp provider: prompts go out via the Messages API, replies come back on
+//! the `/api/providers/twilio/webhook` endpoint.
+//!
+//! SMS has no reply-to, so every prompt carries a short code (`[K3F9] Authorization ...`) that
+//! the responder repeats (`K3F9 yes`). The code may be left out while only one prompt is open.
+
+use crate::server::providers::{infer_response_type, Provider, ProviderResult};
+use ailoop_core::models::{
+    Message, MessageContent, NotificationPriority, ResponseType, TwilioProviderConfig,
+    DEFAULT_TWILIO_MAX_CHARS,
+};
+use ailoop_core::transport::proxy::ProxySettings;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use sha1::Sha1;
+use std::collections::{HashSet, VecDeque};
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::Duration;
+use uuid::Uuid;
+
+const TWILIO_API_BASE: &str = "https://api.twilio.com/2010-04-01/Accounts";
+const HTTP_TIMEOUT_SECS: u64 = 30;
+/// Prompt codes remembered for reply matching (oldest evicted first).
+const MAX_PROMPT_CODES: usize = 256;
+const CODE_LEN: usize = 4;
+const ELLIPSIS: char = '…';
+
+/// Twilio notification sink. Only prompts (and urgent notifications) in opted-in channels are
+/// sent, since every message is billed.
+#[derive(Debug)]
+pub struct TwilioSink {
+    account_sid: String,
+    auth_token: String,
+    from: String,
+    to: Vec<String>,
+    channels: HashSet<String>,
+    max_chars: usize,
+    webhook_url: String,
+    /// Outstanding prompt codes, in send order.
+    codes: Mutex<VecDeque<(String, Uuid)>>,
+    client: Client,
+}
+
+/// A verified inbound reply, ready for `PendingPromptRegistry::submit_reply_for_message_as`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TwilioReply {
+    pub message_id: Uuid,
+    pub answer: String,
+    pub response_type: ResponseType,
+    /// `twilio:<number>`
+    pub responder: String,
+}
+
+/// Why an inbound reply was not matched to a prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TwilioRejection {
+    /// Sender is not one of the configured recipients.
+    UnknownSender,
+    /// The code does not belong to an open prompt.
+    UnknownCode(String),
+    /// No code given and zero or several prompts are open.
+    Ambiguous,
+}
+
+impl std::fmt::Display for TwilioRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TwilioRejection::UnknownSender => write!(f, "sender not allowed"),
+            TwilioRejection::UnknownCode(code) => write!(f, "No open prompt with code {}", code),
+            TwilioRejection::Ambiguous => {
+                write!(f, "Start your reply with the prompt code, e.g. ABCD yes")
+            }
+        }
+    }
+}
+
+impl TwilioSink {
+    /// Build from `[providers.twilio]`; `auth_token` comes from `AILOOP_TWILIO_AUTH_TOKEN`.
+    pub fn new(
+        config: &TwilioProviderConfig,
+        auth_token: String,
+        proxy: &ProxySettings,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let (Some(account_sid), Some(from), Some(webhook_url)) = (
+            config.account_sid.clone(),
+            config.from.clone(),
+            config.webhook_url.clone(),
+        ) else {
+            return Err("providers.twilio: account_sid, from and webhook_url are required".into());
+        };
+        if config.to.is_empty() {
+            return Err("providers.twilio: at least one `to` number is required".into());
+        }
+        let client = proxy
+            .apply(Client::builder())?
+            .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
+            .build()?;
+        Ok(Self {
+            account_sid,
+            auth_token,
+            from,
+            to: config.to.clone(),
+            channels: config.channels.iter().cloned().collect(),
+            max_chars: config.max_chars.unwrap_or(DEFAULT_TWILIO_MAX_CHARS),
+            webhook_url,
+            codes: Mutex::new(VecDeque::new()),
+            client,
+        })
+    }
+
+    /// Build from `[providers.twilio]` with the auth token from `AILOOP_TWILIO_AUTH_TOKEN`.
+    /// Returns `Ok(None)` when the provider is disabled or the token is not set.
+    pub fn from_config(
+        config: &TwilioProviderConfig,
+        proxy: &ProxySettings,
+    ) -> Result<Option<Self>, Box<dyn Error + Send + Sync>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        match std::env::var("AILOOP_TWILIO_AUTH_TOKEN") {
+            Ok(auth_token) => Self::new(config, auth_token, proxy).map(Some),
+            Err(_) => {
+                tracing::warn!("Twilio provider skipped: auth token not set");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Whether `message` is worth an SMS: opted-in channel, and a prompt or urgent notification.
+    fn wants(&self, message: &Message) -> bool {
+        if !self.channels.contains(&message.channel) {
+            return false;
+        }
+        match &message.content {
+            MessageContent::Decision { .. }
+            | MessageContent::Authorization { .. }
+            | MessageContent::Navigate { .. } => true,
+            MessageContent::Notification { priority, .. } => {
+                matches!(priority, NotificationPriority::Urgent)
+            }
+            _ => false,
+        }
+    }
+
+    /// Assign a reply code to prompt `message_id`: the first hex digits of the id, lengthened
+    /// until unique among outstanding codes.
+    fn assign_code(&self, message_id: Uuid) -> String {
+        let hex = message_id.simple().to_string().to_uppercase();
+        let mut codes = self.codes.lock().expect("twilio codes lock");
+        if let Some((code, _)) = codes.iter().find(|(_, id)| *id == message_id) {
+            return code.clone();
+        }
+        let code = (CODE_LEN..=hex.len())
+            .map(|n| hex[..n].to_string())
+            .find(|c| !codes.iter().any(|(existing, _)| existing == c))
+            .unwrap_or(hex);
+        if codes.len() >= MAX_PROMPT_CODES {
+            codes.pop_front();
+        }
+        codes.push_back((code.clone(), message_id));
+        code
+    }
+
+    /// Render `message` within `max_chars` characters. The body is cut at a character boundary
+    /// and marked with `…`; the code prefix and reply hint are never cut.
+    fn format_message(message: &Message, code: Option<&str>, max_chars: usize) -> String {
+        let channel = &message.channel;
+        let (body, hint) = match &message.content {
+            MessageContent::Decision {
+                summary, options, ..
+            } => {
+                let opts: Vec<String> = options
+                    .iter()
+                    .enumerate()
+                    .map(|(i, o)| format!("{}) {}", i + 1, o.label))
+                    .collect();
+                (
+                    format!("Decision [{}]: {} {}", channel, summary, opts.join(" ")),
+                    Some("<n>"),
+                )
+            }
+            MessageContent::Authorization { action, .. } => (
+                format!("Authorization [{}]: {}", channel, action),
+                Some("yes/no"),
+            ),
+            MessageContent::Navigate { url, .. } => {
+                (format!("Navigation [{}]: {}", channel, url), Some("yes/no"))
+            }
+            MessageContent::Notification { text, .. } => {
+                (format!("Notification [{}]: {}", channel, text), None)
+            }
+            _ => (format!("Message [{}]", channel), None),
+        };
+
+        let body = match message.agent_heading() {
+            Some(heading) => format!("{} {}", heading, body),
+            None => body,
+        };
+        // Only the latest step of a thread fits a text; it is cut first when space runs out.
+        let body = match message.thread().last() {
+            Some(entry) => format!(
+                "{} (after: {} → {})",
+                body,
+                entry.question,
+                entry.answer.as_deref().unwrap_or("no answer")
+            ),
+            None => body,
+        };
+        let prefix = code.map(|c| format!("[{}] ", c)).unwrap_or_default();
+        let suffix = match (code, hint) {
+            (Some(c), Some(h)) => format!("\nReply {} {}", c, h),
+            _ => String::new(),
+        };
+        let budget = max_chars.saturating_sub(prefix.chars().count() + suffix.chars().count());
+        format!("{}{}{}", prefix, truncate_chars(&body, budget), suffix)
+    }
+
+    async fn send_sms(&self, to: &str, body: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let url = format!("{}/{}/Messages.json", TWILIO_API_BASE, self.account_sid);
+        let response = self
+            .client
+            .post(&url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&[("To", to), ("From", self.from.as_str()), ("Body", body)])
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!("Twilio API error: {} – {}", status, text).into());
+        }
+        Ok(())
+    }
+
+    /// Check `X-Twilio-Signature`: base64 HMAC-SHA1 (auth token) over the webhook URL followed
+    /// by every POST parameter name and value, sorted by name.
+    pub fn verify_signature(&self, signature: Option<&str>, params: &[(String, String)]) -> bool {
+        let Some(signature) = signature else {
+            return false;
+        };
+        let Ok(expected) = STANDARD.decode(signature) else {
+            return false;
+        };
+        let mut sorted: Vec<&(String, String)> = params.iter().collect();
+        sorted.sort();
+        let mut mac = Hmac::<Sha1>::new_from_slice(self.auth_token.as_bytes())
+            .expect("HMAC accepts any key length");
+        mac.update(self.webhook_url.as_bytes());
+        for (key, value) in sorted {
+            mac.update(key.as_bytes());
+            mac.update(value.as_bytes());
+        }
+        mac.verify_slice(&expected).is_ok()
+    }
+
+    /// Match an inbound message to an open prompt. `pending` lists the message ids still
+    /// waiting in the registry; codes of prompts answered elsewhere are dropped first.
+    pub fn parse_reply(
+        &self,
+        from: &str,
+        body: &str,
+        pending: &[Uuid],
+    ) -> Result<TwilioReply, TwilioRejection> {
+        if !self.to.iter().any(|n| n == from) {
+            return Err(TwilioRejection::UnknownSender);
+        }
+        let mut codes = self.codes.lock().expect("twilio codes lock");
+        codes.retain(|(_, id)| pending.contains(id));
+
+        let body = body.trim();
+        let (first, rest) = body.split_once(char::is_whitespace).unwrap_or((body, ""));
+        let first = first.trim_matches(|c| c == '[' || c == ']');
+        let coded = codes
+            .iter()
+            .position(|(code, _)| code.eq_ignore_ascii_case(first));
+        let (pos, answer) = match coded {
+            Some(pos) => (pos, rest.trim()),
+            None if first.len() >= CODE_LEN
+                && !rest.is_empty()
+                && first.chars().all(|c| c.is_ascii_hexdigit())
+                && !codes.is_empty() =>
+            {
+                return Err(TwilioRejection::UnknownCode(first.to_uppercase()));
+            }
+            None if codes.len() == 1 => (0, body),
+            None => return Err(TwilioRejection::Ambiguous),
+        };
+        let (_, message_id) = codes.remove(pos).expect("position exists");
+        Ok(TwilioReply {
+            message_id,
+            answer: answer.to_string(),
+            response_type: infer_response_type(answer),
+            responder: format!("twilio:{}", from),
+        })
+    }
+}
+
+/// First `max` characters of `text`, ending in `…` when cut.
+fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max.saturating_sub(1)).collect();
+    if max > 0 {
+        out.push(ELLIPSIS);
+    }
+    out
+}
+
+impl TwilioSink {
+    async fn deliver(&self, message: &Message) -> ProviderResult<()> {
+        if !self.wants(message) {
+            return Ok(());
+        }
+        let code = match message.content {
+            MessageContent::Notification { .. } => None,
+            _ => Some(self.assign_code(message.id)),
+        };
+        let text = Self::format_message(message, code.as_deref(), self.max_chars);
+        let mut last_err = None;
+        for to in &self.to {
+            if let Err(e) = self.send_sms(to, &text).await {
+                tracing::error!(provider = "twilio", to = %to, error = %e, "SMS delivery failed");
+                last_err = Some(e);
+            }
+        }
+        last_err.map_or(Ok(()), Err)
+    }
+}
+
+/// Replies arrive on the webhook route, so there is no reply stream; SMS cannot be edited.
+#[async_trait]
+impl Provider for TwilioSink {
+    fn name(&self) -> &str {
+        "twilio"
+    }
+
+    fn handles(&self, message: &Message) -> bool {
+        self.wants(message)
+    }
+
+    async fn send_prompt(&self, message: &Message) -> ProviderResult<Option<String>> {
+        self.deliver(message).await?;
+        Ok(None)
+    }
+
+    async fn send_notification(&self, message: &Message) -> ProviderResult<()> {
+        self.deliver(message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::DecisionOption;
+
+    fn sink(channels: &[&str]) -> TwilioSink {
+        TwilioSink::new(
+            &TwilioProviderConfig {
+                enabled: true,
+                account_sid: Some("AC123".to_string()),
+                from: Some("+15550100".to_string()),
+                to: vec!["+15550199".to_string()],
+                channels: channels.iter().map(|c| c.to_string()).collect(),
+                max_chars: None,
+                webhook_url: Some("https://example.com/api/providers/twilio/webhook".to_string()),
+            },
+            "token".to_string(),
+            &ProxySettings::default(),
+        )
+        .unwrap()
+    }
+
+    fn authorization(channel: &str, action: &str) -> Message {
+        Message::new(
+            channel.to_string(),
+            ailoop_core::models::SenderType::Agent,
+            MessageContent::Authorization {
+                action: action.to_string(),
+                context: None,
+                timeout_seconds: 60,
+            },
+        )
+    }
+
+    #[test]
+    fn test_only_opted_in_channels_and_prompts_are_sent() {
+        let sink = sink(&["oncall"]);
+        assert!(sink.wants(&authorization("oncall", "deploy")));
+        assert!(!sink.wants(&authorization("public", "deploy")));
+
+        let notify = |priority| {
+            Message::new(
+                "oncall".to_string(),
+                ailoop_core::models::SenderType::Agent,
+                MessageContent::Notification {
+                    text: "disk full".to_string(),
+                    priority,
+                },
+            )
+        };
+        assert!(sink.wants(&notify(NotificationPriority::Urgent)));
+        assert!(!sink.wants(&notify(NotificationPriority::High)));
+    }
+
+    #[test]
+    fn test_truncation_keeps_code_and_hint() {
+        let msg = authorization("oncall", &"x".repeat(500));
+        let text = TwilioSink::format_message(&msg, Some("AB12"), 80);
+        assert_eq!(text.chars().count(), 80);
+        assert!(text.starts_with("[AB12] Authorization [oncall]: xxx"));
+        assert!(text.ends_with("…\nReply AB12 yes/no"));
+
+        // Multi-byte text is cut on a character boundary.
+        let msg = authorization("oncall", &"é".repeat(100));
+        let text = TwilioSink::format_message(&msg, Some("AB12"), 60);
+        assert_eq!(text.chars().count(), 60);
+
+        let short = TwilioSink::format_message(&authorization("c", "ok"), Some("AB12"), 320);
+        assert_eq!(short, "[AB12] Authorization [c]: ok\nReply AB12 yes/no");
+    }
+
+    #[test]
+    fn test_decision_lists_numbered_options() {
+        let msg = Message::new(
+            "oncall".to_string(),
+            ailoop_core::models::SenderType::Agent,
+            MessageContent::Decision {
+                decision_id: "d1".to_string(),
+                summary: "Which db?".to_string(),
+                context_markdown: None,
+                options: ["pg", "sqlite"]
+                    .iter()
+                    .map(|l| DecisionOption {
+                        id: l.to_string(),
+                        label: l.to_string(),
+                        detail_markdown: None,
+                    })
+                    .collect(),
+                recommendation: None,
+                timeout_seconds: 0,
+            },
+        );
+        assert_eq!(
+            TwilioSink::format_message(&msg, Some("AB12"), 320),
+            "[AB12] Decision [oncall]: Which db? 1) pg 2) sqlite\nReply AB12 <n>"
+        );
+    }
+
+    #[test]
+    fn test_signature_verification() {
+        let sink = sink(&[]);
+        let params = vec![
+            ("From".to_string(), "+15550199".to_string()),
+            ("Body".to_string(), "yes".to_string()),
+        ];
+        let mut mac = Hmac::<Sha1>::new_from_slice(b"token").unwrap();
+        mac.update(b"https://example.com/api/providers/twilio/webhookBodyyesFrom+15550199");
+        let good = STANDARD.encode(mac.finalize().into_bytes());
+
+        assert!(sink.verify_signature(Some(&good), &params));
+        assert!(!sink.verify_signature(None, &params));
+        assert!(!sink.verify_signature(Some("bm9wZQ=="), &params));
+        let tampered = vec![("Body".to_string(), "no".to_string())];
+        assert!(!sink.verify_signature(Some(&good), &tampered));
+    }
+
+    #[test]
+    fn test_reply_matching() {
+        let sink = sink(&["oncall"]);
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let code_a = sink.assign_code(a);
+        let code_b = sink.assign_code(b);
+        assert_ne!(code_a, code_b);
+        assert_eq!(sink.assign_code(a), code_a);
+
+        assert_eq!(
+            sink.parse_reply("+15550000", "yes", &[a, b]),
+            Err(TwilioRejection::UnknownSender)
+        );
+        assert_eq!(
+            sink.parse_reply("+15550199", "yes", &[a, b]),
+            Err(TwilioRejection::Ambiguous)
+        );
+
+        let reply = sink
+            .parse_reply(
+                "+15550199",
+                &format!("{} no", code_b.to_lowercase()),
+                &[a, b],
+            )
+            .unwrap();
+        assert_eq!(reply.message_id, b);
+        assert_eq!(reply.response_type, ResponseType::AuthorizationDenied);
+        assert_eq!(reply.responder, "twilio:+15550199");
+
+        // One prompt left: the code is optional.
+        let reply = sink.parse_reply("+15550199", " ok ", &[a]).unwrap();
+        assert_eq!(reply.message_id, a);
+        assert_eq!(reply.response_type, ResponseType::AuthorizationApproved);
+
+        // Prompts answered elsewhere are forgotten.
+        sink.assign_code(a);
+        assert_eq!(
+            sink.parse_reply("+15550199", "yes", &[]),
+            Err(TwilioRejection::Ambiguous)
+        );
+    }
+}
@@ -0,0 +1,229 @@
+//! Webhook provider: deliver messages as JSON POSTs to a configured URL
+//!
+//! Each `[[providers.webhook]]` entry POSTs every message it takes to its `url`, with the
+//! message as the JSON body and `X-Ailoop-Event` set to `prompt` or `notification`. When the
+//! entry's secret is set, `X-Ailoop-Signature` carries `sha256=` and the hex HMAC-SHA256 of
+//! `<X-Ailoop-Timestamp>.<body>`, so the receiver can check the request came from this server
+//! and reject replays. A 2xx status means delivered; anything else is a failed delivery,
+//! retried and reported like any other provider.
+
+use crate::server::providers::{is_prompt, Provider, ProviderResult};
+use ailoop_core::models::{
+    Message, WebhookProviderConfig, DEFAULT_WEBHOOK_SECRET_ENV, DEFAULT_WEBHOOK_TIMEOUT_MS,
+};
+use ailoop_core::transport::proxy::ProxySettings;
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use sha2::Sha256;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+/// Longest response body excerpt kept in a delivery error.
+const MAX_ERROR_CHARS: usize = 500;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("webhook provider needs a name and a url")]
+    Incomplete,
+    #[error("webhook {name} cannot start: {source}")]
+    Client {
+        name: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("POST {url} failed: {source}")]
+    Request { url: String, source: reqwest::Error },
+    #[error("POST {url} returned {status}: {body}")]
+    Failed {
+        url: String,
+        status: reqwest::StatusCode,
+        body: String,
+    },
+}
+
+/// POSTs each message it takes to a URL.
+pub struct WebhookProvider {
+    name: String,
+    url: String,
+    secret: Option<String>,
+    channels: Vec<String>,
+    prompts_only: bool,
+    client: Client,
+}
+
+impl WebhookProvider {
+    /// Build from a `[[providers.webhook]]` entry, with the secret read from its `secret_env`.
+    pub fn from_config(
+        config: &WebhookProviderConfig,
+        proxy: &ProxySettings,
+    ) -> Result<Self, WebhookError> {
+        let secret_env = config
+            .secret_env
+            .as_deref()
+            .unwrap_or(DEFAULT_WEBHOOK_SECRET_ENV);
+        let secret = std::env::var(secret_env).ok().filter(|s| !s.is_empty());
+        if secret.is_none() {
+            tracing::warn!(
+                provider = %config.name,
+                "{} not set; webhook requests are unsigned",
+                secret_env
+            );
+        }
+        Self::new(config, secret, proxy)
+    }
+
+    pub fn new(
+        config: &WebhookProviderConfig,
+        secret: Option<String>,
+        proxy: &ProxySettings,
+    ) -> Result<Self, WebhookError> {
+        let name = config.name.trim();
+        let url = config.url.trim();
+        if name.is_empty() || url.is_empty() {
+            return Err(WebhookError::Incomplete);
+        }
+        let client_error =
+            |source: Box<dyn std::error::Error + Send + Sync>| WebhookError::Client {
+                name: name.to_string(),
+                source,
+            };
+        let timeout =
+            Duration::from_millis(config.timeout_ms.unwrap_or(DEFAULT_WEBHOOK_TIMEOUT_MS));
+        let client = proxy
+            .apply(Client::builder())
+            .map_err(|e| client_error(e.into()))?
+            .timeout(timeout)
+            .build()
+            .map_err(|e| client_error(e.into()))?;
+        Ok(Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            secret,
+            channels: config.channels.clone(),
+            prompts_only: config.prompts_only,
+            client,
+        })
+    }
+
+    async fn post(&self, message: &Message, event: &str) -> Result<(), WebhookError> {
+        let body = serde_json::to_vec(message).unwrap_or_default();
+        let timestamp = chrono::Utc::now().timestamp();
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-Ailoop-Event", event)
+            .header("X-Ailoop-Timestamp", timestamp.to_string());
+        if let Some(secret) = &self.secret {
+            request = request.header("X-Ailoop-Signature", sign(secret, timestamp, &body));
+        }
+        let request_error = |source| WebhookError::Request {
+            url: self.url.clone(),
+            source,
+        };
+        let response = request.body(body).send().await.map_err(request_error)?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body: String = response
+            .text()
+            .await
+            .unwrap_or_default()
+            .trim()
+            .chars()
+            .take(MAX_ERROR_CHARS)
+            .collect();
+        Err(WebhookError::Failed {
+            url: self.url.clone(),
+            status,
+            body,
+        })
+    }
+}
+
+/// `X-Ailoop-Signature` for `body` sent at `timestamp`: `sha256=` and the hex HMAC-SHA256 of
+/// `<timestamp>.<body>` keyed with `secret`.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let mut signature = String::from("sha256=");
+    for byte in mac.finalize().into_bytes() {
+        let _ = write!(signature, "{:02x}", byte);
+    }
+    signature
+}
+
+#[async_trait]
+impl Provider for WebhookProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn handles(&self, message: &Message) -> bool {
+        (self.channels.is_empty() || self.channels.contains(&message.channel))
+            && (!self.prompts_only || is_prompt(&message.content))
+    }
+
+    async fn send_prompt(&self, message: &Message) -> ProviderResult<Option<String>> {
+        self.post(message, "prompt").await?;
+        Ok(None)
+    }
+
+    async fn send_notification(&self, message: &Message) -> ProviderResult<()> {
+        self.post(message, "notification").await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_covers_timestamp_and_body() {
+        let signature = sign("s3cret", 1_700_000_000, br#"{"id":"1"}"#);
+        assert_eq!(
+            signature,
+            "sha256=2b9dee6c893e4bf012ad34ee7b89d492b9567b4f47740ccbf0f161ba3717dc08"
+        );
+        assert_ne!(signature, sign("s3cret", 1_700_000_001, br#"{"id":"1"}"#));
+        assert_ne!(signature, sign("s3cret", 1_700_000_000, br#"{"id":"2"}"#));
+    }
+
+    #[test]
+    fn test_channels_and_prompts_only_filter_messages() {
+        let provider = |channels: &[&str], prompts_only| {
+            WebhookProvider::new(
+                &WebhookProviderConfig {
+                    name: "pagerduty".to_string(),
+                    url: "https://events.example.com/ailoop".to_string(),
+                    channels: channels.iter().map(|c| c.to_string()).collect(),
+                    prompts_only,
+                    ..Default::default()
+                },
+                None,
+                &ProxySettings::default(),
+            )
+            .unwrap()
+        };
+        let prompt = ailoop_core::client::authorization("prod", "deploy v2", 60);
+        let note = ailoop_core::client::notification("prod", "deployed", "normal");
+        assert!(provider(&[], false).handles(&note));
+        assert!(provider(&["prod"], true).handles(&prompt));
+        assert!(!provider(&["prod"], true).handles(&note));
+        assert!(!provider(&["dev"], false).handles(&prompt));
+
+        assert!(matches!(
+            WebhookProvider::new(
+                &WebhookProviderConfig::default(),
+                None,
+                &ProxySettings::default()
+            ),
+            Err(WebhookError::Incomplete)
+        ));
+    }
+}
@@ -0,0 +1,311 @@
+//! Queue position feedback to agents
+//!
+//! The server puts one prompt to humans at a time, so an agent's prompt may wait behind others.
+//! While it does, the connection that sent it gets a low-priority system notification each time
+//! its place in line changes. `metadata.queued` carries the `prompt_id`, the `position` (messages
+//! queued before it on its channel, plus the prompt being answered now) and, once the channel
+//! has answered prompts, `eta_seconds`: the position times the channel's median answer time.
+//! The agent can then wait, boost the prompt, or have the prompt dropped. The notices go to no
+//! one else and are not recorded in history. Prompts sent over HTTP get none.
+//!
+//! A boost is a `{"type": "boost", "prompt_id": "...", "priority": "high"}` frame on the
+//! connection that sent the prompt. The prompt moves ahead of the queued messages of lower
+//! priority, and the agent hears its new place from the next notice. Each connection may boost
+//! `[boost] max_per_hour` times, and every boost is written to the event log.
+
+use ailoop_core::models::{
+    BoostConfig, Message, MessageContent, NotificationPriority, QueuePosition, SenderType,
+};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Prompts whose askers are remembered; the oldest are forgotten first.
+const MAX_TRACKED_PROMPTS: usize = 10_000;
+
+#[derive(Default)]
+struct State {
+    /// Agent connection that sent each prompt.
+    askers: HashMap<Uuid, Uuid>,
+    order: VecDeque<Uuid>,
+    /// Position last told per prompt, while it is queued.
+    told: HashMap<Uuid, usize>,
+    /// Whether a prompt is being put to humans now.
+    showing: bool,
+    boost: BoostConfig,
+    /// When each connection boosted a prompt, within the last hour.
+    boosts: HashMap<Uuid, VecDeque<DateTime<Utc>>>,
+}
+
+/// Why a boost was refused.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BoostRefused {
+    /// The prompt is not one this connection sent, or it was forgotten.
+    NotYours,
+    /// The connection used up its boosts for the hour.
+    RateLimited { max_per_hour: u32 },
+}
+
+impl BoostRefused {
+    /// Code of the protocol error sent back.
+    pub fn code(&self) -> &'static str {
+        match self {
+            BoostRefused::NotYours => "not_queued",
+            BoostRefused::RateLimited { .. } => "rate_limited",
+        }
+    }
+}
+
+impl std::fmt::Display for BoostRefused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoostRefused::NotYours => {
+                write!(f, "only the connection that sent a prompt may boost it")
+            }
+            BoostRefused::RateLimited { max_per_hour: 0 } => write!(f, "boosts are disabled"),
+            BoostRefused::RateLimited { max_per_hour } => {
+                write!(f, "at most {} boosts per hour", max_per_hour)
+            }
+        }
+    }
+}
+
+/// Who sent each queued prompt, and where they were last told it waits.
+#[derive(Default)]
+pub struct QueuePositions {
+    state: Mutex<State>,
+}
+
+impl QueuePositions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember that `connection` sent `prompt_id`.
+    pub fn record_prompt(&self, prompt_id: Uuid, connection: Uuid) {
+        let mut state = self.lock();
+        if state.askers.insert(prompt_id, connection).is_some() {
+            return;
+        }
+        if state.order.len() >= MAX_TRACKED_PROMPTS {
+            if let Some(oldest) = state.order.pop_front() {
+                state.askers.remove(&oldest);
+                state.told.remove(&oldest);
+            }
+        }
+        state.order.push_back(prompt_id);
+    }
+
+    pub fn set_boost_config(&self, config: BoostConfig) {
+        self.lock().boost = config;
+    }
+
+    /// Forget a closed connection.
+    pub fn remove_connection(&self, connection: &Uuid) {
+        let mut state = self.lock();
+        state.boosts.remove(connection);
+        let State { askers, told, .. } = &mut *state;
+        askers.retain(|prompt, asker| {
+            let keep = asker != connection;
+            if !keep {
+                told.remove(prompt);
+            }
+            keep
+        });
+    }
+
+    /// Count a boost of `prompt_id` by `connection` at `now`, if it sent the prompt and has
+    /// boosts left this hour.
+    pub fn try_boost(
+        &self,
+        prompt_id: Uuid,
+        connection: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<(), BoostRefused> {
+        let mut state = self.lock();
+        if state.askers.get(&prompt_id) != Some(&connection) {
+            return Err(BoostRefused::NotYours);
+        }
+        let max_per_hour = state.boost.max_per_hour();
+        let recent = state.boosts.entry(connection).or_default();
+        while recent
+            .front()
+            .is_some_and(|at| *at <= now - Duration::hours(1))
+        {
+            recent.pop_front();
+        }
+        if recent.len() >= max_per_hour as usize {
+            return Err(BoostRefused::RateLimited { max_per_hour });
+        }
+        recent.push_back(now);
+        Ok(())
+    }
+
+    /// Note whether a prompt is being put to humans.
+    pub fn set_showing(&self, showing: bool) {
+        self.lock().showing = showing;
+    }
+
+    /// Whether any prompt sent over a connection is waiting to be told its place.
+    pub fn is_tracking(&self) -> bool {
+        !self.lock().askers.is_empty()
+    }
+
+    /// Notices for the askers of prompts in `queues` whose position changed since they were
+    /// last told, each with the connection to send it to. `median_latency` gives a channel's
+    /// median answer time in seconds. Prompts no longer queued are told again if they return.
+    pub fn updates(
+        &self,
+        queues: &[(String, Vec<Message>)],
+        median_latency: impl Fn(&str) -> Option<f64>,
+    ) -> Vec<(Uuid, Message)> {
+        let mut state = self.lock();
+        let showing = usize::from(state.showing);
+        let mut queued = HashMap::new();
+        let mut notices = Vec::new();
+        for (channel, messages) in queues {
+            for (ahead, message) in messages.iter().enumerate() {
+                let Some(asker) = state.askers.get(&message.id).copied() else {
+                    continue;
+                };
+                let position = ahead + showing;
+                queued.insert(message.id, position);
+                if position == 0 || state.told.get(&message.id) == Some(&position) {
+                    continue;
+                }
+                let eta_seconds = median_latency(channel)
+                    .map(|latency| (latency * position as f64).round() as u64);
+                notices.push((
+                    asker,
+                    notice(
+                        message,
+                        QueuePosition {
+                            prompt_id: message.id,
+                            position,
+                            eta_seconds,
+                        },
+                    ),
+                ));
+            }
+        }
+        state.told = queued;
+        notices
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The low-priority system notification telling the sender of `prompt` where it waits.
+pub fn notice(prompt: &Message, queued: QueuePosition) -> Message {
+    let eta = match queued.eta_seconds {
+        Some(eta) => format!(
+            "; expect it to be shown in about {}",
+            crate::server::digest::duration(eta as f64)
+        ),
+        None => String::new(),
+    };
+    let mut notice = Message::new(
+        prompt.channel.clone(),
+        SenderType::System,
+        MessageContent::Notification {
+            text: format!(
+                "Your prompt is queued behind {} message(s) on '{}'{}",
+                queued.position, prompt.channel, eta
+            ),
+            priority: NotificationPriority::Low,
+        },
+    );
+    notice.metadata = Some(serde_json::json!({ "queued": queued }));
+    notice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(channel: &str) -> Message {
+        ailoop_core::client::authorization(channel, "deploy v2", 60)
+    }
+
+    #[test]
+    fn test_askers_are_told_when_their_position_changes() {
+        let positions = QueuePositions::new();
+        let (agent, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let queue = vec![prompt("ops"), prompt("ops"), prompt("ops")];
+        positions.record_prompt(queue[0].id, agent);
+        positions.record_prompt(queue[2].id, agent);
+        let latency = |channel: &str| (channel == "ops").then_some(90.0);
+
+        // First in line with nothing shown: about to be put to humans, so nothing to tell.
+        let updates = positions.updates(&[("ops".to_string(), queue.clone())], latency);
+        assert_eq!(updates.len(), 1);
+        let (to, notice) = &updates[0];
+        assert_eq!(*to, agent);
+        assert_eq!(
+            notice.queue_position(),
+            Some(QueuePosition {
+                prompt_id: queue[2].id,
+                position: 2,
+                eta_seconds: Some(180),
+            })
+        );
+        let MessageContent::Notification { text, .. } = &notice.content else {
+            panic!("expected a notification");
+        };
+        assert_eq!(
+            text,
+            "Your prompt is queued behind 2 message(s) on 'ops'; expect it to be shown in about 3m00s"
+        );
+        assert!(positions
+            .updates(&[("ops".to_string(), queue.clone())], latency)
+            .is_empty());
+
+        // The first prompt is being answered: the third still waits behind two.
+        positions.set_showing(true);
+        assert!(positions
+            .updates(&[("ops".to_string(), queue[1..].to_vec())], latency)
+            .is_empty());
+        let updates = positions.updates(&[("ops".to_string(), queue[2..].to_vec())], |_| None);
+        let queued = updates[0].1.queue_position().unwrap();
+        assert_eq!((queued.position, queued.eta_seconds), (1, None));
+
+        positions.remove_connection(&other);
+        assert!(positions.is_tracking());
+        positions.remove_connection(&agent);
+        assert!(!positions.is_tracking());
+    }
+
+    #[test]
+    fn test_boosts_are_limited_to_the_asker_and_per_hour() {
+        let positions = QueuePositions::new();
+        positions.set_boost_config(BoostConfig {
+            max_per_hour: Some(2),
+        });
+        let (agent, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let asked = prompt("ops");
+        positions.record_prompt(asked.id, agent);
+        let now = Utc::now();
+
+        assert_eq!(
+            positions.try_boost(asked.id, other, now),
+            Err(BoostRefused::NotYours)
+        );
+        assert_eq!(positions.try_boost(asked.id, agent, now), Ok(()));
+        assert_eq!(positions.try_boost(asked.id, agent, now), Ok(()));
+        assert_eq!(
+            positions.try_boost(asked.id, agent, now),
+            Err(BoostRefused::RateLimited { max_per_hour: 2 })
+        );
+        let later = now + Duration::minutes(61);
+        assert_eq!(positions.try_boost(asked.id, agent, later), Ok(()));
+
+        positions.set_boost_config(BoostConfig {
+            max_per_hour: Some(0),
+        });
+        let refused = positions.try_boost(asked.id, agent, later).unwrap_err();
+        assert_eq!(refused.to_string(), "boosts are disabled");
+    }
+}
@@ -0,0 +1,147 @@
+//! Which notifications each responder has read
+//!
+//! An agent's `ailoop say` is easily lost among prompts, so the server keeps read receipts for
+//! notifications and counts the unread ones per channel (`GET /api/v1/unread`, the channel
+//! badges of the web UI and `ailoop status --unread`). A responder reads a notification by
+//! pressing *Mark read* under it in Telegram, opening its channel in the web UI or calling
+//! `POST /api/v1/messages/{id}/read`; the operator reads it when the server terminal shows it.
+//! The handles of one `[team]` member share their receipts. Receipts are kept in memory, for
+//! the most recent notifications only.
+
+use ailoop_core::models::TeamDirectory;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Notifications whose receipts are kept (oldest forgotten first).
+const MAX_TRACKED: usize = 10_000;
+
+#[derive(Default)]
+struct Receipts {
+    team: TeamDirectory,
+    read: HashMap<Uuid, BTreeMap<String, DateTime<Utc>>>,
+    order: VecDeque<Uuid>,
+}
+
+/// Read receipts by notification id.
+#[derive(Default)]
+pub struct ReadLog {
+    inner: Mutex<Receipts>,
+}
+
+impl ReadLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_team(&self, team: TeamDirectory) {
+        self.lock().team = team;
+    }
+
+    /// Who `responder` reads as: `@name` for a team member's key or handle, else `responder`.
+    pub fn reader(&self, responder: &str) -> String {
+        self.lock().reader(responder)
+    }
+
+    /// Mark `message_id` read by `responder`; returns `false` when they had read it already.
+    pub fn mark(&self, message_id: Uuid, responder: &str) -> bool {
+        let mut receipts = self.lock();
+        let reader = receipts.reader(responder);
+        if !receipts.read.contains_key(&message_id) {
+            if receipts.order.len() >= MAX_TRACKED {
+                if let Some(oldest) = receipts.order.pop_front() {
+                    receipts.read.remove(&oldest);
+                }
+            }
+            receipts.order.push_back(message_id);
+        }
+        let readers = receipts.read.entry(message_id).or_default();
+        if readers.contains_key(&reader) {
+            return false;
+        }
+        readers.insert(reader, Utc::now());
+        true
+    }
+
+    pub fn has_read(&self, message_id: Uuid, responder: &str) -> bool {
+        let receipts = self.lock();
+        let reader = receipts.reader(responder);
+        receipts
+            .read
+            .get(&message_id)
+            .is_some_and(|readers| readers.contains_key(&reader))
+    }
+
+    /// Who has read `message_id`, and when.
+    pub fn readers(&self, message_id: Uuid) -> BTreeMap<String, DateTime<Utc>> {
+        self.lock()
+            .read
+            .get(&message_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Receipts> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Parse `/read <id>`, as sent by a provider's *Mark read* button.
+pub(crate) fn parse_command(answer: &str) -> Option<Uuid> {
+    let (command, id) = answer.trim().split_once(' ')?;
+    if !command.eq_ignore_ascii_case("/read") {
+        return None;
+    }
+    id.trim().parse().ok()
+}
+
+impl Receipts {
+    fn reader(&self, responder: &str) -> String {
+        match self.team.resolve(responder) {
+            Some(key) => format!("@{}", key),
+            None => responder.trim().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::TeamMember;
+
+    #[test]
+    fn test_team_handles_share_receipts() {
+        let mut members = BTreeMap::new();
+        members.insert(
+            "alice".to_string(),
+            TeamMember {
+                handles: vec!["telegram:alice".to_string(), "slack:U123".to_string()],
+                ..TeamMember::default()
+            },
+        );
+        let reads = ReadLog::new();
+        reads.set_team(TeamDirectory::new(members));
+        let id = Uuid::new_v4();
+
+        assert!(reads.mark(id, "telegram:@Alice"));
+        assert!(!reads.mark(id, "slack:U123"));
+        assert!(reads.has_read(id, "@alice"));
+        assert!(!reads.has_read(id, "telegram:bob"));
+        assert!(reads.mark(id, "telegram:bob"));
+        assert_eq!(
+            reads.readers(id).keys().collect::<Vec<_>>(),
+            vec!["@alice", "telegram:bob"]
+        );
+        assert_eq!(reads.reader("operator"), "operator");
+    }
+
+    #[test]
+    fn test_parses_read_command() {
+        let id = Uuid::new_v4();
+        assert_eq!(parse_command(&format!("/read {}", id)), Some(id));
+        assert_eq!(parse_command("/read"), None);
+        assert_eq!(parse_command("/read soon"), None);
+        assert_eq!(parse_command(&format!("read {}", id)), None);
+    }
+}
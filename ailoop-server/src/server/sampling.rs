@@ -0,0 +1,137 @@
+//! Sampling of the message stream for viewers
+//!
+//! A dashboard watching a busy `forward` channel does not need every token delta. A viewer
+//! asks for less in its hello frame, `{"subscribe": "*", "sample": {...}}`: `prompts_only`
+//! drops everything but prompts and their responses, and `max_per_second` passes at most that
+//! many messages per channel each second, dropping the rest. `channels` limits the sampling to
+//! channels matching those patterns. Prompts and responses always get through, and dropped
+//! messages stay in history.
+
+use ailoop_core::channel::pattern::matches_pattern;
+use ailoop_core::models::{Message, MessageContent};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// What a viewer asked to receive, from the `sample` field of its hello frame.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sampling {
+    /// Only prompts and responses.
+    #[serde(default)]
+    pub prompts_only: bool,
+    /// Most messages per channel per second.
+    #[serde(default)]
+    pub max_per_second: Option<u32>,
+    /// Channel patterns sampled; empty samples every channel.
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+impl Sampling {
+    /// Parse the `sample` field of a hello frame.
+    pub fn parse(value: &serde_json::Value) -> Result<Self, String> {
+        let sampling: Self =
+            serde_json::from_value(value.clone()).map_err(|e| format!("invalid sample: {}", e))?;
+        if sampling.max_per_second == Some(0) {
+            return Err("sample.max_per_second must be greater than 0".to_string());
+        }
+        Ok(sampling)
+    }
+}
+
+/// One viewer's sampling, with the messages passed per channel in the current second.
+#[derive(Debug)]
+pub struct Sampler {
+    sampling: Sampling,
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl Sampler {
+    pub fn new(sampling: Sampling) -> Self {
+        Self {
+            sampling,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `message` is sent to the viewer; counts it against its channel's rate if so.
+    pub fn admits(&self, message: &Message) -> bool {
+        let sampled = self.sampling.channels.is_empty()
+            || self
+                .sampling
+                .channels
+                .iter()
+                .any(|pattern| matches_pattern(pattern, &message.channel));
+        if !sampled || is_prompt_or_response(message) {
+            return true;
+        }
+        if self.sampling.prompts_only {
+            return false;
+        }
+        let Some(max) = self.sampling.max_per_second else {
+            return true;
+        };
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let window = windows.entry(message.channel.clone()).or_insert((now, 0));
+        if now.duration_since(window.0) >= Duration::from_secs(1) {
+            *window = (now, 0);
+        }
+        if window.1 >= max {
+            return false;
+        }
+        window.1 += 1;
+        true
+    }
+}
+
+fn is_prompt_or_response(message: &Message) -> bool {
+    message.prompt_text().is_some() || matches!(message.content, MessageContent::Response { .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{NotificationPriority, SenderType};
+
+    fn notification(channel: &str) -> Message {
+        Message::new(
+            channel.to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "token".to_string(),
+                priority: NotificationPriority::Low,
+            },
+        )
+    }
+
+    #[test]
+    fn test_sampling_drops_all_but_prompts_and_the_rate() {
+        let prompt = ailoop_core::client::authorization("build-1", "deploy", 60);
+
+        let prompts_only = Sampler::new(Sampling {
+            prompts_only: true,
+            ..Sampling::default()
+        });
+        assert!(!prompts_only.admits(&notification("build-1")));
+        assert!(prompts_only.admits(&prompt));
+
+        let rate = Sampler::new(
+            Sampling::parse(&serde_json::json!({"max_per_second": 2, "channels": ["build-*"]}))
+                .unwrap(),
+        );
+        let passed = (0..10)
+            .filter(|_| rate.admits(&notification("build-1")))
+            .count();
+        assert_eq!(passed, 2);
+        // Each channel has its own rate; prompts and unsampled channels are never dropped.
+        assert!(rate.admits(&notification("build-2")));
+        assert!(rate.admits(&prompt));
+        assert!((0..10).all(|_| rate.admits(&notification("ops"))));
+
+        assert!(Sampling::parse(&serde_json::json!({"max_per_second": 0})).is_err());
+        assert!(Sampling::parse(&serde_json::json!({"rate": 1})).is_err());
+    }
+}
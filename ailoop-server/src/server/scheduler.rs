@@ -0,0 +1,476 @@
+//! Scheduled messages and recurring reminders
+//!
+//! Agents hand a message and a due time to `POST /api/v1/schedule` (`ailoop say --at/--in`).
+//! The server holds it, saved to a JSON file when one is configured so it survives a restart,
+//! and once it is due delivers it like a message just received from the agent: recorded in
+//! history, broadcast and queued on its channel.
+//!
+//! Reminders (`[[scheduler.reminders]]`, `ailoop remind add`) are notifications sent the same
+//! way every time their cron schedule matches. Each one counts the occurrences sent since a
+//! human last acknowledged it, and can be told to skip its next occurrences.
+
+use crate::server::core::AiloopServer;
+use crate::state::AiloopAppState;
+use ailoop_core::models::{
+    CronSchedule, Message, NotificationPriority, Reminder, ReminderConfig, ScheduledMessage,
+    SchedulerConfig,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// How often due messages are looked for.
+const SCHEDULER_TICK: Duration = Duration::from_secs(1);
+
+/// Everything the scheduler keeps, as saved to its file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Schedule {
+    #[serde(default)]
+    messages: Vec<ScheduledMessage>,
+    #[serde(default)]
+    reminders: Vec<Reminder>,
+}
+
+/// Messages waiting for their due time, and recurring reminders.
+pub struct Scheduler {
+    file: Option<PathBuf>,
+    schedule: RwLock<Schedule>,
+}
+
+impl Scheduler {
+    /// A scheduler that forgets its messages when the server stops.
+    pub fn in_memory() -> Self {
+        Self {
+            file: None,
+            schedule: RwLock::new(Schedule::default()),
+        }
+    }
+
+    /// A scheduler saved to `file`, starting with the messages and reminders already in it.
+    pub fn load(file: impl Into<PathBuf>) -> Result<Self> {
+        let file = file.into();
+        let schedule = match std::fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid schedule file {:?}", file))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Schedule::default(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read schedule file {:?}", file))
+            }
+        };
+        Ok(Self {
+            file: Some(file),
+            schedule: RwLock::new(schedule),
+        })
+    }
+
+    /// Load from `[scheduler] file`, or `schedule.json` in the state directory, with the
+    /// reminders in `[scheduler] reminders`.
+    pub fn from_config(config: &SchedulerConfig) -> Result<Self> {
+        let file = config
+            .path()
+            .context("Could not determine state directory; set [scheduler] file")?;
+        let mut scheduler = Self::load(file.clone())?;
+        let schedule = scheduler.schedule.get_mut();
+        schedule.sync_config_reminders(&config.reminders)?;
+        schedule.save_to(&file)?;
+        Ok(scheduler)
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Hold `entry` until it is due.
+    pub async fn add(&self, entry: ScheduledMessage) -> Result<()> {
+        let mut schedule = self.schedule.write().await;
+        schedule.messages.push(entry);
+        if let Err(e) = self.save(&schedule) {
+            schedule.messages.pop();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Messages waiting to be sent, soonest first, optionally on one channel only.
+    pub async fn list(&self, channel: Option<&str>) -> Vec<ScheduledMessage> {
+        let mut listed: Vec<ScheduledMessage> = self
+            .schedule
+            .read()
+            .await
+            .messages
+            .iter()
+            .filter(|e| channel.is_none_or(|ch| e.message.channel == ch))
+            .cloned()
+            .collect();
+        listed.sort_by_key(|e| e.due_at);
+        listed
+    }
+
+    /// Drop a message before it is sent.
+    pub async fn cancel(&self, id: &Uuid) -> Result<Option<ScheduledMessage>> {
+        let mut schedule = self.schedule.write().await;
+        let Some(index) = schedule.messages.iter().position(|e| e.id == *id) else {
+            return Ok(None);
+        };
+        let cancelled = schedule.messages.remove(index);
+        self.save(&schedule)?;
+        Ok(Some(cancelled))
+    }
+
+    /// Remove and return the messages due at `now`, oldest due time first.
+    pub async fn take_due(&self, now: DateTime<Utc>) -> Vec<ScheduledMessage> {
+        let mut schedule = self.schedule.write().await;
+        if !schedule.messages.iter().any(|e| e.due_at <= now) {
+            return Vec::new();
+        }
+        let (mut due, waiting): (Vec<_>, Vec<_>) =
+            schedule.messages.drain(..).partition(|e| e.due_at <= now);
+        schedule.messages = waiting;
+        if let Err(e) = self.save(&schedule) {
+            tracing::error!(error = %e, "Failed to save schedule file");
+        }
+        due.sort_by_key(|e| e.due_at);
+        due
+    }
+
+    /// Start sending `reminder` on its schedule.
+    pub async fn add_reminder(&self, reminder: Reminder) -> Result<()> {
+        let mut schedule = self.schedule.write().await;
+        schedule.reminders.push(reminder);
+        if let Err(e) = self.save(&schedule) {
+            schedule.reminders.pop();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Reminders, soonest first, optionally on one channel only.
+    pub async fn reminders(&self, channel: Option<&str>) -> Vec<Reminder> {
+        let mut listed: Vec<Reminder> = self
+            .schedule
+            .read()
+            .await
+            .reminders
+            .iter()
+            .filter(|r| channel.is_none_or(|ch| r.channel == ch))
+            .cloned()
+            .collect();
+        listed.sort_by_key(|r| (r.next_due.is_none(), r.next_due));
+        listed
+    }
+
+    /// Stop a reminder. Reminders from the config file are only removed by editing it.
+    pub async fn remove_reminder(&self, id: &Uuid) -> Result<Option<Reminder>> {
+        let mut schedule = self.schedule.write().await;
+        let Some(index) = schedule.reminders.iter().position(|r| r.id == *id) else {
+            return Ok(None);
+        };
+        if schedule.reminders[index].from_config {
+            anyhow::bail!("reminder {} is defined in the config file", id);
+        }
+        let removed = schedule.reminders.remove(index);
+        self.save(&schedule)?;
+        Ok(Some(removed))
+    }
+
+    /// Let the next `count` occurrences of a reminder pass without sending it.
+    pub async fn skip_reminder(&self, id: &Uuid, count: u32) -> Result<Option<Reminder>> {
+        self.update_reminder(id, |reminder| reminder.skip += count)
+            .await
+    }
+
+    /// Record that a human has seen the reminder's notifications.
+    pub async fn acknowledge_reminder(&self, id: &Uuid) -> Result<Option<Reminder>> {
+        self.update_reminder(id, |reminder| {
+            reminder.unacknowledged = 0;
+            reminder.acknowledged_at = Some(Utc::now());
+        })
+        .await
+    }
+
+    async fn update_reminder(
+        &self,
+        id: &Uuid,
+        update: impl FnOnce(&mut Reminder),
+    ) -> Result<Option<Reminder>> {
+        let mut schedule = self.schedule.write().await;
+        let Some(reminder) = schedule.reminders.iter_mut().find(|r| r.id == *id) else {
+            return Ok(None);
+        };
+        update(reminder);
+        let updated = reminder.clone();
+        self.save(&schedule)?;
+        Ok(Some(updated))
+    }
+
+    /// Advance the reminders due at `now` to their next occurrence, returning the notifications
+    /// to send for those not being skipped. Occurrences missed while the server was down are
+    /// sent once.
+    pub async fn take_due_reminders(&self, now: DateTime<Utc>) -> Vec<Message> {
+        let mut schedule = self.schedule.write().await;
+        if !schedule.reminders.iter().any(|r| r.is_due(now)) {
+            return Vec::new();
+        }
+        let mut due = Vec::new();
+        for reminder in schedule.reminders.iter_mut().filter(|r| r.is_due(now)) {
+            reminder.schedule_after(now);
+            if reminder.skip > 0 {
+                reminder.skip -= 1;
+                reminder.skipped += 1;
+                tracing::info!(reminder_id = %reminder.id, channel = %reminder.channel, "Skipping reminder");
+                continue;
+            }
+            let message = reminder.notification();
+            reminder.last_sent = Some(now);
+            reminder.last_message_id = Some(message.id);
+            reminder.unacknowledged += 1;
+            due.push(message);
+        }
+        if let Err(e) = self.save(&schedule) {
+            tracing::error!(error = %e, "Failed to save schedule file");
+        }
+        due
+    }
+
+    /// Write the schedule to its file, if it has one.
+    fn save(&self, schedule: &Schedule) -> Result<()> {
+        match &self.file {
+            Some(file) => schedule.save_to(file),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl Schedule {
+    /// Replace the config-file reminders with `configured`, keeping the tracking of those that
+    /// did not change.
+    fn sync_config_reminders(&mut self, configured: &[ReminderConfig]) -> Result<()> {
+        let mut kept: Vec<Reminder> = Vec::new();
+        for config in configured {
+            let cron: CronSchedule = config
+                .schedule
+                .parse()
+                .map_err(|e| anyhow::anyhow!("scheduler.reminders: {}", e))?;
+            let existing = self.reminders.iter().position(|r| {
+                r.from_config
+                    && r.channel == config.channel
+                    && r.schedule == cron
+                    && r.text == config.text
+            });
+            let mut reminder = match existing {
+                Some(index) => self.reminders.remove(index),
+                None => Reminder::new(
+                    config.channel.clone(),
+                    cron,
+                    config.text.clone(),
+                    NotificationPriority::Normal,
+                ),
+            };
+            reminder.from_config = true;
+            if let Some(priority) = &config.priority {
+                reminder.priority = priority.clone();
+            }
+            kept.push(reminder);
+        }
+        self.reminders.retain(|r| !r.from_config);
+        self.reminders.extend(kept);
+        Ok(())
+    }
+
+    /// Write to `file` (through a temporary file, so a crash never leaves half of it).
+    fn save_to(&self, file: &Path) -> Result<()> {
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let tmp = file.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write {:?}", tmp))?;
+        std::fs::rename(&tmp, file).with_context(|| format!("Failed to write {:?}", file))
+    }
+}
+
+/// Deliver the messages and reminders that are due, stamped with the time they are sent.
+pub async fn deliver_due(state: &AiloopAppState, now: DateTime<Utc>) -> usize {
+    let mut due: Vec<Message> = state
+        .scheduler
+        .take_due(now)
+        .await
+        .into_iter()
+        .map(|entry| entry.message)
+        .collect();
+    due.extend(state.scheduler.take_due_reminders(now).await);
+    let count = due.len();
+    for mut message in due {
+        message.timestamp = now;
+        tracing::info!(message_id = %message.id, channel = %message.channel, "Sending scheduled message");
+        AiloopServer::accept_agent_message(
+            &state.channel_manager,
+            &state.message_history,
+            &state.broadcast_manager,
+            message,
+        );
+    }
+    count
+}
+
+/// Send scheduled messages and reminders as they fall due until `token` is cancelled.
+pub(crate) fn spawn_scheduler(
+    state: Arc<AiloopAppState>,
+    token: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(SCHEDULER_TICK);
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tick.tick() => {
+                    deliver_due(&state, Utc::now()).await;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{MessageContent, SenderType};
+
+    fn notification(text: &str) -> Message {
+        Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: text.to_string(),
+                priority: NotificationPriority::Normal,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn due_messages_are_taken_once_and_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("schedule.json");
+        let now = Utc::now();
+        let scheduler = Scheduler::load(&file).unwrap();
+        let soon =
+            ScheduledMessage::new(now + chrono::Duration::minutes(5), notification("standup"));
+        let later = ScheduledMessage::new(now + chrono::Duration::hours(2), notification("deploy"));
+        scheduler.add(later.clone()).await.unwrap();
+        scheduler.add(soon.clone()).await.unwrap();
+
+        let listed: Vec<_> = scheduler.list(None).await.iter().map(|e| e.id).collect();
+        assert_eq!(listed, [soon.id, later.id]);
+        assert!(scheduler.take_due(now).await.is_empty());
+
+        let due = scheduler
+            .take_due(now + chrono::Duration::minutes(10))
+            .await;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, soon.id);
+
+        let restarted = Scheduler::load(&file).unwrap();
+        let listed: Vec<_> = restarted
+            .list(Some("ops"))
+            .await
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(listed, [later.id]);
+        assert!(restarted.list(Some("dev")).await.is_empty());
+        assert!(restarted.cancel(&later.id).await.unwrap().is_some());
+        assert!(restarted.cancel(&later.id).await.unwrap().is_none());
+        assert!(Scheduler::load(&file).unwrap().list(None).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reminders_recur_with_skips_and_acknowledgements() {
+        let scheduler = Scheduler::in_memory();
+        let reminder = Reminder::new(
+            "ops".to_string(),
+            "* * * * *".parse().unwrap(),
+            "Rotate credentials".to_string(),
+            NotificationPriority::High,
+        );
+        let id = reminder.id;
+        let first = reminder.next_due.unwrap();
+        scheduler.add_reminder(reminder).await.unwrap();
+        assert!(scheduler
+            .take_due_reminders(first - chrono::Duration::seconds(1))
+            .await
+            .is_empty());
+
+        let sent = scheduler.take_due_reminders(first).await;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            sent[0].metadata.as_ref().unwrap()["reminder_id"],
+            id.to_string()
+        );
+        // Already moved on to the next minute.
+        assert!(scheduler.take_due_reminders(first).await.is_empty());
+
+        let skipped = scheduler.skip_reminder(&id, 1).await.unwrap().unwrap();
+        assert_eq!(skipped.skip, 1);
+        let second = skipped.next_due.unwrap();
+        assert!(scheduler.take_due_reminders(second).await.is_empty());
+        let third = scheduler.reminders(None).await[0].next_due.unwrap();
+        assert_eq!(scheduler.take_due_reminders(third).await.len(), 1);
+
+        let listed = &scheduler.reminders(Some("ops")).await[0];
+        assert_eq!(
+            (listed.unacknowledged, listed.skipped, listed.skip),
+            (2, 1, 0)
+        );
+        let acked = scheduler.acknowledge_reminder(&id).await.unwrap().unwrap();
+        assert_eq!(acked.unacknowledged, 0);
+        assert!(acked.acknowledged_at.is_some());
+        assert!(scheduler.remove_reminder(&id).await.unwrap().is_some());
+        assert!(scheduler.acknowledge_reminder(&id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn config_reminders_keep_their_tracking_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = SchedulerConfig {
+            file: Some(dir.path().join("schedule.json").display().to_string()),
+            reminders: vec![ReminderConfig {
+                channel: "ops".to_string(),
+                schedule: "0 9 * * MON".to_string(),
+                text: "Rotate credentials".to_string(),
+                priority: None,
+            }],
+        };
+        let scheduler = Scheduler::from_config(&config).unwrap();
+        let id = scheduler.reminders(None).await[0].id;
+        scheduler.acknowledge_reminder(&id).await.unwrap();
+        assert!(scheduler.remove_reminder(&id).await.is_err());
+
+        let restarted = Scheduler::from_config(&config).unwrap();
+        let reminders = restarted.reminders(None).await;
+        assert_eq!(reminders.len(), 1);
+        assert_eq!(reminders[0].id, id);
+        assert!(reminders[0].acknowledged_at.is_some());
+
+        config.reminders[0].text = "Rotate the API keys".to_string();
+        let changed = Scheduler::from_config(&config)
+            .unwrap()
+            .reminders(None)
+            .await;
+        assert_eq!(changed.len(), 1);
+        assert_ne!(changed[0].id, id);
+        assert!(changed[0].from_config);
+    }
+}
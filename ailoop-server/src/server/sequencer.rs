@@ -0,0 +1,150 @@
+//! Ordered delivery of a client's concurrent messages
+//!
+//! A client firing `say`/`ask` calls in quick succession sends each over its own request, so
+//! they can reach the server out of order. Such clients number their messages
+//! (`Message.sequence`: a stream id per client instance and a `seq` counting from 1). Before a
+//! numbered message is recorded it waits for its turn: until every earlier `seq` of its stream
+//! has been handled, or for at most the reorder window, after which a missing message is given
+//! up on. A message is handled once its [`Turn`] is dropped, whether it was accepted or refused.
+
+use ailoop_core::models::Sequence;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// How long a message waits for the ones numbered before it.
+pub const REORDER_WINDOW: Duration = Duration::from_secs(2);
+
+/// Streams idle for this long are forgotten.
+const STREAM_IDLE: Duration = Duration::from_secs(600);
+
+struct Stream {
+    /// The next `seq` to be handled.
+    next: u64,
+    last_seen: Instant,
+}
+
+pub struct Sequencer {
+    streams: Mutex<HashMap<Uuid, Stream>>,
+    turn_taken: Notify,
+    window: Duration,
+}
+
+impl Default for Sequencer {
+    fn default() -> Self {
+        Self::new(REORDER_WINDOW)
+    }
+}
+
+impl Sequencer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            streams: Mutex::new(HashMap::new()),
+            turn_taken: Notify::new(),
+            window,
+        }
+    }
+
+    /// Wait until `sequence` is next on its stream, or the reorder window has passed. Messages
+    /// arriving after a later one was already handled go through at once.
+    pub async fn wait_turn(&self, sequence: Sequence) -> Turn<'_> {
+        let deadline = Instant::now() + self.window;
+        loop {
+            let taken = self.turn_taken.notified();
+            if self.is_turn(sequence) {
+                break;
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() || tokio::time::timeout(left, taken).await.is_err() {
+                tracing::debug!(
+                    stream = %sequence.stream,
+                    seq = sequence.seq,
+                    "Earlier messages did not arrive within the reorder window"
+                );
+                break;
+            }
+        }
+        Turn {
+            sequencer: self,
+            sequence,
+        }
+    }
+
+    fn is_turn(&self, sequence: Sequence) -> bool {
+        let now = Instant::now();
+        let mut streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        streams.retain(|id, s| *id == sequence.stream || now - s.last_seen < STREAM_IDLE);
+        let stream = streams.entry(sequence.stream).or_insert(Stream {
+            next: 1,
+            last_seen: now,
+        });
+        stream.last_seen = now;
+        sequence.seq <= stream.next
+    }
+
+    fn finish(&self, sequence: Sequence) {
+        let mut streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(stream) = streams.get_mut(&sequence.stream) {
+            stream.next = stream.next.max(sequence.seq + 1);
+        }
+        drop(streams);
+        self.turn_taken.notify_waiters();
+    }
+}
+
+/// A numbered message's turn on its stream; dropping it lets the next message through.
+pub struct Turn<'a> {
+    sequencer: &'a Sequencer,
+    sequence: Sequence,
+}
+
+impl Drop for Turn<'_> {
+    fn drop(&mut self) {
+        self.sequencer.finish(self.sequence);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_messages_are_handled_in_sequence_order() {
+        let sequencer = Arc::new(Sequencer::default());
+        let stream = Uuid::new_v4();
+        let handled = Arc::new(Mutex::new(Vec::new()));
+
+        let mut tasks = Vec::new();
+        for seq in [3, 1, 2] {
+            let sequencer = Arc::clone(&sequencer);
+            let handled = Arc::clone(&handled);
+            tasks.push(tokio::spawn(async move {
+                let _turn = sequencer.wait_turn(Sequence { stream, seq }).await;
+                handled.lock().unwrap().push(seq);
+            }));
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*handled.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_missing_message_is_given_up_on() {
+        let sequencer = Sequencer::new(Duration::from_millis(50));
+        let stream = Uuid::new_v4();
+        let started = Instant::now();
+        drop(sequencer.wait_turn(Sequence { stream, seq: 2 }).await);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        // Seq 1 arriving late is not held back behind what already went through.
+        let started = Instant::now();
+        drop(sequencer.wait_turn(Sequence { stream, seq: 1 }).await);
+        drop(sequencer.wait_turn(Sequence { stream, seq: 3 }).await);
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+}
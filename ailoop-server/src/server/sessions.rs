@@ -0,0 +1,347 @@
+//! Agent sessions reconstructed from forwarded messages
+//!
+//! `ailoop forward` tags each message with the agent's `session_id` and the parsed event
+//! (`metadata.event`). Those events are kept per session, independent of channel history
+//! eviction, and rendered as a transcript with tool calls and their results folded together.
+
+use ailoop_core::models::{Message, MessageContent};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use tokio::sync::RwLock;
+
+/// Sessions kept; the least recently updated one is evicted first.
+const MAX_SESSIONS: usize = 200;
+/// Events kept per session (FIFO).
+const MAX_EVENTS_PER_SESSION: usize = 5000;
+/// Longest tool-argument summary shown in a Markdown transcript.
+const MAX_ARGS_SUMMARY: usize = 120;
+
+/// One forwarded agent event.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEvent {
+    pub timestamp: DateTime<Utc>,
+    pub message_id: uuid::Uuid,
+    /// Event type name (`assistant`, `tool_call`, `result`, ...).
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub call_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub args: Option<serde_json::Value>,
+}
+
+/// Everything recorded for one agent session.
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub id: String,
+    pub channel: String,
+    pub agent_type: Option<String>,
+    /// Events evicted because the session grew past its limit.
+    pub dropped_events: usize,
+    pub events: VecDeque<SessionEvent>,
+}
+
+/// Per-session event log fed by [`crate::server::history::MessageHistory`].
+#[derive(Default)]
+pub struct SessionLog {
+    inner: RwLock<HashMap<String, Session>>,
+}
+
+impl SessionLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `message` when it carries a `session_id` in its metadata.
+    pub async fn record(&self, message: &Message) {
+        let Some(metadata) = message.metadata.as_ref() else {
+            return;
+        };
+        let Some(session_id) = metadata.get("session_id").and_then(|v| v.as_str()) else {
+            return;
+        };
+        let event = session_event(message, metadata);
+
+        let mut sessions = self.inner.write().await;
+        if !sessions.contains_key(session_id) && sessions.len() >= MAX_SESSIONS {
+            let oldest = sessions
+                .values()
+                .min_by_key(|s| s.events.back().map(|e| e.timestamp))
+                .map(|s| s.id.clone());
+            if let Some(oldest) = oldest {
+                sessions.remove(&oldest);
+            }
+        }
+        let session = sessions
+            .entry(session_id.to_string())
+            .or_insert_with(|| Session {
+                id: session_id.to_string(),
+                channel: message.channel.clone(),
+                agent_type: None,
+                dropped_events: 0,
+                events: VecDeque::new(),
+            });
+        if let Some(agent_type) = metadata.get("agent_type").and_then(|v| v.as_str()) {
+            session.agent_type = Some(agent_type.to_string());
+        }
+        session.events.push_back(event);
+        while session.events.len() > MAX_EVENTS_PER_SESSION {
+            session.events.pop_front();
+            session.dropped_events += 1;
+        }
+    }
+
+    /// A copy of the session, if known.
+    pub async fn get(&self, session_id: &str) -> Option<Session> {
+        self.inner.read().await.get(session_id).cloned()
+    }
+
+    /// Drop the events stamped before `before` from the sessions on `channel` (every channel
+    /// when `None`), forgetting sessions left empty. Returns how many events were dropped, or
+    /// with `dry_run` would be.
+    pub async fn purge(
+        &self,
+        channel: Option<&str>,
+        before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> usize {
+        let mut sessions = self.inner.write().await;
+        let mut purged = 0;
+        for session in sessions
+            .values_mut()
+            .filter(|s| channel.is_none_or(|ch| s.channel == ch))
+        {
+            let total = session.events.len();
+            if dry_run {
+                purged += session
+                    .events
+                    .iter()
+                    .filter(|e| e.timestamp < before)
+                    .count();
+            } else {
+                session.events.retain(|e| e.timestamp >= before);
+                purged += total - session.events.len();
+            }
+        }
+        if !dry_run {
+            sessions.retain(|_, s| !s.events.is_empty());
+        }
+        purged
+    }
+}
+
+/// The event described by `metadata.event`, or the notification text for messages from
+/// older clients that do not send it.
+fn session_event(message: &Message, metadata: &serde_json::Value) -> SessionEvent {
+    let event = metadata.get("event");
+    let field = |key: &str| {
+        event
+            .and_then(|e| e.get(key))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    let text = field("text").or_else(|| match &message.content {
+        MessageContent::Notification { text, .. } => Some(text.clone()),
+        _ => None,
+    });
+    SessionEvent {
+        timestamp: message.timestamp,
+        message_id: message.id,
+        kind: field("type").unwrap_or_else(|| "notification".to_string()),
+        text,
+        tool: field("tool"),
+        status: field("status"),
+        call_id: field("call_id"),
+        args: metadata
+            .get("tool_args")
+            .filter(|args| !args.is_null())
+            .cloned(),
+    }
+}
+
+/// One line or paragraph of a transcript, after folding.
+enum Entry<'a> {
+    Text(&'a SessionEvent),
+    Tool {
+        first: &'a SessionEvent,
+        status: Option<&'a str>,
+    },
+}
+
+impl Session {
+    /// Markdown narrative of the session. Updates to a tool call (same `call_id`) are folded
+    /// into the line of the call, and consecutive tool calls form one list.
+    pub fn to_markdown(&self) -> String {
+        let mut entries: Vec<Entry> = Vec::new();
+        for event in &self.events {
+            if event.kind != "tool_call" {
+                entries.push(Entry::Text(event));
+                continue;
+            }
+            let earlier = event.call_id.as_ref().and_then(|id| {
+                entries.iter_mut().rev().find_map(|entry| match entry {
+                    Entry::Tool { first, status } if first.call_id.as_ref() == Some(id) => {
+                        Some(status)
+                    }
+                    _ => None,
+                })
+            });
+            match earlier {
+                Some(status) => *status = event.status.as_deref().or(*status),
+                None => entries.push(Entry::Tool {
+                    first: event,
+                    status: event.status.as_deref(),
+                }),
+            }
+        }
+
+        let mut out = format!("# Session {}\n\n", self.id);
+        if let Some(agent_type) = &self.agent_type {
+            let _ = writeln!(out, "- **Agent:** {}", agent_type);
+        }
+        let _ = writeln!(out, "- **Channel:** {}", self.channel);
+        if let (Some(first), Some(last)) = (self.events.front(), self.events.back()) {
+            let _ = writeln!(out, "- **Started:** {}", first.timestamp.to_rfc3339());
+            let _ = writeln!(out, "- **Last event:** {}", last.timestamp.to_rfc3339());
+        }
+        let _ = writeln!(out, "- **Events:** {}", self.events.len());
+        if self.dropped_events > 0 {
+            let _ = write!(
+                out,
+                "\n_{} earlier events were dropped._\n",
+                self.dropped_events
+            );
+        }
+
+        let mut in_list = false;
+        for entry in &entries {
+            let is_tool = matches!(entry, Entry::Tool { .. });
+            if !(is_tool && in_list) {
+                out.push('\n');
+            }
+            in_list = is_tool;
+            match entry {
+                Entry::Tool { first, status } => {
+                    let _ = write!(out, "- `{}`", first.tool.as_deref().unwrap_or("tool"));
+                    if let Some(status) = status {
+                        let _ = write!(out, " ({})", status);
+                    }
+                    if let Some(args) = first.args.as_ref().map(args_summary) {
+                        let _ = write!(out, ": `{}`", args);
+                    }
+                    out.push('\n');
+                }
+                Entry::Text(event) => {
+                    let text = event.text.as_deref().unwrap_or_default().trim();
+                    let _ = match event.kind.as_str() {
+                        "assistant" => writeln!(out, "{}", text),
+                        "user" => writeln!(out, "**User:** {}", text),
+                        "result" => writeln!(out, "**Result:** {}", text),
+                        "error" => writeln!(out, "> **Error:** {}", text),
+                        kind => writeln!(out, "**{}:** {}", kind, text),
+                    };
+                }
+            }
+        }
+        out
+    }
+}
+
+/// The `command`/`cmd` argument, or the arguments as compact JSON, on one line.
+fn args_summary(args: &serde_json::Value) -> String {
+    let summary = ["command", "cmd"]
+        .iter()
+        .find_map(|key| args.get(*key).and_then(|v| v.as_str()))
+        .map(str::to_string)
+        .unwrap_or_else(|| args.to_string());
+    let mut summary: String = summary
+        .chars()
+        .map(|c| match c {
+            '\n' | '\r' => ' ',
+            '`' => '\'',
+            c => c,
+        })
+        .collect();
+    if summary.chars().count() > MAX_ARGS_SUMMARY {
+        summary = summary.chars().take(MAX_ARGS_SUMMARY - 1).collect();
+        summary.push('…');
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{NotificationPriority, SenderType};
+    use serde_json::json;
+
+    fn forwarded(session_id: &str, event: serde_json::Value) -> Message {
+        let mut message = Message::new(
+            "public".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "[cursor] ...".to_string(),
+                priority: NotificationPriority::Normal,
+            },
+        );
+        message.metadata = Some(json!({
+            "agent_type": "cursor",
+            "session_id": session_id,
+            "event": event,
+        }));
+        message
+    }
+
+    #[tokio::test]
+    async fn ignores_messages_without_session() {
+        let log = SessionLog::new();
+        let message = Message::new(
+            "public".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "hi".to_string(),
+                priority: NotificationPriority::Normal,
+            },
+        );
+        log.record(&message).await;
+        assert!(log.inner.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn folds_tool_call_updates() {
+        let log = SessionLog::new();
+        for event in [
+            json!({"type": "assistant", "text": "Listing files."}),
+            json!({"type": "tool_call", "tool": "shell", "status": "started", "call_id": "c1"}),
+            json!({"type": "tool_call", "tool": "read", "status": "started", "call_id": "c2"}),
+            json!({"type": "tool_call", "tool": "shell", "status": "completed", "call_id": "c1"}),
+            json!({"type": "result", "text": "Done"}),
+        ] {
+            log.record(&forwarded("s1", event)).await;
+        }
+
+        let session = log.get("s1").await.unwrap();
+        assert_eq!(session.events.len(), 5);
+        let markdown = session.to_markdown();
+        assert!(markdown.starts_with("# Session s1\n"));
+        assert!(markdown.contains("- **Agent:** cursor\n"));
+        assert!(markdown.contains(
+            "\nListing files.\n\n- `shell` (completed)\n- `read` (started)\n\n**Result:** Done\n"
+        ));
+    }
+
+    #[test]
+    fn summarizes_arguments() {
+        assert_eq!(args_summary(&json!({"command": "ls\n-la"})), "ls -la");
+        assert_eq!(args_summary(&json!({"path": "a`b"})), r#"{"path":"a'b"}"#);
+        let long = args_summary(&json!({"command": "x".repeat(500)}));
+        assert_eq!(long.chars().count(), MAX_ARGS_SUMMARY);
+    }
+}
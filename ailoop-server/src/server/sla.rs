@@ -0,0 +1,430 @@
+//! Response SLAs and breach alerts
+//!
+//! Each `[[sla]]` rule promises that prompts of some kinds on some channels are answered within
+//! `within_seconds`. The tracker observes the broadcast stream next to the response metrics: an
+//! agent prompt starts its clocks and a response stops them. A prompt answered late, timed out,
+//! or still waiting when an SLA runs out breaches it, and the monitor posts a high-priority
+//! alert on the rule's `alert_channel` (`ops` by default), once per prompt and rule. Compliance
+//! per rule is reported by `GET /api/stats` under `responses.slas` and by `ailoop status
+//! --stats`. Clocks run in memory, so prompts waiting at a restart are not followed.
+
+use crate::state::AiloopAppState;
+use ailoop_core::models::{
+    Message, MessageContent, NotificationPriority, PromptKind, ResponseType, SenderType, SlaConfig,
+    SlaSummary,
+};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// Prompts followed at once (oldest forgotten first).
+const MAX_OPEN: usize = 10_000;
+/// Met and breached SLAs remembered for the stats (oldest evicted first).
+const MAX_OUTCOMES: usize = 10_000;
+/// How often running clocks are checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A prompt that missed an SLA.
+#[derive(Debug, Clone)]
+pub struct Breach {
+    /// Name of the rule.
+    pub sla: String,
+    pub within: Duration,
+    pub alert_channel: String,
+    pub prompt: Message,
+    /// How long the prompt had waited when the breach was noticed.
+    pub waited: Duration,
+    /// How the prompt was settled, if it was.
+    pub outcome: Option<ResponseType>,
+}
+
+struct OpenPrompt {
+    prompt: Message,
+    /// Rules whose clock is still running for this prompt.
+    running: Vec<usize>,
+}
+
+struct Outcome {
+    rule: usize,
+    at: DateTime<Utc>,
+    met: bool,
+}
+
+#[derive(Default)]
+struct Tracked {
+    rules: Vec<SlaConfig>,
+    open: HashMap<Uuid, OpenPrompt>,
+    order: VecDeque<Uuid>,
+    outcomes: VecDeque<Outcome>,
+    /// Breaches noticed when a response came in, waiting to be alerted.
+    unalerted: Vec<Breach>,
+}
+
+/// Clocks of the `[[sla]]` rules.
+#[derive(Default)]
+pub struct SlaTracker {
+    tracked: Mutex<Tracked>,
+}
+
+impl SlaTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follow `rules` from now on; clocks already running are dropped.
+    pub fn set_rules(&self, rules: Vec<SlaConfig>) {
+        *self.lock() = Tracked {
+            rules,
+            ..Tracked::default()
+        };
+    }
+
+    /// Note a broadcast message: an agent prompt starts the clocks of the rules covering it, a
+    /// response stops them. Cancelled responses leave the clocks running, as the prompt goes
+    /// back in line.
+    pub fn observe(&self, message: &Message) {
+        match &message.content {
+            MessageContent::Response { response_type, .. }
+                if *response_type != ResponseType::Cancelled =>
+            {
+                self.settled(message, response_type)
+            }
+            content if matches!(message.sender_type, SenderType::Agent) => {
+                if let Some(kind) = prompt_kind(content) {
+                    self.asked(message, kind);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn asked(&self, prompt: &Message, kind: PromptKind) {
+        let mut tracked = self.lock();
+        let running: Vec<usize> = tracked
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.covers(&prompt.channel, kind))
+            .map(|(i, _)| i)
+            .collect();
+        if running.is_empty() || tracked.open.contains_key(&prompt.id) {
+            return;
+        }
+        if tracked.order.len() >= MAX_OPEN {
+            if let Some(oldest) = tracked.order.pop_front() {
+                tracked.open.remove(&oldest);
+            }
+        }
+        tracked.order.push_back(prompt.id);
+        tracked.open.insert(
+            prompt.id,
+            OpenPrompt {
+                prompt: prompt.clone(),
+                running,
+            },
+        );
+    }
+
+    fn settled(&self, response: &Message, outcome: &ResponseType) {
+        let Some(prompt_id) = response.correlation_id else {
+            return;
+        };
+        let mut tracked = self.lock();
+        let Some(open) = tracked.open.remove(&prompt_id) else {
+            return;
+        };
+        tracked.order.retain(|id| *id != prompt_id);
+        let waited = (response.timestamp - open.prompt.timestamp)
+            .to_std()
+            .unwrap_or_default();
+        for rule in open.running {
+            let within = tracked.rules[rule].within();
+            let met = *outcome != ResponseType::Timeout && waited <= within;
+            tracked.record(rule, response.timestamp, met);
+            if !met {
+                let breach = tracked.breach(rule, &open.prompt, waited, Some(outcome.clone()));
+                tracked.unalerted.push(breach);
+            }
+        }
+    }
+
+    /// Breaches to alert on at `now`: prompts whose SLA ran out while they waited, and those
+    /// settled late since the last call. Each is returned once.
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<Breach> {
+        let mut tracked = self.lock();
+        let mut breaches = std::mem::take(&mut tracked.unalerted);
+        let mut overdue = Vec::new();
+        for (id, open) in &tracked.open {
+            let waited = (now - open.prompt.timestamp).to_std().unwrap_or_default();
+            for rule in &open.running {
+                if waited >= tracked.rules[*rule].within() {
+                    overdue.push((*id, *rule, waited));
+                }
+            }
+        }
+        for (id, rule, waited) in overdue {
+            let Some(open) = tracked.open.get_mut(&id) else {
+                continue;
+            };
+            open.running.retain(|r| *r != rule);
+            let prompt = open.prompt.clone();
+            if open.running.is_empty() {
+                tracked.open.remove(&id);
+                tracked.order.retain(|open| *open != id);
+            }
+            tracked.record(rule, now, false);
+            breaches.push(tracked.breach(rule, &prompt, waited, None));
+        }
+        breaches
+    }
+
+    /// Compliance per rule over the SLAs met or breached since `since` (all remembered ones
+    /// when `None`), with the clocks running now.
+    pub fn summaries(&self, since: Option<DateTime<Utc>>) -> BTreeMap<String, SlaSummary> {
+        let tracked = self.lock();
+        let mut summaries: Vec<SlaSummary> = tracked
+            .rules
+            .iter()
+            .map(|rule| SlaSummary {
+                within_seconds: rule.within_seconds,
+                ..SlaSummary::default()
+            })
+            .collect();
+        for outcome in &tracked.outcomes {
+            if since.is_some_and(|since| outcome.at < since) {
+                continue;
+            }
+            let summary = &mut summaries[outcome.rule];
+            if outcome.met {
+                summary.met += 1;
+            } else {
+                summary.breached += 1;
+            }
+        }
+        for open in tracked.open.values() {
+            for rule in &open.running {
+                summaries[*rule].open += 1;
+            }
+        }
+        tracked
+            .rules
+            .iter()
+            .zip(summaries)
+            .map(|(rule, mut summary)| {
+                let settled = summary.met + summary.breached;
+                summary.compliance = (settled > 0).then(|| summary.met as f64 / settled as f64);
+                (rule.name(), summary)
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Tracked> {
+        self.tracked.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Tracked {
+    fn record(&mut self, rule: usize, at: DateTime<Utc>, met: bool) {
+        if self.outcomes.len() >= MAX_OUTCOMES {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back(Outcome { rule, at, met });
+    }
+
+    fn breach(
+        &self,
+        rule: usize,
+        prompt: &Message,
+        waited: Duration,
+        outcome: Option<ResponseType>,
+    ) -> Breach {
+        let config = &self.rules[rule];
+        Breach {
+            sla: config.name(),
+            within: config.within(),
+            alert_channel: config.alert_channel().to_string(),
+            prompt: prompt.clone(),
+            waited,
+            outcome,
+        }
+    }
+}
+
+fn prompt_kind(content: &MessageContent) -> Option<PromptKind> {
+    match content {
+        MessageContent::Decision { .. } => Some(PromptKind::Question),
+        MessageContent::Authorization { .. } => Some(PromptKind::Authorization),
+        MessageContent::Navigate { .. } => Some(PromptKind::Navigation),
+        _ => None,
+    }
+}
+
+/// The high-priority notification on the rule's alert channel reporting `breach`.
+pub fn alert(breach: &Breach) -> Message {
+    let prompt = &breach.prompt;
+    let what = match &prompt.content {
+        MessageContent::Authorization { action, .. } => format!("authorization \"{}\"", action),
+        _ => format!("prompt \"{}\"", prompt.prompt_text().unwrap_or_default()),
+    };
+    let waited = crate::server::digest::duration(breach.waited.as_secs_f64());
+    let how = match &breach.outcome {
+        None => format!("still unanswered after {}", waited),
+        Some(ResponseType::Timeout) => format!("timed out unanswered after {}", waited),
+        Some(_) => format!("answered after {}", waited),
+    };
+    let mut alert = Message::new(
+        breach.alert_channel.clone(),
+        SenderType::System,
+        MessageContent::Notification {
+            text: format!(
+                "⏰ SLA \"{}\" breached on [{}]: {} {}",
+                breach.sla, prompt.channel, what, how
+            ),
+            priority: NotificationPriority::High,
+        },
+    );
+    alert.metadata = Some(serde_json::json!({
+        "sla": breach.sla,
+        "within_seconds": breach.within.as_secs(),
+        "source_channel": prompt.channel,
+        "source_message": prompt.id,
+    }));
+    alert
+}
+
+/// Post an alert for every breach as it happens until `token` is cancelled.
+pub(crate) fn spawn_sla_monitor(
+    state: Arc<AiloopAppState>,
+    token: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut check_interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = check_interval.tick() => {
+                    for breach in state.broadcast_manager.slas().due(Utc::now()) {
+                        let alert = alert(&breach);
+                        tracing::warn!(
+                            sla = %breach.sla,
+                            channel = %breach.prompt.channel,
+                            prompt_id = %breach.prompt.id,
+                            "SLA breached"
+                        );
+                        state
+                            .message_history
+                            .add_message(&alert.channel, alert.clone())
+                            .await;
+                        state.broadcast_manager.broadcast_message(&alert).await;
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorization(channel: &str, asked_at: DateTime<Utc>) -> Message {
+        let mut message = Message::new(
+            channel.to_string(),
+            SenderType::Agent,
+            MessageContent::Authorization {
+                action: "deploy v2".to_string(),
+                context: None,
+                timeout_seconds: 600,
+            },
+        );
+        message.timestamp = asked_at;
+        message
+    }
+
+    fn response(prompt: &Message, response_type: ResponseType, after_secs: i64) -> Message {
+        let mut message = Message::response(
+            prompt.channel.clone(),
+            MessageContent::Response {
+                answer: None,
+                response_type,
+            },
+            prompt.id,
+        );
+        message.timestamp = prompt.timestamp + chrono::Duration::seconds(after_secs);
+        message
+    }
+
+    #[test]
+    fn test_breaches_are_alerted_once_and_counted() {
+        let tracker = SlaTracker::new();
+        tracker.set_rules(vec![SlaConfig {
+            channels: vec!["prod-*".to_string()],
+            prompts: vec![PromptKind::Authorization],
+            within_seconds: 60,
+            ..SlaConfig::default()
+        }]);
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let prompts: Vec<Message> = (0..5).map(|_| authorization("prod-eu", start)).collect();
+        let elsewhere = authorization("dev", start);
+        for prompt in prompts.iter().chain([&elsewhere]) {
+            tracker.observe(prompt);
+        }
+        tracker.observe(&response(
+            &prompts[0],
+            ResponseType::AuthorizationApproved,
+            30,
+        ));
+        tracker.observe(&response(&prompts[1], ResponseType::Cancelled, 10));
+        tracker.observe(&response(
+            &prompts[1],
+            ResponseType::AuthorizationDenied,
+            90,
+        ));
+        tracker.observe(&response(&prompts[2], ResponseType::Timeout, 20));
+
+        let breaches = tracker.due(start + chrono::Duration::seconds(59));
+        assert_eq!(breaches.len(), 2);
+        assert_eq!(breaches[0].outcome, Some(ResponseType::AuthorizationDenied));
+        assert_eq!(breaches[1].outcome, Some(ResponseType::Timeout));
+        let summary = &tracker.summaries(None)["authorization within 60s"];
+        assert_eq!((summary.met, summary.breached, summary.open), (1, 2, 2));
+
+        let breaches = tracker.due(start + chrono::Duration::seconds(61));
+        assert_eq!(breaches.len(), 2);
+        assert!(breaches.iter().all(|b| b.outcome.is_none()));
+        assert!(tracker
+            .due(start + chrono::Duration::seconds(120))
+            .is_empty());
+        tracker.observe(&response(
+            &prompts[3],
+            ResponseType::AuthorizationApproved,
+            130,
+        ));
+        assert!(tracker
+            .due(start + chrono::Duration::seconds(140))
+            .is_empty());
+
+        let summary = &tracker.summaries(None)["authorization within 60s"];
+        assert_eq!((summary.met, summary.breached, summary.open), (1, 4, 0));
+        assert_eq!(summary.compliance, Some(0.2));
+        assert!(
+            tracker.summaries(Some(Utc::now()))["authorization within 60s"]
+                .compliance
+                .is_none()
+        );
+
+        let alert = alert(&breaches[0]);
+        assert_eq!(alert.channel, "ops");
+        let MessageContent::Notification { text, priority } = &alert.content else {
+            panic!("expected a notification");
+        };
+        assert!(matches!(priority, NotificationPriority::High));
+        assert_eq!(
+            text,
+            "⏰ SLA \"authorization within 60s\" breached on [prod-eu]: authorization \"deploy v2\" still unanswered after 1m01s"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Snoozing prompts at the server terminal
+//!
+//! Typing `s` (or `s 30` for 30 minutes) at a decision, authorization or navigation prompt sets
+//! it aside instead of skipping it. The prompt is parked on its channel, the agent is told it is
+//! still pending, and it comes back once the snooze is over, unless it was answered through the
+//! HTTP API in the meantime.
+
+use ailoop_core::models::{Message, MessageContent, NotificationPriority, SenderType};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// Snooze length when none is typed.
+pub const DEFAULT_SNOOZE: Duration = Duration::from_secs(10 * 60);
+
+/// Longest snooze accepted, in minutes.
+const MAX_SNOOZE_MINUTES: u64 = 24 * 60;
+
+/// Parse a snooze command: `s`, `snooze`, or either followed by a number of minutes.
+pub(crate) fn parse_snooze(input: &str) -> Option<Duration> {
+    let mut words = input.split_whitespace();
+    let command = words.next()?.to_ascii_lowercase();
+    if command != "s" && command != "snooze" {
+        return None;
+    }
+    let duration = match words.next() {
+        None => DEFAULT_SNOOZE,
+        Some(minutes) => {
+            let minutes: u64 = minutes.parse().ok()?;
+            if minutes == 0 || minutes > MAX_SNOOZE_MINUTES {
+                return None;
+            }
+            Duration::from_secs(minutes * 60)
+        }
+    };
+    words.next().is_none().then_some(duration)
+}
+
+/// System notification telling the agent that `prompt` is still pending until `until`.
+pub(crate) fn snooze_notice(prompt: &Message, until: DateTime<Utc>) -> Message {
+    let minutes = (until - Utc::now()).num_minutes().max(1);
+    let mut notice = Message::new(
+        prompt.channel.clone(),
+        SenderType::System,
+        MessageContent::Notification {
+            text: format!(
+                "The operator snoozed this prompt for {} minute(s); it is still pending",
+                minutes
+            ),
+            priority: NotificationPriority::Low,
+        },
+    );
+    notice.metadata = Some(serde_json::json!({
+        "snoozed_prompt": prompt.id,
+        "snoozed_until": until,
+    }));
+    notice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_snooze_commands() {
+        assert_eq!(parse_snooze("s"), Some(DEFAULT_SNOOZE));
+        assert_eq!(parse_snooze(" Snooze "), Some(DEFAULT_SNOOZE));
+        assert_eq!(parse_snooze("s 30"), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_snooze("s 0"), None);
+        assert_eq!(parse_snooze("s 30 minutes"), None);
+        assert_eq!(parse_snooze("staging"), None);
+        assert_eq!(parse_snooze(""), None);
+    }
+}
@@ -0,0 +1,337 @@
+//! Human response metrics
+//!
+//! Prompts shown to humans (decisions, authorizations, navigations) and the responses that
+//! settle them are noted as they are broadcast. This shows where agents wait on people: how
+//! long answers take, how often prompts time out and how often authorizations are approved,
+//! per channel and per responder.
+
+use ailoop_core::models::{
+    Message, MessageContent, ResponseMetrics, ResponseSummary, ResponseType, SenderType,
+};
+use ailoop_core::services::signing::OPERATOR_RESPONDER;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Settled prompts, and prompts asked, that are remembered (oldest evicted first).
+const MAX_RECORDS: usize = 10_000;
+/// Unanswered prompts that are tracked (oldest forgotten first).
+const MAX_OPEN_PROMPTS: usize = 10_000;
+
+/// A prompt and the response that settled it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseRecord {
+    pub prompt_id: Uuid,
+    pub channel: String,
+    /// The question, action or URL.
+    pub prompt: String,
+    /// Who answered; `None` when the prompt timed out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub responder: Option<String>,
+    pub asked_at: DateTime<Utc>,
+    pub settled_at: DateTime<Utc>,
+    pub outcome: ResponseType,
+}
+
+impl ResponseRecord {
+    /// Seconds from the prompt to its response.
+    pub fn latency_seconds(&self) -> f64 {
+        (self.settled_at - self.asked_at).num_milliseconds().max(0) as f64 / 1000.0
+    }
+}
+
+#[derive(Debug)]
+struct OpenPrompt {
+    channel: String,
+    prompt: String,
+    asked_at: DateTime<Utc>,
+}
+
+/// Prompts waiting for an answer and recently settled ones.
+#[derive(Debug, Default)]
+pub struct ResponseLog {
+    inner: Mutex<LogState>,
+}
+
+#[derive(Debug, Default)]
+struct LogState {
+    open: HashMap<Uuid, OpenPrompt>,
+    /// Open prompt ids in the order asked, for eviction.
+    open_order: VecDeque<Uuid>,
+    /// When and on which channel each prompt was asked.
+    asked: VecDeque<(DateTime<Utc>, String)>,
+    records: VecDeque<ResponseRecord>,
+}
+
+impl ResponseLog {
+    /// Note a broadcast message: an agent prompt starts waiting, a response settles it.
+    /// Cancelled responses leave the prompt waiting, as it goes back in line.
+    pub fn observe(&self, message: &Message) {
+        match &message.content {
+            MessageContent::Decision { .. }
+            | MessageContent::Authorization { .. }
+            | MessageContent::Navigate { .. }
+                if matches!(message.sender_type, SenderType::Agent) =>
+            {
+                self.asked(message)
+            }
+            MessageContent::Response { response_type, .. }
+                if *response_type != ResponseType::Cancelled =>
+            {
+                self.settled(message, response_type)
+            }
+            _ => {}
+        }
+    }
+
+    fn asked(&self, prompt: &Message) {
+        let mut log = self.inner.lock().expect("response log lock");
+        if log.open.contains_key(&prompt.id) {
+            return;
+        }
+        if log.open_order.len() >= MAX_OPEN_PROMPTS {
+            if let Some(oldest) = log.open_order.pop_front() {
+                log.open.remove(&oldest);
+            }
+        }
+        log.open_order.push_back(prompt.id);
+        log.open.insert(
+            prompt.id,
+            OpenPrompt {
+                channel: prompt.channel.clone(),
+                prompt: prompt.prompt_text().unwrap_or_default().to_string(),
+                asked_at: prompt.timestamp,
+            },
+        );
+        if log.asked.len() >= MAX_RECORDS {
+            log.asked.pop_front();
+        }
+        log.asked
+            .push_back((prompt.timestamp, prompt.channel.clone()));
+    }
+
+    fn settled(&self, response: &Message, outcome: &ResponseType) {
+        let Some(prompt_id) = response.correlation_id else {
+            return;
+        };
+        let mut log = self.inner.lock().expect("response log lock");
+        let Some(open) = log.open.remove(&prompt_id) else {
+            return;
+        };
+        log.open_order.retain(|id| *id != prompt_id);
+        let responder = match outcome {
+            ResponseType::Timeout => None,
+            _ => Some(
+                response
+                    .team_member()
+                    .or(response.responder())
+                    .unwrap_or(OPERATOR_RESPONDER)
+                    .to_string(),
+            ),
+        };
+        if log.records.len() >= MAX_RECORDS {
+            log.records.pop_front();
+        }
+        log.records.push_back(ResponseRecord {
+            prompt_id,
+            channel: open.channel,
+            prompt: open.prompt,
+            responder,
+            asked_at: open.asked_at,
+            settled_at: response.timestamp,
+            outcome: outcome.clone(),
+        });
+    }
+
+    /// Prompts settled since `since` (all remembered ones when `None`), oldest first.
+    pub fn records(&self, since: Option<DateTime<Utc>>) -> Vec<ResponseRecord> {
+        let log = self.inner.lock().expect("response log lock");
+        log.records
+            .iter()
+            .filter(|r| since.is_none_or(|since| r.settled_at >= since))
+            .cloned()
+            .collect()
+    }
+
+    /// Metrics per channel and per responder over the prompts asked or settled since `since`.
+    pub fn metrics(&self, since: Option<DateTime<Utc>>) -> ResponseMetrics {
+        let asked: Vec<String> = {
+            let log = self.inner.lock().expect("response log lock");
+            log.asked
+                .iter()
+                .filter(|(at, _)| since.is_none_or(|since| *at >= since))
+                .map(|(_, channel)| channel.clone())
+                .collect()
+        };
+        let mut channels: BTreeMap<String, Tally> = BTreeMap::new();
+        let mut responders: BTreeMap<String, Tally> = BTreeMap::new();
+        for channel in asked {
+            channels.entry(channel).or_default().asked += 1;
+        }
+        for record in self.records(since) {
+            if let Some(responder) = &record.responder {
+                responders
+                    .entry(responder.clone())
+                    .or_default()
+                    .add(&record);
+            }
+            channels
+                .entry(record.channel.clone())
+                .or_default()
+                .add(&record);
+        }
+        ResponseMetrics {
+            since,
+            channels: channels
+                .into_iter()
+                .map(|(k, t)| (k, t.summary()))
+                .collect(),
+            responders: responders
+                .into_iter()
+                .map(|(k, t)| (k, t.summary()))
+                .collect(),
+            slas: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tally {
+    asked: u64,
+    timed_out: u64,
+    approved: u64,
+    denied: u64,
+    latencies: Vec<f64>,
+}
+
+impl Tally {
+    fn add(&mut self, record: &ResponseRecord) {
+        match record.outcome {
+            ResponseType::Timeout => {
+                self.timed_out += 1;
+                return;
+            }
+            ResponseType::AuthorizationApproved => self.approved += 1,
+            ResponseType::AuthorizationDenied => self.denied += 1,
+            _ => {}
+        }
+        self.latencies.push(record.latency_seconds());
+    }
+
+    fn summary(mut self) -> ResponseSummary {
+        let answered = self.latencies.len() as u64;
+        let settled = answered + self.timed_out;
+        let decided = self.approved + self.denied;
+        self.latencies.sort_by(f64::total_cmp);
+        let median = match self.latencies.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(self.latencies[n / 2]),
+            n => Some((self.latencies[n / 2 - 1] + self.latencies[n / 2]) / 2.0),
+        };
+        ResponseSummary {
+            asked: self.asked,
+            answered,
+            timed_out: self.timed_out,
+            approved: self.approved,
+            denied: self.denied,
+            timeout_rate: (settled > 0).then(|| self.timed_out as f64 / settled as f64),
+            approval_rate: (decided > 0).then(|| self.approved as f64 / decided as f64),
+            mean_latency_seconds: (answered > 0)
+                .then(|| self.latencies.iter().sum::<f64>() / answered as f64),
+            median_latency_seconds: median,
+            max_latency_seconds: self.latencies.last().copied(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorization(channel: &str, asked_at: DateTime<Utc>) -> Message {
+        let mut message = Message::new(
+            channel.to_string(),
+            SenderType::Agent,
+            MessageContent::Authorization {
+                action: "deploy".to_string(),
+                context: None,
+                timeout_seconds: 60,
+            },
+        );
+        message.timestamp = asked_at;
+        message
+    }
+
+    fn response(
+        prompt: &Message,
+        response_type: ResponseType,
+        responder: Option<&str>,
+        after_secs: i64,
+    ) -> Message {
+        let mut message = Message::response(
+            prompt.channel.clone(),
+            MessageContent::Response {
+                answer: None,
+                response_type,
+            },
+            prompt.id,
+        );
+        message.timestamp = prompt.timestamp + chrono::Duration::seconds(after_secs);
+        message.metadata = responder.map(|r| serde_json::json!({ "responder": r }));
+        message
+    }
+
+    #[test]
+    fn metrics_per_channel_and_responder() {
+        let log = ResponseLog::default();
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let prompts: Vec<Message> = (0..4).map(|_| authorization("prod", start)).collect();
+        let waiting = authorization("dev", start);
+        for prompt in prompts.iter().chain([&waiting]) {
+            log.observe(prompt);
+        }
+        let alice = Some("telegram:alice");
+        log.observe(&response(&prompts[0], ResponseType::Cancelled, None, 5));
+        log.observe(&response(
+            &prompts[0],
+            ResponseType::AuthorizationApproved,
+            alice,
+            10,
+        ));
+        log.observe(&response(
+            &prompts[1],
+            ResponseType::AuthorizationDenied,
+            alice,
+            30,
+        ));
+        log.observe(&response(
+            &prompts[2],
+            ResponseType::AuthorizationApproved,
+            None,
+            20,
+        ));
+        log.observe(&response(&prompts[3], ResponseType::Timeout, None, 60));
+
+        let metrics = log.metrics(None);
+        let prod = &metrics.channels["prod"];
+        assert_eq!((prod.asked, prod.answered, prod.timed_out), (4, 3, 1));
+        assert_eq!((prod.approved, prod.denied), (2, 1));
+        assert_eq!(prod.timeout_rate, Some(0.25));
+        assert_eq!(prod.median_latency_seconds, Some(20.0));
+        assert_eq!(prod.max_latency_seconds, Some(30.0));
+        assert_eq!(metrics.channels["dev"].asked, 1);
+        assert_eq!(metrics.channels["dev"].answered, 0);
+
+        let alice = &metrics.responders["telegram:alice"];
+        assert_eq!(alice.answered, 2);
+        assert_eq!(alice.approval_rate, Some(0.5));
+        assert_eq!(alice.mean_latency_seconds, Some(20.0));
+        assert_eq!(metrics.responders[OPERATOR_RESPONDER].answered, 1);
+        assert_eq!(metrics.responders.len(), 2);
+
+        let later = log.metrics(Some(Utc::now()));
+        assert!(later.channels.is_empty());
+    }
+}
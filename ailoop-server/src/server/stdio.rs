@@ -0,0 +1,108 @@
+//! Stdio mode: the JSON protocol over a byte stream instead of a network listener.
+//!
+//! Like an LSP server, ailoop reads messages from stdin and writes to stdout, one JSON message
+//! per line, so editors and wrappers can embed the human-in-the-loop without opening a port.
+//! The peer is treated as an ordinary agent connection. It may also answer a pending prompt
+//! itself by sending a `response` message whose `correlation_id` is the prompt's id.
+
+use crate::server::core::{set_console_output, AiloopServer};
+use crate::state::AiloopAppState;
+use ailoop_core::models::{Message, MessageContent};
+use axum::extract::ws::Message as WsMessage;
+use futures_util::StreamExt;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Responder recorded for prompts answered by the stdio peer.
+pub const STDIO_RESPONDER: &str = "stdio";
+
+/// Serve one peer over `reader`/`writer` until `reader` reaches end of input.
+///
+/// Background processing (`spawn_background_tasks`) must be running for prompts to be handled.
+pub async fn serve_io<R, W>(state: Arc<AiloopAppState>, reader: R, writer: W)
+where
+    R: AsyncRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let lines = futures_util::stream::unfold(BufReader::new(reader).lines(), |mut lines| async {
+        match lines.next_line().await {
+            Ok(Some(line)) => Some((line, lines)),
+            Ok(None) => None,
+            Err(e) => {
+                tracing::warn!("Stdio read error: {}", e);
+                None
+            }
+        }
+    });
+    let answers = Arc::clone(&state);
+    let incoming = lines.filter_map(move |line| {
+        let state = Arc::clone(&answers);
+        async move {
+            if line.trim().is_empty() || answer_prompt(&state, &line).await {
+                None
+            } else {
+                Some(line)
+            }
+        }
+    });
+
+    let outgoing = futures_util::sink::unfold(writer, |mut writer, msg: WsMessage| async move {
+        if let WsMessage::Text(text) = msg {
+            writer.write_all(text.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await?;
+        }
+        Ok::<_, std::io::Error>(writer)
+    });
+
+    AiloopServer::handle_connection(
+        incoming,
+        outgoing,
+        Arc::clone(&state.channel_manager),
+        state.default_channel.clone(),
+        Arc::clone(&state.message_history),
+        Arc::clone(&state.broadcast_manager),
+        Arc::clone(&state.pending_prompt_registry),
+        Arc::clone(&state.loops),
+        Arc::clone(&state.usage),
+        Arc::clone(&state.hooks),
+        None,
+        None,
+        state.strict,
+    )
+    .await;
+}
+
+/// Serve the process's own stdin/stdout. Terminal prompting and echo are turned off for the
+/// rest of the process, since stdout now carries the protocol.
+pub async fn serve_stdio(state: Arc<AiloopAppState>) {
+    set_console_output(false);
+    serve_io(state, tokio::io::stdin(), tokio::io::stdout()).await;
+}
+
+/// Submit `line` as the answer to a pending prompt when it is a response to one.
+/// Returns `false` for anything else, which then takes the normal agent path.
+async fn answer_prompt(state: &AiloopAppState, line: &str) -> bool {
+    let Ok(message) = serde_json::from_str::<Message>(line) else {
+        return false;
+    };
+    let (
+        Some(prompt_id),
+        MessageContent::Response {
+            answer,
+            response_type,
+        },
+    ) = (message.correlation_id, message.content)
+    else {
+        return false;
+    };
+    state
+        .pending_prompt_registry
+        .submit_reply_for_message_as(
+            prompt_id,
+            answer,
+            response_type,
+            Some(STDIO_RESPONDER.to_string()),
+        )
+        .await
+}
@@ -0,0 +1,323 @@
+//! Keyword subscriptions and mention alerts
+//!
+//! A `[team]` member watches keywords with `ailoop me watch deploy,@alice` (`POST
+//! /api/v1/me/watch`). Whenever a message on any channel contains one of them as a whole word
+//! (case-insensitive), the [broadcast](crate::server::broadcast) filter stage sends the member a
+//! high-priority alert on the providers of their handles, whether or not they follow that
+//! channel. Watching `@alice` is how a member gets mention alerts. Members who are
+//! [away](crate::server::away) get no alerts, and neither do server notices or the member's own
+//! answers. Subscriptions are saved to `subscriptions.json` in the state directory.
+
+use ailoop_core::models::{
+    Message, MessageContent, NotificationPriority, SenderType, TeamDirectory,
+};
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Messages remembered as already checked, so one broadcast twice alerts once.
+const MAX_CHECKED: usize = 1024;
+
+/// Longest excerpt of the matching message quoted in an alert.
+const EXCERPT_CHARS: usize = 200;
+
+#[derive(Default)]
+struct Board {
+    file: Option<PathBuf>,
+    team: TeamDirectory,
+    members: BTreeMap<String, Vec<String>>,
+    checked: VecDeque<Uuid>,
+}
+
+/// Keywords watched, by team member key.
+#[derive(Default)]
+pub struct Subscriptions {
+    board: Mutex<Board>,
+}
+
+/// A member to alert about a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watcher {
+    /// Team directory key.
+    pub member: String,
+    /// The keyword the message matched.
+    pub keyword: String,
+    /// Providers of the member's handles.
+    pub providers: Vec<String>,
+}
+
+impl Subscriptions {
+    /// Subscriptions that are forgotten when the server stops.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Save subscriptions to `file` from now on, starting with the ones already in it.
+    pub fn open(&self, file: impl Into<PathBuf>) -> Result<()> {
+        let file = file.into();
+        let members = match std::fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid subscriptions file {:?}", file))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read subscriptions file {:?}", file))
+            }
+        };
+        let mut board = self.lock();
+        board.file = Some(file);
+        board.members = members;
+        Ok(())
+    }
+
+    pub fn set_team(&self, team: TeamDirectory) {
+        self.lock().team = team;
+    }
+
+    /// Keywords `member` watches.
+    pub fn keywords(&self, member: &str) -> Vec<String> {
+        self.lock().members.get(member).cloned().unwrap_or_default()
+    }
+
+    /// Watch `keywords` for `member`; returns everything they watch now.
+    pub fn watch(&self, member: &str, keywords: &[String]) -> Result<Vec<String>> {
+        let mut board = self.lock();
+        let previous = board.members.get(member).cloned();
+        let watched = board.members.entry(member.to_string()).or_default();
+        for keyword in keywords.iter().map(|k| k.trim()).filter(|k| !k.is_empty()) {
+            if !watched.iter().any(|w| w.eq_ignore_ascii_case(keyword)) {
+                watched.push(keyword.to_string());
+            }
+        }
+        let watched = watched.clone();
+        board.save_or_restore(member, previous)?;
+        Ok(watched)
+    }
+
+    /// Stop watching `keywords`, or everything when it is empty; returns what is left.
+    pub fn unwatch(&self, member: &str, keywords: &[String]) -> Result<Vec<String>> {
+        let mut board = self.lock();
+        let previous = board.members.get(member).cloned();
+        if let Some(watched) = board.members.get_mut(member) {
+            watched.retain(|w| {
+                !keywords.is_empty() && !keywords.iter().any(|k| k.trim().eq_ignore_ascii_case(w))
+            });
+            if watched.is_empty() {
+                board.members.remove(member);
+            }
+        }
+        let left = board.members.get(member).cloned().unwrap_or_default();
+        board.save_or_restore(member, previous)?;
+        Ok(left)
+    }
+
+    /// Members whose keywords `message` matches, each with the first keyword it matched.
+    /// Each message is checked once; later calls with the same id return nothing.
+    pub fn watchers(&self, message: &Message) -> Vec<Watcher> {
+        let Some(text) = searchable_text(message) else {
+            return Vec::new();
+        };
+        let mut board = self.lock();
+        if board.members.is_empty() || board.checked.contains(&message.id) {
+            return Vec::new();
+        }
+        if board.checked.len() >= MAX_CHECKED {
+            board.checked.pop_front();
+        }
+        board.checked.push_back(message.id);
+
+        let author = message
+            .responder()
+            .and_then(|responder| board.team.resolve(responder));
+        let mut watchers = Vec::new();
+        for (member, keywords) in &board.members {
+            if author == Some(member.as_str()) {
+                continue;
+            }
+            let Some(team_member) = board.team.get(member) else {
+                continue;
+            };
+            if let Some(keyword) = keywords.iter().find(|k| contains_word(text, k)) {
+                watchers.push(Watcher {
+                    member: member.clone(),
+                    keyword: keyword.clone(),
+                    providers: team_member.providers(),
+                });
+            }
+        }
+        watchers
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Board> {
+        self.board.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Board {
+    /// Save, putting `member`'s `previous` keywords back when that fails.
+    fn save_or_restore(&mut self, member: &str, previous: Option<Vec<String>>) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Err(e) = save_to(file, &self.members) {
+            match previous {
+                Some(previous) => self.members.insert(member.to_string(), previous),
+                None => self.members.remove(member),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+fn save_to(file: &Path, members: &BTreeMap<String, Vec<String>>) -> Result<()> {
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {:?}", parent))?;
+    }
+    let tmp = file.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(members)?)
+        .with_context(|| format!("Failed to write {:?}", tmp))?;
+    std::fs::rename(&tmp, file).with_context(|| format!("Failed to write {:?}", file))
+}
+
+/// The text keywords are looked for in: what a human would read of the message. Server
+/// notices, status pulses and task events have none.
+pub(crate) fn searchable_text(message: &Message) -> Option<&str> {
+    if matches!(message.sender_type, SenderType::System) {
+        return None;
+    }
+    match &message.content {
+        MessageContent::Notification { text, .. } | MessageContent::Chat { text, .. } => Some(text),
+        MessageContent::Response { answer, .. } => answer.as_deref(),
+        MessageContent::Status { .. } => None,
+        _ => message.prompt_text(),
+    }
+}
+
+/// Whether `keyword` occurs in `text` as a whole word, ignoring case.
+fn contains_word(text: &str, keyword: &str) -> bool {
+    let text = text.to_lowercase();
+    let keyword = keyword.to_lowercase();
+    if keyword.is_empty() {
+        return false;
+    }
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    text.match_indices(&keyword).any(|(start, _)| {
+        let end = start + keyword.len();
+        !is_word(text[..start].chars().next_back()) && !is_word(text[end..].chars().next())
+    })
+}
+
+/// The high-priority notification telling `watcher` that `message` matched their keyword.
+pub fn alert(message: &Message, watcher: &Watcher) -> Message {
+    let text = searchable_text(message).unwrap_or_default();
+    let excerpt: String = text.chars().take(EXCERPT_CHARS).collect();
+    let ellipsis = if text.chars().count() > EXCERPT_CHARS {
+        "…"
+    } else {
+        ""
+    };
+    let mut alert = Message::new(
+        message.channel.clone(),
+        SenderType::System,
+        MessageContent::Notification {
+            text: format!(
+                "🔔 @{}: \"{}\" on [{}] from {}: {}{}",
+                watcher.member,
+                watcher.keyword,
+                message.channel,
+                message.speaker(),
+                excerpt,
+                ellipsis
+            ),
+            priority: NotificationPriority::High,
+        },
+    );
+    alert.metadata = Some(serde_json::json!({
+        "alert_for": format!("@{}", watcher.member),
+        "keyword": watcher.keyword,
+        "source_message": message.id,
+    }));
+    alert
+}
+
+/// Keywords from a comma-separated list, without blanks or repeats.
+pub fn parse_keywords(list: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    list.split(',')
+        .map(str::trim)
+        .filter(|k| !k.is_empty() && seen.insert(k.to_lowercase()))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::TeamMember;
+
+    fn say(text: &str) -> Message {
+        Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: text.to_string(),
+                priority: NotificationPriority::Low,
+            },
+        )
+    }
+
+    #[test]
+    fn test_keywords_match_whole_words_once() {
+        let mut members = BTreeMap::new();
+        members.insert(
+            "alice".to_string(),
+            TeamMember {
+                handles: vec!["telegram:alice".to_string()],
+                ..TeamMember::default()
+            },
+        );
+        let subscriptions = Subscriptions::new();
+        subscriptions.set_team(TeamDirectory::new(members));
+        subscriptions
+            .watch("alice", &parse_keywords("Deploy, @alice, deploy"))
+            .unwrap();
+        assert_eq!(subscriptions.keywords("alice"), vec!["Deploy", "@alice"]);
+
+        let message = say("deploy to prod finished");
+        let watchers = subscriptions.watchers(&message);
+        assert_eq!(
+            watchers,
+            vec![Watcher {
+                member: "alice".to_string(),
+                keyword: "Deploy".to_string(),
+                providers: vec!["telegram".to_string()],
+            }]
+        );
+        assert!(subscriptions.watchers(&message).is_empty());
+        assert_eq!(subscriptions.watchers(&say("redeployed")), vec![]);
+        assert_eq!(subscriptions.watchers(&say("ping @alice, please")).len(), 1);
+        assert_eq!(subscriptions.watchers(&say("mail @alice2")), vec![]);
+
+        let alert = alert(&message, &watchers[0]);
+        assert!(matches!(
+            alert.content,
+            MessageContent::Notification {
+                priority: NotificationPriority::High,
+                ..
+            }
+        ));
+        assert!(subscriptions.watchers(&alert).is_empty());
+
+        assert_eq!(
+            subscriptions
+                .unwatch("alice", &["deploy".to_string()])
+                .unwrap(),
+            vec!["@alice"]
+        );
+        assert!(subscriptions.unwatch("alice", &[]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+//! Restarting internal tasks that panic
+//!
+//! A panic in a spawned task only ends that task: the process keeps serving HTTP while, say,
+//! the message loop is gone and no prompt is ever shown again. Background tasks are therefore
+//! started through a [`Supervisor`], which logs the panic, starts the task again after a short
+//! backoff (1s, doubling up to 30s) and counts the restarts for `GET /api/stats`.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Restarts of one supervised task.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskHealth {
+    pub restarts: u32,
+    /// Message of the most recent panic.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_panic: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_panic_at: Option<DateTime<Utc>>,
+}
+
+/// Starts background tasks and restarts them when they panic.
+#[derive(Debug, Default)]
+pub struct Supervisor {
+    tasks: Mutex<BTreeMap<String, TaskHealth>>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the task `start` spawns, spawning it again each time it panics, until it returns or
+    /// `token` is cancelled. The returned handle resolves once the task is over for good.
+    pub fn supervise<F>(
+        self: &Arc<Self>,
+        name: impl Into<String>,
+        token: CancellationToken,
+        start: F,
+    ) -> JoinHandle<()>
+    where
+        F: Fn() -> JoinHandle<()> + Send + 'static,
+    {
+        let name = name.into();
+        self.tasks
+            .lock()
+            .expect("supervisor lock")
+            .entry(name.clone())
+            .or_default();
+        let supervisor = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut backoff = INITIAL_BACKOFF;
+            loop {
+                let error = match start().await {
+                    Ok(()) => break,
+                    Err(e) if e.is_panic() => e,
+                    Err(_) => break,
+                };
+                let message = panic_message(error.into_panic());
+                let restarts = supervisor.record_panic(&name, &message);
+                tracing::error!(
+                    task = %name,
+                    panic = %message,
+                    restarts,
+                    "Internal task panicked; restarting in {}s",
+                    backoff.as_secs()
+                );
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = tokio::time::sleep(backoff) => {}
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        })
+    }
+
+    fn record_panic(&self, name: &str, message: &str) -> u32 {
+        let mut tasks = self.tasks.lock().expect("supervisor lock");
+        let health = tasks.entry(name.to_string()).or_default();
+        health.restarts += 1;
+        health.last_panic = Some(message.to_string());
+        health.last_panic_at = Some(Utc::now());
+        health.restarts
+    }
+
+    /// Every supervised task by name, including those that never panicked.
+    pub fn snapshot(&self) -> BTreeMap<String, TaskHealth> {
+        self.tasks.lock().expect("supervisor lock").clone()
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_panicking_task_is_restarted_and_counted() {
+        let supervisor = Arc::new(Supervisor::new());
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&runs);
+        let handle = supervisor.supervise("flaky", CancellationToken::new(), move || {
+            let counter = Arc::clone(&counter);
+            tokio::spawn(async move {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    panic!("boom");
+                }
+            })
+        });
+        handle.await.unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        let health = &supervisor.snapshot()["flaky"];
+        assert_eq!(health.restarts, 1);
+        assert_eq!(health.last_panic.as_deref(), Some("boom"));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_task_is_not_restarted() {
+        let supervisor = Arc::new(Supervisor::new());
+        let token = CancellationToken::new();
+        let task_token = token.clone();
+        let handle = supervisor.supervise("loop", token.clone(), move || {
+            let token = task_token.clone();
+            tokio::spawn(async move { token.cancelled().await })
+        });
+        token.cancel();
+        handle.await.unwrap();
+        assert_eq!(supervisor.snapshot()["loop"], TaskHealth::default());
+    }
+}
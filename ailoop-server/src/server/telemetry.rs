@@ -0,0 +1,388 @@
+//! OpenTelemetry export (feature `otel`)
+//!
+//! Built with the `otel` feature, the server and `ailoop forward` export traces and metrics over
+//! OTLP/HTTP once `OTEL_EXPORTER_OTLP_ENDPOINT` (or a per-signal `..._TRACES_ENDPOINT` /
+//! `..._METRICS_ENDPOINT`) is set. Everything else comes from the standard variables:
+//! `OTEL_SERVICE_NAME` (default `ailoop`), `OTEL_RESOURCE_ATTRIBUTES`,
+//! `OTEL_EXPORTER_OTLP_HEADERS`, `OTEL_METRIC_EXPORT_INTERVAL`, `OTEL_TRACES_EXPORTER=none`,
+//! `OTEL_METRICS_EXPORTER=none` and `OTEL_SDK_DISABLED=true`.
+//!
+//! Traces: one `prompt` span per prompt, from the moment it is queued until it is answered,
+//! times out, fails or is dropped, with its state changes as events; one `forward` span per forwarded agent event.
+//!
+//! Metrics:
+//!
+//! - `ailoop.messages` (channel, type): messages added to history
+//! - `ailoop.prompts` (channel, state): prompt state changes
+//! - `ailoop.prompt.duration` (channel, state), seconds: time from queued to the final state
+//! - `ailoop.deliveries` (provider, status): provider delivery attempts
+//! - `ailoop.forward.events` (type, result): agent events sent or failed by `forward`
+//!
+//! Without the feature, or without an endpoint, every function here does nothing.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use uuid::Uuid;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Exporters started by [`init`]; dropping it flushes and stops them.
+#[must_use = "dropping the guard stops the exporters"]
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    providers: Option<otlp::Providers>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(providers) = self.providers.take() {
+            providers.shutdown();
+        }
+    }
+}
+
+/// Start exporting when the `OTEL_*` variables ask for it. Call once, from within the Tokio
+/// runtime, and keep the guard until the process exits. Problems are reported on stderr and
+/// leave export off.
+#[cfg(feature = "otel")]
+pub fn init() -> Telemetry {
+    let providers = match otlp::init() {
+        Ok(providers) => providers,
+        Err(e) => {
+            eprintln!("Warning: OpenTelemetry export disabled: {:#}", e);
+            None
+        }
+    };
+    ENABLED.store(providers.is_some(), Ordering::Relaxed);
+    Telemetry { providers }
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn init() -> Telemetry {
+    Telemetry {}
+}
+
+/// Whether traces or metrics are being exported.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Count a message added to history.
+#[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+pub(crate) fn message(channel: &str, content_type: &str) {
+    #[cfg(feature = "otel")]
+    if is_enabled() {
+        otlp::message(channel, content_type);
+    }
+}
+
+/// Record a prompt moving to `state`: opens its span when it is first seen and closes it in a
+/// final state: answered, timed out, errored or dropped.
+#[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+pub(crate) fn prompt(prompt_id: Uuid, channel: &str, state: &str) {
+    #[cfg(feature = "otel")]
+    if is_enabled() {
+        otlp::prompt(prompt_id, channel, state);
+    }
+}
+
+/// Count a provider delivery attempt.
+#[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+pub(crate) fn delivery(provider: &str, status: &str) {
+    #[cfg(feature = "otel")]
+    if is_enabled() {
+        otlp::delivery(provider, status);
+    }
+}
+
+/// Count an agent event sent (`sent`) or not by `ailoop forward`.
+#[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+pub fn forwarded(event_type: &str, sent: bool) {
+    #[cfg(feature = "otel")]
+    if is_enabled() {
+        otlp::forwarded(event_type, sent);
+    }
+}
+
+#[cfg(feature = "otel")]
+mod otlp {
+    use anyhow::Result;
+    use opentelemetry::metrics::{Counter, Histogram};
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+    use opentelemetry_sdk::trace::TracerProvider;
+    use opentelemetry_sdk::{runtime, Resource};
+    use std::collections::HashMap;
+    use std::sync::{Mutex, OnceLock};
+    use std::time::Instant;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+    use tracing_subscriber::EnvFilter;
+    use uuid::Uuid;
+
+    /// Open prompt spans; prompts still waiting when the server stops are never exported.
+    static PROMPTS: OnceLock<Mutex<HashMap<Uuid, (tracing::Span, Instant)>>> = OnceLock::new();
+    static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+
+    pub(super) struct Providers {
+        tracer: Option<TracerProvider>,
+        meter: Option<SdkMeterProvider>,
+    }
+
+    impl Providers {
+        pub(super) fn shutdown(self) {
+            if let Some(tracer) = self.tracer {
+                if let Err(e) = tracer.shutdown() {
+                    eprintln!("Warning: failed to flush OpenTelemetry traces: {}", e);
+                }
+            }
+            if let Some(meter) = self.meter {
+                if let Err(e) = meter.shutdown() {
+                    eprintln!("Warning: failed to flush OpenTelemetry metrics: {}", e);
+                }
+            }
+        }
+    }
+
+    struct Instruments {
+        messages: Counter<u64>,
+        prompts: Counter<u64>,
+        prompt_duration: Histogram<f64>,
+        deliveries: Counter<u64>,
+        forwarded: Counter<u64>,
+    }
+
+    fn instruments() -> &'static Instruments {
+        INSTRUMENTS.get_or_init(|| {
+            let meter = opentelemetry::global::meter("ailoop");
+            Instruments {
+                messages: meter
+                    .u64_counter("ailoop.messages")
+                    .with_description("Messages added to history")
+                    .build(),
+                prompts: meter
+                    .u64_counter("ailoop.prompts")
+                    .with_description("Prompt state changes")
+                    .build(),
+                prompt_duration: meter
+                    .f64_histogram("ailoop.prompt.duration")
+                    .with_description("Time from a prompt being queued to its answer or timeout")
+                    .with_unit("s")
+                    .build(),
+                deliveries: meter
+                    .u64_counter("ailoop.deliveries")
+                    .with_description("Provider delivery attempts")
+                    .build(),
+                forwarded: meter
+                    .u64_counter("ailoop.forward.events")
+                    .with_description("Agent events forwarded by `ailoop forward`")
+                    .build(),
+            }
+        })
+    }
+
+    pub(super) fn init() -> Result<Option<Providers>> {
+        if env_is_true("OTEL_SDK_DISABLED") {
+            return Ok(None);
+        }
+        let traces = signal_enabled("TRACES");
+        let metrics = signal_enabled("METRICS");
+        if !traces && !metrics {
+            return Ok(None);
+        }
+
+        let resource = resource();
+        let meter = if metrics {
+            let exporter = opentelemetry_otlp::MetricExporter::builder()
+                .with_http()
+                .build()?;
+            let reader = PeriodicReader::builder(exporter, runtime::Tokio).build();
+            let provider = SdkMeterProvider::builder()
+                .with_reader(reader)
+                .with_resource(resource.clone())
+                .build();
+            opentelemetry::global::set_meter_provider(provider.clone());
+            Some(provider)
+        } else {
+            None
+        };
+        let tracer = if traces {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .build()?;
+            let provider = TracerProvider::builder()
+                .with_batch_exporter(exporter, runtime::Tokio)
+                .with_resource(resource)
+                .build();
+            let filter =
+                EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("ailoop=info"));
+            let installed = tracing_subscriber::registry()
+                .with(filter)
+                .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+                .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("ailoop")))
+                .try_init();
+            if installed.is_err() {
+                eprintln!(
+                    "Warning: a tracing subscriber is already installed; spans are not exported"
+                );
+            }
+            Some(provider)
+        } else {
+            None
+        };
+        Ok(Some(Providers { tracer, meter }))
+    }
+
+    /// `OTEL_SERVICE_NAME`, defaulting to `ailoop`, over the SDK's resource detection.
+    fn resource() -> Resource {
+        let name = std::env::var("OTEL_SERVICE_NAME")
+            .ok()
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| "ailoop".to_string());
+        Resource::default().merge(&Resource::new([
+            KeyValue::new("service.name", name),
+            KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+        ]))
+    }
+
+    /// A signal is exported when an endpoint is set for it and its exporter is not `none`.
+    fn signal_enabled(signal: &str) -> bool {
+        let endpoint = [
+            "OTEL_EXPORTER_OTLP_ENDPOINT".to_string(),
+            format!("OTEL_EXPORTER_OTLP_{}_ENDPOINT", signal),
+        ]
+        .iter()
+        .any(|var| std::env::var(var).is_ok_and(|v| !v.is_empty()));
+        let exporter = std::env::var(format!("OTEL_{}_EXPORTER", signal)).unwrap_or_default();
+        endpoint && !exporter.eq_ignore_ascii_case("none")
+    }
+
+    fn env_is_true(var: &str) -> bool {
+        std::env::var(var).is_ok_and(|v| v.eq_ignore_ascii_case("true"))
+    }
+
+    pub(super) fn message(channel: &str, content_type: &str) {
+        instruments().messages.add(
+            1,
+            &[
+                KeyValue::new("channel", channel.to_string()),
+                KeyValue::new("type", content_type.to_string()),
+            ],
+        );
+    }
+
+    pub(super) fn prompt(prompt_id: Uuid, channel: &str, state: &str) {
+        let attributes = [
+            KeyValue::new("channel", channel.to_string()),
+            KeyValue::new("state", state.to_string()),
+        ];
+        instruments().prompts.add(1, &attributes);
+
+        let mut open = PROMPTS
+            .get_or_init(Default::default)
+            .lock()
+            .expect("prompt spans lock");
+        let (span, _) = open.entry(prompt_id).or_insert_with(|| {
+            let span = tracing::info_span!(
+                parent: None,
+                "prompt",
+                prompt_id = %prompt_id,
+                channel = channel,
+                state = tracing::field::Empty,
+            );
+            (span, Instant::now())
+        });
+        tracing::info!(parent: &*span, state = state, "prompt {}", state);
+        if is_final(state) {
+            if let Some((span, started)) = open.remove(&prompt_id) {
+                span.record("state", state);
+                instruments()
+                    .prompt_duration
+                    .record(started.elapsed().as_secs_f64(), &attributes);
+            }
+        }
+    }
+
+    /// Whether a prompt in `state` is done with; its span closes.
+    fn is_final(state: &str) -> bool {
+        matches!(state, "answered" | "timeout" | "errored" | "dropped")
+    }
+
+    pub(super) fn delivery(provider: &str, status: &str) {
+        instruments().deliveries.add(
+            1,
+            &[
+                KeyValue::new("provider", provider.to_string()),
+                KeyValue::new("status", status.to_string()),
+            ],
+        );
+    }
+
+    pub(super) fn forwarded(event_type: &str, sent: bool) {
+        instruments().forwarded.add(
+            1,
+            &[
+                KeyValue::new("type", event_type.to_string()),
+                KeyValue::new("result", if sent { "sent" } else { "failed" }),
+            ],
+        );
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn is_open(prompt_id: Uuid) -> bool {
+            PROMPTS
+                .get_or_init(Default::default)
+                .lock()
+                .expect("prompt spans lock")
+                .contains_key(&prompt_id)
+        }
+
+        #[test]
+        fn answered_and_timed_out_prompts_close_their_span() {
+            for state in ["answered", "timeout"] {
+                let prompt_id = Uuid::new_v4();
+                prompt(prompt_id, "ops", "queued");
+                prompt(prompt_id, "ops", "pending");
+                assert!(is_open(prompt_id));
+                prompt(prompt_id, "ops", state);
+                assert!(!is_open(prompt_id), "{}", state);
+            }
+        }
+
+        #[test]
+        fn signals_need_an_endpoint_and_an_exporter() {
+            std::env::set_var(
+                "OTEL_EXPORTER_OTLP_AILOOPTEST_ENDPOINT",
+                "http://127.0.0.1:4318",
+            );
+            assert!(signal_enabled("AILOOPTEST"));
+            std::env::set_var("OTEL_AILOOPTEST_EXPORTER", "None");
+            assert!(!signal_enabled("AILOOPTEST"));
+            std::env::remove_var("OTEL_EXPORTER_OTLP_AILOOPTEST_ENDPOINT");
+            std::env::remove_var("OTEL_AILOOPTEST_EXPORTER");
+        }
+
+        #[test]
+        fn errored_prompts_close_their_span() {
+            let prompt_id = Uuid::new_v4();
+            prompt(prompt_id, "ops", "queued");
+            prompt(prompt_id, "ops", "pending");
+            assert!(is_open(prompt_id));
+            prompt(prompt_id, "ops", "errored");
+            assert!(!is_open(prompt_id));
+        }
+
+        #[test]
+        fn dropped_prompts_close_their_span() {
+            let prompt_id = Uuid::new_v4();
+            prompt(prompt_id, "ops", "queued");
+            assert!(is_open(prompt_id));
+            prompt(prompt_id, "ops", "dropped");
+            assert!(!is_open(prompt_id));
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! Raw mode for terminal prompts, and suspending the server while one is open
+//!
+//! Prompts read keys in raw mode, where Ctrl+Z reaches the server as a key instead of
+//! suspending it, and where a stop from elsewhere (`kill -TSTP`) would leave the shell with a
+//! terminal that neither echoes nor ends lines. [`RawMode`] is an RAII guard: raw mode ends when
+//! it is dropped, also on an early return or a panic. While one is held, Ctrl+Z and SIGTSTP put
+//! the terminal back in cooked mode before the process stops; on SIGCONT raw mode is restored
+//! and the prompt is asked to draw itself again, since the shell wrote over it meanwhile.
+
+use crate::server::providers::split_reason;
+use ailoop_core::models::ResponseType;
+use anyhow::{Context, Result};
+use crossterm::event::{self, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Whether a [`RawMode`] guard is held.
+static RAW: AtomicBool = AtomicBool::new(false);
+/// Times the process was resumed after a stop.
+static RESUMES: AtomicU64 = AtomicU64::new(0);
+
+/// Raw mode for as long as the guard lives.
+pub struct RawMode {
+    resumes_seen: u64,
+}
+
+impl RawMode {
+    pub fn enable() -> Result<Self> {
+        enable_raw_mode().context("Failed to enable raw mode")?;
+        RAW.store(true, Ordering::SeqCst);
+        Ok(Self {
+            resumes_seen: RESUMES.load(Ordering::SeqCst),
+        })
+    }
+
+    /// Whether the process was resumed since the last call, so the prompt must be redrawn.
+    pub fn take_resumed(&mut self) -> bool {
+        let resumes = RESUMES.load(Ordering::SeqCst);
+        let resumed = resumes != self.resumes_seen;
+        self.resumes_seen = resumes;
+        resumed
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        RAW.store(false, Ordering::SeqCst);
+        disable_raw_mode().ok();
+    }
+}
+
+/// Whether `key` is Ctrl+Z, which raw mode delivers as a key.
+pub fn is_suspend_key(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char('z' | 'Z')) && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// The answer typed at an authorization prompt, read once Enter is pressed: `a` or a yes word
+/// approves, `d`, a no word or nothing denies. A reason may follow after a colon
+/// (`deny: too risky`). Other input is not an answer.
+pub fn typed_decision(input: &str) -> Option<(ResponseType, Option<String>)> {
+    let (word, reason) = split_reason(input);
+    let decision = match word.to_lowercase().as_str() {
+        "a" | "y" | "yes" | "authorized" | "approve" | "ok" => ResponseType::AuthorizationApproved,
+        "d" | "n" | "no" | "denied" | "deny" | "reject" | "" => ResponseType::AuthorizationDenied,
+        _ => return None,
+    };
+    Some((decision, reason))
+}
+
+/// Whether the typed input is the one-key approval `a`, which a high-risk action asks to
+/// confirm.
+pub fn is_quick_approval(input: &str) -> bool {
+    input.trim().eq_ignore_ascii_case("a")
+}
+
+/// Whether `key` is a plain `a`, the key that confirms a high-risk approval.
+pub fn is_confirm_key(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char('a' | 'A'))
+        && !key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+}
+
+/// Discard keys typed ahead of an answer, so they reach neither the next prompt nor the
+/// shell.
+pub fn drain_input() {
+    while event::poll(Duration::ZERO).unwrap_or(false) {
+        if event::read().is_err() {
+            break;
+        }
+    }
+}
+
+/// Where the operator moves from the prompt on screen. The prompt goes back in its queue
+/// unanswered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Navigation {
+    /// Tab: on to the next channel with a queued prompt; this one keeps its place.
+    NextChannel,
+    /// Down: the next prompt queued on this channel; this one goes to the back.
+    NextPrompt,
+    /// Up: the last prompt queued on this channel; this one comes next after it.
+    PreviousPrompt,
+    /// `/text` and Enter: list the messages in history that mention `text`, then show the
+    /// prompt again.
+    Search(String),
+}
+
+/// The move a key makes at an empty prompt input: Tab, Down or Up. Other keys are input.
+pub fn navigation_key(key: &KeyEvent) -> Option<Navigation> {
+    if !key.modifiers.is_empty() {
+        return None;
+    }
+    match key.code {
+        KeyCode::Tab => Some(Navigation::NextChannel),
+        KeyCode::Down => Some(Navigation::NextPrompt),
+        KeyCode::Up => Some(Navigation::PreviousPrompt),
+        _ => None,
+    }
+}
+
+/// The history search typed at a prompt (`/deploy`), if the input is one.
+pub fn search_query(input: &str) -> Option<Navigation> {
+    let query = input.strip_prefix('/')?.trim();
+    (!query.is_empty()).then(|| Navigation::Search(query.to_string()))
+}
+
+/// Stop the process as Ctrl+Z would in cooked mode, with the terminal in cooked mode while it
+/// is stopped. Returns once it is resumed. Does nothing where job control does not exist.
+pub fn suspend() {
+    #[cfg(unix)]
+    {
+        if RAW.load(Ordering::SeqCst) {
+            disable_raw_mode().ok();
+        }
+        // SAFETY: raising a signal on the current process has no memory-safety requirements.
+        // SIGSTOP cannot be caught, so this returns only after SIGCONT.
+        unsafe {
+            libc::raise(libc::SIGSTOP);
+        }
+        resumed();
+    }
+}
+
+/// Put raw mode back if a prompt holds it, and have the prompt redrawn.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn resumed() {
+    if RAW.load(Ordering::SeqCst) {
+        enable_raw_mode().ok();
+    }
+    RESUMES.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Handle SIGTSTP like Ctrl+Z at a prompt, and SIGCONT after a stop that skipped it (SIGSTOP),
+/// until `token` is cancelled.
+#[cfg(unix)]
+pub fn spawn_suspend_handler(token: CancellationToken) -> tokio::task::JoinHandle<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    tokio::spawn(async move {
+        let signals = (
+            signal(SignalKind::from_raw(libc::SIGTSTP)),
+            signal(SignalKind::from_raw(libc::SIGCONT)),
+        );
+        let (mut tstp, mut cont) = match signals {
+            (Ok(tstp), Ok(cont)) => (tstp, cont),
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::warn!(error = %e, "Cannot handle terminal suspend signals");
+                return;
+            }
+        };
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                Some(()) = tstp.recv() => suspend(),
+                Some(()) = cont.recv() => resumed(),
+            }
+        }
+    })
+}
+
+#[cfg(not(unix))]
+pub fn spawn_suspend_handler(token: CancellationToken) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move { token.cancelled().await })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ctrl_z_is_the_suspend_key() {
+        assert!(is_suspend_key(&KeyEvent::new(
+            KeyCode::Char('z'),
+            KeyModifiers::CONTROL
+        )));
+        assert!(!is_suspend_key(&KeyEvent::new(
+            KeyCode::Char('z'),
+            KeyModifiers::NONE
+        )));
+        assert!(!is_suspend_key(&KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL
+        )));
+    }
+
+    #[test]
+    fn test_typed_decisions() {
+        assert_eq!(
+            typed_decision("approve"),
+            Some((ResponseType::AuthorizationApproved, None))
+        );
+        assert_eq!(
+            typed_decision("deny: too risky"),
+            Some((
+                ResponseType::AuthorizationDenied,
+                Some("too risky".to_string())
+            ))
+        );
+        assert_eq!(
+            typed_decision("a"),
+            Some((ResponseType::AuthorizationApproved, None))
+        );
+        assert_eq!(
+            typed_decision(" D "),
+            Some((ResponseType::AuthorizationDenied, None))
+        );
+        assert_eq!(
+            typed_decision(""),
+            Some((ResponseType::AuthorizationDenied, None))
+        );
+        assert_eq!(typed_decision("ad"), None);
+        assert_eq!(typed_decision("maybe"), None);
+    }
+
+    #[test]
+    fn test_quick_approval_and_confirm_key() {
+        assert!(is_quick_approval("A"));
+        assert!(!is_quick_approval("approve"));
+        assert!(is_confirm_key(&KeyEvent::new(
+            KeyCode::Char('a'),
+            KeyModifiers::NONE
+        )));
+        assert!(!is_confirm_key(&KeyEvent::new(
+            KeyCode::Char('a'),
+            KeyModifiers::CONTROL
+        )));
+        assert!(!is_confirm_key(&KeyEvent::new(
+            KeyCode::Char('d'),
+            KeyModifiers::NONE
+        )));
+    }
+
+    #[test]
+    fn test_navigation_keys() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert_eq!(
+            navigation_key(&key(KeyCode::Tab)),
+            Some(Navigation::NextChannel)
+        );
+        assert_eq!(
+            navigation_key(&key(KeyCode::Down)),
+            Some(Navigation::NextPrompt)
+        );
+        assert_eq!(
+            navigation_key(&key(KeyCode::Up)),
+            Some(Navigation::PreviousPrompt)
+        );
+        assert_eq!(navigation_key(&key(KeyCode::Char('a'))), None);
+        assert_eq!(
+            navigation_key(&KeyEvent::new(KeyCode::Tab, KeyModifiers::SHIFT)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_search_query() {
+        assert_eq!(
+            search_query("/ deploy "),
+            Some(Navigation::Search("deploy".to_string()))
+        );
+        assert_eq!(search_query("/"), None);
+        assert_eq!(search_query("y"), None);
+    }
+}
@@ -0,0 +1,178 @@
+//! Relaying answers while they are typed
+//!
+//! Drafts come from the server terminal, from `POST /api/v1/messages/{id}/typing` and from
+//! responder connections sending `{"type": "typing", "prompt_id": ..., "text": ...}`. The
+//! [`TypingRelay`] throttles them per prompt and decides who gets each frame: the connection
+//! that asked the prompt and viewers of its channel, as long as they accepted `typing` in a
+//! capabilities request (see [`ailoop_core::models::typing`]).
+
+use ailoop_core::models::{TypingConfig, TypingEvent};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+use uuid::Uuid;
+
+/// Prompts whose askers and throttle state are remembered; the oldest are forgotten first.
+const MAX_TRACKED_PROMPTS: usize = 10_000;
+
+#[derive(Default)]
+struct State {
+    config: TypingConfig,
+    /// Connections that accepted typing frames.
+    listeners: HashSet<Uuid>,
+    /// Listening connection that asked each prompt.
+    askers: HashMap<Uuid, Uuid>,
+    /// Last frame sent per prompt, with the draft length it carried.
+    last_sent: HashMap<Uuid, (Instant, usize)>,
+    order: VecDeque<Uuid>,
+}
+
+/// Who asked for typing frames, and when each prompt may have its next one.
+#[derive(Default)]
+pub struct TypingRelay {
+    state: Mutex<State>,
+}
+
+impl TypingRelay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_config(&self, config: TypingConfig) {
+        self.lock().config = config;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.lock().config.enabled
+    }
+
+    /// Send typing frames to `connection` from now on. False when streaming is off.
+    pub fn accept(&self, connection: Uuid) -> bool {
+        let mut state = self.lock();
+        if state.config.enabled {
+            state.listeners.insert(connection);
+        }
+        state.config.enabled
+    }
+
+    pub fn is_listener(&self, connection: &Uuid) -> bool {
+        self.lock().listeners.contains(connection)
+    }
+
+    /// Forget a closed connection.
+    pub fn remove_connection(&self, connection: &Uuid) {
+        let mut state = self.lock();
+        state.listeners.remove(connection);
+        state.askers.retain(|_, asker| asker != connection);
+    }
+
+    /// Remember that `connection` asked `prompt_id`, if it listens for typing frames.
+    pub fn record_prompt(&self, prompt_id: Uuid, connection: Uuid) {
+        let mut state = self.lock();
+        if !state.listeners.contains(&connection) {
+            return;
+        }
+        state.askers.insert(prompt_id, connection);
+        state.track(prompt_id);
+    }
+
+    /// The frame for `draft` of an answer to `prompt_id`, and the connection that asked the
+    /// prompt. `None` when streaming is off, nobody listens, or the last frame for the prompt
+    /// was sent less than `interval_ms` ago (except when the draft was just cleared).
+    pub fn update(
+        &self,
+        prompt_id: Uuid,
+        channel: &str,
+        draft: &str,
+    ) -> Option<(TypingEvent, Option<Uuid>)> {
+        let mut state = self.lock();
+        if !state.config.enabled || state.listeners.is_empty() {
+            return None;
+        }
+        let length = draft.chars().count();
+        let now = Instant::now();
+        if let Some((at, last_length)) = state.last_sent.get(&prompt_id) {
+            let cleared = length == 0 && *last_length > 0;
+            if !cleared && now.duration_since(*at) < state.config.interval() {
+                return None;
+            }
+        }
+        state.last_sent.insert(prompt_id, (now, length));
+        state.track(prompt_id);
+        let event = TypingEvent {
+            prompt_id,
+            channel: channel.to_string(),
+            text: state.config.partial_text().then(|| draft.to_string()),
+            length,
+            timestamp: chrono::Utc::now(),
+        };
+        Some((event, state.askers.get(&prompt_id).copied()))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("typing relay lock")
+    }
+}
+
+impl State {
+    fn track(&mut self, prompt_id: Uuid) {
+        if !self.order.contains(&prompt_id) {
+            self.order.push_back(prompt_id);
+        }
+        while self.order.len() > MAX_TRACKED_PROMPTS {
+            if let Some(old) = self.order.pop_front() {
+                self.askers.remove(&old);
+                self.last_sent.remove(&old);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled(interval_ms: u64) -> TypingRelay {
+        let relay = TypingRelay::new();
+        relay.set_config(TypingConfig {
+            enabled: true,
+            interval_ms: Some(interval_ms),
+            partial_text: None,
+        });
+        relay
+    }
+
+    #[test]
+    fn test_updates_are_throttled_per_prompt() {
+        let relay = enabled(60_000);
+        let (agent, prompt) = (Uuid::new_v4(), Uuid::new_v4());
+        assert!(
+            relay.update(prompt, "ops", "R").is_none(),
+            "nobody listens yet"
+        );
+
+        assert!(relay.accept(agent));
+        relay.record_prompt(prompt, agent);
+        let (event, asker) = relay.update(prompt, "ops", "Ro").unwrap();
+        assert_eq!(asker, Some(agent));
+        assert_eq!(event.text.as_deref(), Some("Ro"));
+        assert_eq!(event.length, 2);
+
+        assert!(relay.update(prompt, "ops", "Rol").is_none());
+        // Clearing the draft is always passed on.
+        assert_eq!(relay.update(prompt, "ops", "").unwrap().0.length, 0);
+        assert!(relay.update(Uuid::new_v4(), "ops", "other").is_some());
+
+        relay.remove_connection(&agent);
+        assert!(!relay.is_listener(&agent));
+    }
+
+    #[test]
+    fn test_disabled_relay_accepts_nobody() {
+        let relay = TypingRelay::new();
+        let agent = Uuid::new_v4();
+        assert!(!relay.accept(agent));
+        relay.record_prompt(Uuid::new_v4(), agent);
+        assert!(relay.update(Uuid::new_v4(), "ops", "x").is_none());
+    }
+}
@@ -0,0 +1,275 @@
+//! Per-client usage accounting and hourly quotas
+//!
+//! Clients are keyed by auth identity (JWT subject or static-token fingerprint), falling back
+//! to the `client_id` in message metadata, then `"anonymous"`. Messages tagged with an
+//! [incident](crate::server::incidents) count against limits raised by `[incident]`.
+
+use ailoop_core::models::{IncidentConfig, Message, MessageContent};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::RwLock;
+
+/// Length of the sliding quota window.
+const QUOTA_WINDOW_SECS: i64 = 3600;
+
+/// Hourly limits applied to every client. `None` means unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct UsageQuota {
+    pub max_messages_per_hour: Option<u32>,
+    pub max_prompts_per_hour: Option<u32>,
+}
+
+impl From<&ailoop_core::models::QuotasConfig> for UsageQuota {
+    fn from(cfg: &ailoop_core::models::QuotasConfig) -> Self {
+        Self {
+            max_messages_per_hour: cfg.max_messages_per_hour,
+            max_prompts_per_hour: cfg.max_prompts_per_hour,
+        }
+    }
+}
+
+/// What a quota counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaKind {
+    Messages,
+    Prompts,
+}
+
+/// Structured quota-exceeded error returned to agents.
+#[derive(Debug, Clone, Serialize, thiserror::Error)]
+#[error("quota exceeded for {client}: {limit} {kind:?} per hour")]
+pub struct QuotaExceeded {
+    pub client: String,
+    pub kind: QuotaKind,
+    pub limit: u32,
+    pub window_seconds: i64,
+    pub retry_after_seconds: i64,
+}
+
+/// Usage counters for one client, as returned by `/api/usage`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientUsage {
+    pub client: String,
+    pub total_messages: u64,
+    pub total_prompts: u64,
+    pub messages_last_hour: usize,
+    pub prompts_last_hour: usize,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct UsageEntry {
+    total_messages: u64,
+    total_prompts: u64,
+    recent_messages: VecDeque<DateTime<Utc>>,
+    recent_prompts: VecDeque<DateTime<Utc>>,
+    last_seen: Option<DateTime<Utc>>,
+}
+
+impl UsageEntry {
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - Duration::seconds(QUOTA_WINDOW_SECS);
+        for window in [&mut self.recent_messages, &mut self.recent_prompts] {
+            while window.front().is_some_and(|t| *t <= cutoff) {
+                window.pop_front();
+            }
+        }
+    }
+}
+
+/// Tracks per-client message and prompt counts and enforces [`UsageQuota`].
+pub struct UsageTracker {
+    quota: UsageQuota,
+    incident: RwLock<IncidentConfig>,
+    entries: RwLock<HashMap<String, UsageEntry>>,
+}
+
+impl UsageTracker {
+    pub fn new(quota: UsageQuota) -> Self {
+        Self {
+            quota,
+            incident: RwLock::new(IncidentConfig::default()),
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Replace how much quotas are raised for messages sent during an incident.
+    pub async fn set_incident_config(&self, config: IncidentConfig) {
+        *self.incident.write().await = config;
+    }
+
+    /// Resolve the usage key for a message sent by a caller with an optional auth key.
+    pub fn client_key(auth_key: Option<&str>, message: &Message) -> String {
+        auth_key
+            .map(str::to_string)
+            .or_else(|| {
+                message
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.get("client_id"))
+                    .and_then(|v| v.as_str())
+                    .map(|id| format!("client:{}", id))
+            })
+            .unwrap_or_else(|| "anonymous".to_string())
+    }
+
+    /// Count `message` against `client`, or reject it if that would exceed a quota.
+    pub async fn try_record(&self, client: &str, message: &Message) -> Result<(), QuotaExceeded> {
+        let is_prompt = matches!(
+            message.content,
+            MessageContent::Decision { .. }
+                | MessageContent::Authorization { .. }
+                | MessageContent::Navigate { .. }
+        );
+        let incident = match message.incident() {
+            Some(_) => Some(self.incident.read().await.clone()),
+            None => None,
+        };
+        let limit = |limit: Option<u32>| {
+            limit.map(|limit| incident.as_ref().map_or(limit, |c| c.quota(limit)))
+        };
+        let now = Utc::now();
+        let mut entries = self.entries.write().await;
+        let entry = entries.entry(client.to_string()).or_default();
+        entry.prune(now);
+
+        let mut checks = vec![(
+            QuotaKind::Messages,
+            limit(self.quota.max_messages_per_hour),
+            &entry.recent_messages,
+        )];
+        if is_prompt {
+            checks.push((
+                QuotaKind::Prompts,
+                limit(self.quota.max_prompts_per_hour),
+                &entry.recent_prompts,
+            ));
+        }
+        for (kind, limit, window) in checks {
+            if let Some(limit) = limit {
+                if window.len() >= limit as usize {
+                    let oldest = window.front().copied().unwrap_or(now);
+                    let retry_after = (oldest + Duration::seconds(QUOTA_WINDOW_SECS) - now)
+                        .num_seconds()
+                        .max(1);
+                    return Err(QuotaExceeded {
+                        client: client.to_string(),
+                        kind,
+                        limit,
+                        window_seconds: QUOTA_WINDOW_SECS,
+                        retry_after_seconds: retry_after,
+                    });
+                }
+            }
+        }
+
+        entry.total_messages += 1;
+        entry.recent_messages.push_back(now);
+        if is_prompt {
+            entry.total_prompts += 1;
+            entry.recent_prompts.push_back(now);
+        }
+        entry.last_seen = Some(now);
+        Ok(())
+    }
+
+    /// Snapshot of all clients, sorted by client key.
+    pub async fn snapshot(&self) -> Vec<ClientUsage> {
+        let now = Utc::now();
+        let mut entries = self.entries.write().await;
+        let mut out: Vec<ClientUsage> = entries
+            .iter_mut()
+            .map(|(client, entry)| {
+                entry.prune(now);
+                ClientUsage {
+                    client: client.clone(),
+                    total_messages: entry.total_messages,
+                    total_prompts: entry.total_prompts,
+                    messages_last_hour: entry.recent_messages.len(),
+                    prompts_last_hour: entry.recent_prompts.len(),
+                    last_seen: entry.last_seen.unwrap_or(now),
+                }
+            })
+            .collect();
+        out.sort_by(|a, b| a.client.cmp(&b.client));
+        out
+    }
+
+    /// Configured limits.
+    pub fn quota(&self) -> &UsageQuota {
+        &self.quota
+    }
+}
+
+impl Default for UsageTracker {
+    fn default() -> Self {
+        Self::new(UsageQuota::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ailoop_core::models::{NotificationPriority, SenderType};
+
+    fn notification() -> Message {
+        Message::new(
+            "public".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "hi".to_string(),
+                priority: NotificationPriority::Normal,
+            },
+        )
+    }
+
+    fn authorization() -> Message {
+        Message::new(
+            "public".to_string(),
+            SenderType::Agent,
+            MessageContent::Authorization {
+                action: "deploy".to_string(),
+                context: None,
+                timeout_seconds: 0,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_prompt_quota_rejects_after_limit() {
+        let tracker = UsageTracker::new(UsageQuota {
+            max_messages_per_hour: None,
+            max_prompts_per_hour: Some(2),
+        });
+        assert!(tracker.try_record("a", &authorization()).await.is_ok());
+        assert!(tracker.try_record("a", &authorization()).await.is_ok());
+        let err = tracker.try_record("a", &authorization()).await.unwrap_err();
+        assert_eq!(err.kind, QuotaKind::Prompts);
+        assert_eq!(err.limit, 2);
+
+        // Notifications and other clients are unaffected.
+        assert!(tracker.try_record("a", &notification()).await.is_ok());
+        assert!(tracker.try_record("b", &authorization()).await.is_ok());
+
+        let usage = tracker.snapshot().await;
+        assert_eq!(usage[0].client, "a");
+        assert_eq!(usage[0].total_messages, 3);
+        assert_eq!(usage[0].total_prompts, 2);
+
+        // Prompts sent during an incident get four times the quota by default.
+        let mut during_incident = authorization();
+        during_incident.metadata = Some(serde_json::json!({"incident": "INC-7"}));
+        assert!(tracker.try_record("a", &during_incident).await.is_ok());
+    }
+
+    #[test]
+    fn test_client_key_falls_back_to_metadata() {
+        let mut msg = notification();
+        assert_eq!(UsageTracker::client_key(None, &msg), "anonymous");
+        msg.metadata = Some(serde_json::json!({"client_id": "ci-7"}));
+        assert_eq!(UsageTracker::client_key(None, &msg), "client:ci-7");
+        assert_eq!(UsageTracker::client_key(Some("sub:x"), &msg), "sub:x");
+    }
+}
@@ -0,0 +1,222 @@
+//! Second-factor confirmation of risky authorizations
+//!
+//! `[verification]` sorts authorization actions into risk levels by the words they contain and
+//! names a method per level. When the operator approves such an action at the server terminal,
+//! the approval stands only after the method succeeds: a TOTP code typed in, or a touch of a
+//! FIDO2 key (YubiKey) confirmed by an external command. A wrong code, a missing touch or a
+//! method that is not set up turns the approval into a denial.
+
+use ailoop_core::models::{RiskLevel, VerificationConfig, VerificationMethod};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// Seconds one TOTP code is valid for.
+const TOTP_STEP_SECONDS: u64 = 30;
+const TOTP_DIGITS: u32 = 6;
+
+/// One way of confirming an approval.
+#[async_trait]
+pub trait Verifier: Send + Sync {
+    /// What the operator is asked to do, shown at the terminal.
+    fn instruction(&self) -> &str;
+
+    /// Whether `verify` needs a code typed by the operator.
+    fn needs_code(&self) -> bool;
+
+    /// Whether the operator confirmed; `code` is what they typed when `needs_code`.
+    async fn verify(&self, code: Option<&str>) -> Result<bool>;
+}
+
+/// The check required for approving `action`, with the risk level that requires it. An error
+/// means the level's method is not set up, and the approval must not stand.
+pub fn verifier_for(
+    config: &VerificationConfig,
+    action: &str,
+) -> Option<(RiskLevel, Result<Box<dyn Verifier>>)> {
+    let (level, method) = config.rule_for(action)?;
+    let verifier = match method {
+        VerificationMethod::Totp => {
+            Totp::from_config(config).map(|v| Box::new(v) as Box<dyn Verifier>)
+        }
+        VerificationMethod::Fido2 => {
+            Fido2::from_config(config).map(|v| Box::new(v) as Box<dyn Verifier>)
+        }
+    };
+    Some((level, verifier))
+}
+
+/// Time-based one-time codes (RFC 6238) from an authenticator app.
+pub struct Totp {
+    secret: Vec<u8>,
+}
+
+impl Totp {
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self { secret }
+    }
+
+    fn from_config(config: &VerificationConfig) -> Result<Self> {
+        let path = config
+            .totp_secret_file
+            .as_deref()
+            .context("[verification] totp_secret_file is not set")?;
+        let encoded = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read TOTP secret {}", path))?;
+        let secret = decode_base32(&encoded)
+            .with_context(|| format!("TOTP secret in {} is not valid base32", path))?;
+        Ok(Self::new(secret))
+    }
+
+    /// Whether `code` is valid at `unix_secs`, allowing one step of clock drift either way.
+    pub fn check(&self, code: &str, unix_secs: u64) -> bool {
+        let code = code.trim();
+        let step = unix_secs / TOTP_STEP_SECONDS;
+        [step.saturating_sub(1), step, step + 1]
+            .into_iter()
+            .any(|s| totp_code(&self.secret, s) == code)
+    }
+}
+
+#[async_trait]
+impl Verifier for Totp {
+    fn instruction(&self) -> &str {
+        "Enter the code from your authenticator app"
+    }
+
+    fn needs_code(&self) -> bool {
+        true
+    }
+
+    async fn verify(&self, code: Option<&str>) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        Ok(code.is_some_and(|code| self.check(code, now)))
+    }
+}
+
+/// The code for time step `step` (RFC 4226 HOTP over the step counter).
+fn totp_code(secret: &[u8], step: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        value % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+/// Decode an authenticator secret: RFC 4648 base32, case-insensitive, spaces and padding ignored.
+fn decode_base32(encoded: &str) -> Option<Vec<u8>> {
+    let mut bits: u64 = 0;
+    let mut count = 0;
+    let mut out = Vec::new();
+    for c in encoded.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u64 - 'A' as u64,
+            c @ '2'..='7' => c as u64 - '2' as u64 + 26,
+            _ => return None,
+        };
+        bits = (bits << 5) | value;
+        count += 5;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+            bits &= (1 << count) - 1;
+        }
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+/// A touch of a FIDO2 security key. The configured command does the talking to the key
+/// (`fido2-assert`, `ykchalresp` with a touch-protected slot, ...) and exits 0 once touched.
+pub struct Fido2 {
+    command: Vec<String>,
+    timeout: Duration,
+}
+
+impl Fido2 {
+    fn from_config(config: &VerificationConfig) -> Result<Self> {
+        if config.fido2_command.is_empty() {
+            anyhow::bail!("[verification] fido2_command is not set");
+        }
+        Ok(Self {
+            command: config.fido2_command.clone(),
+            timeout: config.timeout(),
+        })
+    }
+}
+
+#[async_trait]
+impl Verifier for Fido2 {
+    fn instruction(&self) -> &str {
+        "Touch your security key"
+    }
+
+    fn needs_code(&self) -> bool {
+        false
+    }
+
+    async fn verify(&self, _code: Option<&str>) -> Result<bool> {
+        let child = tokio::process::Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to run {}", self.command[0]))?;
+        let output = tokio::time::timeout(self.timeout, child.wait_with_output())
+            .await
+            .map_err(|_| anyhow::anyhow!("No key touch within {}s", self.timeout.as_secs()))??;
+        if !output.status.success() {
+            tracing::warn!(
+                status = %output.status,
+                stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+                "FIDO2 command did not confirm a touch"
+            );
+        }
+        Ok(output.status.success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totp_matches_rfc6238_vector() {
+        // RFC 6238 appendix B, SHA-1 secret "12345678901234567890": 94287082 at T=59.
+        let secret = decode_base32("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").unwrap();
+        assert_eq!(secret, b"12345678901234567890");
+        let totp = Totp::new(secret);
+        assert!(totp.check("287082", 59));
+        // One step of drift is tolerated, two are not.
+        assert!(totp.check("287082", 59 + TOTP_STEP_SECONDS));
+        assert!(!totp.check("287082", 59 + 2 * TOTP_STEP_SECONDS));
+        assert!(!totp.check("000000", 59));
+        assert_eq!(decode_base32("not base32!"), None);
+    }
+
+    #[test]
+    fn test_unconfigured_method_fails_closed() {
+        let config: VerificationConfig = serde_json::from_value(serde_json::json!({
+            "high": { "patterns": ["deploy"], "method": "fido2" }
+        }))
+        .unwrap();
+        assert!(verifier_for(&config, "list files").is_none());
+        let (level, verifier) = verifier_for(&config, "deploy v2").unwrap();
+        assert_eq!(level, RiskLevel::High);
+        assert!(verifier.is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Deadlines for putting one queued message to a human
+//!
+//! The message loop handles one message at a time, so a prompt that never finishes (a wedged
+//! terminal reader, a provider call that hangs) would hold up every channel. Each message gets
+//! a deadline: its own timeout plus `[watchdog] grace_seconds`, notifications the grace alone,
+//! and prompts without a timeout `max_processing_seconds` when set. Past the deadline the
+//! message is ended as errored: the waiting agent gets a `cancelled` response with
+//! `metadata.error` `"processing_deadline"`, and the channel gets an urgent system alert.
+
+use ailoop_core::models::{
+    Configuration, Message, MessageContent, NotificationPriority, PromptKind, ResponseType,
+    SenderType, TimeoutPolicy, WatchdogConfig,
+};
+use std::future::Future;
+use std::time::Duration;
+
+/// How long `message` may be processed, or `None` for no deadline. `chat_timeout` is how long
+/// a chat line waits for a reply at the terminal.
+pub fn deadline(
+    message: &Message,
+    config: Option<&Configuration>,
+    chat_timeout: Duration,
+) -> Option<Duration> {
+    let watchdog = config.map(|c| c.watchdog.clone()).unwrap_or_default();
+    if !watchdog.enabled() {
+        return None;
+    }
+    let timeout = match &message.content {
+        MessageContent::Decision {
+            timeout_seconds, ..
+        } => prompt_timeout(PromptKind::Question, *timeout_seconds, config, &watchdog)?,
+        MessageContent::Authorization {
+            action,
+            timeout_seconds,
+            ..
+        } => {
+            // An approval may then wait for its second factor and be held back for its undo
+            // grace period.
+            let verification = config
+                .filter(|c| c.verification.rule_for(action).is_some())
+                .map(|c| c.verification.timeout())
+                .unwrap_or_default();
+            let undo = config
+                .and_then(|c| c.undo.grace_for(&message.channel, action))
+                .unwrap_or_default();
+            prompt_timeout(
+                PromptKind::Authorization,
+                *timeout_seconds,
+                config,
+                &watchdog,
+            )? + verification
+                + undo
+        }
+        MessageContent::Navigate {
+            timeout_seconds, ..
+        } => prompt_timeout(PromptKind::Navigation, *timeout_seconds, config, &watchdog)?,
+        MessageContent::Chat { .. } => chat_timeout,
+        _ => Duration::ZERO,
+    };
+    Some(timeout + watchdog.grace())
+}
+
+fn prompt_timeout(
+    kind: PromptKind,
+    secs: u32,
+    config: Option<&Configuration>,
+    watchdog: &WatchdogConfig,
+) -> Option<Duration> {
+    TimeoutPolicy::resolve(kind, secs, config)
+        .timeout()
+        .or_else(|| watchdog.max_processing())
+}
+
+/// Run `processing` until `deadline`; `None` when the deadline passed first.
+pub async fn run<F: Future>(deadline: Option<Duration>, processing: F) -> Option<F::Output> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout(deadline, processing).await.ok(),
+        None => Some(processing.await),
+    }
+}
+
+/// The errored response ending `message` after `deadline`, so a waiting agent unblocks.
+pub fn errored_reply(message: &Message, deadline: Duration) -> Message {
+    let mut reply = Message::response(
+        message.channel.clone(),
+        MessageContent::Response {
+            answer: Some(format!(
+                "Not answered: processing took longer than {}s",
+                deadline.as_secs()
+            )),
+            response_type: ResponseType::Cancelled,
+        },
+        message.id,
+    );
+    reply.metadata = Some(serde_json::json!({
+        "error": "processing_deadline",
+        "deadline_seconds": deadline.as_secs(),
+    }));
+    reply
+}
+
+/// Urgent system notification telling the channel's humans that `message` was given up on.
+pub fn alert(message: &Message, deadline: Duration) -> Message {
+    let what = message
+        .prompt_text()
+        .map(|text| format!("Prompt \"{}\"", text))
+        .unwrap_or_else(|| format!("Message {}", message.id));
+    Message::new(
+        message.channel.clone(),
+        SenderType::System,
+        MessageContent::Notification {
+            text: format!(
+                "{} was still being processed after {}s and has been ended as errored; the queue moves on",
+                what,
+                deadline.as_secs()
+            ),
+            priority: NotificationPriority::Urgent,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorization(timeout_seconds: u32) -> Message {
+        Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Authorization {
+                action: "deploy".to_string(),
+                context: None,
+                timeout_seconds,
+            },
+        )
+    }
+
+    #[test]
+    fn test_deadline_is_timeout_plus_grace() {
+        let chat = Duration::from_secs(120);
+        let config = Configuration::default();
+        assert_eq!(
+            deadline(&authorization(30), Some(&config), chat),
+            Some(Duration::from_secs(90))
+        );
+
+        // Prompts waiting until answered only get a deadline when one is configured.
+        let mut config = Configuration {
+            timeout_seconds: None,
+            ..Configuration::default()
+        };
+        assert_eq!(deadline(&authorization(0), Some(&config), chat), None);
+        config.watchdog.max_processing_seconds = Some(600);
+        config.watchdog.grace_seconds = Some(5);
+        assert_eq!(
+            deadline(&authorization(0), Some(&config), chat),
+            Some(Duration::from_secs(605))
+        );
+
+        config.watchdog.enabled = Some(false);
+        assert_eq!(deadline(&authorization(30), Some(&config), chat), None);
+    }
+
+    #[tokio::test]
+    async fn test_run_gives_up_past_the_deadline() {
+        let short = Some(Duration::from_millis(10));
+        assert_eq!(run(short, std::future::pending::<()>()).await, None);
+        assert_eq!(run(short, async { 7 }).await, Some(7));
+
+        let message = authorization(30);
+        let reply = errored_reply(&message, Duration::from_secs(90));
+        assert_eq!(reply.correlation_id, Some(message.id));
+        assert_eq!(
+            reply.metadata.unwrap()["error"],
+            serde_json::json!("processing_deadline")
+        );
+    }
+}
@@ -1,11 +1,23 @@
 use ailoop_core::channel::ChannelIsolation;
-use ailoop_core::models::Configuration;
+use ailoop_core::models::{Configuration, EchoConfig, LoopDetectionConfig};
 use ailoop_core::server::TaskStorage;
+use ailoop_core::services::signing::ResponseSigner;
 use std::sync::{atomic::AtomicBool, Arc};
 
+use crate::server::attachments::AttachmentStore;
 use crate::server::broadcast::BroadcastManager;
 use crate::server::history::MessageHistory;
-use crate::server::providers::PendingPromptRegistry;
+use crate::server::hooks::MessageHooks;
+use crate::server::loops::LoopGuard;
+#[cfg(feature = "telegram")]
+use crate::server::providers::TelegramWebhookInbox;
+#[cfg(feature = "twilio")]
+use crate::server::providers::TwilioSink;
+use crate::server::providers::{PendingPromptRegistry, ProviderFactory, ProviderRegistry};
+use crate::server::scheduler::Scheduler;
+use crate::server::sequencer::Sequencer;
+use crate::server::supervisor::Supervisor;
+use crate::server::usage::{UsageQuota, UsageTracker};
 
 /// Shared application state. Construct once; clone (cheap — all fields are `Arc<T>`) for concurrent use.
 ///
@@ -18,12 +30,42 @@ pub struct AiloopAppState {
     pub broadcast_manager: Arc<BroadcastManager>,
     pub task_storage: Arc<TaskStorage>,
     pub pending_prompt_registry: Arc<PendingPromptRegistry>,
+    /// Per-client message/prompt counters and quotas.
+    pub usage: Arc<UsageTracker>,
+    /// Chat providers (Telegram, Twilio, ...) started by `spawn_background_tasks`.
+    pub providers: Arc<ProviderRegistry>,
+    /// Signs prompt responses when response signing is enabled.
+    pub signer: Option<Arc<ResponseSigner>>,
+    /// Files attached to prompt responses.
+    pub attachments: Arc<AttachmentStore>,
+    /// Policy hooks run on agent messages and prompt responses.
+    pub hooks: Arc<MessageHooks>,
+    /// Messages waiting to be sent later.
+    pub scheduler: Arc<Scheduler>,
+    /// Who asked each prompt, to refuse self-answers and answer loops.
+    pub loops: Arc<LoopGuard>,
+    /// Puts numbered messages posted concurrently by one client back in order.
+    pub sequencer: Arc<Sequencer>,
+    /// Restarts background tasks that panic; its counts are in `/api/stats`.
+    pub supervisor: Arc<Supervisor>,
     pub default_channel: String,
     /// Whether to serve the embedded web UI. Set by `router()` from `ServeConfig.web`.
     pub web: bool,
+    /// Reject agent messages that fail strict schema validation instead of accepting them on a
+    /// best-effort basis.
+    pub strict: bool,
+    /// Answer every prompt at once with these canned answers instead of asking a human
+    /// (`serve --echo`).
+    pub echo: Option<EchoConfig>,
     /// Optional provider configuration (e.g. Telegram). Not part of the public schema but
     /// accessible within the crate for `spawn_background_tasks`.
     pub(crate) provider_config: Option<Configuration>,
+    /// Telegram updates received on the webhook endpoint; enabled in webhook mode.
+    #[cfg(feature = "telegram")]
+    pub(crate) telegram_webhook: Arc<TelegramWebhookInbox>,
+    /// Twilio sink, set once the provider starts; the reply webhook returns 404 until then.
+    #[cfg(feature = "twilio")]
+    pub(crate) twilio: Arc<std::sync::OnceLock<Arc<TwilioSink>>>,
     /// Set to `true` by `spawn_background_tasks` when the shutdown token fires.
     /// Handlers check this to return 503 on new enqueue attempts after shutdown.
     pub(crate) is_shutting_down: Arc<AtomicBool>,
@@ -39,16 +81,85 @@ impl AiloopAppState {
             broadcast_manager: Arc::new(BroadcastManager::new()),
             task_storage: Arc::new(TaskStorage::new()),
             pending_prompt_registry: Arc::new(PendingPromptRegistry::new()),
+            usage: Arc::new(UsageTracker::default()),
+            providers: Arc::new(ProviderRegistry::with_builtin()),
+            signer: None,
+            attachments: Arc::new(AttachmentStore::in_temp_dir()),
+            hooks: Arc::new(MessageHooks::default()),
+            scheduler: Arc::new(Scheduler::in_memory()),
+            loops: Arc::new(LoopGuard::default()),
+            sequencer: Arc::new(Sequencer::default()),
+            supervisor: Arc::new(Supervisor::new()),
             default_channel: dc,
             web: false,
+            strict: false,
+            echo: None,
             provider_config: None,
+            #[cfg(feature = "telegram")]
+            telegram_webhook: Arc::new(TelegramWebhookInbox::new()),
+            #[cfg(feature = "twilio")]
+            twilio: Arc::new(std::sync::OnceLock::new()),
             is_shutting_down: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    /// Register a provider factory under `name`, replacing a built-in provider of that name.
+    pub fn with_provider(self, name: impl Into<String>, factory: ProviderFactory) -> Self {
+        self.providers.register(name, factory);
+        self
+    }
+
     /// Attach a provider configuration (Telegram settings, etc.).
     pub fn with_provider_config(mut self, config: Configuration) -> Self {
         self.provider_config = Some(config);
         self
     }
+
+    /// Enforce hourly per-client quotas on incoming agent messages.
+    pub fn with_usage_quota(mut self, quota: UsageQuota) -> Self {
+        self.usage = Arc::new(UsageTracker::new(quota));
+        self
+    }
+
+    /// Keep response attachments in `dir` instead of the temporary directory.
+    pub fn with_attachment_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.attachments = Arc::new(AttachmentStore::new(dir));
+        self
+    }
+
+    /// Run `hooks` on agent messages and prompt responses.
+    pub fn with_hooks(mut self, hooks: MessageHooks) -> Self {
+        self.hooks = Arc::new(hooks);
+        self
+    }
+
+    /// Keep scheduled messages in `scheduler` (e.g. one saved to a file) instead of in memory.
+    pub fn with_scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = Arc::new(scheduler);
+        self
+    }
+
+    /// Check prompt answers for self-answers and loops with `config` instead of the defaults.
+    pub fn with_loop_detection(mut self, config: LoopDetectionConfig) -> Self {
+        self.loops = Arc::new(LoopGuard::new(config));
+        self
+    }
+
+    /// Validate agent messages strictly (see [`ailoop_core::models::strict`]).
+    pub fn with_strict_validation(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Answer prompts from `config` the moment they arrive (see [`crate::server::echo`]).
+    pub fn with_echo(mut self, config: EchoConfig) -> Self {
+        self.echo = Some(config);
+        self
+    }
+
+    /// Sign every prompt response with `signer`.
+    pub fn with_response_signer(mut self, signer: ResponseSigner) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
 }
@@ -0,0 +1,147 @@
+//! OIDC/JWT auth tests — tokens validated against a JWKS served by a local test server.
+
+use ailoop_server::{router, AiloopAppState, AuthConfig, JwtConfig, Role, ServeConfig};
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const SECRET: &[u8] = b"ailoop-test-signing-secret-32byte";
+const SECRET_B64URL: &str = "YWlsb29wLXRlc3Qtc2lnbmluZy1zZWNyZXQtMzJieXRl";
+const ISSUER: &str = "https://sso.example.com";
+const AUDIENCE: &str = "ailoop";
+
+/// Serve a JWKS containing one symmetric key and return its URL.
+async fn spawn_jwks_server() -> String {
+    let jwks = serde_json::json!({
+        "keys": [{"kty": "oct", "kid": "k1", "alg": "HS256", "k": SECRET_B64URL}]
+    });
+    let app = axum::Router::new().route(
+        "/jwks",
+        axum::routing::get(move || {
+            let jwks = jwks.clone();
+            async move { axum::Json(jwks) }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{}/jwks", addr)
+}
+
+fn sign(claims: serde_json::Value) -> String {
+    sign_with(Algorithm::HS256, claims)
+}
+
+fn sign_with(alg: Algorithm, claims: serde_json::Value) -> String {
+    let mut header = Header::new(alg);
+    header.kid = Some("k1".to_string());
+    encode(&header, &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+}
+
+fn claims(aud: &str, roles: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "iss": ISSUER,
+        "aud": aud,
+        "sub": "alice@example.com",
+        "exp": chrono::Utc::now().timestamp() + 600,
+        "groups": roles,
+    })
+}
+
+async fn app() -> axum::Router {
+    let mut jwt = JwtConfig::new(ISSUER, AUDIENCE);
+    jwt.jwks_url = Some(spawn_jwks_server().await);
+    jwt.role_claim = "groups".to_string();
+    jwt.role_mapping
+        .insert("oncall".to_string(), Role::Responder);
+    let config = ServeConfig {
+        auth: Some(AuthConfig {
+            jwt: Some(jwt),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    router(Arc::new(AiloopAppState::new("default")), &config).unwrap()
+}
+
+async fn send(app: axum::Router, method: &str, uri: &str, token: &str) -> StatusCode {
+    app.oneshot(
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {token}"))
+            .header("Content-Type", "application/json")
+            .body(Body::from("{}"))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+    .status()
+}
+
+#[tokio::test]
+async fn valid_jwt_is_accepted_with_mapped_role() {
+    let token = sign(claims(AUDIENCE, serde_json::json!(["oncall"])));
+    assert_eq!(
+        send(app().await, "GET", "/api/v1/health", &token).await,
+        StatusCode::OK
+    );
+    // Responder may not manage tasks.
+    assert_eq!(
+        send(app().await, "POST", "/api/v1/tasks", &token).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn jwt_with_wrong_audience_is_rejected() {
+    let token = sign(claims("someone-else", serde_json::json!("admin")));
+    assert_eq!(
+        send(app().await, "GET", "/api/v1/health", &token).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn jwt_without_role_is_rejected() {
+    let token = sign(claims(AUDIENCE, serde_json::json!(["unrelated-group"])));
+    assert_eq!(
+        send(app().await, "GET", "/api/v1/health", &token).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn jwt_with_another_algorithm_than_its_key_is_rejected() {
+    // The key is published as HS256; the same secret under HS512 must not pass.
+    let token = sign_with(
+        Algorithm::HS512,
+        claims(AUDIENCE, serde_json::json!("admin")),
+    );
+    assert_eq!(
+        send(app().await, "GET", "/api/v1/health", &token).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn jwt_accepted_as_access_token_query_param() {
+    let token = sign(claims(AUDIENCE, serde_json::json!("viewer")));
+    let resp = app()
+        .await
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/v1/health?access_token={token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
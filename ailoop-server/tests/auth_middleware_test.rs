@@ -1,9 +1,10 @@
 //! Auth middleware tests — on/off behaviour for REST and WS upgrade.
 
-use ailoop_server::{router, AiloopAppState, AuthConfig, ServeConfig};
+use ailoop_server::middleware::auth::required_role;
+use ailoop_server::{router, AiloopAppState, AuthConfig, Role, ServeConfig};
 use axum::{
     body::Body,
-    http::{Request, StatusCode},
+    http::{Method, Request, StatusCode},
 };
 use std::sync::Arc;
 use tower::ServiceExt;
@@ -21,6 +22,7 @@ fn config_no_auth() -> ServeConfig {
         web: false,
         auth: None,
         cors: None,
+        ip_filter: None,
     }
 }
 
@@ -28,11 +30,44 @@ fn config_with_auth(tokens: Vec<&str>) -> ServeConfig {
     ServeConfig {
         auth: Some(AuthConfig {
             tokens: tokens.into_iter().map(String::from).collect(),
+            ..Default::default()
         }),
         ..config_no_auth()
     }
 }
 
+fn config_with_roles() -> ServeConfig {
+    ServeConfig {
+        auth: Some(AuthConfig {
+            tokens: vec!["admin-tok".to_string()],
+            roles: [
+                ("viewer-tok".to_string(), Role::Viewer),
+                ("responder-tok".to_string(), Role::Responder),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        }),
+        ..config_no_auth()
+    }
+}
+
+async fn status_for(method: &str, uri: &str, token: &str) -> StatusCode {
+    let r: axum::Router = router(state(), &config_with_roles()).unwrap();
+    r.oneshot(
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {token}"))
+            .header("Content-Type", "application/json")
+            .body(Body::from(r#"{"answer":"ok","response_type":"text"}"#))
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+    .status()
+}
+
 #[tokio::test]
 async fn auth_off_all_requests_pass() {
     let r: axum::Router = router(state(), &config_no_auth()).unwrap();
@@ -136,3 +171,156 @@ async fn auth_on_ws_upgrade_without_token_returns_401() {
         .unwrap();
     assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
 }
+
+#[tokio::test]
+async fn viewer_role_is_read_only() {
+    assert_eq!(
+        status_for("GET", "/api/v1/health", "viewer-tok").await,
+        StatusCode::OK
+    );
+    let id = uuid::Uuid::new_v4();
+    assert_eq!(
+        status_for(
+            "POST",
+            &format!("/api/v1/messages/{id}/response"),
+            "viewer-tok"
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status_for("POST", "/api/v1/tasks", "viewer-tok").await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn responder_role_can_answer_but_not_manage_tasks() {
+    let id = uuid::Uuid::new_v4();
+    // Passes auth; the unknown message id then yields 404 from the handler.
+    assert_eq!(
+        status_for(
+            "POST",
+            &format!("/api/v1/messages/{id}/response"),
+            "responder-tok"
+        )
+        .await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        status_for("POST", "/api/v1/tasks", "responder-tok").await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[test]
+fn required_role_lets_responders_send_typing_drafts() {
+    let id = uuid::Uuid::new_v4();
+    assert_eq!(
+        required_role(&Method::POST, &format!("/api/v1/messages/{id}/typing")),
+        Role::Responder
+    );
+}
+
+#[tokio::test]
+async fn typing_drafts_need_responder_role() {
+    let uri = format!("/api/v1/messages/{}/typing", uuid::Uuid::new_v4());
+    assert_eq!(
+        status_for("POST", &uri, "viewer-tok").await,
+        StatusCode::FORBIDDEN
+    );
+    let status = status_for("POST", &uri, "responder-tok").await;
+    assert_ne!(status, StatusCode::FORBIDDEN);
+    assert_ne!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn required_role_lets_responders_upload_attachments() {
+    assert_eq!(
+        required_role(&Method::POST, "/api/v1/attachments"),
+        Role::Responder
+    );
+    let id = uuid::Uuid::new_v4();
+    assert_eq!(
+        required_role(&Method::GET, &format!("/api/v1/attachments/{id}")),
+        Role::Viewer
+    );
+}
+
+#[tokio::test]
+async fn attachment_upload_needs_responder_role() {
+    let uri = "/api/v1/attachments?name=notes.txt";
+    assert_eq!(
+        status_for("POST", uri, "viewer-tok").await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status_for("POST", uri, "responder-tok").await,
+        StatusCode::CREATED
+    );
+}
+
+#[tokio::test]
+async fn admin_role_can_manage_tasks() {
+    let status = status_for("POST", "/api/v1/tasks", "admin-tok").await;
+    assert_ne!(status, StatusCode::FORBIDDEN);
+    assert_ne!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn event_log_requires_admin_role() {
+    for token in ["viewer-tok", "responder-tok"] {
+        assert_eq!(
+            status_for("GET", "/api/logs", token).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+    // Passes auth; no event log is installed in tests, so the handler answers 404.
+    assert_eq!(
+        status_for("GET", "/api/logs", "admin-tok").await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn provider_webhook_bypasses_token_auth() {
+    let r: axum::Router = router(state(), &config_with_auth(vec!["secret"])).unwrap();
+    let resp = r
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/providers/telegram/webhook")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"update_id":1}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    // Reaches the handler (not 401); webhook mode is off, so the endpoint is hidden.
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn ws_connection_type_is_checked_against_role() {
+    let r: axum::Router = router(state(), &config_with_roles()).unwrap();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, r.into_make_service()).await.ok() });
+
+    let connect = |token: &str, connection: &str| {
+        let url = format!("ws://127.0.0.1:{port}/?access_token={token}&connection={connection}");
+        async move {
+            match tokio_tungstenite::connect_async(&url).await {
+                Ok(_) => 101,
+                Err(tokio_tungstenite::tungstenite::Error::Http(resp)) => resp.status().as_u16(),
+                Err(e) => panic!("unexpected error: {e}"),
+            }
+        }
+    };
+    assert_eq!(connect("viewer-tok", "viewer").await, 101);
+    assert_eq!(connect("viewer-tok", "agent").await, 403);
+    assert_eq!(connect("viewer-tok", "responder").await, 403);
+    assert_eq!(connect("responder-tok", "responder").await, 101);
+    assert_eq!(connect("admin-tok", "agent").await, 101);
+    assert_eq!(connect("admin-tok", "robot").await, 400);
+}
@@ -57,6 +57,8 @@ async fn client_ask_returns_server_response() -> Result<()> {
             ],
             None,
             DECISION_TIMEOUT,
+            false,
+            None,
         )
         .await
     });
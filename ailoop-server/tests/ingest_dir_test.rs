@@ -0,0 +1,112 @@
+//! Integration test: NDJSON files dropped into the ingest directory are delivered like
+//! WebSocket agent messages and archived.
+
+use ailoop_core::models::{Message, MessageContent, NotificationPriority, SenderType};
+use ailoop_server::server::ingest::{ingest_once, spawn_ingest, IngestConfig, IngestReport};
+use ailoop_server::AiloopAppState;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+fn line(channel: &str, text: &str) -> String {
+    serde_json::to_string(&Message::new(
+        channel.to_string(),
+        SenderType::Agent,
+        MessageContent::Notification {
+            text: text.to_string(),
+            priority: NotificationPriority::Normal,
+        },
+    ))
+    .unwrap()
+}
+
+/// Notification texts in `channel`'s history once `count` arrived, sorted (history is written
+/// from spawned tasks, so arrival order is not fixed).
+async fn history_texts(state: &AiloopAppState, channel: &str, count: usize) -> Vec<String> {
+    let mut texts = Vec::new();
+    for _ in 0..50 {
+        texts = state
+            .message_history
+            .get_messages(channel, None)
+            .await
+            .into_iter()
+            .filter_map(|m| match m.content {
+                MessageContent::Notification { text, .. } => Some(text),
+                _ => None,
+            })
+            .collect();
+        if texts.len() >= count {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    texts.sort();
+    texts
+}
+
+#[tokio::test]
+async fn ingests_and_archives_message_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("a.ndjson"),
+        format!("{}\n\n{}\n", line("builds", "one"), line("builds", "two")),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("b.jsonl"),
+        format!(
+            "{}\nnot json\n{}\n",
+            line("builds", "three"),
+            line("bad channel!", "x")
+        ),
+    )
+    .unwrap();
+    std::fs::write(dir.path().join(".c.ndjson.partial"), line("builds", "skip")).unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+    let state = AiloopAppState::new("public");
+    let report = ingest_once(&state, &IngestConfig::new(dir.path()))
+        .await
+        .unwrap();
+    assert_eq!(
+        report,
+        IngestReport {
+            files: 2,
+            messages: 3,
+            rejected: 2
+        }
+    );
+    assert_eq!(
+        history_texts(&state, "builds", 3).await,
+        vec!["one", "three", "two"]
+    );
+
+    assert!(dir.path().join("processed/a.ndjson").exists());
+    assert!(dir.path().join("failed/b.jsonl").exists());
+    assert!(!dir.path().join("a.ndjson").exists());
+    assert!(dir.path().join(".c.ndjson.partial").exists());
+    assert!(dir.path().join("notes.txt").exists());
+
+    // Nothing left to pick up.
+    let again = ingest_once(&state, &IngestConfig::new(dir.path()))
+        .await
+        .unwrap();
+    assert_eq!(again, IngestReport::default());
+}
+
+#[tokio::test]
+async fn spawned_ingest_picks_up_new_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = Arc::new(AiloopAppState::new("public"));
+    let token = CancellationToken::new();
+    let mut config = IngestConfig::new(dir.path());
+    config.poll_interval = Duration::from_millis(50);
+    let handle = spawn_ingest(Arc::clone(&state), config, token.clone());
+
+    std::fs::write(dir.path().join("late.ndjson"), line("public", "hello")).unwrap();
+    assert_eq!(history_texts(&state, "public", 1).await, vec!["hello"]);
+
+    token.cancel();
+    handle.await.unwrap();
+    assert!(dir.path().join("processed/late.ndjson").exists());
+}
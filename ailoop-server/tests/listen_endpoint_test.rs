@@ -0,0 +1,65 @@
+//! Integration test: the HTTP API is served on a Unix domain socket endpoint.
+#![cfg(unix)]
+
+use ailoop_server::server::listen::{serve_endpoint, ListenEndpoint};
+use ailoop_server::{router, AiloopAppState, ServeConfig};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+fn serve_config() -> ServeConfig {
+    ServeConfig {
+        host: "127.0.0.1".to_string(),
+        port: 0,
+        default_channel: "public".to_string(),
+        base_path: None,
+        web: false,
+        auth: None,
+        cors: None,
+        ip_filter: None,
+    }
+}
+
+#[tokio::test]
+async fn serves_api_on_unix_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ailoop.sock");
+    // A stale socket file from an earlier run is replaced.
+    std::fs::write(&path, b"").unwrap();
+
+    let state = Arc::new(AiloopAppState::new("public"));
+    let app = router(state, &serve_config()).unwrap();
+    let endpoint: ListenEndpoint = format!("unix:{}", path.display()).parse().unwrap();
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(async move {
+        serve_endpoint(app, &endpoint, async {
+            let _ = stopped.await;
+        })
+        .await
+    });
+
+    let mut stream = None;
+    for _ in 0..50 {
+        if let Ok(s) = tokio::net::UnixStream::connect(&path).await {
+            stream = Some(s);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let mut stream = stream.expect("socket never accepted");
+    stream
+        .write_all(b"GET /api/v1/health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    stop.send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(!path.exists());
+}
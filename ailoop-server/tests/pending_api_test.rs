@@ -19,6 +19,7 @@ fn default_config() -> ServeConfig {
         web: false,
         auth: None,
         cors: None,
+        ip_filter: None,
     }
 }
 
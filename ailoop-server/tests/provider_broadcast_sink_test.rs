@@ -1,8 +1,9 @@
 //! Integration test: broadcast invokes registered notification sinks; delivery failure is logged.
 
-use ailoop_core::models::{Message, MessageContent, SenderType};
+use ailoop_core::models::{Message, MessageContent, MirrorConfig, SenderType};
 use ailoop_server::server::broadcast::{BroadcastManager, ConnectionType};
-use ailoop_server::server::providers::NotificationSink;
+use ailoop_server::server::oncall::OnCallRouter;
+use ailoop_server::server::providers::{DeliveryPolicy, DeliveryStatus, NotificationSink};
 use async_trait::async_trait;
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -131,3 +132,312 @@ async fn subscribe_to_all_delivers_broadcasts_for_any_channel() {
     };
     assert!(text.contains("live"));
 }
+
+#[tokio::test]
+async fn mirrored_channels_copy_traffic_to_the_aggregate_channel() {
+    let manager = BroadcastManager::new();
+    manager
+        .set_mirror_rules(vec![
+            MirrorConfig {
+                from: "team-*".to_string(),
+                to: "audit".to_string(),
+            },
+            MirrorConfig {
+                from: "audit".to_string(),
+                to: "team-a".to_string(),
+            },
+        ])
+        .await;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let connection_id = manager.add_viewer(ConnectionType::Viewer, tx).await;
+    manager
+        .subscribe_to_channel(&connection_id, "audit")
+        .await
+        .unwrap();
+
+    let message = Message::new(
+        "team-a".to_string(),
+        SenderType::Agent,
+        MessageContent::Notification {
+            text: "deployed".to_string(),
+            priority: ailoop_core::models::NotificationPriority::Normal,
+        },
+    );
+    manager.broadcast_message(&message).await;
+
+    let text = match rx.try_recv().expect("audit subscriber gets a copy") {
+        axum::extract::ws::Message::Text(s) => s,
+        other => panic!("expected Text broadcast, got {:?}", other),
+    };
+    let copy: Message = serde_json::from_str(&text).unwrap();
+    assert_eq!(copy.channel, "audit");
+    assert_ne!(copy.id, message.id);
+    let source = &copy.metadata.as_ref().unwrap()["mirrored_from"];
+    assert_eq!(source["channel"], "team-a");
+    assert_eq!(source["message_id"], message.id.to_string());
+    assert!(rx.try_recv().is_err(), "one copy per aggregate channel");
+
+    // Copies are not mirrored again, even with a rule pointing back.
+    manager.broadcast_message(&copy).await;
+    assert!(rx.try_recv().is_ok());
+    assert!(rx.try_recv().is_err());
+}
+
+fn prompt(channel: &str) -> Message {
+    Message::new(
+        channel.to_string(),
+        SenderType::Agent,
+        MessageContent::Authorization {
+            action: "deploy".to_string(),
+            context: None,
+            timeout_seconds: 60,
+        },
+    )
+}
+
+fn fast_policy(failover: bool, order: &[&str]) -> DeliveryPolicy {
+    DeliveryPolicy {
+        retries: 2,
+        backoff: std::time::Duration::from_millis(1),
+        failover,
+        order: order.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+#[tokio::test]
+async fn prompt_delivery_retries_then_fails_over() {
+    let manager = BroadcastManager::new();
+    manager.set_delivery_policy(fast_policy(true, &[])).await;
+    manager.add_notification_sink(Arc::new(FailingSink)).await;
+    let (mock, received) = MockSink::new("backup");
+    manager.add_notification_sink(Arc::new(mock)).await;
+
+    let message = prompt("ch");
+    manager
+        .send_to_notification_sinks_and_get_reply_to_id(&message)
+        .await;
+
+    assert_eq!(received.read().await.len(), 1);
+    let status = manager.delivery_status(&message.id);
+    assert_eq!(status.len(), 2);
+    assert_eq!(status[0].provider, "failing");
+    assert_eq!(status[0].status, DeliveryStatus::Failed);
+    assert_eq!(status[0].attempts, 3);
+    assert_eq!(status[0].error.as_deref(), Some("delivery failed"));
+    assert_eq!(status[1].provider, "backup");
+    assert_eq!(status[1].status, DeliveryStatus::Delivered);
+}
+
+#[tokio::test]
+async fn failover_stops_at_first_delivered_provider_in_order() {
+    let manager = BroadcastManager::new();
+    manager
+        .set_delivery_policy(fast_policy(true, &["backup"]))
+        .await;
+    manager.add_notification_sink(Arc::new(FailingSink)).await;
+    let (mock, _received) = MockSink::new("backup");
+    manager.add_notification_sink(Arc::new(mock)).await;
+
+    let message = prompt("ch");
+    manager
+        .send_to_notification_sinks_and_get_reply_to_id(&message)
+        .await;
+
+    let status = manager.delivery_status(&message.id);
+    assert_eq!(status.len(), 1, "failing sink is never tried");
+    assert_eq!(status[0].provider, "backup");
+}
+
+#[tokio::test]
+async fn undeliverable_prompt_alerts_viewers() {
+    let manager = BroadcastManager::new();
+    manager.set_delivery_policy(fast_policy(false, &[])).await;
+    manager.add_notification_sink(Arc::new(FailingSink)).await;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let connection_id = manager.add_viewer(ConnectionType::Viewer, tx).await;
+    manager.subscribe_to_all(&connection_id).await.unwrap();
+
+    let message = prompt("ch");
+    manager
+        .send_to_notification_sinks_and_get_reply_to_id(&message)
+        .await;
+
+    let text = match rx.try_recv().expect("viewer alerted") {
+        axum::extract::ws::Message::Text(s) => s,
+        other => panic!("expected Text broadcast, got {:?}", other),
+    };
+    assert!(text.contains("could not be delivered"));
+    assert!(text.contains(&message.id.to_string()));
+}
+
+#[tokio::test]
+async fn on_call_prompt_escalates_to_next_in_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let rotation = dir.path().join("rotation.toml");
+    let start = chrono::Utc::now() - chrono::Duration::hours(1);
+    std::fs::write(
+        &rotation,
+        format!(
+            r#"
+            start = "{}"
+            order = ["alice", "bob"]
+
+            [people.alice]
+            providers = ["failing"]
+
+            [people.bob]
+            providers = ["pager"]
+            contact = "+15550100"
+            "#,
+            start.to_rfc3339()
+        ),
+    )
+    .unwrap();
+
+    let manager = BroadcastManager::new();
+    manager.set_delivery_policy(fast_policy(false, &[])).await;
+    manager
+        .set_on_call_router(Some(OnCallRouter::new(&rotation, vec![])))
+        .await;
+    let (team, team_received) = MockSink::new("team-chat");
+    manager.add_notification_sink(Arc::new(team)).await;
+    manager.add_notification_sink(Arc::new(FailingSink)).await;
+    let (pager, pager_received) = MockSink::new("pager");
+    manager.add_notification_sink(Arc::new(pager)).await;
+
+    let message = prompt("ch");
+    manager
+        .send_to_notification_sinks_and_get_reply_to_id(&message)
+        .await;
+
+    let paged = pager_received.read().await;
+    assert_eq!(paged.len(), 1);
+    assert_eq!(
+        paged[0].metadata.as_ref().unwrap()["oncall"]["name"],
+        "alice"
+    );
+    assert!(
+        team_received.read().await.is_empty(),
+        "no escalation to everyone once the backup is reached"
+    );
+    let status = manager.delivery_status(&message.id);
+    assert_eq!(status[0].provider, "failing");
+    assert_eq!(status[1].provider, "pager");
+    assert_eq!(status[1].status, DeliveryStatus::Delivered);
+}
+
+/// Sink that keeps notifications and status pulses apart.
+struct IndicatorSink {
+    sent: Arc<RwLock<Vec<Message>>>,
+    statuses: Arc<RwLock<Vec<Message>>>,
+}
+
+#[async_trait]
+impl NotificationSink for IndicatorSink {
+    fn name(&self) -> &str {
+        "indicator"
+    }
+
+    async fn send(
+        &self,
+        message: &Message,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.sent.write().await.push(message.clone());
+        Ok(())
+    }
+
+    async fn send_status(
+        &self,
+        message: &Message,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.statuses.write().await.push(message.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn status_pulses_reach_indicators_without_delivery_reports() {
+    let manager = BroadcastManager::new();
+    let sent = Arc::new(RwLock::new(Vec::new()));
+    let statuses = Arc::new(RwLock::new(Vec::new()));
+    manager
+        .add_notification_sink(Arc::new(IndicatorSink {
+            sent: Arc::clone(&sent),
+            statuses: Arc::clone(&statuses),
+        }))
+        .await;
+    manager.add_notification_sink(Arc::new(FailingSink)).await;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let viewer = manager.add_viewer(ConnectionType::Viewer, tx).await;
+    manager.subscribe_to_channel(&viewer, "ci").await.unwrap();
+
+    let status = Message::new(
+        "ci".to_string(),
+        SenderType::Agent,
+        MessageContent::Status {
+            text: "running tests".to_string(),
+        },
+    );
+    manager.broadcast_status(&status).await;
+
+    assert!(sent.read().await.is_empty());
+    assert_eq!(statuses.read().await.len(), 1);
+    assert!(rx.try_recv().is_ok(), "viewers see the pulse");
+    assert!(manager.delivery_status(&status.id).is_empty());
+}
+
+#[tokio::test]
+async fn watched_keywords_alert_the_member_on_their_own_provider() {
+    use ailoop_core::models::{TeamDirectory, TeamMember};
+
+    let manager = BroadcastManager::new();
+    let mut members = std::collections::BTreeMap::new();
+    members.insert(
+        "alice".to_string(),
+        TeamMember {
+            handles: vec!["pager:alice".to_string()],
+            ..TeamMember::default()
+        },
+    );
+    manager
+        .subscriptions()
+        .set_team(TeamDirectory::new(members));
+    manager
+        .subscriptions()
+        .watch("alice", &["rollback".to_string()])
+        .unwrap();
+    let (team, team_received) = MockSink::new("team-chat");
+    manager.add_notification_sink(Arc::new(team)).await;
+    let (pager, pager_received) = MockSink::new("pager");
+    manager.add_notification_sink(Arc::new(pager)).await;
+
+    let message = Message::new(
+        "deploys".to_string(),
+        SenderType::Agent,
+        MessageContent::Notification {
+            text: "Starting rollback of api".to_string(),
+            priority: ailoop_core::models::NotificationPriority::Low,
+        },
+    );
+    manager.broadcast_to_viewers_only(&message).await;
+    manager.broadcast_message(&message).await;
+
+    let paged = pager_received.read().await;
+    assert_eq!(paged.len(), 2, "the message and one alert");
+    let alert = paged
+        .iter()
+        .find(|m| m.id != message.id)
+        .expect("alert sent");
+    assert_eq!(alert.metadata.as_ref().unwrap()["alert_for"], "@alice");
+    assert!(matches!(
+        alert.content,
+        MessageContent::Notification {
+            priority: ailoop_core::models::NotificationPriority::High,
+            ..
+        }
+    ));
+    let team = team_received.read().await;
+    assert_eq!(team.len(), 1, "only the member's providers get the alert");
+    assert_eq!(team[0].id, message.id);
+}
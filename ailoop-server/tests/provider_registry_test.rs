@@ -0,0 +1,183 @@
+//! Integration test: providers are built by the registry, receive prompts and notifications
+//! through `ProviderSink`, and their replies answer pending prompts.
+
+use ailoop_core::models::{
+    Configuration, Message, MessageContent, NotificationPriority, ResponseType, SenderType,
+};
+use ailoop_server::server::providers::{
+    NotificationSink, PromptType, Provider, ProviderRegistry, ProviderReply, ProviderResult,
+    ProviderSink,
+};
+use ailoop_server::{spawn_background_tasks, AiloopAppState, ServeConfig};
+use async_trait::async_trait;
+use futures_util::stream::{BoxStream, StreamExt};
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// Mock provider that records what it was sent and yields preset replies.
+struct MockProvider {
+    name: &'static str,
+    sent: Arc<Mutex<Vec<&'static str>>>,
+    replies: Mutex<Vec<ProviderReply>>,
+}
+
+impl MockProvider {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            sent: Arc::new(Mutex::new(Vec::new())),
+            replies: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn send_prompt(&self, _message: &Message) -> ProviderResult<Option<String>> {
+        self.sent.lock().unwrap().push("prompt");
+        Ok(Some("mock-1".to_string()))
+    }
+
+    async fn send_notification(&self, _message: &Message) -> ProviderResult<()> {
+        self.sent.lock().unwrap().push("notification");
+        Ok(())
+    }
+
+    fn reply_stream(&self) -> Option<BoxStream<'static, ProviderReply>> {
+        let replies = std::mem::take(&mut *self.replies.lock().unwrap());
+        Some(futures_util::stream::iter(replies).boxed())
+    }
+}
+
+fn message(content: MessageContent) -> Message {
+    Message::new("public".to_string(), SenderType::Agent, content)
+}
+
+#[tokio::test]
+async fn registry_starts_enabled_providers_in_order() {
+    let registry = ProviderRegistry::new();
+    registry.register(
+        "b",
+        Arc::new(|_: &Configuration, _: &AiloopAppState| {
+            Ok(Some(Arc::new(MockProvider::new("b")) as Arc<dyn Provider>))
+        }),
+    );
+    registry.register("disabled", Arc::new(|_, _| Ok(None)));
+    registry.register("broken", Arc::new(|_, _| Err("bad config".into())));
+    registry.register(
+        "a",
+        Arc::new(|_: &Configuration, _: &AiloopAppState| {
+            Ok(Some(Arc::new(MockProvider::new("a")) as Arc<dyn Provider>))
+        }),
+    );
+
+    assert_eq!(registry.names(), vec!["b", "disabled", "broken", "a"]);
+    let started = registry.start(&Configuration::default(), &AiloopAppState::new("public"));
+    let names: Vec<&str> = started.iter().map(|p| p.name()).collect();
+    assert_eq!(names, vec!["b", "a"]);
+    assert!(registry.get("a").is_some());
+    assert!(registry.get("disabled").is_none());
+}
+
+#[tokio::test]
+async fn provider_sink_routes_prompts_and_notifications() {
+    let provider = Arc::new(MockProvider::new("mock"));
+    let sent = Arc::clone(&provider.sent);
+    let sink = ProviderSink(provider.clone());
+
+    let prompt = message(MessageContent::Authorization {
+        action: "deploy".to_string(),
+        context: None,
+        timeout_seconds: 60,
+    });
+    let reply_to = sink.send_and_get_reply_to_id(&prompt).await.unwrap();
+    assert_eq!(reply_to.as_deref(), Some("mock-1"));
+
+    let notice = message(MessageContent::Notification {
+        text: "done".to_string(),
+        priority: NotificationPriority::Normal,
+    });
+    assert_eq!(sink.send_and_get_reply_to_id(&notice).await.unwrap(), None);
+    assert_eq!(*sent.lock().unwrap(), vec!["prompt", "notification"]);
+
+    let err = provider.delete("mock-1").await.unwrap_err();
+    assert_eq!(err.to_string(), "mock does not support delete");
+}
+
+#[tokio::test]
+async fn provider_replies_answer_pending_prompts() {
+    let state = Arc::new(
+        AiloopAppState::new("public")
+            .with_provider_config(Configuration::default())
+            .with_provider(
+                "mock",
+                Arc::new(|_: &Configuration, _: &AiloopAppState| {
+                    let provider = MockProvider::new("mock");
+                    provider.replies.lock().unwrap().push(ProviderReply {
+                        reply_to_message_id: Some("mock-1".to_string()),
+                        prompt_id: None,
+                        answer: Some("yes".to_string()),
+                        response_type: ResponseType::AuthorizationApproved,
+                        sender_id: Some("42".to_string()),
+                        sender_name: Some("alice".to_string()),
+                        channel: None,
+                        chat_id: None,
+                    });
+                    Ok(Some(Arc::new(provider) as Arc<dyn Provider>))
+                }),
+            ),
+    );
+    let message_id = Uuid::new_v4();
+    let (rx, _completer) = state
+        .pending_prompt_registry
+        .register(
+            message_id,
+            Some("mock-1".to_string()),
+            PromptType::Authorization,
+            "public".to_string(),
+            "deploy".to_string(),
+        )
+        .await;
+
+    let serve_config = ServeConfig {
+        host: "127.0.0.1".to_string(),
+        port: 0,
+        default_channel: "public".to_string(),
+        base_path: None,
+        web: false,
+        auth: None,
+        cors: None,
+        ip_filter: None,
+    };
+    let token = CancellationToken::new();
+    let handle = spawn_background_tasks(Arc::clone(&state), &serve_config, token.clone());
+
+    let answer = tokio::time::timeout(std::time::Duration::from_secs(5), rx)
+        .await
+        .expect("reply within timeout")
+        .unwrap();
+    assert!(matches!(
+        answer,
+        MessageContent::Response {
+            response_type: ResponseType::AuthorizationApproved,
+            ..
+        }
+    ));
+    assert_eq!(
+        state
+            .pending_prompt_registry
+            .take_responder(message_id)
+            .await
+            .as_deref(),
+        Some("mock:alice")
+    );
+    assert!(state.providers.get("mock").is_some());
+
+    token.cancel();
+    handle.await.unwrap();
+}
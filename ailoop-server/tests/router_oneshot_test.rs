@@ -1,11 +1,12 @@
 //! Router oneshot tests — no TCP listener required.
 
+use ailoop_core::models::{Message, MessageContent, NotificationPriority, SenderType};
 use ailoop_server::{router, AiloopAppState, AiloopError, ServeConfig};
 use axum::{
     body::Body,
     http::{Request, StatusCode},
 };
-use futures_util::SinkExt;
+use futures_util::{SinkExt, StreamExt};
 use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
 use tower::ServiceExt;
@@ -19,6 +20,7 @@ fn default_config() -> ServeConfig {
         web: false,
         auth: None,
         cors: None,
+        ip_filter: None,
     }
 }
 
@@ -101,6 +103,291 @@ async fn create_task_then_list_ready() {
     assert!(!tasks.is_empty(), "at least one ready task expected");
 }
 
+#[tokio::test]
+async fn prompt_quota_returns_429_and_usage_is_reported() {
+    use ailoop_server::server::usage::UsageQuota;
+
+    let state = Arc::new(AiloopAppState::new("default").with_usage_quota(UsageQuota {
+        max_messages_per_hour: None,
+        max_prompts_per_hour: Some(1),
+    }));
+    let r: axum::Router = router(state, &default_config()).unwrap();
+
+    let post_prompt = || {
+        let msg = ailoop_core::models::Message::new(
+            "default".to_string(),
+            ailoop_core::models::SenderType::Agent,
+            ailoop_core::models::MessageContent::Authorization {
+                action: "deploy".to_string(),
+                context: None,
+                timeout_seconds: 0,
+            },
+        );
+        Request::builder()
+            .method("POST")
+            .uri("/api/v1/messages")
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::to_vec(&msg).unwrap()))
+            .unwrap()
+    };
+
+    let first = r.clone().oneshot(post_prompt()).await.unwrap();
+    assert_eq!(first.status(), StatusCode::CREATED);
+
+    let second = r.clone().oneshot(post_prompt()).await.unwrap();
+    assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(second.headers().contains_key("retry-after"));
+    let body = axum::body::to_bytes(second.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["error"], "quota_exceeded");
+    assert_eq!(json["quota"]["kind"], "prompts");
+    assert_eq!(json["quota"]["limit"], 1);
+
+    let usage = r
+        .oneshot(
+            Request::builder()
+                .uri("/api/usage")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(usage.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["max_prompts_per_hour"], 1);
+    assert_eq!(json["clients"][0]["client"], "anonymous");
+    assert_eq!(json["clients"][0]["total_prompts"], 1);
+}
+
+#[tokio::test]
+async fn strict_mode_rejects_what_lenient_mode_accepts() {
+    let mut msg = serde_json::to_value(Message::new(
+        "default".to_string(),
+        SenderType::Agent,
+        MessageContent::Notification {
+            text: "hi".to_string(),
+            priority: NotificationPriority::Normal,
+        },
+    ))
+    .unwrap();
+    msg["content"].as_object_mut().unwrap().remove("priority");
+    msg["colour"] = serde_json::json!("blue");
+    let post = |msg: &serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/v1/messages")
+            .header("Content-Type", "application/json")
+            .body(Body::from(msg.to_string()))
+            .unwrap()
+    };
+
+    let lenient: axum::Router = router(make_state(), &default_config()).unwrap();
+    let resp = lenient.oneshot(post(&msg)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let state = Arc::new(AiloopAppState::new("default").with_strict_validation());
+    let strict: axum::Router = router(state, &default_config()).unwrap();
+    let resp = strict.oneshot(post(&msg)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        json["details"],
+        serde_json::json!(["colour: unknown field", "content.priority: missing"])
+    );
+}
+
+#[tokio::test]
+async fn ip_filter_rejects_peers_outside_allowlist() {
+    use axum::extract::ConnectInfo;
+    use std::net::SocketAddr;
+
+    let config = ServeConfig {
+        ip_filter: Some(
+            ailoop_server::IpFilterConfig::from_cidrs(
+                &["10.0.0.0/8".to_string()],
+                &["10.0.0.13".to_string()],
+            )
+            .unwrap(),
+        ),
+        ..default_config()
+    };
+    let r: axum::Router = router(make_state(), &config).unwrap();
+
+    let status_from = |peer: Option<&str>| {
+        let r = r.clone();
+        let mut builder = Request::builder().uri("/api/v1/health");
+        if let Some(peer) = peer {
+            builder = builder.extension(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        }
+        async move {
+            r.oneshot(builder.body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+                .status()
+        }
+    };
+
+    assert_eq!(status_from(Some("10.1.2.3:5000")).await, StatusCode::OK);
+    assert_eq!(
+        status_from(Some("192.168.0.1:5000")).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status_from(Some("10.0.0.13:5000")).await,
+        StatusCode::FORBIDDEN
+    );
+    // IPv4-mapped IPv6 peers match IPv4 networks.
+    assert_eq!(
+        status_from(Some("[::ffff:10.1.2.3]:5000")).await,
+        StatusCode::OK
+    );
+    // Without connect info the filter fails closed.
+    assert_eq!(status_from(None).await, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn signed_response_verifies_with_operator_key() {
+    use ailoop_core::services::signing::{verify_response, ResponseSigner};
+
+    let signer = ResponseSigner::new(ed25519_dalek::SigningKey::from_bytes(&[9u8; 32]));
+    let operator_key = signer.operator_public_key();
+    let state = Arc::new(AiloopAppState::new("default").with_response_signer(signer));
+    let prompt = ailoop_core::models::Message::new(
+        "default".to_string(),
+        ailoop_core::models::SenderType::Agent,
+        ailoop_core::models::MessageContent::Authorization {
+            action: "drop table".to_string(),
+            context: None,
+            timeout_seconds: 0,
+        },
+    );
+    state
+        .message_history
+        .add_message("default", prompt.clone())
+        .await;
+    let r: axum::Router = router(state, &default_config()).unwrap();
+
+    let resp = r
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/v1/messages/{}/response", prompt.id))
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"answer":null,"response_type":"authorization_approved"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let response: ailoop_core::models::Message = serde_json::from_slice(&body).unwrap();
+    let signature = verify_response(&response).expect("response must carry a valid signature");
+    assert_eq!(signature.signer, "operator");
+    assert_eq!(signature.public_key, operator_key);
+}
+
+#[tokio::test]
+async fn response_carries_uploaded_attachment() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = Arc::new(AiloopAppState::new("default").with_attachment_dir(dir.path()));
+    let option = |id: &str| ailoop_core::models::DecisionOption {
+        id: id.to_string(),
+        label: id.to_string(),
+        detail_markdown: None,
+    };
+    let mut prompt = Message::new(
+        "default".to_string(),
+        SenderType::Agent,
+        MessageContent::Decision {
+            decision_id: "kubeconfig".to_string(),
+            summary: "Send me the kubeconfig".to_string(),
+            context_markdown: None,
+            options: vec![option("staging"), option("production")],
+            recommendation: None,
+            timeout_seconds: 0,
+        },
+    );
+    prompt.metadata = Some(serde_json::json!({ "expect_file": true }));
+    state
+        .message_history
+        .add_message("default", prompt.clone())
+        .await;
+    let r: axum::Router = router(state, &default_config()).unwrap();
+
+    let resp = r
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/attachments?name=kubeconfig")
+                .header("Content-Type", "text/yaml")
+                .body(Body::from("apiVersion: v1\n"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let attachment: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    let resp = r
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/v1/messages/{}/response", prompt.id))
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "answer": null,
+                        "response_type": "text",
+                        "attachment_id": attachment["id"],
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let response: Message = serde_json::from_slice(&body).unwrap();
+    let metadata = response.metadata.expect("response must carry metadata");
+    assert_eq!(metadata["attachment"]["name"], "kubeconfig");
+    assert_eq!(metadata["attachment"]["size"], 15);
+
+    let resp = r
+        .oneshot(
+            Request::builder()
+                .uri(attachment["url"].as_str().unwrap())
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "text/yaml");
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"apiVersion: v1\n");
+}
+
 #[tokio::test]
 async fn base_path_prefix_routes_correctly() {
     let config = ServeConfig {
@@ -218,3 +505,363 @@ async fn websocket_hello_frame_subscribe_all() {
 
     token.cancel();
 }
+
+/// A connection declared as an agent may not answer prompts; the frame gets a protocol error.
+#[tokio::test]
+async fn websocket_agent_connection_cannot_answer_prompts() {
+    let r: axum::Router = router(make_state(), &default_config()).unwrap();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, r.into_make_service()).await.ok() });
+
+    let url = format!("ws://127.0.0.1:{port}/?connection=agent");
+    let (mut ws, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+    let answer = Message::response(
+        "default".to_string(),
+        MessageContent::Response {
+            answer: Some("yes".to_string()),
+            response_type: ailoop_core::models::ResponseType::AuthorizationApproved,
+        },
+        uuid::Uuid::new_v4(),
+    );
+    ws.send(tokio_tungstenite::tungstenite::Message::Text(
+        serde_json::to_string(&answer).unwrap(),
+    ))
+    .await
+    .unwrap();
+
+    let frame = tokio::time::timeout(std::time::Duration::from_secs(2), ws.next())
+        .await
+        .expect("no reply")
+        .unwrap()
+        .unwrap();
+    let frame: serde_json::Value = serde_json::from_str(frame.to_text().unwrap()).unwrap();
+    assert_eq!(frame["type"], "error");
+    assert_eq!(frame["error"], "forbidden");
+}
+
+#[tokio::test]
+async fn session_transcript_from_posted_messages() {
+    let r: axum::Router = router(make_state(), &default_config()).unwrap();
+
+    for event in [
+        serde_json::json!({"type": "assistant", "text": "Running the tests."}),
+        serde_json::json!({"type": "tool_call", "tool": "shell", "status": "completed"}),
+    ] {
+        let mut message = Message::new(
+            "default".to_string(),
+            SenderType::Agent,
+            MessageContent::Notification {
+                text: "[jsonl] ...".to_string(),
+                priority: NotificationPriority::Normal,
+            },
+        );
+        message.metadata = Some(serde_json::json!({
+            "agent_type": "jsonl",
+            "session_id": "run-7",
+            "event": event,
+        }));
+        let message = serde_json::to_value(&message).unwrap();
+        let resp = r
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/messages")
+                    .header("content-type", "application/json")
+                    .body(Body::from(message.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    let resp = r
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/sessions/run-7/transcript")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers()["content-type"],
+        "text/markdown; charset=utf-8"
+    );
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let markdown = String::from_utf8(body.to_vec()).unwrap();
+    assert!(markdown.contains("Running the tests.\n\n- `shell` (completed)\n"));
+
+    let resp = r
+        .oneshot(
+            Request::builder()
+                .uri("/api/sessions/unknown/transcript")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn paused_channel_holds_prompts_and_notifies_agents() {
+    let state = make_state();
+    let r: axum::Router = router(Arc::clone(&state), &default_config()).unwrap();
+    let post = |uri: &str| {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let json_body = |resp: axum::response::Response| async move {
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+    };
+
+    let resp = r
+        .clone()
+        .oneshot(post("/api/v1/channels/ops/pause"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let hold = json_body(resp).await;
+    assert_eq!(hold["held"], true);
+    assert_eq!(hold["changed"], true);
+    assert!(state.channel_manager.held_since("ops").is_some());
+
+    let resp = r
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/pending?channel=ops")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let pending = json_body(resp).await;
+    assert_eq!(pending["held_channels"][0]["channel"], "ops");
+
+    let resp = r
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/channels/ops/messages")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let history = json_body(resp).await;
+    let notice = &history["messages"][0];
+    assert_eq!(notice["sender_type"], "SYSTEM");
+    assert_eq!(notice["content"]["type"], "notification");
+
+    let resp = r
+        .clone()
+        .oneshot(post("/api/v1/channels/ops/resume"))
+        .await
+        .unwrap();
+    let hold = json_body(resp).await;
+    assert_eq!(hold["held"], false);
+    assert_eq!(hold["changed"], true);
+
+    let resp = r
+        .oneshot(post("/api/v1/channels/ops/resume"))
+        .await
+        .unwrap();
+    assert_eq!(json_body(resp).await["changed"], false);
+}
+
+#[tokio::test]
+async fn break_glass_approves_only_long_unanswered_authorizations() {
+    use ailoop_server::server::providers::PromptType;
+
+    let mut config = ailoop_core::models::Configuration::default();
+    config.break_glass.holders.insert(
+        "lead".to_string(),
+        ailoop_core::models::BreakGlassConfig::token_digest("glass-token"),
+    );
+    config.break_glass.after_seconds = Some(600);
+    let state = Arc::new(AiloopAppState::new("default").with_provider_config(config));
+
+    let authorization = |action: &str| {
+        Message::new(
+            "ops".to_string(),
+            SenderType::Agent,
+            MessageContent::Authorization {
+                action: action.to_string(),
+                context: None,
+                timeout_seconds: 0,
+            },
+        )
+    };
+    let fresh = authorization("restart the database");
+    let mut stale = authorization("fail over the database");
+    stale.timestamp -= chrono::Duration::minutes(15);
+    for prompt in [&fresh, &stale] {
+        state
+            .message_history
+            .add_message("ops", prompt.clone())
+            .await;
+    }
+    let (_fresh_rx, _fresh_completer) = state
+        .pending_prompt_registry
+        .register(
+            fresh.id,
+            None,
+            PromptType::Authorization,
+            "ops".to_string(),
+            "restart the database".to_string(),
+        )
+        .await;
+    let (stale_rx, _stale_completer) = state
+        .pending_prompt_registry
+        .register(
+            stale.id,
+            None,
+            PromptType::Authorization,
+            "ops".to_string(),
+            "fail over the database".to_string(),
+        )
+        .await;
+    let r: axum::Router = router(Arc::clone(&state), &default_config()).unwrap();
+
+    let break_glass = |id: uuid::Uuid, token: &str, justification: &str| {
+        Request::builder()
+            .method("POST")
+            .uri(format!("/api/v1/messages/{}/break-glass", id))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "justification": justification }).to_string(),
+            ))
+            .unwrap()
+    };
+    let justification = "primary is down and nobody on call answers";
+
+    let resp = r
+        .clone()
+        .oneshot(break_glass(stale.id, "wrong", justification))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = r
+        .clone()
+        .oneshot(break_glass(stale.id, "glass-token", "because"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = r
+        .clone()
+        .oneshot(break_glass(fresh.id, "glass-token", justification))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let resp = r
+        .clone()
+        .oneshot(break_glass(stale.id, "glass-token", justification))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let response: Message = serde_json::from_slice(&body).unwrap();
+    let metadata = response.metadata.unwrap();
+    assert_eq!(metadata["responder"], "break-glass:lead");
+    assert_eq!(metadata["break_glass"]["justification"], justification);
+    assert!(matches!(
+        stale_rx.await.unwrap(),
+        MessageContent::Response {
+            response_type: ailoop_core::models::ResponseType::AuthorizationApproved,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn break_glass_after_a_normal_answer_records_nothing() {
+    use ailoop_server::server::providers::PromptType;
+
+    let mut config = ailoop_core::models::Configuration::default();
+    config.break_glass.holders.insert(
+        "lead".to_string(),
+        ailoop_core::models::BreakGlassConfig::token_digest("glass-token"),
+    );
+    let state = Arc::new(AiloopAppState::new("default").with_provider_config(config));
+    let mut prompt = Message::new(
+        "ops".to_string(),
+        SenderType::Agent,
+        MessageContent::Authorization {
+            action: "fail over the database".to_string(),
+            context: None,
+            timeout_seconds: 0,
+        },
+    );
+    prompt.timestamp -= chrono::Duration::minutes(15);
+    state
+        .message_history
+        .add_message("ops", prompt.clone())
+        .await;
+    let (rx, _completer) = state
+        .pending_prompt_registry
+        .register(
+            prompt.id,
+            None,
+            PromptType::Authorization,
+            "ops".to_string(),
+            "fail over the database".to_string(),
+        )
+        .await;
+    // The on-call responder answers just before the break-glass request is handled.
+    assert!(
+        state
+            .pending_prompt_registry
+            .submit_reply_for_message(
+                prompt.id,
+                Some("too risky".to_string()),
+                ailoop_core::models::ResponseType::AuthorizationDenied,
+            )
+            .await
+    );
+    let r: axum::Router = router(Arc::clone(&state), &default_config()).unwrap();
+
+    let resp = r
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/v1/messages/{}/break-glass", prompt.id))
+                .header("Authorization", "Bearer glass-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "justification": "primary is down and nobody on call answers"
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    assert_eq!(state.message_history.get_message_count("ops").await, 1);
+    assert!(matches!(
+        rx.await.unwrap(),
+        MessageContent::Response {
+            response_type: ailoop_core::models::ResponseType::AuthorizationDenied,
+            ..
+        }
+    ));
+}
@@ -0,0 +1,103 @@
+//! Integration test: the stdio mode serves the JSON protocol over a byte stream, and the peer
+//! can answer its own prompts.
+
+use ailoop_core::models::{Message, MessageContent, ResponseType, SenderType};
+use ailoop_server::server::stdio::{serve_io, STDIO_RESPONDER};
+use ailoop_server::{spawn_background_tasks, AiloopAppState, ServeConfig};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio_util::sync::CancellationToken;
+
+fn serve_config() -> ServeConfig {
+    ServeConfig {
+        host: "127.0.0.1".to_string(),
+        port: 0,
+        default_channel: "public".to_string(),
+        base_path: None,
+        web: false,
+        auth: None,
+        cors: None,
+        ip_filter: None,
+    }
+}
+
+#[tokio::test]
+async fn stdio_peer_answers_its_own_prompt() {
+    let state = Arc::new(AiloopAppState::new("public"));
+    let token = CancellationToken::new();
+    let tasks = spawn_background_tasks(Arc::clone(&state), &serve_config(), token.clone());
+
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let (server_read, server_write) = tokio::io::split(server);
+    let served = tokio::spawn(serve_io(Arc::clone(&state), server_read, server_write));
+    let (client_read, mut client_write) = tokio::io::split(client);
+    let mut replies = BufReader::new(client_read).lines();
+
+    let prompt = Message::new(
+        "public".to_string(),
+        SenderType::Agent,
+        MessageContent::Authorization {
+            action: "deploy".to_string(),
+            context: None,
+            timeout_seconds: 30,
+        },
+    );
+    let line = serde_json::to_string(&prompt).unwrap() + "\n";
+    client_write.write_all(line.as_bytes()).await.unwrap();
+
+    // Wait for the prompt to be picked up before answering it.
+    for _ in 0..50 {
+        if !state
+            .pending_prompt_registry
+            .snapshot_pending(None)
+            .await
+            .is_empty()
+        {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let answer = Message::response(
+        "public".to_string(),
+        MessageContent::Response {
+            answer: None,
+            response_type: ResponseType::AuthorizationApproved,
+        },
+        prompt.id,
+    );
+    let line = serde_json::to_string(&answer).unwrap() + "\n";
+    client_write.write_all(line.as_bytes()).await.unwrap();
+
+    let response = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let line = replies.next_line().await.unwrap().expect("stream ended");
+            let message: Message = serde_json::from_str(&line).unwrap();
+            if message.correlation_id == Some(prompt.id) {
+                return message;
+            }
+        }
+    })
+    .await
+    .expect("no response to the prompt");
+    assert!(matches!(
+        response.content,
+        MessageContent::Response {
+            response_type: ResponseType::AuthorizationApproved,
+            ..
+        }
+    ));
+    assert_eq!(
+        response.metadata.unwrap()["responder"],
+        serde_json::json!(STDIO_RESPONDER)
+    );
+
+    // End of input ends the session.
+    client_write.shutdown().await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), served)
+        .await
+        .unwrap()
+        .unwrap();
+    token.cancel();
+    tasks.await.unwrap();
+}